@@ -39,6 +39,7 @@ const CERT_SUBJECT: &str = "CN=Teaclave";
 pub struct RemoteAttestation {
     attestation_config: Arc<AttestationConfig>,
     attested_tls_config: Option<Arc<RwLock<AttestedTlsConfig>>>,
+    renewal_interval: Duration,
 }
 
 impl RemoteAttestation {
@@ -47,6 +48,26 @@ impl RemoteAttestation {
         Self {
             attestation_config,
             attested_tls_config: None,
+            renewal_interval: Duration::from_secs(ATTESTATION_VALIDITY_SECS / 2),
+        }
+    }
+
+    /// Construct a `RemoteAttestation` from Teaclave runtime configuration.
+    pub fn from_teaclave_config(config: &teaclave_config::RuntimeConfig) -> Result<Self> {
+        let attestation_config = AttestationConfig::from_teaclave_config(config)?;
+        let remote_attestation = Self::new(attestation_config);
+        match config.attestation.renewal_interval_seconds {
+            Some(seconds) => Ok(remote_attestation.renewal_interval(Duration::from_secs(seconds))),
+            None => Ok(remote_attestation),
+        }
+    }
+
+    /// Set the interval of renewing the attestation report, which should be
+    /// shorter than the validity of a report.
+    pub fn renewal_interval(self, renewal_interval: Duration) -> Self {
+        Self {
+            renewal_interval,
+            ..self
         }
     }
 
@@ -57,12 +78,18 @@ impl RemoteAttestation {
         )?));
         let attestation_config_ref = self.attestation_config.clone();
         let attested_tls_config_ref = attested_tls_config.clone();
+        let renewal_interval = self.renewal_interval;
         thread::spawn(move || {
-            AttestationFreshnessKeeper::new(attestation_config_ref, attested_tls_config_ref).start()
+            AttestationFreshnessKeeper::new(
+                attestation_config_ref,
+                attested_tls_config_ref,
+                renewal_interval,
+            )
+            .start()
         });
         Ok(Self {
-            attestation_config: self.attestation_config,
             attested_tls_config: Some(attested_tls_config),
+            ..self
         })
     }
 
@@ -105,16 +132,19 @@ impl AttestedTlsConfig {
 struct AttestationFreshnessKeeper {
     attestation_config: Arc<AttestationConfig>,
     attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
+    renewal_interval: Duration,
 }
 
 impl AttestationFreshnessKeeper {
     pub(crate) fn new(
         attestation_config: Arc<AttestationConfig>,
         attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
+        renewal_interval: Duration,
     ) -> Self {
         Self {
             attestation_config,
             attested_tls_config,
+            renewal_interval,
         }
     }
 
@@ -123,7 +153,7 @@ impl AttestationFreshnessKeeper {
     pub(crate) fn start(&self) {
         debug!("AttestationFreshnessKeeper started");
         loop {
-            thread::sleep(Duration::from_secs(ATTESTATION_VALIDITY_SECS));
+            thread::sleep(self.renewal_interval);
             match self.refresh() {
                 Ok(_) => debug!("Attestation report updated successfully"),
                 Err(e) => debug!("Failed to refresh attestation report: {:?}", e),
@@ -432,11 +432,63 @@ pub struct AttestationReport {
     pub sgx_quote_body: SgxQuote,
 }
 
+/// Split concatenated DER encoded certificates. Trailing bytes which are not a
+/// complete DER element are kept as the last certificate, so that they fail
+/// to verify.
+fn split_der_certs(mut bytes: &[u8]) -> Vec<&[u8]> {
+    // Length of the DER element at the start of the bytes, including the
+    // header.
+    fn element_len(bytes: &[u8]) -> Option<usize> {
+        let first = *bytes.get(1)? as usize;
+        if first < 0x80 {
+            return Some(2 + first);
+        }
+        let n = first & 0x7f;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let len = bytes
+            .get(2..2 + n)?
+            .iter()
+            .fold(0usize, |len, b| (len << 8) | *b as usize);
+        Some(2 + n + len)
+    }
+
+    let mut certs = Vec::new();
+    while !bytes.is_empty() {
+        match element_len(bytes) {
+            Some(len) if len <= bytes.len() => {
+                let (cert, rest) = bytes.split_at(len);
+                certs.push(cert);
+                bytes = rest;
+            }
+            _ => {
+                certs.push(bytes);
+                break;
+            }
+        }
+    }
+    certs
+}
+
 impl AttestationReport {
     /// Construct a AttestationReport from a X509 certificate and verify
     /// attestation report with the report_ca_cert which is from the attestation
-    /// service provider.
+    /// service provider. The report_ca_cert may be several concatenated DER
+    /// encoded certificates, e.g., while the root certificate is rotated, and
+    /// the report is accepted if it is verified with any of them.
     pub fn from_cert(cert: &[u8], report_ca_cert: &[u8]) -> Result<Self> {
+        let mut result = Err(anyhow!("No report CA certificate"));
+        for report_ca_cert in split_der_certs(report_ca_cert) {
+            result = Self::from_cert_with_ca(cert, report_ca_cert);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    fn from_cert_with_ca(cert: &[u8], report_ca_cert: &[u8]) -> Result<Self> {
         // Before we reach here, Webpki already verifed the cert is properly signed.
         use crate::cert::*;
 
@@ -612,7 +664,8 @@ pub mod tests {
         run_tests!(
             test_sgx_quote_parse_from,
             test_attestation_report_from_cert,
-            test_attestation_report_from_cert_api_version_not_compatible
+            test_attestation_report_from_cert_api_version_not_compatible,
+            test_attestation_report_from_cert_with_ca_bundle
         )
     }
 
@@ -692,4 +745,17 @@ pub mod tests {
         let report = AttestationReport::from_cert(&tls_ra_cert, &ias_root_ca_cert);
         assert!(report.is_err());
     }
+
+    fn test_attestation_report_from_cert_with_ca_bundle() {
+        let tls_ra_cert = tls_ra_cert_der_v4();
+        let ias_root_ca_cert = ias_root_ca_cert_der();
+        // Any DER certificate which does not endorse the report
+        let other_cert = tls_ra_cert_der_v3();
+
+        let mut bundle = other_cert.clone();
+        bundle.extend_from_slice(&ias_root_ca_cert);
+        assert!(AttestationReport::from_cert(&tls_ra_cert, &bundle).is_ok());
+
+        assert!(AttestationReport::from_cert(&tls_ra_cert, &other_cert).is_err());
+    }
 }
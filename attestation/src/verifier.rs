@@ -19,22 +19,85 @@
 
 use crate::report::AttestationReport;
 
+use std::time::Duration;
 use std::vec::Vec;
 
 use log::{debug, error};
-use teaclave_types::EnclaveAttr;
+use teaclave_types::{EnclaveAttr, SgxMeasurement};
 
 /// User defined verification function to further verify the attestation report.
 pub type AttestationReportVerificationFn = fn(&AttestationReport) -> bool;
 
+/// Policy which the attestation report of a peer must satisfy.
+#[derive(Clone, Debug, Default)]
+pub struct AttestationPolicy {
+    /// Valid enclave attributes (only enclaves with attributes in this vector
+    /// will be accepted).
+    pub accepted_enclave_attrs: Vec<EnclaveAttr>,
+    /// Accepted `MR_ENCLAVE` values, any value is accepted if empty.
+    pub mr_enclaves: Vec<SgxMeasurement>,
+    /// Accepted `MR_SIGNER` values, any value is accepted if empty.
+    pub mr_signers: Vec<SgxMeasurement>,
+    /// Maximum age of the attestation report.
+    pub max_report_age: Option<Duration>,
+}
+
+impl AttestationPolicy {
+    pub fn new(accepted_enclave_attrs: Vec<EnclaveAttr>) -> Self {
+        Self {
+            accepted_enclave_attrs,
+            ..Default::default()
+        }
+    }
+
+    pub fn mr_enclaves(self, mr_enclaves: Vec<SgxMeasurement>) -> Self {
+        Self {
+            mr_enclaves,
+            ..self
+        }
+    }
+
+    pub fn mr_signers(self, mr_signers: Vec<SgxMeasurement>) -> Self {
+        Self { mr_signers, ..self }
+    }
+
+    pub fn max_report_age(self, max_report_age: Duration) -> Self {
+        Self {
+            max_report_age: Some(max_report_age),
+            ..self
+        }
+    }
+
+    /// Verify the measures and the freshness of a report against the policy.
+    pub fn verify(
+        &self,
+        mr_signer: &SgxMeasurement,
+        mr_enclave: &SgxMeasurement,
+        freshness: Duration,
+    ) -> bool {
+        let accepted = self.accepted_enclave_attrs.iter().any(|a| {
+            &a.measurement.mr_signer == mr_signer && &a.measurement.mr_enclave == mr_enclave
+        });
+        let enclave_allowed = self.mr_enclaves.is_empty() || self.mr_enclaves.contains(mr_enclave);
+        let signer_allowed = self.mr_signers.is_empty() || self.mr_signers.contains(mr_signer);
+        let fresh = match self.max_report_age {
+            Some(max_report_age) => freshness <= max_report_age,
+            None => true,
+        };
+
+        accepted && enclave_allowed && signer_allowed && fresh
+    }
+}
+
 /// Type used to verify attestation reports (this can be set as a certificate
 /// verifier in `rustls::ClientConfig`).
 #[derive(Clone)]
 pub struct AttestationReportVerifier {
-    /// Valid enclave attributes (only enclaves with attributes in this vector
-    /// will be accepted).
-    pub accepted_enclave_attrs: Vec<EnclaveAttr>,
-    /// Root certificate of the attestation service provider (e.g., IAS).
+    /// Policies of accepted peers (only enclaves satisfying one of the
+    /// policies will be accepted).
+    pub policies: Vec<AttestationPolicy>,
+    /// Root certificate of the attestation service provider (e.g., IAS), or
+    /// several concatenated DER encoded root certificates.
     pub root_ca: Vec<u8>,
     /// User defined function to verify the attestation report.
     pub verifier: AttestationReportVerificationFn,
@@ -51,16 +114,28 @@ impl AttestationReportVerifier {
         accepted_enclave_attrs: Vec<EnclaveAttr>,
         root_ca: &[u8],
         verifier: AttestationReportVerificationFn,
+    ) -> Self {
+        Self::with_policies(
+            vec![AttestationPolicy::new(accepted_enclave_attrs)],
+            root_ca,
+            verifier,
+        )
+    }
+
+    pub fn with_policies(
+        policies: Vec<AttestationPolicy>,
+        root_ca: &[u8],
+        verifier: AttestationReportVerificationFn,
     ) -> Self {
         Self {
-            accepted_enclave_attrs,
+            policies,
             root_ca: root_ca.to_vec(),
             verifier,
         }
     }
 
     /// Verify whether the `MR_SIGNER` and `MR_ENCLAVE` in the attestation report is
-    /// accepted by us, which are defined in `accepted_enclave_attrs`.
+    /// accepted by us, which are defined in `policies`.
     fn verify_measures(&self, attestation_report: &AttestationReport) -> bool {
         debug!("verify measures");
         let this_mr_signer = attestation_report
@@ -72,8 +147,12 @@ impl AttestationReportVerifier {
             .isv_enclave_report
             .mr_enclave;
 
-        self.accepted_enclave_attrs.iter().any(|a| {
-            a.measurement.mr_signer == this_mr_signer && a.measurement.mr_enclave == this_mr_enclave
+        self.policies.iter().any(|p| {
+            p.verify(
+                &this_mr_signer,
+                &this_mr_enclave,
+                attestation_report.freshness,
+            )
         })
     }
 
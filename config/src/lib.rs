@@ -33,4 +33,8 @@ pub(crate) enum ConfigSource {
 pub mod build;
 mod runtime;
 
-pub use runtime::RuntimeConfig;
+pub use runtime::{
+    AccessRule, AuthenticationConfig, CmacVerification, FileAgentConfig, FileAgentCredential,
+    InboundAttestationPolicy, ManagementConfig, RuntimeConfig, S3Credential, ServerConfig,
+    StorageConfig,
+};
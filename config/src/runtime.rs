@@ -25,6 +25,7 @@ use std::untrusted::fs;
 
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 use std::net;
 use std::path::{Path, PathBuf};
@@ -38,6 +39,18 @@ pub struct RuntimeConfig {
     pub audit: AuditConfig,
     pub attestation: AttestationServiceConfig,
     pub mount: MountConfig,
+    #[serde(default)]
+    pub management: ManagementConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub authentication: AuthenticationConfig,
+    // Only used by the file agent outside of enclaves, so credentials are not
+    // serialized into the config passed to enclaves.
+    #[serde(default, skip_serializing)]
+    pub file_agent: FileAgentConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -77,6 +90,29 @@ pub struct AuditConfig {
     pub enclave_info_bytes: Vec<u8>,
     #[serde(default = "Default::default")]
     pub auditor_signatures_bytes: Vec<Vec<u8>>,
+    // DER encoded public keys of auditors accepted in addition to the
+    // built-in ones, so that keys can be rotated without a rebuild
+    #[serde(
+        default,
+        rename(serialize = "auditor_public_keys", deserialize = "auditor_public_keys")
+    )]
+    auditor_public_keys_source: Vec<ConfigSource>,
+    #[serde(default = "Default::default")]
+    pub auditor_public_keys_bytes: Vec<Vec<u8>>,
+}
+
+impl AuditConfig {
+    // Public keys verifying signatures of the enclave info: the built-in keys
+    // followed by the keys loaded at runtime.
+    pub fn auditor_public_keys<'a>(&'a self, built_in: &[&'a [u8]]) -> Vec<&'a [u8]> {
+        let mut keys = built_in.to_vec();
+        for key in &self.auditor_public_keys_bytes {
+            if !keys.contains(&key.as_slice()) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -85,6 +121,34 @@ pub struct AttestationServiceConfig {
     pub url: String,
     pub key: String,
     pub spid: String,
+    // Interval of renewing the attestation report, which defaults to half of
+    // the validity of a report
+    #[serde(default)]
+    pub renewal_interval_seconds: Option<u64>,
+    // DER encoded root certificates of the attestation service accepted in
+    // addition to the built-in one
+    #[serde(
+        default,
+        rename(serialize = "root_ca_certs", deserialize = "root_ca_certs")
+    )]
+    root_ca_certs_source: Vec<ConfigSource>,
+    #[serde(default = "Default::default")]
+    pub root_ca_certs_bytes: Vec<Vec<u8>>,
+}
+
+impl AttestationServiceConfig {
+    // Root certificates verifying attestation reports: the built-in one
+    // followed by those loaded at runtime, concatenated as accepted by
+    // attestation report verifiers.
+    pub fn root_ca_certs(&self, built_in: &[u8]) -> Vec<u8> {
+        let mut certs = built_in.to_vec();
+        for cert in &self.root_ca_certs_bytes {
+            if cert.as_slice() != built_in {
+                certs.extend_from_slice(cert);
+            }
+        }
+        certs
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -92,6 +156,261 @@ pub struct MountConfig {
     pub fusion_base_dir: PathBuf,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ManagementConfig {
+    pub admin_redact_arguments: bool,
+    // Hide co-owners of outputs, get_output_file then only discloses the
+    // number of owners and whether the caller is one of them
+    pub redact_output_owners: bool,
+    pub fusion_output_max_age_seconds: Option<u64>,
+    pub enable_self_test: bool,
+    pub cmac_verification: CmacVerification,
+    pub max_argument_length: usize,
+    pub max_argument_count: usize,
+    pub max_arguments_size: usize,
+    pub max_inline_input_size: usize,
+    pub max_url_length: usize,
+    pub max_active_tasks_per_user: usize,
+    // Stage tasks in queues of their users served in turns instead of a
+    // single FIFO queue
+    pub fair_staging: bool,
+    pub consumption_grace_period_seconds: Option<u64>,
+    pub compress_values: bool,
+    pub access_policy: HashMap<String, AccessRule>,
+    pub inbound_attestation: HashMap<String, InboundAttestationPolicy>,
+    // SHA-256 fingerprints in hex of client certificates accepted by the
+    // internal endpoint, any attested client is accepted if empty
+    pub client_cert_fingerprints: Vec<String>,
+    pub enable_output_presigned_url: bool,
+    pub output_presigned_url_expiry_seconds: u64,
+    pub enable_output_access_tokens: bool,
+    pub output_access_token_expiry_seconds: u64,
+    pub s3: Option<S3Credential>,
+    pub min_fusion_owners: usize,
+    pub max_fusion_outputs_per_user: Option<usize>,
+    pub max_input_files_per_user: Option<usize>,
+    // Percentage of a hard limit from which responses carry a warning
+    pub soft_limit_percent: Option<u8>,
+    pub verify_output_cmac: bool,
+    pub max_task_memory_mb: Option<u64>,
+    pub max_task_runtime_seconds: Option<u64>,
+    pub default_executor: Option<String>,
+    pub approval_excludes_creator: bool,
+    pub function_categories: Vec<String>,
+    // MesaPy functions whose scripts contain any of the patterns are rejected
+    pub mesapy_denied_patterns: Vec<String>,
+    pub function_retention_seconds: u64,
+    pub unique_function_names: bool,
+    pub enforce_unique_iv: bool,
+    pub max_task_retries: u32,
+    pub result_retention_seconds: Option<u64>,
+    pub result_archive_url: Option<String>,
+    pub result_archive_encryption_key: Option<String>,
+    pub task_history_max_count: Option<usize>,
+    pub task_history_max_age_seconds: Option<u64>,
+    // Hosts to which results of finished tasks may be delivered, callbacks
+    // cannot be registered if empty
+    pub callback_allowed_hosts: Vec<String>,
+    pub crypto_key_encryption_key: Option<String>,
+    pub function_payload_encryption_key: Option<String>,
+    pub cursor_signing_secret: Option<String>,
+    #[serde(rename(
+        serialize = "function_publisher_keys",
+        deserialize = "function_publisher_keys"
+    ))]
+    function_publisher_keys_source: Vec<ConfigSource>,
+    pub function_publisher_keys_bytes: Vec<Vec<u8>>,
+    pub storage_format: String,
+    // Advertised addresses of read-only replicas of the storage service
+    pub storage_replicas: Vec<String>,
+    pub replica_read_your_writes_seconds: u64,
+    // Serve reads from a cache and queue writes while the storage service is
+    // unavailable, 0 disables the degraded mode
+    pub degraded_cache_ttl_seconds: u64,
+    pub degraded_cache_capacity: usize,
+    pub degraded_write_queue_depth: usize,
+}
+
+// Credential used by the management service to presign urls of outputs stored
+// in S3.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct S3Credential {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub region: String,
+}
+
+// Who may call a management RPC. Rules in the config override the built-in
+// policy of the management service for the named RPCs.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessRule {
+    // any authenticated user
+    Anyone,
+    // users with the admin role
+    Admin,
+    // the RPC is disabled
+    Nobody,
+}
+
+// Attestation policy of an inbound service of the management service. Callers
+// must match the audited measurement of the service and additionally satisfy
+// the policy. Empty lists do not restrict the measurements.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct InboundAttestationPolicy {
+    // accepted MRENCLAVE values in hex
+    pub mr_enclaves: Vec<String>,
+    // accepted MRSIGNER values in hex
+    pub mr_signers: Vec<String>,
+    // maximum age of the attestation report of callers
+    pub max_report_age_seconds: Option<u64>,
+}
+
+// Whether inputs must carry the cmac of their data when they are registered
+// from outputs and when tasks are staged.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CmacVerification {
+    // reject inputs without a cmac
+    Strict,
+    // accept inputs without a cmac with a warning
+    Permissive,
+}
+
+impl Default for CmacVerification {
+    fn default() -> Self {
+        CmacVerification::Strict
+    }
+}
+
+impl Default for ManagementConfig {
+    fn default() -> Self {
+        Self {
+            admin_redact_arguments: true,
+            redact_output_owners: false,
+            fusion_output_max_age_seconds: None,
+            enable_self_test: false,
+            cmac_verification: CmacVerification::default(),
+            max_argument_length: 4096,
+            max_argument_count: 256,
+            max_arguments_size: 65536,
+            max_inline_input_size: 16384,
+            max_url_length: 4096,
+            max_active_tasks_per_user: 16,
+            fair_staging: false,
+            consumption_grace_period_seconds: None,
+            compress_values: false,
+            access_policy: HashMap::new(),
+            inbound_attestation: HashMap::new(),
+            client_cert_fingerprints: Vec::new(),
+            enable_output_presigned_url: false,
+            output_presigned_url_expiry_seconds: 3600,
+            enable_output_access_tokens: false,
+            output_access_token_expiry_seconds: 3600,
+            s3: None,
+            min_fusion_owners: 2,
+            max_fusion_outputs_per_user: None,
+            max_input_files_per_user: None,
+            soft_limit_percent: None,
+            verify_output_cmac: false,
+            max_task_memory_mb: None,
+            max_task_runtime_seconds: None,
+            default_executor: None,
+            approval_excludes_creator: false,
+            function_categories: Vec::new(),
+            mesapy_denied_patterns: Vec::new(),
+            function_retention_seconds: 604800,
+            unique_function_names: false,
+            enforce_unique_iv: false,
+            max_task_retries: 3,
+            result_retention_seconds: None,
+            result_archive_url: None,
+            result_archive_encryption_key: None,
+            task_history_max_count: None,
+            task_history_max_age_seconds: None,
+            callback_allowed_hosts: Vec::new(),
+            crypto_key_encryption_key: None,
+            function_payload_encryption_key: None,
+            cursor_signing_secret: None,
+            function_publisher_keys_source: vec![],
+            function_publisher_keys_bytes: vec![],
+            storage_format: "json".to_string(),
+            storage_replicas: Vec::new(),
+            replica_read_your_writes_seconds: 30,
+            degraded_cache_ttl_seconds: 0,
+            degraded_cache_capacity: 10000,
+            degraded_write_queue_depth: 100,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub shutdown_timeout_seconds: u64,
+    // Tolerated difference between clocks of machines, which is applied to
+    // time-based checks, e.g., token expiry and report freshness
+    pub clock_skew_tolerance_seconds: u64,
+    // Maximum number of open connections of the management and frontend
+    // services, unlimited if not set
+    pub max_connections: Option<usize>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            shutdown_timeout_seconds: 30,
+            clock_skew_tolerance_seconds: 0,
+            max_connections: None,
+        }
+    }
+}
+
+// Deployments sharing a storage service must use different key namespaces.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub key_namespace: String,
+}
+
+// Admin user provisioned by the authentication service on start, who logs in
+// with the setup token as the password. Nothing is done if the user exists.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AuthenticationConfig {
+    pub bootstrap_admin_id: Option<String>,
+    pub bootstrap_admin_token: Option<String>,
+    // Registering an existing user with the same password succeeds instead of
+    // failing, so that clients can retry registrations
+    pub idempotent_registration: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct FileAgentConfig {
+    pub credentials: HashMap<String, FileAgentCredential>,
+}
+
+// Credential of a remote storage, which is applied to requests of the file
+// agent to the scheme and host which name it, or whose urls start with one of
+// its url prefixes, unless their urls carry inline credentials.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FileAgentCredential {
+    pub scheme: String,
+    pub host: String,
+    // query string appended to urls, e.g., a SAS token
+    #[serde(default)]
+    pub query: Option<String>,
+    // value of the Authorization header
+    #[serde(default)]
+    pub authorization: Option<String>,
+    // urls to which the credential is applied without being named
+    #[serde(default)]
+    pub url_prefixes: Vec<String>,
+}
+
 impl RuntimeConfig {
     pub fn from_toml<T: AsRef<Path>>(path: T) -> Result<Self> {
         let contents = fs::read_to_string(path.as_ref())
@@ -117,6 +436,36 @@ impl RuntimeConfig {
         }
         config.audit.auditor_signatures_bytes = signatures;
 
+        let mut publisher_keys: Vec<Vec<u8>> = vec![];
+        for source in &config.management.function_publisher_keys_source {
+            let key = match source {
+                ConfigSource::Path(ref path) => fs::read(path)
+                    .with_context(|| format!("Cannot read publisher key from {:?}", path))?,
+            };
+            publisher_keys.push(key);
+        }
+        config.management.function_publisher_keys_bytes = publisher_keys;
+
+        let mut auditor_keys: Vec<Vec<u8>> = vec![];
+        for source in &config.audit.auditor_public_keys_source {
+            let key = match source {
+                ConfigSource::Path(ref path) => read_der(path)
+                    .with_context(|| format!("Cannot read auditor public key from {:?}", path))?,
+            };
+            auditor_keys.push(key);
+        }
+        config.audit.auditor_public_keys_bytes = auditor_keys;
+
+        let mut root_ca_certs: Vec<Vec<u8>> = vec![];
+        for source in &config.attestation.root_ca_certs_source {
+            let cert = match source {
+                ConfigSource::Path(ref path) => read_der(path)
+                    .with_context(|| format!("Cannot read root certificate from {:?}", path))?,
+            };
+            root_ca_certs.push(cert);
+        }
+        config.attestation.root_ca_certs_bytes = root_ca_certs;
+
         if env::var("AS_ALGO").is_ok()
             && env::var("AS_URL").is_ok()
             && env::var("AS_SPID").is_ok()
@@ -131,6 +480,7 @@ impl RuntimeConfig {
                 url,
                 key,
                 spid,
+                ..config.attestation
             };
         }
 
@@ -145,6 +495,27 @@ impl RuntimeConfig {
     }
 }
 
+// Reads a DER encoded key or certificate, which must be a single complete
+// SEQUENCE.
+fn read_der(path: &Path) -> Result<Vec<u8>> {
+    let bytes = fs::read(path)?;
+    let lengths = match bytes.get(1) {
+        Some(first) if *first < 0x80 => Some((2, *first as usize)),
+        Some(first) if *first > 0x80 && *first <= 0x84 => {
+            let n = (*first & 0x7f) as usize;
+            bytes.get(2..2 + n).map(|len| {
+                let len = len.iter().fold(0usize, |len, b| (len << 8) | *b as usize);
+                (2 + n, len)
+            })
+        }
+        _ => None,
+    };
+    match lengths {
+        Some((header, len)) if bytes[0] == 0x30 && header + len == bytes.len() => Ok(bytes),
+        _ => bail!("Not DER encoded"),
+    }
+}
+
 fn validate_config(config: &RuntimeConfig) -> Result<()> {
     match config.attestation.algorithm.as_str() {
         "sgx_epid" | "sgx_ecdsa" => (),
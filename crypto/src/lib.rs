@@ -25,7 +25,7 @@ use std::prelude::v1::*;
 use anyhow::{anyhow, ensure, Context, Result};
 use protected_fs::ProtectedFile;
 use rand::prelude::RngCore;
-use ring::aead;
+use ring::{aead, agreement, digest};
 use serde::{Deserialize, Serialize};
 use std::format;
 use std::path::Path;
@@ -37,6 +37,9 @@ const AES_GCM_256_KEY_LENGTH: usize = 32;
 const AES_GCM_256_IV_LENGTH: usize = 12;
 const TEACLAVE_FILE_128_ROOT_KEY_LENGTH: usize = 16;
 
+const X25519_PUBLIC_KEY_LENGTH: usize = 32;
+const SEALING_KEY_INFO: &[u8] = b"teaclave-sealed-secret";
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct AesGcm256Key {
     pub key: [u8; AES_GCM_256_KEY_LENGTH],
@@ -250,13 +253,84 @@ pub fn aead_encrypt(
     Ok(())
 }
 
+// Encrypts a secret returned to a client with the X25519 public key the client
+// provided, so that it can only be decrypted with the private key held by the
+// client. A key pair is generated for each secret and its public key is
+// prepended to the ciphertext, so a fixed nonce is never reused with a key.
+pub fn seal_to_public_key(public_key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let rng = ring::rand::SystemRandom::new();
+    let private_key = agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng)
+        .map_err(|_| anyhow!("Cannot generate ephemeral key"))?;
+    let ephemeral_public_key = private_key
+        .compute_public_key()
+        .map_err(|_| anyhow!("Cannot compute ephemeral public key"))?;
+    let key = agree_sealing_key(private_key, public_key)?;
+
+    let mut ciphertext = plaintext.to_vec();
+    aead_encrypt(
+        &aead::AES_256_GCM,
+        &mut ciphertext,
+        &key,
+        &[0u8; AES_GCM_256_IV_LENGTH],
+    )?;
+
+    let mut sealed = ephemeral_public_key.as_ref().to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+// Decrypts a secret sealed by seal_to_public_key on the client side.
+pub fn open_with_private_key(
+    private_key: agreement::EphemeralPrivateKey,
+    sealed: &[u8],
+) -> Result<Vec<u8>> {
+    ensure!(
+        sealed.len() > X25519_PUBLIC_KEY_LENGTH,
+        "Invalid sealed secret length: {}",
+        sealed.len()
+    );
+    let (ephemeral_public_key, ciphertext) = sealed.split_at(X25519_PUBLIC_KEY_LENGTH);
+    let key = agree_sealing_key(private_key, ephemeral_public_key)?;
+
+    let mut buf = ciphertext.to_vec();
+    let plaintext = aead_decrypt(
+        &aead::AES_256_GCM,
+        &mut buf,
+        &key,
+        &[0u8; AES_GCM_256_IV_LENGTH],
+    )?;
+    Ok(plaintext.to_vec())
+}
+
+fn agree_sealing_key(
+    private_key: agreement::EphemeralPrivateKey,
+    peer_public_key: &[u8],
+) -> Result<Vec<u8>> {
+    let peer_public_key = agreement::UnparsedPublicKey::new(&agreement::X25519, peer_public_key);
+    agreement::agree_ephemeral(
+        private_key,
+        &peer_public_key,
+        anyhow!("X25519 key agreement error"),
+        |shared_secret| {
+            let mut context = digest::Context::new(&digest::SHA256);
+            context.update(SEALING_KEY_INFO);
+            context.update(shared_secret);
+            Ok(context.finish().as_ref().to_vec())
+        },
+    )
+}
+
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
     use super::*;
     use teaclave_test_utils::*;
 
     pub fn run_tests() -> bool {
-        run_tests!(test_aead_enc_then_dec, test_crypto_info,)
+        run_tests!(
+            test_aead_enc_then_dec,
+            test_crypto_info,
+            test_seal_then_open,
+        )
     }
 
     fn test_aead_enc_then_dec() {
@@ -284,4 +358,20 @@ pub mod tests {
         crypto_info.decrypt(&mut buf).unwrap();
         assert_eq!(&buf[..], &plain_text[..]);
     }
+
+    fn test_seal_then_open() {
+        let rng = ring::rand::SystemRandom::new();
+        let private_key =
+            agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng).unwrap();
+        let public_key = private_key.compute_public_key().unwrap();
+
+        let secret = b"aes-gcm-256 key and iv";
+        let sealed = seal_to_public_key(public_key.as_ref(), secret).unwrap();
+        assert_ne!(&sealed[X25519_PUBLIC_KEY_LENGTH..], &secret[..]);
+        let opened = open_with_private_key(private_key, &sealed).unwrap();
+        assert_eq!(&opened[..], &secret[..]);
+
+        let other_key = agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng).unwrap();
+        assert!(open_with_private_key(other_key, &sealed).is_err());
+    }
 }
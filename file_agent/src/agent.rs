@@ -21,16 +21,87 @@ use tokio::io::AsyncWriteExt;
 use tokio_util::codec;
 use url::Url;
 
+use std::collections::HashMap;
 use std::path::{Component, Path, PathBuf};
-use teaclave_types::{FileAgentRequest, HandleFileCommand, HandleFileInfo};
+use std::sync::RwLock;
+use std::time::Duration;
+use teaclave_config::FileAgentCredential;
+use teaclave_types::{FileAgentRequest, HandleFileCommand, HandleFileInfo, FILE_AGENT_URL_SCHEMES};
+
+lazy_static! {
+    static ref CREDENTIALS: RwLock<HashMap<String, FileAgentCredential>> =
+        RwLock::new(HashMap::new());
+}
+
+// Posts are made from the reaper thread of the management service, which must
+// not be held by an unresponsive endpoint.
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn set_credentials(credentials: HashMap<String, FileAgentCredential>) {
+    *CREDENTIALS.write().unwrap() = credentials;
+}
+
+// Applies a credential of the store to the remote url and returns the value of
+// the Authorization header if any. Urls carrying inline credentials, e.g.,
+// presigned urls, are used as is. Without a name, only a credential listing a
+// prefix of the url is applied, so that urls given by users cannot pick up
+// credentials of the service.
+fn resolve_credential(
+    remote: &Url,
+    name: Option<&str>,
+    credentials: &HashMap<String, FileAgentCredential>,
+) -> anyhow::Result<(Url, Option<String>)> {
+    let mut url = remote.clone();
+    if url.query().is_some() || !url.username().is_empty() {
+        return Ok((url, None));
+    }
+
+    let applies = |c: &FileAgentCredential| {
+        c.scheme == url.scheme() && Some(c.host.as_str()) == url.host_str()
+    };
+    let credential = match name {
+        Some(name) => {
+            let credential = credentials
+                .get(name)
+                .ok_or_else(|| anyhow::anyhow!("Credential not found: {}", name))?;
+            anyhow::ensure!(
+                applies(credential),
+                "Credential {} does not apply to {}",
+                name,
+                url
+            );
+            Some(credential)
+        }
+        None => credentials.values().find(|c| {
+            applies(c)
+                && c.url_prefixes
+                    .iter()
+                    .any(|prefix| url.as_str().starts_with(prefix.as_str()))
+        }),
+    };
+
+    match credential {
+        Some(credential) => {
+            if let Some(query) = &credential.query {
+                url.set_query(Some(query));
+            }
+            Ok((url, credential.authorization.clone()))
+        }
+        None => Ok((url, None)),
+    }
+}
 
 async fn download_remote_input_to_file(
     presigned_url: Url,
+    authorization: Option<String>,
     dest: impl AsRef<std::path::Path>,
 ) -> anyhow::Result<()> {
-    let mut download = reqwest::get(presigned_url.as_str())
-        .await?
-        .error_for_status()?;
+    let client = reqwest::Client::new();
+    let mut request = client.get(presigned_url.as_str());
+    if let Some(authorization) = authorization {
+        request = request.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+    let mut download = request.send().await?.error_for_status()?;
 
     let mut outfile = tokio::fs::File::create(dest).await?;
 
@@ -56,6 +127,7 @@ async fn copy_file(
 async fn upload_output_file_to_remote(
     src: impl AsRef<std::path::Path>,
     presigned_url: Url,
+    authorization: Option<String>,
 ) -> anyhow::Result<()> {
     let metadata = std::fs::metadata(&src)?;
     let file_len = metadata.len();
@@ -67,13 +139,14 @@ async fn upload_output_file_to_remote(
     let body = reqwest::Body::wrap_stream(stream);
 
     let client = reqwest::Client::new();
-    let res = client
+    let mut request = client
         .put(presigned_url.as_str())
         .header(reqwest::header::CONTENT_TYPE, "application/x-binary")
-        .header(reqwest::header::CONTENT_LENGTH, file_len.to_string())
-        .body(body)
-        .send()
-        .await?;
+        .header(reqwest::header::CONTENT_LENGTH, file_len.to_string());
+    if let Some(authorization) = authorization {
+        request = request.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+    let res = request.body(body).send().await?;
     match res.status() {
         http::StatusCode::OK => Ok(()),
         status => anyhow::bail!("{}", status),
@@ -94,7 +167,12 @@ async fn handle_download(
 
     match remote.scheme() {
         "https" | "http" => {
-            download_remote_input_to_file(remote, dst).await?;
+            let (url, authorization) = resolve_credential(
+                &remote,
+                info.credential.as_deref(),
+                &CREDENTIALS.read().unwrap(),
+            )?;
+            download_remote_input_to_file(url, authorization, &dst).await?;
         }
         "file" => {
             let src = remote
@@ -105,7 +183,7 @@ async fn handle_download(
                 "[Download] Src local file: {:?} doesn't exist.",
                 src
             );
-            copy_file(src, dst).await?;
+            copy_file(src, &dst).await?;
         }
         "fusion" => {
             let path = remote
@@ -127,10 +205,23 @@ async fn handle_download(
                 "[Download] Src local file: {:?} doesn't exist.",
                 src
             );
-            copy_file(src, dst).await?;
+            copy_file(src, &dst).await?;
         }
         _ => anyhow::bail!("Scheme not supported"),
     }
+
+    if let Some(expected_size) = info.expected_size {
+        let size = tokio::fs::metadata(&dst).await?.len();
+        if size != expected_size {
+            tokio::fs::remove_file(&dst).await?;
+            anyhow::bail!(
+                "[Download] Size of {:?} is {} bytes, expected {} bytes.",
+                dst,
+                size,
+                expected_size
+            );
+        }
+    }
     Ok(())
 }
 
@@ -144,7 +235,12 @@ async fn handle_upload(info: HandleFileInfo, fusion_base: impl AsRef<Path>) -> a
 
     match info.remote.scheme() {
         "https" | "http" => {
-            upload_output_file_to_remote(src, info.remote).await?;
+            let (url, authorization) = resolve_credential(
+                &info.remote,
+                info.credential.as_deref(),
+                &CREDENTIALS.read().unwrap(),
+            )?;
+            upload_output_file_to_remote(src, url, authorization).await?;
         }
         "file" => {
             let dst = info
@@ -182,8 +278,161 @@ async fn handle_upload(info: HandleFileInfo, fusion_base: impl AsRef<Path>) -> a
     Ok(())
 }
 
+async fn delete_remote_file(
+    presigned_url: Url,
+    authorization: Option<String>,
+) -> anyhow::Result<()> {
+    let client = reqwest::Client::new();
+    let mut request = client.delete(presigned_url.as_str());
+    if let Some(authorization) = authorization {
+        request = request.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+    request.send().await?.error_for_status()?;
+    Ok(())
+}
+
+// Deletes the remote file. The local path of the request is not used.
+async fn handle_delete(info: HandleFileInfo, fusion_base: impl AsRef<Path>) -> anyhow::Result<()> {
+    let remote = info.remote;
+
+    let path = match remote.scheme() {
+        "https" | "http" => {
+            let (url, authorization) = resolve_credential(
+                &remote,
+                info.credential.as_deref(),
+                &CREDENTIALS.read().unwrap(),
+            )?;
+            return delete_remote_file(url, authorization).await;
+        }
+        "file" => remote
+            .to_file_path()
+            .map_err(|e| anyhow::anyhow!("Cannot convert file:// to path: {:?}", e))?,
+        "fusion" => {
+            let path = remote
+                .to_file_path()
+                .map_err(|e| anyhow::anyhow!("Cannot convert fusion:// to path: {:?}", e))?;
+            let components = path.components().collect::<Vec<_>>();
+            anyhow::ensure!(
+                (components[0] == Component::RootDir)
+                    && (components[1] == Component::Normal("TEACLAVE_FUSION_BASE".as_ref())),
+                "[Delete] Fusion data format error: {:?}",
+                components
+            );
+
+            let relative_path: PathBuf = components[2..].iter().collect();
+            fusion_base.as_ref().join(relative_path)
+        }
+        _ => anyhow::bail!("Scheme not supported"),
+    };
+
+    anyhow::ensure!(
+        path.exists(),
+        "[Delete] Remote file: {:?} doesn't exist.",
+        path
+    );
+    tokio::fs::remove_file(&path).await?;
+    Ok(())
+}
+
+// Presigned GET urls reject HEAD requests, so only the first byte of the file
+// is requested. An empty file can't satisfy the range but still exists.
+async fn check_remote_file(
+    presigned_url: Url,
+    authorization: Option<String>,
+) -> anyhow::Result<()> {
+    let client = reqwest::Client::new();
+    let mut request = client
+        .get(presigned_url.as_str())
+        .header(reqwest::header::RANGE, "bytes=0-0");
+    if let Some(authorization) = authorization {
+        request = request.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+    let response = request.send().await?;
+    if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(());
+    }
+    response.error_for_status()?;
+    Ok(())
+}
+
+// Checks the remote file exists. The local path of the request is not used.
+async fn handle_check(info: HandleFileInfo, fusion_base: impl AsRef<Path>) -> anyhow::Result<()> {
+    let remote = info.remote;
+
+    let path = match remote.scheme() {
+        "https" | "http" => {
+            let (url, authorization) = resolve_credential(
+                &remote,
+                info.credential.as_deref(),
+                &CREDENTIALS.read().unwrap(),
+            )?;
+            return check_remote_file(url, authorization).await;
+        }
+        "file" => remote
+            .to_file_path()
+            .map_err(|e| anyhow::anyhow!("Cannot convert file:// to path: {:?}", e))?,
+        "fusion" => {
+            let path = remote
+                .to_file_path()
+                .map_err(|e| anyhow::anyhow!("Cannot convert fusion:// to path: {:?}", e))?;
+            let components = path.components().collect::<Vec<_>>();
+            anyhow::ensure!(
+                (components[0] == Component::RootDir)
+                    && (components[1] == Component::Normal("TEACLAVE_FUSION_BASE".as_ref())),
+                "[Check] Fusion data format error: {:?}",
+                components
+            );
+
+            let relative_path: PathBuf = components[2..].iter().collect();
+            fusion_base.as_ref().join(relative_path)
+        }
+        _ => anyhow::bail!("Scheme not supported"),
+    };
+
+    anyhow::ensure!(
+        path.exists(),
+        "[Check] Remote file: {:?} doesn't exist.",
+        path
+    );
+    Ok(())
+}
+
+// Posts the local file to the remote endpoint, which must be an HTTP one.
+async fn handle_post(info: HandleFileInfo) -> anyhow::Result<()> {
+    let remote = info.remote;
+    anyhow::ensure!(
+        remote.scheme() == "https" || remote.scheme() == "http",
+        "[Post] Scheme not supported: {}",
+        remote.scheme()
+    );
+    let (url, authorization) = resolve_credential(
+        &remote,
+        info.credential.as_deref(),
+        &CREDENTIALS.read().unwrap(),
+    )?;
+    let body = tokio::fs::read(&info.local).await?;
+
+    let client = reqwest::Client::new();
+    let mut request = client
+        .post(url.as_str())
+        .timeout(POST_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if let Some(authorization) = authorization {
+        request = request.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+    request.body(body).send().await?.error_for_status()?;
+    Ok(())
+}
+
 fn handle_file_request(bytes: &[u8]) -> anyhow::Result<()> {
     let req: FileAgentRequest = serde_json::from_slice(bytes)?;
+    if let Some(info) = req
+        .info
+        .iter()
+        .find(|info| !FILE_AGENT_URL_SCHEMES.contains(&info.remote.scheme()))
+    {
+        anyhow::bail!("Scheme not supported: {}", info.remote.scheme());
+    }
     let results = tokio::runtime::Builder::new()
         .threaded_scheduler()
         .enable_all()
@@ -213,6 +462,36 @@ fn handle_file_request(bytes: &[u8]) -> anyhow::Result<()> {
                         .collect();
                     join_all(futures).await
                 }
+                HandleFileCommand::Delete => {
+                    let futures: Vec<_> = req
+                        .info
+                        .into_iter()
+                        .map(|info| {
+                            let fusion_base = fusion_base.clone();
+                            tokio::spawn(async { handle_delete(info, fusion_base).await })
+                        })
+                        .collect();
+                    join_all(futures).await
+                }
+                HandleFileCommand::Post => {
+                    let futures: Vec<_> = req
+                        .info
+                        .into_iter()
+                        .map(|info| tokio::spawn(async { handle_post(info).await }))
+                        .collect();
+                    join_all(futures).await
+                }
+                HandleFileCommand::Check => {
+                    let futures: Vec<_> = req
+                        .info
+                        .into_iter()
+                        .map(|info| {
+                            let fusion_base = fusion_base.clone();
+                            tokio::spawn(async { handle_check(info, fusion_base).await })
+                        })
+                        .collect();
+                    join_all(futures).await
+                }
             }
         });
 
@@ -376,4 +655,137 @@ mod tests {
 
         std::fs::remove_dir_all(&base).unwrap();
     }
+
+    #[test]
+    fn test_local_delete() {
+        let base_str = "/tmp/file_agent_local_delete";
+        let base = PathBuf::from(&base_str);
+        std::fs::create_dir_all(&base).unwrap();
+
+        let src = base.join("src.txt");
+        std::fs::write(&src, b"Hello Teaclave!").unwrap();
+        let url = Url::parse(&format!("file://{}/src.txt", base_str)).unwrap();
+
+        let info = HandleFileInfo::new("", &url);
+        let req = FileAgentRequest::new(HandleFileCommand::Delete, vec![info], "");
+        let bytes = serde_json::to_vec(&req).unwrap();
+        handle_file_request(&bytes).unwrap();
+        assert!(!src.exists());
+
+        // deleting a missing file fails
+        assert!(handle_file_request(&bytes).is_err());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_local_check() {
+        let base_str = "/tmp/file_agent_local_check";
+        let base = PathBuf::from(&base_str);
+        std::fs::create_dir_all(&base).unwrap();
+
+        let url = Url::parse(&format!("file://{}/src.txt", base_str)).unwrap();
+        let info = HandleFileInfo::new("", &url);
+        let req = FileAgentRequest::new(HandleFileCommand::Check, vec![info], "");
+        let bytes = serde_json::to_vec(&req).unwrap();
+        assert!(handle_file_request(&bytes).is_err());
+
+        std::fs::write(base.join("src.txt"), b"Hello Teaclave!").unwrap();
+        handle_file_request(&bytes).unwrap();
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_post_requires_http() {
+        let base_str = "/tmp/file_agent_post";
+        let base = PathBuf::from(&base_str);
+        std::fs::create_dir_all(&base).unwrap();
+
+        let src = base.join("payload.json");
+        std::fs::write(&src, b"{}").unwrap();
+        let url = Url::parse(&format!("file://{}/dst.json", base_str)).unwrap();
+
+        let info = HandleFileInfo::new(&src, &url);
+        let req = FileAgentRequest::new(HandleFileCommand::Post, vec![info], "");
+        let bytes = serde_json::to_vec(&req).unwrap();
+        assert!(handle_file_request(&bytes).is_err());
+        assert!(!base.join("dst.json").exists());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_download_size_mismatch() {
+        let base_str = "/tmp/file_agent_size_mismatch";
+        let base = PathBuf::from(&base_str);
+        std::fs::create_dir_all(&base).unwrap();
+
+        let src = base.join("src.txt");
+        {
+            let mut file = std::fs::File::create(&src).unwrap();
+            file.write_all(b"Hello Teaclave Results!").unwrap();
+        }
+        let url = Url::parse(&format!("file://{}/src.txt", base_str)).unwrap();
+
+        let dest = base.join("d1.txt");
+        let info = HandleFileInfo::new(&dest, &url).expected_size(Some(5));
+        let req = FileAgentRequest::new(HandleFileCommand::Download, vec![info], "");
+
+        let bytes = serde_json::to_vec(&req).unwrap();
+        assert!(handle_file_request(&bytes).is_err());
+        assert!(!dest.exists());
+
+        let info = HandleFileInfo::new(&dest, &url).expected_size(Some(23));
+        let req = FileAgentRequest::new(HandleFileCommand::Download, vec![info], "");
+
+        let bytes = serde_json::to_vec(&req).unwrap();
+        handle_file_request(&bytes).unwrap();
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_named_credential() {
+        let mut credentials = HashMap::new();
+        credentials.insert(
+            "s3".to_string(),
+            FileAgentCredential {
+                scheme: "https".to_string(),
+                host: "bucket.s3.amazonaws.com".to_string(),
+                query: None,
+                authorization: Some("AWS4-HMAC-SHA256 Credential=AKID".to_string()),
+                url_prefixes: vec!["https://bucket.s3.amazonaws.com/teaclave/".to_string()],
+            },
+        );
+
+        let url = Url::parse("https://bucket.s3.amazonaws.com/input.txt").unwrap();
+        let info = HandleFileInfo::new("/tmp/input.txt", &url).credential("s3");
+        let (resolved, authorization) =
+            resolve_credential(&info.remote, info.credential.as_deref(), &credentials).unwrap();
+        assert_eq!(resolved, url);
+        assert_eq!(
+            authorization.as_deref(),
+            Some("AWS4-HMAC-SHA256 Credential=AKID")
+        );
+
+        // inline credentials override the store
+        let presigned =
+            Url::parse("https://bucket.s3.amazonaws.com/input.txt?X-Amz-Signature=abc").unwrap();
+        let (resolved, authorization) =
+            resolve_credential(&presigned, Some("s3"), &credentials).unwrap();
+        assert_eq!(resolved, presigned);
+        assert!(authorization.is_none());
+
+        let other = Url::parse("https://other.s3.amazonaws.com/input.txt").unwrap();
+        assert!(resolve_credential(&other, Some("s3"), &credentials).is_err());
+        assert!(resolve_credential(&url, Some("gcs"), &credentials).is_err());
+
+        // unnamed credentials are only applied to urls under their prefixes
+        let (_, authorization) = resolve_credential(&url, None, &credentials).unwrap();
+        assert!(authorization.is_none());
+        let prefixed = Url::parse("https://bucket.s3.amazonaws.com/teaclave/input.txt").unwrap();
+        let (_, authorization) = resolve_credential(&prefixed, None, &credentials).unwrap();
+        assert!(authorization.is_some());
+    }
 }
@@ -17,6 +17,8 @@
 
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate lazy_static;
 
 mod agent;
-pub use agent::ocall_handle_file_request;
+pub use agent::{ocall_handle_file_request, set_credentials};
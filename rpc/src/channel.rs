@@ -18,6 +18,7 @@
 use crate::config::SgxTrustedTlsClientConfig;
 use crate::transport::{ClientTransport, SgxTrustedTlsTransport};
 use crate::Request;
+use crate::Response;
 use anyhow::anyhow;
 use anyhow::Result;
 use http::Uri;
@@ -60,7 +61,13 @@ where
     pub fn invoke(
         &mut self,
         input: Request<U>,
-    ) -> teaclave_types::TeaclaveServiceResponseResult<V> {
+    ) -> teaclave_types::TeaclaveServiceResponseResult<Response<V>> {
         self.transport.send(input)
     }
+
+    // A call which times out leaves the channel in an unknown state, it
+    // should be reconnected before being used again.
+    pub fn set_timeout(&mut self, timeout: Option<std::time::Duration>) -> Result<()> {
+        self.transport.set_timeout(timeout)
+    }
 }
@@ -22,13 +22,55 @@ use std::sync::{Arc, SgxRwLock as RwLock};
 use std::time::SystemTime;
 use std::untrusted::time::SystemTimeEx;
 use teaclave_attestation::report::AttestationReport;
-use teaclave_attestation::verifier::AttestationReportVerifier;
+use teaclave_attestation::verifier::{AttestationPolicy, AttestationReportVerifier};
 use teaclave_attestation::AttestedTlsConfig;
 use teaclave_types::EnclaveAttr;
 
+// SHA-256 fingerprint of a DER encoded certificate in hex
+pub fn cert_fingerprint(cert_der: &[u8]) -> String {
+    hex::encode(ring::digest::digest(&ring::digest::SHA256, cert_der))
+}
+
+// Accepts clients whose certificates have one of the fingerprints and are
+// accepted by the inner verifier, if it authenticates clients.
+struct ClientCertAllowList {
+    inner: Arc<dyn rustls::ClientCertVerifier>,
+    fingerprints: Vec<String>,
+}
+
+impl rustls::ClientCertVerifier for ClientCertAllowList {
+    fn offer_client_auth(&self) -> bool {
+        true
+    }
+
+    fn client_auth_root_subjects(&self) -> rustls::DistinguishedNames {
+        rustls::DistinguishedNames::new()
+    }
+
+    fn verify_client_cert(
+        &self,
+        certs: &[rustls::Certificate],
+    ) -> std::result::Result<rustls::ClientCertVerified, rustls::TLSError> {
+        if certs.len() != 1 {
+            return Err(rustls::TLSError::NoCertificatesPresented);
+        }
+        let fingerprint = cert_fingerprint(&certs[0].0);
+        if !self.fingerprints.contains(&fingerprint) {
+            debug!("client cert {} is not allowed", fingerprint);
+            return Err(rustls::TLSError::WebPKIError(webpki::Error::UnknownIssuer));
+        }
+        if self.inner.offer_client_auth() {
+            self.inner.verify_client_cert(certs)
+        } else {
+            Ok(rustls::ClientCertVerified::assertion())
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct SgxTrustedTlsServerConfig {
     server_config: rustls::ServerConfig,
+    client_cert_verifier: Arc<dyn rustls::ClientCertVerifier>,
     attested_tls_config: Option<Arc<RwLock<AttestedTlsConfig>>>,
     time: std::time::SystemTime,
     validity: std::time::Duration,
@@ -37,12 +79,13 @@ pub struct SgxTrustedTlsServerConfig {
 impl Default for SgxTrustedTlsServerConfig {
     fn default() -> Self {
         let client_cert_verifier = rustls::NoClientAuth::new();
-        let server_config = rustls::ServerConfig::new(client_cert_verifier);
+        let server_config = rustls::ServerConfig::new(client_cert_verifier.clone());
         let time = SystemTime::now();
         let validity = std::time::Duration::from_secs(u64::max_value());
 
         Self {
             server_config,
+            client_cert_verifier,
             attested_tls_config: None,
             time,
             validity,
@@ -87,25 +130,68 @@ impl SgxTrustedTlsServerConfig {
             verifier,
         ));
 
-        self.server_config.set_client_certificate_verifier(verifier);
+        self.set_client_cert_verifier(verifier);
         Ok(Self { ..self })
     }
 
+    // Accepts clients satisfying one of the policies, e.g., a policy for each
+    // inbound service.
+    pub fn attestation_policy_verifier(
+        mut self,
+        policies: Vec<AttestationPolicy>,
+        root_ca: &[u8],
+        verifier: fn(&AttestationReport) -> bool,
+    ) -> Result<Self> {
+        let verifier = Arc::new(AttestationReportVerifier::with_policies(
+            policies, root_ca, verifier,
+        ));
+
+        self.set_client_cert_verifier(verifier);
+        Ok(Self { ..self })
+    }
+
+    // Only accepts clients whose certificates have one of the SHA-256
+    // fingerprints in hex, on top of the verifier set before. Clients are not
+    // restricted if the list is empty.
+    pub fn client_cert_fingerprints(mut self, fingerprints: &[String]) -> Self {
+        if fingerprints.is_empty() {
+            return self;
+        }
+        let verifier = Arc::new(ClientCertAllowList {
+            inner: self.client_cert_verifier.clone(),
+            fingerprints: fingerprints.iter().map(|f| f.to_lowercase()).collect(),
+        });
+        self.set_client_cert_verifier(verifier);
+        self
+    }
+
+    fn set_client_cert_verifier(&mut self, verifier: Arc<dyn rustls::ClientCertVerifier>) {
+        self.server_config
+            .set_client_certificate_verifier(verifier.clone());
+        self.client_cert_verifier = verifier;
+    }
+
     pub fn server_config(&self) -> Arc<rustls::ServerConfig> {
         Arc::new(self.server_config.clone())
     }
 
+    // The attested TLS config is renewed in the background before the
+    // attestation report expires, and the server config is refreshed once it
+    // is renewed.
     pub fn need_refresh(&self) -> bool {
-        let current_time = SystemTime::now();
-        let elapsed_time = current_time
-            .duration_since(self.time)
-            .unwrap_or(self.validity);
+        let renewed_time = match &self.attested_tls_config {
+            Some(lock) => match lock.read() {
+                Ok(attested_tls_config) => attested_tls_config.time,
+                Err(_) => return false,
+            },
+            None => return false,
+        };
         debug!(
-            "current_time: {:?}, self.time: {:?}, elapsed time: {:?}, self.validity: {:?}",
-            current_time, self.time, elapsed_time, self.validity
+            "renewed time: {:?}, self.time: {:?}, self.validity: {:?}",
+            renewed_time, self.time, self.validity
         );
 
-        elapsed_time >= self.validity
+        renewed_time != self.time
     }
 
     pub fn refresh_server_config(&mut self) -> Result<()> {
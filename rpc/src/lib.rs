@@ -21,6 +21,8 @@
 extern crate sgx_tstd as std;
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::prelude::v1::*;
 use teaclave_types::TeaclaveServiceResponseError;
 
 pub trait TeaclaveService<V, U>
@@ -32,6 +34,11 @@ where
         &self,
         request: Request<V>,
     ) -> std::result::Result<U, TeaclaveServiceResponseError>;
+
+    // Metadata sent with the response of the request just handled.
+    fn response_metadata(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
 }
 
 pub mod channel;
@@ -39,7 +46,7 @@ pub mod config;
 pub mod endpoint;
 mod protocol;
 mod request;
-pub use request::{IntoRequest, Request};
+pub use request::{IntoRequest, Request, Response};
 pub use teaclave_rpc_proc_macro::into_request;
 pub mod server;
 mod transport;
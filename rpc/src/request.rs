@@ -57,6 +57,30 @@ impl<T> Request<T> {
     }
 }
 
+// Responses carry metadata, e.g., the correlation id of the request, the same
+// way as requests. Responses without metadata are sent as the message only.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Response<T> {
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    #[serde(flatten)]
+    pub message: T,
+}
+
+impl<T> Response<T> {
+    pub fn new(message: T) -> Self {
+        Response {
+            metadata: HashMap::<String, String>::default(),
+            message,
+        }
+    }
+
+    pub fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+}
+
 pub trait IntoRequest<T> {
     fn into_request(self) -> Request<T>;
 }
@@ -19,8 +19,117 @@ use crate::config::SgxTrustedTlsServerConfig;
 use crate::transport::{ServerTransport, SgxTrustedTlsTransport};
 use crate::TeaclaveService;
 use anyhow::Result;
-use log::{debug, error, warn};
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use std::untrusted::time::SystemTimeEx;
+
+// Interval to check for shutdown while waiting for connections and draining
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Signal shared by the servers of an enclave, which is raised when the
+// enclave is finalized.
+pub static SHUTDOWN_SIGNAL: ShutdownSignal = ShutdownSignal::new();
+
+// Tracks in-flight requests of servers so that they can be drained before
+// the servers stop.
+pub struct ShutdownSignal {
+    requested: AtomicBool,
+    in_flight: AtomicUsize,
+    running_servers: AtomicUsize,
+}
+
+impl ShutdownSignal {
+    pub const fn new() -> Self {
+        Self {
+            requested: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+            running_servers: AtomicUsize::new(0),
+        }
+    }
+
+    // Asks the servers to stop accepting connections, and waits until they
+    // finish draining in-flight requests.
+    pub fn shutdown(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        while self.running_servers.load(Ordering::SeqCst) > 0 {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn enter_request(&self) -> InFlightGuard {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard { signal: self }
+    }
+
+    fn enter_server(&self) -> ServerGuard {
+        self.running_servers.fetch_add(1, Ordering::SeqCst);
+        ServerGuard { signal: self }
+    }
+
+    // Returns false if requests are still in flight after the timeout.
+    fn drain(&self, timeout: Duration) -> bool {
+        let start = SystemTime::now();
+        while self.in_flight() > 0 {
+            if start.elapsed().unwrap_or_default() >= timeout {
+                return false;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        true
+    }
+}
+
+pub(crate) struct InFlightGuard<'a> {
+    signal: &'a ShutdownSignal,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.signal.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct ServerGuard<'a> {
+    signal: &'a ShutdownSignal,
+}
+
+impl Drop for ServerGuard<'_> {
+    fn drop(&mut self) {
+        self.signal.running_servers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Counts an open connection of a server until it is closed.
+struct ConnectionGuard {
+    connections: Arc<AtomicUsize>,
+}
+
+impl ConnectionGuard {
+    fn new(connections: &Arc<AtomicUsize>) -> Self {
+        connections.fetch_add(1, Ordering::SeqCst);
+        Self {
+            connections: connections.clone(),
+        }
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
 
 pub struct SgxTrustedTlsServer<U, V>
 where
@@ -31,6 +140,9 @@ where
     tls_config: SgxTrustedTlsServerConfig,
     tcp_nodelay: bool,
     n_workers: usize,
+    shutdown_signal: &'static ShutdownSignal,
+    shutdown_timeout: Duration,
+    max_connections: Option<usize>,
     maker: std::marker::PhantomData<(U, V)>,
 }
 
@@ -48,6 +160,9 @@ where
             tls_config: server_config,
             tcp_nodelay: true,
             n_workers: 8,
+            shutdown_signal: &SHUTDOWN_SIGNAL,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            max_connections: None,
             maker: std::marker::PhantomData::<(U, V)>,
         }
     }
@@ -66,16 +181,52 @@ where
         }
     }
 
+    pub fn shutdown_signal(self, signal: &'static ShutdownSignal) -> Self {
+        Self {
+            shutdown_signal: signal,
+            ..self
+        }
+    }
+
+    // Maximum time to wait for in-flight requests when shutting down
+    pub fn shutdown_timeout(self, timeout: Duration) -> Self {
+        Self {
+            shutdown_timeout: timeout,
+            ..self
+        }
+    }
+
+    // Maximum number of open connections, including those waiting for a
+    // worker. Connections beyond the limit are closed once accepted.
+    pub fn max_connections(self, max_connections: Option<usize>) -> Self {
+        Self {
+            max_connections,
+            ..self
+        }
+    }
+
     pub fn start<X>(&mut self, service: X) -> Result<()>
     where
         X: 'static + TeaclaveService<V, U> + Clone + core::marker::Send,
     {
+        let signal = self.shutdown_signal;
+        let _server_guard = signal.enter_server();
         let pool = threadpool::ThreadPool::new(self.n_workers);
         let listener = std::net::TcpListener::bind(self.addr)?;
+        // Poll for connections so that the shutdown signal can be checked.
+        listener.set_nonblocking(true)?;
         let mut tls_config_ref = self.tls_config.server_config();
-        for stream in listener.incoming() {
-            match stream {
-                Ok(stream) => {
+        let connections = Arc::new(AtomicUsize::new(0));
+        while !signal.is_requested() {
+            match listener.accept() {
+                Ok((stream, peer_addr)) => {
+                    if let Some(max_connections) = self.max_connections {
+                        if connections.load(Ordering::SeqCst) >= max_connections {
+                            warn!("Too many connections, reject {:}", peer_addr);
+                            continue;
+                        }
+                    }
+
                     // Before introducing async into enclave, we check
                     // freshness for every incoming connection.
                     if self.tls_config.need_refresh() {
@@ -84,6 +235,10 @@ where
                         tls_config_ref = self.tls_config.server_config();
                     }
 
+                    if let Err(e) = stream.set_nonblocking(false) {
+                        warn!("Cannot set_nonblocking: {:}", e);
+                        continue;
+                    }
                     if let Err(e) = stream.set_nodelay(self.tcp_nodelay) {
                         warn!("Cannot set_nodelay: {:}", e);
                         continue;
@@ -92,18 +247,33 @@ where
                     let tls_stream = rustls::StreamOwned::new(session, stream);
                     let mut transport = SgxTrustedTlsTransport::new(tls_stream);
                     let service = service.clone();
-                    pool.execute(move || match transport.serve(service) {
-                        Ok(_) => (),
-                        Err(e) => {
-                            debug!("serve error: {:?}", e);
+                    let connection_guard = ConnectionGuard::new(&connections);
+                    pool.execute(move || {
+                        let _connection_guard = connection_guard;
+                        match transport.serve(service, signal) {
+                            Ok(_) => (),
+                            Err(e) => {
+                                debug!("serve error: {:?}", e);
+                            }
                         }
                     });
                 }
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(POLL_INTERVAL);
+                }
                 Err(e) => {
                     error!("Incoming error: {:}", e);
                 }
             }
         }
+
+        info!("Server is shutting down, draining in-flight requests");
+        if !signal.drain(self.shutdown_timeout) {
+            warn!(
+                "Server stopped with {} in-flight requests after the timeout",
+                signal.in_flight()
+            );
+        }
         Ok(())
     }
 }
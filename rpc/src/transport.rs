@@ -16,7 +16,9 @@
 // under the License.
 
 use crate::protocol;
+use crate::server::ShutdownSignal;
 use crate::Request;
+use crate::Response;
 use crate::TeaclaveService;
 use anyhow::Result;
 use log::debug;
@@ -27,14 +29,14 @@ pub(crate) trait ClientTransport {
     fn send<U, V>(
         &mut self,
         request: Request<U>,
-    ) -> teaclave_types::TeaclaveServiceResponseResult<V>
+    ) -> teaclave_types::TeaclaveServiceResponseResult<Response<V>>
     where
         U: Serialize + std::fmt::Debug,
         V: for<'de> Deserialize<'de> + std::fmt::Debug;
 }
 
 pub(crate) trait ServerTransport {
-    fn serve<U, V, X>(&mut self, service: X) -> Result<()>
+    fn serve<U, V, X>(&mut self, service: X, signal: &ShutdownSignal) -> Result<()>
     where
         U: Serialize + std::fmt::Debug,
         V: for<'de> Deserialize<'de> + std::fmt::Debug,
@@ -54,6 +56,13 @@ where
     pub fn new(stream: rustls::StreamOwned<S, std::net::TcpStream>) -> SgxTrustedTlsTransport<S> {
         SgxTrustedTlsTransport::<S> { stream }
     }
+
+    // Bounds reads and writes of the underlying socket, no bound if None.
+    pub fn set_timeout(&mut self, timeout: Option<std::time::Duration>) -> Result<()> {
+        self.stream.sock.set_read_timeout(timeout)?;
+        self.stream.sock.set_write_timeout(timeout)?;
+        Ok(())
+    }
 }
 
 impl<S> ClientTransport for SgxTrustedTlsTransport<S>
@@ -63,7 +72,7 @@ where
     fn send<U, V>(
         &mut self,
         request: Request<U>,
-    ) -> teaclave_types::TeaclaveServiceResponseResult<V>
+    ) -> teaclave_types::TeaclaveServiceResponseResult<Response<V>>
     where
         U: Serialize + std::fmt::Debug,
         V: for<'de> Deserialize<'de> + std::fmt::Debug,
@@ -71,7 +80,7 @@ where
         let mut protocol = protocol::JsonProtocol::new(&mut self.stream);
         protocol.write_message(request)?;
         protocol.read_message::<protocol::JsonProtocolResult<
-                V,
+                Response<V>,
                 teaclave_types::TeaclaveServiceResponseError,
             >>()?
             .into()
@@ -82,7 +91,7 @@ impl<S> ServerTransport for SgxTrustedTlsTransport<S>
 where
     S: rustls::Session,
 {
-    fn serve<U, V, X>(&mut self, service: X) -> Result<()>
+    fn serve<U, V, X>(&mut self, service: X, signal: &ShutdownSignal) -> Result<()>
     where
         U: Serialize + std::fmt::Debug,
         V: for<'de> Deserialize<'de> + std::fmt::Debug,
@@ -112,8 +121,24 @@ where
                     }
                 },
             };
-            let response: JsonProtocolResult<U, TeaclaveServiceResponseError> =
-                service.handle_request(request).into();
+            // The request is counted as in flight before checking the signal,
+            // so that it is either rejected or drained on shutdown.
+            let _guard = signal.enter_request();
+            if signal.is_requested() {
+                let response: JsonProtocolResult<U, TeaclaveServiceResponseError> =
+                    Err(TeaclaveServiceResponseError::RequestError(
+                        "service is shutting down".to_string(),
+                    ))
+                    .into();
+                protocol.write_message(response)?;
+                return Ok(());
+            }
+            let response = service.handle_request(request).map(|message| Response {
+                metadata: service.response_metadata(),
+                message,
+            });
+            let response: JsonProtocolResult<Response<U>, TeaclaveServiceResponseError> =
+                response.into();
             protocol.write_message(response)?;
         }
     }
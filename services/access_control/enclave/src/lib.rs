@@ -25,7 +25,8 @@ extern crate log;
 use anyhow::{anyhow, Result};
 
 use std::prelude::v1::*;
-use teaclave_attestation::{verifier, AttestationConfig, RemoteAttestation};
+use std::time::Duration;
+use teaclave_attestation::{verifier, RemoteAttestation};
 use teaclave_binder::proto::{
     ECallCommand, FinalizeEnclaveInput, FinalizeEnclaveOutput, InitEnclaveInput, InitEnclaveOutput,
     StartServiceInput, StartServiceOutput,
@@ -47,15 +48,15 @@ mod acs;
 mod service;
 
 fn start_service(config: &RuntimeConfig) -> Result<()> {
+    let root_ca_certs = config.attestation.root_ca_certs(AS_ROOT_CA_CERT);
     let listen_address = config.internal_endpoints.access_control.listen_address;
-    let attestation_config = AttestationConfig::from_teaclave_config(&config)?;
-    let attested_tls_config = RemoteAttestation::new(attestation_config)
+    let attested_tls_config = RemoteAttestation::from_teaclave_config(&config)?
         .generate_and_endorse()?
         .attested_tls_config()
         .ok_or_else(|| anyhow!("cannot get attested TLS config"))?;
     let enclave_info = EnclaveInfo::verify_and_new(
         &config.audit.enclave_info_bytes,
-        AUDITOR_PUBLIC_KEYS,
+        &config.audit.auditor_public_keys(AUDITOR_PUBLIC_KEYS),
         &config.audit.auditor_signatures_bytes,
     )?;
     let accepted_enclave_attrs: Vec<teaclave_types::EnclaveAttr> = ACCESS_CONTROL_INBOUND_SERVICES
@@ -68,7 +69,7 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
     let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
         .attestation_report_verifier(
         accepted_enclave_attrs,
-        AS_ROOT_CA_CERT,
+        &root_ca_certs,
         verifier::universal_quote_verifier,
     )?;
 
@@ -76,7 +77,8 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
     let mut server = SgxTrustedTlsServer::<
         TeaclaveAccessControlResponse,
         TeaclaveAccessControlRequest,
-    >::new(listen_address, server_config);
+    >::new(listen_address, server_config)
+    .shutdown_timeout(Duration::from_secs(config.server.shutdown_timeout_seconds));
     let service = service::TeaclaveAccessControlService::new();
     match server.start(service) {
         Ok(_) => (),
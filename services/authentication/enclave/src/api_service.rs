@@ -16,17 +16,20 @@
 // under the License.
 
 use crate::user_db::{DbClient, DbError};
-use crate::user_info::UserInfo;
+use crate::user_info::{Session, UserInfo};
 use std::prelude::v1::*;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::untrusted::time::SystemTimeEx;
 use teaclave_proto::teaclave_authentication_service::{
-    TeaclaveAuthenticationApi, UserLoginRequest, UserLoginResponse, UserRegisterRequest,
-    UserRegisterResponse,
+    ExportUsersRequest, ExportUsersResponse, ImportUsersRequest, ImportUsersResponse,
+    ListSessionsRequest, ListSessionsResponse, RevokeSessionRequest, RevokeSessionResponse,
+    TeaclaveAuthenticationApi, UserLoginRequest, UserLoginResponse, UserRecord,
+    UserRegisterRequest, UserRegisterResponse, UserSession,
 };
+use teaclave_proto::teaclave_common::UserCredential;
 use teaclave_rpc::Request;
 use teaclave_service_enclave_utils::{bail, ensure, teaclave_service};
-use teaclave_types::{TeaclaveServiceResponseError, TeaclaveServiceResponseResult};
+use teaclave_types::{TeaclaveServiceResponseError, TeaclaveServiceResponseResult, UserRole};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -37,6 +40,10 @@ enum TeaclaveAuthenticationApiError {
     InvalidUserId,
     #[error("invalid password")]
     InvalidPassword,
+    #[error("invalid session id")]
+    InvalidSessionId,
+    #[error("invalid user record")]
+    InvalidUserRecord,
     #[error("service unavailable")]
     ServiceUnavailable,
 }
@@ -56,15 +63,61 @@ impl From<TeaclaveAuthenticationApiError> for TeaclaveServiceResponseError {
 pub(crate) struct TeaclaveAuthenticationApiService {
     db_client: DbClient,
     jwt_secret: Vec<u8>,
+    clock_skew_tolerance: u64,
+    idempotent_registration: bool,
 }
 
 impl TeaclaveAuthenticationApiService {
-    pub(crate) fn new(db_client: DbClient, jwt_secret: Vec<u8>) -> Self {
+    pub(crate) fn new(
+        db_client: DbClient,
+        jwt_secret: Vec<u8>,
+        clock_skew_tolerance: u64,
+        idempotent_registration: bool,
+    ) -> Self {
         Self {
             db_client,
             jwt_secret,
+            clock_skew_tolerance,
+            idempotent_registration,
         }
     }
+
+    fn authenticate(
+        &self,
+        credential: &UserCredential,
+    ) -> Result<UserInfo, TeaclaveAuthenticationApiError> {
+        let user = self
+            .db_client
+            .get_user(&credential.id)
+            .map_err(|_| TeaclaveAuthenticationApiError::PermissionDenied)?;
+        ensure!(
+            user.validate_token(
+                &self.jwt_secret,
+                &credential.token,
+                self.clock_skew_tolerance
+            ),
+            TeaclaveAuthenticationApiError::PermissionDenied
+        );
+        Ok(user)
+    }
+
+    fn authenticate_admin(
+        &self,
+        credential: &UserCredential,
+    ) -> Result<UserInfo, TeaclaveAuthenticationApiError> {
+        let user = self.authenticate(credential)?;
+        ensure!(
+            user.role == UserRole::Admin,
+            TeaclaveAuthenticationApiError::PermissionDenied
+        );
+        Ok(user)
+    }
+}
+
+fn current_time() -> Result<Duration, TeaclaveAuthenticationApiError> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)
 }
 
 impl TeaclaveAuthenticationApi for TeaclaveAuthenticationApiService {
@@ -77,7 +130,11 @@ impl TeaclaveAuthenticationApi for TeaclaveAuthenticationApiService {
             !request.id.is_empty(),
             TeaclaveAuthenticationApiError::InvalidUserId
         );
-        if self.db_client.get_user(&request.id).is_ok() {
+        if let Ok(user) = self.db_client.get_user(&request.id) {
+            // A retried registration succeeds if allowed by the config
+            if self.idempotent_registration && user.verify_password(&request.password) {
+                return Ok(UserRegisterResponse {});
+            }
             bail!(TeaclaveAuthenticationApiError::InvalidUserId);
         }
         let new_user = UserInfo::new(&request.id, &request.password);
@@ -108,16 +165,106 @@ impl TeaclaveAuthenticationApi for TeaclaveAuthenticationApiService {
         if !user.verify_password(&request.password) {
             bail!(TeaclaveAuthenticationApiError::PermissionDenied)
         } else {
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+            let now = current_time()?;
             let exp = (now + Duration::from_secs(24 * 60)).as_secs();
-            match user.get_token(exp, &self.jwt_secret) {
+            let session = Session::new(now.as_secs(), exp);
+            self.db_client
+                .add_session(&user.id, &session, self.clock_skew_tolerance)
+                .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?;
+            match user.get_token(&session, &self.jwt_secret) {
                 Ok(token) => Ok(UserLoginResponse { token }),
                 Err(_) => Err(TeaclaveAuthenticationApiError::ServiceUnavailable.into()),
             }
         }
     }
+
+    fn list_sessions(
+        &self,
+        request: Request<ListSessionsRequest>,
+    ) -> TeaclaveServiceResponseResult<ListSessionsResponse> {
+        let request = request.message;
+        let user = self.authenticate(&request.credential)?;
+        let now = current_time()?.as_secs();
+        let sessions = user
+            .sessions
+            .into_iter()
+            .filter(|s| s.expires_at + self.clock_skew_tolerance > now)
+            .map(|s| UserSession {
+                session_id: s.id,
+                issued_at: s.issued_at,
+                expires_at: s.expires_at,
+            })
+            .collect();
+        Ok(ListSessionsResponse::new(sessions))
+    }
+
+    fn revoke_session(
+        &self,
+        request: Request<RevokeSessionRequest>,
+    ) -> TeaclaveServiceResponseResult<RevokeSessionResponse> {
+        let request = request.message;
+        let user = self.authenticate(&request.credential)?;
+        match self.db_client.revoke_session(&user.id, &request.session_id) {
+            Ok(()) => Ok(RevokeSessionResponse {}),
+            Err(DbError::SessionNotExist) => {
+                Err(TeaclaveAuthenticationApiError::InvalidSessionId.into())
+            }
+            Err(_) => Err(TeaclaveAuthenticationApiError::ServiceUnavailable.into()),
+        }
+    }
+
+    // Sessions are not exported, users log in again after the migration.
+    fn export_users(
+        &self,
+        request: Request<ExportUsersRequest>,
+    ) -> TeaclaveServiceResponseResult<ExportUsersResponse> {
+        let request = request.message;
+        self.authenticate_admin(&request.credential)?;
+        let users = self
+            .db_client
+            .list_users()
+            .map_err(|_| TeaclaveAuthenticationApiError::ServiceUnavailable)?
+            .into_iter()
+            .map(|user| UserRecord {
+                id: user.id,
+                salt: user.salt,
+                password_hash: user.salted_password_hash,
+                role: user.role,
+            })
+            .collect();
+        Ok(ExportUsersResponse::new(users))
+    }
+
+    // All records are validated before any of them is imported.
+    fn import_users(
+        &self,
+        request: Request<ImportUsersRequest>,
+    ) -> TeaclaveServiceResponseResult<ImportUsersResponse> {
+        let request = request.message;
+        self.authenticate_admin(&request.credential)?;
+        let users = request
+            .users
+            .into_iter()
+            .map(|record| {
+                UserInfo::with_password_hash(
+                    &record.id,
+                    record.salt,
+                    record.password_hash,
+                    record.role,
+                )
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|_| TeaclaveAuthenticationApiError::InvalidUserRecord)?;
+        let mut skipped = Vec::new();
+        for user in users {
+            match self.db_client.create_user(&user) {
+                Ok(_) => (),
+                Err(DbError::UserExist) => skipped.push(user.id),
+                Err(_) => bail!(TeaclaveAuthenticationApiError::ServiceUnavailable),
+            }
+        }
+        Ok(ImportUsersResponse::new(skipped))
+    }
 }
 
 #[cfg(feature = "enclave_unit_test")]
@@ -137,6 +284,8 @@ pub mod tests {
         TeaclaveAuthenticationApiService {
             db_client: database.get_client(),
             jwt_secret,
+            clock_skew_tolerance: 0,
+            idempotent_registration: false,
         }
     }
 
@@ -146,6 +295,22 @@ pub mod tests {
         assert!(service.user_register(request).is_ok());
     }
 
+    pub fn test_duplicate_user_register() {
+        let mut service = get_mock_service();
+        let register = |service: &TeaclaveAuthenticationApiService, password: &str| {
+            let request = UserRegisterRequest::new("test_duplicate_id", password).into_request();
+            service.user_register(request)
+        };
+        assert!(register(&service, "test_password").is_ok());
+        assert!(register(&service, "test_password").is_err());
+
+        service.idempotent_registration = true;
+        assert!(register(&service, "test_password").is_ok());
+        assert!(register(&service, "other_password").is_err());
+        let user = service.db_client.get_user("test_duplicate_id").unwrap();
+        assert!(user.verify_password("test_password"));
+    }
+
     pub fn test_user_login() {
         let service = get_mock_service();
         let request = UserRegisterRequest::new("test_login_id", "test_password").into_request();
@@ -155,10 +320,87 @@ pub mod tests {
         assert!(response.is_ok());
         let token = response.unwrap().token;
         let user = service.db_client.get_user("test_login_id").unwrap();
-        assert!(user.validate_token(&service.jwt_secret, &token));
+        assert!(user.validate_token(&service.jwt_secret, &token, 0));
 
         info!("saved user_info: {:?}", user);
         let request = UserLoginRequest::new("test_login_id", "test_password1").into_request();
         assert!(service.user_login(request).is_err());
     }
+
+    pub fn test_list_and_revoke_sessions() {
+        let id = "test_session_id";
+        let service = get_mock_service();
+        let request = UserRegisterRequest::new(id, "test_password").into_request();
+        assert!(service.user_register(request).is_ok());
+        let login = || {
+            let request = UserLoginRequest::new(id, "test_password").into_request();
+            service.user_login(request).unwrap().token
+        };
+        let token1 = login();
+        let token2 = login();
+
+        let credential = UserCredential::new(id, &token1);
+        let request = ListSessionsRequest::new(credential).into_request();
+        let sessions = service.list_sessions(request).unwrap().sessions;
+        assert_eq!(sessions.len(), 2);
+
+        let credential = UserCredential::new(id, &token1);
+        let request = RevokeSessionRequest::new(credential, &sessions[1].session_id).into_request();
+        assert!(service.revoke_session(request).is_ok());
+        let credential = UserCredential::new(id, &token1);
+        let request = RevokeSessionRequest::new(credential, "unknown").into_request();
+        assert!(service.revoke_session(request).is_err());
+
+        let user = service.db_client.get_user(id).unwrap();
+        assert!(user.validate_token(&service.jwt_secret, &token1, 0));
+        assert!(!user.validate_token(&service.jwt_secret, &token2, 0));
+    }
+
+    pub fn test_export_and_import_users() {
+        let admin_login = |service: &TeaclaveAuthenticationApiService| {
+            service
+                .db_client
+                .bootstrap_admin("test_admin_id", "setup_token")
+                .unwrap();
+            let request = UserLoginRequest::new("test_admin_id", "setup_token").into_request();
+            let token = service.user_login(request).unwrap().token;
+            UserCredential::new("test_admin_id", token)
+        };
+
+        let service = get_mock_service();
+        let request = UserRegisterRequest::new("test_export_id", "test_password").into_request();
+        assert!(service.user_register(request).is_ok());
+        let credential = admin_login(&service);
+
+        let request = UserLoginRequest::new("test_export_id", "test_password").into_request();
+        let token = service.user_login(request).unwrap().token;
+        let request =
+            ExportUsersRequest::new(UserCredential::new("test_export_id", token)).into_request();
+        assert!(service.export_users(request).is_err());
+
+        let request = ExportUsersRequest::new(credential).into_request();
+        let users = service.export_users(request).unwrap().users;
+        assert_eq!(users.len(), 2);
+
+        let new_service = get_mock_service();
+        let new_credential = admin_login(&new_service);
+        let mut invalid = users.clone();
+        invalid[0].password_hash.truncate(8);
+        let request = ImportUsersRequest::new(
+            UserCredential::new(&new_credential.id, &new_credential.token),
+            invalid,
+        )
+        .into_request();
+        assert!(new_service.import_users(request).is_err());
+        assert!(new_service.db_client.get_user("test_export_id").is_err());
+
+        let request = ImportUsersRequest::new(new_credential, users).into_request();
+        let skipped = new_service.import_users(request).unwrap().skipped;
+        assert_eq!(skipped, vec!["test_admin_id".to_string()]);
+
+        let request = UserLoginRequest::new("test_export_id", "test_password").into_request();
+        assert!(new_service.user_login(request).is_ok());
+        let request = UserLoginRequest::new("test_admin_id", "setup_token").into_request();
+        assert!(new_service.user_login(request).is_ok());
+    }
 }
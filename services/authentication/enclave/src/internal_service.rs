@@ -30,13 +30,15 @@ use teaclave_types::TeaclaveServiceResponseResult;
 pub(crate) struct TeaclaveAuthenticationInternalService {
     db_client: DbClient,
     jwt_secret: Vec<u8>,
+    clock_skew_tolerance: u64,
 }
 
 impl TeaclaveAuthenticationInternalService {
-    pub(crate) fn new(db_client: DbClient, jwt_secret: Vec<u8>) -> Self {
+    pub(crate) fn new(db_client: DbClient, jwt_secret: Vec<u8>, clock_skew_tolerance: u64) -> Self {
         Self {
             db_client,
             jwt_secret,
+            clock_skew_tolerance,
         }
     }
 }
@@ -54,8 +56,15 @@ impl TeaclaveAuthenticationInternal for TeaclaveAuthenticationInternalService {
             Ok(value) => value,
             Err(_) => return Ok(UserAuthenticateResponse::new(false)),
         };
-        let accept = user.validate_token(&self.jwt_secret, &request.credential.token);
-        Ok(UserAuthenticateResponse::new(accept))
+        let accept = user.validate_token(
+            &self.jwt_secret,
+            &request.credential.token,
+            self.clock_skew_tolerance,
+        );
+        if !accept {
+            return Ok(UserAuthenticateResponse::new(false));
+        }
+        Ok(UserAuthenticateResponse::new(true).role(user.role))
     }
 }
 
@@ -70,6 +79,7 @@ pub mod tests {
     use std::vec;
     use teaclave_proto::teaclave_common::UserCredential;
     use teaclave_rpc::IntoRequest;
+    use teaclave_types::UserRole;
 
     fn get_mock_service() -> TeaclaveAuthenticationInternalService {
         let database = Database::open().unwrap();
@@ -81,6 +91,7 @@ pub mod tests {
         TeaclaveAuthenticationInternalService {
             db_client: database.get_client(),
             jwt_secret,
+            clock_skew_tolerance: 0,
         }
     }
 
@@ -91,14 +102,36 @@ pub mod tests {
 
         let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
         let exp = (now + Duration::from_secs(24 * 60)).as_secs();
-        let token = user.get_token(exp, &service.jwt_secret).unwrap();
+        let session = Session::new(now.as_secs(), exp);
+        service.db_client.add_session(id, &session, 0).unwrap();
+        let token = user.get_token(&session, &service.jwt_secret).unwrap();
 
         let response = get_authenticate_response(id, &token, &service);
         assert!(response.accept);
+        assert_eq!(response.role, UserRole::User);
         let token = validate_token(id, &service.jwt_secret, &token);
         info!("valid token: {:?}", token.unwrap());
     }
 
+    pub fn test_revoked_session() {
+        let id = "test_authenticate_id";
+        let service = get_mock_service();
+        let user = service.db_client.get_user(id).unwrap();
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let exp = (now + Duration::from_secs(24 * 60)).as_secs();
+        let session = Session::new(now.as_secs(), exp);
+        let token = user.get_token(&session, &service.jwt_secret).unwrap();
+        service.db_client.add_session(id, &session, 0).unwrap();
+        assert!(get_authenticate_response(id, &token, &service).accept);
+
+        service.db_client.revoke_session(id, &session.id).unwrap();
+        let response = get_authenticate_response(id, &token, &service);
+        assert!(!response.accept);
+        // The token itself is still well-formed and unexpired.
+        assert!(validate_token(id, &service.jwt_secret, &token).is_ok());
+    }
+
     pub fn test_invalid_algorithm() {
         let id = "test_authenticate_id";
         let service = get_mock_service();
@@ -150,6 +183,32 @@ pub mod tests {
         }
     }
 
+    pub fn test_clock_skew_tolerance() {
+        let id = "test_authenticate_id";
+        let mut service = get_mock_service();
+        service.clock_skew_tolerance = 60;
+        let user = service.db_client.get_user(id).unwrap();
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let session1 = Session::new(now - 1000, now - 30);
+        let session2 = Session::new(now - 1000, now - 120);
+        service.db_client.add_session(id, &session1, 0).unwrap();
+        service.db_client.add_session(id, &session2, 0).unwrap();
+
+        // Just past expiry but within the tolerance
+        let token = user.get_token(&session1, &service.jwt_secret).unwrap();
+        let response = get_authenticate_response(id, &token, &service);
+        assert!(response.accept);
+
+        // Beyond the tolerance
+        let token = user.get_token(&session2, &service.jwt_secret).unwrap();
+        let response = get_authenticate_response(id, &token, &service);
+        assert!(!response.accept);
+    }
+
     pub fn test_invalid_user() {
         let id = "test_authenticate_id";
         let service = get_mock_service();
@@ -190,6 +249,7 @@ pub mod tests {
             sub: id.to_string(),
             iss: ISSUER_NAME.to_string(),
             exp: now + 24 * 60,
+            jti: String::new(),
         }
     }
 
@@ -28,8 +28,9 @@ use rand::RngCore;
 use std::prelude::v1::*;
 use std::sync::{Arc, SgxRwLock as RwLock};
 use std::thread;
+use std::time::Duration;
 
-use teaclave_attestation::{verifier, AttestationConfig, AttestedTlsConfig, RemoteAttestation};
+use teaclave_attestation::{verifier, AttestedTlsConfig, RemoteAttestation};
 use teaclave_binder::proto::{
     ECallCommand, FinalizeEnclaveInput, FinalizeEnclaveOutput, InitEnclaveInput, InitEnclaveOutput,
     StartServiceInput, StartServiceOutput,
@@ -53,27 +54,35 @@ mod internal_service;
 mod user_db;
 mod user_info;
 
+#[allow(clippy::too_many_arguments)]
 fn start_internal_endpoint(
     addr: std::net::SocketAddr,
     db_client: user_db::DbClient,
     jwt_secret: Vec<u8>,
+    clock_skew_tolerance: u64,
     attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
     accepted_enclave_attrs: Vec<teaclave_types::EnclaveAttr>,
+    root_ca_certs: Vec<u8>,
+    shutdown_timeout: Duration,
 ) -> Result<()> {
     let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
         .attestation_report_verifier(
         accepted_enclave_attrs,
-        AS_ROOT_CA_CERT,
+        &root_ca_certs,
         verifier::universal_quote_verifier,
     )?;
 
     let mut server = SgxTrustedTlsServer::<
         TeaclaveAuthenticationInternalResponse,
         TeaclaveAuthenticationInternalRequest,
-    >::new(addr, server_config);
+    >::new(addr, server_config)
+    .shutdown_timeout(shutdown_timeout);
 
-    let service =
-        internal_service::TeaclaveAuthenticationInternalService::new(db_client, jwt_secret);
+    let service = internal_service::TeaclaveAuthenticationInternalService::new(
+        db_client,
+        jwt_secret,
+        clock_skew_tolerance,
+    );
 
     match server.start(service) {
         Ok(_) => Ok(()),
@@ -88,16 +97,25 @@ fn start_api_endpoint(
     addr: std::net::SocketAddr,
     db_client: user_db::DbClient,
     jwt_secret: Vec<u8>,
+    clock_skew_tolerance: u64,
+    idempotent_registration: bool,
     attested_tls_config: Arc<RwLock<AttestedTlsConfig>>,
+    shutdown_timeout: Duration,
 ) -> Result<()> {
     let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?;
 
     let mut server = SgxTrustedTlsServer::<
         TeaclaveAuthenticationApiResponse,
         TeaclaveAuthenticationApiRequest,
-    >::new(addr, server_config);
+    >::new(addr, server_config)
+    .shutdown_timeout(shutdown_timeout);
 
-    let service = api_service::TeaclaveAuthenticationApiService::new(db_client, jwt_secret);
+    let service = api_service::TeaclaveAuthenticationApiService::new(
+        db_client,
+        jwt_secret,
+        clock_skew_tolerance,
+        idempotent_registration,
+    );
 
     match server.start(service) {
         Ok(_) => Ok(()),
@@ -111,7 +129,7 @@ fn start_api_endpoint(
 fn start_service(config: &RuntimeConfig) -> Result<()> {
     let enclave_info = EnclaveInfo::verify_and_new(
         &config.audit.enclave_info_bytes,
-        AUDITOR_PUBLIC_KEYS,
+        &config.audit.auditor_public_keys(AUDITOR_PUBLIC_KEYS),
         &config.audit.auditor_signatures_bytes,
     )?;
     let accepted_enclave_attrs: Vec<teaclave_types::EnclaveAttr> = AUTHENTICATION_INBOUND_SERVICES
@@ -123,16 +141,27 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
         .collect::<Result<_>>()?;
     let api_listen_address = config.api_endpoints.authentication.listen_address;
     let internal_listen_address = config.internal_endpoints.authentication.listen_address;
-    let attestation_config = AttestationConfig::from_teaclave_config(&config)?;
-    let attested_tls_config = RemoteAttestation::new(attestation_config)
+    let attested_tls_config = RemoteAttestation::from_teaclave_config(&config)?
         .generate_and_endorse()?
         .attested_tls_config()
         .ok_or_else(|| anyhow!("cannot get attested TLS config"))?;
     let database = user_db::Database::open()?;
+    if let (Some(id), Some(token)) = (
+        &config.authentication.bootstrap_admin_id,
+        &config.authentication.bootstrap_admin_token,
+    ) {
+        if database.get_client().bootstrap_admin(id, token)? {
+            info!("Bootstrapped admin user {}", id);
+        }
+    }
     let mut api_jwt_secret = vec![0; user_info::JWT_SECRET_LEN];
     let mut rng = rand::thread_rng();
     rng.fill_bytes(&mut api_jwt_secret);
     let internal_jwt_secret = api_jwt_secret.to_owned();
+    let shutdown_timeout = Duration::from_secs(config.server.shutdown_timeout_seconds);
+    let clock_skew_tolerance = config.server.clock_skew_tolerance_seconds;
+    let root_ca_certs = config.attestation.root_ca_certs(AS_ROOT_CA_CERT);
+    let idempotent_registration = config.authentication.idempotent_registration;
 
     let attested_tls_config_ref = attested_tls_config.clone();
     let client = database.get_client();
@@ -141,7 +170,10 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
             api_listen_address,
             client,
             api_jwt_secret,
+            clock_skew_tolerance,
+            idempotent_registration,
             attested_tls_config_ref,
+            shutdown_timeout,
         );
     });
 
@@ -151,8 +183,11 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
             internal_listen_address,
             client,
             internal_jwt_secret,
+            clock_skew_tolerance,
             attested_tls_config,
             accepted_enclave_attrs,
+            root_ca_certs,
+            shutdown_timeout,
         );
     });
 
@@ -205,12 +240,19 @@ pub mod tests {
         run_tests!(
             api_service::tests::test_user_login,
             api_service::tests::test_user_register,
+            api_service::tests::test_duplicate_user_register,
+            api_service::tests::test_list_and_revoke_sessions,
+            api_service::tests::test_export_and_import_users,
             internal_service::tests::test_user_authenticate,
+            internal_service::tests::test_revoked_session,
             internal_service::tests::test_invalid_algorithm,
             internal_service::tests::test_invalid_issuer,
             internal_service::tests::test_expired_token,
+            internal_service::tests::test_clock_skew_tolerance,
             internal_service::tests::test_invalid_user,
             internal_service::tests::test_wrong_secret,
+            user_db::tests::test_bootstrap_admin,
+            user_db::tests::test_concurrent_sessions,
         )
     }
 }
@@ -15,10 +15,12 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::user_info::UserInfo;
+use crate::user_info::{Session, UserInfo};
+use rusty_leveldb::LdbIterator;
 use std::prelude::v1::*;
 use std::sync::mpsc::{channel, Sender};
 use std::thread;
+use teaclave_types::UserRole;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -35,6 +37,8 @@ pub(crate) enum DbError {
     InvalidResponse,
     #[error("invalid request")]
     InvalidRequest,
+    #[error("session not exist")]
+    SessionNotExist,
 }
 
 impl<T> From<std::sync::mpsc::SendError<T>> for DbError {
@@ -64,10 +68,31 @@ struct CreateRequest {
     value: Vec<u8>,
 }
 
+#[derive(Clone)]
+struct AddSessionRequest {
+    key: Vec<u8>,
+    session: Session,
+    leeway: u64,
+}
+
+#[derive(Clone)]
+struct RevokeSessionRequest {
+    key: Vec<u8>,
+    session_id: String,
+}
+
+#[derive(Clone)]
+struct ListResponse {
+    values: Vec<Vec<u8>>,
+}
+
 #[derive(Clone)]
 enum DbRequest {
     Get(GetRequest),
     Create(CreateRequest),
+    AddSession(AddSessionRequest),
+    RevokeSession(RevokeSessionRequest),
+    List,
     Ping,
 }
 
@@ -75,6 +100,9 @@ enum DbRequest {
 enum DbResponse {
     Get(GetResponse),
     Create,
+    AddSession,
+    RevokeSession,
+    List(ListResponse),
     Ping,
 }
 
@@ -88,6 +116,23 @@ pub(crate) struct Database {
     sender: Sender<DBCall>,
 }
 
+// Reads, modifies and writes back a user in the database thread, so that the
+// change is not lost to concurrent modifications of the same user.
+fn modify_user(
+    database: &mut rusty_leveldb::DB,
+    key: &[u8],
+    modify: impl FnOnce(&mut UserInfo) -> Result<(), DbError>,
+) -> Result<(), DbError> {
+    let value = database.get(key).ok_or(DbError::UserNotExist)?;
+    let mut user: UserInfo =
+        serde_json::from_slice(&value).map_err(|_| DbError::InvalidResponse)?;
+    modify(&mut user)?;
+    let value = serde_json::to_vec(&user).map_err(|_| DbError::InvalidRequest)?;
+    database
+        .put(key, &value)
+        .map_err(|_| DbError::LevelDbInternalError)
+}
+
 impl Database {
     pub(crate) fn open() -> Result<Self, DbError> {
         let (sender, receiver) = channel();
@@ -115,6 +160,33 @@ impl Database {
                             Err(_) => Err(DbError::LevelDbInternalError),
                         },
                     },
+                    DbRequest::AddSession(request) => {
+                        modify_user(&mut database, &request.key, |user| {
+                            user.add_session(request.session, request.leeway);
+                            Ok(())
+                        })
+                        .map(|_| DbResponse::AddSession)
+                    }
+                    DbRequest::RevokeSession(request) => {
+                        modify_user(&mut database, &request.key, |user| {
+                            if user.revoke_session(&request.session_id) {
+                                Ok(())
+                            } else {
+                                Err(DbError::SessionNotExist)
+                            }
+                        })
+                        .map(|_| DbResponse::RevokeSession)
+                    }
+                    DbRequest::List => match database.new_iter() {
+                        Ok(mut iter) => {
+                            let mut values = Vec::new();
+                            while let Some((_, value)) = iter.next() {
+                                values.push(value);
+                            }
+                            Ok(DbResponse::List(ListResponse { values }))
+                        }
+                        Err(_) => Err(DbError::LevelDbInternalError),
+                    },
                     DbRequest::Ping => Ok(DbResponse::Ping),
                 };
                 match sender.send(response) {
@@ -182,6 +254,75 @@ impl DbClient {
         }
     }
 
+    // Creates the admin user with the setup token as the password unless the
+    // user exists. Returns whether the user is created.
+    pub(crate) fn bootstrap_admin(&self, id: &str, token: &str) -> Result<bool, DbError> {
+        let mut user = UserInfo::new(id, token);
+        user.role = UserRole::Admin;
+        match self.create_user(&user) {
+            Ok(()) => Ok(true),
+            Err(DbError::UserExist) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Sessions are added and revoked by the database thread, so that
+    // concurrent logins and revocations of a user don't overwrite each other.
+    pub(crate) fn add_session(
+        &self,
+        id: &str,
+        session: &Session,
+        leeway: u64,
+    ) -> Result<(), DbError> {
+        let (sender, receiver) = channel();
+        let request = DbRequest::AddSession(AddSessionRequest {
+            key: id.as_bytes().to_vec(),
+            session: session.clone(),
+            leeway,
+        });
+        let call = DBCall { sender, request };
+        self.sender.send(call)?;
+        let result = receiver.recv()?;
+        let db_response = result?;
+        match db_response {
+            DbResponse::AddSession => Ok(()),
+            _ => Err(DbError::InvalidResponse),
+        }
+    }
+
+    pub(crate) fn revoke_session(&self, id: &str, session_id: &str) -> Result<(), DbError> {
+        let (sender, receiver) = channel();
+        let request = DbRequest::RevokeSession(RevokeSessionRequest {
+            key: id.as_bytes().to_vec(),
+            session_id: session_id.to_string(),
+        });
+        let call = DBCall { sender, request };
+        self.sender.send(call)?;
+        let result = receiver.recv()?;
+        let db_response = result?;
+        match db_response {
+            DbResponse::RevokeSession => Ok(()),
+            _ => Err(DbError::InvalidResponse),
+        }
+    }
+
+    pub(crate) fn list_users(&self) -> Result<Vec<UserInfo>, DbError> {
+        let (sender, receiver) = channel();
+        let request = DbRequest::List;
+        let call = DBCall { sender, request };
+        self.sender.send(call)?;
+        let result = receiver.recv()?;
+        let db_response = result?;
+        match db_response {
+            DbResponse::List(response) => response
+                .values
+                .iter()
+                .map(|value| serde_json::from_slice(value).map_err(|_| DbError::InvalidResponse))
+                .collect(),
+            _ => Err(DbError::InvalidResponse),
+        }
+    }
+
     // Check whether the database is opened successfully.
     fn ping(&self) -> Result<(), DbError> {
         let (sender, receiver) = channel();
@@ -196,3 +337,71 @@ impl DbClient {
         }
     }
 }
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_bootstrap_admin() {
+        let database = Database::open().unwrap();
+        let client = database.get_client();
+        assert!(client
+            .bootstrap_admin("test_admin_id", "setup_token")
+            .unwrap());
+        let admin = client.get_user("test_admin_id").unwrap();
+        assert_eq!(admin.role, UserRole::Admin);
+        assert!(admin.verify_password("setup_token"));
+
+        // Later attempts leave the existing admin untouched.
+        assert!(!client
+            .bootstrap_admin("test_admin_id", "other_token")
+            .unwrap());
+        let user = client.get_user("test_admin_id").unwrap();
+        assert_eq!(user.salt, admin.salt);
+        assert!(user.verify_password("setup_token"));
+        assert!(!user.verify_password("other_token"));
+    }
+
+    pub fn test_concurrent_sessions() {
+        let database = Database::open().unwrap();
+        let client = database.get_client();
+        let user = UserInfo::new("test_session_id", "test_password");
+        client.create_user(&user).unwrap();
+
+        // Sessions added at the same time are all kept.
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let client = client.clone();
+                thread::spawn(move || {
+                    let session = Session::new(1000, 2000);
+                    client.add_session("test_session_id", &session, 0).unwrap();
+                    session
+                })
+            })
+            .collect();
+        let sessions: Vec<Session> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        let user = client.get_user("test_session_id").unwrap();
+        assert_eq!(user.sessions.len(), 8);
+
+        client
+            .revoke_session("test_session_id", &sessions[0].id)
+            .unwrap();
+        assert!(client
+            .revoke_session("test_session_id", &sessions[0].id)
+            .is_err());
+        let user = client.get_user("test_session_id").unwrap();
+        assert_eq!(user.sessions.len(), 7);
+
+        // Expired sessions still accepted within the leeway are kept.
+        client
+            .add_session("test_session_id", &Session::new(2030, 3000), 60)
+            .unwrap();
+        let user = client.get_user("test_session_id").unwrap();
+        assert_eq!(user.sessions.len(), 8);
+        client
+            .add_session("test_session_id", &Session::new(2100, 3000), 60)
+            .unwrap();
+        let user = client.get_user("test_session_id").unwrap();
+        assert_eq!(user.sessions.len(), 2);
+    }
+}
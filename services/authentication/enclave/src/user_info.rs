@@ -15,7 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use jsonwebtoken as jwt;
 use rand::prelude::RngCore;
 use ring::{digest, pbkdf2};
@@ -23,8 +23,10 @@ use serde::{Deserialize, Serialize};
 use std::num;
 use std::prelude::v1::*;
 use std::vec;
+use teaclave_types::UserRole;
 
 const SALT_LEN: usize = 16;
+const SESSION_ID_LEN: usize = 16;
 const PASSWORD_DIGEST_LEN: usize = digest::SHA512_OUTPUT_LEN;
 const PBKDF2_ITERATIONS: u32 = 100_000;
 static PBKDF2_ALG: pbkdf2::Algorithm = pbkdf2::PBKDF2_HMAC_SHA512;
@@ -38,6 +40,30 @@ pub(crate) struct UserInfo {
     pub id: String,
     pub salt: Vec<u8>,
     pub salted_password_hash: Vec<u8>,
+    #[serde(default)]
+    pub role: UserRole,
+    #[serde(default)]
+    pub sessions: Vec<Session>,
+}
+
+// A token issued to the user, identified by the jti claim of the token.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub(crate) struct Session {
+    pub id: String,
+    pub issued_at: u64,
+    pub expires_at: u64,
+}
+
+impl Session {
+    pub(crate) fn new(issued_at: u64, expires_at: u64) -> Self {
+        let mut id = vec![0u8; SESSION_ID_LEN];
+        rand::thread_rng().fill_bytes(&mut id);
+        Self {
+            id: id.iter().map(|b| format!("{:02x}", b)).collect(),
+            issued_at,
+            expires_at,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -48,6 +74,9 @@ pub(crate) struct Claims {
     pub iss: String,
     // expiration time
     pub exp: u64,
+    // token id, which is the id of the session
+    #[serde(default)]
+    pub jti: String,
 }
 
 impl UserInfo {
@@ -68,9 +97,39 @@ impl UserInfo {
             id: id.to_string(),
             salt,
             salted_password_hash,
+            role: UserRole::default(),
+            sessions: Vec::new(),
         }
     }
 
+    // Restores a user from an exported password hash, which is checked to be
+    // produced by the same derivation so that the password keeps working.
+    pub(crate) fn with_password_hash(
+        id: &str,
+        salt: Vec<u8>,
+        salted_password_hash: Vec<u8>,
+        role: UserRole,
+    ) -> Result<Self> {
+        ensure!(!id.is_empty(), "Invalid user id");
+        ensure!(
+            salt.len() == SALT_LEN,
+            "Invalid salt length: {}",
+            salt.len()
+        );
+        ensure!(
+            salted_password_hash.len() == PASSWORD_DIGEST_LEN,
+            "Invalid password hash length: {}",
+            salted_password_hash.len()
+        );
+        Ok(Self {
+            id: id.to_string(),
+            salt,
+            salted_password_hash,
+            role,
+            sessions: Vec::new(),
+        })
+    }
+
     pub(crate) fn verify_password(&self, password: &str) -> bool {
         let pbkdf2_iterations = num::NonZeroU32::new(PBKDF2_ITERATIONS).unwrap();
         pbkdf2::verify(
@@ -83,12 +142,27 @@ impl UserInfo {
         .is_ok()
     }
 
-    pub(crate) fn get_token(&self, exp: u64, secret: &[u8]) -> Result<String> {
+    // Sessions whose tokens are rejected at the issue time of the new one, even
+    // within the leeway of validate_token, are dropped.
+    pub(crate) fn add_session(&mut self, session: Session, leeway: u64) {
+        self.sessions
+            .retain(|s| s.expires_at + leeway > session.issued_at);
+        self.sessions.push(session);
+    }
+
+    pub(crate) fn revoke_session(&mut self, id: &str) -> bool {
+        let count = self.sessions.len();
+        self.sessions.retain(|s| s.id != id);
+        self.sessions.len() != count
+    }
+
+    pub(crate) fn get_token(&self, session: &Session, secret: &[u8]) -> Result<String> {
         let iss = ISSUER_NAME.to_string();
         let claims = Claims {
             sub: self.id.to_string(),
             iss,
-            exp,
+            exp: session.expires_at,
+            jti: session.id.to_string(),
         };
         let mut header = jwt::Header::default();
         header.alg = JWT_ALG;
@@ -96,11 +170,18 @@ impl UserInfo {
         Ok(token)
     }
 
-    pub(crate) fn validate_token(&self, secret: &[u8], token: &str) -> bool {
+    // Tokens expired by no more than leeway seconds are still accepted to
+    // tolerate clock skew.
+    pub(crate) fn validate_token(&self, secret: &[u8], token: &str, leeway: u64) -> bool {
         let iss = ISSUER_NAME.to_string();
         let mut validation = jwt::Validation::new(JWT_ALG);
         validation.iss = Some(iss);
+        validation.leeway = leeway as i64;
         validation.sub = Some(self.id.to_string());
-        jwt::decode::<Claims>(token, secret, &validation).is_ok()
+        // Tokens of revoked sessions are rejected even before they expire.
+        match jwt::decode::<Claims>(token, secret, &validation) {
+            Ok(data) => self.sessions.iter().any(|s| s.id == data.claims.jti),
+            Err(_) => false,
+        }
     }
 }
@@ -33,6 +33,7 @@ fn main() -> Result<()> {
         PACKAGE_NAME,
         "runtime.config.toml",
     )?);
+    teaclave_file_agent::set_credentials(launcher.config().file_agent.credentials.clone());
     let launcher_ref = launcher.clone();
     thread::spawn(move || {
         let _ = launcher_ref.start();
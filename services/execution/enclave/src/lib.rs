@@ -43,16 +43,17 @@ mod service;
 mod task_file_manager;
 
 fn start_service(config: &RuntimeConfig) -> Result<()> {
+    let root_ca_certs = config.attestation.root_ca_certs(AS_ROOT_CA_CERT);
     let enclave_info = EnclaveInfo::verify_and_new(
         &config.audit.enclave_info_bytes,
-        AUDITOR_PUBLIC_KEYS,
+        &config.audit.auditor_public_keys(AUDITOR_PUBLIC_KEYS),
         &config.audit.auditor_signatures_bytes,
     )?;
     let scheduler_service_address = &config.internal_endpoints.scheduler.advertised_address;
     let scheduler_service_endpoint = create_trusted_scheduler_endpoint(
         &scheduler_service_address,
         &enclave_info,
-        AS_ROOT_CA_CERT,
+        &root_ca_certs,
         verifier::universal_quote_verifier,
     );
 
@@ -117,6 +118,8 @@ pub mod tests {
             service::tests::test_invoke_echo,
             service::tests::test_invoke_gbdt_train,
             task_file_manager::tests::test_input,
+            task_file_manager::tests::test_inline_input,
+            task_file_manager::tests::test_sampled_input,
         )
     }
 }
@@ -19,6 +19,8 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::prelude::v1::*;
 use std::sync::{Arc, SgxMutex as Mutex};
+use std::time::SystemTime;
+use std::untrusted::time::SystemTimeEx;
 
 use crate::task_file_manager::TaskFileManager;
 use teaclave_proto::teaclave_scheduler_service::*;
@@ -27,9 +29,31 @@ use teaclave_types::*;
 use teaclave_worker::Worker;
 
 use anyhow::Result;
+use thiserror::Error;
 use uuid::Uuid;
 
 static WORKER_BASE_DIR: &str = "/tmp/teaclave_agent/";
+// Lines logged after the limit are dropped from the log of a task
+const MAX_TASK_LOG_LINES: usize = 256;
+
+#[derive(Error, Debug)]
+#[error("task is canceled")]
+struct TaskCanceled;
+
+// Steps of running a task, which are stored with its result so that the
+// participants can inspect how the task ran.
+#[derive(Default)]
+struct TaskLog {
+    lines: Vec<String>,
+}
+
+impl TaskLog {
+    fn push(&mut self, line: impl Into<String>) {
+        if self.lines.len() < MAX_TASK_LOG_LINES {
+            self.lines.push(line.into());
+        }
+    }
+}
 
 #[derive(Clone)]
 pub(crate) struct TeaclaveExecutionService {
@@ -76,10 +100,25 @@ impl TeaclaveExecutionService {
             };
 
             log::info!("InvokeTask: {:?}", staged_task);
-            let result = self.invoke_task(&staged_task);
+            let mut task_log = TaskLog::default();
+            let result = self.invoke_task(&staged_task, &mut task_log);
             log::info!("InvokeTask result: {:?}", result);
 
-            match self.update_task_result(&staged_task.task_id, result) {
+            if let Err(e) = &result {
+                if e.is::<TaskCanceled>() {
+                    if let Err(e) =
+                        self.update_task_status(&staged_task.task_id, TaskStatus::Canceled)
+                    {
+                        log::error!("UpdateStatus Error: {:?}", e);
+                    }
+                    continue;
+                }
+            }
+
+            if let Err(e) = &result {
+                task_log.push(format!("task failed: {}", e));
+            }
+            match self.update_task_result(&staged_task.task_id, result, task_log.lines) {
                 Ok(_) => (),
                 Err(e) => {
                     log::error!("UpdateResult Error: {:?}", e);
@@ -102,8 +141,9 @@ impl TeaclaveExecutionService {
         Ok(response.staged_task)
     }
 
-    fn invoke_task(&mut self, task: &StagedTask) -> Result<TaskOutputs> {
+    fn invoke_task(&mut self, task: &StagedTask, task_log: &mut TaskLog) -> Result<TaskOutputs> {
         self.update_task_status(&task.task_id, TaskStatus::Running)?;
+        self.check_cancellation(&task.task_id)?;
 
         let file_mgr = TaskFileManager::new(
             WORKER_BASE_DIR,
@@ -113,12 +153,27 @@ impl TeaclaveExecutionService {
             &task.output_data,
         )?;
         let invocation = prepare_task(&task, &file_mgr)?;
+        task_log.push(format!(
+            "prepared {} inputs and {} outputs",
+            task.input_data.len(),
+            task.output_data.len()
+        ));
+        self.check_cancellation(&task.task_id)?;
 
         log::info!("Invoke function: {:?}", invocation);
+        task_log.push(format!(
+            "invoking function {} with the {} executor",
+            task.function_name, task.executor
+        ));
         let worker = Worker::default();
+        let started_at = SystemTime::now();
         let summary = worker.invoke_function(invocation)?;
+        check_runtime_limit(&task.resource_limits, started_at)?;
+        task_log.push(format!("function returned {} bytes", summary.len()));
+        self.check_cancellation(&task.task_id)?;
 
         let outputs_tag = finalize_task(&file_mgr)?;
+        task_log.push(format!("uploaded {} outputs", outputs_tag.len()));
         let task_outputs = TaskOutputs::new(summary.as_bytes(), outputs_tag);
         Ok(task_outputs)
     }
@@ -127,8 +182,9 @@ impl TeaclaveExecutionService {
         &mut self,
         task_id: &Uuid,
         task_result: Result<TaskOutputs>,
+        log: Vec<String>,
     ) -> Result<()> {
-        let request = UpdateTaskResultRequest::new(*task_id, task_result);
+        let request = UpdateTaskResultRequest::new(*task_id, task_result).log(log);
 
         let _response = self
             .scheduler_client
@@ -140,6 +196,24 @@ impl TeaclaveExecutionService {
         Ok(())
     }
 
+    // Cancellation is checked between the steps of running a task, a function
+    // being executed is not interrupted.
+    fn check_cancellation(&mut self, task_id: &Uuid) -> Result<()> {
+        let request = CheckTaskCancellationRequest::new(task_id.to_owned());
+        let response = self
+            .scheduler_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Cannot lock scheduler client"))?
+            .check_task_cancellation(request)?;
+
+        if response.cancel_requested {
+            log::info!("Task {} is canceled", task_id);
+            return Err(TaskCanceled.into());
+        }
+        Ok(())
+    }
+
     fn update_task_status(&mut self, task_id: &Uuid, task_status: TaskStatus) -> Result<()> {
         let request = UpdateTaskStatusRequest::new(task_id.to_owned(), task_status);
         let _response = self
@@ -170,6 +244,25 @@ fn prepare_task(task: &StagedTask, file_mgr: &TaskFileManager) -> Result<StagedF
     Ok(staged_function)
 }
 
+// The runtime is checked after the function returns, a function being
+// executed is not interrupted. Outputs of a task exceeding its limit are not
+// uploaded.
+fn check_runtime_limit(limits: &TaskResourceLimits, started_at: SystemTime) -> Result<()> {
+    if let Some(max_runtime_seconds) = limits.max_runtime_seconds {
+        let elapsed = SystemTime::now()
+            .duration_since(started_at)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        anyhow::ensure!(
+            elapsed <= max_runtime_seconds,
+            "resource limit exceeded: ran for {} seconds, limit is {} seconds",
+            elapsed,
+            max_runtime_seconds
+        );
+    }
+    Ok(())
+}
+
 fn finalize_task(file_mgr: &TaskFileManager) -> Result<HashMap<String, FileAuthTag>> {
     file_mgr.upload_outputs()
 }
@@ -18,6 +18,7 @@
 use crate::ocall::handle_file_request;
 use anyhow::Result;
 use std::collections::HashMap;
+use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
 use std::prelude::v1::*;
@@ -158,8 +159,26 @@ impl InterInput {
                 StagedFileInfo::create_with_bytes(dst, &bytes)?
             }
         };
+        let staged_file_info = match &self.file.sample {
+            Some(sample) => self.stage_sample(&staged_file_info, sample)?,
+            None => staged_file_info,
+        };
         Ok((self.funiq_key.clone(), staged_file_info))
     }
+
+    // The sample is taken from the verified content of the whole input.
+    fn stage_sample(
+        &self,
+        staged_file_info: &StagedFileInfo,
+        sample: &InputSample,
+    ) -> Result<StagedFileInfo> {
+        let mut bytes = Vec::new();
+        staged_file_info
+            .create_readable_io()?
+            .read_to_end(&mut bytes)?;
+        let sample_path = PathBuf::from(format!("{}.sample", self.staged_path.display()));
+        StagedFileInfo::create_with_bytes(sample_path, sample.apply(&bytes))
+    }
 }
 
 impl std::iter::FromIterator<InterInput> for InterInputs {
@@ -179,9 +198,21 @@ impl InterInputs {
     }
 
     pub(crate) fn download(&self, fusion_base: impl AsRef<Path>) -> Result<()> {
-        let req_info = self.inner.iter().map(|inter_input| {
-            HandleFileInfo::new(&inter_input.download_path, &inter_input.file.url)
-        });
+        // Inline inputs are carried in the staged task, so the file agent
+        // only downloads the remote ones.
+        for inter_input in self.inner.iter() {
+            if let Some(data) = &inter_input.file.inline_data {
+                std::untrusted::fs::write(&inter_input.download_path, data)?;
+            }
+        }
+        let req_info = self
+            .inner
+            .iter()
+            .filter(|inter_input| inter_input.file.inline_data.is_none())
+            .map(|inter_input| {
+                HandleFileInfo::new(&inter_input.download_path, &inter_input.file.url)
+                    .expected_size(inter_input.file.expected_size)
+            });
         let request =
             FileAgentRequest::new(HandleFileCommand::Download, req_info, fusion_base.as_ref());
         log::info!("Ocall file download request: {:?}", request);
@@ -352,4 +383,63 @@ pub mod tests {
         file_mgr.prepare_staged_inputs().unwrap();
         file_mgr.prepare_staged_outputs().unwrap();
     }
+
+    pub fn test_inline_input() {
+        let input_url = Url::parse("inline:data").unwrap();
+        let mut input_file =
+            FunctionInputFile::new(input_url, FileAuthTag::default(), FileCrypto::Raw);
+        input_file.inline_data = Some(b"inline data".to_vec());
+        let inputs = hashmap!("input" => input_file);
+        let outputs = hashmap!();
+        let task_id = Uuid::new_v4();
+
+        let file_mgr = TaskFileManager::new(
+            "/tmp",
+            "/tmp/fusion_base",
+            &task_id,
+            &inputs.into(),
+            &outputs.into(),
+        )
+        .unwrap();
+        let staged_inputs = file_mgr.prepare_staged_inputs().unwrap();
+        let mut content = Vec::new();
+        staged_inputs
+            .get("input")
+            .unwrap()
+            .create_readable_io()
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(content, b"inline data");
+    }
+
+    pub fn test_sampled_input() {
+        let input_url = Url::parse("inline:data").unwrap();
+        let mut input_file =
+            FunctionInputFile::new(input_url, FileAuthTag::default(), FileCrypto::Raw);
+        input_file.inline_data = Some(b"inline data".to_vec());
+        input_file.sample = Some(InputSample::FirstBytes(6));
+        let inputs = hashmap!("input" => input_file);
+        let outputs = hashmap!();
+        let task_id = Uuid::new_v4();
+
+        let file_mgr = TaskFileManager::new(
+            "/tmp",
+            "/tmp/fusion_base",
+            &task_id,
+            &inputs.into(),
+            &outputs.into(),
+        )
+        .unwrap();
+        let staged_inputs = file_mgr.prepare_staged_inputs().unwrap();
+        let mut content = Vec::new();
+        staged_inputs
+            .get("input")
+            .unwrap()
+            .create_readable_io()
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(content, b"inline");
+    }
 }
@@ -25,8 +25,9 @@ extern crate log;
 use anyhow::{anyhow, Result};
 
 use std::prelude::v1::*;
+use std::time::Duration;
 use teaclave_attestation::verifier;
-use teaclave_attestation::{AttestationConfig, RemoteAttestation};
+use teaclave_attestation::RemoteAttestation;
 use teaclave_binder::proto::{
     ECallCommand, FinalizeEnclaveInput, FinalizeEnclaveOutput, InitEnclaveInput, InitEnclaveOutput,
     StartServiceInput, StartServiceOutput,
@@ -47,9 +48,9 @@ use teaclave_types::{TeeServiceError, TeeServiceResult};
 mod service;
 
 fn start_service(config: &RuntimeConfig) -> Result<()> {
+    let root_ca_certs = config.attestation.root_ca_certs(AS_ROOT_CA_CERT);
     let listen_address = config.api_endpoints.frontend.listen_address;
-    let attestation_config = AttestationConfig::from_teaclave_config(&config)?;
-    let attested_tls_config = RemoteAttestation::new(attestation_config)
+    let attested_tls_config = RemoteAttestation::from_teaclave_config(&config)?
         .generate_and_endorse()?
         .attested_tls_config()
         .ok_or_else(|| anyhow!("cannot get attested TLS config"))?;
@@ -58,20 +59,22 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
     let mut server = SgxTrustedTlsServer::<TeaclaveFrontendResponse, TeaclaveFrontendRequest>::new(
         listen_address,
         server_config,
-    );
+    )
+    .shutdown_timeout(Duration::from_secs(config.server.shutdown_timeout_seconds))
+    .max_connections(config.server.max_connections);
 
     let enclave_info = teaclave_types::EnclaveInfo::from_bytes(&config.audit.enclave_info_bytes);
     let authentication_service_endpoint = create_trusted_authentication_endpoint(
         &config.internal_endpoints.authentication.advertised_address,
         &enclave_info,
-        AS_ROOT_CA_CERT,
+        &root_ca_certs,
         verifier::universal_quote_verifier,
     );
 
     let management_service_endpoint = create_trusted_management_endpoint(
         &config.internal_endpoints.management.advertised_address,
         &enclave_info,
-        AS_ROOT_CA_CERT,
+        &root_ca_certs,
         verifier::universal_quote_verifier,
     );
 
@@ -16,8 +16,11 @@
 // under the License.
 
 use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::prelude::v1::*;
 use std::sync::{Arc, SgxMutex as Mutex};
+use std::thread_local;
 use thiserror::Error;
 
 use teaclave_proto::teaclave_authentication_service::{
@@ -25,20 +28,44 @@ use teaclave_proto::teaclave_authentication_service::{
 };
 use teaclave_proto::teaclave_common::UserCredential;
 use teaclave_proto::teaclave_frontend_service::{
-    ApproveTaskRequest, ApproveTaskResponse, AssignDataRequest, AssignDataResponse,
-    CreateTaskRequest, CreateTaskResponse, GetFunctionRequest, GetFunctionResponse,
-    GetInputFileRequest, GetInputFileResponse, GetOutputFileRequest, GetOutputFileResponse,
-    GetTaskRequest, GetTaskResponse, InvokeTaskRequest, InvokeTaskResponse,
-    RegisterFunctionRequest, RegisterFunctionResponse, RegisterFusionOutputRequest,
-    RegisterFusionOutputResponse, RegisterInputFileRequest, RegisterInputFileResponse,
-    RegisterInputFromOutputRequest, RegisterInputFromOutputResponse, RegisterOutputFileRequest,
-    RegisterOutputFileResponse, TeaclaveFrontend,
+    AdminGetTaskRequest, AdminGetTaskResponse, ApproveTaskRequest, ApproveTaskResponse,
+    AssignDataRequest, AssignDataResponse, CancelAllTasksRequest, CancelAllTasksResponse,
+    CancelTaskRequest, CancelTaskResponse, CreateOutputAccessTokenRequest,
+    CreateOutputAccessTokenResponse, CreateTaskRequest, CreateTaskResponse, DeleteInputFileRequest,
+    DeleteInputFileResponse, DeprecateFunctionRequest, DeprecateFunctionResponse,
+    EstimateTaskRequest, EstimateTaskResponse, ExportAuditLogRequest, ExportAuditLogResponse,
+    ExportCatalogRequest, ExportCatalogResponse, ExportTaskRequest, ExportTaskResponse,
+    GenerateOutputPresignedUrlRequest, GenerateOutputPresignedUrlResponse, GetCapabilitiesRequest,
+    GetCapabilitiesResponse, GetFunctionRequest, GetFunctionResponse, GetInputFileRequest,
+    GetInputFileResponse, GetMetricsRequest, GetMetricsResponse, GetOutputFileRequest,
+    GetOutputFileResponse, GetTaskReproductionRequest, GetTaskReproductionResponse, GetTaskRequest,
+    GetTaskResponse, GetTasksRequest, GetTasksResponse, ImportCatalogRequest,
+    ImportCatalogResponse, ImportTaskRequest, ImportTaskResponse, InstantiateTemplateRequest,
+    InstantiateTemplateResponse, InvokeTaskRequest, InvokeTaskResponse, LinkTasksRequest,
+    LinkTasksResponse, ListFunctionsRequest, ListFunctionsResponse, ListInputFilesRequest,
+    ListInputFilesResponse, ListOutputFilesRequest, ListOutputFilesResponse, ListTasksRequest,
+    ListTasksResponse, RegisterCryptoKeyRequest, RegisterCryptoKeyResponse,
+    RegisterFunctionBundleRequest, RegisterFunctionBundleResponse, RegisterFunctionRequest,
+    RegisterFunctionResponse, RegisterFusionOutputRequest, RegisterFusionOutputResponse,
+    RegisterInputFileRequest, RegisterInputFileResponse, RegisterInputFromOutputRequest,
+    RegisterInputFromOutputResponse, RegisterInputFromTaskLogRequest,
+    RegisterInputFromTaskLogResponse, RegisterOutputFileRequest, RegisterOutputFileResponse,
+    RegisterTaskTemplateRequest, RegisterTaskTemplateResponse, ReproduceTaskRequest,
+    ReproduceTaskResponse, RestoreFunctionRequest, RestoreFunctionResponse, RetryTaskRequest,
+    RetryTaskResponse, RunSelfTestRequest, RunSelfTestResponse, SetFunctionRateLimitRequest,
+    SetFunctionRateLimitResponse, SetResultCallbackRequest, SetResultCallbackResponse,
+    SoftDeleteFunctionRequest, SoftDeleteFunctionResponse, SubmitTaskGraphRequest,
+    SubmitTaskGraphResponse, SubscribeEventsRequest, SubscribeEventsResponse, TeaclaveFrontend,
 };
 use teaclave_proto::teaclave_management_service::TeaclaveManagementClient;
 use teaclave_rpc::endpoint::Endpoint;
 use teaclave_rpc::Request;
 use teaclave_service_enclave_utils::{bail, teaclave_service};
-use teaclave_types::{TeaclaveServiceResponseError, TeaclaveServiceResponseResult};
+use teaclave_types::{
+    Executor, ExecutorType, FileCrypto, TeaclaveServiceResponseError,
+    TeaclaveServiceResponseResult, UserRole, FILE_AGENT_URL_SCHEMES,
+};
+use uuid::Uuid;
 
 #[derive(Error, Debug)]
 enum TeaclaveFrontendError {
@@ -61,24 +88,68 @@ pub(crate) struct TeaclaveFrontendService {
     management_client: Arc<Mutex<TeaclaveManagementClient>>,
 }
 
+const CORRELATION_ID_METADATA_KEY: &str = "correlation_id";
+
+thread_local! {
+    // Correlation id of the request being handled on this thread
+    static CORRELATION_ID: RefCell<String> = RefCell::new(String::new());
+}
+
+fn request_correlation_id() -> String {
+    CORRELATION_ID.with(|id| id.borrow().clone())
+}
+
+// Attach the correlation id to errors, so that users can reference it in
+// bug reports.
+fn with_correlation_id(
+    error: TeaclaveServiceResponseError,
+    correlation_id: &str,
+) -> TeaclaveServiceResponseError {
+    let attach = |message: String| format!("{} (correlation id: {})", message, correlation_id);
+    match error {
+        TeaclaveServiceResponseError::RequestError(m) => {
+            TeaclaveServiceResponseError::RequestError(attach(m))
+        }
+        TeaclaveServiceResponseError::ConnectionError(m) => {
+            TeaclaveServiceResponseError::ConnectionError(attach(m))
+        }
+        TeaclaveServiceResponseError::InternalError(m) => {
+            TeaclaveServiceResponseError::InternalError(attach(m))
+        }
+    }
+}
+
 macro_rules! authentication_and_forward_to_management {
     ($service: ident, $request: ident, $func: ident) => {{
-        match $service.authenticate(&$request) {
-            Ok(true) => (),
+        let role = match $service.authenticate(&$request) {
+            Ok(Some(role)) => role,
             _ => bail!(TeaclaveFrontendError::AuthenticationError),
-        }
+        };
 
         let client = $service.management_client.clone();
         let mut client = client
             .lock()
             .map_err(|_| TeaclaveFrontendError::LockError)?;
+        let mut metadata = $request.metadata;
+        let correlation_id = request_correlation_id();
+        metadata.insert(
+            CORRELATION_ID_METADATA_KEY.to_string(),
+            correlation_id.clone(),
+        );
+        log::info!("[{}] {}", correlation_id, stringify!($func));
+
         client.metadata_mut().clear();
-        client.metadata_mut().extend($request.metadata);
+        client.metadata_mut().extend(metadata);
+        // The role is always taken from the authentication service, never from
+        // the metadata supplied by users.
+        client
+            .metadata_mut()
+            .insert("role".to_string(), role.to_string());
 
         let response = client.$func($request.message);
 
         client.metadata_mut().clear();
-        let response = response?;
+        let response = response.map_err(|e| with_correlation_id(e, &correlation_id))?;
         Ok(response)
     }};
 }
@@ -128,6 +199,31 @@ impl TeaclaveFrontendService {
 }
 
 impl TeaclaveFrontend for TeaclaveFrontendService {
+    // Requests without a correlation id from users get a new one, which is
+    // forwarded to the management service and included in its logs.
+    fn begin_request(
+        &self,
+        metadata: &HashMap<String, String>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let correlation_id = metadata
+            .get(CORRELATION_ID_METADATA_KEY)
+            .cloned()
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        CORRELATION_ID.with(|id| *id.borrow_mut() = correlation_id);
+        Ok(())
+    }
+
+    // The correlation id is returned to users, so that they can reference it
+    // in bug reports.
+    fn response_metadata(&self) -> HashMap<String, String> {
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            CORRELATION_ID_METADATA_KEY.to_string(),
+            request_correlation_id(),
+        );
+        metadata
+    }
+
     fn register_input_file(
         &self,
         request: Request<RegisterInputFileRequest>,
@@ -169,6 +265,13 @@ impl TeaclaveFrontend for TeaclaveFrontendService {
         authentication_and_forward_to_management!(self, request, get_input_file)
     }
 
+    fn delete_input_file(
+        &self,
+        request: Request<DeleteInputFileRequest>,
+    ) -> TeaclaveServiceResponseResult<DeleteInputFileResponse> {
+        authentication_and_forward_to_management!(self, request, delete_input_file)
+    }
+
     fn register_function(
         &self,
         request: Request<RegisterFunctionRequest>,
@@ -183,6 +286,13 @@ impl TeaclaveFrontend for TeaclaveFrontendService {
         authentication_and_forward_to_management!(self, request, get_function)
     }
 
+    fn deprecate_function(
+        &self,
+        request: Request<DeprecateFunctionRequest>,
+    ) -> TeaclaveServiceResponseResult<DeprecateFunctionResponse> {
+        authentication_and_forward_to_management!(self, request, deprecate_function)
+    }
+
     fn create_task(
         &self,
         request: Request<CreateTaskRequest>,
@@ -217,10 +327,263 @@ impl TeaclaveFrontend for TeaclaveFrontendService {
     ) -> TeaclaveServiceResponseResult<InvokeTaskResponse> {
         authentication_and_forward_to_management!(self, request, invoke_task)
     }
+
+    fn admin_get_task(
+        &self,
+        request: Request<AdminGetTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<AdminGetTaskResponse> {
+        authentication_and_forward_to_management!(self, request, admin_get_task)
+    }
+
+    fn run_self_test(
+        &self,
+        request: Request<RunSelfTestRequest>,
+    ) -> TeaclaveServiceResponseResult<RunSelfTestResponse> {
+        authentication_and_forward_to_management!(self, request, run_self_test)
+    }
+
+    fn link_tasks(
+        &self,
+        request: Request<LinkTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<LinkTasksResponse> {
+        authentication_and_forward_to_management!(self, request, link_tasks)
+    }
+
+    fn list_input_files(
+        &self,
+        request: Request<ListInputFilesRequest>,
+    ) -> TeaclaveServiceResponseResult<ListInputFilesResponse> {
+        authentication_and_forward_to_management!(self, request, list_input_files)
+    }
+
+    fn list_output_files(
+        &self,
+        request: Request<ListOutputFilesRequest>,
+    ) -> TeaclaveServiceResponseResult<ListOutputFilesResponse> {
+        authentication_and_forward_to_management!(self, request, list_output_files)
+    }
+
+    fn register_task_template(
+        &self,
+        request: Request<RegisterTaskTemplateRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterTaskTemplateResponse> {
+        authentication_and_forward_to_management!(self, request, register_task_template)
+    }
+
+    fn instantiate_template(
+        &self,
+        request: Request<InstantiateTemplateRequest>,
+    ) -> TeaclaveServiceResponseResult<InstantiateTemplateResponse> {
+        authentication_and_forward_to_management!(self, request, instantiate_template)
+    }
+
+    fn get_tasks(
+        &self,
+        request: Request<GetTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<GetTasksResponse> {
+        authentication_and_forward_to_management!(self, request, get_tasks)
+    }
+
+    fn get_capabilities(
+        &self,
+        request: Request<GetCapabilitiesRequest>,
+    ) -> TeaclaveServiceResponseResult<GetCapabilitiesResponse> {
+        match self.authenticate(&request) {
+            Ok(Some(_)) => (),
+            _ => bail!(TeaclaveFrontendError::AuthenticationError),
+        };
+
+        let to_strings =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+        let response = GetCapabilitiesResponse {
+            crypto_schemas: to_strings(FileCrypto::SUPPORTED_SCHEMAS),
+            executors: Executor::SUPPORTED.to_vec(),
+            executor_types: ExecutorType::SUPPORTED.to_vec(),
+            url_schemes: to_strings(FILE_AGENT_URL_SCHEMES),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        };
+        Ok(response)
+    }
+
+    fn cancel_task(
+        &self,
+        request: Request<CancelTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<CancelTaskResponse> {
+        authentication_and_forward_to_management!(self, request, cancel_task)
+    }
+
+    fn list_tasks(
+        &self,
+        request: Request<ListTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<ListTasksResponse> {
+        authentication_and_forward_to_management!(self, request, list_tasks)
+    }
+
+    fn export_task(
+        &self,
+        request: Request<ExportTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<ExportTaskResponse> {
+        authentication_and_forward_to_management!(self, request, export_task)
+    }
+
+    fn import_task(
+        &self,
+        request: Request<ImportTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<ImportTaskResponse> {
+        authentication_and_forward_to_management!(self, request, import_task)
+    }
+
+    fn generate_output_presigned_url(
+        &self,
+        request: Request<GenerateOutputPresignedUrlRequest>,
+    ) -> TeaclaveServiceResponseResult<GenerateOutputPresignedUrlResponse> {
+        authentication_and_forward_to_management!(self, request, generate_output_presigned_url)
+    }
+
+    fn estimate_task(
+        &self,
+        request: Request<EstimateTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<EstimateTaskResponse> {
+        authentication_and_forward_to_management!(self, request, estimate_task)
+    }
+
+    fn list_functions(
+        &self,
+        request: Request<ListFunctionsRequest>,
+    ) -> TeaclaveServiceResponseResult<ListFunctionsResponse> {
+        authentication_and_forward_to_management!(self, request, list_functions)
+    }
+
+    fn retry_task(
+        &self,
+        request: Request<RetryTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<RetryTaskResponse> {
+        authentication_and_forward_to_management!(self, request, retry_task)
+    }
+
+    fn register_input_from_task_log(
+        &self,
+        request: Request<RegisterInputFromTaskLogRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterInputFromTaskLogResponse> {
+        authentication_and_forward_to_management!(self, request, register_input_from_task_log)
+    }
+
+    fn submit_task_graph(
+        &self,
+        request: Request<SubmitTaskGraphRequest>,
+    ) -> TeaclaveServiceResponseResult<SubmitTaskGraphResponse> {
+        authentication_and_forward_to_management!(self, request, submit_task_graph)
+    }
+
+    fn subscribe_events(
+        &self,
+        request: Request<SubscribeEventsRequest>,
+    ) -> TeaclaveServiceResponseResult<SubscribeEventsResponse> {
+        authentication_and_forward_to_management!(self, request, subscribe_events)
+    }
+
+    fn soft_delete_function(
+        &self,
+        request: Request<SoftDeleteFunctionRequest>,
+    ) -> TeaclaveServiceResponseResult<SoftDeleteFunctionResponse> {
+        authentication_and_forward_to_management!(self, request, soft_delete_function)
+    }
+
+    fn restore_function(
+        &self,
+        request: Request<RestoreFunctionRequest>,
+    ) -> TeaclaveServiceResponseResult<RestoreFunctionResponse> {
+        authentication_and_forward_to_management!(self, request, restore_function)
+    }
+
+    fn register_function_bundle(
+        &self,
+        request: Request<RegisterFunctionBundleRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterFunctionBundleResponse> {
+        authentication_and_forward_to_management!(self, request, register_function_bundle)
+    }
+
+    fn register_crypto_key(
+        &self,
+        request: Request<RegisterCryptoKeyRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterCryptoKeyResponse> {
+        authentication_and_forward_to_management!(self, request, register_crypto_key)
+    }
+
+    fn cancel_all_tasks(
+        &self,
+        request: Request<CancelAllTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<CancelAllTasksResponse> {
+        authentication_and_forward_to_management!(self, request, cancel_all_tasks)
+    }
+
+    fn create_output_access_token(
+        &self,
+        request: Request<CreateOutputAccessTokenRequest>,
+    ) -> TeaclaveServiceResponseResult<CreateOutputAccessTokenResponse> {
+        authentication_and_forward_to_management!(self, request, create_output_access_token)
+    }
+
+    fn get_metrics(
+        &self,
+        request: Request<GetMetricsRequest>,
+    ) -> TeaclaveServiceResponseResult<GetMetricsResponse> {
+        authentication_and_forward_to_management!(self, request, get_metrics)
+    }
+
+    fn export_catalog(
+        &self,
+        request: Request<ExportCatalogRequest>,
+    ) -> TeaclaveServiceResponseResult<ExportCatalogResponse> {
+        authentication_and_forward_to_management!(self, request, export_catalog)
+    }
+
+    fn import_catalog(
+        &self,
+        request: Request<ImportCatalogRequest>,
+    ) -> TeaclaveServiceResponseResult<ImportCatalogResponse> {
+        authentication_and_forward_to_management!(self, request, import_catalog)
+    }
+
+    fn set_function_rate_limit(
+        &self,
+        request: Request<SetFunctionRateLimitRequest>,
+    ) -> TeaclaveServiceResponseResult<SetFunctionRateLimitResponse> {
+        authentication_and_forward_to_management!(self, request, set_function_rate_limit)
+    }
+
+    fn export_audit_log(
+        &self,
+        request: Request<ExportAuditLogRequest>,
+    ) -> TeaclaveServiceResponseResult<ExportAuditLogResponse> {
+        authentication_and_forward_to_management!(self, request, export_audit_log)
+    }
+
+    fn set_result_callback(
+        &self,
+        request: Request<SetResultCallbackRequest>,
+    ) -> TeaclaveServiceResponseResult<SetResultCallbackResponse> {
+        authentication_and_forward_to_management!(self, request, set_result_callback)
+    }
+
+    fn reproduce_task(
+        &self,
+        request: Request<ReproduceTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<ReproduceTaskResponse> {
+        authentication_and_forward_to_management!(self, request, reproduce_task)
+    }
+
+    fn get_task_reproduction(
+        &self,
+        request: Request<GetTaskReproductionRequest>,
+    ) -> TeaclaveServiceResponseResult<GetTaskReproductionResponse> {
+        authentication_and_forward_to_management!(self, request, get_task_reproduction)
+    }
 }
 
 impl TeaclaveFrontendService {
-    fn authenticate<T>(&self, request: &Request<T>) -> anyhow::Result<bool> {
+    // Returns the role of the user if the credential is accepted.
+    fn authenticate<T>(&self, request: &Request<T>) -> anyhow::Result<Option<UserRole>> {
         use anyhow::anyhow;
         let id = request
             .metadata
@@ -238,6 +601,11 @@ impl TeaclaveFrontendService {
             .lock()
             .map_err(|_| anyhow!("Cannot lock authentication client"))?
             .user_authenticate(auth_request);
-        Ok(auth_response?.accept)
+        let auth_response = auth_response?;
+        if auth_response.accept {
+            Ok(Some(auth_response.role))
+        } else {
+            Ok(None)
+        }
     }
 }
@@ -0,0 +1,76 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use teaclave_config::AccessRule;
+use teaclave_types::UserRole;
+
+// Built-in rules of RPCs which are not open to anyone. Conditions on the
+// accessed objects, e.g., task participants, are checked by the handlers.
+const DEFAULT_ACCESS_RULES: &[(&str, AccessRule)] = &[
+    ("admin_get_task", AccessRule::Admin),
+    ("get_metrics", AccessRule::Admin),
+    ("run_self_test", AccessRule::Admin),
+    ("export_audit_log", AccessRule::Admin),
+];
+
+#[derive(Debug, Clone)]
+pub(crate) struct AccessPolicy {
+    rules: HashMap<String, AccessRule>,
+}
+
+impl AccessPolicy {
+    pub(crate) fn new(overrides: &HashMap<String, AccessRule>) -> Self {
+        let mut rules: HashMap<String, AccessRule> = DEFAULT_ACCESS_RULES
+            .iter()
+            .map(|(method, rule)| (method.to_string(), *rule))
+            .collect();
+        rules.extend(overrides.iter().map(|(k, v)| (k.clone(), *v)));
+        Self { rules }
+    }
+
+    pub(crate) fn allows(&self, method: &str, role: UserRole) -> bool {
+        match self.rules.get(method).unwrap_or(&AccessRule::Anyone) {
+            AccessRule::Anyone => true,
+            AccessRule::Admin => role == UserRole::Admin,
+            AccessRule::Nobody => false,
+        }
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_access_policy() {
+        let policy = AccessPolicy::new(&HashMap::new());
+        assert!(policy.allows("create_task", UserRole::User));
+        assert!(!policy.allows("admin_get_task", UserRole::User));
+        assert!(policy.allows("admin_get_task", UserRole::Admin));
+
+        let mut overrides = HashMap::new();
+        overrides.insert("register_function".to_string(), AccessRule::Admin);
+        overrides.insert("run_self_test".to_string(), AccessRule::Nobody);
+        overrides.insert("admin_get_task".to_string(), AccessRule::Anyone);
+        let policy = AccessPolicy::new(&overrides);
+        assert!(!policy.allows("register_function", UserRole::User));
+        assert!(policy.allows("register_function", UserRole::Admin));
+        assert!(!policy.allows("run_self_test", UserRole::Admin));
+        assert!(policy.allows("admin_get_task", UserRole::User));
+    }
+}
@@ -0,0 +1,88 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::crypto_keys::key_encryption_key;
+use crate::ocall::handle_file_request;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::prelude::v1::*;
+use teaclave_crypto::AesGcm256Key;
+use teaclave_types::{FileAgentRequest, HandleFileCommand, HandleFileInfo};
+use url::Url;
+use uuid::Uuid;
+
+static ARCHIVE_BASE_DIR: &str = "/tmp/teaclave_result_archive_staging/";
+
+// Moves the content through a local staging file, which is removed whether or
+// not the file agent succeeds.
+pub(crate) fn with_staging_file<T>(f: impl FnOnce(&PathBuf) -> Result<T>) -> Result<T> {
+    std::untrusted::fs::create_dir_all(ARCHIVE_BASE_DIR)?;
+    let local = Path::new(ARCHIVE_BASE_DIR).join(Uuid::new_v4().to_string());
+    let result = f(&local);
+    if local.exists() {
+        std::untrusted::fs::remove_file(&local)?;
+    }
+    result
+}
+
+// Encrypts an archived result in the enclave with the archive key, and uploads
+// it to the cold storage with the file agent. Returns the iv of the archive.
+pub(crate) fn upload_archive(
+    bytes: &[u8],
+    hex_key: Option<&str>,
+    url: &Url,
+    fusion_base: impl AsRef<Path>,
+) -> Result<Vec<u8>> {
+    let key = key_encryption_key(hex_key)?;
+    // A fresh iv for every archive
+    let iv = AesGcm256Key::random().iv;
+    let mut ciphertext = bytes.to_vec();
+    AesGcm256Key::new(&key, &iv)?.encrypt(&mut ciphertext)?;
+    with_staging_file(|local| {
+        std::untrusted::fs::write(local, &ciphertext)?;
+        let info = HandleFileInfo::new(local, url);
+        let request =
+            FileAgentRequest::new(HandleFileCommand::Upload, vec![info], fusion_base.as_ref());
+        handle_file_request(request)
+    })?;
+    Ok(iv.to_vec())
+}
+
+// Downloads an archived result from the cold storage with the file agent, and
+// decrypts it in the enclave. Archives without an iv are stored in plaintext.
+pub(crate) fn download_archive(
+    url: &Url,
+    hex_key: Option<&str>,
+    iv: &[u8],
+    fusion_base: impl AsRef<Path>,
+) -> Result<Vec<u8>> {
+    let mut bytes = with_staging_file(|local| {
+        let info = HandleFileInfo::new(local, url);
+        let request = FileAgentRequest::new(
+            HandleFileCommand::Download,
+            vec![info],
+            fusion_base.as_ref(),
+        );
+        handle_file_request(request)?;
+        Ok(std::untrusted::fs::read(local)?)
+    })?;
+    if !iv.is_empty() {
+        let key = key_encryption_key(hex_key)?;
+        AesGcm256Key::new(&key, iv)?.decrypt(&mut bytes)?;
+    }
+    Ok(bytes)
+}
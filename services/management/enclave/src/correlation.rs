@@ -0,0 +1,43 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// The correlation id of the request being handled is kept per thread like its
+// deadline, so that storage calls made on behalf of the request carry it in
+// their metadata.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use std::thread_local;
+
+pub(crate) const CORRELATION_ID_METADATA_KEY: &str = "correlation_id";
+
+thread_local! {
+    static CORRELATION_ID: RefCell<Option<String>> = RefCell::new(None);
+}
+
+// Sets the correlation id of the request about to be handled on this thread.
+pub(crate) fn set_request_correlation_id(metadata: &HashMap<String, String>) {
+    let correlation_id = metadata.get(CORRELATION_ID_METADATA_KEY).cloned();
+    CORRELATION_ID.with(|id| *id.borrow_mut() = correlation_id);
+}
+
+// Correlation id of the current request, None if the request has none or the
+// thread doesn't handle requests.
+pub(crate) fn request_correlation_id() -> Option<String> {
+    CORRELATION_ID.with(|id| id.borrow().clone())
+}
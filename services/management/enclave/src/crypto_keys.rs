@@ -0,0 +1,74 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Named crypto keys which users store in the service and reference when
+// registering files. Keys are encrypted with the key encryption key of the
+// service before they are stored.
+
+use anyhow::{anyhow, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use teaclave_crypto::AesGcm256Key;
+use teaclave_types::{FileCrypto, UserID};
+
+const CRYPTO_KEYS_PREFIX: &str = "crypto-keys";
+// Maximum length of the name of a key
+const MAX_CRYPTO_KEY_NAME_LEN: usize = 64;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct EncryptedCryptoKey {
+    iv: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+pub(crate) fn crypto_key_storage_key(user_id: &UserID, name: &str) -> Vec<u8> {
+    format!("{}-{}-{}", CRYPTO_KEYS_PREFIX, user_id, name).into_bytes()
+}
+
+pub(crate) fn key_encryption_key(hex_key: Option<&str>) -> Result<Vec<u8>> {
+    let hex_key = hex_key.ok_or_else(|| anyhow!("key encryption key is not configured"))?;
+    Ok(hex::decode(hex_key)?)
+}
+
+pub(crate) fn encrypt_crypto_key(hex_key: Option<&str>, crypto: &FileCrypto) -> Result<Vec<u8>> {
+    let kek = key_encryption_key(hex_key)?;
+    // A fresh iv for every encryption
+    let iv = AesGcm256Key::random().iv;
+    let mut ciphertext = serde_json::to_vec(crypto)?;
+    AesGcm256Key::new(&kek, &iv)?.encrypt(&mut ciphertext)?;
+    let encrypted = EncryptedCryptoKey {
+        iv: iv.to_vec(),
+        ciphertext,
+    };
+    Ok(serde_json::to_vec(&encrypted)?)
+}
+
+pub(crate) fn decrypt_crypto_key(hex_key: Option<&str>, value: &[u8]) -> Result<FileCrypto> {
+    let kek = key_encryption_key(hex_key)?;
+    let encrypted: EncryptedCryptoKey = serde_json::from_slice(value)?;
+    let mut plaintext = encrypted.ciphertext;
+    AesGcm256Key::new(&kek, &encrypted.iv)?.decrypt(&mut plaintext)?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+pub(crate) fn validate_crypto_key_name(name: &str) -> Result<()> {
+    ensure!(
+        !name.is_empty() && name.len() <= MAX_CRYPTO_KEY_NAME_LEN,
+        "invalid key name"
+    );
+    Ok(())
+}
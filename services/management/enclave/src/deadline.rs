@@ -0,0 +1,61 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Clients may set a deadline, as a UNIX timestamp in seconds, in the
+// "deadline" metadata of a request. The deadline of the request being handled
+// is kept per thread, so that storage calls made on behalf of the request can
+// be bounded by it without passing it through every handler.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use std::thread_local;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::untrusted::time::SystemTimeEx;
+use thiserror::Error;
+
+pub(crate) const DEADLINE_METADATA_KEY: &str = "deadline";
+
+#[derive(Error, Debug)]
+#[error("deadline exceeded")]
+pub(crate) struct DeadlineExceeded;
+
+thread_local! {
+    static DEADLINE: Cell<Option<u64>> = Cell::new(None);
+}
+
+// Sets the deadline of the request about to be handled on this thread. A
+// malformed deadline is ignored like a missing one.
+pub(crate) fn set_request_deadline(metadata: &HashMap<String, String>) {
+    let deadline = metadata
+        .get(DEADLINE_METADATA_KEY)
+        .and_then(|deadline| deadline.parse::<u64>().ok());
+    DEADLINE.with(|d| d.set(deadline));
+}
+
+// Time left until the deadline of the current request, None if the request
+// has no deadline.
+pub(crate) fn remaining_time() -> Result<Option<Duration>, DeadlineExceeded> {
+    let deadline = match DEADLINE.with(|d| d.get()) {
+        Some(deadline) => UNIX_EPOCH + Duration::from_secs(deadline),
+        None => return Ok(None),
+    };
+    match deadline.duration_since(SystemTime::now()) {
+        Ok(remaining) if remaining > Duration::from_secs(0) => Ok(Some(remaining)),
+        _ => Err(DeadlineExceeded),
+    }
+}
@@ -0,0 +1,150 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::pagination::CursorSigner;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use teaclave_proto::teaclave_frontend_service::FileEntry;
+use teaclave_types::{ExternalID, UserID};
+
+const FILE_INDEX_PREFIX: &str = "file-index";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FileKind {
+    Input,
+    Output,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct FileIndexEntry {
+    data_id: ExternalID,
+    created_at: u64,
+    consumed: bool,
+    // order of registration, entries indexed before it was introduced are
+    // ordered by their ids
+    #[serde(default)]
+    seq: u64,
+    #[serde(default)]
+    fusion: bool,
+}
+
+// Input and output files registered by or shared with a user, in the order
+// of registration.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct FileIndex {
+    inputs: Vec<FileIndexEntry>,
+    outputs: Vec<FileIndexEntry>,
+    #[serde(default)]
+    last_seq: u64,
+}
+
+impl FileIndex {
+    pub(crate) fn get_key(user_id: &UserID) -> String {
+        format!("{}-{}", FILE_INDEX_PREFIX, user_id)
+    }
+
+    fn entries(&self, kind: FileKind) -> &Vec<FileIndexEntry> {
+        match kind {
+            FileKind::Input => &self.inputs,
+            FileKind::Output => &self.outputs,
+        }
+    }
+
+    fn entries_mut(&mut self, kind: FileKind) -> &mut Vec<FileIndexEntry> {
+        match kind {
+            FileKind::Input => &mut self.inputs,
+            FileKind::Output => &mut self.outputs,
+        }
+    }
+
+    pub(crate) fn add(&mut self, kind: FileKind, data_id: ExternalID, created_at: u64) {
+        self.push(kind, data_id, created_at, false);
+    }
+
+    pub(crate) fn add_fusion_output(&mut self, data_id: ExternalID, created_at: u64) {
+        self.push(FileKind::Output, data_id, created_at, true);
+    }
+
+    fn push(&mut self, kind: FileKind, data_id: ExternalID, created_at: u64, fusion: bool) {
+        self.last_seq += 1;
+        let seq = self.last_seq;
+        self.entries_mut(kind).push(FileIndexEntry {
+            data_id,
+            created_at,
+            consumed: false,
+            seq,
+            fusion,
+        });
+    }
+
+    // Fusion outputs which are not yet registered as inputs.
+    pub(crate) fn outstanding_fusion_outputs(&self) -> usize {
+        self.outputs
+            .iter()
+            .filter(|entry| entry.fusion && !entry.consumed)
+            .count()
+    }
+
+    // Inputs which are not yet assigned to tasks.
+    pub(crate) fn outstanding_inputs(&self) -> usize {
+        self.inputs.iter().filter(|entry| !entry.consumed).count()
+    }
+
+    // An input is consumed when it is assigned to a task, and an output is
+    // consumed when it is registered as an input.
+    pub(crate) fn mark_consumed(&mut self, kind: FileKind, data_id: &ExternalID) {
+        for entry in self.entries_mut(kind).iter_mut() {
+            if &entry.data_id == data_id {
+                entry.consumed = true;
+            }
+        }
+    }
+
+    pub(crate) fn is_consumed(&self, kind: FileKind, data_id: &ExternalID) -> bool {
+        self.entries(kind)
+            .iter()
+            .any(|entry| &entry.data_id == data_id && entry.consumed)
+    }
+
+    pub(crate) fn remove(&mut self, kind: FileKind, data_id: &ExternalID) {
+        self.entries_mut(kind)
+            .retain(|entry| &entry.data_id != data_id);
+    }
+
+    // Returns a page of files in the order of registration and the cursor of
+    // the next page.
+    pub(crate) fn page(
+        &self,
+        signer: &CursorSigner,
+        kind: FileKind,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<FileEntry>, Option<String>)> {
+        let (entries, next_cursor) = signer.paginate(
+            self.entries(kind),
+            |entry| (entry.seq, entry.data_id.to_string()),
+            cursor,
+            limit,
+        )?;
+        let files = entries
+            .into_iter()
+            .map(|entry| FileEntry::new(entry.data_id.clone(), entry.created_at, entry.consumed))
+            .collect();
+        Ok((files, next_cursor))
+    }
+}
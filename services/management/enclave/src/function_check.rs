@@ -0,0 +1,61 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Static checks of functions before they are registered. Checks implement
+// FunctionCheck and are built from the config in function_checks.
+
+use std::prelude::v1::*;
+use std::sync::Arc;
+use teaclave_config::ManagementConfig;
+use teaclave_types::{ExecutorType, Function};
+
+pub(crate) trait FunctionCheck: Send + Sync {
+    // Returns why the function is rejected, if it is.
+    fn check(&self, function: &Function) -> Result<(), String>;
+}
+
+// Rejects MesaPy scripts containing any of the patterns, e.g., "import os".
+struct DeniedPatterns {
+    patterns: Vec<String>,
+}
+
+impl FunctionCheck for DeniedPatterns {
+    fn check(&self, function: &Function) -> Result<(), String> {
+        if function.executor_type != ExecutorType::Python {
+            return Ok(());
+        }
+        let script = String::from_utf8_lossy(&function.payload);
+        match self
+            .patterns
+            .iter()
+            .find(|pattern| script.contains(pattern.as_str()))
+        {
+            Some(pattern) => Err(format!("script contains denied pattern {:?}", pattern)),
+            None => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn function_checks(config: &ManagementConfig) -> Vec<Arc<dyn FunctionCheck>> {
+    let mut checks: Vec<Arc<dyn FunctionCheck>> = Vec::new();
+    if !config.mesapy_denied_patterns.is_empty() {
+        checks.push(Arc::new(DeniedPatterns {
+            patterns: config.mesapy_denied_patterns.clone(),
+        }));
+    }
+    checks
+}
@@ -0,0 +1,134 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::pagination::CursorSigner;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use teaclave_proto::teaclave_frontend_service::FunctionEntry;
+use teaclave_types::{ExternalID, Function, Storable, UserID};
+
+const FUNCTION_INDEX_KEY: &str = "function-index";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct FunctionIndexEntry {
+    function_id: ExternalID,
+    name: String,
+    owner: UserID,
+    public: bool,
+    category: Option<String>,
+    tags: Vec<String>,
+    seq: u64,
+    #[serde(default)]
+    deleted: bool,
+}
+
+// Registered functions in the order of registration. Functions are listed to
+// users who can get them, i.e., public functions and their own ones.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct FunctionIndex {
+    functions: Vec<FunctionIndexEntry>,
+    last_seq: u64,
+}
+
+impl FunctionIndex {
+    pub(crate) fn get_key() -> &'static str {
+        FUNCTION_INDEX_KEY
+    }
+
+    pub(crate) fn add(&mut self, function: &Function) {
+        self.last_seq += 1;
+        self.functions.push(FunctionIndexEntry {
+            function_id: function.external_id(),
+            name: function.name.clone(),
+            owner: function.owner.clone(),
+            public: function.public,
+            category: function.category.clone(),
+            tags: function.tags.clone(),
+            seq: self.last_seq,
+            deleted: false,
+        });
+    }
+
+    // Soft deleted functions are hidden from listings until they are
+    // restored.
+    pub(crate) fn set_deleted(&mut self, function_id: &ExternalID, deleted: bool) {
+        for entry in self.functions.iter_mut() {
+            if &entry.function_id == function_id {
+                entry.deleted = deleted;
+            }
+        }
+    }
+
+    // Deleted functions do not hold their names.
+    pub(crate) fn contains_name(&self, owner: &UserID, name: &str) -> bool {
+        self.functions
+            .iter()
+            .any(|entry| !entry.deleted && &entry.owner == owner && entry.name == name)
+    }
+
+    pub(crate) fn remove(&mut self, function_id: &ExternalID) {
+        self.functions
+            .retain(|entry| &entry.function_id != function_id);
+    }
+
+    // Functions visible to the user, in the order of registration.
+    pub(crate) fn visible(&self, user_id: &UserID) -> Vec<ExternalID> {
+        self.functions
+            .iter()
+            .filter(|entry| !entry.deleted && (entry.public || &entry.owner == user_id))
+            .map(|entry| entry.function_id.clone())
+            .collect()
+    }
+
+    // Returns a page of functions visible to the user in the given category
+    // carrying all of the given tags and the cursor of the next page.
+    pub(crate) fn page(
+        &self,
+        signer: &CursorSigner,
+        user_id: &UserID,
+        category: Option<&str>,
+        tags: &[String],
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<FunctionEntry>, Option<String>)> {
+        let matched = self.functions.iter().filter(|entry| {
+            !entry.deleted
+                && (entry.public || &entry.owner == user_id)
+                && category.map_or(true, |category| entry.category.as_deref() == Some(category))
+                && tags.iter().all(|tag| entry.tags.contains(tag))
+        });
+        let (entries, next_cursor) = signer.paginate(
+            matched,
+            |entry| (entry.seq, entry.function_id.to_string()),
+            cursor,
+            limit,
+        )?;
+        let functions = entries
+            .into_iter()
+            .map(|entry| {
+                FunctionEntry::new(
+                    entry.function_id.clone(),
+                    entry.name.clone(),
+                    entry.category.clone(),
+                    entry.tags.clone(),
+                )
+            })
+            .collect();
+        Ok((functions, next_cursor))
+    }
+}
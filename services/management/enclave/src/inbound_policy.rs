@@ -0,0 +1,155 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Builds the attestation policy of each inbound service from its audited
+// measurement and the policy configured for it. The maximum age of reports is
+// extended by the clock skew tolerance.
+
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use std::time::Duration;
+use teaclave_attestation::verifier::AttestationPolicy;
+use teaclave_config::InboundAttestationPolicy;
+use teaclave_types::{EnclaveInfo, SgxMeasurement};
+
+fn parse_measurement(value: &str) -> Result<SgxMeasurement> {
+    let bytes = hex::decode(value).map_err(|_| anyhow!("invalid measurement {}", value))?;
+    let mut measurement: SgxMeasurement = Default::default();
+    if bytes.len() != measurement.len() {
+        bail!("invalid measurement {}", value);
+    }
+    measurement.copy_from_slice(&bytes);
+    Ok(measurement)
+}
+
+fn parse_measurements(values: &[String]) -> Result<Vec<SgxMeasurement>> {
+    values.iter().map(|v| parse_measurement(v)).collect()
+}
+
+pub(crate) fn inbound_attestation_policies(
+    enclave_info: &EnclaveInfo,
+    services: &[&str],
+    config: &HashMap<String, InboundAttestationPolicy>,
+    clock_skew_tolerance: Duration,
+) -> Result<Vec<AttestationPolicy>> {
+    if let Some(name) = config
+        .keys()
+        .find(|name| !services.contains(&name.as_str()))
+    {
+        bail!("{} is not an inbound service", name);
+    }
+
+    services
+        .iter()
+        .map(|service| {
+            let attr = enclave_info
+                .get_enclave_attr(service)
+                .ok_or_else(|| anyhow!("cannot get enclave attribute of {}", service))?;
+            let mut policy = AttestationPolicy::new(vec![attr]);
+            if let Some(config) = config.get(*service) {
+                policy = policy
+                    .mr_enclaves(parse_measurements(&config.mr_enclaves)?)
+                    .mr_signers(parse_measurements(&config.mr_signers)?);
+                if let Some(seconds) = config.max_report_age_seconds {
+                    policy =
+                        policy.max_report_age(Duration::from_secs(seconds) + clock_skew_tolerance);
+                }
+            }
+            Ok(policy)
+        })
+        .collect()
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use teaclave_types::EnclaveMeasurement;
+
+    pub fn test_inbound_attestation_policies() {
+        let frontend = EnclaveMeasurement::new([1; 32], [2; 32]);
+        let scheduler = EnclaveMeasurement::new([3; 32], [2; 32]);
+        let mut measurements = HashMap::new();
+        measurements.insert("frontend".to_string(), frontend);
+        measurements.insert("scheduler".to_string(), scheduler);
+        let enclave_info = EnclaveInfo { measurements };
+        let services = ["frontend", "scheduler"];
+
+        let mut config = HashMap::new();
+        config.insert(
+            "scheduler".to_string(),
+            InboundAttestationPolicy {
+                max_report_age_seconds: Some(60),
+                ..Default::default()
+            },
+        );
+        let policies =
+            inbound_attestation_policies(&enclave_info, &services, &config, Duration::default())
+                .unwrap();
+        let accepted = |m: &EnclaveMeasurement, age: u64| {
+            policies
+                .iter()
+                .any(|p| p.verify(&m.mr_signer, &m.mr_enclave, Duration::from_secs(age)))
+        };
+        // Only the scheduler is held to the freshness requirement.
+        assert!(accepted(&frontend, 120));
+        assert!(accepted(&scheduler, 30));
+        assert!(!accepted(&scheduler, 120));
+        assert!(!accepted(&EnclaveMeasurement::new([4; 32], [2; 32]), 0));
+
+        // Reports just past the maximum age are accepted within the tolerance.
+        let policies = inbound_attestation_policies(
+            &enclave_info,
+            &services,
+            &config,
+            Duration::from_secs(60),
+        )
+        .unwrap();
+        let accepted = |m: &EnclaveMeasurement, age: u64| {
+            policies
+                .iter()
+                .any(|p| p.verify(&m.mr_signer, &m.mr_enclave, Duration::from_secs(age)))
+        };
+        assert!(accepted(&scheduler, 90));
+        assert!(!accepted(&scheduler, 150));
+
+        config.insert(
+            "frontend".to_string(),
+            InboundAttestationPolicy {
+                mr_signers: vec![hex::encode([5u8; 32])],
+                ..Default::default()
+            },
+        );
+        let policies =
+            inbound_attestation_policies(&enclave_info, &services, &config, Duration::default())
+                .unwrap();
+        assert!(!policies[0].verify(
+            &frontend.mr_signer,
+            &frontend.mr_enclave,
+            Duration::from_secs(0)
+        ));
+
+        config.insert("storage".to_string(), InboundAttestationPolicy::default());
+        assert!(inbound_attestation_policies(
+            &enclave_info,
+            &services,
+            &config,
+            Duration::default()
+        )
+        .is_err());
+    }
+}
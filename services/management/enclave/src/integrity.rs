@@ -0,0 +1,61 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::ocall::handle_file_request;
+use anyhow::{anyhow, bail, Result};
+use std::path::Path;
+use std::prelude::v1::*;
+use teaclave_types::{
+    FileAgentRequest, FileCrypto, HandleFileCommand, HandleFileInfo, StagedFileInfo,
+    TeaclaveOutputFile,
+};
+use uuid::Uuid;
+
+static VERIFICATION_BASE_DIR: &str = "/tmp/teaclave_cmac_verification/";
+
+// Downloads the stored object of an output with the file agent and checks
+// that its content still matches the recorded cmac, so that corruption of the
+// storage is detected before the output is consumed.
+pub(crate) fn verify_output_cmac(
+    output: &TeaclaveOutputFile,
+    fusion_base: impl AsRef<Path>,
+) -> Result<()> {
+    let cmac = output.cmac.ok_or_else(|| anyhow!("output has no cmac"))?;
+    let crypto = match output.crypto_info {
+        FileCrypto::TeaclaveFile128(crypto) => crypto,
+        _ => bail!("unsupported crypto of output"),
+    };
+
+    std::untrusted::fs::create_dir_all(VERIFICATION_BASE_DIR)?;
+    let local = Path::new(VERIFICATION_BASE_DIR).join(Uuid::new_v4().to_string());
+    let info = HandleFileInfo::new(&local, &output.url);
+    let request = FileAgentRequest::new(
+        HandleFileCommand::Download,
+        vec![info],
+        fusion_base.as_ref(),
+    );
+    let result = handle_file_request(request).and_then(|_| {
+        // Nodes of the protected file are authenticated as they are read
+        let mut file = StagedFileInfo::new(&local, crypto, cmac).create_readable_io()?;
+        std::io::copy(&mut file, &mut std::io::sink())?;
+        Ok(())
+    });
+    if local.exists() {
+        std::untrusted::fs::remove_file(&local)?;
+    }
+    result
+}
@@ -25,8 +25,9 @@ extern crate log;
 use anyhow::{anyhow, Result};
 
 use std::prelude::v1::*;
+use std::time::Duration;
 
-use teaclave_attestation::{verifier, AttestationConfig, RemoteAttestation};
+use teaclave_attestation::{verifier, RemoteAttestation};
 use teaclave_binder::proto::{
     ECallCommand, FinalizeEnclaveInput, FinalizeEnclaveOutput, InitEnclaveInput, InitEnclaveOutput,
     StartServiceInput, StartServiceOutput,
@@ -42,47 +43,91 @@ use teaclave_rpc::server::SgxTrustedTlsServer;
 use teaclave_service_enclave_utils::{create_trusted_storage_endpoint, ServiceEnclave};
 use teaclave_types::{EnclaveInfo, TeeServiceError, TeeServiceResult};
 
+mod access_policy;
+mod archive;
+mod correlation;
+mod crypto_keys;
+mod deadline;
+mod file_index;
+mod function_check;
+mod function_index;
+mod inbound_policy;
+mod integrity;
+mod metrics;
+mod ocall;
+mod output_access;
+mod pagination;
+mod payload_encryption;
+mod presign;
+mod reaper;
+mod result_callback;
 mod service;
+mod storage;
+mod task_index;
 
 fn start_service(config: &RuntimeConfig) -> Result<()> {
+    let root_ca_certs = config.attestation.root_ca_certs(AS_ROOT_CA_CERT);
     let listen_address = config.internal_endpoints.management.listen_address;
-    let attestation_config = AttestationConfig::from_teaclave_config(&config)?;
-    let attested_tls_config = RemoteAttestation::new(attestation_config)
+    let attested_tls_config = RemoteAttestation::from_teaclave_config(&config)?
         .generate_and_endorse()?
         .attested_tls_config()
         .ok_or_else(|| anyhow!("cannot get attested TLS config"))?;
     let enclave_info = EnclaveInfo::verify_and_new(
         &config.audit.enclave_info_bytes,
-        AUDITOR_PUBLIC_KEYS,
+        &config.audit.auditor_public_keys(AUDITOR_PUBLIC_KEYS),
         &config.audit.auditor_signatures_bytes,
     )?;
-    let accepted_enclave_attrs: Vec<teaclave_types::EnclaveAttr> = MANAGEMENT_INBOUND_SERVICES
-        .iter()
-        .map(|service| match enclave_info.get_enclave_attr(service) {
-            Some(attr) => Ok(attr),
-            None => Err(anyhow!("cannot get enclave attribute of {}", service)),
-        })
-        .collect::<Result<_>>()?;
-    let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
-        .attestation_report_verifier(
-        accepted_enclave_attrs,
-        AS_ROOT_CA_CERT,
-        verifier::universal_quote_verifier,
+    let inbound_policies = inbound_policy::inbound_attestation_policies(
+        &enclave_info,
+        MANAGEMENT_INBOUND_SERVICES,
+        &config.management.inbound_attestation,
+        Duration::from_secs(config.server.clock_skew_tolerance_seconds),
     )?;
+    let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
+        .attestation_policy_verifier(
+            inbound_policies,
+            &root_ca_certs,
+            verifier::universal_quote_verifier,
+        )?
+        .client_cert_fingerprints(&config.management.client_cert_fingerprints);
     let mut server =
         SgxTrustedTlsServer::<TeaclaveManagementResponse, TeaclaveManagementRequest>::new(
             listen_address,
             server_config,
-        );
+        )
+        .shutdown_timeout(Duration::from_secs(config.server.shutdown_timeout_seconds))
+        .max_connections(config.server.max_connections);
 
     let storage_service_endpoint = create_trusted_storage_endpoint(
         &config.internal_endpoints.storage.advertised_address,
         &enclave_info,
-        AS_ROOT_CA_CERT,
+        &root_ca_certs,
         verifier::universal_quote_verifier,
     );
 
-    let service = service::TeaclaveManagementService::new(storage_service_endpoint)?;
+    let replica_endpoints = config
+        .management
+        .storage_replicas
+        .iter()
+        .map(|address| {
+            create_trusted_storage_endpoint(
+                address,
+                &enclave_info,
+                &root_ca_certs,
+                verifier::universal_quote_verifier,
+            )
+        })
+        .collect();
+
+    let service = service::TeaclaveManagementService::new(
+        storage_service_endpoint,
+        replica_endpoints,
+        config.management.clone(),
+        &config.storage.key_namespace,
+    )?
+    .fusion_base(&config.mount.fusion_base_dir)
+    .clock_skew_tolerance(config.server.clock_skew_tolerance_seconds);
+    reaper::start(service.clone());
     match server.start(service) {
         Ok(_) => (),
         Err(e) => {
@@ -130,10 +175,89 @@ pub mod tests {
     pub fn run_tests() -> bool {
         run_tests!(
             service::tests::handle_input_file,
+            service::tests::handle_sealed_crypto_info,
+            service::tests::handle_input_file_metadata,
+            service::tests::handle_unique_iv,
             service::tests::handle_output_file,
+            service::tests::handle_output_presigned_url,
+            service::tests::handle_output_access_token,
+            service::tests::handle_redact_output_owners,
+            service::tests::handle_cmac_verification,
+            service::tests::handle_partial_output,
+            service::tests::handle_output_cmac_integrity,
+            service::tests::handle_consumption_grace_period,
+            service::tests::handle_file_index,
+            service::tests::handle_max_url_length,
+            service::tests::handle_inline_input_file,
+            service::tests::handle_assign_data_response,
+            service::tests::handle_lazy_input,
+            service::tests::handle_delete_input_file,
+            service::tests::handle_binary_argument,
+            service::tests::handle_optional_input,
+            service::tests::handle_exclusive_inputs,
+            service::tests::handle_sampled_input,
+            service::tests::handle_storage_error_cause,
+            service::tests::handle_degraded_storage,
+            service::tests::handle_skip_unchanged_inputs,
+            service::tests::handle_reproduce_task,
+            service::tests::handle_storage_metrics,
+            service::tests::handle_input_from_task_log,
+            service::tests::handle_storage_namespace,
+            service::tests::handle_storage_replicas,
+            service::tests::handle_compressed_values,
             service::tests::handle_function,
+            service::tests::handle_unique_function_names,
+            service::tests::handle_mesapy_denied_patterns,
+            service::tests::handle_function_payload_hash,
+            service::tests::handle_function_payload_encryption,
+            service::tests::handle_soft_delete_function,
+            service::tests::handle_reap_deleted_functions_with_failures,
+            service::tests::handle_register_function_bundle,
+            service::tests::handle_crypto_keys,
             service::tests::handle_task,
+            service::tests::handle_get_tasks,
+            service::tests::handle_pending_approvals,
+            service::tests::handle_invalid_arguments,
+            service::tests::handle_argument_limits,
+            service::tests::handle_task_approval_deadline,
+            service::tests::handle_reap_expired_tasks,
+            service::tests::handle_fusion_output_retention,
+            service::tests::handle_min_fusion_owners,
+            service::tests::handle_max_fusion_outputs,
+            service::tests::handle_soft_limit_warnings,
+            service::tests::handle_fusion_output_recipient_keys,
+            service::tests::handle_access_policy,
+            service::tests::handle_request_deadline,
+            service::tests::handle_cancel_task,
+            service::tests::handle_cancel_all_tasks,
+            service::tests::handle_task_events,
+            service::tests::handle_export_audit_log,
+            service::tests::handle_result_archive,
+            service::tests::handle_reap_expired_fusion_outputs,
+            service::tests::handle_temporary_fusion_output,
+            service::tests::handle_result_callback,
+            service::tests::handle_task_history,
+            service::tests::handle_retry_task,
+            service::tests::handle_submit_task_graph,
+            service::tests::handle_restart_recovery,
+            service::tests::handle_export_import_task,
+            service::tests::handle_export_import_catalog,
+            service::tests::handle_active_task_limit,
+            service::tests::handle_function_rate_limit,
+            service::tests::handle_task_resource_limits,
+            service::tests::handle_default_executor,
+            service::tests::handle_approval_excludes_creator,
+            service::tests::handle_estimate_task,
+            service::tests::handle_list_tasks_by_label,
+            service::tests::handle_list_functions_by_category,
             service::tests::handle_staged_task,
+            access_policy::tests::test_access_policy,
+            metrics::tests::test_storage_metrics,
+            inbound_policy::tests::test_inbound_attestation_policies,
+            pagination::tests::test_paginate_with_insertions,
+            pagination::tests::test_forged_cursor,
+            pagination::tests::test_cursor_with_shared_secret,
+            presign::tests::test_presign_get,
         )
     }
 }
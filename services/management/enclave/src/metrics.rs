@@ -0,0 +1,163 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Latency of storage operations of the service. Samples are aggregated with
+// atomics, so that recording them doesn't add contention between requests.
+
+use std::prelude::v1::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use std::untrusted::time::SystemTimeEx;
+use teaclave_proto::teaclave_frontend_service::StorageLatency;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum StorageOperation {
+    Get,
+    Put,
+    Enqueue,
+    Dequeue,
+}
+
+impl StorageOperation {
+    const ALL: [StorageOperation; 4] = [
+        StorageOperation::Get,
+        StorageOperation::Put,
+        StorageOperation::Enqueue,
+        StorageOperation::Dequeue,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            StorageOperation::Get => "get",
+            StorageOperation::Put => "put",
+            StorageOperation::Enqueue => "enqueue",
+            StorageOperation::Dequeue => "dequeue",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LatencyStats {
+    count: AtomicU64,
+    total_micros: AtomicU64,
+    min_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+impl Default for LatencyStats {
+    fn default() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            total_micros: AtomicU64::new(0),
+            min_micros: AtomicU64::new(u64::MAX),
+            max_micros: AtomicU64::new(0),
+        }
+    }
+}
+
+// Stores the value if it is preferred to the current one by the predicate.
+fn update_if(value: &AtomicU64, sample: u64, preferred: impl Fn(u64, u64) -> bool) {
+    let mut current = value.load(Ordering::Relaxed);
+    while preferred(sample, current) {
+        match value.compare_exchange_weak(current, sample, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => break,
+            Err(actual) => current = actual,
+        }
+    }
+}
+
+impl LatencyStats {
+    fn record(&self, micros: u64) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_micros.fetch_add(micros, Ordering::Relaxed);
+        update_if(&self.min_micros, micros, |sample, min| sample < min);
+        update_if(&self.max_micros, micros, |sample, max| sample > max);
+    }
+
+    // Fields are read separately, so a snapshot taken while samples are
+    // recorded may be slightly inconsistent.
+    fn snapshot(&self, operation: StorageOperation) -> StorageLatency {
+        let count = self.count.load(Ordering::Relaxed);
+        let total_micros = self.total_micros.load(Ordering::Relaxed);
+        StorageLatency {
+            operation: operation.name().to_string(),
+            count,
+            min_micros: if count == 0 {
+                0
+            } else {
+                self.min_micros.load(Ordering::Relaxed)
+            },
+            max_micros: self.max_micros.load(Ordering::Relaxed),
+            avg_micros: total_micros.checked_div(count).unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StorageMetrics {
+    stats: [LatencyStats; 4],
+}
+
+impl StorageMetrics {
+    fn stats(&self, operation: StorageOperation) -> &LatencyStats {
+        &self.stats[operation as usize]
+    }
+
+    pub(crate) fn record(&self, operation: StorageOperation, elapsed: Duration) {
+        self.stats(operation).record(elapsed.as_micros() as u64);
+    }
+
+    // Runs the operation and records how long it takes.
+    pub(crate) fn time<T>(&self, operation: StorageOperation, f: impl FnOnce() -> T) -> T {
+        let start = SystemTime::now();
+        let result = f();
+        let elapsed = SystemTime::now().duration_since(start).unwrap_or_default();
+        self.record(operation, elapsed);
+        result
+    }
+
+    pub(crate) fn snapshot(&self) -> Vec<StorageLatency> {
+        StorageOperation::ALL
+            .iter()
+            .map(|operation| self.stats(*operation).snapshot(*operation))
+            .collect()
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_storage_metrics() {
+        let metrics = StorageMetrics::default();
+        metrics.record(StorageOperation::Put, Duration::from_micros(10));
+        metrics.record(StorageOperation::Put, Duration::from_micros(30));
+        metrics.record(StorageOperation::Get, Duration::from_micros(5));
+
+        let snapshot = metrics.snapshot();
+        let put = snapshot.iter().find(|l| l.operation == "put").unwrap();
+        assert_eq!(put.count, 2);
+        assert_eq!(put.min_micros, 10);
+        assert_eq!(put.max_micros, 30);
+        assert_eq!(put.avg_micros, 20);
+        let get = snapshot.iter().find(|l| l.operation == "get").unwrap();
+        assert_eq!(get.count, 1);
+        let dequeue = snapshot.iter().find(|l| l.operation == "dequeue").unwrap();
+        assert_eq!(dequeue.count, 0);
+        assert_eq!(dequeue.min_micros, 0);
+    }
+}
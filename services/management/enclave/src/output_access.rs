@@ -0,0 +1,54 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Tokens with which owners of an output let other users get it. Only digests
+// of tokens are stored, so that tokens cannot be recovered from the storage.
+
+use rand::RngCore;
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use teaclave_types::{ExternalID, OwnerList, UserID};
+
+const OUTPUT_ACCESS_TOKENS_PREFIX: &str = "output-access-tokens";
+const OUTPUT_ACCESS_TOKEN_LEN: usize = 32;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct OutputAccessToken {
+    pub(crate) data_id: ExternalID,
+    pub(crate) issuer: UserID,
+    pub(crate) expires_at: u64,
+}
+
+impl OutputAccessToken {
+    // A token is only valid for the output it is bound to, until it expires
+    // and while its issuer still owns the output.
+    pub(crate) fn grants(&self, data_id: &ExternalID, owners: &OwnerList, now: u64) -> bool {
+        &self.data_id == data_id && now < self.expires_at && owners.contains(&self.issuer)
+    }
+}
+
+pub(crate) fn generate_output_access_token() -> String {
+    let mut token = vec![0u8; OUTPUT_ACCESS_TOKEN_LEN];
+    rand::thread_rng().fill_bytes(&mut token);
+    hex::encode(token)
+}
+
+pub(crate) fn output_access_token_storage_key(token: &str) -> Vec<u8> {
+    let digest = digest::digest(&digest::SHA256, token.as_bytes());
+    format!("{}-{}", OUTPUT_ACCESS_TOKENS_PREFIX, hex::encode(digest)).into_bytes()
+}
@@ -0,0 +1,181 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use anyhow::{anyhow, Result};
+use rand::RngCore;
+use ring::hmac;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::prelude::v1::*;
+
+// Maximum number of items returned in one page
+pub(crate) const MAX_PAGE_SIZE: usize = 100;
+pub(crate) const CURSOR_SECRET_LEN: usize = 32;
+
+// Signs cursors of listings with a secret of the service, so that users can
+// only continue listings from cursors returned to them.
+#[derive(Clone)]
+pub(crate) struct CursorSigner {
+    key: hmac::Key,
+}
+
+impl CursorSigner {
+    // Cursors signed with a random secret are invalid once the service
+    // restarts.
+    pub(crate) fn new() -> Self {
+        Self::from_secret(&Self::random_secret())
+    }
+
+    // Cursors signed with the same secret stay valid across restarts and
+    // replicas of the service.
+    pub(crate) fn from_secret(secret: &[u8]) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret),
+        }
+    }
+
+    pub(crate) fn random_secret() -> Vec<u8> {
+        let mut secret = vec![0; CURSOR_SECRET_LEN];
+        rand::thread_rng().fill_bytes(&mut secret);
+        secret
+    }
+
+    // A cursor is the hex encoded key of the last returned item followed by
+    // its signature.
+    fn encode<K: Serialize>(&self, key: &K) -> Result<String> {
+        let payload = serde_json::to_vec(key)?;
+        let tag = hmac::sign(&self.key, &payload);
+        Ok(format!("{}.{}", hex::encode(&payload), hex::encode(tag)))
+    }
+
+    fn decode<K: DeserializeOwned>(&self, cursor: &str) -> Result<K> {
+        let mut parts = cursor.splitn(2, '.');
+        let payload = hex::decode(parts.next().unwrap_or_default())?;
+        let tag = hex::decode(parts.next().ok_or_else(|| anyhow!("malformed cursor"))?)?;
+        hmac::verify(&self.key, &payload, &tag).map_err(|_| anyhow!("invalid cursor"))?;
+        Ok(serde_json::from_slice(&payload)?)
+    }
+
+    // Returns at most `limit` items ordered by their keys, which follow the
+    // last item of the previous page identified by `cursor`, and the cursor
+    // of the next page if there are more items. Keys must be unique and must
+    // not change, so that items added or removed between calls neither shift
+    // the pages nor cause duplicates or gaps.
+    pub(crate) fn paginate<T, K, F>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+        key_of: F,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<T>, Option<String>)>
+    where
+        K: Ord + Serialize + DeserializeOwned,
+        F: Fn(&T) -> K,
+    {
+        let limit = if limit == 0 || limit > MAX_PAGE_SIZE {
+            MAX_PAGE_SIZE
+        } else {
+            limit
+        };
+        let last_key: Option<K> = match cursor {
+            Some(cursor) => Some(self.decode(cursor)?),
+            None => None,
+        };
+
+        let mut items: Vec<(K, T)> = items
+            .into_iter()
+            .map(|item| (key_of(&item), item))
+            .filter(|(key, _)| last_key.as_ref().map_or(true, |last| key > last))
+            .collect();
+        items.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let has_more = items.len() > limit;
+        items.truncate(limit);
+        let next_cursor = match items.last() {
+            Some((key, _)) if has_more => Some(self.encode(key)?),
+            _ => None,
+        };
+        let page = items.into_iter().map(|(_, item)| item).collect();
+        Ok((page, next_cursor))
+    }
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_paginate_with_insertions() {
+        let signer = CursorSigner::new();
+        let mut items: Vec<u64> = (0..5).collect();
+        let mut listed = Vec::new();
+
+        let (page, cursor) = signer.paginate(items.clone(), |i| *i, None, 2).unwrap();
+        listed.extend(page);
+        assert!(cursor.is_some());
+
+        // items added and removed between pages do not shift the listing
+        items.push(5);
+        items.retain(|i| *i != 0);
+        let (page, cursor) = signer
+            .paginate(items.clone(), |i| *i, cursor.as_deref(), 2)
+            .unwrap();
+        listed.extend(page);
+
+        items.push(6);
+        let (page, cursor) = signer
+            .paginate(items.clone(), |i| *i, cursor.as_deref(), 10)
+            .unwrap();
+        listed.extend(page);
+        assert!(cursor.is_none());
+        assert_eq!(listed, vec![0, 1, 2, 3, 4, 5, 6]);
+    }
+
+    pub fn test_forged_cursor() {
+        let signer = CursorSigner::new();
+        let (_, cursor) = signer.paginate(vec![1u64, 2, 3], |i| *i, None, 1).unwrap();
+        let cursor = cursor.unwrap();
+        assert!(signer
+            .paginate(vec![1u64, 2, 3], |i| *i, Some(&cursor), 1)
+            .is_ok());
+
+        let forged = format!(
+            "{}{}",
+            hex::encode(b"2"),
+            &cursor[cursor.find('.').unwrap()..]
+        );
+        assert!(signer
+            .paginate(vec![1u64, 2, 3], |i| *i, Some(&forged), 1)
+            .is_err());
+        let other = CursorSigner::new();
+        assert!(other
+            .paginate(vec![1u64, 2, 3], |i| *i, Some(&cursor), 1)
+            .is_err());
+    }
+
+    pub fn test_cursor_with_shared_secret() {
+        let secret = CursorSigner::random_secret();
+        let signer = CursorSigner::from_secret(&secret);
+        let (_, cursor) = signer.paginate(vec![1u64, 2, 3], |i| *i, None, 1).unwrap();
+
+        // e.g., signed before a restart or by another replica
+        let restarted = CursorSigner::from_secret(&secret);
+        let (page, _) = restarted
+            .paginate(vec![1u64, 2, 3], |i| *i, cursor.as_deref(), 1)
+            .unwrap();
+        assert_eq!(page, vec![2]);
+    }
+}
@@ -0,0 +1,55 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Payloads of functions are encrypted before they are stored if a payload
+// encryption key is configured. The iv stored with each function marks its
+// payload as encrypted, so functions stored before the key is configured are
+// still readable.
+
+use crate::crypto_keys::key_encryption_key;
+use anyhow::Result;
+use std::prelude::v1::*;
+use teaclave_crypto::AesGcm256Key;
+use teaclave_types::Function;
+
+pub(crate) fn encrypt_function_payload(
+    hex_key: Option<&str>,
+    function: &mut Function,
+) -> Result<()> {
+    if hex_key.is_none() || function.is_payload_encrypted() {
+        return Ok(());
+    }
+    let key = key_encryption_key(hex_key)?;
+    // A fresh iv for every encryption
+    let iv = AesGcm256Key::random().iv;
+    AesGcm256Key::new(&key, &iv)?.encrypt(&mut function.payload)?;
+    function.payload_iv = iv.to_vec();
+    Ok(())
+}
+
+pub(crate) fn decrypt_function_payload(
+    hex_key: Option<&str>,
+    function: &mut Function,
+) -> Result<()> {
+    if !function.is_payload_encrypted() {
+        return Ok(());
+    }
+    let key = key_encryption_key(hex_key)?;
+    AesGcm256Key::new(&key, &function.payload_iv)?.decrypt(&mut function.payload)?;
+    function.payload_iv.clear();
+    Ok(())
+}
@@ -0,0 +1,56 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::service::TeaclaveManagementService;
+use std::prelude::v1::*;
+use std::thread;
+use std::time::Duration;
+
+// Interval between two rounds of housekeeping.
+const REAPER_INTERVAL_SECS: u64 = 30;
+
+// Spawns a background thread that periodically cleans up tasks and data
+// managed by the service, e.g., cancels tasks after their approval deadline
+// removes expired fusion outputs and deleted functions, archives aged results,
+// prunes the task history, removes consumed temporary fusion outputs and
+// delivers results of terminated tasks to callbacks.
+pub(crate) fn start(service: TeaclaveManagementService) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(REAPER_INTERVAL_SECS));
+        if let Err(e) = service.reap_expired_tasks() {
+            log::warn!("Reaper: failed to reap expired tasks: {:?}", e);
+        }
+        if let Err(e) = service.reap_expired_fusion_outputs() {
+            log::warn!("Reaper: failed to reap expired fusion outputs: {:?}", e);
+        }
+        if let Err(e) = service.reap_deleted_functions() {
+            log::warn!("Reaper: failed to reap deleted functions: {:?}", e);
+        }
+        if let Err(e) = service.reap_aged_results() {
+            log::warn!("Reaper: failed to archive aged results: {:?}", e);
+        }
+        if let Err(e) = service.reap_task_history() {
+            log::warn!("Reaper: failed to prune task history: {:?}", e);
+        }
+        if let Err(e) = service.reap_temporary_fusion_outputs() {
+            log::warn!("Reaper: failed to reap temporary fusion outputs: {:?}", e);
+        }
+        if let Err(e) = service.deliver_result_callbacks() {
+            log::warn!("Reaper: failed to deliver result callbacks: {:?}", e);
+        }
+    });
+}
@@ -0,0 +1,173 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+// Endpoints to which results of a user's tasks are posted when the tasks
+// finish. Results are sealed to the public key of the endpoint in the enclave,
+// since the untrusted file agent posts them. Payloads are signed with a secret
+// returned when the callback is registered, so that endpoints can authenticate
+// deliveries. Failed deliveries are retried with exponential backoff.
+
+use crate::archive::with_staging_file;
+use crate::ocall::handle_file_request;
+use anyhow::Result;
+use rand::RngCore;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::prelude::v1::*;
+use teaclave_crypto::seal_to_public_key;
+use teaclave_types::{
+    FileAgentRequest, HandleFileCommand, HandleFileInfo, Task, TaskResult, TaskStatus, UserID,
+};
+use url::Url;
+
+const RESULT_CALLBACK_PREFIX: &str = "result-callback";
+pub(crate) const CALLBACK_DELIVERY_QUEUE_KEY: &str = "callback-deliveries";
+const CALLBACK_SECRET_LEN: usize = 32;
+// Delay before the first retry, doubled by each further failed attempt
+const CALLBACK_RETRY_DELAY_SECS: u64 = 30;
+const CALLBACK_MAX_ATTEMPTS: u32 = 8;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ResultCallback {
+    pub(crate) url: Url,
+    // Hex encoded HMAC-SHA256 key
+    pub(crate) secret: String,
+    // X25519 public key of the endpoint
+    pub(crate) recipient_public_key: Vec<u8>,
+}
+
+impl ResultCallback {
+    pub(crate) fn new(url: Url, recipient_public_key: Vec<u8>) -> Self {
+        let mut secret = vec![0u8; CALLBACK_SECRET_LEN];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self {
+            url,
+            secret: hex::encode(secret),
+            recipient_public_key,
+        }
+    }
+}
+
+pub(crate) fn result_callback_storage_key(user_id: &UserID) -> Vec<u8> {
+    format!("{}-{}", RESULT_CALLBACK_PREFIX, user_id).into_bytes()
+}
+
+// Callbacks are only posted to HTTPS endpoints on allowed hosts.
+pub(crate) fn callback_allowed(url: &Url, allowed_hosts: &[String]) -> bool {
+    url.scheme() == "https"
+        && url
+            .host_str()
+            .map_or(false, |host| allowed_hosts.iter().any(|h| h == host))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ResultSummary {
+    pub(crate) task_id: String,
+    // "succeeded", "failed" or "canceled"
+    pub(crate) status: String,
+    // Hex encoded return value of a succeeded task
+    pub(crate) return_value: Option<String>,
+    pub(crate) failure: Option<String>,
+    pub(crate) timestamp: u64,
+}
+
+impl ResultSummary {
+    fn new(task: &Task, timestamp: u64) -> Self {
+        let (status, return_value, failure) = match &task.result {
+            _ if task.status == TaskStatus::Canceled => ("canceled", None, None),
+            TaskResult::Ok(outputs) => {
+                ("succeeded", Some(hex::encode(&outputs.return_value)), None)
+            }
+            TaskResult::Err(failure) => ("failed", None, Some(failure.reason.clone())),
+            _ => ("failed", None, None),
+        };
+        Self {
+            task_id: task.external_id().to_string(),
+            status: status.to_string(),
+            return_value,
+            failure,
+            timestamp,
+        }
+    }
+}
+
+// The posted body. The summary is serialized and sealed to the public key of
+// the endpoint, and the signature is the hex encoded HMAC-SHA256 of the hex
+// encoded sealed summary, so that endpoints verify it before opening.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct SignedResultSummary {
+    pub(crate) sealed_summary: String,
+    pub(crate) signature: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct CallbackDelivery {
+    pub(crate) url: Url,
+    pub(crate) body: String,
+    pub(crate) attempts: u32,
+    pub(crate) next_attempt_at: u64,
+}
+
+impl CallbackDelivery {
+    pub(crate) fn new(task: &Task, callback: &ResultCallback, now: u64) -> Result<Self> {
+        let summary = serde_json::to_vec(&ResultSummary::new(task, now))?;
+        let sealed_summary = hex::encode(seal_to_public_key(
+            &callback.recipient_public_key,
+            &summary,
+        )?);
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &hex::decode(&callback.secret)?);
+        let signature = hex::encode(hmac::sign(&key, sealed_summary.as_bytes()));
+        let body = serde_json::to_string(&SignedResultSummary {
+            sealed_summary,
+            signature,
+        })?;
+        Ok(Self {
+            url: callback.url.clone(),
+            body,
+            attempts: 0,
+            next_attempt_at: now,
+        })
+    }
+
+    pub(crate) fn is_due(&self, now: u64) -> bool {
+        now >= self.next_attempt_at
+    }
+
+    // Records a failed attempt and schedules the next one. Returns false if
+    // the delivery is given up.
+    pub(crate) fn retry_later(&mut self, now: u64) -> bool {
+        self.attempts += 1;
+        self.next_attempt_at = now + (CALLBACK_RETRY_DELAY_SECS << (self.attempts - 1));
+        self.attempts < CALLBACK_MAX_ATTEMPTS
+    }
+}
+
+// Posts the delivery with the file agent. The body only carries the sealed
+// summary, so nothing of the result is exposed outside the enclave.
+pub(crate) fn post_callback(
+    delivery: &CallbackDelivery,
+    fusion_base: impl AsRef<Path>,
+) -> Result<()> {
+    with_staging_file(|local| {
+        std::untrusted::fs::write(local, delivery.body.as_bytes())?;
+        let info = HandleFileInfo::new(local, &delivery.url);
+        let request =
+            FileAgentRequest::new(HandleFileCommand::Post, vec![info], fusion_base.as_ref());
+        handle_file_request(request)
+    })
+}
@@ -15,36 +15,162 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use crate::access_policy::AccessPolicy;
+use crate::archive::{download_archive, upload_archive};
+use crate::correlation::{set_request_correlation_id, CORRELATION_ID_METADATA_KEY};
+use crate::crypto_keys::{
+    crypto_key_storage_key, decrypt_crypto_key, encrypt_crypto_key, validate_crypto_key_name,
+};
+use crate::deadline::{remaining_time, set_request_deadline};
+use crate::file_index::{FileIndex, FileKind};
+use crate::function_check::{function_checks, FunctionCheck};
+use crate::function_index::FunctionIndex;
+use crate::integrity::verify_output_cmac;
+use crate::metrics::{StorageMetrics, StorageOperation};
+use crate::ocall::handle_file_request;
+use crate::output_access::{
+    generate_output_access_token, output_access_token_storage_key, OutputAccessToken,
+};
+use crate::pagination::{CursorSigner, MAX_PAGE_SIZE};
+use crate::payload_encryption::{decrypt_function_payload, encrypt_function_payload};
+use crate::presign::{is_s3_url, presign_get};
+use crate::result_callback::{
+    callback_allowed, post_callback, result_callback_storage_key, CallbackDelivery, ResultCallback,
+    CALLBACK_DELIVERY_QUEUE_KEY,
+};
+use crate::storage::{
+    is_unavailable, DegradedCall, DegradedStorage, NamespacedStorage, RemoteStorage,
+    ReplicatedStorage, StorageBackend,
+};
+use crate::task_index::TaskIndex;
 use anyhow::{anyhow, Result};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::path::PathBuf;
 use std::prelude::v1::*;
-use std::sync::{Arc, SgxMutex as Mutex};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::untrusted::time::SystemTimeEx;
+use teaclave_config::{CmacVerification, ManagementConfig};
+use teaclave_crypto::seal_to_public_key;
 use teaclave_proto::teaclave_frontend_service::{
-    ApproveTaskRequest, ApproveTaskResponse, AssignDataRequest, AssignDataResponse,
-    CreateTaskRequest, CreateTaskResponse, GetFunctionRequest, GetFunctionResponse,
-    GetInputFileRequest, GetInputFileResponse, GetOutputFileRequest, GetOutputFileResponse,
-    GetTaskRequest, GetTaskResponse, InvokeTaskRequest, InvokeTaskResponse,
+    AdminGetTaskRequest, AdminGetTaskResponse, ApproveTaskRequest, ApproveTaskResponse,
+    AssignDataRequest, AssignDataResponse, CancelAllTasksRequest, CancelAllTasksResponse,
+    CancelTaskRequest, CancelTaskResponse, CreateOutputAccessTokenRequest,
+    CreateOutputAccessTokenResponse, CreateTaskRequest, CreateTaskResponse, DeleteInputFileRequest,
+    DeleteInputFileResponse, DeprecateFunctionRequest, DeprecateFunctionResponse,
+    EstimateTaskRequest, EstimateTaskResponse, ExportAuditLogRequest, ExportAuditLogResponse,
+    ExportCatalogRequest, ExportCatalogResponse, ExportTaskRequest, ExportTaskResponse,
+    GenerateOutputPresignedUrlRequest, GenerateOutputPresignedUrlResponse, GetFunctionRequest,
+    GetFunctionResponse, GetInputFileRequest, GetInputFileResponse, GetMetricsRequest,
+    GetMetricsResponse, GetOutputFileRequest, GetOutputFileResponse, GetTaskReproductionRequest,
+    GetTaskReproductionResponse, GetTaskRequest, GetTaskResponse, GetTasksEntry, GetTasksRequest,
+    GetTasksResponse, ImportCatalogRequest, ImportCatalogResponse, ImportTaskRequest,
+    ImportTaskResponse, InstantiateTemplateRequest, InstantiateTemplateResponse, InvokeTaskRequest,
+    InvokeTaskResponse, LinkTasksRequest, LinkTasksResponse, ListFunctionsRequest,
+    ListFunctionsResponse, ListInputFilesRequest, ListInputFilesResponse, ListOutputFilesRequest,
+    ListOutputFilesResponse, ListTasksRequest, ListTasksResponse, RegisterCryptoKeyRequest,
+    RegisterCryptoKeyResponse, RegisterFunctionBundleRequest, RegisterFunctionBundleResponse,
     RegisterFunctionRequest, RegisterFunctionResponse, RegisterFusionOutputRequest,
     RegisterFusionOutputResponse, RegisterInputFileRequest, RegisterInputFileResponse,
-    RegisterInputFromOutputRequest, RegisterInputFromOutputResponse, RegisterOutputFileRequest,
-    RegisterOutputFileResponse,
+    RegisterInputFromOutputRequest, RegisterInputFromOutputResponse,
+    RegisterInputFromTaskLogRequest, RegisterInputFromTaskLogResponse, RegisterOutputFileRequest,
+    RegisterOutputFileResponse, RegisterTaskTemplateRequest, RegisterTaskTemplateResponse,
+    ReproduceTaskRequest, ReproduceTaskResponse, RestoreFunctionRequest, RestoreFunctionResponse,
+    RetryTaskRequest, RetryTaskResponse, RunSelfTestRequest, RunSelfTestResponse, SelfTestStage,
+    SetFunctionRateLimitRequest, SetFunctionRateLimitResponse, SetResultCallbackRequest,
+    SetResultCallbackResponse, SoftDeleteFunctionRequest, SoftDeleteFunctionResponse,
+    SubmitTaskGraphRequest, SubmitTaskGraphResponse, SubscribeEventsRequest,
+    SubscribeEventsResponse, TaskEventEntry,
 };
 use teaclave_proto::teaclave_management_service::TeaclaveManagement;
-use teaclave_proto::teaclave_storage_service::{
-    EnqueueRequest, GetRequest, PutRequest, TeaclaveStorageClient,
-};
+use teaclave_proto::teaclave_storage_service::TeaclaveStorageClient;
 use teaclave_rpc::endpoint::Endpoint;
 use teaclave_rpc::Request;
-use teaclave_service_enclave_utils::{ensure, teaclave_service};
+use teaclave_service_enclave_utils::{bail, ensure, teaclave_service};
 use teaclave_types::{
-    ExternalID, FileCrypto, Function, OwnerList, StagedTask, Storable, Task, TaskStatus,
-    TeaclaveInputFile, TeaclaveOutputFile, TeaclaveServiceResponseError,
-    TeaclaveServiceResponseResult, UserID,
+    compress_value, hashmap, resolve_task_executor, validate_fusion_owners, validate_task_graph,
+    validate_task_labels, AuditEntry, Executor, ExecutorType, ExternalID, FileAgentRequest,
+    FileAuthTag, FileCrypto, Function, FunctionArguments, FunctionBundle, FunctionCatalog,
+    FunctionManifest, FunctionOutput, HandleFileCommand, HandleFileInfo, InputFileMetadata,
+    Notification, OwnerList, ResultArchive, StagedTask, Storable, StorageFormat, Task,
+    TaskCreationError, TaskEvent, TaskExport, TaskFileOwners, TaskReproduction, TaskResourceLimits,
+    TaskResult, TaskStatus, TaskTemplate, TeaclaveInputFile, TeaclaveOutputFile,
+    TeaclaveServiceResponseError, TeaclaveServiceResponseResult, UserID, UserList, UserRole,
 };
 use thiserror::Error;
 use url::Url;
 use uuid::Uuid;
 
+const APPROVAL_DEADLINE_QUEUE_KEY: &str = "approval-deadline";
+const FUSION_OUTPUT_QUEUE_KEY: &str = "fusion-output";
+// Maximum number of tasks requested in one get_tasks request
+const MAX_TASKS_PER_REQUEST: usize = 100;
+const ACTIVE_TASKS_PREFIX: &str = "active-tasks";
+// Recent invocation timestamps of rate limited functions
+const FUNCTION_INVOCATIONS_PREFIX: &str = "function-invocations";
+// Invoked tasks checked by the recovery pass when the service starts
+const TRANSIENT_TASKS_QUEUE_KEY: &str = "transient-tasks";
+// Fingerprints of (key, iv) pairs of files registered by a user
+const CRYPTO_NONCES_PREFIX: &str = "crypto-nonces";
+const DELETED_FUNCTION_QUEUE_KEY: &str = "deleted-functions";
+// Offset in the task event log up to which finished results are archived
+const RESULT_ARCHIVE_OFFSET_KEY: &str = "result-archive-offset";
+// Secret signing listing cursors if none is configured
+const CURSOR_SECRET_KEY: &str = "cursor-secret";
+// Offset in the task event log up to which terminated tasks are added to the
+// task history
+const TASK_HISTORY_OFFSET_KEY: &str = "task-history-offset";
+const TASK_HISTORY_QUEUE_KEY: &str = "task-history";
+// Offset in the task event log up to which consumers of temporary fusion
+// outputs are checked
+const TEMPORARY_OUTPUT_OFFSET_KEY: &str = "temporary-output-offset";
+// Offset in the task event log up to which results of terminated tasks are
+// queued for delivery to callbacks
+const RESULT_CALLBACK_OFFSET_KEY: &str = "result-callback-offset";
+
+// Terminated tasks tracked by the history pruning sweep, in the order of
+// termination
+#[derive(Debug, Deserialize, Serialize)]
+struct TaskHistoryRecord {
+    task_id: ExternalID,
+    creator: UserID,
+    terminated_at: u64,
+}
+
+// Fusion outputs tracked by the retention sweep
+#[derive(Debug, Deserialize, Serialize)]
+struct FusionOutputRecord {
+    data_id: ExternalID,
+    created_at: u64,
+}
+
+#[derive(Debug, PartialEq)]
+enum RetentionAction {
+    // keep tracking the output
+    Keep,
+    // the output is consumed as an input, stop tracking it
+    Release,
+    // the output is expired and should be removed
+    Reap,
+}
+
+fn fusion_retention_action(
+    record: &FusionOutputRecord,
+    consumed: bool,
+    now: u64,
+    max_age: u64,
+) -> RetentionAction {
+    if consumed {
+        RetentionAction::Release
+    } else if now > record.created_at + max_age {
+        RetentionAction::Reap
+    } else {
+        RetentionAction::Keep
+    }
+}
+
 #[derive(Error, Debug)]
 enum ServiceError {
     #[error("invalid request")]
@@ -52,23 +178,69 @@ enum ServiceError {
     #[error("data error")]
     DataError,
     #[error("storage error")]
-    StorageError,
+    StorageError(#[source] anyhow::Error),
     #[error("permission denied")]
     PermissionDenied,
     #[error("bad task")]
     BadTask,
+    #[error("bad task: {0}")]
+    InvalidTask(TaskCreationError),
+    #[error("approval window closed")]
+    ApprovalWindowClosed,
+    #[error("self test disabled")]
+    SelfTestDisabled,
+    #[error("too many active tasks, retry later")]
+    TooManyActiveTasks,
+    #[error("function rate limit exceeded, retry later")]
+    FunctionRateLimited,
+    #[error("output is not yet consumable, retry later")]
+    NotYetConsumable,
+    #[error("presigned url disabled")]
+    PresignedUrlDisabled,
+    #[error("output access token disabled")]
+    OutputAccessTokenDisabled,
+    #[error("fusion data must be owned by at least {0} users")]
+    TooFewFusionOwners(usize),
+    #[error("at most {0} unconsumed fusion outputs are allowed per user")]
+    TooManyFusionOutputs(usize),
+    #[error("at most {0} unconsumed input files are allowed per user")]
+    TooManyInputFiles(usize),
+    #[error("integrity check of stored data failed")]
+    IntegrityError,
+    #[error("key and iv are already used by another file")]
+    IvReused,
+    #[error("function {0} already exists")]
+    DuplicateFunctionName(String),
+    #[error("deadline exceeded")]
+    DeadlineExceeded,
+    #[error("cannot delete the remote file")]
+    RemoteDeleteFailed,
+    #[error("function rejected: {0}")]
+    FunctionRejected(String),
+    #[error("callback endpoint is not allowed")]
+    CallbackNotAllowed,
+    #[error("input {0} is not available yet, retry later")]
+    LazyInputUnavailable(String),
 }
 
 impl From<ServiceError> for TeaclaveServiceResponseError {
     fn from(error: ServiceError) -> Self {
-        TeaclaveServiceResponseError::RequestError(error.to_string())
+        TeaclaveServiceResponseError::request_error_with_causes(&error)
     }
 }
 
 #[teaclave_service(teaclave_management_service, TeaclaveManagement, ServiceError)]
 #[derive(Clone)]
 pub(crate) struct TeaclaveManagementService {
-    storage_client: Arc<Mutex<TeaclaveStorageClient>>,
+    storage: Arc<dyn StorageBackend>,
+    config: ManagementConfig,
+    cursor_signer: CursorSigner,
+    access_policy: AccessPolicy,
+    fusion_base: PathBuf,
+    clock_skew_tolerance: u64,
+    storage_metrics: Arc<StorageMetrics>,
+    degraded_storage: Option<Arc<DegradedStorage>>,
+    function_checks: Vec<Arc<dyn FunctionCheck>>,
 }
 
 impl TeaclaveManagement for TeaclaveManagementService {
@@ -79,17 +251,84 @@ impl TeaclaveManagement for TeaclaveManagementService {
     ) -> TeaclaveServiceResponseResult<RegisterInputFileResponse> {
         let user_id = self.get_request_user_id(request.metadata())?;
         let request = request.message;
-        let input_file = TeaclaveInputFile::new(
-            request.url,
-            request.cmac,
-            request.crypto_info,
-            vec![user_id],
+        let expected_size = request.expected_size;
+        let recipient_public_key = request.recipient_public_key;
+        let source = request.source;
+        let lazy = request.lazy;
+        let crypto_info = match &request.crypto_key_name {
+            Some(name) => self.read_crypto_key(&user_id, name)?,
+            None => request.crypto_info,
+        };
+        // X25519 public keys are 32 bytes
+        ensure!(
+            recipient_public_key
+                .as_ref()
+                .map_or(true, |public_key| public_key.len() == 32),
+            ServiceError::InvalidRequest
+        );
+        ensure!(
+            request.url.as_str().len() <= self.config.max_url_length,
+            ServiceError::DataError
+        );
+        // Inline data is always available
+        ensure!(
+            !(lazy && request.inline_data.is_some()),
+            ServiceError::InvalidRequest
         );
+        let mut size = expected_size;
+        let mut input_file = match request.inline_data {
+            Some(data) => {
+                ensure!(
+                    data.len() <= self.config.max_inline_input_size,
+                    ServiceError::InvalidRequest
+                );
+                ensure!(
+                    expected_size.map_or(true, |size| size == data.len() as u64),
+                    ServiceError::InvalidRequest
+                );
+                size = Some(data.len() as u64);
+                TeaclaveInputFile::new_inline(data, request.cmac, crypto_info, vec![user_id])
+            }
+            None => TeaclaveInputFile::new(request.url, request.cmac, crypto_info, vec![user_id]),
+        };
+        let mut warnings = Vec::new();
+        if let Some(max_inputs) = self.config.max_input_files_per_user {
+            let inputs = self
+                .read_file_index(&user_id)
+                .map_err(ServiceError::StorageError)?
+                .outstanding_inputs();
+            ensure!(
+                inputs < max_inputs,
+                ServiceError::TooManyInputFiles(max_inputs)
+            );
+            warnings.extend(self.soft_limit_warning(
+                "unconsumed input files",
+                inputs + 1,
+                max_inputs,
+            ));
+        }
+        self.claim_crypto_nonce(&user_id, &input_file.crypto_info)?;
+        input_file.expected_size = expected_size;
+        input_file.recipient_public_key = recipient_public_key;
+        input_file.lazy = lazy;
+        input_file.metadata = InputFileMetadata {
+            size,
+            created_at: current_timestamp(),
+            source,
+        };
 
         self.write_to_db(&input_file)
-            .map_err(|_| ServiceError::StorageError)?;
+            .map_err(ServiceError::StorageError)?;
+        self.update_file_index(&input_file.owner, |index| {
+            index.add(
+                FileKind::Input,
+                input_file.external_id(),
+                current_timestamp(),
+            )
+        })
+        .map_err(ServiceError::StorageError)?;
 
-        let response = RegisterInputFileResponse::new(input_file.external_id());
+        let response = RegisterInputFileResponse::new(input_file.external_id()).warnings(warnings);
         Ok(response)
     }
 
@@ -100,15 +339,53 @@ impl TeaclaveManagement for TeaclaveManagementService {
     ) -> TeaclaveServiceResponseResult<RegisterOutputFileResponse> {
         let user_id = self.get_request_user_id(request.metadata())?;
         let request = request.message;
-        let output_file = TeaclaveOutputFile::new(request.url, request.crypto_info, vec![user_id]);
+        let crypto_info = match &request.crypto_key_name {
+            Some(name) => self.read_crypto_key(&user_id, name)?,
+            None => request.crypto_info,
+        };
+        ensure!(
+            request.url.as_str().len() <= self.config.max_url_length,
+            ServiceError::DataError
+        );
+        self.claim_crypto_nonce(&user_id, &crypto_info)?;
+        let output_file = TeaclaveOutputFile::new(request.url, crypto_info, vec![user_id]);
 
         self.write_to_db(&output_file)
-            .map_err(|_| ServiceError::StorageError)?;
+            .map_err(ServiceError::StorageError)?;
+        self.update_file_index(&output_file.owner, |index| {
+            index.add(
+                FileKind::Output,
+                output_file.external_id(),
+                current_timestamp(),
+            )
+        })
+        .map_err(ServiceError::StorageError)?;
 
         let response = RegisterOutputFileResponse::new(output_file.external_id());
         Ok(response)
     }
 
+    // access control: keys are only used by the user who registers them
+    fn register_crypto_key(
+        &self,
+        request: Request<RegisterCryptoKeyRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterCryptoKeyResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+        validate_crypto_key_name(&request.name).map_err(|_| ServiceError::InvalidRequest)?;
+
+        let value = encrypt_crypto_key(
+            self.config.crypto_key_encryption_key.as_deref(),
+            &request.crypto_info,
+        )
+        .map_err(|_| ServiceError::InvalidRequest)?;
+        self.storage
+            .put(&crypto_key_storage_key(&user_id, &request.name), &value)
+            .map_err(ServiceError::StorageError)?;
+
+        Ok(RegisterCryptoKeyResponse)
+    }
+
     // access control: user_id in owner_list
     fn register_fusion_output(
         &self,
@@ -118,16 +395,55 @@ impl TeaclaveManagement for TeaclaveManagementService {
 
         let owner_list = request.message.owner_list;
         ensure!(
-            owner_list.len() > 1 && owner_list.contains(&user_id),
+            owner_list.contains(&user_id),
             ServiceError::PermissionDenied
         );
+        let min_owners = self.config.min_fusion_owners.max(2);
+        ensure!(
+            owner_list.len() >= min_owners,
+            ServiceError::TooFewFusionOwners(min_owners)
+        );
+        if let Some(max_outputs) = self.config.max_fusion_outputs_per_user {
+            let index = self
+                .read_file_index(&user_id)
+                .map_err(ServiceError::StorageError)?;
+            ensure!(
+                index.outstanding_fusion_outputs() < max_outputs,
+                ServiceError::TooManyFusionOutputs(max_outputs)
+            );
+        }
+
+        // Keys are given for all owners or none. X25519 public keys are 32
+        // bytes.
+        let recipient_public_keys = request.message.recipient_public_keys;
+        ensure!(
+            recipient_public_keys.is_empty()
+                || (recipient_public_keys.len() == owner_list.len()
+                    && recipient_public_keys.iter().all(|(owner, public_key)| {
+                        owner_list.contains(&UserID::from(owner.as_str())) && public_key.len() == 32
+                    })),
+            ServiceError::InvalidRequest
+        );
 
-        let output_file = self
+        let mut output_file = self
             .create_fusion_data(owner_list)
             .map_err(|_| ServiceError::DataError)?;
+        output_file.recipient_public_keys = recipient_public_keys;
+        output_file.temporary = request.message.temporary;
 
         self.write_to_db(&output_file)
-            .map_err(|_| ServiceError::StorageError)?;
+            .map_err(ServiceError::StorageError)?;
+
+        let record = FusionOutputRecord {
+            data_id: output_file.external_id(),
+            created_at: current_timestamp(),
+        };
+        self.update_file_index(&output_file.owner, |index| {
+            index.add_fusion_output(record.data_id.clone(), record.created_at)
+        })
+        .map_err(ServiceError::StorageError)?;
+        let value = serde_json::to_vec(&record).map_err(|_| ServiceError::DataError)?;
+        self.enqueue_bytes_to_db(FUSION_OUTPUT_QUEUE_KEY.as_bytes(), value)?;
 
         let response = RegisterFusionOutputResponse::new(output_file.external_id());
         Ok(response)
@@ -135,33 +451,120 @@ impl TeaclaveManagement for TeaclaveManagementService {
 
     // access control:
     // 1) user_id in output.owner
-    // 2) cmac != none
+    // 2) cmac != none, unless cmac verification is permissive
     fn register_input_from_output(
         &self,
         request: Request<RegisterInputFromOutputRequest>,
     ) -> TeaclaveServiceResponseResult<RegisterInputFromOutputResponse> {
         let user_id = self.get_request_user_id(request.metadata())?;
 
+        let output_id = request.message.data_id;
         let output: TeaclaveOutputFile = self
-            .read_from_db(&request.message.data_id)
+            .read_from_db(&output_id)
             .map_err(|_| ServiceError::PermissionDenied)?;
 
         ensure!(
             output.owner.contains(&user_id),
             ServiceError::PermissionDenied
         );
+        if let (Some(grace_period), Some(finalized_at)) = (
+            self.config.consumption_grace_period_seconds,
+            output.finalized_at,
+        ) {
+            ensure!(
+                current_timestamp() + self.clock_skew_tolerance >= finalized_at + grace_period,
+                ServiceError::NotYetConsumable
+            );
+        }
+        self.verify_stored_output(&output)?;
 
-        let input =
-            TeaclaveInputFile::from_output(output).map_err(|_| ServiceError::PermissionDenied)?;
+        let mut input = match self.config.cmac_verification {
+            CmacVerification::Strict => TeaclaveInputFile::from_output(output)
+                .map_err(|_| ServiceError::PermissionDenied)?,
+            CmacVerification::Permissive => {
+                if output.cmac.is_none() {
+                    log::warn!(
+                        "RegisterInputFromOutput: cmac of {} is missing, verification deferred",
+                        output.external_id().to_string()
+                    );
+                }
+                TeaclaveInputFile::from_output_unverified(output)
+            }
+        };
+        input.metadata = InputFileMetadata {
+            size: None,
+            created_at: current_timestamp(),
+            source: Some(output_id.to_string()),
+        };
 
         self.write_to_db(&input)
-            .map_err(|_| ServiceError::StorageError)?;
+            .map_err(ServiceError::StorageError)?;
+        self.update_file_index(&input.owner, |index| {
+            index.add(FileKind::Input, input.external_id(), current_timestamp());
+            index.mark_consumed(FileKind::Output, &output_id);
+        })
+        .map_err(ServiceError::StorageError)?;
 
         let response = RegisterInputFromOutputResponse::new(input.external_id());
         Ok(response)
     }
 
-    // access control: output_file.owner contains user_id
+    // access control:
+    // 1) task.participants.contains(&user_id)
+    // 2) the task is finished and its log is stored
+    // the log is registered as an inline input owned by the user
+    fn register_input_from_task_log(
+        &self,
+        request: Request<RegisterInputFromTaskLogRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterInputFromTaskLogResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let task_id = request.message.task_id;
+
+        let task: Task = self
+            .read_from_db(&task_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        ensure!(
+            task.participants.contains(&user_id),
+            ServiceError::PermissionDenied
+        );
+        ensure!(
+            task.status == TaskStatus::Finished && task.log_stored,
+            ServiceError::BadTask
+        );
+
+        let value = self
+            .read_bytes_from_db(task.log_key())
+            .map_err(ServiceError::StorageError)?;
+        let lines: Vec<String> =
+            serde_json::from_slice(&value).map_err(|_| ServiceError::DataError)?;
+        let data = lines.join("\n").into_bytes();
+
+        let mut input = TeaclaveInputFile::new_inline(
+            data,
+            FileAuthTag::default(),
+            FileCrypto::Raw,
+            vec![user_id],
+        );
+        input.metadata = InputFileMetadata {
+            size: input.inline_data.as_ref().map(|data| data.len() as u64),
+            created_at: current_timestamp(),
+            source: Some(task_id.to_string()),
+        };
+
+        self.write_to_db(&input)
+            .map_err(ServiceError::StorageError)?;
+        self.update_file_index(&input.owner, |index| {
+            index.add(FileKind::Input, input.external_id(), current_timestamp())
+        })
+        .map_err(ServiceError::StorageError)?;
+
+        let response = RegisterInputFromTaskLogResponse::new(input.external_id());
+        Ok(response)
+    }
+
+    // access control: output_file.owner contains user_id or the request
+    // carries a valid access token of the output
     fn get_output_file(
         &self,
         request: Request<GetOutputFileRequest>,
@@ -172,13 +575,116 @@ impl TeaclaveManagement for TeaclaveManagementService {
             .read_from_db(&request.message.data_id)
             .map_err(|_| ServiceError::PermissionDenied)?;
 
+        if !output_file.owner.contains(&user_id) {
+            let token = request
+                .message
+                .access_token
+                .as_ref()
+                .ok_or(ServiceError::PermissionDenied)?;
+            ensure!(
+                self.config.enable_output_access_tokens,
+                ServiceError::OutputAccessTokenDisabled
+            );
+            let value = self
+                .read_bytes_from_db(output_access_token_storage_key(token))
+                .map_err(|_| ServiceError::PermissionDenied)?;
+            let token: OutputAccessToken =
+                serde_json::from_slice(&value).map_err(|_| ServiceError::DataError)?;
+            ensure!(
+                token.grants(
+                    &request.message.data_id,
+                    &output_file.owner,
+                    self.skewed_timestamp()
+                ),
+                ServiceError::PermissionDenied
+            );
+            self.audit(
+                &self.get_request_correlation_id(request.metadata()),
+                "GetOutputFile",
+                format!(
+                    "user:{} output:{} token issuer:{}",
+                    user_id,
+                    request.message.data_id.to_string(),
+                    token.issuer
+                ),
+            );
+        }
+        self.verify_stored_output(&output_file)?;
+
+        let mut response = GetOutputFileResponse::new(output_file.owner, output_file.cmac);
+        if self.config.redact_output_owners {
+            response = response.redact_owners(&user_id);
+        }
+        if let Some(public_key) = output_file.recipient_public_keys.get(&user_id.to_string()) {
+            let crypto_info = serde_json::to_vec(&output_file.crypto_info)
+                .map_err(|_| ServiceError::DataError)?;
+            let sealed = seal_to_public_key(public_key, &crypto_info)
+                .map_err(|_| ServiceError::InvalidRequest)?;
+            response = response.sealed_crypto_info(sealed);
+        }
+        Ok(response)
+    }
+
+    // access control:
+    // 1) input_file.owner == [user_id]
+    // 2) the remote file is only deleted if the input is not assigned to any
+    //    task
+    fn delete_input_file(
+        &self,
+        request: Request<DeleteInputFileRequest>,
+    ) -> TeaclaveServiceResponseResult<DeleteInputFileResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let request = request.message;
+
+        let input_file: TeaclaveInputFile = self
+            .read_from_db(&request.data_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        // Shared inputs are not deleted on behalf of the other owners
         ensure!(
-            output_file.owner.contains(&user_id),
+            input_file.owner.contains(&user_id) && input_file.owner.len() == 1,
             ServiceError::PermissionDenied
         );
 
-        let response = GetOutputFileResponse::new(output_file.owner, output_file.cmac);
-        Ok(response)
+        // Inline inputs have no remote file
+        if request.delete_remote && input_file.inline_data.is_none() {
+            let index = self
+                .read_file_index(&user_id)
+                .map_err(ServiceError::StorageError)?;
+            ensure!(
+                !index.is_consumed(FileKind::Input, &request.data_id),
+                ServiceError::InvalidRequest
+            );
+            let info = HandleFileInfo::new("", &input_file.url);
+            let file_request =
+                FileAgentRequest::new(HandleFileCommand::Delete, vec![info], &self.fusion_base);
+            handle_file_request(file_request).map_err(|e| {
+                log::warn!(
+                    "[{}] DeleteInputFile: cannot delete remote file of {}: {:?}",
+                    correlation_id,
+                    request.data_id.to_string(),
+                    e
+                );
+                ServiceError::RemoteDeleteFailed
+            })?;
+        }
+
+        log::info!(
+            "[{}] DeleteInputFile: {} delete_remote:{}",
+            correlation_id,
+            request.data_id.to_string(),
+            request.delete_remote
+        );
+
+        self.delete_from_db(&request.data_id)
+            .map_err(ServiceError::StorageError)?;
+        self.update_file_index(&input_file.owner, |index| {
+            index.remove(FileKind::Input, &request.data_id)
+        })
+        .map_err(ServiceError::StorageError)?;
+
+        Ok(DeleteInputFileResponse)
     }
 
     // access control: input_file.owner contains user_id
@@ -197,7 +703,15 @@ impl TeaclaveManagement for TeaclaveManagementService {
             ServiceError::PermissionDenied
         );
 
-        let response = GetInputFileResponse::new(input_file.owner, input_file.cmac);
+        let mut response = GetInputFileResponse::new(input_file.owner, input_file.cmac)
+            .metadata(input_file.metadata);
+        if let Some(public_key) = &input_file.recipient_public_key {
+            let crypto_info =
+                serde_json::to_vec(&input_file.crypto_info).map_err(|_| ServiceError::DataError)?;
+            let sealed = seal_to_public_key(public_key, &crypto_info)
+                .map_err(|_| ServiceError::InvalidRequest)?;
+            response = response.sealed_crypto_info(sealed);
+        }
         Ok(response)
     }
 
@@ -208,17 +722,69 @@ impl TeaclaveManagement for TeaclaveManagementService {
     ) -> TeaclaveServiceResponseResult<RegisterFunctionResponse> {
         let user_id = self.get_request_user_id(request.metadata())?;
 
-        let function = Function::from(request.message)
+        let mut function = Function::from(request.message)
             .id(Uuid::new_v4())
             .owner(user_id);
 
-        self.write_to_db(&function)
-            .map_err(|_| ServiceError::StorageError)?;
+        if let Some(category) = &function.category {
+            let categories = &self.config.function_categories;
+            ensure!(
+                categories.is_empty() || categories.contains(category),
+                ServiceError::InvalidRequest
+            );
+        }
+        self.check_function_name(&function.owner, &function.name)?;
+        self.check_function(&function)?;
+
+        self.write_function(&mut function)
+            .map_err(ServiceError::StorageError)?;
+        self.update_function_index(|index| index.add(&function))
+            .map_err(ServiceError::StorageError)?;
 
-        let response = RegisterFunctionResponse::new(function.external_id());
+        let response = RegisterFunctionResponse::new(function.external_id())
+            .payload_hash(function.payload_hash);
         Ok(response)
     }
 
+    // access control: the bundle is signed by a configured publisher
+    // verified functions are registered as public
+    fn register_function_bundle(
+        &self,
+        request: Request<RegisterFunctionBundleRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterFunctionBundleResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+
+        let bundle = FunctionBundle::from(request.message);
+        let mut function = bundle
+            .verify_and_open(&self.config.function_publisher_keys_bytes)
+            .map_err(|e| {
+                log::warn!("Rejected function bundle: {:?}", e);
+                ServiceError::PermissionDenied
+            })?
+            .id(Uuid::new_v4())
+            .owner(user_id);
+
+        if let Some(category) = &function.category {
+            let categories = &self.config.function_categories;
+            ensure!(
+                categories.is_empty() || categories.contains(category),
+                ServiceError::InvalidRequest
+            );
+        }
+        self.check_function_name(&function.owner, &function.name)?;
+        self.check_function(&function)?;
+
+        self.write_function(&mut function)
+            .map_err(ServiceError::StorageError)?;
+        self.update_function_index(|index| index.add(&function))
+            .map_err(ServiceError::StorageError)?;
+
+        Ok(RegisterFunctionBundleResponse::new(
+            function.external_id(),
+            function.payload_hash,
+        ))
+    }
+
     // access control: function.public || function.owner == user_id
     fn get_function(
         &self,
@@ -226,15 +792,23 @@ impl TeaclaveManagement for TeaclaveManagementService {
     ) -> TeaclaveServiceResponseResult<GetFunctionResponse> {
         let user_id = self.get_request_user_id(request.metadata())?;
 
-        let function: Function = self
-            .read_from_db(&request.message.function_id)
-            .map_err(|_| ServiceError::PermissionDenied)?;
+        let (function, storage_degraded) = self.track_degraded(|service| {
+            service
+                .read_function(&request.message.function_id)
+                .map_err(|_| ServiceError::PermissionDenied.into())
+        })?;
 
         ensure!(
-            (function.public || function.owner == user_id),
+            (function.public || function.owner == user_id) && !function.is_deleted(),
             ServiceError::PermissionDenied
         );
 
+        // Functions registered before payload hashes are stored
+        let payload_hash = if function.payload_hash.is_empty() {
+            Function::hash_payload(&function.payload)
+        } else {
+            function.payload_hash
+        };
         let response = GetFunctionResponse {
             name: function.name,
             description: function.description,
@@ -245,10 +819,162 @@ impl TeaclaveManagement for TeaclaveManagementService {
             arguments: function.arguments,
             inputs: function.inputs,
             outputs: function.outputs,
+            deprecated: function.deprecated,
+            replacement: function.replacement,
+            return_schema: function.return_schema,
+            payload_hash,
+            category: function.category,
+            tags: function.tags,
+            verified: function.verified,
+            storage_degraded,
         };
         Ok(response)
     }
 
+    // access control: function.public || function.owner == user_id
+    fn list_functions(
+        &self,
+        request: Request<ListFunctionsRequest>,
+    ) -> TeaclaveServiceResponseResult<ListFunctionsResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let index = self
+            .read_function_index()
+            .map_err(ServiceError::StorageError)?;
+        let (functions, next_cursor) = index
+            .page(
+                &self.cursor_signer,
+                &user_id,
+                request.category.as_deref(),
+                &request.tags,
+                request.cursor.as_deref(),
+                request.limit,
+            )
+            .map_err(|_| ServiceError::InvalidRequest)?;
+
+        Ok(ListFunctionsResponse::new(functions, next_cursor))
+    }
+
+    // access control: function.owner == user_id
+    fn deprecate_function(
+        &self,
+        request: Request<DeprecateFunctionRequest>,
+    ) -> TeaclaveServiceResponseResult<DeprecateFunctionResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let request = request.message;
+
+        let mut function: Function = self
+            .read_from_db(&request.function_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        ensure!(function.owner == user_id, ServiceError::PermissionDenied);
+
+        if let Some(replacement) = &request.replacement {
+            let _: Function = self
+                .read_from_db(replacement)
+                .map_err(|_| ServiceError::InvalidRequest)?;
+        }
+
+        function.deprecated = request.deprecated;
+        function.replacement = if request.deprecated {
+            request.replacement
+        } else {
+            None
+        };
+
+        log::info!(
+            "[{}] DeprecateFunction: {} deprecated:{}",
+            correlation_id,
+            function.external_id().to_string(),
+            function.deprecated
+        );
+
+        self.write_to_db(&function)
+            .map_err(ServiceError::StorageError)?;
+
+        Ok(DeprecateFunctionResponse)
+    }
+
+    // access control: function.owner == user_id
+    fn soft_delete_function(
+        &self,
+        request: Request<SoftDeleteFunctionRequest>,
+    ) -> TeaclaveServiceResponseResult<SoftDeleteFunctionResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let function_id = request.message.function_id;
+
+        let mut function: Function = self
+            .read_from_db(&function_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        ensure!(function.owner == user_id, ServiceError::PermissionDenied);
+        ensure!(!function.is_deleted(), ServiceError::InvalidRequest);
+
+        let now = current_timestamp();
+        function.deleted_at = Some(now);
+
+        log::info!(
+            "[{}] SoftDeleteFunction: {}",
+            correlation_id,
+            function_id.to_string()
+        );
+
+        self.write_to_db(&function)
+            .map_err(ServiceError::StorageError)?;
+        self.update_function_index(|index| index.set_deleted(&function_id, true))
+            .map_err(ServiceError::StorageError)?;
+        // Deleted functions are tracked by the reaper
+        self.enqueue_bytes_to_db(
+            DELETED_FUNCTION_QUEUE_KEY.as_bytes(),
+            function_id.to_bytes(),
+        )?;
+
+        let restorable_until = now + self.config.function_retention_seconds;
+        Ok(SoftDeleteFunctionResponse::new(restorable_until))
+    }
+
+    // access control: function.owner == user_id
+    fn restore_function(
+        &self,
+        request: Request<RestoreFunctionRequest>,
+    ) -> TeaclaveServiceResponseResult<RestoreFunctionResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let function_id = request.message.function_id;
+
+        let mut function: Function = self
+            .read_from_db(&function_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        ensure!(function.owner == user_id, ServiceError::PermissionDenied);
+        let deleted_at = function.deleted_at.ok_or(ServiceError::InvalidRequest)?;
+        // The function is removed by the next sweep after the window closes
+        ensure!(
+            self.skewed_timestamp() < deleted_at + self.config.function_retention_seconds,
+            ServiceError::InvalidRequest
+        );
+
+        // The name may have been taken by another function in the meantime
+        self.check_function_name(&function.owner, &function.name)?;
+        function.deleted_at = None;
+
+        log::info!(
+            "[{}] RestoreFunction: {}",
+            correlation_id,
+            function_id.to_string()
+        );
+
+        self.write_to_db(&function)
+            .map_err(ServiceError::StorageError)?;
+        self.update_function_index(|index| index.set_deleted(&function_id, false))
+            .map_err(ServiceError::StorageError)?;
+
+        Ok(RestoreFunctionResponse)
+    }
+
     // access control: none
     // when a task is created, following rules will be verified:
     // 1) arugments match function definition
@@ -259,29 +985,34 @@ impl TeaclaveManagement for TeaclaveManagementService {
         request: Request<CreateTaskRequest>,
     ) -> TeaclaveServiceResponseResult<CreateTaskResponse> {
         let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
 
         let request = request.message;
 
-        let function: Function = self
-            .read_from_db(&request.function_id)
-            .map_err(|_| ServiceError::PermissionDenied)?;
-
-        let task = Task::new(
-            user_id,
-            request.executor,
-            request.function_arguments,
-            request.inputs_ownership,
-            request.outputs_ownership,
-            function,
-        )
-        .map_err(|_| ServiceError::BadTask)?;
+        let ((task, function_deprecated), storage_degraded) = self.track_degraded(|service| {
+            let new_task = service.new_task(&user_id, &correlation_id, request)?;
+            service.store_new_task(&new_task.0)?;
+            Ok(new_task)
+        })?;
 
-        log::info!("CreateTask: {:?}", task);
+        let arguments = &task.function_arguments;
+        let warnings = vec![
+            self.soft_limit_warning(
+                "function arguments",
+                arguments.inner().len(),
+                self.config.max_argument_count,
+            ),
+            self.soft_limit_warning(
+                "bytes of function arguments",
+                arguments.total_size(),
+                self.config.max_arguments_size,
+            ),
+        ];
 
-        self.write_to_db(&task)
-            .map_err(|_| ServiceError::StorageError)?;
-
-        Ok(CreateTaskResponse::new(task.external_id()))
+        Ok(CreateTaskResponse::new(task.external_id())
+            .function_deprecated(function_deprecated)
+            .storage_degraded(storage_degraded)
+            .warnings(warnings.into_iter().flatten().collect()))
     }
 
     // access control: task.participants.contains(&user_id)
@@ -290,6 +1021,39 @@ impl TeaclaveManagement for TeaclaveManagementService {
         request: Request<GetTaskRequest>,
     ) -> TeaclaveServiceResponseResult<GetTaskResponse> {
         let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+
+        let (task, storage_degraded) = self.track_degraded(|service| {
+            let mut task: Task = service
+                .read_from_db(&request.message.task_id)
+                .map_err(|_| ServiceError::PermissionDenied)?;
+
+            ensure!(
+                task.participants.contains(&user_id),
+                ServiceError::PermissionDenied
+            );
+
+            service
+                .read_result_tags_chunks(&mut task)
+                .map_err(ServiceError::StorageError)?;
+            Ok(task)
+        })?;
+
+        log::info!("[{}] GetTask: {:?}", correlation_id, task);
+
+        let mut response = to_task_response(task);
+        response.storage_degraded = storage_degraded;
+        Ok(response)
+    }
+
+    // access control:
+    // 1) task.participants.contains(&user_id)
+    // 2) task.status == Approved
+    fn estimate_task(
+        &self,
+        request: Request<EstimateTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<EstimateTaskResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
 
         let task: Task = self
             .read_from_db(&request.message.task_id)
@@ -299,24 +1063,28 @@ impl TeaclaveManagement for TeaclaveManagementService {
             task.participants.contains(&user_id),
             ServiceError::PermissionDenied
         );
+        ensure!(task.status == TaskStatus::Approved, ServiceError::BadTask);
 
-        log::info!("GetTask: {:?}", task);
+        // Sizes of inputs are recorded at registration, sizes of outputs are
+        // unknown until the task finishes.
+        let mut response = EstimateTaskResponse::default();
+        for (_, input) in task.assigned_inputs.iter() {
+            match input.metadata.size.or(input.expected_size) {
+                Some(size) => response.input_bytes += size,
+                None => response.unsized_files += 1,
+            }
+        }
+        response.unsized_files += task.assigned_outputs.keys().len() as u32;
+        for key in self
+            .staged_queue_keys()
+            .map_err(ServiceError::StorageError)?
+        {
+            response.queued_tasks += self
+                .storage
+                .queue_len(&key)
+                .map_err(ServiceError::StorageError)?;
+        }
 
-        let response = GetTaskResponse {
-            task_id: task.external_id(),
-            creator: task.creator,
-            function_id: task.function_id,
-            function_owner: task.function_owner,
-            function_arguments: task.function_arguments,
-            inputs_ownership: task.inputs_ownership,
-            outputs_ownership: task.outputs_ownership,
-            participants: task.participants,
-            approved_users: task.approved_users,
-            assigned_inputs: task.assigned_inputs.external_ids(),
-            assigned_outputs: task.assigned_outputs.external_ids(),
-            result: task.result,
-            status: task.status,
-        };
         Ok(response)
     }
 
@@ -330,70 +1098,141 @@ impl TeaclaveManagement for TeaclaveManagementService {
     //    * inputs_ownership or outputs_ownership contains the data name
     //    * input file: OwnerList match input_file.owner
     //    * output file: OwnerList match output_file.owner
+    //    * output file: crypto schema match the function output if specified
     fn assign_data(
         &self,
         request: Request<AssignDataRequest>,
     ) -> TeaclaveServiceResponseResult<AssignDataResponse> {
         let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
 
         let request = request.message;
 
         let mut task: Task = self
             .read_from_db(&request.task_id)
             .map_err(|_| ServiceError::PermissionDenied)?;
+        let old_status = task.status;
 
         ensure!(
             task.participants.contains(&user_id),
             ServiceError::PermissionDenied
         );
 
+        let mut input_owners = Vec::new();
         for (data_name, data_id) in request.inputs.iter() {
             let file: TeaclaveInputFile = self
                 .read_from_db(&data_id)
                 .map_err(|_| ServiceError::PermissionDenied)?;
+            input_owners.push((data_id, file.owner.clone()));
             task.assign_input(&user_id, data_name, file)
                 .map_err(|_| ServiceError::PermissionDenied)?;
         }
 
+        let function: Function = self
+            .read_from_db(&task.function_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
         for (data_name, data_id) in request.outputs.iter() {
             let file: TeaclaveOutputFile = self
                 .read_from_db(&data_id)
                 .map_err(|_| ServiceError::PermissionDenied)?;
+            let accepted = function
+                .outputs
+                .iter()
+                .filter(|output| &output.name == data_name)
+                .all(|output| output.accepts(&file.crypto_info));
+            ensure!(accepted, ServiceError::BadTask);
             task.assign_output(&user_id, data_name, file)
                 .map_err(|_| ServiceError::PermissionDenied)?;
         }
 
-        log::info!("AssignData: {:?}", task);
+        // Users can only sample inputs they assign
+        for (data_name, sample) in request.input_samples.iter() {
+            ensure!(
+                request.inputs.contains_key(data_name),
+                ServiceError::BadTask
+            );
+            task.sample_input(data_name, *sample)
+                .map_err(|_| ServiceError::BadTask)?;
+        }
+
+        log::info!("[{}] AssignData: {:?}", correlation_id, task);
+
+        self.write_task(&task, Some(old_status))
+            .map_err(ServiceError::StorageError)?;
 
-        self.write_to_db(&task)
-            .map_err(|_| ServiceError::StorageError)?;
+        for (data_id, owner) in input_owners {
+            self.update_file_index(&owner, |index| {
+                index.mark_consumed(FileKind::Input, data_id)
+            })
+            .map_err(ServiceError::StorageError)?;
+        }
+        self.notify_pending_approvals(&task);
 
-        Ok(AssignDataResponse)
+        Ok(AssignDataResponse::new(
+            task.assigned_inputs.external_ids(),
+            task.assigned_outputs.external_ids(),
+            task.status == TaskStatus::DataAssigned,
+        ))
     }
 
     // access_control:
     // 1) task status == Ready
     // 2) user_id in task.participants
+    // 3) task.approval_deadline has not passed
+    // approving a task twice is a no-op and reported as already_approved
     fn approve_task(
         &self,
         request: Request<ApproveTaskRequest>,
     ) -> TeaclaveServiceResponseResult<ApproveTaskResponse> {
         let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
 
         let request = request.message;
         let mut task: Task = self
             .read_from_db(&request.task_id)
             .map_err(|_| ServiceError::PermissionDenied)?;
+        let old_status = task.status;
+
+        ensure!(
+            task.participants.contains(&user_id),
+            ServiceError::PermissionDenied
+        );
+
+        let now = self.skewed_timestamp();
+        if !task.approved_users.contains(&user_id) && task.approval_window_closed(now) {
+            if task.cancel_if_approval_expired(now) {
+                log::info!(
+                    "[{}] ApproveTask: approval deadline passed, cancel:{:?}",
+                    correlation_id,
+                    task
+                );
+                self.write_task(&task, Some(old_status))
+                    .map_err(ServiceError::StorageError)?;
+            }
+            bail!(ServiceError::ApprovalWindowClosed);
+        }
 
-        task.approve(&user_id)
+        let newly_approved = task
+            .approve(&user_id)
             .map_err(|_| ServiceError::PermissionDenied)?;
 
-        log::info!("ApproveTask: approve:{:?}", task);
+        if !newly_approved {
+            log::info!(
+                "[{}] ApproveTask: already approved by {}",
+                correlation_id,
+                user_id
+            );
+            return Ok(ApproveTaskResponse::new(true));
+        }
+
+        log::info!("[{}] ApproveTask: approve:{:?}", correlation_id, task);
 
-        self.write_to_db(&task)
-            .map_err(|_| ServiceError::StorageError)?;
+        self.write_task(&task, Some(old_status))
+            .map_err(ServiceError::StorageError)?;
+        self.notify_pending_approvals(&task);
 
-        Ok(ApproveTaskResponse)
+        Ok(ApproveTaskResponse::new(false))
     }
 
     // access_control:
@@ -404,241 +1243,6730 @@ impl TeaclaveManagement for TeaclaveManagementService {
         request: Request<InvokeTaskRequest>,
     ) -> TeaclaveServiceResponseResult<InvokeTaskResponse> {
         let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
         let request = request.message;
 
         let mut task: Task = self
             .read_from_db(&request.task_id)
             .map_err(|_| ServiceError::PermissionDenied)?;
+        let old_status = task.status;
 
-        log::info!("InvokeTask: get task: {:?}", task);
+        log::info!("[{}] InvokeTask: get task: {:?}", correlation_id, task);
 
         // Early validation
         ensure!(task.creator == user_id, ServiceError::PermissionDenied);
+        if request.skip_if_inputs_unchanged && task.inputs_unchanged_since_last_run() {
+            log::info!(
+                "[{}] InvokeTask: inputs unchanged, skipped: {}",
+                correlation_id,
+                request.task_id.to_string()
+            );
+            return Ok(InvokeTaskResponse::new(true));
+        }
         ensure!(
             task.status == TaskStatus::Approved,
             ServiceError::PermissionDenied
         );
 
-        let function: Function = self
-            .read_from_db(&task.function_id)
+        let function = self
+            .read_function(&task.function_id)
             .map_err(|_| ServiceError::PermissionDenied)?;
 
-        log::info!("InvokeTask: get function: {:?}", function);
+        self.resolve_pending_cmacs(&mut task)?;
+        self.check_lazy_inputs(&task)?;
 
-        let staged_task = task.stage_for_running(&user_id, function)?;
+        let mut active_tasks = self
+            .read_active_tasks(&user_id)
+            .map_err(ServiceError::StorageError)?;
+        ensure!(
+            active_tasks.len() < self.config.max_active_tasks_per_user,
+            ServiceError::TooManyActiveTasks
+        );
 
-        log::info!("InvokeTask: staged task: {:?}", staged_task);
+        let function_id = function.external_id();
+        let rate_limit = function.rate_limit;
+        let mut invocations = Vec::new();
+        if let Some(rate_limit) = &rate_limit {
+            invocations = self
+                .read_function_invocations(&function_id)
+                .map_err(ServiceError::StorageError)?;
+            if !rate_limit.admit(&mut invocations, current_timestamp()) {
+                log::warn!(
+                    "[{}] InvokeTask: function {} rate limited",
+                    correlation_id,
+                    function_id.to_string()
+                );
+                bail!(ServiceError::FunctionRateLimited);
+            }
+        }
 
-        self.enqueue_to_db(StagedTask::get_queue_key().as_bytes(), &staged_task)?;
-        self.write_to_db(&task)
-            .map_err(|_| ServiceError::StorageError)?;
-        Ok(InvokeTaskResponse)
-    }
-}
+        log::info!(
+            "[{}] InvokeTask: get function: {:?}",
+            correlation_id,
+            function
+        );
 
-impl TeaclaveManagementService {
-    pub(crate) fn new(storage_service_endpoint: Endpoint) -> Result<Self> {
-        let mut i = 0;
-        let channel = loop {
-            match storage_service_endpoint.connect() {
-                Ok(channel) => break channel,
-                Err(_) => {
-                    anyhow::ensure!(i < 10, "failed to connect to storage service");
-                    log::debug!("Failed to connect to storage service, retry {}", i);
-                    i += 1;
-                }
-            }
-            std::thread::sleep(std::time::Duration::from_secs(3));
-        };
-        let storage_client = Arc::new(Mutex::new(TeaclaveStorageClient::new(channel)?));
-        let service = Self { storage_client };
+        let staged_task = task.stage_for_running(&user_id, function)?;
 
-        #[cfg(test_mode)]
-        service.add_mock_data()?;
+        log::info!(
+            "[{}] InvokeTask: staged task: {:?}, sampled: {}",
+            correlation_id,
+            staged_task,
+            task.sampled()
+        );
 
-        Ok(service)
-    }
+        self.enqueue_staged_task(&user_id, &staged_task)?;
+        self.write_task(&task, Some(old_status))
+            .map_err(ServiceError::StorageError)?;
 
-    pub fn create_fusion_data(&self, owners: impl Into<OwnerList>) -> Result<TeaclaveOutputFile> {
-        let uuid = Uuid::new_v4();
-        let url = format!("fusion:///TEACLAVE_FUSION_BASE/{}.fusion", uuid.to_string());
-        let url = Url::parse(&url).map_err(|_| anyhow!("invalid url"))?;
-        let crypto_info = FileCrypto::default();
+        self.enqueue_bytes_to_db(
+            TRANSIENT_TASKS_QUEUE_KEY.as_bytes(),
+            task.external_id().to_bytes(),
+        )?;
 
-        Ok(TeaclaveOutputFile::new(url, crypto_info, owners))
+        active_tasks.push(task.external_id());
+        self.write_active_tasks(&user_id, &active_tasks)
+            .map_err(ServiceError::StorageError)?;
+        if rate_limit.is_some() {
+            self.write_function_invocations(&function_id, &invocations)
+                .map_err(ServiceError::StorageError)?;
+        }
+        Ok(InvokeTaskResponse::new(false))
     }
 
-    fn get_request_user_id(
+    // access control:
+    // 1) user_id in participants of both tasks
+    // 2) the producer task is not finished or canceled
+    // 3) the consumer task is created and the input is not assigned
+    // 4) the owners of the output match the owners of the input
+    fn link_tasks(
         &self,
-        meta: &HashMap<String, String>,
-    ) -> TeaclaveServiceResponseResult<UserID> {
-        let user_id = meta.get("id").ok_or_else(|| ServiceError::InvalidRequest)?;
-        Ok(user_id.to_string().into())
-    }
+        request: Request<LinkTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<LinkTasksResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let request = request.message;
+
+        let mut producer: Task = self
+            .read_from_db(&request.producer_task_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+        let consumer: Task = self
+            .read_from_db(&request.consumer_task_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        ensure!(
+            producer.participants.contains(&user_id) && consumer.participants.contains(&user_id),
+            ServiceError::PermissionDenied
+        );
+
+        producer
+            .link_output(&request.output_name, &consumer, &request.input_name)
+            .map_err(|_| ServiceError::BadTask)?;
+
+        log::info!(
+            "[{}] LinkTasks: {}:{} -> {}:{}",
+            correlation_id,
+            request.producer_task_id.to_string(),
+            request.output_name,
+            request.consumer_task_id.to_string(),
+            request.input_name
+        );
+
+        self.write_to_db(&producer)
+            .map_err(ServiceError::StorageError)?;
+
+        Ok(LinkTasksResponse)
+    }
+
+    // access control: same as create_task for every node, the requester is
+    // the creator of every task
+    fn submit_task_graph(
+        &self,
+        request: Request<SubmitTaskGraphRequest>,
+    ) -> TeaclaveServiceResponseResult<SubmitTaskGraphResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let request = request.message;
+
+        ensure!(!request.nodes.is_empty(), ServiceError::InvalidRequest);
+        let names: HashSet<String> = request.nodes.keys().cloned().collect();
+        validate_task_graph(&names, &request.edges).map_err(ServiceError::InvalidTask)?;
+
+        // Tasks are stored once every task is created and linked
+        let mut tasks = HashMap::new();
+        for (name, node) in request.nodes {
+            let (task, _) = self.new_task(&user_id, &correlation_id, node)?;
+            tasks.insert(name, task);
+        }
+        for edge in request.edges.iter() {
+            let mut producer = tasks
+                .remove(&edge.producer)
+                .ok_or(ServiceError::InvalidRequest)?;
+            let consumer = tasks
+                .get(&edge.consumer)
+                .ok_or(ServiceError::InvalidRequest)?;
+            producer
+                .link_output(&edge.output_name, consumer, &edge.input_name)
+                .map_err(|_| ServiceError::BadTask)?;
+            tasks.insert(edge.producer.clone(), producer);
+        }
+
+        // The graph is submitted as a whole, so tasks already stored are
+        // removed if another one cannot be stored.
+        let mut stored = Vec::new();
+        for task in tasks.values() {
+            stored.push(task);
+            if let Err(e) = self.store_new_task(task) {
+                for task in stored {
+                    if let Err(e) = self.remove_new_task(task) {
+                        log::warn!(
+                            "[{}] SubmitTaskGraph: cannot remove task {:?}: {:?}",
+                            correlation_id,
+                            task.external_id(),
+                            e
+                        );
+                    }
+                }
+                return Err(e);
+            }
+        }
+        let task_ids: HashMap<String, ExternalID> = tasks
+            .iter()
+            .map(|(name, task)| (name.clone(), task.external_id()))
+            .collect();
+
+        log::info!(
+            "[{}] SubmitTaskGraph: {:?}, edges: {:?}",
+            correlation_id,
+            task_ids,
+            request.edges
+        );
+
+        Ok(SubmitTaskGraphResponse::new(task_ids))
+    }
+
+    // access control: user role == Admin, by the built-in access policy
+    fn admin_get_task(
+        &self,
+        request: Request<AdminGetTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<AdminGetTaskResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let task_id = request.message.task_id;
+
+        self.audit(
+            &correlation_id,
+            "AdminGetTask",
+            format!("user:{} task:{}", user_id, task_id.to_string()),
+        );
+
+        let mut task: Task = self
+            .read_from_db(&task_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        self.read_result_tags_chunks(&mut task)
+            .map_err(ServiceError::StorageError)?;
+
+        if self.config.admin_redact_arguments {
+            task.function_arguments = task.function_arguments.redacted();
+        }
+
+        Ok(AdminGetTaskResponse::new(to_task_response(task)))
+    }
+
+    // access control:
+    // 1) self test is enabled in the config
+    // 2) user role == Admin, by the built-in access policy
+    fn run_self_test(
+        &self,
+        request: Request<RunSelfTestRequest>,
+    ) -> TeaclaveServiceResponseResult<RunSelfTestResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+
+        ensure!(self.config.enable_self_test, ServiceError::SelfTestDisabled);
+
+        let stages = self.self_test(&user_id);
+        log::info!("[{}] RunSelfTest: {:?}", correlation_id, stages);
+
+        Ok(RunSelfTestResponse::new(stages))
+    }
+
+    // access control: admin only, enforced by the access policy
+    fn get_metrics(
+        &self,
+        _request: Request<GetMetricsRequest>,
+    ) -> TeaclaveServiceResponseResult<GetMetricsResponse> {
+        // Storage is degraded as long as writes can't be replayed
+        let pending_writes = match &self.degraded_storage {
+            Some(storage) => storage
+                .flush_pending_writes()
+                .map_err(ServiceError::StorageError)?,
+            None => 0,
+        };
+        Ok(GetMetricsResponse::new(self.storage_metrics.snapshot())
+            .storage_degraded(pending_writes > 0, pending_writes as u64))
+    }
+
+    // access control: only files owned by user_id are listed
+    fn list_input_files(
+        &self,
+        request: Request<ListInputFilesRequest>,
+    ) -> TeaclaveServiceResponseResult<ListInputFilesResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let index = self
+            .read_file_index(&user_id)
+            .map_err(ServiceError::StorageError)?;
+        let (files, next_cursor) = index
+            .page(
+                &self.cursor_signer,
+                FileKind::Input,
+                request.cursor.as_deref(),
+                request.limit,
+            )
+            .map_err(|_| ServiceError::InvalidRequest)?;
+
+        Ok(ListInputFilesResponse::new(files, next_cursor))
+    }
+
+    // access control: only files owned by user_id are listed
+    fn list_output_files(
+        &self,
+        request: Request<ListOutputFilesRequest>,
+    ) -> TeaclaveServiceResponseResult<ListOutputFilesResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let index = self
+            .read_file_index(&user_id)
+            .map_err(ServiceError::StorageError)?;
+        let (files, next_cursor) = index
+            .page(
+                &self.cursor_signer,
+                FileKind::Output,
+                request.cursor.as_deref(),
+                request.limit,
+            )
+            .map_err(|_| ServiceError::InvalidRequest)?;
+
+        Ok(ListOutputFilesResponse::new(files, next_cursor))
+    }
+
+    // access control: function.public || function.owner == user_id
+    fn register_task_template(
+        &self,
+        request: Request<RegisterTaskTemplateRequest>,
+    ) -> TeaclaveServiceResponseResult<RegisterTaskTemplateResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let request = request.message;
+
+        let function: Function = self
+            .read_from_db(&request.function_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+        ensure!(
+            function.public || function.owner == user_id,
+            ServiceError::PermissionDenied
+        );
+
+        let template = TaskTemplate::new()
+            .name(request.name)
+            .owner(user_id)
+            .public(request.public)
+            .function_id(request.function_id)
+            .executor(request.executor)
+            .function_arguments(request.function_arguments)
+            .inputs_ownership(request.inputs_ownership)
+            .outputs_ownership(request.outputs_ownership);
+
+        log::info!("[{}] RegisterTaskTemplate: {:?}", correlation_id, template);
+
+        self.write_to_db(&template)
+            .map_err(ServiceError::StorageError)?;
+
+        Ok(RegisterTaskTemplateResponse::new(template.external_id()))
+    }
+
+    // access control: template.public || template.owner == user_id
+    // the task is created as if requested by create_task with the overrides
+    // applied to the template
+    fn instantiate_template(
+        &self,
+        request: Request<InstantiateTemplateRequest>,
+    ) -> TeaclaveServiceResponseResult<InstantiateTemplateResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+
+        let template: TaskTemplate = self
+            .read_from_db(&request.message.template_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+        ensure!(
+            template.can_be_used_by(&user_id),
+            ServiceError::PermissionDenied
+        );
+
+        let request = request.map(|message| CreateTaskRequest {
+            function_id: template.function_id.clone(),
+            function_arguments: template.instance_arguments(message.function_arguments),
+            executor: template.executor,
+            inputs_ownership: TaskTemplate::instance_ownership(
+                &template.inputs_ownership,
+                message.inputs_ownership,
+            ),
+            outputs_ownership: TaskTemplate::instance_ownership(
+                &template.outputs_ownership,
+                message.outputs_ownership,
+            ),
+            approval_deadline_seconds: message.approval_deadline_seconds,
+            labels: HashMap::new(),
+            max_memory_mb: None,
+            max_runtime_seconds: None,
+        });
+        let response = self.create_task(request)?;
+
+        Ok(InstantiateTemplateResponse::new(
+            response.task_id,
+            response.function_deprecated,
+        ))
+    }
+
+    // access control: same as get_task for each task, tasks which do not
+    // exist or are not accessible are returned without details
+    fn get_tasks(
+        &self,
+        request: Request<GetTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<GetTasksResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+
+        let task_ids = request.message.task_ids;
+        ensure!(
+            task_ids.len() <= MAX_TASKS_PER_REQUEST,
+            ServiceError::InvalidRequest
+        );
+
+        let keys: Vec<Vec<u8>> = task_ids.iter().map(|task_id| task_id.to_bytes()).collect();
+        let values = self
+            .storage
+            .get_multi(&keys)
+            .map_err(ServiceError::StorageError)?;
+
+        let mut tasks = Vec::new();
+        for (task_id, value) in task_ids.into_iter().zip(values) {
+            let task = value
+                .filter(|_| Task::match_prefix(&task_id.prefix))
+                .and_then(|value| Task::from_slice(&value).ok())
+                .filter(|task| task.participants.contains(&user_id));
+            let task = match task {
+                Some(mut task) => {
+                    self.read_result_tags_chunks(&mut task)
+                        .map_err(ServiceError::StorageError)?;
+                    Some(to_task_response(task))
+                }
+                None => None,
+            };
+            tasks.push(GetTasksEntry::new(task_id, task));
+        }
+
+        log::info!(
+            "[{}] GetTasks: {} of {} tasks accessible",
+            correlation_id,
+            tasks.iter().filter(|entry| entry.task.is_some()).count(),
+            tasks.len()
+        );
+
+        Ok(GetTasksResponse::new(tasks))
+    }
+
+    // access control: tasks created by or shared with the user
+    fn list_tasks(
+        &self,
+        request: Request<ListTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<ListTasksResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let index = self
+            .read_task_index(&user_id)
+            .map_err(ServiceError::StorageError)?;
+        let (tasks, next_cursor) = index
+            .page(
+                &self.cursor_signer,
+                &request.labels,
+                request.cursor.as_deref(),
+                request.limit,
+            )
+            .map_err(|_| ServiceError::InvalidRequest)?;
+
+        Ok(ListTasksResponse::new(tasks, next_cursor))
+    }
+
+    // access control:
+    // 1) user_id == task.creator or user role == Admin
+    // 2) task status != Finished and != Canceled
+    // staged and running tasks are canceled once the executor acknowledges
+    fn cancel_task(
+        &self,
+        request: Request<CancelTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<CancelTaskResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let role = self.get_request_role(request.metadata());
+        let request = request.message;
+
+        let mut task: Task = self
+            .read_from_db(&request.task_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        ensure!(
+            task.creator == user_id || role == UserRole::Admin,
+            ServiceError::PermissionDenied
+        );
+
+        let old_status = task.status;
+        let canceled = task.request_cancel().map_err(|_| ServiceError::BadTask)?;
+
+        log::info!(
+            "[{}] CancelTask: canceled:{}, task:{:?}",
+            correlation_id,
+            canceled,
+            task
+        );
+
+        self.write_task(&task, Some(old_status))
+            .map_err(ServiceError::StorageError)?;
+
+        Ok(CancelTaskResponse::new(canceled))
+    }
+
+    // access control: only tasks created by the user are canceled
+    // tasks are found in the task index of the user
+    fn cancel_all_tasks(
+        &self,
+        request: Request<CancelAllTasksRequest>,
+    ) -> TeaclaveServiceResponseResult<CancelAllTasksResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+
+        let index = self
+            .read_task_index(&user_id)
+            .map_err(ServiceError::StorageError)?;
+        let mut task_ids = Vec::new();
+        let mut failed_task_ids = Vec::new();
+        for task_id in index.task_ids() {
+            let mut task: Task = match self.read_from_db(task_id) {
+                Ok(task) => task,
+                Err(e) => {
+                    log::warn!(
+                        "[{}] CancelAllTasks: cannot read task {:?}: {:?}",
+                        correlation_id,
+                        task_id,
+                        e
+                    );
+                    failed_task_ids.push(task_id.clone());
+                    continue;
+                }
+            };
+            let cancelable = match task.status {
+                TaskStatus::Created
+                | TaskStatus::DataAssigned
+                | TaskStatus::Approved
+                | TaskStatus::Staged => !task.cancel_requested,
+                _ => false,
+            };
+            if task.creator != user_id || !cancelable {
+                continue;
+            }
+
+            let old_status = task.status;
+            let result = task
+                .request_cancel()
+                .and_then(|_| self.write_task(&task, Some(old_status)));
+            if let Err(e) = result {
+                log::warn!(
+                    "[{}] CancelAllTasks: cannot cancel task {:?}: {:?}",
+                    correlation_id,
+                    task_id,
+                    e
+                );
+                failed_task_ids.push(task_id.clone());
+                continue;
+            }
+            task_ids.push(task_id.clone());
+        }
+
+        log::info!(
+            "[{}] CancelAllTasks: user:{:?}, tasks:{:?}, failed:{:?}",
+            correlation_id,
+            user_id,
+            task_ids,
+            failed_task_ids
+        );
+
+        Ok(CancelAllTasksResponse::new(task_ids, failed_task_ids))
+    }
+
+    // access control:
+    // 1) user_id == task.creator
+    // 2) task failed and has not been retried max_task_retries times
+    // 3) no output of the task has been written
+    fn retry_task(
+        &self,
+        request: Request<RetryTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<RetryTaskResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let request = request.message;
+
+        let mut task: Task = self
+            .read_from_db(&request.task_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        ensure!(task.creator == user_id, ServiceError::PermissionDenied);
+
+        // Outputs are reused by the retried task, which cannot overwrite
+        // outputs finalized by other tasks.
+        for output_id in task.assigned_outputs.external_ids().values() {
+            let output_file: TeaclaveOutputFile = self
+                .read_from_db(output_id)
+                .map_err(|_| ServiceError::DataError)?;
+            ensure!(output_file.cmac.is_none(), ServiceError::BadTask);
+        }
+
+        let max_retries = self.config.max_task_retries;
+        let old_status = task.status;
+        task.retry(&user_id, max_retries)
+            .map_err(|_| ServiceError::BadTask)?;
+
+        log::info!(
+            "[{}] RetryTask: retries:{}, task:{:?}",
+            correlation_id,
+            task.retries,
+            task
+        );
+
+        self.write_task(&task, Some(old_status))
+            .map_err(ServiceError::StorageError)?;
+
+        Ok(RetryTaskResponse::new(max_retries - task.retries))
+    }
+
+    // access control:
+    // 1) task.participants.contains(&user_id)
+    // 2) arguments are only exported for task.creator
+    fn export_task(
+        &self,
+        request: Request<ExportTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<ExportTaskResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let task: Task = self
+            .read_from_db(&request.task_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        ensure!(
+            task.participants.contains(&user_id),
+            ServiceError::PermissionDenied
+        );
+        ensure!(
+            !request.include_arguments || task.creator == user_id,
+            ServiceError::PermissionDenied
+        );
+
+        let export = TaskExport::new(&task, request.include_arguments)
+            .to_json()
+            .map_err(|_| ServiceError::DataError)?;
+
+        Ok(ExportTaskResponse::new(export))
+    }
+
+    // access control: same as create_task, the importer becomes the creator
+    fn import_task(
+        &self,
+        request: Request<ImportTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<ImportTaskResponse> {
+        let export = TaskExport::from_json(&request.message.task)
+            .map_err(|_| ServiceError::InvalidRequest)?;
+
+        let request = request.map(|message| {
+            let function_arguments = if message.function_arguments.inner().is_empty() {
+                export.function_arguments.unwrap_or_default()
+            } else {
+                message.function_arguments
+            };
+            CreateTaskRequest {
+                function_id: export.function_id,
+                function_arguments,
+                executor: export.executor,
+                inputs_ownership: export.inputs_ownership,
+                outputs_ownership: export.outputs_ownership,
+                approval_deadline_seconds: None,
+                labels: export.labels,
+                max_memory_mb: None,
+                max_runtime_seconds: None,
+            }
+        });
+        let response = self.create_task(request)?;
+
+        Ok(ImportTaskResponse::new(response.task_id))
+    }
+
+    // access control: function.public || function.owner == user_id
+    fn export_catalog(
+        &self,
+        request: Request<ExportCatalogRequest>,
+    ) -> TeaclaveServiceResponseResult<ExportCatalogResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+
+        let index = self
+            .read_function_index()
+            .map_err(ServiceError::StorageError)?;
+        let mut functions = Vec::new();
+        for function_id in index.visible(&user_id) {
+            let function: Function = self
+                .read_from_db(&function_id)
+                .map_err(ServiceError::StorageError)?;
+            functions.push(FunctionManifest::from(function));
+        }
+        let catalog = FunctionCatalog::new(functions)
+            .to_json()
+            .map_err(|_| ServiceError::DataError)?;
+
+        Ok(ExportCatalogResponse::new(catalog))
+    }
+
+    // access control: same as register_function, the importer becomes the
+    // owner of the functions
+    fn import_catalog(
+        &self,
+        request: Request<ImportCatalogRequest>,
+    ) -> TeaclaveServiceResponseResult<ImportCatalogResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let request = request.message;
+
+        let catalog = FunctionCatalog::from_json(&request.catalog)
+            .map_err(|_| ServiceError::InvalidRequest)?;
+        let mut payloads = request.payloads;
+
+        // All functions are checked before any of them is registered
+        let mut names = HashSet::new();
+        let mut functions = Vec::new();
+        for manifest in catalog.functions {
+            let exported_id = manifest.function_id.to_string();
+            let payload = payloads
+                .remove(&exported_id)
+                .ok_or(ServiceError::InvalidRequest)?;
+            let function = manifest
+                .into_function(payload)
+                .map_err(|_| ServiceError::InvalidRequest)?
+                .id(Uuid::new_v4())
+                .owner(user_id.clone());
+
+            if let Some(category) = &function.category {
+                let categories = &self.config.function_categories;
+                ensure!(
+                    categories.is_empty() || categories.contains(category),
+                    ServiceError::InvalidRequest
+                );
+            }
+            self.check_function_name(&function.owner, &function.name)?;
+            self.check_function(&function)?;
+            ensure!(
+                !self.config.unique_function_names || names.insert(function.name.clone()),
+                ServiceError::DuplicateFunctionName(function.name)
+            );
+            functions.push((exported_id, function));
+        }
+
+        let mut function_ids = HashMap::new();
+        for (exported_id, function) in &mut functions {
+            self.write_function(function)
+                .map_err(ServiceError::StorageError)?;
+            function_ids.insert(exported_id.clone(), function.external_id());
+        }
+        self.update_function_index(|index| {
+            for (_, function) in &functions {
+                index.add(function);
+            }
+        })
+        .map_err(ServiceError::StorageError)?;
+
+        Ok(ImportCatalogResponse::new(function_ids))
+    }
+
+    // access control: function.owner == user_id
+    fn set_function_rate_limit(
+        &self,
+        request: Request<SetFunctionRateLimitRequest>,
+    ) -> TeaclaveServiceResponseResult<SetFunctionRateLimitResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let request = request.message;
+
+        let mut function: Function = self
+            .read_from_db(&request.function_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        ensure!(function.owner == user_id, ServiceError::PermissionDenied);
+        if let Some(rate_limit) = &request.rate_limit {
+            ensure!(
+                rate_limit.max_invocations > 0 && rate_limit.window_seconds > 0,
+                ServiceError::InvalidRequest
+            );
+        }
+
+        function.rate_limit = request.rate_limit;
+
+        log::info!(
+            "[{}] SetFunctionRateLimit: {} rate_limit:{:?}",
+            correlation_id,
+            function.external_id().to_string(),
+            function.rate_limit
+        );
+
+        self.write_to_db(&function)
+            .map_err(ServiceError::StorageError)?;
+
+        Ok(SetFunctionRateLimitResponse)
+    }
+
+    // access control: output_file.owner contains user_id
+    fn generate_output_presigned_url(
+        &self,
+        request: Request<GenerateOutputPresignedUrlRequest>,
+    ) -> TeaclaveServiceResponseResult<GenerateOutputPresignedUrlResponse> {
+        ensure!(
+            self.config.enable_output_presigned_url,
+            ServiceError::PresignedUrlDisabled
+        );
+        let credential = self
+            .config
+            .s3
+            .as_ref()
+            .ok_or(ServiceError::PresignedUrlDisabled)?;
+        let user_id = self.get_request_user_id(request.metadata())?;
+
+        let output_file: TeaclaveOutputFile = self
+            .read_from_db(&request.message.data_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+        ensure!(
+            output_file.owner.contains(&user_id),
+            ServiceError::PermissionDenied
+        );
+        ensure!(is_s3_url(&output_file.url), ServiceError::InvalidRequest);
+
+        let expiry = self.config.output_presigned_url_expiry_seconds;
+        let now = current_timestamp();
+        let url = presign_get(&output_file.url, credential, now, expiry)
+            .map_err(|_| ServiceError::InvalidRequest)?;
+        self.audit(
+            &self.get_request_correlation_id(request.metadata()),
+            "GenerateOutputPresignedUrl",
+            format!(
+                "user:{} output:{} expiry:{}",
+                user_id,
+                request.message.data_id.to_string(),
+                expiry
+            ),
+        );
+
+        Ok(GenerateOutputPresignedUrlResponse::new(url, now + expiry))
+    }
+
+    // access control: output_file.owner contains user_id
+    fn create_output_access_token(
+        &self,
+        request: Request<CreateOutputAccessTokenRequest>,
+    ) -> TeaclaveServiceResponseResult<CreateOutputAccessTokenResponse> {
+        ensure!(
+            self.config.enable_output_access_tokens,
+            ServiceError::OutputAccessTokenDisabled
+        );
+        let user_id = self.get_request_user_id(request.metadata())?;
+
+        let output_file: TeaclaveOutputFile = self
+            .read_from_db(&request.message.data_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+        ensure!(
+            output_file.owner.contains(&user_id),
+            ServiceError::PermissionDenied
+        );
+
+        let expiry = self.config.output_access_token_expiry_seconds;
+        let expires_at = current_timestamp() + expiry;
+        let token = generate_output_access_token();
+        let record = OutputAccessToken {
+            data_id: request.message.data_id.clone(),
+            issuer: user_id.clone(),
+            expires_at,
+        };
+        let value = serde_json::to_vec(&record).map_err(|_| ServiceError::DataError)?;
+        self.storage
+            .put(&output_access_token_storage_key(&token), &value)
+            .map_err(ServiceError::StorageError)?;
+        self.audit(
+            &self.get_request_correlation_id(request.metadata()),
+            "CreateOutputAccessToken",
+            format!(
+                "user:{} output:{} expiry:{}",
+                user_id,
+                request.message.data_id.to_string(),
+                expiry
+            ),
+        );
+
+        Ok(CreateOutputAccessTokenResponse::new(token, expires_at))
+    }
+
+    // access control: events of tasks the user participates in, admins can
+    // see all events
+    fn subscribe_events(
+        &self,
+        request: Request<SubscribeEventsRequest>,
+    ) -> TeaclaveServiceResponseResult<SubscribeEventsResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let role = self.get_request_role(request.metadata());
+        let request = request.message;
+
+        let limit = if request.limit == 0 || request.limit > MAX_PAGE_SIZE {
+            MAX_PAGE_SIZE
+        } else {
+            request.limit
+        };
+        let values = self
+            .storage
+            .queue_range(TaskEvent::get_queue_key().as_bytes(), request.offset, limit)
+            .map_err(ServiceError::StorageError)?;
+        let next_offset = request.offset + values.len() as u64;
+
+        let events = values
+            .iter()
+            .filter_map(|value| TaskEvent::from_slice(value).ok())
+            .filter(|event| role == UserRole::Admin || event.participants.contains(&user_id))
+            .map(TaskEventEntry::from)
+            .collect();
+
+        Ok(SubscribeEventsResponse::new(events, next_offset))
+    }
+
+    // access control: user role == Admin, by the built-in access policy
+    fn export_audit_log(
+        &self,
+        request: Request<ExportAuditLogRequest>,
+    ) -> TeaclaveServiceResponseResult<ExportAuditLogResponse> {
+        let request = request.message;
+        ensure!(
+            request.end_time == 0 || request.start_time <= request.end_time,
+            ServiceError::InvalidRequest
+        );
+
+        let limit = if request.limit == 0 || request.limit > MAX_PAGE_SIZE {
+            MAX_PAGE_SIZE
+        } else {
+            request.limit
+        };
+        let values = self
+            .storage
+            .queue_range(
+                AuditEntry::get_queue_key().as_bytes(),
+                request.offset,
+                limit,
+            )
+            .map_err(ServiceError::StorageError)?;
+        let next_offset = request.offset + values.len() as u64;
+
+        // Entries are exported as stored, so that the checksum covers the
+        // recorded bytes.
+        let in_range = |entry: &AuditEntry| {
+            entry.timestamp >= request.start_time
+                && (request.end_time == 0 || entry.timestamp <= request.end_time)
+        };
+        let entries: Vec<String> = values
+            .into_iter()
+            .filter(|value| AuditEntry::from_slice(value).map_or(false, |e| in_range(&e)))
+            .filter_map(|value| String::from_utf8(value).ok())
+            .collect();
+        let checksum = AuditEntry::page_checksum(&entries);
+
+        Ok(ExportAuditLogResponse::new(entries, next_offset, checksum))
+    }
+
+    // access control: callbacks are set for the requesting user
+    fn set_result_callback(
+        &self,
+        request: Request<SetResultCallbackRequest>,
+    ) -> TeaclaveServiceResponseResult<SetResultCallbackResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let key = result_callback_storage_key(&user_id);
+        let request = request.message;
+
+        let url = match request.url {
+            Some(url) => url,
+            None => {
+                self.storage
+                    .delete(&key)
+                    .map_err(ServiceError::StorageError)?;
+                return Ok(SetResultCallbackResponse::new(""));
+            }
+        };
+        ensure!(
+            callback_allowed(&url, &self.config.callback_allowed_hosts),
+            ServiceError::CallbackNotAllowed
+        );
+        // X25519 public keys are 32 bytes
+        ensure!(
+            request.recipient_public_key.len() == 32,
+            ServiceError::InvalidRequest
+        );
+
+        let callback = ResultCallback::new(url, request.recipient_public_key);
+        let value = serde_json::to_vec(&callback).map_err(|_| ServiceError::DataError)?;
+        self.storage
+            .put(&key, &value)
+            .map_err(ServiceError::StorageError)?;
+
+        Ok(SetResultCallbackResponse::new(callback.secret))
+    }
+
+    // access control:
+    // 1) task.participants.contains(&user_id) or user role == Admin
+    // 2) the task finished successfully and its inputs are unchanged
+    fn reproduce_task(
+        &self,
+        request: Request<ReproduceTaskRequest>,
+    ) -> TeaclaveServiceResponseResult<ReproduceTaskResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let correlation_id = self.get_request_correlation_id(request.metadata());
+        let role = self.get_request_role(request.metadata());
+        let request = request.message;
+
+        let mut task: Task = self
+            .read_from_db(&request.task_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+        ensure!(
+            task.participants.contains(&user_id) || role == UserRole::Admin,
+            ServiceError::PermissionDenied
+        );
+        let function = self
+            .read_function(&task.function_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+
+        self.read_result_tags_chunks(&mut task)
+            .map_err(ServiceError::StorageError)?;
+        let expected_digest = match &task.result {
+            TaskResult::Ok(outputs) => outputs.digest(),
+            _ => bail!(ServiceError::BadTask),
+        };
+        let reproduction = TaskReproduction::new(&task, user_id.clone(), expected_digest);
+        let staged_task = task
+            .stage_for_reproduction(reproduction.id, function, &request.output_urls)
+            .map_err(|_| ServiceError::BadTask)?;
+
+        self.audit(
+            &correlation_id,
+            "ReproduceTask",
+            format!(
+                "user:{} task:{} reproduction:{}",
+                user_id,
+                request.task_id.to_string(),
+                reproduction.id
+            ),
+        );
+
+        self.write_to_db(&reproduction)
+            .map_err(ServiceError::StorageError)?;
+        self.enqueue_staged_task(&user_id, &staged_task)?;
+
+        Ok(ReproduceTaskResponse::new(reproduction.external_id()))
+    }
+
+    // access control: reproduction.participants.contains(&user_id) or user
+    // role == Admin
+    fn get_task_reproduction(
+        &self,
+        request: Request<GetTaskReproductionRequest>,
+    ) -> TeaclaveServiceResponseResult<GetTaskReproductionResponse> {
+        let user_id = self.get_request_user_id(request.metadata())?;
+        let role = self.get_request_role(request.metadata());
+
+        let reproduction: TaskReproduction = self
+            .read_from_db(&request.message.reproduction_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+        ensure!(
+            reproduction.participants.contains(&user_id) || role == UserRole::Admin,
+            ServiceError::PermissionDenied
+        );
+
+        Ok(GetTaskReproductionResponse {
+            task_id: reproduction.task_id.clone(),
+            status: reproduction.status,
+            matches: reproduction.matches(),
+            failure: reproduction.failure,
+        })
+    }
+
+    // Requests whose deadline has already passed are rejected before doing
+    // any work. The deadline also bounds the storage calls of the request.
+    fn begin_request(
+        &self,
+        metadata: &HashMap<String, String>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        set_request_deadline(metadata);
+        set_request_correlation_id(metadata);
+        if remaining_time().is_err() {
+            log::info!(
+                "[{}] request rejected, deadline exceeded",
+                self.get_request_correlation_id(metadata)
+            );
+            bail!(ServiceError::DeadlineExceeded);
+        }
+        Ok(())
+    }
+
+    // Every request is checked against the access policy before its handler
+    // runs, the handlers check conditions on the accessed objects.
+    fn authorize(
+        &self,
+        method: &str,
+        metadata: &HashMap<String, String>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let role = self.get_request_role(metadata);
+        if !self.access_policy.allows(method, role) {
+            let correlation_id = self.get_request_correlation_id(metadata);
+            let user_id = metadata.get("id").map(String::as_str).unwrap_or_default();
+            log::warn!(
+                target: "audit",
+                "[{}] {}: denied by access policy, user:{}",
+                correlation_id,
+                method,
+                user_id
+            );
+            self.record_audit_entry(&AuditEntry::new(
+                current_timestamp(),
+                correlation_id,
+                method,
+                format!("denied by access policy, user:{}", user_id),
+            ));
+            bail!(ServiceError::PermissionDenied);
+        }
+        Ok(())
+    }
+}
+
+impl TeaclaveManagementService {
+    pub(crate) fn new(
+        storage_service_endpoint: Endpoint,
+        replica_endpoints: Vec<Endpoint>,
+        config: ManagementConfig,
+        key_namespace: &str,
+    ) -> Result<Self> {
+        let primary: Arc<dyn StorageBackend> = Arc::new(connect_storage(storage_service_endpoint)?);
+        let storage: Arc<dyn StorageBackend> = if replica_endpoints.is_empty() {
+            primary
+        } else {
+            let replicas = replica_endpoints
+                .into_iter()
+                .map(|endpoint| {
+                    connect_storage(endpoint).map(|s| Arc::new(s) as Arc<dyn StorageBackend>)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Arc::new(ReplicatedStorage::new(
+                primary,
+                replicas,
+                config.replica_read_your_writes_seconds,
+            ))
+        };
+        let storage = NamespacedStorage::new(storage, key_namespace);
+        let service = Self::with_storage(Arc::new(storage), config);
+        if let Err(e) = service.recover_transient_tasks() {
+            log::error!("Failed to recover staged and running tasks: {:?}", e);
+        }
+
+        #[cfg(test_mode)]
+        service.add_mock_data()?;
+
+        Ok(service)
+    }
+
+    // Validates a task creation request and builds the task without storing
+    // it. Returns whether the function of the task is deprecated.
+    fn new_task(
+        &self,
+        user_id: &UserID,
+        correlation_id: &str,
+        request: CreateTaskRequest,
+    ) -> TeaclaveServiceResponseResult<(Task, bool)> {
+        let function: Function = self
+            .read_from_db(&request.function_id)
+            .map_err(|_| ServiceError::PermissionDenied)?;
+        ensure!(!function.is_deleted(), ServiceError::PermissionDenied);
+
+        // Deprecated functions still accept new tasks, but the client is warned
+        let function_deprecated = function.deprecated;
+        if function_deprecated {
+            log::warn!(
+                "[{}] CreateTask: function {} is deprecated",
+                correlation_id,
+                function.external_id().to_string()
+            );
+        }
+
+        request
+            .function_arguments
+            .validate(self.config.max_argument_length)
+            .map_err(ServiceError::InvalidTask)?;
+        request
+            .function_arguments
+            .validate_total(
+                self.config.max_argument_count,
+                self.config.max_arguments_size,
+            )
+            .map_err(ServiceError::InvalidTask)?;
+        validate_task_labels(&request.labels).map_err(ServiceError::InvalidTask)?;
+        for owners in &[&request.inputs_ownership, &request.outputs_ownership] {
+            validate_fusion_owners(owners, self.config.min_fusion_owners)
+                .map_err(ServiceError::InvalidTask)?;
+        }
+        let resource_limits = TaskResourceLimits {
+            max_memory_mb: request.max_memory_mb,
+            max_runtime_seconds: request.max_runtime_seconds,
+        }
+        .bounded_by(&TaskResourceLimits {
+            max_memory_mb: self.config.max_task_memory_mb,
+            max_runtime_seconds: self.config.max_task_runtime_seconds,
+        })
+        .map_err(ServiceError::InvalidTask)?;
+
+        let executor = resolve_task_executor(
+            request.executor,
+            self.default_executor(),
+            function.executor_type,
+        )
+        .map_err(ServiceError::InvalidTask)?;
+
+        let mut task = Task::new(
+            user_id.clone(),
+            executor,
+            request.function_arguments,
+            request.inputs_ownership,
+            request.outputs_ownership,
+            function,
+        )
+        .map_err(ServiceError::InvalidTask)?;
+
+        if let Some(seconds) = request.approval_deadline_seconds {
+            task.approval_deadline = Some(current_timestamp() + seconds);
+        }
+        task.labels = request.labels;
+        task.resource_limits = resource_limits;
+        task.approval_excludes_creator = self.config.approval_excludes_creator;
+
+        log::info!("[{}] CreateTask: {:?}", correlation_id, task);
+
+        Ok((task, function_deprecated))
+    }
+
+    fn store_new_task(&self, task: &Task) -> TeaclaveServiceResponseResult<()> {
+        self.write_task(task, None)
+            .map_err(ServiceError::StorageError)?;
+        let created_at = current_timestamp();
+        self.update_task_index(&task.participants, |index| {
+            index.add(task.external_id(), created_at, task.labels.clone())
+        })
+        .map_err(ServiceError::StorageError)?;
+
+        // Tasks with an approval deadline are tracked by the reaper
+        if task.approval_deadline.is_some() {
+            self.enqueue_bytes_to_db(
+                APPROVAL_DEADLINE_QUEUE_KEY.as_bytes(),
+                task.external_id().to_bytes(),
+            )?;
+        }
+        self.notify_pending_approvals(task);
+        Ok(())
+    }
+
+    // Reverts a task which may be partially stored by store_new_task.
+    fn remove_new_task(&self, task: &Task) -> Result<()> {
+        let task_id = task.external_id();
+        self.update_task_index(&task.participants, |index| index.remove(&task_id))?;
+        self.delete_from_db(&task_id)
+    }
+
+    // An invalid default executor in the config is ignored, so tasks must
+    // specify their executors.
+    fn default_executor(&self) -> Option<Executor> {
+        let executor = self.config.default_executor.as_deref()?;
+        match Executor::try_from(executor) {
+            Ok(executor) => Some(executor),
+            Err(e) => {
+                log::error!("Invalid default executor: {:?}", e);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn with_storage(storage: Arc<dyn StorageBackend>, config: ManagementConfig) -> Self {
+        let access_policy = AccessPolicy::new(&config.access_policy);
+        let function_checks = function_checks(&config);
+        let degraded_storage = if config.degraded_cache_ttl_seconds > 0 {
+            Some(Arc::new(DegradedStorage::new(
+                storage.clone(),
+                config.degraded_cache_ttl_seconds,
+                config.degraded_cache_capacity,
+                config.degraded_write_queue_depth,
+            )))
+        } else {
+            None
+        };
+        let storage = match &degraded_storage {
+            Some(degraded_storage) => degraded_storage.clone() as Arc<dyn StorageBackend>,
+            None => storage,
+        };
+        let cursor_signer = cursor_signer(&config, storage.as_ref());
+        Self {
+            storage,
+            config,
+            cursor_signer,
+            access_policy,
+            fusion_base: PathBuf::new(),
+            clock_skew_tolerance: 0,
+            storage_metrics: Arc::new(StorageMetrics::default()),
+            degraded_storage,
+            function_checks,
+        }
+    }
+
+    // The fusion base directory is needed to read fusion outputs when their
+    // cmacs are verified.
+    pub(crate) fn fusion_base(self, fusion_base: impl Into<PathBuf>) -> Self {
+        Self {
+            fusion_base: fusion_base.into(),
+            ..self
+        }
+    }
+
+    // Clocks of other machines, e.g., where finalized_at of outputs is set,
+    // may be off by up to this many seconds.
+    pub(crate) fn clock_skew_tolerance(self, seconds: u64) -> Self {
+        Self {
+            clock_skew_tolerance: seconds,
+            ..self
+        }
+    }
+
+    // Current time moved back by the clock skew tolerance, so that deadlines
+    // are only considered passed when they are passed on every clock.
+    fn skewed_timestamp(&self) -> u64 {
+        current_timestamp().saturating_sub(self.clock_skew_tolerance)
+    }
+
+    fn verify_stored_output(
+        &self,
+        output: &TeaclaveOutputFile,
+    ) -> TeaclaveServiceResponseResult<()> {
+        if !self.config.verify_output_cmac || output.cmac.is_none() {
+            return Ok(());
+        }
+        if let Err(e) = verify_output_cmac(output, &self.fusion_base) {
+            log::error!(
+                "Cmac verification of output {} failed: {:?}",
+                output.external_id().to_string(),
+                e
+            );
+            bail!(ServiceError::IntegrityError);
+        }
+        Ok(())
+    }
+
+    pub fn create_fusion_data(&self, owners: impl Into<OwnerList>) -> Result<TeaclaveOutputFile> {
+        let uuid = Uuid::new_v4();
+        let url = format!("fusion:///TEACLAVE_FUSION_BASE/{}.fusion", uuid.to_string());
+        let url = Url::parse(&url).map_err(|_| anyhow!("invalid url"))?;
+        let crypto_info = FileCrypto::default();
+
+        Ok(TeaclaveOutputFile::new(url, crypto_info, owners))
+    }
+
+    fn get_request_user_id(
+        &self,
+        meta: &HashMap<String, String>,
+    ) -> TeaclaveServiceResponseResult<UserID> {
+        let user_id = meta.get("id").ok_or_else(|| ServiceError::InvalidRequest)?;
+        Ok(user_id.to_string().into())
+    }
+
+    // The correlation id is set by the frontend service to trace a request
+    // across services.
+    fn get_request_correlation_id(&self, meta: &HashMap<String, String>) -> String {
+        meta.get(CORRELATION_ID_METADATA_KEY)
+            .cloned()
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    // The role is set by the frontend service after authentication. Requests
+    // without a valid role are treated as from normal users.
+    fn get_request_role(&self, meta: &HashMap<String, String>) -> UserRole {
+        meta.get("role")
+            .and_then(|role| UserRole::try_from(role.as_str()).ok())
+            .unwrap_or_default()
+    }
+
+    // Runs the storage accesses of a call on a view of the storage, which
+    // records whether any of them is served from the cache or queued because
+    // the storage service is unavailable.
+    fn track_degraded<T>(
+        &self,
+        call: impl FnOnce(&Self) -> TeaclaveServiceResponseResult<T>,
+    ) -> TeaclaveServiceResponseResult<(T, bool)> {
+        let storage = match &self.degraded_storage {
+            Some(storage) => Arc::new(DegradedCall::new(storage.clone())),
+            None => return call(self).map(|value| (value, false)),
+        };
+        let service = Self {
+            storage: storage.clone() as Arc<dyn StorageBackend>,
+            ..self.clone()
+        };
+        let value = call(&service)?;
+        Ok((value, storage.is_degraded()))
+    }
+
+    // Warns once the usage reaches the configured percentage of a limit.
+    fn soft_limit_warning(&self, name: &str, usage: usize, limit: usize) -> Option<String> {
+        let percent = self.config.soft_limit_percent? as usize;
+        if usage * 100 >= limit * percent {
+            Some(format!("{} of {} {} used", usage, limit, name))
+        } else {
+            None
+        }
+    }
 
     fn write_to_db(&self, item: &impl Storable) -> Result<()> {
         let k = item.key();
-        let v = item.to_vec()?;
-        let put_request = PutRequest::new(k.as_slice(), v.as_slice());
-        let _put_response = self
-            .storage_client
-            .clone()
-            .lock()
-            .map_err(|_| anyhow!("Cannot lock storage client"))?
-            .put(put_request)?;
+        let v = self.serialize(item)?;
+        self.storage_metrics
+            .time(StorageOperation::Put, || self.storage.put(&k, &v))
+    }
+
+    // The payload is encrypted in place if a payload encryption key is
+    // configured.
+    fn write_function(&self, function: &mut Function) -> Result<()> {
+        encrypt_function_payload(
+            self.config.function_payload_encryption_key.as_deref(),
+            function,
+        )?;
+        self.write_to_db(function)
+    }
+
+    fn serialize(&self, item: &impl Storable) -> Result<Vec<u8>> {
+        let value = item.to_vec_in(self.storage_format())?;
+        if self.config.compress_values {
+            Ok(compress_value(&value))
+        } else {
+            Ok(value)
+        }
+    }
+
+    // Values are written as JSON if the format in the config is invalid.
+    fn storage_format(&self) -> StorageFormat {
+        match StorageFormat::try_from(self.config.storage_format.as_str()) {
+            Ok(format) => format,
+            Err(e) => {
+                log::error!("Invalid storage format: {:?}", e);
+                StorageFormat::Json
+            }
+        }
+    }
+
+    fn read_from_db<T: Storable>(&self, key: &ExternalID) -> Result<T> {
+        anyhow::ensure!(T::match_prefix(&key.prefix), "Key prefix doesn't match.");
+
+        let value = self.read_bytes_from_db(key.to_bytes())?;
+        T::from_slice(value.as_slice())
+    }
+
+    fn read_function(&self, function_id: &ExternalID) -> Result<Function> {
+        let mut function: Function = self.read_from_db(function_id)?;
+        decrypt_function_payload(
+            self.config.function_payload_encryption_key.as_deref(),
+            &mut function,
+        )?;
+        Ok(function)
+    }
+
+    fn read_bytes_from_db(&self, key: Vec<u8>) -> Result<Vec<u8>> {
+        self.storage_metrics
+            .time(StorageOperation::Get, || self.storage.get(&key))
+    }
+
+    // Tags of large task results are stored in separate chunks by the
+    // scheduler, reassemble them before returning the task.
+    fn read_result_tags_chunks(&self, task: &mut Task) -> Result<()> {
+        let keys: Vec<Vec<u8>> = (0..task.result_tags_chunks)
+            .map(|index| task.result_tags_chunk_key(index))
+            .collect();
+        let chunks = self
+            .storage
+            .get_multi(&keys)?
+            .into_iter()
+            .map(|value| {
+                let value = value.ok_or_else(|| anyhow!("Missing result tags chunk"))?;
+                Ok(serde_json::from_slice(&value)?)
+            })
+            .collect::<Result<_>>()?;
+        task.restore_result_tags_chunks(chunks)?;
+        self.restore_archived_result(task)
+    }
+
+    // Tags of aged results are moved to the archive by the reaper, restore
+    // them transparently when the task is read. The archive is downloaded on
+    // every read, which is why only results older than the retention period
+    // are archived.
+    fn restore_archived_result(&self, task: &mut Task) -> Result<()> {
+        let archive = match task.result_archive.take() {
+            Some(archive) => archive,
+            None => return Ok(()),
+        };
+        let bytes = download_archive(
+            &archive.url,
+            self.config.result_archive_encryption_key.as_deref(),
+            &archive.iv,
+            &self.fusion_base,
+        )?;
+        let digest = hex::encode(ring::digest::digest(&ring::digest::SHA256, &bytes));
+        anyhow::ensure!(digest == archive.digest, "Archived result is corrupted");
+        if let TaskResult::Ok(outputs) = &mut task.result {
+            outputs.tags_map.merge(serde_json::from_slice(&bytes)?);
+        }
+        Ok(())
+    }
+
+    // A user without registered files has no index yet.
+    fn read_file_index(&self, user_id: &UserID) -> Result<FileIndex> {
+        let key = FileIndex::get_key(user_id);
+        match self.read_bytes_from_db(key.into_bytes()) {
+            Ok(value) => Ok(serde_json::from_slice(&value)?),
+            Err(_) => Ok(FileIndex::default()),
+        }
+    }
+
+    // Updates the file index of every owner.
+    fn update_file_index(&self, owners: &OwnerList, update: impl Fn(&mut FileIndex)) -> Result<()> {
+        for user_id in owners.uids.iter() {
+            let mut index = self.read_file_index(user_id)?;
+            update(&mut index);
+            let value = serde_json::to_vec(&index)?;
+            self.storage
+                .put(FileIndex::get_key(user_id).as_bytes(), &value)?;
+        }
+        Ok(())
+    }
+
+    // Tasks invoked by the user which are still staged or running. Tasks which
+    // have finished or been canceled since are dropped from the list.
+    fn read_active_tasks(&self, user_id: &UserID) -> Result<Vec<ExternalID>> {
+        let key = format!("{}-{}", ACTIVE_TASKS_PREFIX, user_id);
+        let task_ids: Vec<ExternalID> = match self.read_bytes_from_db(key.into_bytes()) {
+            Ok(value) => serde_json::from_slice(&value)?,
+            Err(_) => Vec::new(),
+        };
+        let keys: Vec<Vec<u8>> = task_ids.iter().map(|task_id| task_id.to_bytes()).collect();
+        let values = self.storage.get_multi(&keys)?;
+        let active_tasks = task_ids
+            .into_iter()
+            .zip(values)
+            .filter(|(_, value)| {
+                value
+                    .as_ref()
+                    .and_then(|value| Task::from_slice(value).ok())
+                    .map_or(false, |task| {
+                        task.status == TaskStatus::Staged || task.status == TaskStatus::Running
+                    })
+            })
+            .map(|(task_id, _)| task_id)
+            .collect();
+        Ok(active_tasks)
+    }
+
+    // Records the (key, iv) pair of a file registered by the user, rejecting
+    // pairs used before if unique IVs are enforced.
+    fn read_crypto_key(
+        &self,
+        user_id: &UserID,
+        name: &str,
+    ) -> TeaclaveServiceResponseResult<FileCrypto> {
+        let value = self
+            .read_bytes_from_db(crypto_key_storage_key(user_id, name))
+            .map_err(|_| ServiceError::InvalidRequest)?;
+        let crypto = decrypt_crypto_key(self.config.crypto_key_encryption_key.as_deref(), &value)
+            .map_err(|_| ServiceError::DataError)?;
+        Ok(crypto)
+    }
+
+    fn claim_crypto_nonce(
+        &self,
+        user_id: &UserID,
+        crypto: &FileCrypto,
+    ) -> TeaclaveServiceResponseResult<()> {
+        if !self.config.enforce_unique_iv {
+            return Ok(());
+        }
+        let fingerprint = match crypto.nonce_fingerprint() {
+            Some(fingerprint) => fingerprint,
+            None => return Ok(()),
+        };
+        let key = format!("{}-{}", CRYPTO_NONCES_PREFIX, user_id);
+        let mut fingerprints: Vec<String> = match self.read_bytes_from_db(key.clone().into_bytes())
+        {
+            Ok(value) => serde_json::from_slice(&value).map_err(|_| ServiceError::DataError)?,
+            Err(_) => Vec::new(),
+        };
+        ensure!(!fingerprints.contains(&fingerprint), ServiceError::IvReused);
+        fingerprints.push(fingerprint);
+        let value = serde_json::to_vec(&fingerprints).map_err(|_| ServiceError::DataError)?;
+        self.storage
+            .put(key.as_bytes(), &value)
+            .map_err(ServiceError::StorageError)?;
+        Ok(())
+    }
+
+    fn write_active_tasks(&self, user_id: &UserID, task_ids: &[ExternalID]) -> Result<()> {
+        let key = format!("{}-{}", ACTIVE_TASKS_PREFIX, user_id);
+        let value = serde_json::to_vec(task_ids)?;
+        self.storage.put(key.as_bytes(), &value)
+    }
+
+    // Timestamps of tasks of the function invoked within its rate limit
+    // window, as of the last invocation.
+    fn read_function_invocations(&self, function_id: &ExternalID) -> Result<Vec<u64>> {
+        let key = format!("{}-{}", FUNCTION_INVOCATIONS_PREFIX, function_id);
+        match self.read_bytes_from_db(key.into_bytes()) {
+            Ok(value) => Ok(serde_json::from_slice(&value)?),
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
+    fn write_function_invocations(
+        &self,
+        function_id: &ExternalID,
+        invocations: &[u64],
+    ) -> Result<()> {
+        let key = format!("{}-{}", FUNCTION_INVOCATIONS_PREFIX, function_id);
+        let value = serde_json::to_vec(invocations)?;
+        self.storage.put(key.as_bytes(), &value)
+    }
+
+    // No function has been registered if there is no index yet.
+    fn read_function_index(&self) -> Result<FunctionIndex> {
+        let key = FunctionIndex::get_key();
+        match self.read_bytes_from_db(key.as_bytes().to_vec()) {
+            Ok(value) => Ok(serde_json::from_slice(&value)?),
+            Err(_) => Ok(FunctionIndex::default()),
+        }
+    }
+
+    // Names of functions are unique per owner if enforced by the config.
+    fn check_function_name(&self, owner: &UserID, name: &str) -> TeaclaveServiceResponseResult<()> {
+        if !self.config.unique_function_names {
+            return Ok(());
+        }
+        let index = self
+            .read_function_index()
+            .map_err(ServiceError::StorageError)?;
+        ensure!(
+            !index.contains_name(owner, name),
+            ServiceError::DuplicateFunctionName(name.to_string())
+        );
+        Ok(())
+    }
+
+    fn check_function(&self, function: &Function) -> TeaclaveServiceResponseResult<()> {
+        for check in self.function_checks.iter() {
+            if let Err(reason) = check.check(function) {
+                log::warn!("Rejected function {}: {}", function.name, reason);
+                bail!(ServiceError::FunctionRejected(reason));
+            }
+        }
+        Ok(())
+    }
+
+    fn update_function_index(&self, update: impl Fn(&mut FunctionIndex)) -> Result<()> {
+        let mut index = self.read_function_index()?;
+        update(&mut index);
+        let value = serde_json::to_vec(&index)?;
+        self.storage
+            .put(FunctionIndex::get_key().as_bytes(), &value)
+    }
+
+    // A user without tasks has no index yet.
+    fn read_task_index(&self, user_id: &UserID) -> Result<TaskIndex> {
+        let key = TaskIndex::get_key(user_id);
+        match self.read_bytes_from_db(key.into_bytes()) {
+            Ok(value) => Ok(serde_json::from_slice(&value)?),
+            Err(_) => Ok(TaskIndex::default()),
+        }
+    }
+
+    // Updates the task index of every participant.
+    fn update_task_index(&self, users: &UserList, update: impl Fn(&mut TaskIndex)) -> Result<()> {
+        for user_id in users.uids.iter() {
+            let mut index = self.read_task_index(user_id)?;
+            update(&mut index);
+            let value = serde_json::to_vec(&index)?;
+            self.storage
+                .put(TaskIndex::get_key(user_id).as_bytes(), &value)?;
+        }
+        Ok(())
+    }
+
+    fn enqueue_to_db(&self, key: &[u8], item: &impl Storable) -> TeaclaveServiceResponseResult<()> {
+        let value = self.serialize(item).map_err(|_| ServiceError::DataError)?;
+        self.enqueue_bytes_to_db(key, value)
+    }
+
+    fn enqueue_bytes_to_db(
+        &self,
+        key: &[u8],
+        value: impl Into<Vec<u8>>,
+    ) -> TeaclaveServiceResponseResult<()> {
+        let value = value.into();
+        self.storage_metrics
+            .time(StorageOperation::Enqueue, || {
+                self.storage.enqueue(key, &value)
+            })
+            .map_err(ServiceError::StorageError)?;
+        Ok(())
+    }
+
+    fn dequeue_from_db(&self, key: &[u8]) -> Result<Vec<u8>> {
+        self.storage_metrics
+            .time(StorageOperation::Dequeue, || self.storage.dequeue(key))
+    }
+
+    // With fair staging, the user of each staged task is recorded as arrived,
+    // and the scheduler puts arrived users in turn unless they are already,
+    // keeping them in turn until their queues are drained.
+    fn enqueue_staged_task(
+        &self,
+        user_id: &UserID,
+        staged_task: &StagedTask,
+    ) -> TeaclaveServiceResponseResult<()> {
+        if !self.config.fair_staging {
+            return self.enqueue_to_db(StagedTask::get_queue_key().as_bytes(), staged_task);
+        }
+        // Only the scheduler puts users in turn, so that checking whether the
+        // user is in turn doesn't race with the scheduler taking the user out.
+        let key = StagedTask::get_user_queue_key(user_id);
+        self.enqueue_to_db(key.as_bytes(), staged_task)?;
+        self.enqueue_bytes_to_db(
+            StagedTask::get_arrivals_queue_key().as_bytes(),
+            user_id.to_string(),
+        )
+    }
+
+    // Keys of the FIFO queue and the queues of users in turn or about to be,
+    // staged tasks may be left in both if fair staging is switched.
+    fn staged_queue_keys(&self) -> Result<Vec<Vec<u8>>> {
+        let mut keys = vec![StagedTask::get_queue_key().as_bytes().to_vec()];
+        let mut users = Vec::new();
+        for users_key in &[
+            StagedTask::get_users_queue_key(),
+            StagedTask::get_arrivals_queue_key(),
+        ] {
+            users.extend(
+                self.storage
+                    .queue_range(users_key.as_bytes(), 0, usize::MAX)?,
+            );
+        }
+        for user_id in users {
+            let user_id = UserID::from(String::from_utf8(user_id)?);
+            let key = StagedTask::get_user_queue_key(&user_id).into_bytes();
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    // Staged and running tasks may be left inconsistent if the service is
+    // restarted. Tasks whose staged task is still in the queue are kept for
+    // executors, the others can never finish and are failed. Staged queues
+    // are only read, not drained, so that schedulers can keep pulling tasks
+    // while the service recovers. Tasks which cannot be read or failed are
+    // put back to the queue for the next restart.
+    pub(crate) fn recover_transient_tasks(&self) -> Result<()> {
+        let mut queued_task_ids = Vec::new();
+        for staged_key in self.staged_queue_keys()? {
+            for value in self.storage.queue_range(&staged_key, 0, usize::MAX)? {
+                if let Ok(staged_task) = StagedTask::from_slice(&value) {
+                    queued_task_ids.push(staged_task.task_id);
+                }
+            }
+        }
+
+        let key = TRANSIENT_TASKS_QUEUE_KEY.as_bytes();
+        let mut pending = Vec::new();
+        while let Ok(value) = self.dequeue_from_db(key) {
+            let task_id = match String::from_utf8(value.clone())
+                .map_err(anyhow::Error::from)
+                .and_then(ExternalID::try_from)
+            {
+                Ok(task_id) => task_id,
+                Err(e) => {
+                    log::warn!("Recovery: invalid task id in queue: {:?}", e);
+                    continue;
+                }
+            };
+            let mut task: Task = match self.read_from_db(&task_id) {
+                Ok(task) => task,
+                Err(e) => {
+                    log::warn!("Recovery: cannot read task {:?}: {:?}", task_id, e);
+                    pending.push(value);
+                    continue;
+                }
+            };
+            if task.status != TaskStatus::Staged && task.status != TaskStatus::Running {
+                continue;
+            }
+
+            if task.status == TaskStatus::Staged && queued_task_ids.contains(&task.task_id) {
+                pending.push(value);
+                continue;
+            }
+            log::warn!("Recovery: staged task is lost, fail:{:?}", task_id);
+            let old_status = task.status;
+            let result = task
+                .abort("task is lost when the service is restarted")
+                .and_then(|_| self.write_task(&task, Some(old_status)));
+            if let Err(e) = result {
+                log::warn!("Recovery: cannot fail task {:?}: {:?}", task_id, e);
+                pending.push(value);
+            }
+        }
+
+        for value in pending {
+            if let Err(e) = self.enqueue_bytes_to_db(key, value) {
+                log::warn!("Recovery: cannot requeue task: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Cancel tasks whose approval deadline has passed. Tasks still waiting
+    // for approvals are put back to the queue for the next round, and so are
+    // tasks which cannot be read or canceled, so that a storage error doesn't
+    // drop their deadline.
+    pub(crate) fn reap_expired_tasks(&self) -> Result<()> {
+        let key = APPROVAL_DEADLINE_QUEUE_KEY.as_bytes();
+        let now = self.skewed_timestamp();
+        let mut pending = Vec::new();
+
+        while let Ok(value) = self.dequeue_from_db(key) {
+            let task_id = match String::from_utf8(value.clone())
+                .map_err(anyhow::Error::from)
+                .and_then(ExternalID::try_from)
+            {
+                Ok(task_id) => task_id,
+                Err(e) => {
+                    log::warn!("Reaper: invalid task id in queue: {:?}", e);
+                    continue;
+                }
+            };
+            let mut task: Task = match self.read_from_db(&task_id) {
+                Ok(task) => task,
+                Err(e) => {
+                    log::warn!("Reaper: cannot read task {:?}: {:?}", task_id, e);
+                    pending.push(value);
+                    continue;
+                }
+            };
+
+            let old_status = task.status;
+            if task.cancel_if_approval_expired(now) {
+                log::info!("Reaper: approval deadline passed, cancel:{:?}", task_id);
+                if let Err(e) = self.write_task(&task, Some(old_status)) {
+                    log::warn!("Reaper: cannot cancel task {:?}: {:?}", task_id, e);
+                    pending.push(value);
+                }
+            } else if task.awaiting_approval() {
+                pending.push(value);
+            }
+        }
+
+        for value in pending {
+            if let Err(e) = self.enqueue_bytes_to_db(key, value) {
+                log::warn!("Reaper: cannot requeue approval deadline: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Removes soft deleted functions which are not restored in the retention
+    // window. Functions which cannot be read or removed are put back to the
+    // queue for the next round. The function is removed from the index first,
+    // so that a retried round still finds its record.
+    pub(crate) fn reap_deleted_functions(&self) -> Result<()> {
+        let key = DELETED_FUNCTION_QUEUE_KEY.as_bytes();
+        let now = self.skewed_timestamp();
+        let mut pending = Vec::new();
+
+        while let Ok(value) = self.dequeue_from_db(key) {
+            let function_id = match String::from_utf8(value.clone())
+                .map_err(anyhow::Error::from)
+                .and_then(ExternalID::try_from)
+            {
+                Ok(function_id) => function_id,
+                Err(e) => {
+                    log::warn!("Reaper: invalid function id in queue: {:?}", e);
+                    continue;
+                }
+            };
+            let function: Function = match self.read_from_db(&function_id) {
+                Ok(function) => function,
+                Err(e) => {
+                    log::warn!("Reaper: cannot read function {:?}: {:?}", function_id, e);
+                    pending.push(value);
+                    continue;
+                }
+            };
+            // Restored functions are no longer tracked
+            let deleted_at = match function.deleted_at {
+                Some(deleted_at) => deleted_at,
+                None => continue,
+            };
+
+            if now >= deleted_at + self.config.function_retention_seconds {
+                log::info!("Reaper: remove deleted function:{:?}", function_id);
+                let result = self
+                    .update_function_index(|index| index.remove(&function_id))
+                    .and_then(|_| self.delete_from_db(&function_id));
+                if let Err(e) = result {
+                    log::warn!("Reaper: cannot remove function {:?}: {:?}", function_id, e);
+                    pending.push(value);
+                }
+            } else {
+                pending.push(value);
+            }
+        }
+
+        for value in pending {
+            if let Err(e) = self.enqueue_bytes_to_db(key, value) {
+                log::warn!("Reaper: cannot requeue deleted function: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Archives tags of results which are finished for longer than the
+    // retention period. Finished tasks are found by walking the task event
+    // log from the offset where the previous round stopped. Results which
+    // cannot be archived are kept in the storage.
+    pub(crate) fn reap_aged_results(&self) -> Result<()> {
+        let retention = match self.config.result_retention_seconds {
+            Some(retention) => retention,
+            None => return Ok(()),
+        };
+        let base = match &self.config.result_archive_url {
+            Some(url) => Url::parse(url)?,
+            None => return Ok(()),
+        };
+        // Results are never archived in plaintext
+        if self.config.result_archive_encryption_key.is_none() {
+            log::warn!("Reaper: results are not archived without an encryption key");
+            return Ok(());
+        }
+        let now = self.skewed_timestamp();
+        let mut offset = match self.storage.get(RESULT_ARCHIVE_OFFSET_KEY.as_bytes()) {
+            Ok(value) => serde_json::from_slice(&value)?,
+            Err(_) => 0u64,
+        };
+
+        'walk: loop {
+            let values = self.storage.queue_range(
+                TaskEvent::get_queue_key().as_bytes(),
+                offset,
+                MAX_PAGE_SIZE,
+            )?;
+            if values.is_empty() {
+                break;
+            }
+            for value in values {
+                if let Ok(event) = TaskEvent::from_slice(&value) {
+                    if event.new_status == TaskStatus::Finished {
+                        // Events are appended in time order
+                        if event.timestamp + retention > now {
+                            break 'walk;
+                        }
+                        if let Err(e) = self.archive_task_result(&event.task_id, &base) {
+                            log::warn!(
+                                "Reaper: cannot archive result of task {:?}: {:?}",
+                                event.task_id,
+                                e
+                            );
+                        }
+                    }
+                }
+                offset += 1;
+            }
+        }
+
+        self.storage.put(
+            RESULT_ARCHIVE_OFFSET_KEY.as_bytes(),
+            &serde_json::to_vec(&offset)?,
+        )
+    }
+
+    // Keeps at most the configured number of finished or canceled tasks of
+    // each creator, and none terminated longer ago than the configured age.
+    // Pruned tasks are recorded in the audit log. The walk of the event log
+    // stops at an event whose record cannot be queued, and is resumed from it
+    // in the next round.
+    pub(crate) fn reap_task_history(&self) -> Result<()> {
+        let max_count = self.config.task_history_max_count;
+        let max_age = self.config.task_history_max_age_seconds;
+        if max_count.is_none() && max_age.is_none() {
+            return Ok(());
+        }
+        let key = TASK_HISTORY_QUEUE_KEY.as_bytes();
+        let mut offset = match self.storage.get(TASK_HISTORY_OFFSET_KEY.as_bytes()) {
+            Ok(value) => serde_json::from_slice(&value)?,
+            Err(_) => 0u64,
+        };
+
+        'walk: loop {
+            let values = self.storage.queue_range(
+                TaskEvent::get_queue_key().as_bytes(),
+                offset,
+                MAX_PAGE_SIZE,
+            )?;
+            if values.is_empty() {
+                break;
+            }
+            for value in values {
+                if let Ok(event) = TaskEvent::from_slice(&value) {
+                    if event.new_status == TaskStatus::Finished
+                        || event.new_status == TaskStatus::Canceled
+                    {
+                        if let Ok(task) = self.read_from_db::<Task>(&event.task_id) {
+                            let record = TaskHistoryRecord {
+                                task_id: event.task_id,
+                                creator: task.creator,
+                                terminated_at: event.timestamp,
+                            };
+                            if let Err(e) = self.enqueue_task_history(&record) {
+                                log::warn!("Reaper: cannot queue task history: {:?}", e);
+                                break 'walk;
+                            }
+                        }
+                    }
+                }
+                offset += 1;
+            }
+        }
+        self.storage.put(
+            TASK_HISTORY_OFFSET_KEY.as_bytes(),
+            &serde_json::to_vec(&offset)?,
+        )?;
+
+        let mut histories: HashMap<UserID, Vec<TaskHistoryRecord>> = HashMap::new();
+        while let Ok(value) = self.dequeue_from_db(key) {
+            match serde_json::from_slice::<TaskHistoryRecord>(&value) {
+                Ok(record) => histories
+                    .entry(record.creator.clone())
+                    .or_default()
+                    .push(record),
+                Err(e) => log::warn!("Reaper: invalid task history record: {:?}", e),
+            }
+        }
+
+        let now = self.skewed_timestamp();
+        for (_, records) in histories {
+            let mut kept = Vec::new();
+            let mut seen = HashSet::new();
+            // Newest first, a task terminated again after a retry is counted
+            // once.
+            for record in records.into_iter().rev() {
+                if !seen.insert(record.task_id.to_string()) {
+                    continue;
+                }
+                let task: Task = match self.read_from_db(&record.task_id) {
+                    Ok(task) => task,
+                    Err(_) => continue,
+                };
+                // Retried tasks are added again when they terminate.
+                if task.status != TaskStatus::Finished && task.status != TaskStatus::Canceled {
+                    continue;
+                }
+                let expired = max_age.map_or(false, |age| record.terminated_at + age < now);
+                let excess = max_count.map_or(false, |count| kept.len() >= count);
+                if !(expired || excess) {
+                    kept.push(record);
+                    continue;
+                }
+                if let Err(e) = self.prune_task(&task, record.terminated_at) {
+                    log::warn!("Reaper: failed to prune task {:?}: {:?}", record.task_id, e);
+                    kept.push(record);
+                }
+            }
+            for record in kept.into_iter().rev() {
+                if let Err(e) = self.enqueue_task_history(&record) {
+                    log::warn!(
+                        "Reaper: cannot requeue task history {:?}: {:?}",
+                        record.task_id,
+                        e
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn enqueue_task_history(&self, record: &TaskHistoryRecord) -> Result<()> {
+        let value = serde_json::to_vec(record)?;
+        self.enqueue_bytes_to_db(TASK_HISTORY_QUEUE_KEY.as_bytes(), value)?;
+        Ok(())
+    }
+
+    fn prune_task(&self, task: &Task, terminated_at: u64) -> Result<()> {
+        let task_id = task.external_id();
+        self.audit(
+            "",
+            "PruneTask",
+            format!("task:{} creator:{} function:{} participants:{:?} status:{:?} succeeded:{} terminated_at:{}", task_id.to_string(), task.creator, task.function_id.to_string(), task.participants.uids, task.status, task.result.is_ok(), terminated_at),
+        );
+        for index in 0..task.result_tags_chunks {
+            self.storage.delete(&task.result_tags_chunk_key(index))?;
+        }
+        if task.log_stored {
+            self.storage.delete(&task.log_key())?;
+        }
+        self.update_task_index(&task.participants, |index| index.remove(&task_id))?;
+        self.delete_from_db(&task_id)
+    }
+
+    // Moves the tags of a task result, including the chunks stored apart
+    // from the task, to one archive object.
+    fn archive_task_result(&self, task_id: &ExternalID, base: &Url) -> Result<()> {
+        let mut task: Task = match self.read_from_db(task_id) {
+            Ok(task) => task,
+            Err(_) => return Ok(()),
+        };
+        if task.result_archive.is_some() {
+            return Ok(());
+        }
+        let chunk_keys: Vec<Vec<u8>> = (0..task.result_tags_chunks)
+            .map(|index| task.result_tags_chunk_key(index))
+            .collect();
+        self.read_result_tags_chunks(&mut task)?;
+
+        let tags_map = match &mut task.result {
+            TaskResult::Ok(outputs) => std::mem::take(&mut outputs.tags_map),
+            _ => return Ok(()),
+        };
+        let bytes = serde_json::to_vec(&tags_map)?;
+        let url = base.join(&format!("{}.json", task.task_id))?;
+        let iv = upload_archive(
+            &bytes,
+            self.config.result_archive_encryption_key.as_deref(),
+            &url,
+            &self.fusion_base,
+        )?;
+        log::info!("Reaper: archive result of task:{:?}", task_id);
+
+        task.result_archive = Some(ResultArchive {
+            url,
+            digest: hex::encode(ring::digest::digest(&ring::digest::SHA256, &bytes)),
+            iv,
+        });
+        self.write_to_db(&task)?;
+        for key in chunk_keys {
+            self.storage.delete(&key)?;
+        }
+        Ok(())
+    }
+
+    // Remove fusion outputs which are not consumed as inputs within the
+    // configured period. Owners are notified before the output is deleted,
+    // and the fusion file is deleted along with its record.
+    pub(crate) fn reap_expired_fusion_outputs(&self) -> Result<()> {
+        let max_age = match self.config.fusion_output_max_age_seconds {
+            Some(max_age) => max_age,
+            None => return Ok(()),
+        };
+        let key = FUSION_OUTPUT_QUEUE_KEY.as_bytes();
+        let now = self.skewed_timestamp();
+        let mut pending = Vec::new();
+
+        while let Ok(value) = self.dequeue_from_db(key) {
+            let record: FusionOutputRecord = match serde_json::from_slice(&value) {
+                Ok(record) => record,
+                Err(e) => {
+                    log::warn!("Reaper: invalid fusion output record: {:?}", e);
+                    continue;
+                }
+            };
+            let output: TeaclaveOutputFile = match self.read_from_db(&record.data_id) {
+                Ok(output) => output,
+                Err(_) => continue,
+            };
+            let input_id = ExternalID::new(TeaclaveInputFile::key_prefix(), output.uuid);
+            let consumed = self.read_from_db::<TeaclaveInputFile>(&input_id).is_ok();
+
+            match fusion_retention_action(&record, consumed, now, max_age) {
+                RetentionAction::Keep => pending.push(value),
+                RetentionAction::Release => (),
+                RetentionAction::Reap => {
+                    if let Err(e) = self.remove_expired_fusion_output(&record, &output, max_age) {
+                        log::warn!(
+                            "Reaper: cannot remove fusion output {:?}: {:?}",
+                            record.data_id,
+                            e
+                        );
+                        pending.push(value);
+                    }
+                }
+            }
+        }
+
+        for value in pending {
+            if let Err(e) = self.enqueue_bytes_to_db(key, value) {
+                log::warn!("Reaper: cannot requeue fusion output: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Owners are notified before the output is removed, so that the output is
+    // kept for the next round if they cannot be notified.
+    fn remove_expired_fusion_output(
+        &self,
+        record: &FusionOutputRecord,
+        output: &TeaclaveOutputFile,
+        max_age: u64,
+    ) -> Result<()> {
+        let message = format!(
+            "Fusion output {} is removed because it was not consumed in {} seconds",
+            record.data_id.to_string(),
+            max_age
+        );
+        for owner in output.owner.uids.iter() {
+            self.notify(owner, &message)?;
+        }
+
+        let info = HandleFileInfo::new("", &output.url);
+        let request =
+            FileAgentRequest::new(HandleFileCommand::Delete, vec![info], &self.fusion_base);
+        if let Err(e) = handle_file_request(request) {
+            log::warn!(
+                "Reaper: cannot delete fusion file of {:?}: {:?}",
+                record.data_id,
+                e
+            );
+        }
+
+        log::info!("Reaper: remove fusion output:{:?}", record.data_id);
+        self.delete_from_db(&record.data_id)?;
+        self.update_file_index(&output.owner, |index| {
+            index.remove(FileKind::Output, &record.data_id)
+        })
+    }
+
+    // Removes temporary fusion outputs once a task consuming them finishes
+    // successfully and no other unfinished task uses them, along with the
+    // inputs registered from them and the fusion files. Finished tasks are
+    // found by walking the task event log from the offset where the previous
+    // round stopped. The walk stops at a task whose inputs cannot be removed,
+    // and is resumed from it in the next round.
+    pub(crate) fn reap_temporary_fusion_outputs(&self) -> Result<()> {
+        let mut offset = match self.storage.get(TEMPORARY_OUTPUT_OFFSET_KEY.as_bytes()) {
+            Ok(value) => serde_json::from_slice(&value)?,
+            Err(_) => 0u64,
+        };
+
+        'walk: loop {
+            let values = self.storage.queue_range(
+                TaskEvent::get_queue_key().as_bytes(),
+                offset,
+                MAX_PAGE_SIZE,
+            )?;
+            if values.is_empty() {
+                break;
+            }
+            for value in values {
+                if let Ok(event) = TaskEvent::from_slice(&value) {
+                    if event.new_status == TaskStatus::Finished {
+                        if let Ok(task) = self.read_from_db::<Task>(&event.task_id) {
+                            if let TaskResult::Ok(_) = task.result {
+                                if !self.remove_temporary_inputs(&task) {
+                                    break 'walk;
+                                }
+                            }
+                        }
+                    }
+                }
+                offset += 1;
+            }
+        }
+
+        self.storage.put(
+            TEMPORARY_OUTPUT_OFFSET_KEY.as_bytes(),
+            &serde_json::to_vec(&offset)?,
+        )
+    }
+
+    // Returns whether all the temporary inputs of the task are removed or
+    // still used by other tasks.
+    fn remove_temporary_inputs(&self, task: &Task) -> bool {
+        let mut removed = true;
+        for (_, input) in task.assigned_inputs.iter() {
+            if !input.temporary {
+                continue;
+            }
+            if let Err(e) = self.remove_temporary_fusion_data(input) {
+                log::warn!(
+                    "Reaper: cannot remove temporary input {:?}: {:?}",
+                    input.external_id(),
+                    e
+                );
+                removed = false;
+            }
+        }
+        removed
+    }
+
+    fn remove_temporary_fusion_data(&self, input: &TeaclaveInputFile) -> Result<()> {
+        let input_id = input.external_id();
+        // Already removed when another consumer finished
+        if self.read_from_db::<TeaclaveInputFile>(&input_id).is_err() {
+            return Ok(());
+        }
+        // Removed when the last of the other consumers finishes
+        if self.temporary_input_in_use(input)? {
+            return Ok(());
+        }
+        let output_id = ExternalID::new(TeaclaveOutputFile::key_prefix(), input.uuid);
+
+        let info = HandleFileInfo::new("", &input.url);
+        let request =
+            FileAgentRequest::new(HandleFileCommand::Delete, vec![info], &self.fusion_base);
+        if let Err(e) = handle_file_request(request) {
+            log::warn!(
+                "Reaper: cannot delete fusion file of {:?}: {:?}",
+                output_id,
+                e
+            );
+        }
+
+        log::info!("Reaper: remove temporary fusion output:{:?}", output_id);
+        self.delete_from_db(&input_id)?;
+        self.delete_from_db(&output_id)?;
+        self.update_file_index(&input.owner, |index| {
+            index.remove(FileKind::Input, &input_id);
+            index.remove(FileKind::Output, &output_id);
+        })
+    }
+
+    // Tasks using an input are participated by its owners, so only their
+    // tasks are checked.
+    fn temporary_input_in_use(&self, input: &TeaclaveInputFile) -> Result<bool> {
+        let input_id = input.external_id();
+        for user_id in input.owner.uids.iter() {
+            let index = self.read_task_index(user_id)?;
+            for task_id in index.task_ids() {
+                let task: Task = match self.read_from_db(task_id) {
+                    Ok(task) => task,
+                    Err(_) => continue,
+                };
+                if task.status == TaskStatus::Finished || task.status == TaskStatus::Canceled {
+                    continue;
+                }
+                if task
+                    .assigned_inputs
+                    .iter()
+                    .any(|(_, file)| file.external_id() == input_id)
+                {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    // Queues deliveries of results of finished and canceled tasks whose
+    // creators have a callback, found by walking the task event log from the
+    // offset where the previous round stopped. The walk stops at a task whose
+    // delivery cannot be queued, so that the offset saved covers only queued
+    // deliveries. Then posts the deliveries which are due, keeping failed ones
+    // for a later retry.
+    pub(crate) fn deliver_result_callbacks(&self) -> Result<()> {
+        let mut offset = match self.storage.get(RESULT_CALLBACK_OFFSET_KEY.as_bytes()) {
+            Ok(value) => serde_json::from_slice(&value)?,
+            Err(_) => 0u64,
+        };
+
+        'walk: loop {
+            let values = self.storage.queue_range(
+                TaskEvent::get_queue_key().as_bytes(),
+                offset,
+                MAX_PAGE_SIZE,
+            )?;
+            if values.is_empty() {
+                break;
+            }
+            for value in values {
+                if let Ok(event) = TaskEvent::from_slice(&value) {
+                    if event.new_status == TaskStatus::Finished
+                        || event.new_status == TaskStatus::Canceled
+                    {
+                        if let Ok(task) = self.read_from_db::<Task>(&event.task_id) {
+                            let delivery = match self.result_callback_delivery(&task) {
+                                Ok(delivery) => delivery,
+                                Err(e) => {
+                                    log::warn!(
+                                        "Reaper: invalid result callback of {:?}: {:?}",
+                                        event.task_id,
+                                        e
+                                    );
+                                    None
+                                }
+                            };
+                            if let Some(value) = delivery {
+                                if let Err(e) = self.enqueue_bytes_to_db(
+                                    CALLBACK_DELIVERY_QUEUE_KEY.as_bytes(),
+                                    value,
+                                ) {
+                                    log::warn!(
+                                        "Reaper: cannot queue result callback of {:?}: {:?}",
+                                        event.task_id,
+                                        e
+                                    );
+                                    break 'walk;
+                                }
+                            }
+                        }
+                    }
+                }
+                offset += 1;
+            }
+        }
+
+        self.storage.put(
+            RESULT_CALLBACK_OFFSET_KEY.as_bytes(),
+            &serde_json::to_vec(&offset)?,
+        )?;
+
+        let key = CALLBACK_DELIVERY_QUEUE_KEY.as_bytes();
+        let mut deliveries = Vec::new();
+        while let Ok(value) = self.dequeue_from_db(key) {
+            deliveries.push(value);
+        }
+        let now = current_timestamp();
+        for value in deliveries {
+            let mut delivery: CallbackDelivery = match serde_json::from_slice(&value) {
+                Ok(delivery) => delivery,
+                Err(e) => {
+                    log::warn!("Reaper: invalid callback delivery: {:?}", e);
+                    continue;
+                }
+            };
+            if delivery.is_due(now) {
+                // The allowed hosts may have changed since it was queued
+                if !callback_allowed(&delivery.url, &self.config.callback_allowed_hosts) {
+                    log::warn!("Reaper: callback to {} is not allowed", delivery.url);
+                    continue;
+                }
+                match post_callback(&delivery, &self.fusion_base) {
+                    Ok(()) => {
+                        log::info!("Reaper: delivered result callback to {}", delivery.url);
+                        continue;
+                    }
+                    Err(e) => {
+                        if !delivery.retry_later(now) {
+                            log::warn!(
+                                "Reaper: give up result callback to {}: {:?}",
+                                delivery.url,
+                                e
+                            );
+                            continue;
+                        }
+                        log::warn!(
+                            "Reaper: result callback to {} failed, attempts:{}: {:?}",
+                            delivery.url,
+                            delivery.attempts,
+                            e
+                        );
+                    }
+                }
+            }
+            let result = serde_json::to_vec(&delivery)
+                .map_err(anyhow::Error::from)
+                .and_then(|value| Ok(self.enqueue_bytes_to_db(key, value)?));
+            if let Err(e) = result {
+                log::warn!(
+                    "Reaper: cannot requeue result callback to {}: {:?}",
+                    delivery.url,
+                    e
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    // Returns the serialized delivery of the task result if its creator has a
+    // callback.
+    fn result_callback_delivery(&self, task: &Task) -> Result<Option<Vec<u8>>> {
+        let value = match self
+            .storage
+            .get(&result_callback_storage_key(&task.creator))
+        {
+            Ok(value) => value,
+            Err(_) => return Ok(None),
+        };
+        let callback: ResultCallback = serde_json::from_slice(&value)?;
+        let delivery = CallbackDelivery::new(task, &callback, current_timestamp())?;
+        Ok(Some(serde_json::to_vec(&delivery)?))
+    }
+
+    // Drives a trivial builtin task through creation, data assignment,
+    // approval and invocation, and reports the result of each stage. Stages
+    // after a failed one are skipped. The staged task is not queued for
+    // execution, and all records are removed afterwards.
+    fn self_test(&self, user_id: &UserID) -> Vec<SelfTestStage> {
+        let mut stages = Vec::new();
+        let mut function = Function::new()
+            .id(Uuid::new_v4())
+            .name("builtin-echo")
+            .description("self test")
+            .executor_type(ExecutorType::Builtin)
+            .arguments(vec!["message".to_string()])
+            .outputs(vec![FunctionOutput::new("output", "self test output")])
+            .owner(user_id.clone());
+        let function_id = function.external_id();
+        let output_file = match self.create_fusion_data(vec![user_id.to_string()]) {
+            Ok(output_file) => output_file,
+            Err(e) => {
+                stages.push(SelfTestStage::new("create_task", false, e));
+                return stages;
+            }
+        };
+        let mut records = vec![function_id.clone(), output_file.external_id()];
+
+        let mut create_task = || -> Result<Task> {
+            self.write_function(&mut function)?;
+            self.write_to_db(&output_file)?;
+            let task = Task::new(
+                user_id.clone(),
+                Executor::Builtin,
+                FunctionArguments::new(hashmap!("message" => "self test")),
+                TaskFileOwners::default(),
+                hashmap!("output" => vec![user_id.to_string()]),
+                self.read_from_db(&function_id)?,
+            )?;
+            self.write_to_db(&task)?;
+            Ok(task)
+        };
+        let mut task = match record_stage(&mut stages, "create_task", create_task()) {
+            Some(task) => task,
+            None => return self.clean_up_self_test(stages, records),
+        };
+        records.push(task.external_id());
+
+        let result = task
+            .assign_output(user_id, "output", output_file.clone())
+            .and_then(|_| self.write_to_db(&task));
+        if record_stage(&mut stages, "assign_data", result).is_none() {
+            return self.clean_up_self_test(stages, records);
+        }
+
+        let result = task.approve(user_id).and_then(|_| self.write_to_db(&task));
+        if record_stage(&mut stages, "approve_task", result).is_none() {
+            return self.clean_up_self_test(stages, records);
+        }
+
+        let result = self
+            .read_function(&function_id)
+            .and_then(|function| task.stage_for_running(user_id, function))
+            .and_then(|_| self.write_to_db(&task));
+        record_stage(&mut stages, "invoke_task", result);
+
+        self.clean_up_self_test(stages, records)
+    }
+
+    fn clean_up_self_test(
+        &self,
+        stages: Vec<SelfTestStage>,
+        records: Vec<ExternalID>,
+    ) -> Vec<SelfTestStage> {
+        for key in records {
+            if let Err(e) = self.delete_from_db(&key) {
+                log::warn!("SelfTest: failed to remove {:?}: {:?}", key, e);
+            }
+        }
+        stages
+    }
+
+    // Inputs registered from unfinished outputs get their cmac from the
+    // outputs, which should be finished before the task is staged.
+    fn resolve_pending_cmacs(&self, task: &mut Task) -> TeaclaveServiceResponseResult<()> {
+        for (fname, input) in task.assigned_inputs.iter_mut() {
+            if !input.cmac_pending {
+                continue;
+            }
+            let output_id = ExternalID::new(TeaclaveOutputFile::key_prefix(), input.uuid);
+            let cmac = self
+                .read_from_db::<TeaclaveOutputFile>(&output_id)
+                .ok()
+                .and_then(|output| output.cmac);
+            match cmac {
+                Some(cmac) => {
+                    input.resolve_cmac(cmac);
+                    self.write_to_db(&*input)
+                        .map_err(ServiceError::StorageError)?;
+                }
+                None => {
+                    ensure!(
+                        self.config.cmac_verification == CmacVerification::Permissive,
+                        ServiceError::BadTask
+                    );
+                    log::warn!("InvokeTask: cmac of input {} is missing", fname);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Lazily registered inputs are not checked at registration, so their
+    // remote files are checked here instead of failing the staged task.
+    fn check_lazy_inputs(&self, task: &Task) -> TeaclaveServiceResponseResult<()> {
+        for (fname, input) in task.assigned_inputs.iter() {
+            if !input.lazy {
+                continue;
+            }
+            let info = HandleFileInfo::new("", &input.url);
+            let request =
+                FileAgentRequest::new(HandleFileCommand::Check, vec![info], &self.fusion_base);
+            if let Err(e) = handle_file_request(request) {
+                log::warn!("InvokeTask: lazy input {} is unavailable: {:?}", fname, e);
+                bail!(ServiceError::LazyInputUnavailable(fname.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    // Writes a task and appends its status transition from old_status to the
+    // event log. Failing to record the event doesn't fail the request.
+    fn write_task(&self, task: &Task, old_status: Option<TaskStatus>) -> Result<()> {
+        self.write_to_db(task)?;
+        if old_status != Some(task.status) {
+            if let Err(e) = self.record_task_event(task, old_status) {
+                log::warn!("Failed to record event of task {:?}: {:?}", task.task_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    fn record_task_event(&self, task: &Task, old_status: Option<TaskStatus>) -> Result<()> {
+        let event = TaskEvent::new(task, old_status, current_timestamp());
+        self.storage
+            .enqueue(TaskEvent::get_queue_key().as_bytes(), &event.to_vec()?)
+    }
+
+    // Logs an action to the audit log and keeps it for export. Failing to
+    // keep the entry doesn't fail the action.
+    fn audit(&self, correlation_id: &str, action: &str, detail: String) {
+        if correlation_id.is_empty() {
+            log::info!(target: "audit", "{}: {}", action, detail);
+        } else {
+            log::info!(target: "audit", "[{}] {}: {}", correlation_id, action, detail);
+        }
+        self.record_audit_entry(&AuditEntry::new(
+            current_timestamp(),
+            correlation_id,
+            action,
+            detail,
+        ));
+    }
+
+    fn record_audit_entry(&self, entry: &AuditEntry) {
+        let result = entry.to_vec().and_then(|value| {
+            self.storage
+                .enqueue(AuditEntry::get_queue_key().as_bytes(), &value)
+        });
+        if let Err(e) = result {
+            log::warn!("Failed to record audit entry {}: {:?}", entry.action, e);
+        }
+    }
+
+    fn notify(&self, user_id: &UserID, message: &str) -> Result<()> {
+        let notification = Notification::new(message, current_timestamp());
+        let key = Notification::get_queue_key(user_id);
+        self.enqueue_bytes_to_db(key.as_bytes(), notification.to_vec()?)?;
+        Ok(())
+    }
+
+    // Reminds participants who have not approved a task in the approval
+    // phase. Failing to notify doesn't fail the request.
+    fn notify_pending_approvals(&self, task: &Task) {
+        if task.status != TaskStatus::DataAssigned {
+            return;
+        }
+        let pending_approvals = task.pending_approvals();
+        let message = format!(
+            "Task {} is waiting for your approval, {} of {} approvals pending",
+            task.external_id().to_string(),
+            pending_approvals.len(),
+            task.participants.len()
+        );
+        for user_id in pending_approvals.uids.iter() {
+            if let Err(e) = self.notify(user_id, &message) {
+                log::warn!("Failed to notify {} of pending approval: {:?}", user_id, e);
+            }
+        }
+    }
+
+    fn delete_from_db(&self, key: &ExternalID) -> Result<()> {
+        self.storage.delete(&key.to_bytes())
+    }
+
+    #[cfg(test_mode)]
+    fn add_mock_data(&self) -> Result<()> {
+        use teaclave_types::FunctionInput;
+        let mut output_file = self.create_fusion_data(vec!["mock_user1", "frontend_user"])?;
+        output_file.uuid = Uuid::parse_str("00000000-0000-0000-0000-000000000001")?;
+        output_file.cmac = Some(FileAuthTag::mock());
+        self.write_to_db(&output_file)?;
+
+        let mut output_file = self.create_fusion_data(vec!["mock_user2", "mock_user3"])?;
+        output_file.uuid = Uuid::parse_str("00000000-0000-0000-0000-000000000002")?;
+        output_file.cmac = Some(FileAuthTag::mock());
+        self.write_to_db(&output_file)?;
+
+        let mut input_file = TeaclaveInputFile::from_output(output_file)?;
+        input_file.uuid = Uuid::parse_str("00000000-0000-0000-0000-000000000002")?;
+        self.write_to_db(&input_file)?;
+
+        let function_input = FunctionInput::new("input", "input_desc");
+        let function_output = FunctionOutput::new("output", "output_desc");
+        let function_input2 = FunctionInput::new("input2", "input_desc");
+        let function_output2 = FunctionOutput::new("output2", "output_desc");
+
+        let function = Function::new()
+            .id(Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap())
+            .name("mock-func-1")
+            .description("mock-desc")
+            .payload(b"mock-payload".to_vec())
+            .public(true)
+            .arguments(vec!["arg1".to_string(), "arg2".to_string()])
+            .inputs(vec![function_input, function_input2])
+            .outputs(vec![function_output, function_output2])
+            .owner("teaclave".to_string());
+
+        self.write_to_db(&function)?;
+
+        let function_output = FunctionOutput::new("output", "output_desc");
+        let function = Function::new()
+            .id(Uuid::parse_str("00000000-0000-0000-0000-000000000002").unwrap())
+            .name("mock-func-2")
+            .description("mock-desc")
+            .payload(b"mock-payload".to_vec())
+            .public(true)
+            .arguments(vec!["arg1".to_string()])
+            .outputs(vec![function_output])
+            .owner("teaclave".to_string());
+
+        self.write_to_db(&function)?;
         Ok(())
     }
+}
+
+fn to_task_response(task: Task) -> GetTaskResponse {
+    let pending_approvals = task.pending_approvals();
+    GetTaskResponse {
+        task_id: task.external_id(),
+        creator: task.creator,
+        function_id: task.function_id,
+        function_owner: task.function_owner,
+        function_arguments: task.function_arguments,
+        inputs_ownership: task.inputs_ownership,
+        outputs_ownership: task.outputs_ownership,
+        participants: task.participants,
+        approved_users: task.approved_users,
+        pending_approvals,
+        assigned_inputs: task.assigned_inputs.external_ids(),
+        assigned_outputs: task.assigned_outputs.external_ids(),
+        input_samples: task.input_samples,
+        result: task.result,
+        status: task.status,
+        storage_degraded: false,
+    }
+}
+
+fn record_stage<T>(stages: &mut Vec<SelfTestStage>, name: &str, result: Result<T>) -> Option<T> {
+    match result {
+        Ok(value) => {
+            stages.push(SelfTestStage::new(name, true, ""));
+            Some(value)
+        }
+        Err(e) => {
+            stages.push(SelfTestStage::new(name, false, e));
+            None
+        }
+    }
+}
+
+fn connect_storage(endpoint: Endpoint) -> Result<RemoteStorage> {
+    let mut i = 0;
+    let channel = loop {
+        match endpoint.connect() {
+            Ok(channel) => break channel,
+            Err(_) => {
+                anyhow::ensure!(i < 10, "failed to connect to storage service");
+                log::debug!("Failed to connect to storage service, retry {}", i);
+                i += 1;
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_secs(3));
+    };
+    Ok(RemoteStorage::new(
+        TeaclaveStorageClient::new(channel)?,
+        endpoint,
+    ))
+}
+
+// Cursors are signed with the configured secret, or with a secret generated
+// once and kept in the storage, so that they stay valid across restarts and
+// replicas. Only if the storage is unreachable, cursors are signed with a
+// secret of this process.
+fn cursor_signer(config: &ManagementConfig, storage: &dyn StorageBackend) -> CursorSigner {
+    if let Some(hex_secret) = &config.cursor_signing_secret {
+        match hex::decode(hex_secret) {
+            Ok(secret) => return CursorSigner::from_secret(&secret),
+            Err(e) => log::error!("Invalid cursor signing secret: {:?}", e),
+        }
+    }
+    let key = CURSOR_SECRET_KEY.as_bytes();
+    match storage.get(key) {
+        Ok(secret) => CursorSigner::from_secret(&secret),
+        Err(e) if is_unavailable(&e) => {
+            log::warn!("Cannot read cursor secret, cursors are valid until restart");
+            CursorSigner::new()
+        }
+        Err(_) => {
+            let secret = CursorSigner::random_secret();
+            if let Err(e) = storage.put(key, &secret) {
+                log::warn!("Cannot store cursor secret: {:?}", e);
+                return CursorSigner::from_secret(&secret);
+            }
+            // Another replica may have stored its secret at the same time
+            let secret = storage.get(key).unwrap_or(secret);
+            CursorSigner::from_secret(&secret)
+        }
+    }
+}
+
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::result_callback::{ResultSummary, SignedResultSummary};
+    use crate::storage::MemoryStorage;
+    use ring::agreement;
+    use std::collections::HashMap;
+    use teaclave_config::AccessRule;
+    use teaclave_crypto::open_with_private_key;
+    use teaclave_types::{
+        hashmap, ArgumentValue, Executor, FunctionArguments, FunctionInput, FunctionInputFile,
+        FunctionOutput, FunctionOutputFile, InputSample, StagedFileInfo, TaskFailure,
+        TaskFileOwners, TaskGraphEdge, TaskOutputs, TaskResult,
+    };
+    use url::Url;
+
+    fn mock_service() -> TeaclaveManagementService {
+        mock_service_with_config(ManagementConfig::default())
+    }
+
+    fn mock_service_with_config(config: ManagementConfig) -> TeaclaveManagementService {
+        TeaclaveManagementService::with_storage(Arc::new(MemoryStorage::default()), config)
+    }
+
+    fn mock_request<T>(message: T, user_id: &str) -> Request<T> {
+        let mut request = Request::new(message);
+        request
+            .metadata_mut()
+            .insert("id".to_string(), user_id.to_string());
+        request
+    }
+
+    pub fn handle_input_file() {
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let cmac = FileAuthTag::mock();
+        let input_file =
+            TeaclaveInputFile::new(url, cmac, FileCrypto::default(), vec!["mock_user"]);
+        assert!(TeaclaveInputFile::match_prefix(&input_file.key_string()));
+        let service = mock_service();
+        service.write_to_db(&input_file).unwrap();
+        let deserialized_file: TeaclaveInputFile =
+            service.read_from_db(&input_file.external_id()).unwrap();
+        info!("file: {:?}", deserialized_file);
+    }
+
+    pub fn handle_sealed_crypto_info() {
+        let service = mock_service();
+        let rng = ring::rand::SystemRandom::new();
+        let private_key =
+            agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng).unwrap();
+        let public_key = private_key.compute_public_key().unwrap();
+
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let crypto_info = FileCrypto::default();
+        let request = RegisterInputFileRequest::new(url, FileAuthTag::mock(), crypto_info)
+            .recipient_public_key(public_key.as_ref().to_vec());
+        let data_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let request = GetInputFileRequest::new(data_id);
+        let response = service
+            .get_input_file(mock_request(request, "mock_user"))
+            .unwrap();
+        let sealed = response.sealed_crypto_info.unwrap();
+        let opened = open_with_private_key(private_key, &sealed).unwrap();
+        let opened: FileCrypto = serde_json::from_slice(&opened).unwrap();
+        assert_eq!(opened, crypto_info);
+    }
+
+    pub fn handle_input_file_metadata() {
+        let service = mock_service();
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let request = RegisterInputFileRequest::new(url, FileAuthTag::mock(), FileCrypto::Raw)
+            .expected_size(1024)
+            .source("mock_source");
+        let data_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let input_file: TeaclaveInputFile = service.read_from_db(&data_id).unwrap();
+        assert_eq!(input_file.metadata.size, Some(1024));
+        assert_eq!(input_file.metadata.source, Some("mock_source".to_string()));
+        assert!(input_file.metadata.created_at > 0);
+        let bytes = serde_json::to_vec(&input_file).unwrap();
+        let deserialized: TeaclaveInputFile = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(deserialized.metadata, input_file.metadata);
+
+        let request = GetInputFileRequest::new(data_id);
+        let response = service
+            .get_input_file(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.metadata, input_file.metadata);
+
+        let data = b"inline data".to_vec();
+        let request =
+            RegisterInputFileRequest::new_inline(data, FileAuthTag::mock(), FileCrypto::Raw);
+        let data_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+        let input_file: TeaclaveInputFile = service.read_from_db(&data_id).unwrap();
+        assert_eq!(input_file.metadata.size, Some(11));
+        assert_eq!(input_file.metadata.source, None);
+    }
+
+    pub fn handle_unique_iv() {
+        let mut config = ManagementConfig::default();
+        config.enforce_unique_iv = true;
+        let service = mock_service_with_config(config);
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let crypto_info = FileCrypto::new("aes-gcm-128", &[1; 16], &[2; 12]).unwrap();
+
+        let request = RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), crypto_info);
+        assert!(service
+            .register_input_file(mock_request(request, "mock_user"))
+            .is_ok());
+        let request = RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), crypto_info);
+        assert!(service
+            .register_input_file(mock_request(request, "mock_user"))
+            .is_err());
+        let request = RegisterOutputFileRequest::new(url.clone(), crypto_info);
+        assert!(service
+            .register_output_file(mock_request(request, "mock_user"))
+            .is_err());
+
+        // a fresh iv and other users are not affected
+        let fresh_crypto_info = FileCrypto::new("aes-gcm-128", &[1; 16], &[3; 12]).unwrap();
+        let request = RegisterOutputFileRequest::new(url.clone(), fresh_crypto_info);
+        assert!(service
+            .register_output_file(mock_request(request, "mock_user"))
+            .is_ok());
+        let request = RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), crypto_info);
+        assert!(service
+            .register_input_file(mock_request(request, "other_user"))
+            .is_ok());
+
+        // reuse is accepted unless enforced
+        let service = mock_service();
+        for _ in 0..2 {
+            let request =
+                RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), crypto_info);
+            assert!(service
+                .register_input_file(mock_request(request, "mock_user"))
+                .is_ok());
+        }
+    }
+
+    pub fn handle_output_file() {
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let output_file = TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user"]);
+        assert!(TeaclaveOutputFile::match_prefix(&output_file.key_string()));
+        let service = mock_service();
+        service.write_to_db(&output_file).unwrap();
+        let deserialized_file: TeaclaveOutputFile =
+            service.read_from_db(&output_file.external_id()).unwrap();
+        info!("file: {:?}", deserialized_file);
+
+        service.delete_from_db(&output_file.external_id()).unwrap();
+        assert!(service
+            .read_from_db::<TeaclaveOutputFile>(&output_file.external_id())
+            .is_err());
+    }
+
+    pub fn handle_output_presigned_url() {
+        let url = Url::parse("https://bucket.s3.amazonaws.com/output?X-Amz-Signature=put").unwrap();
+        let output_file = TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user"]);
+        let request = || GenerateOutputPresignedUrlRequest::new(output_file.external_id());
+
+        // disabled by default
+        let service = mock_service();
+        service.write_to_db(&output_file).unwrap();
+        let response = service.generate_output_presigned_url(mock_request(request(), "mock_user"));
+        assert!(response.is_err());
+
+        let mut config = ManagementConfig::default();
+        config.enable_output_presigned_url = true;
+        config.output_presigned_url_expiry_seconds = 600;
+        config.s3 = Some(teaclave_config::S3Credential {
+            access_key_id: "mock_key_id".to_string(),
+            secret_access_key: "mock_secret".to_string(),
+            region: "us-east-1".to_string(),
+        });
+        let service = mock_service_with_config(config);
+        service.write_to_db(&output_file).unwrap();
+
+        let response =
+            service.generate_output_presigned_url(mock_request(request(), "mock_user_b"));
+        assert!(response.is_err());
+
+        let response = service
+            .generate_output_presigned_url(mock_request(request(), "mock_user"))
+            .unwrap();
+        let query: HashMap<_, _> = response.url.query_pairs().into_owned().collect();
+        assert_eq!(query["X-Amz-Expires"], "600");
+        assert!(query.contains_key("X-Amz-Signature"));
+        assert_ne!(query["X-Amz-Signature"], "put");
+        assert_eq!(response.url.path(), "/output");
+        assert!(response.expires_at >= current_timestamp() + 600 - 1);
+    }
+
+    pub fn handle_output_access_token() {
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let output_file = TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user"]);
+        let request = || CreateOutputAccessTokenRequest::new(output_file.external_id());
+
+        // disabled by default
+        let service = mock_service();
+        service.write_to_db(&output_file).unwrap();
+        let response = service.create_output_access_token(mock_request(request(), "mock_user"));
+        assert!(response.is_err());
+
+        let mut config = ManagementConfig::default();
+        config.enable_output_access_tokens = true;
+        let service = mock_service_with_config(config);
+        service.write_to_db(&output_file).unwrap();
+
+        let response = service.create_output_access_token(mock_request(request(), "mock_user_b"));
+        assert!(response.is_err());
+        let token = service
+            .create_output_access_token(mock_request(request(), "mock_user"))
+            .unwrap()
+            .token;
+
+        let get = |token: &str| {
+            let request = GetOutputFileRequest::new(output_file.external_id()).access_token(token);
+            service.get_output_file(mock_request(request, "mock_user_b"))
+        };
+        let request = GetOutputFileRequest::new(output_file.external_id());
+        assert!(service
+            .get_output_file(mock_request(request, "mock_user_b"))
+            .is_err());
+        assert!(get("unknown").is_err());
+        let response = get(&token).unwrap();
+        assert_eq!(response.owner, output_file.owner);
+
+        // the token is bound to the output
+        let other_file = TeaclaveOutputFile::new(
+            Url::parse("s3://bucket_id/other?token=mock_token").unwrap(),
+            FileCrypto::default(),
+            vec!["mock_user"],
+        );
+        service.write_to_db(&other_file).unwrap();
+        let request = GetOutputFileRequest::new(other_file.external_id()).access_token(&token);
+        assert!(service
+            .get_output_file(mock_request(request, "mock_user_b"))
+            .is_err());
+
+        // expired tokens no longer grant access
+        let key = output_access_token_storage_key(&token);
+        let mut record: OutputAccessToken =
+            serde_json::from_slice(&service.read_bytes_from_db(key.clone()).unwrap()).unwrap();
+        record.expires_at = current_timestamp() - 1;
+        service
+            .storage
+            .put(&key, &serde_json::to_vec(&record).unwrap())
+            .unwrap();
+        assert!(get(&token).is_err());
+    }
+
+    pub fn handle_redact_output_owners() {
+        let output_file = TeaclaveOutputFile::new(
+            Url::parse("s3://bucket_id/path?token=mock_token").unwrap(),
+            FileCrypto::default(),
+            vec!["mock_user", "mock_user_b", "mock_user_c"],
+        );
+        let get = |service: &TeaclaveManagementService| {
+            let request = GetOutputFileRequest::new(output_file.external_id());
+            service
+                .get_output_file(mock_request(request, "mock_user"))
+                .unwrap()
+        };
+
+        let service = mock_service();
+        service.write_to_db(&output_file).unwrap();
+        let response = get(&service);
+        assert_eq!(response.owner, output_file.owner);
+        assert_eq!(response.owner_count, 3);
+
+        let mut config = ManagementConfig::default();
+        config.redact_output_owners = true;
+        let service = mock_service_with_config(config);
+        service.write_to_db(&output_file).unwrap();
+        let response = get(&service);
+        assert_eq!(response.owner, OwnerList::new(vec!["mock_user"]));
+        assert!(!response.owner.contains(&UserID::from("mock_user_b")));
+        assert_eq!(response.owner_count, 3);
+    }
+
+    pub fn handle_cmac_verification() {
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let output_file = TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user"]);
+
+        // an unfinished output is rejected in strict mode
+        let service = mock_service();
+        service.write_to_db(&output_file).unwrap();
+        let request = RegisterInputFromOutputRequest::new(output_file.external_id());
+        let response = service.register_input_from_output(mock_request(request, "mock_user"));
+        assert!(response.is_err());
+
+        // and accepted in permissive mode with the cmac pending
+        let mut config = ManagementConfig::default();
+        config.cmac_verification = CmacVerification::Permissive;
+        let service = mock_service_with_config(config);
+        service.write_to_db(&output_file).unwrap();
+        let request = RegisterInputFromOutputRequest::new(output_file.external_id());
+        let input_id = service
+            .register_input_from_output(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+        let input_file: TeaclaveInputFile = service.read_from_db(&input_id).unwrap();
+        assert!(input_file.cmac_pending);
+    }
+
+    pub fn handle_partial_output() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user");
+        let mut task = Task::new(
+            UserID::from("mock_user"),
+            Executor::Builtin,
+            FunctionArguments::default(),
+            HashMap::<String, OwnerList>::new(),
+            hashmap!("output" => vec!["mock_user"]),
+            function,
+        )
+        .unwrap();
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let output_file = TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user"]);
+        task.assign_output(&UserID::from("mock_user"), "output", output_file.clone())
+            .unwrap();
+        // the executor exposes a partial output of the running task
+        task.status = TaskStatus::Running;
+        task.update_partial_output("output", FileAuthTag::mock())
+            .unwrap();
+        let service = mock_service();
+        service.write_to_db(&task).unwrap();
+        service.write_to_db(&output_file).unwrap();
+
+        let request = GetTaskRequest::new(task.external_id());
+        let response = service
+            .get_task(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.status, TaskStatus::Running);
+        match response.result {
+            TaskResult::InProgress(tags) => {
+                assert_eq!(tags.get("output"), Some(&FileAuthTag::mock()))
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        // the partial output cannot be consumed before the task finishes
+        let request = RegisterInputFromOutputRequest::new(output_file.external_id());
+        assert!(service
+            .register_input_from_output(mock_request(request, "mock_user"))
+            .is_err());
+    }
+
+    pub fn handle_output_cmac_integrity() {
+        let path = "/tmp/management_cmac_integrity.txt";
+        let staged = StagedFileInfo::create_with_bytes(path, b"Hello Teaclave!").unwrap();
+        let url = Url::parse(&format!("file://{}", path)).unwrap();
+        let mut output_file = TeaclaveOutputFile::new(
+            url,
+            FileCrypto::TeaclaveFile128(staged.crypto_info),
+            vec!["mock_user"],
+        );
+        output_file.assign_cmac(&staged.cmac).unwrap();
+
+        let mut config = ManagementConfig::default();
+        config.verify_output_cmac = true;
+        let service = mock_service_with_config(config);
+        service.write_to_db(&output_file).unwrap();
+        let request = GetOutputFileRequest::new(output_file.external_id());
+        assert!(service
+            .get_output_file(mock_request(request, "mock_user"))
+            .is_ok());
+
+        // corrupt the stored object
+        let mut bytes = std::untrusted::fs::read(path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::untrusted::fs::write(path, &bytes).unwrap();
+
+        let request = GetOutputFileRequest::new(output_file.external_id());
+        assert!(service
+            .get_output_file(mock_request(request, "mock_user"))
+            .is_err());
+        let request = RegisterInputFromOutputRequest::new(output_file.external_id());
+        assert!(service
+            .register_input_from_output(mock_request(request, "mock_user"))
+            .is_err());
+
+        std::untrusted::fs::remove_file(path).unwrap();
+    }
+
+    pub fn handle_consumption_grace_period() {
+        let mut config = ManagementConfig::default();
+        config.consumption_grace_period_seconds = Some(60);
+        let service = mock_service_with_config(config);
+
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let mut output_file =
+            TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user"]);
+        output_file.assign_cmac(&FileAuthTag::mock()).unwrap();
+
+        // a freshly finalized output can be read but not consumed
+        output_file.finalized_at = Some(current_timestamp());
+        service.write_to_db(&output_file).unwrap();
+        let request = GetOutputFileRequest::new(output_file.external_id());
+        assert!(service
+            .get_output_file(mock_request(request, "mock_user"))
+            .is_ok());
+        let request = RegisterInputFromOutputRequest::new(output_file.external_id());
+        let response = service.register_input_from_output(mock_request(request, "mock_user"));
+        assert!(response.is_err());
+
+        // and consumed once the grace period has passed
+        output_file.finalized_at = Some(current_timestamp() - 61);
+        service.write_to_db(&output_file).unwrap();
+        let request = RegisterInputFromOutputRequest::new(output_file.external_id());
+        let response = service.register_input_from_output(mock_request(request, "mock_user"));
+        assert!(response.is_ok());
+    }
+
+    pub fn handle_file_index() {
+        let mut config = ManagementConfig::default();
+        config.cmac_verification = CmacVerification::Permissive;
+        let service = mock_service_with_config(config);
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let request = RegisterOutputFileRequest::new(url, FileCrypto::default());
+        let output_id = service
+            .register_output_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let request = ListOutputFilesRequest::new(None, 10);
+        let response = service
+            .list_output_files(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.files.len(), 1);
+        assert!(!response.files[0].consumed);
+
+        // the output is consumed once registered as an input
+        let request = RegisterInputFromOutputRequest::new(output_id);
+        let input_id = service
+            .register_input_from_output(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+        let request = ListOutputFilesRequest::new(None, 10);
+        let response = service
+            .list_output_files(mock_request(request, "mock_user"))
+            .unwrap();
+        assert!(response.files[0].consumed);
+
+        let request = ListInputFilesRequest::new(None, 10);
+        let response = service
+            .list_input_files(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.files.len(), 1);
+        assert_eq!(response.files[0].data_id, input_id);
+        assert_eq!(response.next_cursor, None);
+    }
+
+    pub fn handle_max_url_length() {
+        let mut config = ManagementConfig::default();
+        config.max_url_length = 64;
+        let service = mock_service_with_config(config);
+
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let request =
+            RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), FileCrypto::Raw);
+        assert!(service
+            .register_input_file(mock_request(request, "mock_user"))
+            .is_ok());
+
+        let url = Url::parse(&format!("{}{}", url, "a".repeat(64))).unwrap();
+        let request =
+            RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), FileCrypto::Raw);
+        assert!(service
+            .register_input_file(mock_request(request, "mock_user"))
+            .is_err());
+        let request = RegisterOutputFileRequest::new(url, FileCrypto::Raw);
+        assert!(service
+            .register_output_file(mock_request(request, "mock_user"))
+            .is_err());
+    }
+
+    pub fn handle_inline_input_file() {
+        let mut config = ManagementConfig::default();
+        config.max_inline_input_size = 16;
+        let service = mock_service_with_config(config);
+
+        let data = vec![0u8; 17];
+        let request =
+            RegisterInputFileRequest::new_inline(data, FileAuthTag::default(), FileCrypto::Raw);
+        assert!(service
+            .register_input_file(mock_request(request, "mock_user"))
+            .is_err());
+
+        let data = b"inline data".to_vec();
+        let request = RegisterInputFileRequest::new_inline(
+            data.clone(),
+            FileAuthTag::default(),
+            FileCrypto::Raw,
+        );
+        let input_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .inputs(vec![FunctionInput::new("input", "input_desc")])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .inputs_ownership(hashmap!("input" => vec!["mock_user"]));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request = AssignDataRequest::new(
+            task_id.clone(),
+            hashmap!("input" => input_id),
+            HashMap::new(),
+        );
+        service
+            .assign_data(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = InvokeTaskRequest::new(task_id);
+        service
+            .invoke_task(mock_request(request, "mock_user"))
+            .unwrap();
+
+        // the data is staged with the task instead of being downloaded
+        let queue_key = StagedTask::get_queue_key().as_bytes();
+        let value = service.dequeue_from_db(queue_key).unwrap();
+        let staged_task = StagedTask::from_slice(&value).unwrap();
+        let (_, input) = staged_task.input_data.iter().next().unwrap();
+        assert_eq!(input.inline_data, Some(data));
+    }
+
+    pub fn handle_lazy_input() {
+        let base_dir = "/tmp/teaclave_lazy_input";
+        std::untrusted::fs::create_dir_all(base_dir).unwrap();
+        let service = mock_service();
+
+        // inline data cannot be lazy
+        let request = RegisterInputFileRequest::new_inline(
+            b"inline data".to_vec(),
+            FileAuthTag::mock(),
+            FileCrypto::Raw,
+        )
+        .lazy(true);
+        assert!(service
+            .register_input_file(mock_request(request, "mock_user"))
+            .is_err());
+
+        // the remote file doesn't exist when the input is registered
+        let path = format!("{}/input", base_dir);
+        let url = Url::parse(&format!("file://{}", path)).unwrap();
+        let request =
+            RegisterInputFileRequest::new(url, FileAuthTag::mock(), FileCrypto::Raw).lazy(true);
+        let input_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .inputs(vec![FunctionInput::new("input", "input_desc")])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .inputs_ownership(hashmap!("input" => vec!["mock_user"]));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request = AssignDataRequest::new(
+            task_id.clone(),
+            hashmap!("input" => input_id),
+            HashMap::new(),
+        );
+        service
+            .assign_data(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+
+        // the task is not staged until the remote file exists
+        let request = InvokeTaskRequest::new(task_id.clone());
+        let error = service
+            .invoke_task(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            TeaclaveServiceResponseError::from(ServiceError::LazyInputUnavailable(
+                "input".to_string()
+            ))
+        );
+        let task = service.read_from_db::<Task>(&task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Approved);
+
+        std::untrusted::fs::write(&path, b"input").unwrap();
+        let request = InvokeTaskRequest::new(task_id);
+        service
+            .invoke_task(mock_request(request, "mock_user"))
+            .unwrap();
+
+        std::untrusted::fs::remove_dir_all(base_dir).unwrap();
+    }
+
+    pub fn handle_delete_input_file() {
+        let base_dir = "/tmp/teaclave_delete_input_file";
+        std::untrusted::fs::create_dir_all(base_dir).unwrap();
+        let service = mock_service();
+        let register_input = |name: &str| {
+            let path = format!("{}/{}", base_dir, name);
+            std::untrusted::fs::write(&path, b"input").unwrap();
+            let url = Url::parse(&format!("file://{}", path)).unwrap();
+            let request = RegisterInputFileRequest::new(url, FileAuthTag::mock(), FileCrypto::Raw);
+            let data_id = service
+                .register_input_file(mock_request(request, "mock_user"))
+                .unwrap()
+                .data_id;
+            (data_id, std::path::PathBuf::from(path))
+        };
+
+        // other users cannot delete the input
+        let (data_id, path) = register_input("exclusive");
+        let request = DeleteInputFileRequest::new(data_id.clone()).delete_remote(true);
+        assert!(service
+            .delete_input_file(mock_request(request, "mock_user_b"))
+            .is_err());
+
+        // the remote file of an exclusive and unconsumed input is deleted
+        let request = DeleteInputFileRequest::new(data_id.clone()).delete_remote(true);
+        service
+            .delete_input_file(mock_request(request, "mock_user"))
+            .unwrap();
+        assert!(!path.exists());
+        assert!(service.read_from_db::<TeaclaveInputFile>(&data_id).is_err());
+
+        // the remote file of a consumed input is kept
+        let (data_id, path) = register_input("consumed");
+        service
+            .update_file_index(&OwnerList::from(vec!["mock_user"]), |index| {
+                index.mark_consumed(FileKind::Input, &data_id)
+            })
+            .unwrap();
+        let request = DeleteInputFileRequest::new(data_id.clone()).delete_remote(true);
+        assert!(service
+            .delete_input_file(mock_request(request, "mock_user"))
+            .is_err());
+        assert!(path.exists());
+        let request = DeleteInputFileRequest::new(data_id.clone());
+        service
+            .delete_input_file(mock_request(request, "mock_user"))
+            .unwrap();
+        assert!(path.exists());
+        assert!(service.read_from_db::<TeaclaveInputFile>(&data_id).is_err());
+
+        // shared inputs cannot be deleted
+        let url = Url::parse(&format!("file://{}/shared", base_dir)).unwrap();
+        let input_file = TeaclaveInputFile::new(
+            url,
+            FileAuthTag::mock(),
+            FileCrypto::Raw,
+            vec!["mock_user", "mock_user_b"],
+        );
+        service.write_to_db(&input_file).unwrap();
+        let request = DeleteInputFileRequest::new(input_file.external_id()).delete_remote(true);
+        assert!(service
+            .delete_input_file(mock_request(request, "mock_user"))
+            .is_err());
+        assert!(service
+            .read_from_db::<TeaclaveInputFile>(&input_file.external_id())
+            .is_ok());
+
+        std::untrusted::fs::remove_dir_all(base_dir).unwrap();
+    }
+
+    pub fn handle_assign_data_response() {
+        let service = mock_service();
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .inputs(vec![FunctionInput::new("input", "input_desc")])
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .inputs_ownership(hashmap!("input" => vec!["mock_user"]))
+            .outputs_ownership(hashmap!("output" => vec!["mock_user"]));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+
+        let url = Url::parse("s3://s3.us-west-2.amazonaws.com/mybucket/input").unwrap();
+        let request = RegisterInputFileRequest::new(url, FileAuthTag::mock(), FileCrypto::Raw);
+        let input_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+        let url = Url::parse("s3://s3.us-west-2.amazonaws.com/mybucket/output").unwrap();
+        let request = RegisterOutputFileRequest::new(url, FileCrypto::Raw);
+        let output_id = service
+            .register_output_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let request = AssignDataRequest::new(
+            task_id.clone(),
+            hashmap!("input" => input_id.clone()),
+            HashMap::new(),
+        );
+        let response = service
+            .assign_data(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(
+            response.inputs,
+            hashmap!("input".to_string() => input_id.clone())
+        );
+        assert!(response.outputs.is_empty());
+        assert!(!response.ready);
+
+        // earlier assignments are reported along with the new ones
+        let request = AssignDataRequest::new(
+            task_id,
+            HashMap::new(),
+            hashmap!("output" => output_id.clone()),
+        );
+        let response = service
+            .assign_data(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.inputs, hashmap!("input".to_string() => input_id));
+        assert_eq!(
+            response.outputs,
+            hashmap!("output".to_string() => output_id)
+        );
+        assert!(response.ready);
+    }
+
+    pub fn handle_storage_metrics() {
+        let service = mock_service();
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        service
+            .read_from_db::<Function>(&function.external_id())
+            .unwrap();
+        service.enqueue_to_db(b"mock-queue", &function).unwrap();
+        service.dequeue_from_db(b"mock-queue").unwrap();
+
+        let response = service
+            .get_metrics(mock_request(GetMetricsRequest::new(), "mock_admin"))
+            .unwrap();
+        for operation in &["get", "put", "enqueue", "dequeue"] {
+            let latency = response
+                .storage_latencies
+                .iter()
+                .find(|latency| &latency.operation == operation)
+                .unwrap();
+            assert!(latency.count >= 1);
+            assert!(latency.min_micros <= latency.avg_micros);
+            assert!(latency.avg_micros <= latency.max_micros);
+        }
+    }
+
+    // Backend of which every operation fails as if the storage service is
+    // unreachable.
+    struct UnreachableStorage;
+
+    impl StorageBackend for UnreachableStorage {
+        fn get(&self, _key: &[u8]) -> Result<Vec<u8>> {
+            Err(anyhow!("connection refused"))
+        }
+
+        fn put(&self, _key: &[u8], _value: &[u8]) -> Result<()> {
+            Err(anyhow!("connection refused"))
+        }
+
+        fn delete(&self, _key: &[u8]) -> Result<()> {
+            Err(anyhow!("connection refused"))
+        }
+
+        fn enqueue(&self, _key: &[u8], _value: &[u8]) -> Result<()> {
+            Err(anyhow!("connection refused"))
+        }
+
+        fn dequeue(&self, _key: &[u8]) -> Result<Vec<u8>> {
+            Err(anyhow!("connection refused"))
+        }
+    }
+
+    pub fn handle_storage_error_cause() {
+        let mut config = ManagementConfig::default();
+        config.crypto_key_encryption_key = Some(hex::encode([1u8; 32]));
+        let service = TeaclaveManagementService::with_storage(Arc::new(UnreachableStorage), config);
+
+        let request = RegisterCryptoKeyRequest::new("default", FileCrypto::default());
+        let error = service
+            .register_crypto_key(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            TeaclaveServiceResponseError::RequestError(
+                "storage error: connection refused".to_string()
+            )
+        );
+    }
+
+    // Backend failing with connection errors while it is down.
+    #[derive(Default)]
+    struct FlakyStorage {
+        inner: MemoryStorage,
+        down: std::sync::atomic::AtomicBool,
+    }
+
+    impl FlakyStorage {
+        fn set_down(&self, down: bool) {
+            self.down.store(down, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        fn check(&self) -> Result<()> {
+            if self.down.load(std::sync::atomic::Ordering::Relaxed) {
+                return Err(TeaclaveServiceResponseError::ConnectionError(
+                    "connection refused".to_string(),
+                )
+                .into());
+            }
+            Ok(())
+        }
+    }
+
+    impl StorageBackend for FlakyStorage {
+        fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+            self.check()?;
+            self.inner.get(key)
+        }
+
+        fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+            self.check()?;
+            self.inner.put(key, value)
+        }
+
+        fn delete(&self, key: &[u8]) -> Result<()> {
+            self.check()?;
+            self.inner.delete(key)
+        }
+
+        fn enqueue(&self, key: &[u8], value: &[u8]) -> Result<()> {
+            self.check()?;
+            self.inner.enqueue(key, value)
+        }
+
+        fn dequeue(&self, key: &[u8]) -> Result<Vec<u8>> {
+            self.check()?;
+            self.inner.dequeue(key)
+        }
+    }
+
+    // Backend failing to enqueue into one queue, e.g., the notifications of a
+    // user.
+    #[derive(Default)]
+    struct FailingQueueStorage {
+        inner: MemoryStorage,
+        failing_key: std::sync::SgxMutex<Option<Vec<u8>>>,
+    }
+
+    impl FailingQueueStorage {
+        fn set_failing_key(&self, key: Option<&[u8]>) {
+            *self.failing_key.lock().unwrap() = key.map(|key| key.to_vec());
+        }
+    }
+
+    impl StorageBackend for FailingQueueStorage {
+        fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+            self.inner.get(key)
+        }
+
+        fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+            self.inner.put(key, value)
+        }
+
+        fn delete(&self, key: &[u8]) -> Result<()> {
+            self.inner.delete(key)
+        }
+
+        fn enqueue(&self, key: &[u8], value: &[u8]) -> Result<()> {
+            if self.failing_key.lock().unwrap().as_deref() == Some(key) {
+                return Err(anyhow!("connection refused"));
+            }
+            self.inner.enqueue(key, value)
+        }
+
+        fn dequeue(&self, key: &[u8]) -> Result<Vec<u8>> {
+            self.inner.dequeue(key)
+        }
+
+        fn queue_len(&self, key: &[u8]) -> Result<u64> {
+            self.inner.queue_len(key)
+        }
+
+        fn queue_range(&self, key: &[u8], start: u64, limit: usize) -> Result<Vec<Vec<u8>>> {
+            self.inner.queue_range(key, start, limit)
+        }
+    }
+
+    pub fn handle_degraded_storage() {
+        let mut config = ManagementConfig::default();
+        config.degraded_cache_ttl_seconds = 60;
+        config.degraded_write_queue_depth = 2;
+        let storage = Arc::new(FlakyStorage::default());
+        let service = TeaclaveManagementService::with_storage(storage.clone(), config);
+        let new_function = |name: &str| {
+            Function::new()
+                .id(Uuid::new_v4())
+                .name(name)
+                .public(true)
+                .owner("mock_user")
+        };
+        let get_function = |function_id: ExternalID| {
+            let request = GetFunctionRequest::new(function_id);
+            service.get_function(mock_request(request, "mock_user"))
+        };
+        let get_metrics = || {
+            service
+                .get_metrics(mock_request(GetMetricsRequest::new(), "mock_admin"))
+                .unwrap()
+        };
+
+        let cached = new_function("cached_function");
+        service.write_to_db(&cached).unwrap();
+        let uncached = new_function("uncached_function");
+        storage
+            .inner
+            .put(&uncached.key(), &uncached.to_vec().unwrap())
+            .unwrap();
+        assert!(!get_function(cached.external_id()).unwrap().storage_degraded);
+
+        // cached reads succeed and writes are queued while storage is down
+        storage.set_down(true);
+        assert!(get_function(cached.external_id()).unwrap().storage_degraded);
+        assert!(get_function(uncached.external_id()).is_err());
+
+        let queued = new_function("queued_function");
+        service.write_to_db(&queued).unwrap();
+        assert!(get_function(queued.external_id()).unwrap().storage_degraded);
+        service.delete_from_db(&cached.external_id()).unwrap();
+        assert!(get_function(cached.external_id()).is_err());
+        let metrics = get_metrics();
+        assert!(metrics.storage_degraded);
+        assert_eq!(metrics.pending_storage_writes, 2);
+        assert!(storage.inner.get(&queued.key()).is_err());
+
+        // writes beyond the queue depth fail
+        assert!(service
+            .write_to_db(&new_function("dropped_function"))
+            .is_err());
+
+        // queued writes are replayed in order once storage is back
+        storage.set_down(false);
+        assert!(!get_function(queued.external_id()).unwrap().storage_degraded);
+        assert!(storage.inner.get(&queued.key()).is_ok());
+        assert!(storage.inner.get(&cached.key()).is_err());
+        let metrics = get_metrics();
+        assert!(!metrics.storage_degraded);
+        assert_eq!(metrics.pending_storage_writes, 0);
+
+        // only the most recently cached values are kept
+        let mut config = ManagementConfig::default();
+        config.degraded_cache_ttl_seconds = 60;
+        config.degraded_cache_capacity = 2;
+        let storage = Arc::new(FlakyStorage::default());
+        let service = TeaclaveManagementService::with_storage(storage.clone(), config);
+        let functions: Vec<Function> = (0..3)
+            .map(|i| new_function(&format!("function_{}", i)))
+            .collect();
+        for function in functions.iter() {
+            service.write_to_db(function).unwrap();
+        }
+        storage.set_down(true);
+        assert!(service
+            .read_from_db::<Function>(&functions[0].external_id())
+            .is_err());
+        assert!(service
+            .read_from_db::<Function>(&functions[2].external_id())
+            .is_ok());
+    }
+
+    pub fn handle_binary_argument() {
+        let service = mock_service();
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .arguments(vec!["key".to_string()])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+
+        let function_arguments = FunctionArguments::new(hashmap!(
+            "key" => ArgumentValue::from_base64("not base64!".to_string())
+        ));
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .function_arguments(function_arguments)
+            .executor(Executor::Builtin);
+        assert!(service
+            .create_task(mock_request(request, "mock_user"))
+            .is_err());
+
+        let key = [0u8, 0xff, b'\n', 0x80];
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .binary_function_argument("key", &key)
+            .executor(Executor::Builtin);
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = InvokeTaskRequest::new(task_id);
+        service
+            .invoke_task(mock_request(request, "mock_user"))
+            .unwrap();
+
+        let queue_key = StagedTask::get_queue_key().as_bytes();
+        let value = service.dequeue_from_db(queue_key).unwrap();
+        let staged_task = StagedTask::from_slice(&value).unwrap();
+        let argument = staged_task.function_arguments.get("key").unwrap();
+        assert!(argument.is_binary());
+        assert_eq!(argument.as_bytes().unwrap(), key.to_vec());
+    }
+
+    pub fn handle_optional_input() {
+        let service = mock_service();
+        let request = RegisterInputFileRequest::new_inline(
+            b"inline data".to_vec(),
+            FileAuthTag::mock(),
+            FileCrypto::Raw,
+        );
+        let input_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .inputs(vec![
+                FunctionInput::new("input", "input_desc"),
+                FunctionInput::new("extra", "extra_desc").optional(true),
+            ])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+
+        // required inputs cannot be omitted
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .inputs_ownership(hashmap!("extra" => vec!["mock_user"]));
+        assert!(service
+            .create_task(mock_request(request, "mock_user"))
+            .is_err());
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .inputs_ownership(hashmap!("input" => vec!["mock_user"]));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+
+        let request = AssignDataRequest::new(
+            task_id.clone(),
+            hashmap!("input" => input_id),
+            HashMap::new(),
+        );
+        service
+            .assign_data(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = InvokeTaskRequest::new(task_id);
+        service
+            .invoke_task(mock_request(request, "mock_user"))
+            .unwrap();
+
+        let queue_key = StagedTask::get_queue_key().as_bytes();
+        let value = service.dequeue_from_db(queue_key).unwrap();
+        let staged_task = StagedTask::from_slice(&value).unwrap();
+        let inputs: Vec<&String> = staged_task
+            .input_data
+            .iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(inputs, vec!["input"]);
+    }
+
+    pub fn handle_exclusive_inputs() {
+        let service = mock_service();
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .inputs(vec![
+                FunctionInput::new("inline", "inline_desc").exclusive_group("data"),
+                FunctionInput::new("file", "file_desc").exclusive_group("data"),
+            ])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+
+        let create_task = |inputs: Vec<&str>| {
+            let inputs_ownership: TaskFileOwners = inputs
+                .into_iter()
+                .map(|name| (name.to_string(), vec!["mock_user"]))
+                .collect();
+            let request = CreateTaskRequest::new()
+                .function_id(function.external_id())
+                .executor(Executor::Builtin)
+                .inputs_ownership(inputs_ownership);
+            service.create_task(mock_request(request, "mock_user"))
+        };
+
+        // exactly one of the alternatives is assigned
+        assert!(create_task(vec!["inline", "file"]).is_err());
+        assert!(create_task(vec![]).is_err());
+        assert!(create_task(vec!["inline"]).is_ok());
+        assert!(create_task(vec!["file"]).is_ok());
+    }
+
+    pub fn handle_sampled_input() {
+        let service = mock_service();
+        let request = RegisterInputFileRequest::new_inline(
+            b"inline data".to_vec(),
+            FileAuthTag::mock(),
+            FileCrypto::Raw,
+        );
+        let input_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .inputs(vec![FunctionInput::new("input", "input_desc")])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .inputs_ownership(hashmap!("input" => vec!["mock_user"]));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+
+        // only inputs assigned in the request can be sampled
+        let request = AssignDataRequest::new(task_id.clone(), HashMap::new(), HashMap::new())
+            .input_sample("input", InputSample::FirstBytes(6));
+        assert!(service
+            .assign_data(mock_request(request, "mock_user"))
+            .is_err());
+        let request = AssignDataRequest::new(
+            task_id.clone(),
+            hashmap!("input" => input_id),
+            HashMap::new(),
+        )
+        .input_sample("input", InputSample::FirstBytes(6));
+        service
+            .assign_data(mock_request(request, "mock_user"))
+            .unwrap();
+
+        // the sampled run is visible to participants
+        let request = GetTaskRequest::new(task_id.clone());
+        let response = service
+            .get_task(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(
+            response.input_samples.get("input"),
+            Some(&InputSample::FirstBytes(6))
+        );
+
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = InvokeTaskRequest::new(task_id);
+        service
+            .invoke_task(mock_request(request, "mock_user"))
+            .unwrap();
+
+        let queue_key = StagedTask::get_queue_key().as_bytes();
+        let value = service.dequeue_from_db(queue_key).unwrap();
+        let staged_task = StagedTask::from_slice(&value).unwrap();
+        let (_, input) = staged_task.input_data.iter().next().unwrap();
+        assert_eq!(input.sample, Some(InputSample::FirstBytes(6)));
+    }
+
+    pub fn handle_skip_unchanged_inputs() {
+        let service = mock_service();
+        let request = RegisterInputFileRequest::new_inline(
+            b"inline data".to_vec(),
+            FileAuthTag::mock(),
+            FileCrypto::Raw,
+        );
+        let input_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .inputs(vec![FunctionInput::new("input", "input_desc")])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .inputs_ownership(hashmap!("input" => vec!["mock_user"]));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request = AssignDataRequest::new(
+            task_id.clone(),
+            hashmap!("input" => input_id),
+            HashMap::new(),
+        );
+        service
+            .assign_data(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let invoke = || {
+            let request = InvokeTaskRequest::new(task_id.clone()).skip_if_inputs_unchanged(true);
+            service.invoke_task(mock_request(request, "mock_user"))
+        };
+
+        // the first invocation runs the task
+        assert!(!invoke().unwrap().skipped);
+        let queue_key = StagedTask::get_queue_key().as_bytes();
+        assert!(service.dequeue_from_db(queue_key).is_ok());
+        let mut task: Task = service.read_from_db(&task_id).unwrap();
+        task.invoking_by_executor().unwrap();
+        task.finish(TaskResult::Ok(TaskOutputs::new(
+            "return value",
+            HashMap::new(),
+        )))
+        .unwrap();
+        service
+            .write_task(&task, Some(TaskStatus::Running))
+            .unwrap();
+
+        // the second one is a no-op
+        assert!(invoke().unwrap().skipped);
+        assert!(service.dequeue_from_db(queue_key).is_err());
+        let request = InvokeTaskRequest::new(task_id.clone());
+        assert!(service
+            .invoke_task(mock_request(request, "mock_user"))
+            .is_err());
+    }
+
+    pub fn handle_reproduce_task() {
+        let service = mock_service();
+        let request = RegisterInputFileRequest::new_inline(
+            b"inline data".to_vec(),
+            FileAuthTag::mock(),
+            FileCrypto::Raw,
+        );
+        let input_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+        let output_url = Url::parse("s3://bucket_id/output?token=mock_token").unwrap();
+        let request = RegisterOutputFileRequest::new(output_url.clone(), FileCrypto::default());
+        let output_id = service
+            .register_output_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .payload(b"python script".to_vec())
+            .arguments(vec!["arg".to_string()])
+            .inputs(vec![FunctionInput::new("input", "input_desc")])
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .function_arguments(hashmap!("arg" => "data"))
+            .inputs_ownership(hashmap!("input" => vec!["mock_user"]))
+            .outputs_ownership(hashmap!("output" => vec!["mock_user"]));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request = AssignDataRequest::new(
+            task_id.clone(),
+            hashmap!("input" => input_id),
+            hashmap!("output" => output_id),
+        );
+        service
+            .assign_data(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = InvokeTaskRequest::new(task_id.clone());
+        service
+            .invoke_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let queue_key = StagedTask::get_queue_key().as_bytes();
+        let original =
+            StagedTask::from_slice(&service.dequeue_from_db(queue_key).unwrap()).unwrap();
+
+        let reproduction_url = Url::parse("s3://bucket_id/reproduction?token=mock_token").unwrap();
+        let reproduce = |user_id, url: &Url| {
+            let request =
+                ReproduceTaskRequest::new(task_id.clone(), hashmap!("output" => url.clone()));
+            service.reproduce_task(mock_request(request, user_id))
+        };
+
+        // only finished tasks can be reproduced
+        assert!(reproduce("mock_user", &reproduction_url).is_err());
+        let mut task: Task = service.read_from_db(&task_id).unwrap();
+        task.invoking_by_executor().unwrap();
+        let tags: HashMap<String, FileAuthTag> = hashmap!("output" => FileAuthTag::mock());
+        task.finish(TaskResult::Ok(TaskOutputs::new(
+            "return value",
+            tags.clone(),
+        )))
+        .unwrap();
+        service
+            .write_task(&task, Some(TaskStatus::Running))
+            .unwrap();
+
+        // outputs of the task are not overwritten, and only participants can
+        // reproduce the task
+        assert!(reproduce("mock_user", &output_url).is_err());
+        assert!(reproduce("other_user", &reproduction_url).is_err());
+        let reproduction_id = reproduce("mock_user", &reproduction_url)
+            .unwrap()
+            .reproduction_id;
+
+        // the run uses the same function, arguments and inputs
+        let staged = StagedTask::from_slice(&service.dequeue_from_db(queue_key).unwrap()).unwrap();
+        assert_eq!(staged.task_id, reproduction_id.uuid);
+        assert_eq!(staged.reproduction_of, Some(original.task_id));
+        assert_eq!(staged.function_id, original.function_id);
+        assert_eq!(staged.function_payload, original.function_payload);
+        assert_eq!(staged.executor, original.executor);
+        assert_eq!(
+            serde_json::to_value(&staged.function_arguments).unwrap(),
+            serde_json::to_value(&original.function_arguments).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&staged.input_data).unwrap(),
+            serde_json::to_value(&original.input_data).unwrap()
+        );
+        let (_, output) = staged.output_data.iter().next().unwrap();
+        assert_eq!(output.url, reproduction_url);
+        let (_, original_output) = original.output_data.iter().next().unwrap();
+        assert_eq!(output.crypto_info, original_output.crypto_info);
+
+        let get = |user_id| {
+            let request = GetTaskReproductionRequest::new(reproduction_id.clone());
+            service.get_task_reproduction(mock_request(request, user_id))
+        };
+        assert!(get("other_user").is_err());
+        let response = get("mock_user").unwrap();
+        assert_eq!(response.task_id, task_id);
+        assert_eq!(response.status, TaskStatus::Staged);
+        assert_eq!(response.matches, None);
+
+        // the result of the run is compared with that of the task, which is
+        // left untouched
+        let mut reproduction: TaskReproduction = service.read_from_db(&reproduction_id).unwrap();
+        reproduction.start();
+        reproduction.finish(&TaskResult::Ok(TaskOutputs::new("return value", tags)));
+        service.write_to_db(&reproduction).unwrap();
+        let response = get("mock_user").unwrap();
+        assert_eq!(response.status, TaskStatus::Finished);
+        assert_eq!(response.matches, Some(true));
+        reproduction.finish(&TaskResult::Ok(TaskOutputs::new(
+            "other value",
+            hashmap!("output" => FileAuthTag::mock()),
+        )));
+        service.write_to_db(&reproduction).unwrap();
+        assert_eq!(get("mock_user").unwrap().matches, Some(false));
+        let task: Task = service.read_from_db(&task_id).unwrap();
+        assert_eq!(task.result.unwrap().return_value, b"return value".to_vec());
+
+        // admins can reproduce any task
+        let request =
+            ReproduceTaskRequest::new(task_id.clone(), hashmap!("output" => reproduction_url));
+        let mut request = mock_request(request, "mock_admin");
+        request
+            .metadata_mut()
+            .insert("role".to_string(), "admin".to_string());
+        assert!(service.reproduce_task(request).is_ok());
+    }
+
+    pub fn handle_input_from_task_log() {
+        let service = mock_service();
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin);
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let register = |user_id| {
+            let request = RegisterInputFromTaskLogRequest::new(task_id.clone());
+            service.register_input_from_task_log(mock_request(request, user_id))
+        };
+        assert!(register("mock_user").is_err());
+
+        // the scheduler stores the log of the finished task
+        let mut task: Task = service.read_from_db(&task_id).unwrap();
+        task.status = TaskStatus::Running;
+        task.finish(TaskResult::Err(TaskFailure::new("mock failure")))
+            .unwrap();
+        let log = vec!["invoking function", "task failed: mock failure"];
+        let value = serde_json::to_vec(&log).unwrap();
+        service.storage.put(&task.log_key(), &value).unwrap();
+        task.log_stored = true;
+        service.write_to_db(&task).unwrap();
+
+        assert!(register("other_user").is_err());
+        let log_id = register("mock_user").unwrap().data_id;
+        let input: TeaclaveInputFile = service.read_from_db(&log_id).unwrap();
+        assert_eq!(
+            input.inline_data,
+            Some(b"invoking function\ntask failed: mock failure".to_vec())
+        );
+        assert_eq!(input.metadata.source, Some(task_id.to_string()));
+
+        // the log is an input of an analysis task
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_analysis")
+            .inputs(vec![FunctionInput::new("log", "log of a task")])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .inputs_ownership(hashmap!("log" => vec!["mock_user"]));
+        let analysis_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request = AssignDataRequest::new(
+            analysis_id.clone(),
+            hashmap!("log" => log_id),
+            HashMap::new(),
+        );
+        service
+            .assign_data(mock_request(request, "mock_user"))
+            .unwrap();
+        let task: Task = service.read_from_db(&analysis_id).unwrap();
+        assert_eq!(task.status, TaskStatus::DataAssigned);
+    }
+
+    pub fn handle_compressed_values() {
+        let mut config = ManagementConfig::default();
+        config.compress_values = true;
+        let service = mock_service_with_config(config);
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .payload(b"python script".repeat(1024))
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let value = service
+            .read_bytes_from_db(function.external_id().to_bytes())
+            .unwrap();
+        assert!(value.len() < function.to_vec().unwrap().len());
+        let read: Function = service.read_from_db(&function.external_id()).unwrap();
+        assert_eq!(read.payload, function.payload);
+
+        // values written before compression was enabled are still readable
+        let legacy = Function::new()
+            .id(Uuid::new_v4())
+            .name("legacy_function")
+            .public(true)
+            .owner("mock_user");
+        service
+            .storage
+            .put(&legacy.key(), &legacy.to_vec().unwrap())
+            .unwrap();
+        let read: Function = service.read_from_db(&legacy.external_id()).unwrap();
+        assert_eq!(read.name, "legacy_function");
+    }
+
+    pub fn handle_storage_replicas() {
+        let primary: Arc<dyn StorageBackend> = Arc::new(MemoryStorage::default());
+        let replica: Arc<dyn StorageBackend> = Arc::new(MemoryStorage::default());
+        let storage = ReplicatedStorage::new(primary.clone(), vec![replica.clone()], 0);
+        let service =
+            TeaclaveManagementService::with_storage(Arc::new(storage), ManagementConfig::default());
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        assert!(primary.get(&function.key()).is_ok());
+        assert!(replica.get(&function.key()).is_err());
+
+        // Reads are served by the replica once it has the key.
+        let replicated = Function::new()
+            .id(function.id)
+            .name("replicated_function")
+            .owner("mock_user");
+        replica
+            .put(&function.key(), &service.serialize(&replicated).unwrap())
+            .unwrap();
+        let read: Function = service.read_from_db(&function.external_id()).unwrap();
+        assert_eq!(read.name, "replicated_function");
+
+        // Recent writes are read from the primary in the read-your-writes mode.
+        let storage = ReplicatedStorage::new(primary.clone(), vec![replica.clone()], 60);
+        let service =
+            TeaclaveManagementService::with_storage(Arc::new(storage), ManagementConfig::default());
+        service.write_to_db(&function).unwrap();
+        let read: Function = service.read_from_db(&function.external_id()).unwrap();
+        assert_eq!(read.name, "mock_function");
+    }
+
+    pub fn handle_storage_namespace() {
+        let storage: Arc<dyn StorageBackend> = Arc::new(MemoryStorage::default());
+        let service_a = TeaclaveManagementService::with_storage(
+            Arc::new(NamespacedStorage::new(storage.clone(), "deployment_a")),
+            ManagementConfig::default(),
+        );
+        let service_b = TeaclaveManagementService::with_storage(
+            Arc::new(NamespacedStorage::new(storage.clone(), "deployment_b")),
+            ManagementConfig::default(),
+        );
+
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let output_file = TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user"]);
+        service_a.write_to_db(&output_file).unwrap();
+        let data_id = output_file.external_id();
+        assert!(service_a
+            .read_from_db::<TeaclaveOutputFile>(&data_id)
+            .is_ok());
+        assert!(service_b
+            .read_from_db::<TeaclaveOutputFile>(&data_id)
+            .is_err());
+        assert!(storage.get(&output_file.key()).is_err());
+
+        let queue_key = StagedTask::get_queue_key().as_bytes();
+        service_a
+            .enqueue_bytes_to_db(queue_key, b"staged".to_vec())
+            .unwrap();
+        assert!(service_b.dequeue_from_db(queue_key).is_err());
+        assert!(service_a.dequeue_from_db(queue_key).is_ok());
+    }
+
+    pub fn handle_function() {
+        let function_input = FunctionInput::new("input", "input_desc");
+        let function_output = FunctionOutput::new("output", "output_desc");
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .description("mock function")
+            .payload(b"python script".to_vec())
+            .arguments(vec!["arg".to_string()])
+            .inputs(vec![function_input])
+            .outputs(vec![function_output])
+            .public(true)
+            .owner("mock_user");
+        assert!(Function::match_prefix(&function.key_string()));
+        let service = mock_service();
+        service.write_to_db(&function).unwrap();
+        let deserialized_function: Function =
+            service.read_from_db(&function.external_id()).unwrap();
+        info!("function: {:?}", deserialized_function);
+    }
+
+    pub fn handle_crypto_keys() {
+        let mut config = ManagementConfig::default();
+        config.crypto_key_encryption_key = Some(hex::encode([1u8; 32]));
+        let service = mock_service_with_config(config);
+        let crypto = FileCrypto::default();
+
+        let request = RegisterCryptoKeyRequest::new("default", crypto);
+        service
+            .register_crypto_key(mock_request(request, "mock_user"))
+            .unwrap();
+        // the key is not stored in plaintext
+        let value = service
+            .read_bytes_from_db(crypto_key_storage_key(
+                &UserID::from("mock_user"),
+                "default",
+            ))
+            .unwrap();
+        let plaintext = serde_json::to_vec(&crypto).unwrap();
+        assert!(!value
+            .windows(plaintext.len())
+            .any(|window| window == plaintext.as_slice()));
+
+        // files are registered with the key referenced by name
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let request =
+            RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), FileCrypto::Raw)
+                .crypto_key_name("default");
+        let data_id = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+        let input_file: TeaclaveInputFile = service.read_from_db(&data_id).unwrap();
+        assert_eq!(input_file.crypto_info, crypto);
+
+        let request =
+            RegisterOutputFileRequest::new(url.clone(), FileCrypto::Raw).crypto_key_name("default");
+        let data_id = service
+            .register_output_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+        let output_file: TeaclaveOutputFile = service.read_from_db(&data_id).unwrap();
+        assert_eq!(output_file.crypto_info, crypto);
+
+        // keys are per user
+        let request =
+            RegisterOutputFileRequest::new(url, FileCrypto::Raw).crypto_key_name("default");
+        assert!(service
+            .register_output_file(mock_request(request, "mock_user_b"))
+            .is_err());
+
+        // keys cannot be registered without a key encryption key
+        let service = mock_service();
+        let request = RegisterCryptoKeyRequest::new("default", crypto);
+        assert!(service
+            .register_crypto_key(mock_request(request, "mock_user"))
+            .is_err());
+    }
+
+    pub fn handle_register_function_bundle() {
+        // The bundle is signed with the test key of the godzilla auditor
+        let fixture_dir = format!(
+            "{}/fixtures/functions/signed_bundle",
+            env!("TEACLAVE_TEST_INSTALL_DIR")
+        );
+        let read =
+            |name: &str| std::untrusted::fs::read(format!("{}/{}", fixture_dir, name)).unwrap();
+        let mut config = ManagementConfig::default();
+        config.function_publisher_keys_bytes = vec![read("publisher.public.der")];
+        let service = mock_service_with_config(config);
+
+        let request = RegisterFunctionBundleRequest::new(
+            read("payload.py"),
+            read("metadata.json"),
+            read("bundle.sign.sha256"),
+        );
+        let response = service
+            .register_function_bundle(mock_request(request, "mock_user"))
+            .unwrap();
+        let function: Function = service.read_from_db(&response.function_id).unwrap();
+        assert_eq!(function.name, "signed_echo");
+        assert_eq!(function.executor_type, ExecutorType::Python);
+        assert_eq!(function.owner, UserID::from("mock_user"));
+        assert!(function.public && function.verified);
+        assert_eq!(
+            response.payload_hash,
+            Function::hash_payload(&read("payload.py"))
+        );
+
+        // a tampered payload no longer matches the signature
+        let mut payload = read("payload.py");
+        payload.extend_from_slice(b"# tampered\n");
+        let request = RegisterFunctionBundleRequest::new(
+            payload,
+            read("metadata.json"),
+            read("bundle.sign.sha256"),
+        );
+        assert!(service
+            .register_function_bundle(mock_request(request, "mock_user"))
+            .is_err());
+
+        // bundles are rejected without configured publishers
+        let service = mock_service();
+        let request = RegisterFunctionBundleRequest::new(
+            read("payload.py"),
+            read("metadata.json"),
+            read("bundle.sign.sha256"),
+        );
+        assert!(service
+            .register_function_bundle(mock_request(request, "mock_user"))
+            .is_err());
+    }
+
+    pub fn handle_soft_delete_function() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        let function_id = function.external_id();
+        let register = |service: &TeaclaveManagementService| {
+            service.write_to_db(&function).unwrap();
+            service
+                .update_function_index(|index| index.add(&function))
+                .unwrap();
+        };
+        let get = |service: &TeaclaveManagementService| {
+            let request = GetFunctionRequest::new(function_id.clone());
+            service.get_function(mock_request(request, "mock_user"))
+        };
+        let listed = |service: &TeaclaveManagementService| {
+            let request = ListFunctionsRequest::new(None, 0);
+            let response = service
+                .list_functions(mock_request(request, "mock_user"))
+                .unwrap();
+            response
+                .functions
+                .iter()
+                .any(|entry| entry.function_id == function_id)
+        };
+        let soft_delete = |service: &TeaclaveManagementService, user_id| {
+            let request = SoftDeleteFunctionRequest::new(function_id.clone());
+            service.soft_delete_function(mock_request(request, user_id))
+        };
+
+        let service = mock_service();
+        register(&service);
+        assert!(soft_delete(&service, "other_user").is_err());
+        assert!(soft_delete(&service, "mock_user").is_ok());
+
+        // a deleted function is hidden and blocked from new tasks
+        assert!(get(&service).is_err());
+        assert!(!listed(&service));
+        let request = CreateTaskRequest::new()
+            .function_id(function_id.clone())
+            .executor(Executor::Builtin);
+        assert!(service
+            .create_task(mock_request(request, "mock_user"))
+            .is_err());
+
+        // a restored function is visible again and kept by the sweep
+        let request = RestoreFunctionRequest::new(function_id.clone());
+        service
+            .restore_function(mock_request(request, "mock_user"))
+            .unwrap();
+        assert!(get(&service).is_ok());
+        assert!(listed(&service));
+        service.reap_deleted_functions().unwrap();
+        assert!(get(&service).is_ok());
+
+        // a function not restored in the retention window is removed
+        let mut config = ManagementConfig::default();
+        config.function_retention_seconds = 0;
+        let service = mock_service_with_config(config);
+        register(&service);
+        soft_delete(&service, "mock_user").unwrap();
+        let request = RestoreFunctionRequest::new(function_id.clone());
+        assert!(service
+            .restore_function(mock_request(request, "mock_user"))
+            .is_err());
+        service.reap_deleted_functions().unwrap();
+        assert!(service.read_from_db::<Function>(&function_id).is_err());
+        assert!(!listed(&service));
+    }
+
+    pub fn handle_reap_deleted_functions_with_failures() {
+        let mut config = ManagementConfig::default();
+        config.function_retention_seconds = 0;
+        let storage = Arc::new(FailingQueueStorage::default());
+        let service = TeaclaveManagementService::with_storage(storage.clone(), config);
+        let key = DELETED_FUNCTION_QUEUE_KEY.as_bytes();
+        let delete_function = |name: &str| {
+            let function = Function::new()
+                .id(Uuid::new_v4())
+                .name(name)
+                .public(true)
+                .owner("mock_user");
+            service.write_to_db(&function).unwrap();
+            service
+                .update_function_index(|index| index.add(&function))
+                .unwrap();
+            let request = SoftDeleteFunctionRequest::new(function.external_id());
+            service
+                .soft_delete_function(mock_request(request, "mock_user"))
+                .unwrap();
+            function.external_id()
+        };
+
+        // an invalid entry and a missing function don't stop the sweep
+        service.enqueue_bytes_to_db(key, vec![0xff, 0xfe]).unwrap();
+        let missing_id = Function::new().id(Uuid::new_v4()).external_id();
+        service
+            .enqueue_bytes_to_db(key, missing_id.to_bytes())
+            .unwrap();
+        let function_id = delete_function("expired_function");
+        service.reap_deleted_functions().unwrap();
+        assert!(service.read_from_db::<Function>(&function_id).is_err());
+        assert!(!service
+            .read_function_index()
+            .unwrap()
+            .contains_name(&UserID::from("mock_user"), "expired_function"));
+
+        // the missing function is kept for the next round
+        let value = service.dequeue_from_db(key).unwrap();
+        assert_eq!(value, missing_id.to_bytes());
+        assert!(service.dequeue_from_db(key).is_err());
+
+        // a failed requeue is logged and doesn't fail the round
+        let mut config = ManagementConfig::default();
+        config.function_retention_seconds = 3600;
+        let service = TeaclaveManagementService::with_storage(storage.clone(), config);
+        let function_id = delete_function("deleted_function");
+        storage.set_failing_key(Some(key));
+        assert!(service.reap_deleted_functions().is_ok());
+        storage.set_failing_key(None);
+        assert!(service.read_from_db::<Function>(&function_id).is_ok());
+    }
+
+    pub fn handle_unique_function_names() {
+        let register = |service: &TeaclaveManagementService, name: &str, user_id| {
+            let request = RegisterFunctionRequest::new()
+                .name(name)
+                .payload(b"python script".to_vec())
+                .public(true);
+            service.register_function(mock_request(request, user_id))
+        };
+
+        // duplicates are allowed by default
+        let service = mock_service();
+        assert!(register(&service, "unique_function", "mock_user").is_ok());
+        assert!(register(&service, "unique_function", "mock_user").is_ok());
+
+        let mut config = ManagementConfig::default();
+        config.unique_function_names = true;
+        let service = mock_service_with_config(config);
+        let function_id = register(&service, "strict_function", "mock_user")
+            .unwrap()
+            .function_id;
+        assert!(register(&service, "strict_function", "mock_user").is_err());
+        // names are unique per owner
+        assert!(register(&service, "strict_function", "other_user").is_ok());
+
+        // a deleted function releases its name until it is restored
+        let request = SoftDeleteFunctionRequest::new(function_id.clone());
+        service
+            .soft_delete_function(mock_request(request, "mock_user"))
+            .unwrap();
+        assert!(register(&service, "strict_function", "mock_user").is_ok());
+        let request = RestoreFunctionRequest::new(function_id);
+        assert!(service
+            .restore_function(mock_request(request, "mock_user"))
+            .is_err());
+    }
+
+    pub fn handle_mesapy_denied_patterns() {
+        let mut config = ManagementConfig::default();
+        config.mesapy_denied_patterns = vec!["import os".to_string()];
+        let service = mock_service_with_config(config);
+        let register = |executor_type, script: &[u8]| {
+            let request = RegisterFunctionRequest::new()
+                .name("mock_function")
+                .executor_type(executor_type)
+                .payload(script.to_vec())
+                .public(true);
+            service.register_function(mock_request(request, "mock_user"))
+        };
+
+        let script = b"import os\ndef entrypoint(argv):\n    return os.getcwd()\n";
+        let error = register(ExecutorType::Python, script).unwrap_err();
+        assert!(error.to_string().contains("import os"));
+
+        let script = b"def entrypoint(argv):\n    return argv[0]\n";
+        assert!(register(ExecutorType::Python, script).is_ok());
+        // only MesaPy scripts are checked
+        assert!(register(ExecutorType::Builtin, b"import os").is_ok());
+    }
+
+    pub fn handle_function_payload_hash() {
+        let payload = b"python script".to_vec();
+        let expected = hex::encode(ring::digest::digest(&ring::digest::SHA256, &payload));
+
+        let service = mock_service();
+        let request = RegisterFunctionRequest::new()
+            .name("mock_function")
+            .payload(payload)
+            .public(true);
+        let response = service
+            .register_function(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.payload_hash, expected);
+
+        let request = GetFunctionRequest::new(response.function_id);
+        let response = service
+            .get_function(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.payload_hash, expected);
+    }
+
+    pub fn handle_function_payload_encryption() {
+        let payload = b"python script".to_vec();
+        let mut config = ManagementConfig::default();
+        config.function_payload_encryption_key = Some(hex::encode([2u8; 32]));
+        let service = mock_service_with_config(config);
+
+        let request = RegisterFunctionRequest::new()
+            .name("mock_function")
+            .payload(payload.clone())
+            .public(true);
+        let response = service
+            .register_function(mock_request(request, "mock_user"))
+            .unwrap();
+        let function_id = response.function_id;
+
+        // only the ciphertext is stored
+        let stored: Function = service.read_from_db(&function_id).unwrap();
+        assert!(stored.is_payload_encrypted());
+        assert_ne!(stored.payload, payload);
+
+        let request = GetFunctionRequest::new(function_id);
+        let response = service
+            .get_function(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.payload, payload);
+        assert_eq!(response.payload_hash, Function::hash_payload(&payload));
+
+        // payloads stored before the key is configured are still readable
+        let legacy = Function::new()
+            .id(Uuid::new_v4())
+            .name("legacy_function")
+            .payload(payload.clone())
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&legacy).unwrap();
+        let request = GetFunctionRequest::new(legacy.external_id());
+        let response = service
+            .get_function(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.payload, payload);
+    }
+
+    pub fn handle_task() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .description("mock function")
+            .payload(b"python script".to_vec())
+            .arguments(vec!["arg".to_string()])
+            .public(true)
+            .owner("mock_user");
+        let function_arguments = FunctionArguments::new(hashmap!("arg" => "data"));
+
+        let task = Task::new(
+            UserID::from("mock_user"),
+            Executor::MesaPy,
+            function_arguments,
+            HashMap::new(),
+            HashMap::new(),
+            function,
+        )
+        .unwrap();
+
+        assert!(Task::match_prefix(&task.key_string()));
+        let service = mock_service();
+        service.write_to_db(&task).unwrap();
+        let deserialized_task: Task = service.read_from_db(&task.external_id()).unwrap();
+        info!("task: {:?}", deserialized_task);
+    }
+
+    pub fn handle_get_tasks() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        let service = mock_service();
+        service.write_to_db(&function).unwrap();
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin);
+        let own_task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin);
+        let other_task_id = service
+            .create_task(mock_request(request, "other_user"))
+            .unwrap()
+            .task_id;
+        let missing_task_id = ExternalID::new("task", Uuid::new_v4());
+
+        let task_ids = vec![
+            own_task_id.clone(),
+            other_task_id.clone(),
+            missing_task_id.clone(),
+            function.external_id(),
+        ];
+        let request = GetTasksRequest::new(task_ids.clone());
+        let response = service
+            .get_tasks(mock_request(request, "mock_user"))
+            .unwrap();
+
+        assert_eq!(response.tasks.len(), task_ids.len());
+        for (entry, task_id) in response.tasks.iter().zip(task_ids.iter()) {
+            assert_eq!(&entry.task_id, task_id);
+        }
+        let task = response.tasks[0].task.as_ref().unwrap();
+        assert_eq!(task.task_id, own_task_id);
+        assert!(response.tasks[1].task.is_none());
+        assert!(response.tasks[2].task.is_none());
+        assert!(response.tasks[3].task.is_none());
+    }
+
+    pub fn handle_pending_approvals() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user");
+        let service = mock_service();
+        service.write_to_db(&function).unwrap();
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .outputs_ownership(hashmap!("output" => vec!["mock_user1", "mock_user2"]));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let output_file =
+            TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user1", "mock_user2"]);
+        service.write_to_db(&output_file).unwrap();
+        let request = AssignDataRequest::new(
+            task_id.clone(),
+            HashMap::new(),
+            hashmap!("output" => output_file.external_id()),
+        );
+        service
+            .assign_data(mock_request(request, "mock_user1"))
+            .unwrap();
+
+        let pending_approvals = |service: &TeaclaveManagementService| {
+            let request = GetTaskRequest::new(task_id.clone());
+            service
+                .get_task(mock_request(request, "mock_user"))
+                .unwrap()
+                .pending_approvals
+        };
+        assert_eq!(pending_approvals(&service).len(), 3);
+
+        // every pending participant is notified after each change
+        let notification_key = Notification::get_queue_key(&UserID::from("mock_user2"));
+        for approver in &["mock_user", "mock_user1"] {
+            let request = ApproveTaskRequest::new(task_id.clone());
+            service
+                .approve_task(mock_request(request, approver))
+                .unwrap();
+        }
+        let pending = pending_approvals(&service);
+        assert_eq!(pending.len(), 1);
+        assert!(pending.contains(&UserID::from("mock_user2")));
+        for _ in 0..3 {
+            assert!(service.dequeue_from_db(notification_key.as_bytes()).is_ok());
+        }
+
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user2"))
+            .unwrap();
+        assert!(pending_approvals(&service).is_empty());
+        assert!(service
+            .dequeue_from_db(notification_key.as_bytes())
+            .is_err());
+    }
+
+    pub fn handle_invalid_arguments() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .arguments(vec!["arg".to_string()])
+            .public(true)
+            .owner("mock_user");
+        let mut config = ManagementConfig::default();
+        config.max_argument_length = 8;
+        let service = mock_service_with_config(config);
+        service.write_to_db(&function).unwrap();
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .function_arguments(hashmap!("arg" => "data"));
+        assert!(service
+            .create_task(mock_request(request, "mock_user"))
+            .is_ok());
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .function_arguments(hashmap!("arg" => "da\x1bta"));
+        let error = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert!(error.to_string().contains("control characters"));
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .function_arguments(hashmap!("arg" => "data_too_long"));
+        let error = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert!(error.to_string().contains("longer than 8 bytes"));
+    }
+
+    pub fn handle_argument_limits() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .arguments(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+            .public(true)
+            .owner("mock_user");
+        let mut config = ManagementConfig::default();
+        config.max_argument_count = 2;
+        let service = mock_service_with_config(config);
+        service.write_to_db(&function).unwrap();
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .function_arguments(hashmap!("a" => "1", "b" => "2", "c" => "3"));
+        let error = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert!(error.to_string().contains("too many function_arguments"));
+
+        let mut config = ManagementConfig::default();
+        config.max_arguments_size = 5;
+        let service = mock_service_with_config(config);
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .function_arguments(hashmap!("a" => "1", "b" => "2", "c" => "3"));
+        let error = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert!(error.to_string().contains("larger than 5 bytes"));
+    }
+
+    pub fn handle_task_approval_deadline() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .description("mock function")
+            .payload(b"python script".to_vec())
+            .public(true)
+            .owner("mock_user");
+
+        let mut task = Task::new(
+            UserID::from("mock_user"),
+            Executor::MesaPy,
+            FunctionArguments::default(),
+            HashMap::new(),
+            HashMap::new(),
+            function,
+        )
+        .unwrap();
+        task.approval_deadline = Some(100);
+
+        assert!(!task.approval_window_closed(100));
+        assert!(!task.cancel_if_approval_expired(100));
+        assert_eq!(task.status, TaskStatus::DataAssigned);
+
+        assert!(task.approval_window_closed(101));
+        assert!(task.cancel_if_approval_expired(101));
+        assert_eq!(task.status, TaskStatus::Canceled);
+        assert!(task.approve(&UserID::from("mock_user")).is_err());
+    }
+
+    pub fn handle_reap_expired_tasks() {
+        let service = mock_service();
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        let mut task = Task::new(
+            UserID::from("mock_user"),
+            Executor::MesaPy,
+            FunctionArguments::default(),
+            HashMap::new(),
+            HashMap::new(),
+            function,
+        )
+        .unwrap();
+        task.approval_deadline = Some(100);
+        let key = APPROVAL_DEADLINE_QUEUE_KEY.as_bytes();
+        service.enqueue_bytes_to_db(key, "invalid").unwrap();
+        service
+            .enqueue_bytes_to_db(key, task.external_id().to_bytes())
+            .unwrap();
+
+        // the task cannot be read yet, it is kept while invalid ids are dropped
+        service.reap_expired_tasks().unwrap();
+        let value = service.dequeue_from_db(key).unwrap();
+        assert_eq!(value, task.external_id().to_bytes());
+        assert!(service.dequeue_from_db(key).is_err());
+        service.enqueue_bytes_to_db(key, value).unwrap();
+
+        service.write_to_db(&task).unwrap();
+        service.reap_expired_tasks().unwrap();
+        let task: Task = service.read_from_db(&task.external_id()).unwrap();
+        assert_eq!(task.status, TaskStatus::Canceled);
+        assert!(service.dequeue_from_db(key).is_err());
+    }
+
+    pub fn handle_min_fusion_owners() {
+        let mut config = ManagementConfig::default();
+        config.min_fusion_owners = 3;
+        let service = mock_service_with_config(config);
+
+        let request = RegisterFusionOutputRequest::new(vec!["mock_user", "mock_user_b"]);
+        let error = service
+            .register_fusion_output(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert!(error.to_string().contains("at least 3"));
+        let request =
+            RegisterFusionOutputRequest::new(vec!["mock_user", "mock_user_b", "mock_user_c"]);
+        assert!(service
+            .register_fusion_output(mock_request(request, "mock_user"))
+            .is_ok());
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .outputs_ownership(hashmap!("output" => vec!["mock_user", "mock_user_b"]));
+        let error = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert!(error.to_string().contains("at least 3"));
+
+        // files of a single owner are not fusion files
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .outputs_ownership(hashmap!("output" => vec!["mock_user"]));
+        assert!(service
+            .create_task(mock_request(request, "mock_user"))
+            .is_ok());
+    }
+
+    pub fn handle_max_fusion_outputs() {
+        let mut config = ManagementConfig::default();
+        config.max_fusion_outputs_per_user = Some(2);
+        let service = mock_service_with_config(config);
+
+        for _ in 0..2 {
+            let request = RegisterFusionOutputRequest::new(vec!["mock_user", "mock_user_b"]);
+            service
+                .register_fusion_output(mock_request(request, "mock_user"))
+                .unwrap();
+        }
+        let request = RegisterFusionOutputRequest::new(vec!["mock_user", "mock_user_b"]);
+        let error = service
+            .register_fusion_output(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert!(error.to_string().contains("at most 2"));
+
+        // other outputs do not count towards the cap
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        for _ in 0..2 {
+            let request = RegisterOutputFileRequest::new(url.clone(), FileCrypto::default());
+            service
+                .register_output_file(mock_request(request, "mock_user_c"))
+                .unwrap();
+        }
+        let request = RegisterFusionOutputRequest::new(vec!["mock_user_c", "mock_user_b"]);
+        assert!(service
+            .register_fusion_output(mock_request(request, "mock_user_c"))
+            .is_ok());
+    }
+
+    pub fn handle_soft_limit_warnings() {
+        let mut config = ManagementConfig::default();
+        config.max_input_files_per_user = Some(5);
+        config.max_argument_count = 4;
+        config.soft_limit_percent = Some(75);
+        let service = mock_service_with_config(config);
+
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let mut warnings = Vec::new();
+        for _ in 0..5 {
+            let request =
+                RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), FileCrypto::Raw);
+            let response = service
+                .register_input_file(mock_request(request, "mock_user"))
+                .unwrap();
+            warnings.push(response.warnings);
+        }
+        assert!(warnings[..3].iter().all(|warnings| warnings.is_empty()));
+        assert_eq!(warnings[3], vec!["4 of 5 unconsumed input files used"]);
+        assert_eq!(warnings[4], vec!["5 of 5 unconsumed input files used"]);
+
+        // the hard limit still applies
+        let request = RegisterInputFileRequest::new(url, FileAuthTag::mock(), FileCrypto::Raw);
+        let error = service
+            .register_input_file(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert!(error.to_string().contains("at most 5"));
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin);
+        let response = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap();
+        assert!(response.warnings.is_empty());
+
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .arguments(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .function_arguments(hashmap!("a" => "1", "b" => "2", "c" => "3"));
+        let response = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.warnings, vec!["3 of 4 function arguments used"]);
+    }
+
+    pub fn handle_fusion_output_recipient_keys() {
+        let service = mock_service();
+        let rng = ring::rand::SystemRandom::new();
+        let mut private_keys = Vec::new();
+        let mut public_keys = Vec::new();
+        for _ in 0..2 {
+            let private_key =
+                agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng).unwrap();
+            public_keys.push(private_key.compute_public_key().unwrap().as_ref().to_vec());
+            private_keys.push(private_key);
+        }
+
+        // every owner must have a key
+        let request = RegisterFusionOutputRequest::new(vec!["mock_user", "mock_user_b"])
+            .recipient_public_key("mock_user", public_keys[0].clone());
+        assert!(service
+            .register_fusion_output(mock_request(request, "mock_user"))
+            .is_err());
+
+        let request = RegisterFusionOutputRequest::new(vec!["mock_user", "mock_user_b"])
+            .recipient_public_key("mock_user", public_keys[0].clone())
+            .recipient_public_key("mock_user_b", public_keys[1].clone());
+        let data_id = service
+            .register_fusion_output(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+        let output: TeaclaveOutputFile = service.read_from_db(&data_id).unwrap();
+
+        // each owner opens the crypto info sealed to its own key
+        for (user_id, private_key) in vec!["mock_user", "mock_user_b"]
+            .into_iter()
+            .zip(private_keys)
+        {
+            let request = GetOutputFileRequest::new(data_id.clone());
+            let response = service
+                .get_output_file(mock_request(request, user_id))
+                .unwrap();
+            let sealed = response.sealed_crypto_info.unwrap();
+            let other_key =
+                agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng).unwrap();
+            assert!(open_with_private_key(other_key, &sealed).is_err());
+            let opened = open_with_private_key(private_key, &sealed).unwrap();
+            let opened: FileCrypto = serde_json::from_slice(&opened).unwrap();
+            assert_eq!(opened, output.crypto_info);
+        }
+    }
+
+    pub fn handle_fusion_output_retention() {
+        let record = FusionOutputRecord {
+            data_id: ExternalID::new("output", Uuid::new_v4()),
+            created_at: 100,
+        };
+
+        // aged and unconsumed
+        assert_eq!(
+            fusion_retention_action(&record, false, 201, 100),
+            RetentionAction::Reap
+        );
+        // aged but consumed as an input
+        assert_eq!(
+            fusion_retention_action(&record, true, 201, 100),
+            RetentionAction::Release
+        );
+        // not aged yet
+        assert_eq!(
+            fusion_retention_action(&record, false, 200, 100),
+            RetentionAction::Keep
+        );
+    }
+
+    pub fn handle_export_import_task() {
+        let service = mock_service();
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .executor_type(ExecutorType::Builtin)
+            .arguments(vec!["secret".to_string()])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .function_arguments(hashmap!("secret" => "xxxx"))
+            .executor(Executor::Builtin)
+            .labels(hashmap!("team" => "blue"));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+
+        // arguments are omitted by default and only exported for the creator
+        let request = ExportTaskRequest::new(task_id.clone());
+        let export = service
+            .export_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task;
+        assert!(!export.contains("xxxx"));
+        let request = ExportTaskRequest::new(task_id.clone()).include_arguments(true);
+        assert!(service
+            .export_task(mock_request(request, "other_user"))
+            .is_err());
+        let request = ExportTaskRequest::new(task_id.clone()).include_arguments(true);
+        let export = service
+            .export_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task;
+
+        let request = ImportTaskRequest::new(export);
+        let imported_id = service
+            .import_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        assert_ne!(imported_id, task_id);
+
+        let task: Task = service.read_from_db(&task_id).unwrap();
+        let imported: Task = service.read_from_db(&imported_id).unwrap();
+        assert_eq!(imported.function_id, task.function_id);
+        assert_eq!(imported.executor, task.executor);
+        assert_eq!(
+            imported.function_arguments.get("secret").unwrap().as_str(),
+            "xxxx"
+        );
+        assert_eq!(imported.labels, task.labels);
+        assert_eq!(imported.participants, task.participants);
+
+        assert!(service
+            .import_task(mock_request(ImportTaskRequest::new("{}"), "mock_user"))
+            .is_err());
+    }
+
+    pub fn handle_export_import_catalog() {
+        let service = mock_service();
+        let register = |name: &str, payload: &[u8], public: bool, user_id: &str| {
+            let request = RegisterFunctionRequest::new()
+                .name(name)
+                .payload(payload.to_vec())
+                .public(public)
+                .arguments(vec!["arg"])
+                .inputs(vec![FunctionInput::new("input", "input_desc")])
+                .tags(vec!["tag"]);
+            service
+                .register_function(mock_request(request, user_id))
+                .unwrap()
+                .function_id
+        };
+        let public_id = register("public_function", b"public script", true, "other_user");
+        let private_id = register("private_function", b"private script", false, "mock_user");
+        register("hidden_function", b"hidden script", false, "other_user");
+
+        // only functions visible to the user are exported, without payloads
+        let catalog = service
+            .export_catalog(mock_request(ExportCatalogRequest::new(), "mock_user"))
+            .unwrap()
+            .catalog;
+        assert!(!catalog.contains("hidden_function"));
+        assert!(!catalog.contains("script"));
+        let manifests = FunctionCatalog::from_json(&catalog).unwrap().functions;
+        let names: Vec<&str> = manifests.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["public_function", "private_function"]);
+
+        // every payload must be supplied and match its hash
+        let request = ImportCatalogRequest::new(catalog.clone())
+            .payload(&public_id, b"public script".to_vec());
+        assert!(service
+            .import_catalog(mock_request(request, "new_user"))
+            .is_err());
+        let request = ImportCatalogRequest::new(catalog.clone())
+            .payload(&public_id, b"public script".to_vec())
+            .payload(&private_id, b"tampered script".to_vec());
+        assert!(service
+            .import_catalog(mock_request(request, "new_user"))
+            .is_err());
+
+        let request = ImportCatalogRequest::new(catalog)
+            .payload(&public_id, b"public script".to_vec())
+            .payload(&private_id, b"private script".to_vec());
+        let function_ids = service
+            .import_catalog(mock_request(request, "new_user"))
+            .unwrap()
+            .function_ids;
+        assert_eq!(function_ids.len(), 2);
+        for (exported_id, payload) in vec![
+            (&public_id, "public script"),
+            (&private_id, "private script"),
+        ] {
+            let original: Function = service.read_from_db(exported_id).unwrap();
+            let imported: Function = service
+                .read_from_db(&function_ids[&exported_id.to_string()])
+                .unwrap();
+            assert_eq!(imported.owner, UserID::from("new_user"));
+            assert_eq!(imported.name, original.name);
+            assert_eq!(imported.public, original.public);
+            assert_eq!(imported.arguments, original.arguments);
+            assert_eq!(imported.inputs.len(), 1);
+            assert_eq!(imported.tags, original.tags);
+            assert_eq!(imported.payload, payload.as_bytes().to_vec());
+            assert_eq!(imported.payload_hash, original.payload_hash);
+        }
+
+        // imported functions are listed for the importer
+        let catalog = service
+            .export_catalog(mock_request(ExportCatalogRequest::new(), "new_user"))
+            .unwrap()
+            .catalog;
+        let manifests = FunctionCatalog::from_json(&catalog).unwrap().functions;
+        assert_eq!(manifests.len(), 3);
+    }
+
+    pub fn handle_access_policy() {
+        let mut config = ManagementConfig::default();
+        config
+            .access_policy
+            .insert("register_function".to_string(), AccessRule::Admin);
+        let service = mock_service_with_config(config);
+
+        let mut metadata = HashMap::new();
+        metadata.insert("id".to_string(), "mock_user".to_string());
+        assert!(service.authorize("register_function", &metadata).is_err());
+        assert!(service.authorize("admin_get_task", &metadata).is_err());
+        assert!(service.authorize("create_task", &metadata).is_ok());
+
+        metadata.insert("role".to_string(), "admin".to_string());
+        assert!(service.authorize("register_function", &metadata).is_ok());
+        assert!(service.authorize("admin_get_task", &metadata).is_ok());
+    }
+
+    pub fn handle_request_deadline() {
+        let service = mock_service();
+        let mut metadata = HashMap::new();
+        metadata.insert("id".to_string(), "mock_user".to_string());
+        assert!(service.begin_request(&metadata).is_ok());
+
+        metadata.insert(
+            "deadline".to_string(),
+            (current_timestamp() + 60).to_string(),
+        );
+        assert!(service.begin_request(&metadata).is_ok());
+        assert!(remaining_time().unwrap().is_some());
+
+        metadata.insert(
+            "deadline".to_string(),
+            (current_timestamp() - 1).to_string(),
+        );
+        let error = service.begin_request(&metadata).unwrap_err();
+        assert!(error.to_string().contains("deadline exceeded"));
+        assert!(remaining_time().is_err());
+
+        metadata.remove("deadline");
+        assert!(service.begin_request(&metadata).is_ok());
+        assert!(remaining_time().unwrap().is_none());
+    }
+
+    pub fn handle_restart_recovery() {
+        let storage: Arc<dyn StorageBackend> = Arc::new(MemoryStorage::default());
+        let service =
+            TeaclaveManagementService::with_storage(storage.clone(), ManagementConfig::default());
+        let new_task = |status| {
+            let function = Function::new()
+                .id(Uuid::new_v4())
+                .name("mock_function")
+                .public(true)
+                .owner("mock_user");
+            let mut task = Task::new(
+                UserID::from("mock_user"),
+                Executor::MesaPy,
+                FunctionArguments::default(),
+                HashMap::<String, OwnerList>::new(),
+                HashMap::<String, OwnerList>::new(),
+                function,
+            )
+            .unwrap();
+            task.status = status;
+            service.write_to_db(&task).unwrap();
+            service
+                .enqueue_bytes_to_db(
+                    TRANSIENT_TASKS_QUEUE_KEY.as_bytes(),
+                    task.external_id().to_bytes(),
+                )
+                .unwrap();
+            task
+        };
+
+        let running = new_task(TaskStatus::Running);
+        let lost = new_task(TaskStatus::Staged);
+        let staged = new_task(TaskStatus::Staged);
+        let staged_task = StagedTask::new().task_id(staged.task_id);
+        service
+            .enqueue_to_db(StagedTask::get_queue_key().as_bytes(), &staged_task)
+            .unwrap();
+
+        // the service is restarted with the same storage
+        let service = TeaclaveManagementService::with_storage(storage, ManagementConfig::default());
+        service.recover_transient_tasks().unwrap();
+
+        for task in &[running, lost] {
+            let task: Task = service.read_from_db(&task.external_id()).unwrap();
+            assert_eq!(task.status, TaskStatus::Finished);
+            match task.result {
+                TaskResult::Err(failure) => assert!(failure.reason.contains("restarted")),
+                _ => panic!("unexpected task result"),
+            }
+        }
+
+        let task: Task = service.read_from_db(&staged.external_id()).unwrap();
+        assert_eq!(task.status, TaskStatus::Staged);
+        let value = service
+            .dequeue_from_db(StagedTask::get_queue_key().as_bytes())
+            .unwrap();
+        assert_eq!(
+            StagedTask::from_slice(&value).unwrap().task_id,
+            staged.task_id
+        );
+        let value = service
+            .dequeue_from_db(TRANSIENT_TASKS_QUEUE_KEY.as_bytes())
+            .unwrap();
+        assert_eq!(value, staged.external_id().to_bytes());
+    }
+
+    pub fn handle_cancel_task() {
+        let service = mock_service();
+        let new_task = |status| {
+            let function = Function::new()
+                .id(Uuid::new_v4())
+                .name("mock_function")
+                .public(true)
+                .owner("mock_user");
+            let mut task = Task::new(
+                UserID::from("mock_user"),
+                Executor::MesaPy,
+                FunctionArguments::default(),
+                HashMap::<String, OwnerList>::new(),
+                HashMap::<String, OwnerList>::new(),
+                function,
+            )
+            .unwrap();
+            task.status = status;
+            service.write_to_db(&task).unwrap();
+            task.external_id()
+        };
+
+        let task_id = new_task(TaskStatus::DataAssigned);
+        let request = mock_request(CancelTaskRequest::new(task_id.clone()), "other_user");
+        assert!(service.cancel_task(request).is_err());
+        let request = mock_request(CancelTaskRequest::new(task_id.clone()), "mock_user");
+        assert!(service.cancel_task(request).unwrap().canceled);
+        let task: Task = service.read_from_db(&task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Canceled);
+
+        // a running task is marked for cancellation
+        let task_id = new_task(TaskStatus::Running);
+        let request = mock_request(CancelTaskRequest::new(task_id.clone()), "mock_user");
+        assert!(!service.cancel_task(request).unwrap().canceled);
+        let task: Task = service.read_from_db(&task_id).unwrap();
+        assert!(task.cancel_requested);
+        assert_eq!(task.status, TaskStatus::Running);
+    }
+
+    pub fn handle_cancel_all_tasks() {
+        let service = mock_service();
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let create_task = || {
+            let request = CreateTaskRequest::new()
+                .function_id(function.external_id())
+                .executor(Executor::Builtin);
+            service
+                .create_task(mock_request(request, "mock_user"))
+                .unwrap()
+                .task_id
+        };
+
+        let assigned_id = create_task();
+        let approved_id = create_task();
+        let request = ApproveTaskRequest::new(approved_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let staged_id = create_task();
+        let request = ApproveTaskRequest::new(staged_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = InvokeTaskRequest::new(staged_id.clone());
+        service
+            .invoke_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let lost_id = create_task();
+        service.delete_from_db(&lost_id).unwrap();
+
+        // tasks of other users are not affected
+        let response = service
+            .cancel_all_tasks(mock_request(CancelAllTasksRequest::new(), "other_user"))
+            .unwrap();
+        assert!(response.task_ids.is_empty());
+
+        // a task which cannot be read is reported, and the others are canceled
+        let response = service
+            .cancel_all_tasks(mock_request(CancelAllTasksRequest::new(), "mock_user"))
+            .unwrap();
+        assert_eq!(
+            response.task_ids,
+            vec![assigned_id.clone(), approved_id.clone(), staged_id.clone()]
+        );
+        assert_eq!(response.failed_task_ids, vec![lost_id]);
+        for task_id in &[assigned_id, approved_id] {
+            let task: Task = service.read_from_db(task_id).unwrap();
+            assert_eq!(task.status, TaskStatus::Canceled);
+        }
+        // a staged task is marked for cancellation
+        let task: Task = service.read_from_db(&staged_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Staged);
+        assert!(task.cancel_requested);
+
+        let response = service
+            .cancel_all_tasks(mock_request(CancelAllTasksRequest::new(), "mock_user"))
+            .unwrap();
+        assert!(response.task_ids.is_empty());
+    }
 
-    fn read_from_db<T: Storable>(&self, key: &ExternalID) -> Result<T> {
-        anyhow::ensure!(T::match_prefix(&key.prefix), "Key prefix doesn't match.");
+    pub fn handle_task_history() {
+        let mut config = ManagementConfig::default();
+        config.task_history_max_count = Some(2);
+        let storage = Arc::new(FailingQueueStorage::default());
+        let service = TeaclaveManagementService::with_storage(storage.clone(), config);
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let new_task = || {
+            let request = CreateTaskRequest::new()
+                .function_id(function.external_id())
+                .executor(Executor::Builtin);
+            service
+                .create_task(mock_request(request, "mock_user"))
+                .unwrap()
+                .task_id
+        };
+        let finish = |task_id: &ExternalID| {
+            let mut task: Task = service.read_from_db(task_id).unwrap();
+            let old_status = task.status;
+            task.status = TaskStatus::Finished;
+            service.write_task(&task, Some(old_status)).unwrap();
+        };
+        let task_ids: Vec<ExternalID> = (0..4).map(|_| new_task()).collect();
+        for task_id in &task_ids[..3] {
+            finish(task_id);
+        }
 
-        let request = GetRequest::new(key.to_bytes());
-        let response = self
-            .storage_client
-            .clone()
-            .lock()
-            .map_err(|_| anyhow!("Cannot lock storage client"))?
-            .get(request)?;
-        T::from_slice(response.value.as_slice())
+        // events whose records cannot be queued are walked again
+        storage.set_failing_key(Some(TASK_HISTORY_QUEUE_KEY.as_bytes()));
+        service.reap_task_history().unwrap();
+        storage.set_failing_key(None);
+        assert!(service.read_from_db::<Task>(&task_ids[0]).is_ok());
+
+        // the oldest finished task is pruned, unfinished tasks are kept
+        service.reap_task_history().unwrap();
+        assert!(service.read_from_db::<Task>(&task_ids[0]).is_err());
+        for task_id in &task_ids[1..] {
+            assert!(service.read_from_db::<Task>(task_id).is_ok());
+        }
+        let index = service.read_task_index(&UserID::from("mock_user")).unwrap();
+        assert!(index.task_ids().all(|task_id| task_id != &task_ids[0]));
+        assert_eq!(index.task_ids().count(), 3);
+
+        service.reap_task_history().unwrap();
+        assert!(service.read_from_db::<Task>(&task_ids[1]).is_ok());
+        finish(&task_ids[3]);
+        service.reap_task_history().unwrap();
+        assert!(service.read_from_db::<Task>(&task_ids[1]).is_err());
+        assert!(service.read_from_db::<Task>(&task_ids[2]).is_ok());
+        assert!(service.read_from_db::<Task>(&task_ids[3]).is_ok());
     }
 
-    fn enqueue_to_db(&self, key: &[u8], item: &impl Storable) -> TeaclaveServiceResponseResult<()> {
-        let value = item.to_vec().map_err(|_| ServiceError::DataError)?;
-        let enqueue_request = EnqueueRequest::new(key, value);
-        let _enqueue_response = self
-            .storage_client
-            .clone()
-            .lock()
-            .map_err(|_| ServiceError::StorageError)?
-            .enqueue(enqueue_request)?;
-        Ok(())
+    pub fn handle_task_events() {
+        let service = mock_service();
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin);
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = InvokeTaskRequest::new(task_id.clone());
+        service
+            .invoke_task(mock_request(request, "mock_user"))
+            .unwrap();
+
+        let response = service
+            .subscribe_events(mock_request(SubscribeEventsRequest::new(0, 0), "mock_user"))
+            .unwrap();
+        let transitions: Vec<(Option<TaskStatus>, TaskStatus)> = response
+            .events
+            .iter()
+            .map(|event| {
+                assert_eq!(event.task_id, task_id);
+                (event.old_status, event.new_status)
+            })
+            .collect();
+        assert_eq!(
+            transitions,
+            vec![
+                (None, TaskStatus::DataAssigned),
+                (Some(TaskStatus::DataAssigned), TaskStatus::Approved),
+                (Some(TaskStatus::Approved), TaskStatus::Staged),
+            ]
+        );
+        assert_eq!(response.next_offset, 3);
+
+        // events are replayed from an offset
+        let response = service
+            .subscribe_events(mock_request(SubscribeEventsRequest::new(2, 0), "mock_user"))
+            .unwrap();
+        assert_eq!(response.events.len(), 1);
+        assert_eq!(response.events[0].new_status, TaskStatus::Staged);
+
+        // other users cannot see events of the task
+        let response = service
+            .subscribe_events(mock_request(
+                SubscribeEventsRequest::new(0, 0),
+                "mock_user_b",
+            ))
+            .unwrap();
+        assert!(response.events.is_empty());
+        assert_eq!(response.next_offset, 3);
     }
 
-    #[cfg(test_mode)]
-    fn add_mock_data(&self) -> Result<()> {
-        use teaclave_types::{FileAuthTag, FunctionInput, FunctionOutput};
-        let mut output_file = self.create_fusion_data(vec!["mock_user1", "frontend_user"])?;
-        output_file.uuid = Uuid::parse_str("00000000-0000-0000-0000-000000000001")?;
-        output_file.cmac = Some(FileAuthTag::mock());
-        self.write_to_db(&output_file)?;
+    pub fn handle_export_audit_log() {
+        let service = mock_service();
+        for timestamp in 100..106 {
+            service.record_audit_entry(&AuditEntry::new(
+                timestamp,
+                "mock_correlation_id",
+                "MockAction",
+                format!("timestamp:{}", timestamp),
+            ));
+        }
 
-        let mut output_file = self.create_fusion_data(vec!["mock_user2", "mock_user3"])?;
-        output_file.uuid = Uuid::parse_str("00000000-0000-0000-0000-000000000002")?;
-        output_file.cmac = Some(FileAuthTag::mock());
-        self.write_to_db(&output_file)?;
+        // pages are bounded by the offset and limit, then filtered by time
+        let mut exported = Vec::new();
+        let mut offset = 0;
+        loop {
+            let request = ExportAuditLogRequest::new(101, 104).offset(offset).limit(2);
+            let response = service
+                .export_audit_log(mock_request(request, "mock_admin"))
+                .unwrap();
+            assert!(response.entries.len() <= 2);
+            assert_eq!(
+                response.checksum,
+                AuditEntry::page_checksum(&response.entries)
+            );
+            if response.next_offset == offset {
+                break;
+            }
+            offset = response.next_offset;
+            exported.extend(response.entries);
+        }
+        let timestamps: Vec<u64> = exported
+            .iter()
+            .map(|entry| AuditEntry::from_slice(entry.as_bytes()).unwrap().timestamp)
+            .collect();
+        assert_eq!(timestamps, vec![101, 102, 103, 104]);
+        assert_eq!(offset, 6);
 
-        let mut input_file = TeaclaveInputFile::from_output(output_file)?;
-        input_file.uuid = Uuid::parse_str("00000000-0000-0000-0000-000000000002")?;
-        self.write_to_db(&input_file)?;
+        // a page which is altered no longer matches its checksum
+        let response = service
+            .export_audit_log(mock_request(ExportAuditLogRequest::new(0, 0), "mock_admin"))
+            .unwrap();
+        assert_eq!(response.entries.len(), 6);
+        let mut entries = response.entries.clone();
+        entries.remove(0);
+        assert_ne!(AuditEntry::page_checksum(&entries), response.checksum);
 
-        let function_input = FunctionInput::new("input", "input_desc");
-        let function_output = FunctionOutput::new("output", "output_desc");
-        let function_input2 = FunctionInput::new("input2", "input_desc");
-        let function_output2 = FunctionOutput::new("output2", "output_desc");
+        let request = ExportAuditLogRequest::new(104, 101);
+        assert!(service
+            .export_audit_log(mock_request(request, "mock_admin"))
+            .is_err());
+
+        // only admins can export the log, and denials are audited as well
+        let mut metadata = HashMap::new();
+        metadata.insert("id".to_string(), "mock_user".to_string());
+        assert!(service.authorize("export_audit_log", &metadata).is_err());
+        let response = service
+            .export_audit_log(mock_request(
+                ExportAuditLogRequest::new(0, 0).offset(6),
+                "mock_admin",
+            ))
+            .unwrap();
+        assert_eq!(response.entries.len(), 1);
+        let entry = AuditEntry::from_slice(response.entries[0].as_bytes()).unwrap();
+        assert_eq!(entry.action, "export_audit_log");
+        metadata.insert("role".to_string(), "admin".to_string());
+        assert!(service.authorize("export_audit_log", &metadata).is_ok());
+    }
+
+    pub fn handle_result_archive() {
+        let archive_dir = "/tmp/teaclave_result_archive/";
+        std::untrusted::fs::create_dir_all(archive_dir).unwrap();
+        let mut config = ManagementConfig::default();
+        config.result_retention_seconds = Some(0);
+        config.result_archive_url = Some(format!("file://{}", archive_dir));
+        config.result_archive_encryption_key = Some(hex::encode([3u8; 32]));
+        let service = mock_service_with_config(config);
+
+        let finish_task = || {
+            let function = Function::new()
+                .id(Uuid::new_v4())
+                .name("mock_function")
+                .outputs(vec![FunctionOutput::new("output", "output_desc")])
+                .public(true)
+                .owner("mock_user");
+            let mut task = Task::new(
+                UserID::from("mock_user"),
+                Executor::Builtin,
+                FunctionArguments::default(),
+                HashMap::<String, OwnerList>::new(),
+                hashmap!("output" => vec!["mock_user"]),
+                function,
+            )
+            .unwrap();
+            task.status = TaskStatus::Running;
+            let tags_map = hashmap!("output" => FileAuthTag::mock());
+            task.finish(TaskResult::Ok(TaskOutputs::new("return value", tags_map)))
+                .unwrap();
+            service
+                .write_task(&task, Some(TaskStatus::Running))
+                .unwrap();
+            task
+        };
+        // the archive of the first task cannot be written
+        let failed = finish_task();
+        let failed_path =
+            std::path::Path::new(archive_dir).join(format!("{}.json", failed.task_id));
+        std::untrusted::fs::create_dir_all(&failed_path).unwrap();
+        let task = finish_task();
+
+        service.reap_aged_results().unwrap();
+        let archived: Task = service.read_from_db(&task.external_id()).unwrap();
+        assert!(archived.result_archive.is_some());
+        match &archived.result {
+            TaskResult::Ok(outputs) => assert!(outputs.tags_map.is_empty()),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        let stored: Task = service.read_from_db(&failed.external_id()).unwrap();
+        assert!(stored.result_archive.is_none());
+        std::untrusted::fs::remove_dir_all(&failed_path).unwrap();
+
+        // only the ciphertext leaves the enclave
+        let path = std::path::Path::new(archive_dir).join(format!("{}.json", task.task_id));
+        let bytes = std::untrusted::fs::read(path).unwrap();
+        assert!(serde_json::from_slice::<serde_json::Value>(&bytes).is_err());
+
+        // the archived tags are restored when the task is read
+        let request = GetTaskRequest::new(task.external_id());
+        let response = service
+            .get_task(mock_request(request, "mock_user"))
+            .unwrap();
+        match response.result {
+            TaskResult::Ok(outputs) => {
+                assert_eq!(outputs.tags_map.get("output"), Some(&FileAuthTag::mock()))
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        // a later round doesn't archive the task again
+        service.reap_aged_results().unwrap();
+        let stored: Task = service.read_from_db(&task.external_id()).unwrap();
+        assert_eq!(stored.result_archive, archived.result_archive);
+    }
+
+    pub fn handle_reap_expired_fusion_outputs() {
+        let fusion_base = "/tmp/teaclave_reap_expired_fusion_outputs";
+        std::untrusted::fs::create_dir_all(fusion_base).unwrap();
+        let mut config = ManagementConfig::default();
+        config.fusion_output_max_age_seconds = Some(100);
+        let storage = Arc::new(FailingQueueStorage::default());
+        let service = TeaclaveManagementService::with_storage(storage.clone(), config)
+            .fusion_base(fusion_base);
+        let register = || {
+            let request = RegisterFusionOutputRequest::new(vec!["mock_user", "mock_user_b"]);
+            service
+                .register_fusion_output(mock_request(request, "mock_user"))
+                .unwrap()
+                .data_id
+        };
+        let expired_id = register();
+        let fresh_id = register();
+
+        // the first output was registered long ago
+        let key = FUSION_OUTPUT_QUEUE_KEY.as_bytes();
+        service.dequeue_from_db(key).unwrap();
+        let record = FusionOutputRecord {
+            data_id: expired_id.clone(),
+            created_at: 0,
+        };
+        service
+            .enqueue_bytes_to_db(key, serde_json::to_vec(&record).unwrap())
+            .unwrap();
+        let expired: TeaclaveOutputFile = service.read_from_db(&expired_id).unwrap();
+        let fusion_file =
+            std::path::Path::new(fusion_base).join(format!("{}.fusion", expired.uuid));
+        std::untrusted::fs::write(&fusion_file, b"fusion data").unwrap();
+
+        // kept for the next round while an owner cannot be notified
+        let notification_key = Notification::get_queue_key(&UserID::from("mock_user_b"));
+        storage.set_failing_key(Some(notification_key.as_bytes()));
+        service.reap_expired_fusion_outputs().unwrap();
+        assert!(service
+            .read_from_db::<TeaclaveOutputFile>(&expired_id)
+            .is_ok());
+        assert!(fusion_file.exists());
+        assert_eq!(storage.queue_len(key).unwrap(), 2);
+
+        storage.set_failing_key(None);
+        service.reap_expired_fusion_outputs().unwrap();
+        assert!(service
+            .read_from_db::<TeaclaveOutputFile>(&expired_id)
+            .is_err());
+        assert!(!fusion_file.exists());
+        assert!(service.dequeue_from_db(notification_key.as_bytes()).is_ok());
+
+        // outputs not expired yet are still tracked
+        assert!(service
+            .read_from_db::<TeaclaveOutputFile>(&fresh_id)
+            .is_ok());
+        let value = service.dequeue_from_db(key).unwrap();
+        let record: FusionOutputRecord = serde_json::from_slice(&value).unwrap();
+        assert_eq!(record.data_id, fresh_id);
+        assert!(service.dequeue_from_db(key).is_err());
+
+        std::untrusted::fs::remove_dir_all(fusion_base).unwrap();
+    }
+
+    pub fn handle_temporary_fusion_output() {
+        let fusion_base = "/tmp/teaclave_temporary_fusion_output";
+        std::untrusted::fs::create_dir_all(fusion_base).unwrap();
+        let service = mock_service().fusion_base(fusion_base);
+        let owners = vec!["mock_user", "mock_user_b"];
+
+        let request = RegisterFusionOutputRequest::new(owners.clone()).temporary(true);
+        let output_id = service
+            .register_fusion_output(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+        // the producer task finishes
+        let mut output: TeaclaveOutputFile = service.read_from_db(&output_id).unwrap();
+        output.assign_cmac(&FileAuthTag::mock()).unwrap();
+        service.write_to_db(&output).unwrap();
+        let fusion_file = std::path::Path::new(fusion_base).join(format!("{}.fusion", output.uuid));
+        std::untrusted::fs::write(&fusion_file, b"fusion data").unwrap();
+
+        let request = RegisterInputFromOutputRequest::new(output_id.clone());
+        let input_id = service
+            .register_input_from_output(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
 
         let function = Function::new()
-            .id(Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap())
-            .name("mock-func-1")
-            .description("mock-desc")
-            .payload(b"mock-payload".to_vec())
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .inputs(vec![FunctionInput::new("input", "input_desc")])
             .public(true)
-            .arguments(vec!["arg1".to_string(), "arg2".to_string()])
-            .inputs(vec![function_input, function_input2])
-            .outputs(vec![function_output, function_output2])
-            .owner("teaclave".to_string());
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let new_consumer = || {
+            let request = CreateTaskRequest::new()
+                .function_id(function.external_id())
+                .executor(Executor::Builtin)
+                .inputs_ownership(hashmap!("input" => owners.clone()));
+            let task_id = service
+                .create_task(mock_request(request, "mock_user"))
+                .unwrap()
+                .task_id;
+            let request = AssignDataRequest::new(
+                task_id.clone(),
+                hashmap!("input" => input_id.clone()),
+                HashMap::new(),
+            );
+            service
+                .assign_data(mock_request(request, "mock_user"))
+                .unwrap();
+            task_id
+        };
+        let finish = |task_id: &ExternalID| {
+            let mut task: Task = service.read_from_db(task_id).unwrap();
+            task.status = TaskStatus::Running;
+            task.finish(TaskResult::Ok(TaskOutputs::new("", HashMap::new())))
+                .unwrap();
+            service
+                .write_task(&task, Some(TaskStatus::Running))
+                .unwrap();
+        };
+        let task_ids = vec![new_consumer(), new_consumer()];
 
-        self.write_to_db(&function)?;
+        // kept until the consumers finish
+        service.reap_temporary_fusion_outputs().unwrap();
+        assert!(service
+            .read_from_db::<TeaclaveOutputFile>(&output_id)
+            .is_ok());
+
+        finish(&task_ids[0]);
+        service.reap_temporary_fusion_outputs().unwrap();
+        assert!(service.read_from_db::<TeaclaveInputFile>(&input_id).is_ok());
+        assert!(fusion_file.exists());
+
+        finish(&task_ids[1]);
+        service.reap_temporary_fusion_outputs().unwrap();
+        assert!(service
+            .read_from_db::<TeaclaveOutputFile>(&output_id)
+            .is_err());
+        assert!(service
+            .read_from_db::<TeaclaveInputFile>(&input_id)
+            .is_err());
+        assert!(!fusion_file.exists());
+
+        std::untrusted::fs::remove_dir_all(fusion_base).unwrap();
+    }
+
+    pub fn handle_result_callback() {
+        let mut config = ManagementConfig::default();
+        config.callback_allowed_hosts = vec!["localhost".to_string()];
+        let storage = Arc::new(FailingQueueStorage::default());
+        let service = TeaclaveManagementService::with_storage(storage.clone(), config);
+        let rng = ring::rand::SystemRandom::new();
+        let private_key =
+            agreement::EphemeralPrivateKey::generate(&agreement::X25519, &rng).unwrap();
+        let public_key = private_key.compute_public_key().unwrap().as_ref().to_vec();
+
+        // only https endpoints on allowed hosts can be registered
+        for url in &[
+            "http://localhost:1/callback",
+            "https://example.com/callback",
+        ] {
+            let request =
+                SetResultCallbackRequest::new(Url::parse(url).unwrap(), public_key.clone());
+            assert!(service
+                .set_result_callback(mock_request(request, "mock_user"))
+                .is_err());
+        }
+        // results cannot be delivered without a valid public key
+        let url = Url::parse("https://localhost:1/callback").unwrap();
+        let request = SetResultCallbackRequest::new(url.clone(), vec![0u8; 16]);
+        assert!(service
+            .set_result_callback(mock_request(request, "mock_user"))
+            .is_err());
+        let request = SetResultCallbackRequest::new(url.clone(), public_key);
+        let secret = service
+            .set_result_callback(mock_request(request, "mock_user"))
+            .unwrap()
+            .secret;
 
-        let function_output = FunctionOutput::new("output", "output_desc");
         let function = Function::new()
-            .id(Uuid::parse_str("00000000-0000-0000-0000-000000000002").unwrap())
-            .name("mock-func-2")
-            .description("mock-desc")
-            .payload(b"mock-payload".to_vec())
+            .id(Uuid::new_v4())
+            .name("mock_function")
             .public(true)
-            .arguments(vec!["arg1".to_string()])
-            .outputs(vec![function_output])
-            .owner("teaclave".to_string());
+            .owner("mock_user");
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin);
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
 
-        self.write_to_db(&function)?;
-        Ok(())
+        // nothing is delivered until the task finishes
+        service.deliver_result_callbacks().unwrap();
+        let key = CALLBACK_DELIVERY_QUEUE_KEY.as_bytes();
+        assert!(service.dequeue_from_db(key).is_err());
+
+        let mut task: Task = service.read_from_db(&task_id).unwrap();
+        task.status = TaskStatus::Running;
+        task.finish(TaskResult::Ok(TaskOutputs::new("result", HashMap::new())))
+            .unwrap();
+        service
+            .write_task(&task, Some(TaskStatus::Running))
+            .unwrap();
+
+        // a delivery which cannot be queued is queued in the next round
+        storage.set_failing_key(Some(key));
+        service.deliver_result_callbacks().unwrap();
+        storage.set_failing_key(None);
+        assert!(service.dequeue_from_db(key).is_err());
+
+        // the endpoint is unreachable, so the attempt fails and is retried later
+        let now = current_timestamp();
+        service.deliver_result_callbacks().unwrap();
+        let delivery: CallbackDelivery =
+            serde_json::from_slice(&service.dequeue_from_db(key).unwrap()).unwrap();
+        assert!(service.dequeue_from_db(key).is_err());
+        assert_eq!(delivery.url, url);
+        assert_eq!(delivery.attempts, 1);
+        assert!(delivery.next_attempt_at > now);
+
+        // the payload is signed with the secret of the callback, and the
+        // result is only readable by the endpoint
+        let signed: SignedResultSummary = serde_json::from_str(&delivery.body).unwrap();
+        assert!(!delivery.body.contains(&hex::encode("result")));
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, &hex::decode(&secret).unwrap());
+        assert!(ring::hmac::verify(
+            &key,
+            signed.sealed_summary.as_bytes(),
+            &hex::decode(&signed.signature).unwrap()
+        )
+        .is_ok());
+        let sealed = hex::decode(&signed.sealed_summary).unwrap();
+        let opened = open_with_private_key(private_key, &sealed).unwrap();
+        let summary: ResultSummary = serde_json::from_slice(&opened).unwrap();
+        assert_eq!(summary.task_id, task_id.to_string());
+        assert_eq!(summary.status, "succeeded");
+        assert_eq!(summary.return_value, Some(hex::encode("result")));
+
+        let request = SetResultCallbackRequest::remove();
+        let response = service
+            .set_result_callback(mock_request(request, "mock_user"))
+            .unwrap();
+        assert!(response.secret.is_empty());
     }
-}
 
-#[cfg(feature = "enclave_unit_test")]
-pub mod tests {
-    use super::*;
-    use std::collections::HashMap;
-    use teaclave_types::{
-        hashmap, Executor, FileAuthTag, FileCrypto, FunctionArguments, FunctionInput,
-        FunctionInputFile, FunctionOutput, FunctionOutputFile,
-    };
-    use url::Url;
+    pub fn handle_retry_task() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        let mut config = ManagementConfig::default();
+        config.max_task_retries = 1;
+        let service = mock_service_with_config(config);
+        service.write_to_db(&function).unwrap();
 
-    pub fn handle_input_file() {
-        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
-        let cmac = FileAuthTag::mock();
-        let input_file =
-            TeaclaveInputFile::new(url, cmac, FileCrypto::default(), vec!["mock_user"]);
-        assert!(TeaclaveInputFile::match_prefix(&input_file.key_string()));
-        let value = input_file.to_vec().unwrap();
-        let deserialized_file = TeaclaveInputFile::from_slice(&value).unwrap();
-        info!("file: {:?}", deserialized_file);
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin);
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let fail = || {
+            let request = InvokeTaskRequest::new(task_id.clone());
+            service
+                .invoke_task(mock_request(request, "mock_user"))
+                .unwrap();
+            let mut task: Task = service.read_from_db(&task_id).unwrap();
+            task.abort("mock failure").unwrap();
+            service.write_to_db(&task).unwrap();
+        };
+        let retry = |user_id| {
+            let request = RetryTaskRequest::new(task_id.clone());
+            service.retry_task(mock_request(request, user_id))
+        };
+
+        // only failed tasks can be retried
+        assert!(retry("mock_user").is_err());
+        fail();
+        assert!(retry("other_user").is_err());
+        assert_eq!(retry("mock_user").unwrap().retries_left, 0);
+        let task: Task = service.read_from_db(&task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Approved);
+        assert_eq!(task.retries, 1);
+        assert!(task.approved_users.contains(&UserID::from("mock_user")));
+
+        // the retried task can be invoked again, up to the retry limit
+        fail();
+        assert!(retry("mock_user").is_err());
     }
 
-    pub fn handle_output_file() {
-        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
-        let output_file = TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user"]);
-        assert!(TeaclaveOutputFile::match_prefix(&output_file.key_string()));
-        let value = output_file.to_vec().unwrap();
-        let deserialized_file = TeaclaveOutputFile::from_slice(&value).unwrap();
-        info!("file: {:?}", deserialized_file);
+    pub fn handle_submit_task_graph() {
+        let storage = Arc::new(FailingQueueStorage::default());
+        let service =
+            TeaclaveManagementService::with_storage(storage.clone(), ManagementConfig::default());
+        let producer = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_producer")
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user");
+        let consumer = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_consumer")
+            .inputs(vec![FunctionInput::new("input", "input_desc")])
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user");
+        service.write_to_db(&producer).unwrap();
+        service.write_to_db(&consumer).unwrap();
+        let node = |function: &Function, inputs: Vec<&str>| {
+            let inputs_ownership: TaskFileOwners = inputs
+                .into_iter()
+                .map(|name| (name.to_string(), vec!["mock_user"]))
+                .collect();
+            CreateTaskRequest::new()
+                .function_id(function.external_id())
+                .executor(Executor::Builtin)
+                .inputs_ownership(inputs_ownership)
+                .outputs_ownership(hashmap!("output" => vec!["mock_user"]))
+        };
+
+        let request = SubmitTaskGraphRequest::new()
+            .node("prepare", node(&producer, vec![]))
+            .node("analyze", node(&consumer, vec!["input"]))
+            .edge(TaskGraphEdge::new("prepare", "output", "analyze", "input"));
+        let task_ids = service
+            .submit_task_graph(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_ids;
+        let prepare: Task = service.read_from_db(&task_ids["prepare"]).unwrap();
+        let analyze: Task = service.read_from_db(&task_ids["analyze"]).unwrap();
+        assert_eq!(prepare.output_links.len(), 1);
+        assert_eq!(
+            prepare.output_links[0].consumer_task_id,
+            analyze.external_id()
+        );
+        assert_eq!(prepare.output_links[0].input_name, "input");
+        assert_eq!(analyze.status, TaskStatus::Created);
+
+        // no task is created for cyclic graphs
+        let request = SubmitTaskGraphRequest::new()
+            .node("first", node(&consumer, vec!["input"]))
+            .node("second", node(&consumer, vec!["input"]))
+            .edge(TaskGraphEdge::new("first", "output", "second", "input"))
+            .edge(TaskGraphEdge::new("second", "output", "first", "input"));
+        assert!(service
+            .submit_task_graph(mock_request(request, "mock_user"))
+            .is_err());
+        let list_tasks = || {
+            let request = ListTasksRequest::new(None, 10);
+            service
+                .list_tasks(mock_request(request, "mock_user"))
+                .unwrap()
+                .tasks
+        };
+        assert_eq!(list_tasks().len(), 2);
+
+        // tasks already stored are removed if another one cannot be stored
+        storage.set_failing_key(Some(APPROVAL_DEADLINE_QUEUE_KEY.as_bytes()));
+        let request = SubmitTaskGraphRequest::new()
+            .node("prepare", node(&producer, vec![]))
+            .node(
+                "analyze",
+                node(&consumer, vec!["input"]).approval_deadline_seconds(60),
+            )
+            .edge(TaskGraphEdge::new("prepare", "output", "analyze", "input"));
+        assert!(service
+            .submit_task_graph(mock_request(request, "mock_user"))
+            .is_err());
+        storage.set_failing_key(None);
+        assert_eq!(list_tasks().len(), 2);
     }
 
-    pub fn handle_function() {
-        let function_input = FunctionInput::new("input", "input_desc");
-        let function_output = FunctionOutput::new("output", "output_desc");
+    pub fn handle_active_task_limit() {
         let function = Function::new()
             .id(Uuid::new_v4())
             .name("mock_function")
-            .description("mock function")
-            .payload(b"python script".to_vec())
-            .arguments(vec!["arg".to_string()])
-            .inputs(vec![function_input])
-            .outputs(vec![function_output])
             .public(true)
             .owner("mock_user");
-        assert!(Function::match_prefix(&function.key_string()));
-        let value = function.to_vec().unwrap();
-        let deserialized_function = Function::from_slice(&value).unwrap();
-        info!("function: {:?}", deserialized_function);
+        let mut config = ManagementConfig::default();
+        config.max_active_tasks_per_user = 2;
+        let service = mock_service_with_config(config);
+        service.write_to_db(&function).unwrap();
+
+        let new_approved_task = || {
+            let request = CreateTaskRequest::new()
+                .function_id(function.external_id())
+                .executor(Executor::Builtin);
+            let task_id = service
+                .create_task(mock_request(request, "mock_user"))
+                .unwrap()
+                .task_id;
+            let request = ApproveTaskRequest::new(task_id.clone());
+            service
+                .approve_task(mock_request(request, "mock_user"))
+                .unwrap();
+            task_id
+        };
+        let invoke = |task_id: &ExternalID| {
+            let request = InvokeTaskRequest::new(task_id.clone());
+            service.invoke_task(mock_request(request, "mock_user"))
+        };
+        let task_ids: Vec<ExternalID> = (0..3).map(|_| new_approved_task()).collect();
+
+        assert!(invoke(&task_ids[0]).is_ok());
+        assert!(invoke(&task_ids[1]).is_ok());
+        let error = invoke(&task_ids[2]).unwrap_err();
+        assert!(error.to_string().contains("retry later"));
+
+        // finished tasks do not count
+        let mut task: Task = service.read_from_db(&task_ids[0]).unwrap();
+        task.status = TaskStatus::Finished;
+        service.write_to_db(&task).unwrap();
+        assert!(invoke(&task_ids[2]).is_ok());
     }
 
-    pub fn handle_task() {
+    pub fn handle_function_rate_limit() {
         let function = Function::new()
             .id(Uuid::new_v4())
             .name("mock_function")
-            .description("mock function")
-            .payload(b"python script".to_vec())
-            .arguments(vec!["arg".to_string()])
             .public(true)
             .owner("mock_user");
-        let function_arguments = FunctionArguments::new(hashmap!("arg" => "data"));
+        let service = mock_service();
+        service.write_to_db(&function).unwrap();
 
-        let task = Task::new(
-            UserID::from("mock_user"),
-            Executor::MesaPy,
-            function_arguments,
-            HashMap::new(),
-            HashMap::new(),
-            function,
-        )
-        .unwrap();
+        let request = SetFunctionRateLimitRequest::new(function.external_id()).rate_limit(2, 60);
+        assert!(service
+            .set_function_rate_limit(mock_request(request, "mock_user_b"))
+            .is_err());
+        let request = SetFunctionRateLimitRequest::new(function.external_id()).rate_limit(2, 0);
+        assert!(service
+            .set_function_rate_limit(mock_request(request, "mock_user"))
+            .is_err());
+        let request = SetFunctionRateLimitRequest::new(function.external_id()).rate_limit(2, 60);
+        assert!(service
+            .set_function_rate_limit(mock_request(request, "mock_user"))
+            .is_ok());
 
-        assert!(Task::match_prefix(&task.key_string()));
-        let value = task.to_vec().unwrap();
-        let deserialized_task = Task::from_slice(&value).unwrap();
-        info!("task: {:?}", deserialized_task);
+        let new_approved_task = || {
+            let request = CreateTaskRequest::new()
+                .function_id(function.external_id())
+                .executor(Executor::Builtin);
+            let task_id = service
+                .create_task(mock_request(request, "mock_user"))
+                .unwrap()
+                .task_id;
+            let request = ApproveTaskRequest::new(task_id.clone());
+            service
+                .approve_task(mock_request(request, "mock_user"))
+                .unwrap();
+            task_id
+        };
+        let invoke = |task_id: &ExternalID| {
+            let request = InvokeTaskRequest::new(task_id.clone());
+            service.invoke_task(mock_request(request, "mock_user"))
+        };
+        let task_ids: Vec<ExternalID> = (0..3).map(|_| new_approved_task()).collect();
+
+        assert!(invoke(&task_ids[0]).is_ok());
+        assert!(invoke(&task_ids[1]).is_ok());
+        let error = invoke(&task_ids[2]).unwrap_err();
+        assert!(error.to_string().contains("retry later"));
+        let task: Task = service.read_from_db(&task_ids[2]).unwrap();
+        assert_eq!(task.status, TaskStatus::Approved);
+
+        // invocations older than the window do not count
+        let now = current_timestamp();
+        service
+            .write_function_invocations(&function.external_id(), &[now - 120, now - 60])
+            .unwrap();
+        assert!(invoke(&task_ids[2]).is_ok());
+    }
+
+    pub fn handle_task_resource_limits() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        let mut config = ManagementConfig::default();
+        config.max_task_memory_mb = Some(512);
+        config.max_task_runtime_seconds = Some(600);
+        let service = mock_service_with_config(config);
+        service.write_to_db(&function).unwrap();
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .max_memory_mb(1024);
+        let error = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert!(error.to_string().contains("maximum of 512"));
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .max_memory_mb(256);
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = InvokeTaskRequest::new(task_id);
+        service
+            .invoke_task(mock_request(request, "mock_user"))
+            .unwrap();
+
+        let value = service
+            .dequeue_from_db(StagedTask::get_queue_key().as_bytes())
+            .unwrap();
+        let staged_task = StagedTask::from_slice(&value).unwrap();
+        assert_eq!(staged_task.resource_limits.max_memory_mb, Some(256));
+        // the maximum applies to limits not requested
+        assert_eq!(staged_task.resource_limits.max_runtime_seconds, Some(600));
+    }
+
+    pub fn handle_default_executor() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .executor_type(ExecutorType::Builtin)
+            .public(true)
+            .owner("mock_user");
+        let service = mock_service();
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new().function_id(function.external_id());
+        assert!(service
+            .create_task(mock_request(request, "mock_user"))
+            .is_err());
+
+        let mut config = ManagementConfig::default();
+        config.default_executor = Some("builtin".to_string());
+        let service = mock_service_with_config(config);
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new().function_id(function.external_id());
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let task: Task = service.read_from_db(&task_id).unwrap();
+        assert_eq!(task.executor, Executor::Builtin);
+
+        let mut config = ManagementConfig::default();
+        config.default_executor = Some("mesapy".to_string());
+        let service = mock_service_with_config(config);
+        service.write_to_db(&function).unwrap();
+        let request = CreateTaskRequest::new().function_id(function.external_id());
+        let error = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap_err();
+        assert!(error.to_string().contains("cannot run builtin functions"));
+    }
+
+    pub fn handle_approval_excludes_creator() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user");
+        let mut config = ManagementConfig::default();
+        config.approval_excludes_creator = true;
+        let service = mock_service_with_config(config);
+        service.write_to_db(&function).unwrap();
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .outputs_ownership(hashmap!("output" => vec!["mock_user_b", "mock_user_c"]));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let mut task: Task = service.read_from_db(&task_id).unwrap();
+        assert!(task.approval_excludes_creator);
+        assert_eq!(task.participants.len(), 3);
+        task.status = TaskStatus::DataAssigned;
+        service.write_to_db(&task).unwrap();
+
+        let approve = |user: &str| {
+            let request = ApproveTaskRequest::new(task_id.clone());
+            service.approve_task(mock_request(request, user)).unwrap();
+            service.read_from_db::<Task>(&task_id).unwrap()
+        };
+        let task = approve("mock_user");
+        assert_eq!(task.status, TaskStatus::DataAssigned);
+        assert!(!task
+            .pending_approvals()
+            .contains(&UserID::from("mock_user")));
+        let task = approve("mock_user_b");
+        assert_eq!(task.status, TaskStatus::DataAssigned);
+        let task = approve("mock_user_c");
+        assert_eq!(task.status, TaskStatus::Approved);
+    }
+
+    pub fn handle_estimate_task() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .inputs(vec![
+                FunctionInput::new("input1", "input_desc"),
+                FunctionInput::new("input2", "input_desc"),
+                FunctionInput::new("input3", "input_desc"),
+            ])
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user");
+        let service = mock_service();
+        service.write_to_db(&function).unwrap();
+
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let register_input = |size: Option<u64>| {
+            let mut request =
+                RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), FileCrypto::Raw);
+            if let Some(size) = size {
+                request = request.expected_size(size);
+            }
+            service
+                .register_input_file(mock_request(request, "mock_user"))
+                .unwrap()
+                .data_id
+        };
+        let inputs = hashmap!(
+            "input1" => register_input(Some(1024)),
+            "input2" => register_input(Some(2048)),
+            "input3" => register_input(None),
+        );
+        let request = RegisterOutputFileRequest::new(url, FileCrypto::default());
+        let output_id = service
+            .register_output_file(mock_request(request, "mock_user"))
+            .unwrap()
+            .data_id;
+
+        let request = CreateTaskRequest::new()
+            .function_id(function.external_id())
+            .executor(Executor::Builtin)
+            .inputs_ownership(hashmap!(
+                "input1" => vec!["mock_user"],
+                "input2" => vec!["mock_user"],
+                "input3" => vec!["mock_user"],
+            ))
+            .outputs_ownership(hashmap!("output" => vec!["mock_user"]));
+        let task_id = service
+            .create_task(mock_request(request, "mock_user"))
+            .unwrap()
+            .task_id;
+        let request =
+            AssignDataRequest::new(task_id.clone(), inputs, hashmap!("output" => output_id));
+        service
+            .assign_data(mock_request(request, "mock_user"))
+            .unwrap();
+
+        // only approved tasks are estimated
+        let request = EstimateTaskRequest::new(task_id.clone());
+        assert!(service
+            .estimate_task(mock_request(request, "mock_user"))
+            .is_err());
+
+        let request = ApproveTaskRequest::new(task_id.clone());
+        service
+            .approve_task(mock_request(request, "mock_user"))
+            .unwrap();
+        let request = EstimateTaskRequest::new(task_id.clone());
+        assert!(service
+            .estimate_task(mock_request(request, "other_user"))
+            .is_err());
+        let request = EstimateTaskRequest::new(task_id);
+        let response = service
+            .estimate_task(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.input_bytes, 3072);
+        assert_eq!(response.unsized_files, 2);
+        assert_eq!(response.queued_tasks, 0);
+    }
+
+    pub fn handle_list_tasks_by_label() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        let service = mock_service();
+        service.write_to_db(&function).unwrap();
+
+        let create_task = |labels: HashMap<String, String>| {
+            let request = CreateTaskRequest::new()
+                .function_id(function.external_id())
+                .executor(Executor::Builtin)
+                .labels(labels);
+            service.create_task(mock_request(request, "mock_user"))
+        };
+        let first_id = create_task(hashmap!("project" => "a", "run" => "1"))
+            .unwrap()
+            .task_id;
+        create_task(hashmap!("project" => "b")).unwrap();
+        let second_id = create_task(hashmap!("project" => "a")).unwrap().task_id;
+        assert!(create_task(hashmap!("Project" => "a")).is_err());
+
+        let request = ListTasksRequest::new(None, 10).labels(hashmap!("project" => "a"));
+        let response = service
+            .list_tasks(mock_request(request, "mock_user"))
+            .unwrap();
+        let task_ids: Vec<ExternalID> = response.tasks.into_iter().map(|t| t.task_id).collect();
+        assert_eq!(task_ids, vec![first_id.clone(), second_id]);
+
+        let request = ListTasksRequest::new(None, 10).labels(hashmap!("run" => "1"));
+        let response = service
+            .list_tasks(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.tasks.len(), 1);
+        assert_eq!(response.tasks[0].task_id, first_id);
+
+        let request = ListTasksRequest::new(None, 10);
+        let response = service
+            .list_tasks(mock_request(request, "other_user"))
+            .unwrap();
+        assert!(response.tasks.is_empty());
+    }
+
+    pub fn handle_list_functions_by_category() {
+        let mut config = ManagementConfig::default();
+        config.function_categories = vec!["statistics".to_string(), "ml".to_string()];
+        let service = mock_service_with_config(config);
+
+        let register = |category: &str, tags: &[&str], public: bool| {
+            let request = RegisterFunctionRequest::new()
+                .name("mock_function")
+                .payload(b"python script".to_vec())
+                .public(public)
+                .category(category)
+                .tags(tags.iter());
+            service.register_function(mock_request(request, "mock_user"))
+        };
+        let mean_id = register("statistics", &["numeric"], true)
+            .unwrap()
+            .function_id;
+        let sort_id = register("statistics", &["numeric", "order"], false)
+            .unwrap()
+            .function_id;
+        register("ml", &["numeric"], true).unwrap();
+        assert!(register("unknown", &[], true).is_err());
+
+        let request = ListFunctionsRequest::new(None, 10).category("statistics");
+        let response = service
+            .list_functions(mock_request(request, "mock_user"))
+            .unwrap();
+        let function_ids: Vec<ExternalID> = response
+            .functions
+            .into_iter()
+            .map(|f| f.function_id)
+            .collect();
+        assert_eq!(function_ids, vec![mean_id.clone(), sort_id.clone()]);
+
+        let request = ListFunctionsRequest::new(None, 10)
+            .category("statistics")
+            .tags(vec!["order"]);
+        let response = service
+            .list_functions(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.functions.len(), 1);
+        assert_eq!(response.functions[0].function_id, sort_id);
+        assert_eq!(response.functions[0].tags, vec!["numeric", "order"]);
+
+        // private functions are only listed to their owners
+        let request = ListFunctionsRequest::new(None, 10).category("statistics");
+        let response = service
+            .list_functions(mock_request(request, "other_user"))
+            .unwrap();
+        assert_eq!(response.functions.len(), 1);
+        assert_eq!(response.functions[0].function_id, mean_id);
+
+        let request = GetFunctionRequest::new(mean_id);
+        let response = service
+            .get_function(mock_request(request, "mock_user"))
+            .unwrap();
+        assert_eq!(response.category.as_deref(), Some("statistics"));
     }
 
     pub fn handle_staged_task() {
@@ -663,8 +7991,12 @@ pub mod tests {
             .input_data(hashmap!("input" => input_data))
             .output_data(hashmap!("output" => output_data));
 
-        let value = staged_task.to_vec().unwrap();
+        let service = mock_service();
+        let queue_key = StagedTask::get_queue_key().as_bytes();
+        service.enqueue_to_db(queue_key, &staged_task).unwrap();
+        let value = service.dequeue_from_db(queue_key).unwrap();
         let deserialized_data = StagedTask::from_slice(&value).unwrap();
         info!("staged task: {:?}", deserialized_data);
+        assert!(service.dequeue_from_db(queue_key).is_err());
     }
 }
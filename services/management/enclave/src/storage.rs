@@ -0,0 +1,740 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::correlation::{request_correlation_id, CORRELATION_ID_METADATA_KEY};
+use crate::deadline::remaining_time;
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, VecDeque};
+use std::prelude::v1::*;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::sync::{SgxMutex as Mutex, SgxMutexGuard as MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::untrusted::time::SystemTimeEx;
+use teaclave_proto::teaclave_storage_service::{
+    DeleteRequest, DequeueRequest, EnqueueRequest, GetMultiRequest, GetRequest, PutRequest,
+    TeaclaveStorageClient,
+};
+use teaclave_rpc::endpoint::Endpoint;
+use teaclave_types::{namespaced_key, TeaclaveServiceResponseError, TeaclaveServiceResponseResult};
+
+// Key-value storage used by the management service to persist functions,
+// tasks and data, and to pass staged tasks to the scheduler.
+pub(crate) trait StorageBackend: Send + Sync {
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>>;
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()>;
+    fn delete(&self, key: &[u8]) -> Result<()>;
+    fn enqueue(&self, key: &[u8], value: &[u8]) -> Result<()>;
+    fn dequeue(&self, key: &[u8]) -> Result<Vec<u8>>;
+
+    // Reads values of keys in one batch, a missing key has no value.
+    fn get_multi(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>> {
+        Ok(keys.iter().map(|key| self.get(key).ok()).collect())
+    }
+
+    // Number of elements in a queue, read from the head and tail indices which
+    // the storage service keeps at queue-<key>-head and queue-<key>-tail.
+    fn queue_len(&self, key: &[u8]) -> Result<u64> {
+        let (head, tail) = self.queue_indices(key)?;
+        Ok(tail.saturating_sub(head))
+    }
+
+    // Reads up to limit elements of a queue from the index start without
+    // dequeuing them. Elements are kept at queue-<key>-<index>.
+    fn queue_range(&self, key: &[u8], start: u64, limit: usize) -> Result<Vec<Vec<u8>>> {
+        let (head, tail) = self.queue_indices(key)?;
+        let start = std::cmp::max(start, head);
+        let end = std::cmp::min(tail, start.saturating_add(limit as u64));
+        let keys: Vec<Vec<u8>> = (start..end)
+            .map(|index| [b"queue-", key, b"-", &(index as u32).to_le_bytes()].concat())
+            .collect();
+        Ok(self.get_multi(&keys)?.into_iter().flatten().collect())
+    }
+
+    fn queue_indices(&self, key: &[u8]) -> Result<(u64, u64)> {
+        let index_key = |suffix: &[u8]| [b"queue-", key, suffix].concat();
+        let values = self.get_multi(&[index_key(b"-head"), index_key(b"-tail")])?;
+        let index = |value: &Option<Vec<u8>>| match value {
+            Some(bytes) if bytes.len() == 4 => {
+                let mut index = [0u8; 4];
+                index.copy_from_slice(bytes);
+                u32::from_le_bytes(index) as u64
+            }
+            _ => 0,
+        };
+        Ok((index(&values[0]), index(&values[1])))
+    }
+}
+
+// Backend connected to the storage service. Calls made on behalf of a request
+// with a deadline time out when the deadline passes.
+pub(crate) struct RemoteStorage {
+    client: Mutex<TeaclaveStorageClient>,
+    endpoint: Endpoint,
+}
+
+impl RemoteStorage {
+    pub(crate) fn new(client: TeaclaveStorageClient, endpoint: Endpoint) -> Self {
+        Self {
+            client: Mutex::new(client),
+            endpoint,
+        }
+    }
+
+    fn call<T>(
+        &self,
+        f: impl FnOnce(&mut TeaclaveStorageClient) -> TeaclaveServiceResponseResult<T>,
+    ) -> Result<T> {
+        let timeout = remaining_time()?;
+        let mut client = self
+            .client
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?;
+        client.set_timeout(timeout)?;
+        match request_correlation_id() {
+            Some(correlation_id) => client
+                .metadata_mut()
+                .insert(CORRELATION_ID_METADATA_KEY.to_string(), correlation_id),
+            None => client.metadata_mut().remove(CORRELATION_ID_METADATA_KEY),
+        };
+        let result = f(&mut client);
+        match &result {
+            // The response of a timed out call may still arrive and be read
+            // as the response of the next call, so the client is replaced.
+            Err(TeaclaveServiceResponseError::ConnectionError(_)) if timeout.is_some() => {
+                match self.endpoint.connect() {
+                    Ok(channel) => *client = TeaclaveStorageClient::new(channel)?,
+                    Err(e) => log::warn!("Failed to reconnect to storage service: {:?}", e),
+                }
+            }
+            _ => client.set_timeout(None)?,
+        }
+        Ok(result?)
+    }
+}
+
+impl StorageBackend for RemoteStorage {
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let response = self.call(|client| client.get(GetRequest::new(key)))?;
+        Ok(response.value)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.call(|client| client.put(PutRequest::new(key, value)))?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.call(|client| client.delete(DeleteRequest::new(key)))?;
+        Ok(())
+    }
+
+    fn enqueue(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.call(|client| client.enqueue(EnqueueRequest::new(key, value)))?;
+        Ok(())
+    }
+
+    fn dequeue(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let response = self.call(|client| client.dequeue(DequeueRequest::new(key)))?;
+        Ok(response.value)
+    }
+
+    fn get_multi(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let response = self.call(|client| client.get_multi(GetMultiRequest::new(keys.to_vec())))?;
+        Ok(response.values)
+    }
+}
+
+// Backend prefixing all keys, including queue keys, with the key namespace
+// of the deployment.
+pub(crate) struct NamespacedStorage {
+    inner: Arc<dyn StorageBackend>,
+    namespace: String,
+}
+
+impl NamespacedStorage {
+    pub(crate) fn new(inner: Arc<dyn StorageBackend>, namespace: impl ToString) -> Self {
+        Self {
+            inner,
+            namespace: namespace.to_string(),
+        }
+    }
+
+    fn key(&self, key: &[u8]) -> Vec<u8> {
+        namespaced_key(&self.namespace, key)
+    }
+}
+
+impl StorageBackend for NamespacedStorage {
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+        self.inner.get(&self.key(key))
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.inner.put(&self.key(key), value)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.inner.delete(&self.key(key))
+    }
+
+    fn enqueue(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.inner.enqueue(&self.key(key), value)
+    }
+
+    fn dequeue(&self, key: &[u8]) -> Result<Vec<u8>> {
+        self.inner.dequeue(&self.key(key))
+    }
+
+    fn get_multi(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>> {
+        let keys: Vec<Vec<u8>> = keys.iter().map(|key| self.key(key)).collect();
+        self.inner.get_multi(&keys)
+    }
+
+    fn queue_len(&self, key: &[u8]) -> Result<u64> {
+        self.inner.queue_len(&self.key(key))
+    }
+
+    fn queue_range(&self, key: &[u8], start: u64, limit: usize) -> Result<Vec<Vec<u8>>> {
+        self.inner.queue_range(&self.key(key), start, limit)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Keys written recently and when they are written, in the order of writes.
+#[derive(Default)]
+struct RecentWrites {
+    written_at: HashMap<Vec<u8>, u64>,
+    order: VecDeque<(u64, Vec<u8>)>,
+}
+
+// Backend sending writes to the primary storage service and spreading reads
+// over its read-only replicas. Replicas may lag behind the primary, so keys
+// written within the read-your-writes window are read from the primary, as are
+// queues. A window of 0 disables the tracking of writes.
+pub(crate) struct ReplicatedStorage {
+    primary: Arc<dyn StorageBackend>,
+    replicas: Vec<Arc<dyn StorageBackend>>,
+    next_replica: AtomicUsize,
+    read_your_writes_seconds: u64,
+    recent_writes: Mutex<RecentWrites>,
+}
+
+impl ReplicatedStorage {
+    pub(crate) fn new(
+        primary: Arc<dyn StorageBackend>,
+        replicas: Vec<Arc<dyn StorageBackend>>,
+        read_your_writes_seconds: u64,
+    ) -> Self {
+        Self {
+            primary,
+            replicas,
+            next_replica: AtomicUsize::new(0),
+            read_your_writes_seconds,
+            recent_writes: Mutex::new(RecentWrites::default()),
+        }
+    }
+
+    fn record_write(&self, key: &[u8]) -> Result<()> {
+        if self.read_your_writes_seconds == 0 {
+            return Ok(());
+        }
+        let now = now();
+        let mut writes = self
+            .recent_writes
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock"))?;
+        while let Some((written_at, _)) = writes.order.front() {
+            if written_at + self.read_your_writes_seconds > now {
+                break;
+            }
+            if let Some((written_at, key)) = writes.order.pop_front() {
+                if writes.written_at.get(&key) == Some(&written_at) {
+                    writes.written_at.remove(&key);
+                }
+            }
+        }
+        writes.written_at.insert(key.to_vec(), now);
+        writes.order.push_back((now, key.to_vec()));
+        Ok(())
+    }
+
+    fn recently_written(&self, keys: &[&[u8]]) -> bool {
+        if self.read_your_writes_seconds == 0 {
+            return false;
+        }
+        let now = now();
+        match self.recent_writes.lock() {
+            Ok(writes) => keys.iter().any(|key| {
+                writes.written_at.get(*key).map_or(false, |written_at| {
+                    written_at + self.read_your_writes_seconds > now
+                })
+            }),
+            // Reading from the primary is always consistent.
+            Err(_) => true,
+        }
+    }
+
+    // Replicas are picked in turn, the primary serves reads without replicas.
+    fn reader(&self, keys: &[&[u8]]) -> &Arc<dyn StorageBackend> {
+        if self.replicas.is_empty() || self.recently_written(keys) {
+            return &self.primary;
+        }
+        let index = self.next_replica.fetch_add(1, Ordering::Relaxed);
+        &self.replicas[index % self.replicas.len()]
+    }
+}
+
+impl StorageBackend for ReplicatedStorage {
+    // Keys missing on a lagging replica are read again from the primary.
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let reader = self.reader(&[key]);
+        reader.get(key).or_else(|e| {
+            if Arc::ptr_eq(reader, &self.primary) {
+                Err(e)
+            } else {
+                self.primary.get(key)
+            }
+        })
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.primary.put(key, value)?;
+        self.record_write(key)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.primary.delete(key)?;
+        self.record_write(key)
+    }
+
+    fn enqueue(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.primary.enqueue(key, value)
+    }
+
+    fn dequeue(&self, key: &[u8]) -> Result<Vec<u8>> {
+        self.primary.dequeue(key)
+    }
+
+    fn get_multi(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>> {
+        let key_refs: Vec<&[u8]> = keys.iter().map(|key| key.as_slice()).collect();
+        self.reader(&key_refs).get_multi(keys)
+    }
+
+    fn queue_len(&self, key: &[u8]) -> Result<u64> {
+        self.primary.queue_len(key)
+    }
+
+    fn queue_range(&self, key: &[u8], start: u64, limit: usize) -> Result<Vec<Vec<u8>>> {
+        self.primary.queue_range(key, start, limit)
+    }
+}
+
+// A write made while the storage service is unavailable.
+enum PendingWrite {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+    Enqueue(Vec<u8>, Vec<u8>),
+}
+
+impl PendingWrite {
+    fn apply(&self, storage: &dyn StorageBackend) -> Result<()> {
+        match self {
+            PendingWrite::Put(key, value) => storage.put(key, value),
+            PendingWrite::Delete(key) => storage.delete(key),
+            PendingWrite::Enqueue(key, value) => storage.enqueue(key, value),
+        }
+    }
+}
+
+// Values recently read or written and when they are cached, in the order of
+// caching. Deleted keys are cached without a value. A key cached again is in
+// the order more than once, only the entry with the sequence number of its
+// value is current.
+#[derive(Default)]
+struct ReadCache {
+    values: HashMap<Vec<u8>, (u64, u64, Option<Vec<u8>>)>,
+    order: VecDeque<(u64, u64, Vec<u8>)>,
+    sequence: u64,
+}
+
+// Only failures to reach the storage service degrade the backend, other
+// errors, e.g., of missing keys, are returned as is.
+pub(crate) fn is_unavailable(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<TeaclaveServiceResponseError>() {
+        Some(TeaclaveServiceResponseError::ConnectionError(_)) => true,
+        _ => false,
+    }
+}
+
+// Backend which keeps the service available during short outages of the
+// storage service. Up to cache_capacity values cached within the TTL are
+// served while the service is unavailable, and up to write_queue_depth writes
+// are queued and replayed in order once it is back. Dequeues and queue reads
+// are not served.
+pub(crate) struct DegradedStorage {
+    inner: Arc<dyn StorageBackend>,
+    cache_ttl_seconds: u64,
+    cache_capacity: usize,
+    write_queue_depth: usize,
+    cache: Mutex<ReadCache>,
+    pending_writes: Mutex<VecDeque<PendingWrite>>,
+}
+
+impl DegradedStorage {
+    pub(crate) fn new(
+        inner: Arc<dyn StorageBackend>,
+        cache_ttl_seconds: u64,
+        cache_capacity: usize,
+        write_queue_depth: usize,
+    ) -> Self {
+        Self {
+            inner,
+            cache_ttl_seconds,
+            cache_capacity,
+            write_queue_depth,
+            cache: Mutex::new(ReadCache::default()),
+            pending_writes: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    // Replays queued writes and returns the number of writes still queued
+    // because the service is unavailable.
+    pub(crate) fn flush_pending_writes(&self) -> Result<usize> {
+        let mut pending = self.lock_pending()?;
+        match self.replay(&mut pending) {
+            Err(e) if !is_unavailable(&e) => Err(e),
+            _ => Ok(pending.len()),
+        }
+    }
+
+    // Evicts expired values, and the oldest values while the cache holds more
+    // than cache_capacity entries.
+    fn cache_value(&self, key: &[u8], value: Option<Vec<u8>>) {
+        let now = now();
+        let mut cache = match self.cache.lock() {
+            Ok(cache) => cache,
+            Err(_) => return,
+        };
+        cache.sequence += 1;
+        let sequence = cache.sequence;
+        cache.values.insert(key.to_vec(), (sequence, now, value));
+        cache.order.push_back((sequence, now, key.to_vec()));
+        while let Some((_, cached_at, _)) = cache.order.front() {
+            if cached_at + self.cache_ttl_seconds > now && cache.order.len() <= self.cache_capacity
+            {
+                break;
+            }
+            if let Some((sequence, _, key)) = cache.order.pop_front() {
+                if cache.values.get(&key).map(|(s, _, _)| *s) == Some(sequence) {
+                    cache.values.remove(&key);
+                }
+            }
+        }
+    }
+
+    fn cached_value(&self, key: &[u8]) -> Option<Option<Vec<u8>>> {
+        let now = now();
+        let cache = self.cache.lock().ok()?;
+        match cache.values.get(key) {
+            Some((_, cached_at, value)) if cached_at + self.cache_ttl_seconds > now => {
+                Some(value.clone())
+            }
+            _ => None,
+        }
+    }
+
+    // Replays queued writes in order, stopping at the first failure. Reads
+    // and writes go to the service only after all queued writes are replayed.
+    fn replay(&self, pending: &mut VecDeque<PendingWrite>) -> Result<()> {
+        while let Some(write) = pending.front() {
+            write.apply(self.inner.as_ref())?;
+            pending.pop_front();
+        }
+        Ok(())
+    }
+
+    fn lock_pending(&self) -> Result<MutexGuard<VecDeque<PendingWrite>>> {
+        self.pending_writes
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock pending writes"))
+    }
+
+    // Runs the operation on the service once queued writes are replayed, and
+    // falls back if the service is unavailable. Returns whether the value is
+    // from the fallback.
+    fn run<T>(
+        &self,
+        operation: impl FnOnce(&dyn StorageBackend) -> Result<T>,
+        fallback: impl FnOnce(anyhow::Error) -> Result<T>,
+    ) -> Result<(T, bool)> {
+        let result = {
+            let mut pending = self.lock_pending()?;
+            self.replay(&mut pending)
+        }
+        .and_then(|_| operation(self.inner.as_ref()));
+        match result {
+            Ok(value) => Ok((value, false)),
+            Err(e) if is_unavailable(&e) => Ok((fallback(e)?, true)),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Returns whether the write is queued.
+    fn write(&self, write: PendingWrite) -> Result<bool> {
+        let cached = match &write {
+            PendingWrite::Put(key, value) => Some((key.clone(), Some(value.clone()))),
+            PendingWrite::Delete(key) => Some((key.clone(), None)),
+            PendingWrite::Enqueue(_, _) => None,
+        };
+        let mut pending = self.lock_pending()?;
+        let result = self
+            .replay(&mut pending)
+            .and_then(|_| write.apply(self.inner.as_ref()));
+        let queued = match result {
+            Ok(()) => false,
+            Err(e) if is_unavailable(&e) && pending.len() < self.write_queue_depth => {
+                pending.push_back(write);
+                true
+            }
+            Err(e) => return Err(e),
+        };
+        if let Some((key, value)) = cached {
+            self.cache_value(&key, value);
+        }
+        Ok(queued)
+    }
+
+    fn get_value(&self, key: &[u8]) -> Result<(Vec<u8>, bool)> {
+        self.run(
+            |storage| {
+                let value = storage.get(key)?;
+                self.cache_value(key, Some(value.clone()));
+                Ok(value)
+            },
+            |e| match self.cached_value(key) {
+                Some(Some(value)) => Ok(value),
+                Some(None) => Err(anyhow!("Key not found")),
+                None => Err(e),
+            },
+        )
+    }
+
+    // All keys must be cached to be served while the service is unavailable.
+    fn get_values(&self, keys: &[Vec<u8>]) -> Result<(Vec<Option<Vec<u8>>>, bool)> {
+        self.run(
+            |storage| {
+                let values = storage.get_multi(keys)?;
+                for (key, value) in keys.iter().zip(values.iter()) {
+                    if value.is_some() {
+                        self.cache_value(key, value.clone());
+                    }
+                }
+                Ok(values)
+            },
+            |e| {
+                keys.iter()
+                    .map(|key| self.cached_value(key))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(e)
+            },
+        )
+    }
+}
+
+impl StorageBackend for DegradedStorage {
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+        self.get_value(key).map(|(value, _)| value)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.write(PendingWrite::Put(key.to_vec(), value.to_vec()))
+            .map(|_| ())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.write(PendingWrite::Delete(key.to_vec())).map(|_| ())
+    }
+
+    fn enqueue(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.write(PendingWrite::Enqueue(key.to_vec(), value.to_vec()))
+            .map(|_| ())
+    }
+
+    fn dequeue(&self, key: &[u8]) -> Result<Vec<u8>> {
+        self.run(|storage| storage.dequeue(key), Err)
+            .map(|(value, _)| value)
+    }
+
+    fn get_multi(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>> {
+        self.get_values(keys).map(|(values, _)| values)
+    }
+
+    fn queue_len(&self, key: &[u8]) -> Result<u64> {
+        self.run(|storage| storage.queue_len(key), Err)
+            .map(|(len, _)| len)
+    }
+
+    fn queue_range(&self, key: &[u8], start: u64, limit: usize) -> Result<Vec<Vec<u8>>> {
+        self.run(|storage| storage.queue_range(key, start, limit), Err)
+            .map(|(values, _)| values)
+    }
+}
+
+// Degraded storage used by a single call, which records whether any access of
+// the call is served from the cache or queued.
+pub(crate) struct DegradedCall {
+    storage: Arc<DegradedStorage>,
+    degraded: AtomicBool,
+}
+
+impl DegradedCall {
+    pub(crate) fn new(storage: Arc<DegradedStorage>) -> Self {
+        Self {
+            storage,
+            degraded: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    fn record<T>(&self, result: Result<(T, bool)>) -> Result<T> {
+        let (value, degraded) = result?;
+        if degraded {
+            self.degraded.store(true, Ordering::Relaxed);
+        }
+        Ok(value)
+    }
+}
+
+impl StorageBackend for DegradedCall {
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+        self.record(self.storage.get_value(key))
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let write = PendingWrite::Put(key.to_vec(), value.to_vec());
+        self.record(self.storage.write(write).map(|queued| ((), queued)))
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        let write = PendingWrite::Delete(key.to_vec());
+        self.record(self.storage.write(write).map(|queued| ((), queued)))
+    }
+
+    fn enqueue(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let write = PendingWrite::Enqueue(key.to_vec(), value.to_vec());
+        self.record(self.storage.write(write).map(|queued| ((), queued)))
+    }
+
+    fn dequeue(&self, key: &[u8]) -> Result<Vec<u8>> {
+        self.record(self.storage.run(|storage| storage.dequeue(key), Err))
+    }
+
+    fn get_multi(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>> {
+        self.record(self.storage.get_values(keys))
+    }
+
+    fn queue_len(&self, key: &[u8]) -> Result<u64> {
+        self.record(self.storage.run(|storage| storage.queue_len(key), Err))
+    }
+
+    fn queue_range(&self, key: &[u8], start: u64, limit: usize) -> Result<Vec<Vec<u8>>> {
+        self.record(
+            self.storage
+                .run(|storage| storage.queue_range(key, start, limit), Err),
+        )
+    }
+}
+
+// Backend keeping everything in memory, which is used for testing the service
+// without a storage service.
+#[cfg(feature = "enclave_unit_test")]
+#[derive(Default)]
+pub(crate) struct MemoryStorage {
+    items: Mutex<std::collections::HashMap<Vec<u8>, Vec<u8>>>,
+    queues: Mutex<std::collections::HashMap<Vec<u8>, std::collections::VecDeque<Vec<u8>>>>,
+}
+
+#[cfg(feature = "enclave_unit_test")]
+impl StorageBackend for MemoryStorage {
+    fn get(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let items = self.items.lock().map_err(|_| anyhow!("Cannot lock"))?;
+        items
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow!("Key not found"))
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let mut items = self.items.lock().map_err(|_| anyhow!("Cannot lock"))?;
+        items.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        let mut items = self.items.lock().map_err(|_| anyhow!("Cannot lock"))?;
+        items.remove(key);
+        Ok(())
+    }
+
+    fn enqueue(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let mut queues = self.queues.lock().map_err(|_| anyhow!("Cannot lock"))?;
+        queues
+            .entry(key.to_vec())
+            .or_default()
+            .push_back(value.to_vec());
+        Ok(())
+    }
+
+    fn dequeue(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let mut queues = self.queues.lock().map_err(|_| anyhow!("Cannot lock"))?;
+        queues
+            .get_mut(key)
+            .and_then(|queue| queue.pop_front())
+            .ok_or_else(|| anyhow!("Queue is empty"))
+    }
+
+    fn queue_len(&self, key: &[u8]) -> Result<u64> {
+        let queues = self.queues.lock().map_err(|_| anyhow!("Cannot lock"))?;
+        Ok(queues.get(key).map_or(0, |queue| queue.len() as u64))
+    }
+
+    // Indices of elements start from the current head of the queue.
+    fn queue_range(&self, key: &[u8], start: u64, limit: usize) -> Result<Vec<Vec<u8>>> {
+        let queues = self.queues.lock().map_err(|_| anyhow!("Cannot lock"))?;
+        Ok(queues.get(key).map_or_else(Vec::new, |queue| {
+            queue
+                .iter()
+                .skip(start as usize)
+                .take(limit)
+                .cloned()
+                .collect()
+        }))
+    }
+}
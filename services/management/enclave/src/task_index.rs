@@ -0,0 +1,104 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::pagination::CursorSigner;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::prelude::v1::*;
+use teaclave_proto::teaclave_frontend_service::TaskEntry;
+use teaclave_types::{ExternalID, UserID};
+
+const TASK_INDEX_PREFIX: &str = "task-index";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct TaskIndexEntry {
+    task_id: ExternalID,
+    created_at: u64,
+    labels: HashMap<String, String>,
+    seq: u64,
+}
+
+// Tasks created by or shared with a user, in the order of creation. Labels
+// of tasks never change, so they are kept in the index for filtering.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct TaskIndex {
+    tasks: Vec<TaskIndexEntry>,
+    last_seq: u64,
+}
+
+impl TaskIndex {
+    pub(crate) fn get_key(user_id: &UserID) -> String {
+        format!("{}-{}", TASK_INDEX_PREFIX, user_id)
+    }
+
+    pub(crate) fn add(
+        &mut self,
+        task_id: ExternalID,
+        created_at: u64,
+        labels: HashMap<String, String>,
+    ) {
+        self.last_seq += 1;
+        self.tasks.push(TaskIndexEntry {
+            task_id,
+            created_at,
+            labels,
+            seq: self.last_seq,
+        });
+    }
+
+    pub(crate) fn remove(&mut self, task_id: &ExternalID) {
+        self.tasks.retain(|entry| &entry.task_id != task_id);
+    }
+
+    pub(crate) fn task_ids(&self) -> impl Iterator<Item = &ExternalID> {
+        self.tasks.iter().map(|entry| &entry.task_id)
+    }
+
+    // Returns a page of tasks carrying all of the given labels in the order
+    // of creation and the cursor of the next page.
+    pub(crate) fn page(
+        &self,
+        signer: &CursorSigner,
+        labels: &HashMap<String, String>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<TaskEntry>, Option<String>)> {
+        let matched = self.tasks.iter().filter(|entry| {
+            labels
+                .iter()
+                .all(|(key, value)| entry.labels.get(key) == Some(value))
+        });
+        let (entries, next_cursor) = signer.paginate(
+            matched,
+            |entry| (entry.seq, entry.task_id.to_string()),
+            cursor,
+            limit,
+        )?;
+        let tasks = entries
+            .into_iter()
+            .map(|entry| {
+                TaskEntry::new(
+                    entry.task_id.clone(),
+                    entry.created_at,
+                    entry.labels.clone(),
+                )
+            })
+            .collect();
+        Ok((tasks, next_cursor))
+    }
+}
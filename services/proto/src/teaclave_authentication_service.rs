@@ -20,6 +20,7 @@ use anyhow::{Error, Result};
 use core::convert::TryInto;
 use std::prelude::v1::*;
 use teaclave_rpc::into_request;
+use teaclave_types::UserRole;
 
 use crate::teaclave_authentication_service_proto as proto;
 use crate::teaclave_common;
@@ -98,11 +99,131 @@ impl UserAuthenticateRequest {
 #[derive(Debug)]
 pub struct UserAuthenticateResponse {
     pub accept: bool,
+    pub role: UserRole,
 }
 
 impl UserAuthenticateResponse {
     pub fn new(accept: bool) -> Self {
-        Self { accept }
+        Self {
+            accept,
+            role: UserRole::default(),
+        }
+    }
+
+    pub fn role(self, role: UserRole) -> Self {
+        Self { role, ..self }
+    }
+}
+
+// A token issued at login, the secret token itself is not listed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserSession {
+    pub session_id: std::string::String,
+    pub issued_at: u64,
+    pub expires_at: u64,
+}
+
+#[into_request(TeaclaveAuthenticationApiRequest::ListSessions)]
+#[derive(Debug)]
+pub struct ListSessionsRequest {
+    pub credential: teaclave_common::UserCredential,
+}
+
+impl ListSessionsRequest {
+    pub fn new(credential: teaclave_common::UserCredential) -> Self {
+        Self { credential }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::ListSessions)]
+#[derive(Debug)]
+pub struct ListSessionsResponse {
+    pub sessions: Vec<UserSession>,
+}
+
+impl ListSessionsResponse {
+    pub fn new(sessions: Vec<UserSession>) -> Self {
+        Self { sessions }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiRequest::RevokeSession)]
+#[derive(Debug)]
+pub struct RevokeSessionRequest {
+    pub credential: teaclave_common::UserCredential,
+    pub session_id: std::string::String,
+}
+
+impl RevokeSessionRequest {
+    pub fn new(credential: teaclave_common::UserCredential, session_id: impl Into<String>) -> Self {
+        Self {
+            credential,
+            session_id: session_id.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::RevokeSession)]
+#[derive(Debug, Default)]
+pub struct RevokeSessionResponse;
+
+// An exported user account. Only the salted password hash is carried, never
+// the plaintext password.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserRecord {
+    pub id: std::string::String,
+    pub salt: Vec<u8>,
+    pub password_hash: Vec<u8>,
+    pub role: UserRole,
+}
+
+#[into_request(TeaclaveAuthenticationApiRequest::ExportUsers)]
+#[derive(Debug)]
+pub struct ExportUsersRequest {
+    pub credential: teaclave_common::UserCredential,
+}
+
+impl ExportUsersRequest {
+    pub fn new(credential: teaclave_common::UserCredential) -> Self {
+        Self { credential }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiResponse::ExportUsers)]
+#[derive(Debug)]
+pub struct ExportUsersResponse {
+    pub users: Vec<UserRecord>,
+}
+
+impl ExportUsersResponse {
+    pub fn new(users: Vec<UserRecord>) -> Self {
+        Self { users }
+    }
+}
+
+#[into_request(TeaclaveAuthenticationApiRequest::ImportUsers)]
+#[derive(Debug)]
+pub struct ImportUsersRequest {
+    pub credential: teaclave_common::UserCredential,
+    pub users: Vec<UserRecord>,
+}
+
+impl ImportUsersRequest {
+    pub fn new(credential: teaclave_common::UserCredential, users: Vec<UserRecord>) -> Self {
+        Self { credential, users }
+    }
+}
+
+// Users already existing in the service are skipped and left untouched.
+#[into_request(TeaclaveAuthenticationApiResponse::ImportUsers)]
+#[derive(Debug)]
+pub struct ImportUsersResponse {
+    pub skipped: Vec<std::string::String>,
+}
+
+impl ImportUsersResponse {
+    pub fn new(skipped: Vec<std::string::String>) -> Self {
+        Self { skipped }
     }
 }
 
@@ -211,6 +332,7 @@ impl std::convert::TryFrom<proto::UserAuthenticateResponse> for UserAuthenticate
     fn try_from(proto: proto::UserAuthenticateResponse) -> Result<Self> {
         let ret = Self {
             accept: proto.accept,
+            role: proto.role.try_into()?,
         };
 
         Ok(ret)
@@ -221,6 +343,246 @@ impl From<UserAuthenticateResponse> for proto::UserAuthenticateResponse {
     fn from(response: UserAuthenticateResponse) -> Self {
         Self {
             accept: response.accept,
+            role: response.role.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::UserSession> for UserSession {
+    type Error = Error;
+
+    fn try_from(proto: proto::UserSession) -> Result<Self> {
+        let ret = Self {
+            session_id: proto.session_id,
+            issued_at: proto.issued_at,
+            expires_at: proto.expires_at,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<UserSession> for proto::UserSession {
+    fn from(session: UserSession) -> Self {
+        Self {
+            session_id: session.session_id,
+            issued_at: session.issued_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListSessionsRequest> for ListSessionsRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListSessionsRequest) -> Result<Self> {
+        let ret = Self {
+            credential: proto
+                .credential
+                .ok_or_else(|| anyhow!("Missing credential"))?
+                .try_into()?,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListSessionsRequest> for proto::ListSessionsRequest {
+    fn from(request: ListSessionsRequest) -> Self {
+        Self {
+            credential: Some(request.credential.into()),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListSessionsResponse> for ListSessionsResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListSessionsResponse) -> Result<Self> {
+        let sessions = proto
+            .sessions
+            .into_iter()
+            .map(UserSession::try_from)
+            .collect::<Result<_>>()?;
+
+        Ok(Self { sessions })
+    }
+}
+
+impl From<ListSessionsResponse> for proto::ListSessionsResponse {
+    fn from(response: ListSessionsResponse) -> Self {
+        Self {
+            sessions: response
+                .sessions
+                .into_iter()
+                .map(proto::UserSession::from)
+                .collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RevokeSessionRequest> for RevokeSessionRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RevokeSessionRequest) -> Result<Self> {
+        let ret = Self {
+            credential: proto
+                .credential
+                .ok_or_else(|| anyhow!("Missing credential"))?
+                .try_into()?,
+            session_id: proto.session_id,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<RevokeSessionRequest> for proto::RevokeSessionRequest {
+    fn from(request: RevokeSessionRequest) -> Self {
+        Self {
+            credential: Some(request.credential.into()),
+            session_id: request.session_id,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RevokeSessionResponse> for RevokeSessionResponse {
+    type Error = Error;
+
+    fn try_from(_response: proto::RevokeSessionResponse) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl From<RevokeSessionResponse> for proto::RevokeSessionResponse {
+    fn from(_response: RevokeSessionResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::UserRecord> for UserRecord {
+    type Error = Error;
+
+    fn try_from(proto: proto::UserRecord) -> Result<Self> {
+        let ret = Self {
+            id: proto.id,
+            salt: proto.salt,
+            password_hash: proto.password_hash,
+            role: proto.role.try_into()?,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<UserRecord> for proto::UserRecord {
+    fn from(record: UserRecord) -> Self {
+        Self {
+            id: record.id,
+            salt: record.salt,
+            password_hash: record.password_hash,
+            role: record.role.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ExportUsersRequest> for ExportUsersRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ExportUsersRequest) -> Result<Self> {
+        let ret = Self {
+            credential: proto
+                .credential
+                .ok_or_else(|| anyhow!("Missing credential"))?
+                .try_into()?,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ExportUsersRequest> for proto::ExportUsersRequest {
+    fn from(request: ExportUsersRequest) -> Self {
+        Self {
+            credential: Some(request.credential.into()),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ExportUsersResponse> for ExportUsersResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ExportUsersResponse) -> Result<Self> {
+        let users = proto
+            .users
+            .into_iter()
+            .map(UserRecord::try_from)
+            .collect::<Result<_>>()?;
+
+        Ok(Self { users })
+    }
+}
+
+impl From<ExportUsersResponse> for proto::ExportUsersResponse {
+    fn from(response: ExportUsersResponse) -> Self {
+        Self {
+            users: response
+                .users
+                .into_iter()
+                .map(proto::UserRecord::from)
+                .collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ImportUsersRequest> for ImportUsersRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ImportUsersRequest) -> Result<Self> {
+        let users = proto
+            .users
+            .into_iter()
+            .map(UserRecord::try_from)
+            .collect::<Result<_>>()?;
+        let ret = Self {
+            credential: proto
+                .credential
+                .ok_or_else(|| anyhow!("Missing credential"))?
+                .try_into()?,
+            users,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ImportUsersRequest> for proto::ImportUsersRequest {
+    fn from(request: ImportUsersRequest) -> Self {
+        Self {
+            credential: Some(request.credential.into()),
+            users: request
+                .users
+                .into_iter()
+                .map(proto::UserRecord::from)
+                .collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ImportUsersResponse> for ImportUsersResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ImportUsersResponse) -> Result<Self> {
+        Ok(Self {
+            skipped: proto.skipped,
+        })
+    }
+}
+
+impl From<ImportUsersResponse> for proto::ImportUsersResponse {
+    fn from(response: ImportUsersResponse) -> Self {
+        Self {
+            skipped: response.skipped,
         }
     }
 }
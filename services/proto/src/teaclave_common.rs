@@ -111,6 +111,7 @@ pub fn i32_to_task_status(status: i32) -> Result<TaskStatus> {
         Some(proto::TaskStatus::Staged) => TaskStatus::Staged,
         Some(proto::TaskStatus::Running) => TaskStatus::Running,
         Some(proto::TaskStatus::Finished) => TaskStatus::Finished,
+        Some(proto::TaskStatus::Canceled) => TaskStatus::Canceled,
         None => bail!("invalid task status"),
     };
     Ok(ret)
@@ -124,6 +125,7 @@ pub fn i32_from_task_status(status: TaskStatus) -> i32 {
         TaskStatus::Staged => proto::TaskStatus::Staged as i32,
         TaskStatus::Running => proto::TaskStatus::Running as i32,
         TaskStatus::Finished => proto::TaskStatus::Finished as i32,
+        TaskStatus::Canceled => proto::TaskStatus::Canceled as i32,
     }
 }
 
@@ -176,6 +178,9 @@ impl std::convert::TryFrom<proto::TaskResult> for TaskResult {
                     let failure_info = task_failure.try_into()?;
                     TaskResult::Err(failure_info)
                 }
+                proto::task_result::Result::InProgress(task_outputs) => {
+                    TaskResult::InProgress(task_outputs.tags_map.try_into()?)
+                }
             },
             None => TaskResult::NotReady,
         };
@@ -188,6 +193,12 @@ impl std::convert::From<TaskResult> for proto::TaskResult {
         let opt_result = match result {
             TaskResult::Ok(outputs) => Some(proto::task_result::Result::Ok(outputs.into())),
             TaskResult::Err(failure) => Some(proto::task_result::Result::Err(failure.into())),
+            TaskResult::InProgress(tags) => {
+                Some(proto::task_result::Result::InProgress(proto::TaskOutputs {
+                    return_value: Vec::new(),
+                    tags_map: tags.into(),
+                }))
+            }
             TaskResult::NotReady => None,
         };
 
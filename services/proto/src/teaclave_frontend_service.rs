@@ -26,9 +26,10 @@ use std::collections::HashMap;
 use std::prelude::v1::*;
 use teaclave_rpc::into_request;
 use teaclave_types::{
-    Executor, ExecutorType, ExternalID, FileAuthTag, FileCrypto, Function, FunctionArguments,
-    FunctionInput, FunctionOutput, OwnerList, TaskFileOwners, TaskResult, TaskStatus, UserID,
-    UserList,
+    ArgumentValue, Executor, ExecutorType, ExternalID, FileAuthTag, FileCrypto, Function,
+    FunctionArguments, FunctionBundle, FunctionInput, FunctionOutput, FunctionRateLimit,
+    InputFileMetadata, InputSample, OwnerList, ReturnValueSchema, TaskEvent, TaskFileOwners,
+    TaskGraphEdge, TaskResult, TaskStatus, UserID, UserList,
 };
 use url::Url;
 use uuid::Uuid;
@@ -45,6 +46,12 @@ pub struct RegisterInputFileRequest {
     pub url: Url,
     pub cmac: FileAuthTag,
     pub crypto_info: FileCrypto,
+    pub inline_data: Option<Vec<u8>>,
+    pub expected_size: Option<u64>,
+    pub recipient_public_key: Option<Vec<u8>>,
+    pub source: Option<String>,
+    pub crypto_key_name: Option<String>,
+    pub lazy: bool,
 }
 
 impl RegisterInputFileRequest {
@@ -53,8 +60,70 @@ impl RegisterInputFileRequest {
             url,
             cmac,
             crypto_info: crypto.into(),
+            inline_data: None,
+            expected_size: None,
+            recipient_public_key: None,
+            source: None,
+            crypto_key_name: None,
+            lazy: false,
         }
     }
+
+    // The encrypted data is carried in the request and kept by the service,
+    // the url is ignored.
+    pub fn new_inline(data: Vec<u8>, cmac: FileAuthTag, crypto: impl Into<FileCrypto>) -> Self {
+        Self {
+            url: Url::parse("inline:data").unwrap(),
+            cmac,
+            crypto_info: crypto.into(),
+            inline_data: Some(data),
+            expected_size: None,
+            recipient_public_key: None,
+            source: None,
+            crypto_key_name: None,
+            lazy: false,
+        }
+    }
+
+    // Staging fails if the downloaded file does not have this size.
+    pub fn expected_size(self, expected_size: u64) -> Self {
+        Self {
+            expected_size: Some(expected_size),
+            ..self
+        }
+    }
+
+    // Secrets of the file, e.g., its crypto info, are returned encrypted to
+    // this X25519 public key, see teaclave_crypto::seal_to_public_key.
+    pub fn recipient_public_key(self, public_key: Vec<u8>) -> Self {
+        Self {
+            recipient_public_key: Some(public_key),
+            ..self
+        }
+    }
+
+    // Recorded in the metadata of the file for auditing.
+    pub fn source(self, source: impl Into<String>) -> Self {
+        Self {
+            source: Some(source.into()),
+            ..self
+        }
+    }
+
+    // The file is encrypted with the key registered under this name, which
+    // replaces the crypto info of the request.
+    pub fn crypto_key_name(self, name: impl Into<String>) -> Self {
+        Self {
+            crypto_key_name: Some(name.into()),
+            ..self
+        }
+    }
+
+    // The remote file may not exist yet, it is checked when a task consuming
+    // the file is invoked rather than at registration.
+    pub fn lazy(self, lazy: bool) -> Self {
+        Self { lazy, ..self }
+    }
 }
 
 #[into_request(TeaclaveFrontendResponse::RegisterInputFile)]
@@ -62,11 +131,21 @@ impl RegisterInputFileRequest {
 #[derive(Debug, PartialEq)]
 pub struct RegisterInputFileResponse {
     pub data_id: ExternalID,
+    // Soft limits of the user which are crossed, the file is registered
+    // nevertheless
+    pub warnings: Vec<String>,
 }
 
 impl RegisterInputFileResponse {
     pub fn new(data_id: ExternalID) -> Self {
-        Self { data_id }
+        Self {
+            data_id,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn warnings(self, warnings: Vec<String>) -> Self {
+        Self { warnings, ..self }
     }
 }
 
@@ -76,6 +155,7 @@ impl RegisterInputFileResponse {
 pub struct RegisterOutputFileRequest {
     pub url: Url,
     pub crypto_info: FileCrypto,
+    pub crypto_key_name: Option<String>,
 }
 
 impl RegisterOutputFileRequest {
@@ -83,6 +163,16 @@ impl RegisterOutputFileRequest {
         Self {
             url,
             crypto_info: crypto.into(),
+            crypto_key_name: None,
+        }
+    }
+
+    // The file is encrypted with the key registered under this name, which
+    // replaces the crypto info of the request.
+    pub fn crypto_key_name(self, name: impl Into<String>) -> Self {
+        Self {
+            crypto_key_name: Some(name.into()),
+            ..self
         }
     }
 }
@@ -105,14 +195,32 @@ impl RegisterOutputFileResponse {
 #[derive(Debug)]
 pub struct RegisterFusionOutputRequest {
     pub owner_list: OwnerList,
+    // X25519 public keys of owners, to which the crypto info of the output is
+    // sealed separately
+    pub recipient_public_keys: HashMap<String, Vec<u8>>,
+    pub temporary: bool,
 }
 
 impl RegisterFusionOutputRequest {
     pub fn new(owner_list: impl Into<OwnerList>) -> Self {
         Self {
             owner_list: owner_list.into(),
+            recipient_public_keys: HashMap::new(),
+            temporary: false,
         }
     }
+
+    // The output, the input registered from it and the fusion file are
+    // deleted once a task consuming the output finishes successfully.
+    pub fn temporary(self, temporary: bool) -> Self {
+        Self { temporary, ..self }
+    }
+
+    pub fn recipient_public_key(mut self, owner: impl ToString, public_key: Vec<u8>) -> Self {
+        self.recipient_public_keys
+            .insert(owner.to_string(), public_key);
+        self
+    }
 }
 
 #[into_request(TeaclaveFrontendResponse::RegisterFusionOutput)]
@@ -173,24 +281,85 @@ impl GetInputFileRequest {
 pub struct GetInputFileResponse {
     pub owner: OwnerList,
     pub cmac: FileAuthTag,
+    // JSON of the crypto info sealed to the recipient public key of the file
+    pub sealed_crypto_info: Option<Vec<u8>>,
+    pub metadata: InputFileMetadata,
 }
 
 impl GetInputFileResponse {
     pub fn new(owner: OwnerList, cmac: FileAuthTag) -> Self {
-        Self { owner, cmac }
+        Self {
+            owner,
+            cmac,
+            sealed_crypto_info: None,
+            metadata: InputFileMetadata::default(),
+        }
+    }
+
+    pub fn metadata(self, metadata: InputFileMetadata) -> Self {
+        Self { metadata, ..self }
+    }
+
+    pub fn sealed_crypto_info(self, sealed_crypto_info: Vec<u8>) -> Self {
+        Self {
+            sealed_crypto_info: Some(sealed_crypto_info),
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::DeleteInputFile)]
+#[into_request(TeaclaveManagementRequest::DeleteInputFile)]
+#[derive(Debug)]
+pub struct DeleteInputFileRequest {
+    pub data_id: ExternalID,
+    pub delete_remote: bool,
+}
+
+impl DeleteInputFileRequest {
+    pub fn new(data_id: ExternalID) -> Self {
+        Self {
+            data_id,
+            delete_remote: false,
+        }
+    }
+
+    pub fn delete_remote(self, delete_remote: bool) -> Self {
+        Self {
+            delete_remote,
+            ..self
+        }
     }
 }
 
+#[into_request(TeaclaveFrontendResponse::DeleteInputFile)]
+#[into_request(TeaclaveManagementResponse::DeleteInputFile)]
+#[derive(Debug)]
+pub struct DeleteInputFileResponse;
+
 #[into_request(TeaclaveFrontendRequest::GetOutputFile)]
 #[into_request(TeaclaveManagementRequest::GetOutputFile)]
 #[derive(Debug)]
 pub struct GetOutputFileRequest {
     pub data_id: ExternalID,
+    pub access_token: Option<String>,
 }
 
 impl GetOutputFileRequest {
     pub fn new(data_id: ExternalID) -> Self {
-        Self { data_id }
+        Self {
+            data_id,
+            access_token: None,
+        }
+    }
+
+    // Users who are not owners of the output get it with a token created by
+    // one of the owners.
+    pub fn access_token(self, token: impl Into<String>) -> Self {
+        Self {
+            access_token: Some(token.into()),
+            ..self
+        }
     }
 }
 
@@ -199,12 +368,38 @@ impl GetOutputFileRequest {
 #[derive(Debug)]
 pub struct GetOutputFileResponse {
     pub owner: OwnerList,
+    // Number of owners, which is kept when co-owners are redacted
+    pub owner_count: u32,
     pub cmac: Option<FileAuthTag>,
+    // JSON of the crypto info sealed to the public key of the requesting owner
+    pub sealed_crypto_info: Option<Vec<u8>>,
 }
 
 impl GetOutputFileResponse {
     pub fn new(owner: OwnerList, cmac: Option<FileAuthTag>) -> Self {
-        Self { owner, cmac }
+        Self {
+            owner_count: owner.len() as u32,
+            owner,
+            cmac,
+            sealed_crypto_info: None,
+        }
+    }
+
+    // Only the caller is left in the owner list if the caller is an owner.
+    pub fn redact_owners(self, user_id: &UserID) -> Self {
+        let owner = if self.owner.contains(user_id) {
+            OwnerList::new(vec![user_id.clone()])
+        } else {
+            OwnerList::default()
+        };
+        Self { owner, ..self }
+    }
+
+    pub fn sealed_crypto_info(self, sealed_crypto_info: Vec<u8>) -> Self {
+        Self {
+            sealed_crypto_info: Some(sealed_crypto_info),
+            ..self
+        }
     }
 }
 
@@ -220,6 +415,9 @@ pub struct RegisterFunctionRequest {
     pub arguments: Vec<String>,
     pub inputs: Vec<FunctionInput>,
     pub outputs: Vec<FunctionOutput>,
+    pub return_schema: Option<ReturnValueSchema>,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
 }
 
 impl RegisterFunctionRequest {
@@ -277,11 +475,36 @@ impl RegisterFunctionRequest {
     pub fn outputs(self, outputs: Vec<FunctionOutput>) -> Self {
         Self { outputs, ..self }
     }
+
+    pub fn return_schema(self, return_schema: ReturnValueSchema) -> Self {
+        Self {
+            return_schema: Some(return_schema),
+            ..self
+        }
+    }
+
+    pub fn category(self, category: impl ToString) -> Self {
+        Self {
+            category: Some(category.to_string()),
+            ..self
+        }
+    }
+
+    pub fn tags<T: IntoIterator>(self, tags: T) -> Self
+    where
+        <T as IntoIterator>::Item: ToString,
+    {
+        Self {
+            tags: tags.into_iter().map(|x| x.to_string()).collect(),
+            ..self
+        }
+    }
 }
 
 // We explicitly construct Function here in case of missing any field
 impl From<RegisterFunctionRequest> for Function {
     fn from(request: RegisterFunctionRequest) -> Self {
+        let payload_hash = Function::hash_payload(&request.payload);
         Function {
             id: Uuid::default(),
             owner: UserID::default(),
@@ -293,6 +516,16 @@ impl From<RegisterFunctionRequest> for Function {
             arguments: request.arguments,
             inputs: request.inputs,
             outputs: request.outputs,
+            deprecated: false,
+            replacement: None,
+            return_schema: request.return_schema,
+            payload_hash,
+            category: request.category,
+            tags: request.tags,
+            deleted_at: None,
+            verified: false,
+            rate_limit: None,
+            payload_iv: Vec::new(),
         }
     }
 }
@@ -301,11 +534,23 @@ impl From<RegisterFunctionRequest> for Function {
 #[derive(Debug)]
 pub struct RegisterFunctionResponse {
     pub function_id: ExternalID,
+    // Hex encoded SHA-256 of the stored payload
+    pub payload_hash: String,
 }
 
 impl RegisterFunctionResponse {
     pub fn new(function_id: ExternalID) -> Self {
-        Self { function_id }
+        Self {
+            function_id,
+            payload_hash: String::new(),
+        }
+    }
+
+    pub fn payload_hash(self, payload_hash: impl Into<String>) -> Self {
+        Self {
+            payload_hash: payload_hash.into(),
+            ..self
+        }
     }
 }
 
@@ -334,17 +579,91 @@ pub struct GetFunctionResponse {
     pub arguments: Vec<String>,
     pub inputs: Vec<FunctionInput>,
     pub outputs: Vec<FunctionOutput>,
+    pub deprecated: bool,
+    pub replacement: Option<ExternalID>,
+    pub return_schema: Option<ReturnValueSchema>,
+    pub payload_hash: String,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    // Registered from a bundle signed by a trusted publisher
+    pub verified: bool,
+    // Served from the cache of the management service while the storage
+    // service is unavailable, so it may be stale
+    pub storage_degraded: bool,
+}
+
+#[into_request(TeaclaveManagementRequest::DeprecateFunction)]
+#[into_request(TeaclaveFrontendRequest::DeprecateFunction)]
+#[derive(Debug)]
+pub struct DeprecateFunctionRequest {
+    pub function_id: ExternalID,
+    pub deprecated: bool,
+    pub replacement: Option<ExternalID>,
+}
+
+impl DeprecateFunctionRequest {
+    pub fn new(function_id: ExternalID, deprecated: bool) -> Self {
+        Self {
+            function_id,
+            deprecated,
+            replacement: None,
+        }
+    }
+
+    pub fn replacement(self, replacement: ExternalID) -> Self {
+        Self {
+            replacement: Some(replacement),
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::DeprecateFunction)]
+#[derive(Debug)]
+pub struct DeprecateFunctionResponse;
+
+#[into_request(TeaclaveManagementRequest::SetFunctionRateLimit)]
+#[into_request(TeaclaveFrontendRequest::SetFunctionRateLimit)]
+#[derive(Debug)]
+pub struct SetFunctionRateLimitRequest {
+    pub function_id: ExternalID,
+    pub rate_limit: Option<FunctionRateLimit>,
+}
+
+impl SetFunctionRateLimitRequest {
+    // Removes the limit unless one is set with rate_limit.
+    pub fn new(function_id: ExternalID) -> Self {
+        Self {
+            function_id,
+            rate_limit: None,
+        }
+    }
+
+    pub fn rate_limit(self, max_invocations: u32, window_seconds: u64) -> Self {
+        Self {
+            rate_limit: Some(FunctionRateLimit::new(max_invocations, window_seconds)),
+            ..self
+        }
+    }
 }
 
+#[into_request(TeaclaveManagementResponse::SetFunctionRateLimit)]
+#[derive(Debug)]
+pub struct SetFunctionRateLimitResponse;
+
 #[into_request(TeaclaveManagementRequest::CreateTask)]
 #[into_request(TeaclaveFrontendRequest::CreateTask)]
 #[derive(Default)]
 pub struct CreateTaskRequest {
     pub function_id: ExternalID,
     pub function_arguments: FunctionArguments,
-    pub executor: Executor,
+    pub executor: Option<Executor>,
     pub inputs_ownership: TaskFileOwners,
     pub outputs_ownership: TaskFileOwners,
+    pub approval_deadline_seconds: Option<u64>,
+    pub labels: HashMap<String, String>,
+    pub max_memory_mb: Option<u64>,
+    pub max_runtime_seconds: Option<u64>,
 }
 
 impl CreateTaskRequest {
@@ -366,9 +685,16 @@ impl CreateTaskRequest {
         }
     }
 
+    pub fn binary_function_argument(mut self, name: impl ToString, value: &[u8]) -> Self {
+        self.function_arguments
+            .inner_mut()
+            .insert(name.to_string(), ArgumentValue::binary(value));
+        self
+    }
+
     pub fn executor(self, executor: impl Into<Executor>) -> Self {
         Self {
-            executor: executor.into(),
+            executor: Some(executor.into()),
             ..self
         }
     }
@@ -386,17 +712,70 @@ impl CreateTaskRequest {
             ..self
         }
     }
+
+    pub fn approval_deadline_seconds(self, seconds: u64) -> Self {
+        Self {
+            approval_deadline_seconds: Some(seconds),
+            ..self
+        }
+    }
+
+    pub fn labels(self, labels: HashMap<String, String>) -> Self {
+        Self { labels, ..self }
+    }
+
+    pub fn max_memory_mb(self, max_memory_mb: u64) -> Self {
+        Self {
+            max_memory_mb: Some(max_memory_mb),
+            ..self
+        }
+    }
+
+    pub fn max_runtime_seconds(self, max_runtime_seconds: u64) -> Self {
+        Self {
+            max_runtime_seconds: Some(max_runtime_seconds),
+            ..self
+        }
+    }
 }
 
 #[into_request(TeaclaveManagementResponse::CreateTask)]
 #[derive(Debug)]
 pub struct CreateTaskResponse {
     pub task_id: ExternalID,
+    pub function_deprecated: bool,
+    // The task is queued to be stored once the storage service is available
+    pub storage_degraded: bool,
+    // Soft limits crossed by the task, which is created nevertheless
+    pub warnings: Vec<String>,
 }
 
 impl CreateTaskResponse {
     pub fn new(task_id: ExternalID) -> Self {
-        Self { task_id }
+        Self {
+            task_id,
+            function_deprecated: false,
+            storage_degraded: false,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn function_deprecated(self, function_deprecated: bool) -> Self {
+        Self {
+            function_deprecated,
+            ..self
+        }
+    }
+
+    pub fn storage_degraded(self, storage_degraded: bool) -> Self {
+        Self {
+            storage_degraded,
+            ..self
+        }
+    }
+
+    pub fn warnings(self, warnings: Vec<String>) -> Self {
+        Self { warnings, ..self }
     }
 }
 
@@ -425,10 +804,17 @@ pub struct GetTaskResponse {
     pub outputs_ownership: TaskFileOwners,
     pub participants: UserList,
     pub approved_users: UserList,
+    // participants who have not approved the task yet
+    pub pending_approvals: UserList,
     pub assigned_inputs: HashMap<String, ExternalID>,
     pub assigned_outputs: HashMap<String, ExternalID>,
+    // the task is only run on samples of these inputs
+    pub input_samples: HashMap<String, InputSample>,
     pub status: TaskStatus,
     pub result: TaskResult,
+    // Served from the cache of the management service while the storage
+    // service is unavailable, so it may be stale
+    pub storage_degraded: bool,
 }
 
 #[into_request(TeaclaveManagementRequest::AssignData)]
@@ -438,6 +824,7 @@ pub struct AssignDataRequest {
     pub task_id: ExternalID,
     pub inputs: HashMap<String, ExternalID>,
     pub outputs: HashMap<String, ExternalID>,
+    pub input_samples: HashMap<String, InputSample>,
 }
 
 impl AssignDataRequest {
@@ -450,12 +837,37 @@ impl AssignDataRequest {
             task_id,
             inputs,
             outputs,
+            input_samples: HashMap::new(),
         }
     }
+
+    // The task is only run on a sample of the input assigned in this request.
+    pub fn input_sample(mut self, data_name: impl Into<String>, sample: InputSample) -> Self {
+        self.input_samples.insert(data_name.into(), sample);
+        self
+    }
 }
 
 #[derive(Debug)]
-pub struct AssignDataResponse;
+pub struct AssignDataResponse {
+    pub inputs: HashMap<String, ExternalID>,
+    pub outputs: HashMap<String, ExternalID>,
+    pub ready: bool,
+}
+
+impl AssignDataResponse {
+    pub fn new(
+        inputs: HashMap<String, ExternalID>,
+        outputs: HashMap<String, ExternalID>,
+        ready: bool,
+    ) -> Self {
+        Self {
+            inputs,
+            outputs,
+            ready,
+        }
+    }
+}
 
 #[into_request(TeaclaveManagementRequest::ApproveTask)]
 #[into_request(TeaclaveFrontendRequest::ApproveTask)]
@@ -471,59 +883,1369 @@ impl ApproveTaskRequest {
 }
 
 #[derive(Debug)]
-pub struct ApproveTaskResponse;
+pub struct ApproveTaskResponse {
+    pub already_approved: bool,
+}
+
+impl ApproveTaskResponse {
+    pub fn new(already_approved: bool) -> Self {
+        Self { already_approved }
+    }
+}
 
 #[into_request(TeaclaveManagementRequest::InvokeTask)]
 #[into_request(TeaclaveFrontendRequest::InvokeTask)]
 #[derive(Debug)]
 pub struct InvokeTaskRequest {
     pub task_id: ExternalID,
+    pub skip_if_inputs_unchanged: bool,
 }
 
 impl InvokeTaskRequest {
     pub fn new(task_id: ExternalID) -> Self {
-        Self { task_id }
+        Self {
+            task_id,
+            skip_if_inputs_unchanged: false,
+        }
+    }
+
+    // A successfully finished task is not run again if its inputs are
+    // unchanged since the last run.
+    pub fn skip_if_inputs_unchanged(self, skip: bool) -> Self {
+        Self {
+            skip_if_inputs_unchanged: skip,
+            ..self
+        }
     }
 }
 
+// skipped is set if the task is not staged because its inputs are unchanged.
 #[derive(Debug)]
-pub struct InvokeTaskResponse;
+pub struct InvokeTaskResponse {
+    pub skipped: bool,
+}
 
-impl std::convert::TryFrom<proto::RegisterInputFileRequest> for RegisterInputFileRequest {
-    type Error = Error;
+impl InvokeTaskResponse {
+    pub fn new(skipped: bool) -> Self {
+        Self { skipped }
+    }
+}
 
-    fn try_from(proto: proto::RegisterInputFileRequest) -> Result<Self> {
-        let url = Url::parse(&proto.url)?;
-        let cmac = FileAuthTag::from_hex(proto.cmac)?;
-        let crypto_info = proto
-            .crypto_info
-            .ok_or_else(|| anyhow!("missing crypto_info"))?
-            .try_into()?;
+#[into_request(TeaclaveManagementRequest::AdminGetTask)]
+#[into_request(TeaclaveFrontendRequest::AdminGetTask)]
+#[derive(Debug)]
+pub struct AdminGetTaskRequest {
+    pub task_id: ExternalID,
+}
 
-        Ok(RegisterInputFileRequest {
-            url,
-            cmac,
-            crypto_info,
-        })
+impl AdminGetTaskRequest {
+    pub fn new(task_id: ExternalID) -> Self {
+        Self { task_id }
     }
 }
 
-impl From<RegisterInputFileRequest> for proto::RegisterInputFileRequest {
-    fn from(request: RegisterInputFileRequest) -> Self {
-        Self {
-            url: request.url.into_string(),
-            cmac: request.cmac.to_hex(),
-            crypto_info: Some(request.crypto_info.into()),
-        }
+#[into_request(TeaclaveManagementResponse::AdminGetTask)]
+#[derive(Debug)]
+pub struct AdminGetTaskResponse {
+    pub task: GetTaskResponse,
+}
+
+impl AdminGetTaskResponse {
+    pub fn new(task: GetTaskResponse) -> Self {
+        Self { task }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterInputFileResponse> for RegisterInputFileResponse {
-    type Error = Error;
+#[into_request(TeaclaveManagementRequest::LinkTasks)]
+#[into_request(TeaclaveFrontendRequest::LinkTasks)]
+#[derive(Debug)]
+pub struct LinkTasksRequest {
+    pub producer_task_id: ExternalID,
+    pub output_name: String,
+    pub consumer_task_id: ExternalID,
+    pub input_name: String,
+}
 
-    fn try_from(proto: proto::RegisterInputFileResponse) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        Ok(Self { data_id })
+impl LinkTasksRequest {
+    pub fn new(
+        producer_task_id: ExternalID,
+        output_name: impl ToString,
+        consumer_task_id: ExternalID,
+        input_name: impl ToString,
+    ) -> Self {
+        Self {
+            producer_task_id,
+            output_name: output_name.to_string(),
+            consumer_task_id,
+            input_name: input_name.to_string(),
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::LinkTasks)]
+#[derive(Debug)]
+pub struct LinkTasksResponse;
+
+#[into_request(TeaclaveManagementRequest::RunSelfTest)]
+#[into_request(TeaclaveFrontendRequest::RunSelfTest)]
+#[derive(Debug, Default)]
+pub struct RunSelfTestRequest;
+
+impl RunSelfTestRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Debug)]
+pub struct SelfTestStage {
+    pub name: String,
+    pub success: bool,
+    pub message: String,
+}
+
+impl SelfTestStage {
+    pub fn new(name: impl ToString, success: bool, message: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            success,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::RunSelfTest)]
+#[derive(Debug)]
+pub struct RunSelfTestResponse {
+    pub stages: Vec<SelfTestStage>,
+}
+
+impl RunSelfTestResponse {
+    pub fn new(stages: Vec<SelfTestStage>) -> Self {
+        Self { stages }
+    }
+
+    pub fn success(&self) -> bool {
+        self.stages.iter().all(|stage| stage.success)
+    }
+}
+
+#[derive(Debug)]
+pub struct FileEntry {
+    pub data_id: ExternalID,
+    pub created_at: u64,
+    pub consumed: bool,
+}
+
+impl FileEntry {
+    pub fn new(data_id: ExternalID, created_at: u64, consumed: bool) -> Self {
+        Self {
+            data_id,
+            created_at,
+            consumed,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::ListInputFiles)]
+#[into_request(TeaclaveFrontendRequest::ListInputFiles)]
+#[derive(Debug, Default)]
+pub struct ListInputFilesRequest {
+    pub cursor: Option<String>,
+    pub limit: usize,
+}
+
+impl ListInputFilesRequest {
+    pub fn new(cursor: Option<String>, limit: usize) -> Self {
+        Self { cursor, limit }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::ListInputFiles)]
+#[derive(Debug)]
+pub struct ListInputFilesResponse {
+    pub files: Vec<FileEntry>,
+    pub next_cursor: Option<String>,
+}
+
+impl ListInputFilesResponse {
+    pub fn new(files: Vec<FileEntry>, next_cursor: Option<String>) -> Self {
+        Self { files, next_cursor }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::ListOutputFiles)]
+#[into_request(TeaclaveFrontendRequest::ListOutputFiles)]
+#[derive(Debug, Default)]
+pub struct ListOutputFilesRequest {
+    pub cursor: Option<String>,
+    pub limit: usize,
+}
+
+impl ListOutputFilesRequest {
+    pub fn new(cursor: Option<String>, limit: usize) -> Self {
+        Self { cursor, limit }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::ListOutputFiles)]
+#[derive(Debug)]
+pub struct ListOutputFilesResponse {
+    pub files: Vec<FileEntry>,
+    pub next_cursor: Option<String>,
+}
+
+impl ListOutputFilesResponse {
+    pub fn new(files: Vec<FileEntry>, next_cursor: Option<String>) -> Self {
+        Self { files, next_cursor }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::RegisterTaskTemplate)]
+#[into_request(TeaclaveFrontendRequest::RegisterTaskTemplate)]
+#[derive(Default)]
+pub struct RegisterTaskTemplateRequest {
+    pub name: String,
+    pub function_id: ExternalID,
+    pub function_arguments: FunctionArguments,
+    pub executor: Executor,
+    pub public: bool,
+    pub inputs_ownership: TaskFileOwners,
+    pub outputs_ownership: TaskFileOwners,
+}
+
+impl RegisterTaskTemplateRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name(self, name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            ..self
+        }
+    }
+
+    pub fn function_id(self, function_id: ExternalID) -> Self {
+        Self {
+            function_id,
+            ..self
+        }
+    }
+
+    pub fn function_arguments(self, function_arguments: impl Into<FunctionArguments>) -> Self {
+        Self {
+            function_arguments: function_arguments.into(),
+            ..self
+        }
+    }
+
+    pub fn executor(self, executor: impl Into<Executor>) -> Self {
+        Self {
+            executor: executor.into(),
+            ..self
+        }
+    }
+
+    pub fn public(self, public: bool) -> Self {
+        Self { public, ..self }
+    }
+
+    pub fn inputs_ownership(self, map: impl Into<TaskFileOwners>) -> Self {
+        Self {
+            inputs_ownership: map.into(),
+            ..self
+        }
+    }
+
+    pub fn outputs_ownership(self, map: impl Into<TaskFileOwners>) -> Self {
+        Self {
+            outputs_ownership: map.into(),
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::RegisterTaskTemplate)]
+#[derive(Debug)]
+pub struct RegisterTaskTemplateResponse {
+    pub template_id: ExternalID,
+}
+
+impl RegisterTaskTemplateResponse {
+    pub fn new(template_id: ExternalID) -> Self {
+        Self { template_id }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::InstantiateTemplate)]
+#[into_request(TeaclaveFrontendRequest::InstantiateTemplate)]
+#[derive(Default)]
+pub struct InstantiateTemplateRequest {
+    pub template_id: ExternalID,
+    pub function_arguments: FunctionArguments,
+    pub inputs_ownership: TaskFileOwners,
+    pub outputs_ownership: TaskFileOwners,
+    pub approval_deadline_seconds: Option<u64>,
+}
+
+impl InstantiateTemplateRequest {
+    pub fn new(template_id: ExternalID) -> Self {
+        Self {
+            template_id,
+            ..Self::default()
+        }
+    }
+
+    pub fn function_arguments(self, function_arguments: impl Into<FunctionArguments>) -> Self {
+        Self {
+            function_arguments: function_arguments.into(),
+            ..self
+        }
+    }
+
+    pub fn inputs_ownership(self, map: impl Into<TaskFileOwners>) -> Self {
+        Self {
+            inputs_ownership: map.into(),
+            ..self
+        }
+    }
+
+    pub fn outputs_ownership(self, map: impl Into<TaskFileOwners>) -> Self {
+        Self {
+            outputs_ownership: map.into(),
+            ..self
+        }
+    }
+
+    pub fn approval_deadline_seconds(self, seconds: u64) -> Self {
+        Self {
+            approval_deadline_seconds: Some(seconds),
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::InstantiateTemplate)]
+#[derive(Debug)]
+pub struct InstantiateTemplateResponse {
+    pub task_id: ExternalID,
+    pub function_deprecated: bool,
+}
+
+impl InstantiateTemplateResponse {
+    pub fn new(task_id: ExternalID, function_deprecated: bool) -> Self {
+        Self {
+            task_id,
+            function_deprecated,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::GetTasks)]
+#[into_request(TeaclaveFrontendRequest::GetTasks)]
+#[derive(Debug)]
+pub struct GetTasksRequest {
+    pub task_ids: Vec<ExternalID>,
+}
+
+impl GetTasksRequest {
+    pub fn new(task_ids: Vec<ExternalID>) -> Self {
+        Self { task_ids }
+    }
+}
+
+// A task which does not exist or is not accessible to the user has no
+// details.
+#[derive(Debug)]
+pub struct GetTasksEntry {
+    pub task_id: ExternalID,
+    pub task: Option<GetTaskResponse>,
+}
+
+impl GetTasksEntry {
+    pub fn new(task_id: ExternalID, task: Option<GetTaskResponse>) -> Self {
+        Self { task_id, task }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::GetTasks)]
+#[derive(Debug)]
+pub struct GetTasksResponse {
+    pub tasks: Vec<GetTasksEntry>,
+}
+
+impl GetTasksResponse {
+    pub fn new(tasks: Vec<GetTasksEntry>) -> Self {
+        Self { tasks }
+    }
+}
+
+#[into_request(TeaclaveFrontendRequest::GetCapabilities)]
+#[derive(Debug, Default)]
+pub struct GetCapabilitiesRequest;
+
+impl GetCapabilitiesRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Debug)]
+pub struct GetCapabilitiesResponse {
+    pub crypto_schemas: Vec<String>,
+    pub executors: Vec<Executor>,
+    pub executor_types: Vec<ExecutorType>,
+    pub url_schemes: Vec<String>,
+    pub version: String,
+}
+
+#[into_request(TeaclaveManagementRequest::CancelTask)]
+#[into_request(TeaclaveFrontendRequest::CancelTask)]
+#[derive(Debug)]
+pub struct CancelTaskRequest {
+    pub task_id: ExternalID,
+}
+
+impl CancelTaskRequest {
+    pub fn new(task_id: ExternalID) -> Self {
+        Self { task_id }
+    }
+}
+
+// A staged or running task is not canceled until the executor acknowledges
+// the cancellation, which is reported as canceled == false.
+#[into_request(TeaclaveManagementResponse::CancelTask)]
+#[derive(Debug)]
+pub struct CancelTaskResponse {
+    pub canceled: bool,
+}
+
+impl CancelTaskResponse {
+    pub fn new(canceled: bool) -> Self {
+        Self { canceled }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::ListTasks)]
+#[into_request(TeaclaveFrontendRequest::ListTasks)]
+#[derive(Debug, Default)]
+pub struct ListTasksRequest {
+    pub cursor: Option<String>,
+    pub limit: usize,
+    pub labels: HashMap<String, String>,
+}
+
+impl ListTasksRequest {
+    pub fn new(cursor: Option<String>, limit: usize) -> Self {
+        Self {
+            cursor,
+            limit,
+            ..Default::default()
+        }
+    }
+
+    pub fn labels(self, labels: HashMap<String, String>) -> Self {
+        Self { labels, ..self }
+    }
+}
+
+#[derive(Debug)]
+pub struct TaskEntry {
+    pub task_id: ExternalID,
+    pub created_at: u64,
+    pub labels: HashMap<String, String>,
+}
+
+impl TaskEntry {
+    pub fn new(task_id: ExternalID, created_at: u64, labels: HashMap<String, String>) -> Self {
+        Self {
+            task_id,
+            created_at,
+            labels,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::ListTasks)]
+#[derive(Debug)]
+pub struct ListTasksResponse {
+    pub tasks: Vec<TaskEntry>,
+    pub next_cursor: Option<String>,
+}
+
+impl ListTasksResponse {
+    pub fn new(tasks: Vec<TaskEntry>, next_cursor: Option<String>) -> Self {
+        Self { tasks, next_cursor }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::ExportTask)]
+#[into_request(TeaclaveFrontendRequest::ExportTask)]
+#[derive(Debug)]
+pub struct ExportTaskRequest {
+    pub task_id: ExternalID,
+    pub include_arguments: bool,
+}
+
+impl ExportTaskRequest {
+    pub fn new(task_id: ExternalID) -> Self {
+        Self {
+            task_id,
+            include_arguments: false,
+        }
+    }
+
+    // Only the creator of the task may export its arguments.
+    pub fn include_arguments(self, include_arguments: bool) -> Self {
+        Self {
+            include_arguments,
+            ..self
+        }
+    }
+}
+
+// The task is serialized as JSON of teaclave_types::TaskExport.
+#[into_request(TeaclaveManagementResponse::ExportTask)]
+#[derive(Debug)]
+pub struct ExportTaskResponse {
+    pub task: String,
+}
+
+impl ExportTaskResponse {
+    pub fn new(task: impl Into<String>) -> Self {
+        Self { task: task.into() }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::ImportTask)]
+#[into_request(TeaclaveFrontendRequest::ImportTask)]
+#[derive(Debug)]
+pub struct ImportTaskRequest {
+    pub task: String,
+    pub function_arguments: FunctionArguments,
+}
+
+impl ImportTaskRequest {
+    pub fn new(task: impl Into<String>) -> Self {
+        Self {
+            task: task.into(),
+            function_arguments: FunctionArguments::default(),
+        }
+    }
+
+    // Arguments provided here replace those in the exported task, which are
+    // absent unless they were explicitly exported.
+    pub fn function_arguments(self, function_arguments: impl Into<FunctionArguments>) -> Self {
+        Self {
+            function_arguments: function_arguments.into(),
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::ImportTask)]
+#[derive(Debug)]
+pub struct ImportTaskResponse {
+    pub task_id: ExternalID,
+}
+
+impl ImportTaskResponse {
+    pub fn new(task_id: ExternalID) -> Self {
+        Self { task_id }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::GenerateOutputPresignedUrl)]
+#[into_request(TeaclaveFrontendRequest::GenerateOutputPresignedUrl)]
+#[derive(Debug)]
+pub struct GenerateOutputPresignedUrlRequest {
+    pub data_id: ExternalID,
+}
+
+impl GenerateOutputPresignedUrlRequest {
+    pub fn new(data_id: ExternalID) -> Self {
+        Self { data_id }
+    }
+}
+
+// The url grants GET access to the output until the Unix timestamp in
+// expires_at.
+#[into_request(TeaclaveManagementResponse::GenerateOutputPresignedUrl)]
+#[derive(Debug)]
+pub struct GenerateOutputPresignedUrlResponse {
+    pub url: Url,
+    pub expires_at: u64,
+}
+
+impl GenerateOutputPresignedUrlResponse {
+    pub fn new(url: Url, expires_at: u64) -> Self {
+        Self { url, expires_at }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::EstimateTask)]
+#[into_request(TeaclaveFrontendRequest::EstimateTask)]
+#[derive(Debug)]
+pub struct EstimateTaskRequest {
+    pub task_id: ExternalID,
+}
+
+impl EstimateTaskRequest {
+    pub fn new(task_id: ExternalID) -> Self {
+        Self { task_id }
+    }
+}
+
+// A rough estimate of running a task: the bytes of inputs to be transferred
+// when the task is staged, the number of files whose sizes are unknown, e.g.,
+// outputs, and the number of tasks queued ahead of it.
+#[into_request(TeaclaveManagementResponse::EstimateTask)]
+#[derive(Debug, Default)]
+pub struct EstimateTaskResponse {
+    pub input_bytes: u64,
+    pub unsized_files: u32,
+    pub queued_tasks: u64,
+}
+
+#[into_request(TeaclaveManagementRequest::ListFunctions)]
+#[into_request(TeaclaveFrontendRequest::ListFunctions)]
+#[derive(Debug, Default)]
+pub struct ListFunctionsRequest {
+    pub cursor: Option<String>,
+    pub limit: usize,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl ListFunctionsRequest {
+    pub fn new(cursor: Option<String>, limit: usize) -> Self {
+        Self {
+            cursor,
+            limit,
+            ..Default::default()
+        }
+    }
+
+    pub fn category(self, category: impl ToString) -> Self {
+        Self {
+            category: Some(category.to_string()),
+            ..self
+        }
+    }
+
+    pub fn tags<T: IntoIterator>(self, tags: T) -> Self
+    where
+        <T as IntoIterator>::Item: ToString,
+    {
+        Self {
+            tags: tags.into_iter().map(|x| x.to_string()).collect(),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FunctionEntry {
+    pub function_id: ExternalID,
+    pub name: String,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl FunctionEntry {
+    pub fn new(
+        function_id: ExternalID,
+        name: String,
+        category: Option<String>,
+        tags: Vec<String>,
+    ) -> Self {
+        Self {
+            function_id,
+            name,
+            category,
+            tags,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::ListFunctions)]
+#[derive(Debug)]
+pub struct ListFunctionsResponse {
+    pub functions: Vec<FunctionEntry>,
+    pub next_cursor: Option<String>,
+}
+
+impl ListFunctionsResponse {
+    pub fn new(functions: Vec<FunctionEntry>, next_cursor: Option<String>) -> Self {
+        Self {
+            functions,
+            next_cursor,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::RetryTask)]
+#[into_request(TeaclaveFrontendRequest::RetryTask)]
+#[derive(Debug)]
+pub struct RetryTaskRequest {
+    pub task_id: ExternalID,
+}
+
+impl RetryTaskRequest {
+    pub fn new(task_id: ExternalID) -> Self {
+        Self { task_id }
+    }
+}
+
+// The retried task is approved again and can be invoked as usual.
+#[into_request(TeaclaveManagementResponse::RetryTask)]
+#[derive(Debug)]
+pub struct RetryTaskResponse {
+    pub retries_left: u32,
+}
+
+impl RetryTaskResponse {
+    pub fn new(retries_left: u32) -> Self {
+        Self { retries_left }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::RegisterInputFromTaskLog)]
+#[into_request(TeaclaveFrontendRequest::RegisterInputFromTaskLog)]
+#[derive(Debug)]
+pub struct RegisterInputFromTaskLogRequest {
+    pub task_id: ExternalID,
+}
+
+impl RegisterInputFromTaskLogRequest {
+    pub fn new(task_id: ExternalID) -> Self {
+        Self { task_id }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::RegisterInputFromTaskLog)]
+#[derive(Debug)]
+pub struct RegisterInputFromTaskLogResponse {
+    pub data_id: ExternalID,
+}
+
+impl RegisterInputFromTaskLogResponse {
+    pub fn new(data_id: ExternalID) -> Self {
+        Self { data_id }
+    }
+}
+
+// Tasks of a graph are created and linked together, none of them is created if
+// any node or edge is invalid.
+#[into_request(TeaclaveManagementRequest::SubmitTaskGraph)]
+#[into_request(TeaclaveFrontendRequest::SubmitTaskGraph)]
+#[derive(Default)]
+pub struct SubmitTaskGraphRequest {
+    pub nodes: HashMap<String, CreateTaskRequest>,
+    pub edges: Vec<TaskGraphEdge>,
+}
+
+impl SubmitTaskGraphRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn node(mut self, name: impl ToString, task: CreateTaskRequest) -> Self {
+        self.nodes.insert(name.to_string(), task);
+        self
+    }
+
+    pub fn edge(mut self, edge: TaskGraphEdge) -> Self {
+        self.edges.push(edge);
+        self
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::SubmitTaskGraph)]
+#[derive(Debug)]
+pub struct SubmitTaskGraphResponse {
+    pub task_ids: HashMap<String, ExternalID>,
+}
+
+impl SubmitTaskGraphResponse {
+    pub fn new(task_ids: HashMap<String, ExternalID>) -> Self {
+        Self { task_ids }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::SubscribeEvents)]
+#[into_request(TeaclaveFrontendRequest::SubscribeEvents)]
+#[derive(Debug, Default)]
+pub struct SubscribeEventsRequest {
+    pub offset: u64,
+    pub limit: usize,
+}
+
+impl SubscribeEventsRequest {
+    pub fn new(offset: u64, limit: usize) -> Self {
+        Self { offset, limit }
+    }
+}
+
+#[derive(Debug)]
+pub struct TaskEventEntry {
+    pub task_id: ExternalID,
+    pub old_status: Option<TaskStatus>,
+    pub new_status: TaskStatus,
+    pub timestamp: u64,
+}
+
+impl From<TaskEvent> for TaskEventEntry {
+    fn from(event: TaskEvent) -> Self {
+        Self {
+            task_id: event.task_id,
+            old_status: event.old_status,
+            new_status: event.new_status,
+            timestamp: event.timestamp,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::SubscribeEvents)]
+#[derive(Debug)]
+pub struct SubscribeEventsResponse {
+    pub events: Vec<TaskEventEntry>,
+    pub next_offset: u64,
+}
+
+impl SubscribeEventsResponse {
+    pub fn new(events: Vec<TaskEventEntry>, next_offset: u64) -> Self {
+        Self {
+            events,
+            next_offset,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::SoftDeleteFunction)]
+#[into_request(TeaclaveFrontendRequest::SoftDeleteFunction)]
+#[derive(Debug)]
+pub struct SoftDeleteFunctionRequest {
+    pub function_id: ExternalID,
+}
+
+impl SoftDeleteFunctionRequest {
+    pub fn new(function_id: ExternalID) -> Self {
+        Self { function_id }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::SoftDeleteFunction)]
+#[derive(Debug)]
+pub struct SoftDeleteFunctionResponse {
+    pub restorable_until: u64,
+}
+
+impl SoftDeleteFunctionResponse {
+    pub fn new(restorable_until: u64) -> Self {
+        Self { restorable_until }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::RestoreFunction)]
+#[into_request(TeaclaveFrontendRequest::RestoreFunction)]
+#[derive(Debug)]
+pub struct RestoreFunctionRequest {
+    pub function_id: ExternalID,
+}
+
+impl RestoreFunctionRequest {
+    pub fn new(function_id: ExternalID) -> Self {
+        Self { function_id }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::RestoreFunction)]
+#[derive(Debug)]
+pub struct RestoreFunctionResponse;
+
+// A function bundle signed by a publisher, see FunctionBundle.
+#[into_request(TeaclaveManagementRequest::RegisterFunctionBundle)]
+#[into_request(TeaclaveFrontendRequest::RegisterFunctionBundle)]
+#[derive(Debug, Default)]
+pub struct RegisterFunctionBundleRequest {
+    pub payload: Vec<u8>,
+    pub metadata: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl RegisterFunctionBundleRequest {
+    pub fn new(
+        payload: impl Into<Vec<u8>>,
+        metadata: impl Into<Vec<u8>>,
+        signature: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self {
+            payload: payload.into(),
+            metadata: metadata.into(),
+            signature: signature.into(),
+        }
+    }
+}
+
+impl From<RegisterFunctionBundleRequest> for FunctionBundle {
+    fn from(request: RegisterFunctionBundleRequest) -> Self {
+        FunctionBundle::new(request.payload, request.metadata, request.signature)
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::RegisterFunctionBundle)]
+#[derive(Debug)]
+pub struct RegisterFunctionBundleResponse {
+    pub function_id: ExternalID,
+    // Hex encoded SHA-256 of the stored payload
+    pub payload_hash: String,
+}
+
+impl RegisterFunctionBundleResponse {
+    pub fn new(function_id: ExternalID, payload_hash: impl Into<String>) -> Self {
+        Self {
+            function_id,
+            payload_hash: payload_hash.into(),
+        }
+    }
+}
+
+// Stores a crypto key of the user under a name, which can be referenced
+// instead of the crypto info when registering files. A key registered again
+// under the same name replaces the old one.
+#[into_request(TeaclaveManagementRequest::RegisterCryptoKey)]
+#[into_request(TeaclaveFrontendRequest::RegisterCryptoKey)]
+#[derive(Debug)]
+pub struct RegisterCryptoKeyRequest {
+    pub name: String,
+    pub crypto_info: FileCrypto,
+}
+
+impl RegisterCryptoKeyRequest {
+    pub fn new(name: impl Into<String>, crypto: impl Into<FileCrypto>) -> Self {
+        Self {
+            name: name.into(),
+            crypto_info: crypto.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::RegisterCryptoKey)]
+#[derive(Debug)]
+pub struct RegisterCryptoKeyResponse;
+
+// Cancels all tasks created by the user which are not running or ended yet.
+// Staged tasks are only marked for cancellation, see CancelTaskResponse.
+#[into_request(TeaclaveManagementRequest::CancelAllTasks)]
+#[into_request(TeaclaveFrontendRequest::CancelAllTasks)]
+#[derive(Debug, Default)]
+pub struct CancelAllTasksRequest;
+
+impl CancelAllTasksRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::CancelAllTasks)]
+#[derive(Debug)]
+pub struct CancelAllTasksResponse {
+    // Tasks which are canceled or marked for cancellation
+    pub task_ids: Vec<ExternalID>,
+    // Tasks which cannot be read or canceled, and are left as is
+    pub failed_task_ids: Vec<ExternalID>,
+}
+
+impl CancelAllTasksResponse {
+    pub fn new(task_ids: Vec<ExternalID>, failed_task_ids: Vec<ExternalID>) -> Self {
+        Self {
+            task_ids,
+            failed_task_ids,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::CreateOutputAccessToken)]
+#[into_request(TeaclaveFrontendRequest::CreateOutputAccessToken)]
+#[derive(Debug)]
+pub struct CreateOutputAccessTokenRequest {
+    pub data_id: ExternalID,
+}
+
+impl CreateOutputAccessTokenRequest {
+    pub fn new(data_id: ExternalID) -> Self {
+        Self { data_id }
+    }
+}
+
+// The token lets any user get the output with get_output_file until the Unix
+// timestamp in expires_at.
+#[into_request(TeaclaveManagementResponse::CreateOutputAccessToken)]
+#[derive(Debug)]
+pub struct CreateOutputAccessTokenResponse {
+    pub token: String,
+    pub expires_at: u64,
+}
+
+impl CreateOutputAccessTokenResponse {
+    pub fn new(token: impl Into<String>, expires_at: u64) -> Self {
+        Self {
+            token: token.into(),
+            expires_at,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::GetMetrics)]
+#[into_request(TeaclaveFrontendRequest::GetMetrics)]
+#[derive(Debug, Default)]
+pub struct GetMetricsRequest;
+
+impl GetMetricsRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// Latency in microseconds of one kind of storage operations of the management
+// service since it started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageLatency {
+    pub operation: String,
+    pub count: u64,
+    pub min_micros: u64,
+    pub max_micros: u64,
+    pub avg_micros: u64,
+}
+
+#[into_request(TeaclaveManagementResponse::GetMetrics)]
+#[derive(Debug)]
+pub struct GetMetricsResponse {
+    pub storage_latencies: Vec<StorageLatency>,
+    // Whether the storage service is unavailable, and the number of writes
+    // queued until it is back
+    pub storage_degraded: bool,
+    pub pending_storage_writes: u64,
+}
+
+impl GetMetricsResponse {
+    pub fn new(storage_latencies: Vec<StorageLatency>) -> Self {
+        Self {
+            storage_latencies,
+            storage_degraded: false,
+            pending_storage_writes: 0,
+        }
+    }
+
+    pub fn storage_degraded(self, storage_degraded: bool, pending_storage_writes: u64) -> Self {
+        Self {
+            storage_degraded,
+            pending_storage_writes,
+            ..self
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::ExportCatalog)]
+#[into_request(TeaclaveFrontendRequest::ExportCatalog)]
+#[derive(Debug, Default)]
+pub struct ExportCatalogRequest;
+
+impl ExportCatalogRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// The catalog is serialized as JSON of teaclave_types::FunctionCatalog.
+#[into_request(TeaclaveManagementResponse::ExportCatalog)]
+#[derive(Debug)]
+pub struct ExportCatalogResponse {
+    pub catalog: String,
+}
+
+impl ExportCatalogResponse {
+    pub fn new(catalog: impl Into<String>) -> Self {
+        Self {
+            catalog: catalog.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::ImportCatalog)]
+#[into_request(TeaclaveFrontendRequest::ImportCatalog)]
+#[derive(Debug)]
+pub struct ImportCatalogRequest {
+    pub catalog: String,
+    pub payloads: HashMap<String, Vec<u8>>,
+}
+
+impl ImportCatalogRequest {
+    pub fn new(catalog: impl Into<String>) -> Self {
+        Self {
+            catalog: catalog.into(),
+            payloads: HashMap::new(),
+        }
+    }
+
+    // The payload of each function in the catalog, keyed by its exported id.
+    pub fn payload(mut self, function_id: &ExternalID, payload: impl Into<Vec<u8>>) -> Self {
+        self.payloads
+            .insert(function_id.to_string(), payload.into());
+        self
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::ImportCatalog)]
+#[derive(Debug)]
+pub struct ImportCatalogResponse {
+    pub function_ids: HashMap<String, ExternalID>,
+}
+
+impl ImportCatalogResponse {
+    pub fn new(function_ids: HashMap<String, ExternalID>) -> Self {
+        Self { function_ids }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::ExportAuditLog)]
+#[into_request(TeaclaveFrontendRequest::ExportAuditLog)]
+#[derive(Debug, Default)]
+pub struct ExportAuditLogRequest {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub offset: u64,
+    pub limit: usize,
+}
+
+impl ExportAuditLogRequest {
+    pub fn new(start_time: u64, end_time: u64) -> Self {
+        Self {
+            start_time,
+            end_time,
+            ..Default::default()
+        }
+    }
+
+    pub fn offset(self, offset: u64) -> Self {
+        Self { offset, ..self }
+    }
+
+    pub fn limit(self, limit: usize) -> Self {
+        Self { limit, ..self }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::ExportAuditLog)]
+#[derive(Debug)]
+pub struct ExportAuditLogResponse {
+    pub entries: Vec<String>,
+    pub next_offset: u64,
+    pub checksum: String,
+}
+
+impl ExportAuditLogResponse {
+    pub fn new(entries: Vec<String>, next_offset: u64, checksum: impl Into<String>) -> Self {
+        Self {
+            entries,
+            next_offset,
+            checksum: checksum.into(),
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::SetResultCallback)]
+#[into_request(TeaclaveFrontendRequest::SetResultCallback)]
+#[derive(Debug, Default)]
+pub struct SetResultCallbackRequest {
+    pub url: Option<Url>,
+    pub recipient_public_key: Vec<u8>,
+}
+
+impl SetResultCallbackRequest {
+    // Results are sealed to the X25519 public key of the endpoint, see
+    // teaclave_crypto::seal_to_public_key.
+    pub fn new(url: Url, recipient_public_key: Vec<u8>) -> Self {
+        Self {
+            url: Some(url),
+            recipient_public_key,
+        }
+    }
+
+    pub fn remove() -> Self {
+        Self::default()
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::SetResultCallback)]
+#[derive(Debug)]
+pub struct SetResultCallbackResponse {
+    pub secret: String,
+}
+
+impl SetResultCallbackResponse {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self {
+            secret: secret.into(),
+        }
+    }
+}
+
+// The task is run again with the same function and inputs, and the result is
+// compared with that of the task without replacing it.
+#[into_request(TeaclaveManagementRequest::ReproduceTask)]
+#[into_request(TeaclaveFrontendRequest::ReproduceTask)]
+#[derive(Debug)]
+pub struct ReproduceTaskRequest {
+    pub task_id: ExternalID,
+    pub output_urls: HashMap<String, Url>,
+}
+
+impl ReproduceTaskRequest {
+    pub fn new(task_id: ExternalID, output_urls: HashMap<String, Url>) -> Self {
+        Self {
+            task_id,
+            output_urls,
+        }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::ReproduceTask)]
+#[derive(Debug)]
+pub struct ReproduceTaskResponse {
+    pub reproduction_id: ExternalID,
+}
+
+impl ReproduceTaskResponse {
+    pub fn new(reproduction_id: ExternalID) -> Self {
+        Self { reproduction_id }
+    }
+}
+
+#[into_request(TeaclaveManagementRequest::GetTaskReproduction)]
+#[into_request(TeaclaveFrontendRequest::GetTaskReproduction)]
+#[derive(Debug)]
+pub struct GetTaskReproductionRequest {
+    pub reproduction_id: ExternalID,
+}
+
+impl GetTaskReproductionRequest {
+    pub fn new(reproduction_id: ExternalID) -> Self {
+        Self { reproduction_id }
+    }
+}
+
+#[into_request(TeaclaveManagementResponse::GetTaskReproduction)]
+#[derive(Debug)]
+pub struct GetTaskReproductionResponse {
+    pub task_id: ExternalID,
+    pub status: TaskStatus,
+    // None until the run finishes without failure
+    pub matches: Option<bool>,
+    pub failure: Option<String>,
+}
+
+impl std::convert::TryFrom<proto::RegisterInputFileRequest> for RegisterInputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInputFileRequest) -> Result<Self> {
+        let url = Url::parse(&proto.url)?;
+        let cmac = FileAuthTag::from_hex(proto.cmac)?;
+        let crypto_key_name = from_proto_crypto_key_name(proto.crypto_key_name);
+        let crypto_info = from_proto_file_crypto(proto.crypto_info, &crypto_key_name)?;
+        let inline_data = if proto.inline_data.is_empty() {
+            None
+        } else {
+            Some(proto.inline_data)
+        };
+        let expected_size = if proto.expected_size == 0 {
+            None
+        } else {
+            Some(proto.expected_size)
+        };
+        let recipient_public_key = if proto.recipient_public_key.is_empty() {
+            None
+        } else {
+            Some(proto.recipient_public_key)
+        };
+        let source = if proto.source.is_empty() {
+            None
+        } else {
+            Some(proto.source)
+        };
+
+        Ok(RegisterInputFileRequest {
+            url,
+            cmac,
+            crypto_info,
+            inline_data,
+            expected_size,
+            recipient_public_key,
+            source,
+            crypto_key_name,
+            lazy: proto.lazy,
+        })
+    }
+}
+
+fn from_proto_crypto_key_name(name: String) -> Option<String> {
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+// The crypto info is replaced by the service if a key is referenced by name.
+fn from_proto_file_crypto(
+    crypto_info: Option<proto::FileCryptoInfo>,
+    crypto_key_name: &Option<String>,
+) -> Result<FileCrypto> {
+    match crypto_info {
+        Some(crypto_info) => crypto_info.try_into(),
+        None if crypto_key_name.is_some() => Ok(FileCrypto::Raw),
+        None => Err(anyhow!("missing crypto_info")),
+    }
+}
+
+fn to_proto_file_crypto(
+    crypto_info: FileCrypto,
+    crypto_key_name: &Option<String>,
+) -> Option<proto::FileCryptoInfo> {
+    if crypto_key_name.is_some() {
+        None
+    } else {
+        Some(crypto_info.into())
+    }
+}
+
+impl From<RegisterInputFileRequest> for proto::RegisterInputFileRequest {
+    fn from(request: RegisterInputFileRequest) -> Self {
+        Self {
+            url: request.url.into_string(),
+            cmac: request.cmac.to_hex(),
+            crypto_info: to_proto_file_crypto(request.crypto_info, &request.crypto_key_name),
+            inline_data: request.inline_data.unwrap_or_default(),
+            expected_size: request.expected_size.unwrap_or_default(),
+            recipient_public_key: request.recipient_public_key.unwrap_or_default(),
+            source: request.source.unwrap_or_default(),
+            crypto_key_name: request.crypto_key_name.unwrap_or_default(),
+            lazy: request.lazy,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInputFileResponse> for RegisterInputFileResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInputFileResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self {
+            data_id,
+            warnings: proto.warnings,
+        })
     }
 }
 
@@ -531,690 +2253,2755 @@ impl From<RegisterInputFileResponse> for proto::RegisterInputFileResponse {
     fn from(request: RegisterInputFileResponse) -> Self {
         Self {
             data_id: request.data_id.to_string(),
+            warnings: request.warnings,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterOutputFileRequest> for RegisterOutputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterOutputFileRequest) -> Result<Self> {
+        let crypto_key_name = from_proto_crypto_key_name(proto.crypto_key_name);
+        let ret = Self {
+            url: Url::parse(&proto.url)?,
+            crypto_info: from_proto_file_crypto(proto.crypto_info, &crypto_key_name)?,
+            crypto_key_name,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterOutputFileRequest> for proto::RegisterOutputFileRequest {
+    fn from(request: RegisterOutputFileRequest) -> Self {
+        Self {
+            url: request.url.into_string(),
+            crypto_info: to_proto_file_crypto(request.crypto_info, &request.crypto_key_name),
+            crypto_key_name: request.crypto_key_name.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterOutputFileResponse> for RegisterOutputFileResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterOutputFileResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<RegisterOutputFileResponse> for proto::RegisterOutputFileResponse {
+    fn from(request: RegisterOutputFileResponse) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterFusionOutputRequest> for RegisterFusionOutputRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterFusionOutputRequest) -> Result<Self> {
+        let ret = Self {
+            owner_list: OwnerList::new(proto.owner_list),
+            recipient_public_keys: proto.recipient_public_keys,
+            temporary: proto.temporary,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterFusionOutputRequest> for proto::RegisterFusionOutputRequest {
+    fn from(request: RegisterFusionOutputRequest) -> Self {
+        Self {
+            owner_list: request.owner_list.into(),
+            recipient_public_keys: request.recipient_public_keys,
+            temporary: request.temporary,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterFusionOutputResponse> for RegisterFusionOutputResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterFusionOutputResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<RegisterFusionOutputResponse> for proto::RegisterFusionOutputResponse {
+    fn from(request: RegisterFusionOutputResponse) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInputFromOutputRequest>
+    for RegisterInputFromOutputRequest
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInputFromOutputRequest) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        let ret = Self { data_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterInputFromOutputRequest> for proto::RegisterInputFromOutputRequest {
+    fn from(request: RegisterInputFromOutputRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInputFromOutputResponse>
+    for RegisterInputFromOutputResponse
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInputFromOutputResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<RegisterInputFromOutputResponse> for proto::RegisterInputFromOutputResponse {
+    fn from(request: RegisterInputFromOutputResponse) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetInputFileRequest> for GetInputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetInputFileRequest) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        let ret = Self { data_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetInputFileRequest> for proto::GetInputFileRequest {
+    fn from(request: GetInputFileRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetInputFileResponse> for GetInputFileResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetInputFileResponse) -> Result<Self> {
+        let sealed_crypto_info = if proto.sealed_crypto_info.is_empty() {
+            None
+        } else {
+            Some(proto.sealed_crypto_info)
+        };
+        let metadata = InputFileMetadata {
+            size: match proto.size {
+                0 => None,
+                size => Some(size),
+            },
+            created_at: proto.created_at,
+            source: if proto.source.is_empty() {
+                None
+            } else {
+                Some(proto.source)
+            },
+        };
+        Ok(Self {
+            owner: OwnerList::new(proto.owner),
+            cmac: FileAuthTag::from_hex(proto.cmac)?,
+            sealed_crypto_info,
+            metadata,
+        })
+    }
+}
+
+impl From<GetInputFileResponse> for proto::GetInputFileResponse {
+    fn from(request: GetInputFileResponse) -> Self {
+        Self {
+            owner: request.owner.into(),
+            cmac: request.cmac.to_hex(),
+            sealed_crypto_info: request.sealed_crypto_info.unwrap_or_default(),
+            size: request.metadata.size.unwrap_or_default(),
+            created_at: request.metadata.created_at,
+            source: request.metadata.source.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::DeleteInputFileRequest> for DeleteInputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::DeleteInputFileRequest) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self {
+            data_id,
+            delete_remote: proto.delete_remote,
+        })
+    }
+}
+
+impl From<DeleteInputFileRequest> for proto::DeleteInputFileRequest {
+    fn from(request: DeleteInputFileRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+            delete_remote: request.delete_remote,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::DeleteInputFileResponse> for DeleteInputFileResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::DeleteInputFileResponse) -> Result<Self> {
+        Ok(DeleteInputFileResponse)
+    }
+}
+
+impl From<DeleteInputFileResponse> for proto::DeleteInputFileResponse {
+    fn from(_response: DeleteInputFileResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::GetOutputFileRequest> for GetOutputFileRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetOutputFileRequest) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        let access_token = if proto.access_token.is_empty() {
+            None
+        } else {
+            Some(proto.access_token)
+        };
+        let ret = Self {
+            data_id,
+            access_token,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetOutputFileRequest> for proto::GetOutputFileRequest {
+    fn from(request: GetOutputFileRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+            access_token: request.access_token.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetOutputFileResponse> for GetOutputFileResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetOutputFileResponse) -> Result<Self> {
+        let cmac = {
+            if proto.cmac.is_empty() {
+                None
+            } else {
+                Some(FileAuthTag::from_hex(&proto.cmac)?)
+            }
+        };
+
+        let sealed_crypto_info = if proto.sealed_crypto_info.is_empty() {
+            None
+        } else {
+            Some(proto.sealed_crypto_info)
+        };
+
+        Ok(Self {
+            owner: OwnerList::new(proto.owner),
+            owner_count: proto.owner_count,
+            cmac,
+            sealed_crypto_info,
+        })
+    }
+}
+
+impl From<GetOutputFileResponse> for proto::GetOutputFileResponse {
+    fn from(request: GetOutputFileResponse) -> Self {
+        Self {
+            owner: request.owner.into(),
+            cmac: request.cmac.map_or_else(String::new, |cmac| cmac.to_hex()),
+            sealed_crypto_info: request.sealed_crypto_info.unwrap_or_default(),
+            owner_count: request.owner_count,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::FunctionInput> for FunctionInput {
+    type Error = Error;
+
+    fn try_from(proto: proto::FunctionInput) -> Result<Self> {
+        let ret = Self {
+            name: proto.name,
+            description: proto.description,
+            optional: proto.optional,
+            exclusive_group: Some(proto.exclusive_group).filter(|group| !group.is_empty()),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<FunctionInput> for proto::FunctionInput {
+    fn from(input: FunctionInput) -> Self {
+        Self {
+            name: input.name,
+            description: input.description,
+            optional: input.optional,
+            exclusive_group: input.exclusive_group.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::FunctionOutput> for FunctionOutput {
+    type Error = Error;
+
+    fn try_from(proto: proto::FunctionOutput) -> Result<Self> {
+        let crypto_schema = if proto.crypto_schema.is_empty() {
+            None
+        } else {
+            Some(proto.crypto_schema)
+        };
+        let ret = Self {
+            name: proto.name,
+            description: proto.description,
+            crypto_schema,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<FunctionOutput> for proto::FunctionOutput {
+    fn from(output: FunctionOutput) -> Self {
+        Self {
+            name: output.name,
+            description: output.description,
+            crypto_schema: output.crypto_schema.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterFunctionRequest> for RegisterFunctionRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterFunctionRequest) -> Result<Self> {
+        let inputs: Result<Vec<FunctionInput>> = proto
+            .inputs
+            .into_iter()
+            .map(FunctionInput::try_from)
+            .collect();
+        let outputs: Result<Vec<FunctionOutput>> = proto
+            .outputs
+            .into_iter()
+            .map(FunctionOutput::try_from)
+            .collect();
+        let executor_type = proto.executor_type.try_into()?;
+        let return_schema = from_proto_return_schema(proto.return_schema)?;
+
+        let ret = Self {
+            name: proto.name,
+            description: proto.description,
+            executor_type,
+            payload: proto.payload,
+            public: proto.public,
+            arguments: proto.arguments,
+            inputs: inputs?,
+            outputs: outputs?,
+            return_schema,
+            category: from_proto_category(proto.category),
+            tags: proto.tags,
+        };
+        Ok(ret)
+    }
+}
+
+impl From<RegisterFunctionRequest> for proto::RegisterFunctionRequest {
+    fn from(request: RegisterFunctionRequest) -> Self {
+        let inputs: Vec<proto::FunctionInput> = request
+            .inputs
+            .into_iter()
+            .map(proto::FunctionInput::from)
+            .collect();
+        let outputs: Vec<proto::FunctionOutput> = request
+            .outputs
+            .into_iter()
+            .map(proto::FunctionOutput::from)
+            .collect();
+
+        Self {
+            name: request.name,
+            description: request.description,
+            executor_type: request.executor_type.into(),
+            payload: request.payload,
+            public: request.public,
+            arguments: request.arguments,
+            inputs,
+            outputs,
+            return_schema: to_proto_return_schema(request.return_schema),
+            category: request.category.unwrap_or_default(),
+            tags: request.tags,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterFunctionResponse> for RegisterFunctionResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterFunctionResponse) -> Result<Self> {
+        let function_id = proto.function_id.try_into()?;
+        let ret = Self {
+            function_id,
+            payload_hash: proto.payload_hash,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterFunctionResponse> for proto::RegisterFunctionResponse {
+    fn from(response: RegisterFunctionResponse) -> Self {
+        Self {
+            function_id: response.function_id.to_string(),
+            payload_hash: response.payload_hash,
+        }
+    }
+}
+
+fn from_proto_category(category: String) -> Option<String> {
+    if category.is_empty() {
+        None
+    } else {
+        Some(category)
+    }
+}
+
+impl std::convert::TryFrom<proto::GetFunctionRequest> for GetFunctionRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetFunctionRequest) -> Result<Self> {
+        let function_id = proto.function_id.try_into()?;
+        let ret = Self { function_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetFunctionRequest> for proto::GetFunctionRequest {
+    fn from(request: GetFunctionRequest) -> Self {
+        Self {
+            function_id: request.function_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetFunctionResponse> for GetFunctionResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetFunctionResponse) -> Result<Self> {
+        let inputs: Result<Vec<FunctionInput>> = proto
+            .inputs
+            .into_iter()
+            .map(FunctionInput::try_from)
+            .collect();
+        let outputs: Result<Vec<FunctionOutput>> = proto
+            .outputs
+            .into_iter()
+            .map(FunctionOutput::try_from)
+            .collect();
+        let executor_type = proto.executor_type.try_into()?;
+        let replacement = from_proto_optional_id(proto.replacement_function_id)?;
+        let return_schema = from_proto_return_schema(proto.return_schema)?;
+
+        let ret = Self {
+            name: proto.name,
+            description: proto.description,
+            owner: proto.owner.into(),
+            executor_type,
+            payload: proto.payload,
+            public: proto.public,
+            arguments: proto.arguments,
+            inputs: inputs?,
+            outputs: outputs?,
+            deprecated: proto.deprecated,
+            replacement,
+            return_schema,
+            payload_hash: proto.payload_hash,
+            category: from_proto_category(proto.category),
+            tags: proto.tags,
+            verified: proto.verified,
+            storage_degraded: proto.storage_degraded,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetFunctionResponse> for proto::GetFunctionResponse {
+    fn from(response: GetFunctionResponse) -> Self {
+        let inputs: Vec<proto::FunctionInput> = response
+            .inputs
+            .into_iter()
+            .map(proto::FunctionInput::from)
+            .collect();
+        let outputs: Vec<proto::FunctionOutput> = response
+            .outputs
+            .into_iter()
+            .map(proto::FunctionOutput::from)
+            .collect();
+
+        Self {
+            name: response.name,
+            description: response.description,
+            owner: response.owner.into(),
+            executor_type: response.executor_type.into(),
+            payload: response.payload,
+            public: response.public,
+            arguments: response.arguments,
+            inputs,
+            outputs,
+            deprecated: response.deprecated,
+            replacement_function_id: to_proto_optional_id(response.replacement),
+            return_schema: to_proto_return_schema(response.return_schema),
+            payload_hash: response.payload_hash,
+            category: response.category.unwrap_or_default(),
+            tags: response.tags,
+            verified: response.verified,
+            storage_degraded: response.storage_degraded,
+        }
+    }
+}
+
+fn from_proto_optional_id(id: String) -> Result<Option<ExternalID>> {
+    if id.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(id.try_into()?))
+    }
+}
+
+fn to_proto_optional_id(id: Option<ExternalID>) -> String {
+    id.map(|id| id.to_string()).unwrap_or_default()
+}
+
+fn from_proto_return_schema(schema: String) -> Result<Option<ReturnValueSchema>> {
+    if schema.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(schema.try_into()?))
+    }
+}
+
+fn to_proto_return_schema(schema: Option<ReturnValueSchema>) -> String {
+    schema.map(String::from).unwrap_or_default()
+}
+
+impl std::convert::TryFrom<proto::DeprecateFunctionRequest> for DeprecateFunctionRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::DeprecateFunctionRequest) -> Result<Self> {
+        let ret = Self {
+            function_id: proto.function_id.try_into()?,
+            deprecated: proto.deprecated,
+            replacement: from_proto_optional_id(proto.replacement_function_id)?,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<DeprecateFunctionRequest> for proto::DeprecateFunctionRequest {
+    fn from(request: DeprecateFunctionRequest) -> Self {
+        Self {
+            function_id: request.function_id.to_string(),
+            deprecated: request.deprecated,
+            replacement_function_id: to_proto_optional_id(request.replacement),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::DeprecateFunctionResponse> for DeprecateFunctionResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::DeprecateFunctionResponse) -> Result<Self> {
+        Ok(DeprecateFunctionResponse)
+    }
+}
+
+impl From<DeprecateFunctionResponse> for proto::DeprecateFunctionResponse {
+    fn from(_response: DeprecateFunctionResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::SetFunctionRateLimitRequest> for SetFunctionRateLimitRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::SetFunctionRateLimitRequest) -> Result<Self> {
+        let function_id = proto.function_id.try_into()?;
+        let rate_limit = if proto.max_invocations == 0 {
+            None
+        } else {
+            Some(FunctionRateLimit::new(
+                proto.max_invocations,
+                proto.window_seconds,
+            ))
+        };
+        Ok(Self {
+            function_id,
+            rate_limit,
+        })
+    }
+}
+
+impl From<SetFunctionRateLimitRequest> for proto::SetFunctionRateLimitRequest {
+    fn from(request: SetFunctionRateLimitRequest) -> Self {
+        let (max_invocations, window_seconds) = request.rate_limit.map_or((0, 0), |limit| {
+            (limit.max_invocations, limit.window_seconds)
+        });
+        Self {
+            function_id: request.function_id.to_string(),
+            max_invocations,
+            window_seconds,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::SetFunctionRateLimitResponse> for SetFunctionRateLimitResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::SetFunctionRateLimitResponse) -> Result<Self> {
+        Ok(SetFunctionRateLimitResponse)
+    }
+}
+
+impl From<SetFunctionRateLimitResponse> for proto::SetFunctionRateLimitResponse {
+    fn from(_response: SetFunctionRateLimitResponse) -> Self {
+        Self {}
+    }
+}
+
+fn from_proto_ownership(proto: Vec<proto::OwnerList>) -> TaskFileOwners {
+    proto
+        .into_iter()
+        .map(|ol| (ol.data_name, ol.uids))
+        .collect()
+}
+
+fn to_proto_ownership(ownership: TaskFileOwners) -> Vec<proto::OwnerList> {
+    ownership
+        .into_iter()
+        .map(|(name, ol)| proto::OwnerList {
+            data_name: name,
+            uids: ol.into(),
+        })
+        .collect()
+}
+
+impl std::convert::TryFrom<proto::CreateTaskRequest> for CreateTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateTaskRequest) -> Result<Self> {
+        let function_arguments = FunctionArguments::from(proto.function_arguments)
+            .with_binary(proto.binary_function_arguments);
+        let inputs_ownership = from_proto_ownership(proto.inputs_ownership);
+        let outputs_ownership = from_proto_ownership(proto.outputs_ownership);
+        let function_id = proto.function_id.try_into()?;
+        let executor = match proto.executor.as_str() {
+            "" => None,
+            executor => Some(executor.try_into()?),
+        };
+        let approval_deadline_seconds = match proto.approval_deadline_seconds {
+            0 => None,
+            seconds => Some(seconds),
+        };
+
+        let ret = Self {
+            function_id,
+            function_arguments,
+            executor,
+            inputs_ownership,
+            outputs_ownership,
+            approval_deadline_seconds,
+            labels: proto.labels,
+            max_memory_mb: match proto.max_memory_mb {
+                0 => None,
+                v => Some(v),
+            },
+            max_runtime_seconds: match proto.max_runtime_seconds {
+                0 => None,
+                v => Some(v),
+            },
+        };
+        Ok(ret)
+    }
+}
+
+impl From<CreateTaskRequest> for proto::CreateTaskRequest {
+    fn from(request: CreateTaskRequest) -> Self {
+        let (function_arguments, binary_function_arguments) =
+            request.function_arguments.into_string_and_binary();
+        let inputs_ownership = to_proto_ownership(request.inputs_ownership);
+        let outputs_ownership = to_proto_ownership(request.outputs_ownership);
+
+        Self {
+            function_id: request.function_id.to_string(),
+            function_arguments,
+            binary_function_arguments,
+            executor: request
+                .executor
+                .map(|executor| executor.to_string())
+                .unwrap_or_default(),
+            approval_deadline_seconds: request.approval_deadline_seconds.unwrap_or(0),
+            labels: request.labels,
+            max_memory_mb: request.max_memory_mb.unwrap_or(0),
+            max_runtime_seconds: request.max_runtime_seconds.unwrap_or(0),
+            inputs_ownership,
+            outputs_ownership,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CreateTaskResponse> for CreateTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateTaskResponse) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self {
+            task_id,
+            function_deprecated: proto.function_deprecated,
+            storage_degraded: proto.storage_degraded,
+            warnings: proto.warnings,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<CreateTaskResponse> for proto::CreateTaskResponse {
+    fn from(response: CreateTaskResponse) -> Self {
+        Self {
+            task_id: response.task_id.to_string(),
+            function_deprecated: response.function_deprecated,
+            storage_degraded: response.storage_degraded,
+            warnings: response.warnings,
+        }
+    }
+}
+
+fn to_proto_file_ids(map: HashMap<String, ExternalID>) -> Vec<proto::DataMap> {
+    map.into_iter()
+        .map(|(name, ext_id)| proto::DataMap {
+            data_name: name,
+            data_id: ext_id.to_string(),
+        })
+        .collect()
+}
+
+fn to_proto_input_samples(map: HashMap<String, InputSample>) -> Vec<proto::InputSample> {
+    map.into_iter()
+        .map(|(name, sample)| proto::InputSample {
+            data_name: name,
+            unit: sample.unit().to_string(),
+            limit: sample.limit(),
+        })
+        .collect()
+}
+
+fn from_proto_input_samples(
+    vector: Vec<proto::InputSample>,
+) -> Result<HashMap<String, InputSample>> {
+    vector
+        .into_iter()
+        .map(|item| InputSample::new(&item.unit, item.limit).map(|sample| (item.data_name, sample)))
+        .collect()
+}
+
+fn from_proto_file_ids(vector: Vec<proto::DataMap>) -> Result<HashMap<String, ExternalID>> {
+    vector
+        .into_iter()
+        .map(|item| {
+            item.data_id
+                .clone()
+                .try_into()
+                .map(|ext_id| (item.data_name, ext_id))
+        })
+        .collect()
+}
+
+impl std::convert::TryFrom<proto::GetTaskRequest> for GetTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self { task_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTaskRequest> for proto::GetTaskRequest {
+    fn from(request: GetTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTaskResponse> for GetTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTaskResponse) -> Result<Self> {
+        let function_arguments = FunctionArguments::from(proto.function_arguments)
+            .with_binary(proto.binary_function_arguments);
+        let inputs_ownership = from_proto_ownership(proto.inputs_ownership);
+        let outputs_ownership = from_proto_ownership(proto.outputs_ownership);
+        let assigned_inputs = from_proto_file_ids(proto.assigned_inputs)?;
+        let assigned_outputs = from_proto_file_ids(proto.assigned_outputs)?;
+        let input_samples = from_proto_input_samples(proto.input_samples)?;
+        let status = i32_to_task_status(proto.status)?;
+        let function_id = proto.function_id.try_into()?;
+        let task_id = proto.task_id.try_into()?;
+        let result = proto.result.try_into()?;
+
+        let ret = Self {
+            task_id,
+            creator: proto.creator.into(),
+            function_id,
+            function_owner: proto.function_owner.into(),
+            function_arguments,
+            inputs_ownership,
+            outputs_ownership,
+            participants: UserList::new(proto.participants),
+            approved_users: UserList::new(proto.approved_users),
+            pending_approvals: UserList::new(proto.pending_approvals),
+            assigned_inputs,
+            assigned_outputs,
+            input_samples,
+            status,
+            result,
+            storage_degraded: proto.storage_degraded,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTaskResponse> for proto::GetTaskResponse {
+    fn from(response: GetTaskResponse) -> Self {
+        let (function_arguments, binary_function_arguments) =
+            response.function_arguments.into_string_and_binary();
+        let inputs_ownership = to_proto_ownership(response.inputs_ownership);
+        let outputs_ownership = to_proto_ownership(response.outputs_ownership);
+        let assigned_inputs = to_proto_file_ids(response.assigned_inputs);
+        let assigned_outputs = to_proto_file_ids(response.assigned_outputs);
+        let status = i32_from_task_status(response.status);
+        Self {
+            task_id: response.task_id.to_string(),
+            creator: response.creator.to_string(),
+            function_id: response.function_id.to_string(),
+            function_owner: response.function_owner.to_string(),
+            function_arguments,
+            binary_function_arguments,
+            inputs_ownership,
+            outputs_ownership,
+            participants: response.participants.into(),
+            approved_users: response.approved_users.into(),
+            pending_approvals: response.pending_approvals.into(),
+            assigned_inputs,
+            assigned_outputs,
+            input_samples: to_proto_input_samples(response.input_samples),
+            storage_degraded: response.storage_degraded,
+            status,
+            result: Some(response.result.into()),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AssignDataRequest> for AssignDataRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::AssignDataRequest) -> Result<Self> {
+        let inputs = from_proto_file_ids(proto.inputs)?;
+        let outputs = from_proto_file_ids(proto.outputs)?;
+        let input_samples = from_proto_input_samples(proto.input_samples)?;
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self {
+            task_id,
+            inputs,
+            outputs,
+            input_samples,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<AssignDataRequest> for proto::AssignDataRequest {
+    fn from(request: AssignDataRequest) -> Self {
+        let inputs = to_proto_file_ids(request.inputs);
+        let outputs = to_proto_file_ids(request.outputs);
+        Self {
+            task_id: request.task_id.to_string(),
+            inputs,
+            outputs,
+            input_samples: to_proto_input_samples(request.input_samples),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AssignDataResponse> for AssignDataResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::AssignDataResponse) -> Result<Self> {
+        let inputs = from_proto_file_ids(proto.inputs)?;
+        let outputs = from_proto_file_ids(proto.outputs)?;
+        Ok(Self {
+            inputs,
+            outputs,
+            ready: proto.ready,
+        })
+    }
+}
+
+impl From<AssignDataResponse> for proto::AssignDataResponse {
+    fn from(response: AssignDataResponse) -> Self {
+        Self {
+            inputs: to_proto_file_ids(response.inputs),
+            outputs: to_proto_file_ids(response.outputs),
+            ready: response.ready,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ApproveTaskRequest> for ApproveTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ApproveTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self { task_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<ApproveTaskRequest> for proto::ApproveTaskRequest {
+    fn from(request: ApproveTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ApproveTaskResponse> for ApproveTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ApproveTaskResponse) -> Result<Self> {
+        Ok(ApproveTaskResponse::new(proto.already_approved))
+    }
+}
+
+impl From<ApproveTaskResponse> for proto::ApproveTaskResponse {
+    fn from(response: ApproveTaskResponse) -> Self {
+        Self {
+            already_approved: response.already_approved,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::InvokeTaskRequest> for InvokeTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::InvokeTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self {
+            task_id,
+            skip_if_inputs_unchanged: proto.skip_if_inputs_unchanged,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<InvokeTaskRequest> for proto::InvokeTaskRequest {
+    fn from(request: InvokeTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+            skip_if_inputs_unchanged: request.skip_if_inputs_unchanged,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::InvokeTaskResponse> for InvokeTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::InvokeTaskResponse) -> Result<Self> {
+        Ok(Self {
+            skipped: proto.skipped,
+        })
+    }
+}
+
+impl From<InvokeTaskResponse> for proto::InvokeTaskResponse {
+    fn from(response: InvokeTaskResponse) -> Self {
+        Self {
+            skipped: response.skipped,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AdminGetTaskRequest> for AdminGetTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::AdminGetTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self { task_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<AdminGetTaskRequest> for proto::AdminGetTaskRequest {
+    fn from(request: AdminGetTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::AdminGetTaskResponse> for AdminGetTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::AdminGetTaskResponse) -> Result<Self> {
+        let task = proto
+            .task
+            .ok_or_else(|| anyhow!("missing task"))?
+            .try_into()?;
+        let ret = Self { task };
+
+        Ok(ret)
+    }
+}
+
+impl From<AdminGetTaskResponse> for proto::AdminGetTaskResponse {
+    fn from(response: AdminGetTaskResponse) -> Self {
+        Self {
+            task: Some(response.task.into()),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RunSelfTestRequest> for RunSelfTestRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::RunSelfTestRequest) -> Result<Self> {
+        Ok(RunSelfTestRequest)
+    }
+}
+
+impl From<RunSelfTestRequest> for proto::RunSelfTestRequest {
+    fn from(_request: RunSelfTestRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::SelfTestStage> for SelfTestStage {
+    type Error = Error;
+
+    fn try_from(proto: proto::SelfTestStage) -> Result<Self> {
+        let ret = Self {
+            name: proto.name,
+            success: proto.success,
+            message: proto.message,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<SelfTestStage> for proto::SelfTestStage {
+    fn from(stage: SelfTestStage) -> Self {
+        Self {
+            name: stage.name,
+            success: stage.success,
+            message: stage.message,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RunSelfTestResponse> for RunSelfTestResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RunSelfTestResponse) -> Result<Self> {
+        let stages: Result<Vec<SelfTestStage>> = proto
+            .stages
+            .into_iter()
+            .map(SelfTestStage::try_from)
+            .collect();
+        let ret = Self { stages: stages? };
+
+        Ok(ret)
+    }
+}
+
+impl From<RunSelfTestResponse> for proto::RunSelfTestResponse {
+    fn from(response: RunSelfTestResponse) -> Self {
+        Self {
+            stages: response
+                .stages
+                .into_iter()
+                .map(proto::SelfTestStage::from)
+                .collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::LinkTasksRequest> for LinkTasksRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::LinkTasksRequest) -> Result<Self> {
+        let ret = Self {
+            producer_task_id: proto.producer_task_id.try_into()?,
+            output_name: proto.output_name,
+            consumer_task_id: proto.consumer_task_id.try_into()?,
+            input_name: proto.input_name,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<LinkTasksRequest> for proto::LinkTasksRequest {
+    fn from(request: LinkTasksRequest) -> Self {
+        Self {
+            producer_task_id: request.producer_task_id.to_string(),
+            output_name: request.output_name,
+            consumer_task_id: request.consumer_task_id.to_string(),
+            input_name: request.input_name,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::LinkTasksResponse> for LinkTasksResponse {
+    type Error = Error;
+
+    fn try_from(_proto: proto::LinkTasksResponse) -> Result<Self> {
+        Ok(LinkTasksResponse)
+    }
+}
+
+impl From<LinkTasksResponse> for proto::LinkTasksResponse {
+    fn from(_response: LinkTasksResponse) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::FileEntry> for FileEntry {
+    type Error = Error;
+
+    fn try_from(proto: proto::FileEntry) -> Result<Self> {
+        let ret = Self {
+            data_id: proto.data_id.try_into()?,
+            created_at: proto.created_at,
+            consumed: proto.consumed,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<FileEntry> for proto::FileEntry {
+    fn from(entry: FileEntry) -> Self {
+        Self {
+            data_id: entry.data_id.to_string(),
+            created_at: entry.created_at,
+            consumed: entry.consumed,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListInputFilesRequest> for ListInputFilesRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListInputFilesRequest) -> Result<Self> {
+        let cursor = match proto.cursor.as_str() {
+            "" => None,
+            _ => Some(proto.cursor),
+        };
+        let ret = Self {
+            cursor,
+            limit: proto.limit as usize,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListInputFilesRequest> for proto::ListInputFilesRequest {
+    fn from(request: ListInputFilesRequest) -> Self {
+        Self {
+            limit: request.limit as u32,
+            cursor: request.cursor.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListInputFilesResponse> for ListInputFilesResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListInputFilesResponse) -> Result<Self> {
+        let files: Result<Vec<FileEntry>> =
+            proto.files.into_iter().map(FileEntry::try_from).collect();
+        let next_cursor = match proto.next_cursor.as_str() {
+            "" => None,
+            _ => Some(proto.next_cursor),
+        };
+        let ret = Self {
+            files: files?,
+            next_cursor,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListInputFilesResponse> for proto::ListInputFilesResponse {
+    fn from(response: ListInputFilesResponse) -> Self {
+        Self {
+            files: response
+                .files
+                .into_iter()
+                .map(proto::FileEntry::from)
+                .collect(),
+            next_cursor: response.next_cursor.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListOutputFilesRequest> for ListOutputFilesRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListOutputFilesRequest) -> Result<Self> {
+        let cursor = match proto.cursor.as_str() {
+            "" => None,
+            _ => Some(proto.cursor),
+        };
+        let ret = Self {
+            cursor,
+            limit: proto.limit as usize,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListOutputFilesRequest> for proto::ListOutputFilesRequest {
+    fn from(request: ListOutputFilesRequest) -> Self {
+        Self {
+            limit: request.limit as u32,
+            cursor: request.cursor.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListOutputFilesResponse> for ListOutputFilesResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListOutputFilesResponse) -> Result<Self> {
+        let files: Result<Vec<FileEntry>> =
+            proto.files.into_iter().map(FileEntry::try_from).collect();
+        let next_cursor = match proto.next_cursor.as_str() {
+            "" => None,
+            _ => Some(proto.next_cursor),
+        };
+        let ret = Self {
+            files: files?,
+            next_cursor,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListOutputFilesResponse> for proto::ListOutputFilesResponse {
+    fn from(response: ListOutputFilesResponse) -> Self {
+        Self {
+            files: response
+                .files
+                .into_iter()
+                .map(proto::FileEntry::from)
+                .collect(),
+            next_cursor: response.next_cursor.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterTaskTemplateRequest> for RegisterTaskTemplateRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterTaskTemplateRequest) -> Result<Self> {
+        let ret = Self {
+            name: proto.name,
+            function_id: proto.function_id.try_into()?,
+            function_arguments: proto.function_arguments.into(),
+            executor: proto.executor.try_into()?,
+            public: proto.public,
+            inputs_ownership: from_proto_ownership(proto.inputs_ownership),
+            outputs_ownership: from_proto_ownership(proto.outputs_ownership),
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterTaskTemplateRequest> for proto::RegisterTaskTemplateRequest {
+    fn from(request: RegisterTaskTemplateRequest) -> Self {
+        Self {
+            name: request.name,
+            function_id: request.function_id.to_string(),
+            function_arguments: request.function_arguments.into(),
+            executor: request.executor.to_string(),
+            public: request.public,
+            inputs_ownership: to_proto_ownership(request.inputs_ownership),
+            outputs_ownership: to_proto_ownership(request.outputs_ownership),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterTaskTemplateResponse> for RegisterTaskTemplateResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterTaskTemplateResponse) -> Result<Self> {
+        let ret = Self {
+            template_id: proto.template_id.try_into()?,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterTaskTemplateResponse> for proto::RegisterTaskTemplateResponse {
+    fn from(response: RegisterTaskTemplateResponse) -> Self {
+        Self {
+            template_id: response.template_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::InstantiateTemplateRequest> for InstantiateTemplateRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::InstantiateTemplateRequest) -> Result<Self> {
+        let approval_deadline_seconds = match proto.approval_deadline_seconds {
+            0 => None,
+            seconds => Some(seconds),
+        };
+        let ret = Self {
+            template_id: proto.template_id.try_into()?,
+            function_arguments: proto.function_arguments.into(),
+            inputs_ownership: from_proto_ownership(proto.inputs_ownership),
+            outputs_ownership: from_proto_ownership(proto.outputs_ownership),
+            approval_deadline_seconds,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<InstantiateTemplateRequest> for proto::InstantiateTemplateRequest {
+    fn from(request: InstantiateTemplateRequest) -> Self {
+        Self {
+            template_id: request.template_id.to_string(),
+            function_arguments: request.function_arguments.into(),
+            approval_deadline_seconds: request.approval_deadline_seconds.unwrap_or(0),
+            inputs_ownership: to_proto_ownership(request.inputs_ownership),
+            outputs_ownership: to_proto_ownership(request.outputs_ownership),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::InstantiateTemplateResponse> for InstantiateTemplateResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::InstantiateTemplateResponse) -> Result<Self> {
+        let ret = Self {
+            task_id: proto.task_id.try_into()?,
+            function_deprecated: proto.function_deprecated,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<InstantiateTemplateResponse> for proto::InstantiateTemplateResponse {
+    fn from(response: InstantiateTemplateResponse) -> Self {
+        Self {
+            task_id: response.task_id.to_string(),
+            function_deprecated: response.function_deprecated,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTasksRequest> for GetTasksRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTasksRequest) -> Result<Self> {
+        let task_ids: Result<Vec<ExternalID>> = proto
+            .task_ids
+            .into_iter()
+            .map(|task_id| task_id.try_into())
+            .collect();
+        let ret = Self {
+            task_ids: task_ids?,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTasksRequest> for proto::GetTasksRequest {
+    fn from(request: GetTasksRequest) -> Self {
+        Self {
+            task_ids: request
+                .task_ids
+                .into_iter()
+                .map(|task_id| task_id.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTasksEntry> for GetTasksEntry {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTasksEntry) -> Result<Self> {
+        let task = match (proto.accessible, proto.task) {
+            (true, Some(task)) => Some(task.try_into()?),
+            (true, None) => return Err(anyhow!("missing task")),
+            (false, _) => None,
+        };
+        let ret = Self {
+            task_id: proto.task_id.try_into()?,
+            task,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTasksEntry> for proto::GetTasksEntry {
+    fn from(entry: GetTasksEntry) -> Self {
+        Self {
+            task_id: entry.task_id.to_string(),
+            accessible: entry.task.is_some(),
+            task: entry.task.map(proto::GetTaskResponse::from),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetTasksResponse> for GetTasksResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetTasksResponse) -> Result<Self> {
+        let tasks: Result<Vec<GetTasksEntry>> = proto
+            .tasks
+            .into_iter()
+            .map(GetTasksEntry::try_from)
+            .collect();
+        let ret = Self { tasks: tasks? };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetTasksResponse> for proto::GetTasksResponse {
+    fn from(response: GetTasksResponse) -> Self {
+        Self {
+            tasks: response
+                .tasks
+                .into_iter()
+                .map(proto::GetTasksEntry::from)
+                .collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetCapabilitiesRequest> for GetCapabilitiesRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::GetCapabilitiesRequest) -> Result<Self> {
+        Ok(GetCapabilitiesRequest)
+    }
+}
+
+impl From<GetCapabilitiesRequest> for proto::GetCapabilitiesRequest {
+    fn from(_request: GetCapabilitiesRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::GetCapabilitiesResponse> for GetCapabilitiesResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetCapabilitiesResponse) -> Result<Self> {
+        let executors: Result<Vec<Executor>> = proto
+            .executors
+            .into_iter()
+            .map(Executor::try_from)
+            .collect();
+        let executor_types: Result<Vec<ExecutorType>> = proto
+            .executor_types
+            .into_iter()
+            .map(ExecutorType::try_from)
+            .collect();
+        let ret = Self {
+            crypto_schemas: proto.crypto_schemas,
+            executors: executors?,
+            executor_types: executor_types?,
+            url_schemes: proto.url_schemes,
+            version: proto.version,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetCapabilitiesResponse> for proto::GetCapabilitiesResponse {
+    fn from(response: GetCapabilitiesResponse) -> Self {
+        Self {
+            crypto_schemas: response.crypto_schemas,
+            executors: response
+                .executors
+                .into_iter()
+                .map(|executor| executor.to_string())
+                .collect(),
+            executor_types: response
+                .executor_types
+                .into_iter()
+                .map(|executor_type| executor_type.to_string())
+                .collect(),
+            url_schemes: response.url_schemes,
+            version: response.version,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CancelTaskRequest> for CancelTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::CancelTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self { task_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<CancelTaskRequest> for proto::CancelTaskRequest {
+    fn from(request: CancelTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CancelTaskResponse> for CancelTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::CancelTaskResponse) -> Result<Self> {
+        Ok(CancelTaskResponse::new(proto.canceled))
+    }
+}
+
+impl From<CancelTaskResponse> for proto::CancelTaskResponse {
+    fn from(response: CancelTaskResponse) -> Self {
+        Self {
+            canceled: response.canceled,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListTasksRequest> for ListTasksRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListTasksRequest) -> Result<Self> {
+        let cursor = match proto.cursor.as_str() {
+            "" => None,
+            _ => Some(proto.cursor),
+        };
+        let ret = Self {
+            cursor,
+            limit: proto.limit as usize,
+            labels: proto.labels,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListTasksRequest> for proto::ListTasksRequest {
+    fn from(request: ListTasksRequest) -> Self {
+        Self {
+            limit: request.limit as u32,
+            cursor: request.cursor.unwrap_or_default(),
+            labels: request.labels,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::TaskEntry> for TaskEntry {
+    type Error = Error;
+
+    fn try_from(proto: proto::TaskEntry) -> Result<Self> {
+        let ret = Self {
+            task_id: proto.task_id.try_into()?,
+            created_at: proto.created_at,
+            labels: proto.labels,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<TaskEntry> for proto::TaskEntry {
+    fn from(entry: TaskEntry) -> Self {
+        Self {
+            task_id: entry.task_id.to_string(),
+            created_at: entry.created_at,
+            labels: entry.labels,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListTasksResponse> for ListTasksResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListTasksResponse) -> Result<Self> {
+        let tasks: Result<Vec<TaskEntry>> =
+            proto.tasks.into_iter().map(TaskEntry::try_from).collect();
+        let next_cursor = match proto.next_cursor.as_str() {
+            "" => None,
+            _ => Some(proto.next_cursor),
+        };
+        let ret = Self {
+            tasks: tasks?,
+            next_cursor,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListTasksResponse> for proto::ListTasksResponse {
+    fn from(response: ListTasksResponse) -> Self {
+        Self {
+            tasks: response
+                .tasks
+                .into_iter()
+                .map(proto::TaskEntry::from)
+                .collect(),
+            next_cursor: response.next_cursor.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ExportTaskRequest> for ExportTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ExportTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        Ok(Self {
+            task_id,
+            include_arguments: proto.include_arguments,
+        })
+    }
+}
+
+impl From<ExportTaskRequest> for proto::ExportTaskRequest {
+    fn from(request: ExportTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+            include_arguments: request.include_arguments,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ExportTaskResponse> for ExportTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ExportTaskResponse) -> Result<Self> {
+        Ok(Self { task: proto.task })
+    }
+}
+
+impl From<ExportTaskResponse> for proto::ExportTaskResponse {
+    fn from(response: ExportTaskResponse) -> Self {
+        Self {
+            task: response.task,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ImportTaskRequest> for ImportTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ImportTaskRequest) -> Result<Self> {
+        Ok(Self {
+            task: proto.task,
+            function_arguments: proto.function_arguments.into(),
+        })
+    }
+}
+
+impl From<ImportTaskRequest> for proto::ImportTaskRequest {
+    fn from(request: ImportTaskRequest) -> Self {
+        Self {
+            task: request.task,
+            function_arguments: request.function_arguments.into(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ImportTaskResponse> for ImportTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ImportTaskResponse) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        Ok(Self { task_id })
+    }
+}
+
+impl From<ImportTaskResponse> for proto::ImportTaskResponse {
+    fn from(response: ImportTaskResponse) -> Self {
+        Self {
+            task_id: response.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GenerateOutputPresignedUrlRequest>
+    for GenerateOutputPresignedUrlRequest
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::GenerateOutputPresignedUrlRequest) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
+
+impl From<GenerateOutputPresignedUrlRequest> for proto::GenerateOutputPresignedUrlRequest {
+    fn from(request: GenerateOutputPresignedUrlRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::GenerateOutputPresignedUrlResponse>
+    for GenerateOutputPresignedUrlResponse
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::GenerateOutputPresignedUrlResponse) -> Result<Self> {
+        Ok(Self {
+            url: Url::parse(&proto.url)?,
+            expires_at: proto.expires_at,
+        })
+    }
+}
+
+impl From<GenerateOutputPresignedUrlResponse> for proto::GenerateOutputPresignedUrlResponse {
+    fn from(response: GenerateOutputPresignedUrlResponse) -> Self {
+        Self {
+            url: response.url.into_string(),
+            expires_at: response.expires_at,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::EstimateTaskRequest> for EstimateTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::EstimateTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        Ok(Self { task_id })
+    }
+}
+
+impl From<EstimateTaskRequest> for proto::EstimateTaskRequest {
+    fn from(request: EstimateTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::EstimateTaskResponse> for EstimateTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::EstimateTaskResponse) -> Result<Self> {
+        Ok(Self {
+            input_bytes: proto.input_bytes,
+            unsized_files: proto.unsized_files,
+            queued_tasks: proto.queued_tasks,
+        })
+    }
+}
+
+impl From<EstimateTaskResponse> for proto::EstimateTaskResponse {
+    fn from(response: EstimateTaskResponse) -> Self {
+        Self {
+            input_bytes: response.input_bytes,
+            unsized_files: response.unsized_files,
+            queued_tasks: response.queued_tasks,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListFunctionsRequest> for ListFunctionsRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListFunctionsRequest) -> Result<Self> {
+        let cursor = match proto.cursor.as_str() {
+            "" => None,
+            _ => Some(proto.cursor),
+        };
+        let ret = Self {
+            cursor,
+            limit: proto.limit as usize,
+            category: from_proto_category(proto.category),
+            tags: proto.tags,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListFunctionsRequest> for proto::ListFunctionsRequest {
+    fn from(request: ListFunctionsRequest) -> Self {
+        Self {
+            limit: request.limit as u32,
+            cursor: request.cursor.unwrap_or_default(),
+            category: request.category.unwrap_or_default(),
+            tags: request.tags,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::FunctionEntry> for FunctionEntry {
+    type Error = Error;
+
+    fn try_from(proto: proto::FunctionEntry) -> Result<Self> {
+        let ret = Self {
+            function_id: proto.function_id.try_into()?,
+            name: proto.name,
+            category: from_proto_category(proto.category),
+            tags: proto.tags,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<FunctionEntry> for proto::FunctionEntry {
+    fn from(entry: FunctionEntry) -> Self {
+        Self {
+            function_id: entry.function_id.to_string(),
+            name: entry.name,
+            category: entry.category.unwrap_or_default(),
+            tags: entry.tags,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::ListFunctionsResponse> for ListFunctionsResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ListFunctionsResponse) -> Result<Self> {
+        let functions: Result<Vec<FunctionEntry>> = proto
+            .functions
+            .into_iter()
+            .map(FunctionEntry::try_from)
+            .collect();
+        let next_cursor = match proto.next_cursor.as_str() {
+            "" => None,
+            _ => Some(proto.next_cursor),
+        };
+        let ret = Self {
+            functions: functions?,
+            next_cursor,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl From<ListFunctionsResponse> for proto::ListFunctionsResponse {
+    fn from(response: ListFunctionsResponse) -> Self {
+        Self {
+            functions: response
+                .functions
+                .into_iter()
+                .map(proto::FunctionEntry::from)
+                .collect(),
+            next_cursor: response.next_cursor.unwrap_or_default(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RetryTaskRequest> for RetryTaskRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::RetryTaskRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self { task_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<RetryTaskRequest> for proto::RetryTaskRequest {
+    fn from(request: RetryTaskRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RetryTaskResponse> for RetryTaskResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::RetryTaskResponse) -> Result<Self> {
+        Ok(RetryTaskResponse::new(proto.retries_left))
+    }
+}
+
+impl From<RetryTaskResponse> for proto::RetryTaskResponse {
+    fn from(response: RetryTaskResponse) -> Self {
+        Self {
+            retries_left: response.retries_left,
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInputFromTaskLogRequest>
+    for RegisterInputFromTaskLogRequest
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInputFromTaskLogRequest) -> Result<Self> {
+        let task_id = proto.task_id.try_into()?;
+        let ret = Self { task_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterInputFromTaskLogRequest> for proto::RegisterInputFromTaskLogRequest {
+    fn from(request: RegisterInputFromTaskLogRequest) -> Self {
+        Self {
+            task_id: request.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::RegisterInputFromTaskLogResponse>
+    for RegisterInputFromTaskLogResponse
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::RegisterInputFromTaskLogResponse) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        let ret = Self { data_id };
+
+        Ok(ret)
+    }
+}
+
+impl From<RegisterInputFromTaskLogResponse> for proto::RegisterInputFromTaskLogResponse {
+    fn from(response: RegisterInputFromTaskLogResponse) -> Self {
+        Self {
+            data_id: response.data_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterOutputFileRequest> for RegisterOutputFileRequest {
+impl std::convert::TryFrom<proto::SubmitTaskGraphRequest> for SubmitTaskGraphRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterOutputFileRequest) -> Result<Self> {
+    fn try_from(proto: proto::SubmitTaskGraphRequest) -> Result<Self> {
+        let mut nodes = HashMap::new();
+        for node in proto.nodes {
+            let task = node
+                .task
+                .ok_or_else(|| anyhow!("Missing task of node {}", node.name))?;
+            let task = CreateTaskRequest::try_from(task)?;
+            if nodes.insert(node.name.clone(), task).is_some() {
+                return Err(anyhow!("Duplicate node name: {}", node.name));
+            }
+        }
+        let edges = proto
+            .edges
+            .into_iter()
+            .map(|edge| {
+                TaskGraphEdge::new(
+                    edge.producer,
+                    edge.output_name,
+                    edge.consumer,
+                    edge.input_name,
+                )
+            })
+            .collect();
+        let ret = Self { nodes, edges };
+
+        Ok(ret)
+    }
+}
+
+impl From<SubmitTaskGraphRequest> for proto::SubmitTaskGraphRequest {
+    fn from(request: SubmitTaskGraphRequest) -> Self {
+        let nodes = request
+            .nodes
+            .into_iter()
+            .map(|(name, task)| proto::TaskGraphNode {
+                name,
+                task: Some(task.into()),
+            })
+            .collect();
+        let edges = request
+            .edges
+            .into_iter()
+            .map(|edge| proto::TaskGraphEdge {
+                producer: edge.producer,
+                output_name: edge.output_name,
+                consumer: edge.consumer,
+                input_name: edge.input_name,
+            })
+            .collect();
+        Self { nodes, edges }
+    }
+}
+
+impl std::convert::TryFrom<proto::SubmitTaskGraphResponse> for SubmitTaskGraphResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::SubmitTaskGraphResponse) -> Result<Self> {
+        let task_ids: Result<HashMap<String, ExternalID>> = proto
+            .task_ids
+            .into_iter()
+            .map(|(name, task_id)| Ok((name, task_id.try_into()?)))
+            .collect();
         let ret = Self {
-            url: Url::parse(&proto.url)?,
-            crypto_info: proto
-                .crypto_info
-                .ok_or_else(|| anyhow!("missing crypto_info"))?
-                .try_into()?,
+            task_ids: task_ids?,
         };
 
         Ok(ret)
     }
 }
 
-impl From<RegisterOutputFileRequest> for proto::RegisterOutputFileRequest {
-    fn from(request: RegisterOutputFileRequest) -> Self {
-        Self {
-            url: request.url.into_string(),
-            crypto_info: Some(request.crypto_info.into()),
-        }
+impl From<SubmitTaskGraphResponse> for proto::SubmitTaskGraphResponse {
+    fn from(response: SubmitTaskGraphResponse) -> Self {
+        let task_ids = response
+            .task_ids
+            .into_iter()
+            .map(|(name, task_id)| (name, task_id.to_string()))
+            .collect();
+        Self { task_ids }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterOutputFileResponse> for RegisterOutputFileResponse {
+impl std::convert::TryFrom<proto::SubscribeEventsRequest> for SubscribeEventsRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterOutputFileResponse) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        Ok(Self { data_id })
+    fn try_from(proto: proto::SubscribeEventsRequest) -> Result<Self> {
+        let ret = Self {
+            offset: proto.offset,
+            limit: proto.limit as usize,
+        };
+
+        Ok(ret)
     }
 }
 
-impl From<RegisterOutputFileResponse> for proto::RegisterOutputFileResponse {
-    fn from(request: RegisterOutputFileResponse) -> Self {
+impl From<SubscribeEventsRequest> for proto::SubscribeEventsRequest {
+    fn from(request: SubscribeEventsRequest) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            offset: request.offset,
+            limit: request.limit as u32,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterFusionOutputRequest> for RegisterFusionOutputRequest {
+impl std::convert::TryFrom<proto::TaskEventEntry> for TaskEventEntry {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterFusionOutputRequest) -> Result<Self> {
+    fn try_from(proto: proto::TaskEventEntry) -> Result<Self> {
+        let old_status = if proto.created {
+            None
+        } else {
+            Some(i32_to_task_status(proto.old_status)?)
+        };
         let ret = Self {
-            owner_list: OwnerList::new(proto.owner_list),
+            task_id: proto.task_id.try_into()?,
+            old_status,
+            new_status: i32_to_task_status(proto.new_status)?,
+            timestamp: proto.timestamp,
         };
 
         Ok(ret)
     }
 }
 
-impl From<RegisterFusionOutputRequest> for proto::RegisterFusionOutputRequest {
-    fn from(request: RegisterFusionOutputRequest) -> Self {
+impl From<TaskEventEntry> for proto::TaskEventEntry {
+    fn from(entry: TaskEventEntry) -> Self {
         Self {
-            owner_list: request.owner_list.into(),
+            task_id: entry.task_id.to_string(),
+            created: entry.old_status.is_none(),
+            old_status: entry.old_status.map_or(0, i32_from_task_status),
+            new_status: i32_from_task_status(entry.new_status),
+            timestamp: entry.timestamp,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterFusionOutputResponse> for RegisterFusionOutputResponse {
+impl std::convert::TryFrom<proto::SubscribeEventsResponse> for SubscribeEventsResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterFusionOutputResponse) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        Ok(Self { data_id })
+    fn try_from(proto: proto::SubscribeEventsResponse) -> Result<Self> {
+        let events: Result<Vec<TaskEventEntry>> = proto
+            .events
+            .into_iter()
+            .map(TaskEventEntry::try_from)
+            .collect();
+        let ret = Self {
+            events: events?,
+            next_offset: proto.next_offset,
+        };
+
+        Ok(ret)
     }
 }
 
-impl From<RegisterFusionOutputResponse> for proto::RegisterFusionOutputResponse {
-    fn from(request: RegisterFusionOutputResponse) -> Self {
+impl From<SubscribeEventsResponse> for proto::SubscribeEventsResponse {
+    fn from(response: SubscribeEventsResponse) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            events: response
+                .events
+                .into_iter()
+                .map(proto::TaskEventEntry::from)
+                .collect(),
+            next_offset: response.next_offset,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterInputFromOutputRequest>
-    for RegisterInputFromOutputRequest
-{
+impl std::convert::TryFrom<proto::SoftDeleteFunctionRequest> for SoftDeleteFunctionRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterInputFromOutputRequest) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        let ret = Self { data_id };
+    fn try_from(proto: proto::SoftDeleteFunctionRequest) -> Result<Self> {
+        let function_id = proto.function_id.try_into()?;
+        let ret = Self { function_id };
 
         Ok(ret)
     }
 }
 
-impl From<RegisterInputFromOutputRequest> for proto::RegisterInputFromOutputRequest {
-    fn from(request: RegisterInputFromOutputRequest) -> Self {
+impl From<SoftDeleteFunctionRequest> for proto::SoftDeleteFunctionRequest {
+    fn from(request: SoftDeleteFunctionRequest) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            function_id: request.function_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterInputFromOutputResponse>
-    for RegisterInputFromOutputResponse
-{
+impl std::convert::TryFrom<proto::SoftDeleteFunctionResponse> for SoftDeleteFunctionResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterInputFromOutputResponse) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        Ok(Self { data_id })
+    fn try_from(proto: proto::SoftDeleteFunctionResponse) -> Result<Self> {
+        Ok(Self {
+            restorable_until: proto.restorable_until,
+        })
     }
 }
 
-impl From<RegisterInputFromOutputResponse> for proto::RegisterInputFromOutputResponse {
-    fn from(request: RegisterInputFromOutputResponse) -> Self {
+impl From<SoftDeleteFunctionResponse> for proto::SoftDeleteFunctionResponse {
+    fn from(response: SoftDeleteFunctionResponse) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            restorable_until: response.restorable_until,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetInputFileRequest> for GetInputFileRequest {
+impl std::convert::TryFrom<proto::RestoreFunctionRequest> for RestoreFunctionRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::GetInputFileRequest) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        let ret = Self { data_id };
+    fn try_from(proto: proto::RestoreFunctionRequest) -> Result<Self> {
+        let function_id = proto.function_id.try_into()?;
+        let ret = Self { function_id };
 
         Ok(ret)
     }
 }
 
-impl From<GetInputFileRequest> for proto::GetInputFileRequest {
-    fn from(request: GetInputFileRequest) -> Self {
+impl From<RestoreFunctionRequest> for proto::RestoreFunctionRequest {
+    fn from(request: RestoreFunctionRequest) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            function_id: request.function_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetInputFileResponse> for GetInputFileResponse {
+impl std::convert::TryFrom<proto::RestoreFunctionResponse> for RestoreFunctionResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::GetInputFileResponse) -> Result<Self> {
-        Ok(Self {
-            owner: OwnerList::new(proto.owner),
-            cmac: FileAuthTag::from_hex(proto.cmac)?,
-        })
+    fn try_from(_proto: proto::RestoreFunctionResponse) -> Result<Self> {
+        Ok(RestoreFunctionResponse)
     }
 }
 
-impl From<GetInputFileResponse> for proto::GetInputFileResponse {
-    fn from(request: GetInputFileResponse) -> Self {
-        Self {
-            owner: request.owner.into(),
-            cmac: request.cmac.to_hex(),
-        }
+impl From<RestoreFunctionResponse> for proto::RestoreFunctionResponse {
+    fn from(_response: RestoreFunctionResponse) -> Self {
+        Self {}
     }
 }
 
-impl std::convert::TryFrom<proto::GetOutputFileRequest> for GetOutputFileRequest {
+impl std::convert::TryFrom<proto::RegisterFunctionBundleRequest> for RegisterFunctionBundleRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::GetOutputFileRequest) -> Result<Self> {
-        let data_id = proto.data_id.try_into()?;
-        let ret = Self { data_id };
+    fn try_from(proto: proto::RegisterFunctionBundleRequest) -> Result<Self> {
+        let ret = Self {
+            payload: proto.payload,
+            metadata: proto.metadata,
+            signature: proto.signature,
+        };
 
         Ok(ret)
     }
 }
 
-impl From<GetOutputFileRequest> for proto::GetOutputFileRequest {
-    fn from(request: GetOutputFileRequest) -> Self {
+impl From<RegisterFunctionBundleRequest> for proto::RegisterFunctionBundleRequest {
+    fn from(request: RegisterFunctionBundleRequest) -> Self {
         Self {
-            data_id: request.data_id.to_string(),
+            payload: request.payload,
+            metadata: request.metadata,
+            signature: request.signature,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetOutputFileResponse> for GetOutputFileResponse {
+impl std::convert::TryFrom<proto::RegisterFunctionBundleResponse>
+    for RegisterFunctionBundleResponse
+{
     type Error = Error;
 
-    fn try_from(proto: proto::GetOutputFileResponse) -> Result<Self> {
-        let cmac = {
-            if proto.cmac.is_empty() {
-                None
-            } else {
-                Some(FileAuthTag::from_hex(&proto.cmac)?)
-            }
+    fn try_from(proto: proto::RegisterFunctionBundleResponse) -> Result<Self> {
+        let function_id = proto.function_id.try_into()?;
+        let ret = Self {
+            function_id,
+            payload_hash: proto.payload_hash,
         };
 
-        Ok(Self {
-            owner: OwnerList::new(proto.owner),
-            cmac,
-        })
+        Ok(ret)
     }
 }
 
-impl From<GetOutputFileResponse> for proto::GetOutputFileResponse {
-    fn from(request: GetOutputFileResponse) -> Self {
+impl From<RegisterFunctionBundleResponse> for proto::RegisterFunctionBundleResponse {
+    fn from(response: RegisterFunctionBundleResponse) -> Self {
         Self {
-            owner: request.owner.into(),
-            cmac: request.cmac.map_or_else(String::new, |cmac| cmac.to_hex()),
+            function_id: response.function_id.to_string(),
+            payload_hash: response.payload_hash,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::FunctionInput> for FunctionInput {
+impl std::convert::TryFrom<proto::RegisterCryptoKeyRequest> for RegisterCryptoKeyRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::FunctionInput) -> Result<Self> {
+    fn try_from(proto: proto::RegisterCryptoKeyRequest) -> Result<Self> {
         let ret = Self {
             name: proto.name,
-            description: proto.description,
+            crypto_info: proto
+                .crypto_info
+                .ok_or_else(|| anyhow!("missing crypto_info"))?
+                .try_into()?,
         };
 
         Ok(ret)
     }
 }
 
-impl From<FunctionInput> for proto::FunctionInput {
-    fn from(input: FunctionInput) -> Self {
+impl From<RegisterCryptoKeyRequest> for proto::RegisterCryptoKeyRequest {
+    fn from(request: RegisterCryptoKeyRequest) -> Self {
         Self {
-            name: input.name,
-            description: input.description,
+            name: request.name,
+            crypto_info: Some(request.crypto_info.into()),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::FunctionOutput> for FunctionOutput {
+impl std::convert::TryFrom<proto::RegisterCryptoKeyResponse> for RegisterCryptoKeyResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::FunctionOutput) -> Result<Self> {
-        let ret = Self {
-            name: proto.name,
-            description: proto.description,
-        };
+    fn try_from(_proto: proto::RegisterCryptoKeyResponse) -> Result<Self> {
+        Ok(Self)
+    }
+}
 
-        Ok(ret)
+impl From<RegisterCryptoKeyResponse> for proto::RegisterCryptoKeyResponse {
+    fn from(_response: RegisterCryptoKeyResponse) -> Self {
+        Self {}
     }
 }
 
-impl From<FunctionOutput> for proto::FunctionOutput {
-    fn from(output: FunctionOutput) -> Self {
-        Self {
-            name: output.name,
-            description: output.description,
-        }
+impl std::convert::TryFrom<proto::CancelAllTasksRequest> for CancelAllTasksRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::CancelAllTasksRequest) -> Result<Self> {
+        Ok(CancelAllTasksRequest)
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterFunctionRequest> for RegisterFunctionRequest {
+impl From<CancelAllTasksRequest> for proto::CancelAllTasksRequest {
+    fn from(_request: CancelAllTasksRequest) -> Self {
+        Self {}
+    }
+}
+
+impl std::convert::TryFrom<proto::CancelAllTasksResponse> for CancelAllTasksResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterFunctionRequest) -> Result<Self> {
-        let inputs: Result<Vec<FunctionInput>> = proto
-            .inputs
+    fn try_from(proto: proto::CancelAllTasksResponse) -> Result<Self> {
+        let task_ids: Result<Vec<ExternalID>> = proto
+            .task_ids
             .into_iter()
-            .map(FunctionInput::try_from)
+            .map(|task_id| task_id.try_into())
             .collect();
-        let outputs: Result<Vec<FunctionOutput>> = proto
-            .outputs
+        let failed_task_ids: Result<Vec<ExternalID>> = proto
+            .failed_task_ids
             .into_iter()
-            .map(FunctionOutput::try_from)
+            .map(|task_id| task_id.try_into())
             .collect();
-        let executor_type = proto.executor_type.try_into()?;
-
         let ret = Self {
-            name: proto.name,
-            description: proto.description,
-            executor_type,
-            payload: proto.payload,
-            public: proto.public,
-            arguments: proto.arguments,
-            inputs: inputs?,
-            outputs: outputs?,
+            task_ids: task_ids?,
+            failed_task_ids: failed_task_ids?,
         };
+
         Ok(ret)
     }
 }
 
-impl From<RegisterFunctionRequest> for proto::RegisterFunctionRequest {
-    fn from(request: RegisterFunctionRequest) -> Self {
-        let inputs: Vec<proto::FunctionInput> = request
-            .inputs
-            .into_iter()
-            .map(proto::FunctionInput::from)
-            .collect();
-        let outputs: Vec<proto::FunctionOutput> = request
-            .outputs
-            .into_iter()
-            .map(proto::FunctionOutput::from)
-            .collect();
-
+impl From<CancelAllTasksResponse> for proto::CancelAllTasksResponse {
+    fn from(response: CancelAllTasksResponse) -> Self {
         Self {
-            name: request.name,
-            description: request.description,
-            executor_type: request.executor_type.into(),
-            payload: request.payload,
-            public: request.public,
-            arguments: request.arguments,
-            inputs,
-            outputs,
+            task_ids: response
+                .task_ids
+                .iter()
+                .map(|task_id| task_id.to_string())
+                .collect(),
+            failed_task_ids: response
+                .failed_task_ids
+                .iter()
+                .map(|task_id| task_id.to_string())
+                .collect(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::RegisterFunctionResponse> for RegisterFunctionResponse {
+impl std::convert::TryFrom<proto::CreateOutputAccessTokenRequest>
+    for CreateOutputAccessTokenRequest
+{
     type Error = Error;
 
-    fn try_from(proto: proto::RegisterFunctionResponse) -> Result<Self> {
-        let function_id = proto.function_id.try_into()?;
-        let ret = Self { function_id };
+    fn try_from(proto: proto::CreateOutputAccessTokenRequest) -> Result<Self> {
+        let data_id = proto.data_id.try_into()?;
+        Ok(Self { data_id })
+    }
+}
 
-        Ok(ret)
+impl From<CreateOutputAccessTokenRequest> for proto::CreateOutputAccessTokenRequest {
+    fn from(request: CreateOutputAccessTokenRequest) -> Self {
+        Self {
+            data_id: request.data_id.to_string(),
+        }
     }
 }
 
-impl From<RegisterFunctionResponse> for proto::RegisterFunctionResponse {
-    fn from(response: RegisterFunctionResponse) -> Self {
+impl std::convert::TryFrom<proto::CreateOutputAccessTokenResponse>
+    for CreateOutputAccessTokenResponse
+{
+    type Error = Error;
+
+    fn try_from(proto: proto::CreateOutputAccessTokenResponse) -> Result<Self> {
+        Ok(Self {
+            token: proto.token,
+            expires_at: proto.expires_at,
+        })
+    }
+}
+
+impl From<CreateOutputAccessTokenResponse> for proto::CreateOutputAccessTokenResponse {
+    fn from(response: CreateOutputAccessTokenResponse) -> Self {
         Self {
-            function_id: response.function_id.to_string(),
+            token: response.token,
+            expires_at: response.expires_at,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetFunctionRequest> for GetFunctionRequest {
+impl std::convert::TryFrom<proto::GetMetricsRequest> for GetMetricsRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::GetFunctionRequest) -> Result<Self> {
-        let function_id = proto.function_id.try_into()?;
-        let ret = Self { function_id };
+    fn try_from(_proto: proto::GetMetricsRequest) -> Result<Self> {
+        Ok(GetMetricsRequest)
+    }
+}
 
-        Ok(ret)
+impl From<GetMetricsRequest> for proto::GetMetricsRequest {
+    fn from(_request: GetMetricsRequest) -> Self {
+        Self {}
+    }
+}
+
+impl From<proto::StorageLatency> for StorageLatency {
+    fn from(proto: proto::StorageLatency) -> Self {
+        Self {
+            operation: proto.operation,
+            count: proto.count,
+            min_micros: proto.min_micros,
+            max_micros: proto.max_micros,
+            avg_micros: proto.avg_micros,
+        }
     }
 }
 
-impl From<GetFunctionRequest> for proto::GetFunctionRequest {
-    fn from(request: GetFunctionRequest) -> Self {
+impl From<StorageLatency> for proto::StorageLatency {
+    fn from(latency: StorageLatency) -> Self {
         Self {
-            function_id: request.function_id.to_string(),
+            operation: latency.operation,
+            count: latency.count,
+            min_micros: latency.min_micros,
+            max_micros: latency.max_micros,
+            avg_micros: latency.avg_micros,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetFunctionResponse> for GetFunctionResponse {
+impl std::convert::TryFrom<proto::GetMetricsResponse> for GetMetricsResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::GetFunctionResponse) -> Result<Self> {
-        let inputs: Result<Vec<FunctionInput>> = proto
-            .inputs
-            .into_iter()
-            .map(FunctionInput::try_from)
-            .collect();
-        let outputs: Result<Vec<FunctionOutput>> = proto
-            .outputs
-            .into_iter()
-            .map(FunctionOutput::try_from)
-            .collect();
-        let executor_type = proto.executor_type.try_into()?;
-
-        let ret = Self {
-            name: proto.name,
-            description: proto.description,
-            owner: proto.owner.into(),
-            executor_type,
-            payload: proto.payload,
-            public: proto.public,
-            arguments: proto.arguments,
-            inputs: inputs?,
-            outputs: outputs?,
-        };
-
-        Ok(ret)
+    fn try_from(proto: proto::GetMetricsResponse) -> Result<Self> {
+        Ok(Self {
+            storage_latencies: proto
+                .storage_latencies
+                .into_iter()
+                .map(StorageLatency::from)
+                .collect(),
+            storage_degraded: proto.storage_degraded,
+            pending_storage_writes: proto.pending_storage_writes,
+        })
     }
 }
 
-impl From<GetFunctionResponse> for proto::GetFunctionResponse {
-    fn from(response: GetFunctionResponse) -> Self {
-        let inputs: Vec<proto::FunctionInput> = response
-            .inputs
-            .into_iter()
-            .map(proto::FunctionInput::from)
-            .collect();
-        let outputs: Vec<proto::FunctionOutput> = response
-            .outputs
-            .into_iter()
-            .map(proto::FunctionOutput::from)
-            .collect();
-
+impl From<GetMetricsResponse> for proto::GetMetricsResponse {
+    fn from(response: GetMetricsResponse) -> Self {
         Self {
-            name: response.name,
-            description: response.description,
-            owner: response.owner.into(),
-            executor_type: response.executor_type.into(),
-            payload: response.payload,
-            public: response.public,
-            arguments: response.arguments,
-            inputs,
-            outputs,
+            storage_latencies: response
+                .storage_latencies
+                .into_iter()
+                .map(proto::StorageLatency::from)
+                .collect(),
+            storage_degraded: response.storage_degraded,
+            pending_storage_writes: response.pending_storage_writes,
         }
     }
 }
 
-fn from_proto_ownership(proto: Vec<proto::OwnerList>) -> TaskFileOwners {
-    proto
-        .into_iter()
-        .map(|ol| (ol.data_name, ol.uids))
-        .collect()
+impl std::convert::TryFrom<proto::ExportCatalogRequest> for ExportCatalogRequest {
+    type Error = Error;
+
+    fn try_from(_proto: proto::ExportCatalogRequest) -> Result<Self> {
+        Ok(ExportCatalogRequest)
+    }
 }
 
-fn to_proto_ownership(ownership: TaskFileOwners) -> Vec<proto::OwnerList> {
-    ownership
-        .into_iter()
-        .map(|(name, ol)| proto::OwnerList {
-            data_name: name,
-            uids: ol.into(),
-        })
-        .collect()
+impl From<ExportCatalogRequest> for proto::ExportCatalogRequest {
+    fn from(_request: ExportCatalogRequest) -> Self {
+        Self {}
+    }
 }
 
-impl std::convert::TryFrom<proto::CreateTaskRequest> for CreateTaskRequest {
+impl std::convert::TryFrom<proto::ExportCatalogResponse> for ExportCatalogResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::CreateTaskRequest) -> Result<Self> {
-        let function_arguments = proto.function_arguments.into();
-        let inputs_ownership = from_proto_ownership(proto.inputs_ownership);
-        let outputs_ownership = from_proto_ownership(proto.outputs_ownership);
-        let function_id = proto.function_id.try_into()?;
-        let executor = proto.executor.try_into()?;
-
-        let ret = Self {
-            function_id,
-            function_arguments,
-            executor,
-            inputs_ownership,
-            outputs_ownership,
-        };
-        Ok(ret)
+    fn try_from(proto: proto::ExportCatalogResponse) -> Result<Self> {
+        Ok(Self {
+            catalog: proto.catalog,
+        })
     }
 }
 
-impl From<CreateTaskRequest> for proto::CreateTaskRequest {
-    fn from(request: CreateTaskRequest) -> Self {
-        let function_arguments = request.function_arguments.into();
-        let inputs_ownership = to_proto_ownership(request.inputs_ownership);
-        let outputs_ownership = to_proto_ownership(request.outputs_ownership);
-
+impl From<ExportCatalogResponse> for proto::ExportCatalogResponse {
+    fn from(response: ExportCatalogResponse) -> Self {
         Self {
-            function_id: request.function_id.to_string(),
-            function_arguments,
-            executor: request.executor.to_string(),
-            inputs_ownership,
-            outputs_ownership,
+            catalog: response.catalog,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::CreateTaskResponse> for CreateTaskResponse {
+impl std::convert::TryFrom<proto::ImportCatalogRequest> for ImportCatalogRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::CreateTaskResponse) -> Result<Self> {
-        let task_id = proto.task_id.try_into()?;
-        let ret = Self { task_id };
-
-        Ok(ret)
+    fn try_from(proto: proto::ImportCatalogRequest) -> Result<Self> {
+        Ok(Self {
+            catalog: proto.catalog,
+            payloads: proto.payloads,
+        })
     }
 }
 
-impl From<CreateTaskResponse> for proto::CreateTaskResponse {
-    fn from(response: CreateTaskResponse) -> Self {
+impl From<ImportCatalogRequest> for proto::ImportCatalogRequest {
+    fn from(request: ImportCatalogRequest) -> Self {
         Self {
-            task_id: response.task_id.to_string(),
+            catalog: request.catalog,
+            payloads: request.payloads,
         }
     }
 }
 
-fn to_proto_file_ids(map: HashMap<String, ExternalID>) -> Vec<proto::DataMap> {
-    map.into_iter()
-        .map(|(name, ext_id)| proto::DataMap {
-            data_name: name,
-            data_id: ext_id.to_string(),
-        })
-        .collect()
+impl std::convert::TryFrom<proto::ImportCatalogResponse> for ImportCatalogResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::ImportCatalogResponse) -> Result<Self> {
+        let function_ids = proto
+            .function_ids
+            .into_iter()
+            .map(|(exported_id, function_id)| Ok((exported_id, function_id.try_into()?)))
+            .collect::<Result<HashMap<String, ExternalID>>>()?;
+        Ok(Self { function_ids })
+    }
 }
 
-fn from_proto_file_ids(vector: Vec<proto::DataMap>) -> Result<HashMap<String, ExternalID>> {
-    vector
-        .into_iter()
-        .map(|item| {
-            item.data_id
-                .clone()
-                .try_into()
-                .map(|ext_id| (item.data_name, ext_id))
-        })
-        .collect()
+impl From<ImportCatalogResponse> for proto::ImportCatalogResponse {
+    fn from(response: ImportCatalogResponse) -> Self {
+        Self {
+            function_ids: response
+                .function_ids
+                .into_iter()
+                .map(|(exported_id, function_id)| (exported_id, function_id.to_string()))
+                .collect(),
+        }
+    }
 }
 
-impl std::convert::TryFrom<proto::GetTaskRequest> for GetTaskRequest {
+impl std::convert::TryFrom<proto::ExportAuditLogRequest> for ExportAuditLogRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::GetTaskRequest) -> Result<Self> {
-        let task_id = proto.task_id.try_into()?;
-        let ret = Self { task_id };
+    fn try_from(proto: proto::ExportAuditLogRequest) -> Result<Self> {
+        let ret = Self {
+            start_time: proto.start_time,
+            end_time: proto.end_time,
+            offset: proto.offset,
+            limit: proto.limit as usize,
+        };
 
         Ok(ret)
     }
 }
 
-impl From<GetTaskRequest> for proto::GetTaskRequest {
-    fn from(request: GetTaskRequest) -> Self {
+impl From<ExportAuditLogRequest> for proto::ExportAuditLogRequest {
+    fn from(request: ExportAuditLogRequest) -> Self {
         Self {
-            task_id: request.task_id.to_string(),
+            start_time: request.start_time,
+            end_time: request.end_time,
+            offset: request.offset,
+            limit: request.limit as u32,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::GetTaskResponse> for GetTaskResponse {
+impl std::convert::TryFrom<proto::ExportAuditLogResponse> for ExportAuditLogResponse {
     type Error = Error;
 
-    fn try_from(proto: proto::GetTaskResponse) -> Result<Self> {
-        let function_arguments = proto.function_arguments.into();
-        let inputs_ownership = from_proto_ownership(proto.inputs_ownership);
-        let outputs_ownership = from_proto_ownership(proto.outputs_ownership);
-        let assigned_inputs = from_proto_file_ids(proto.assigned_inputs)?;
-        let assigned_outputs = from_proto_file_ids(proto.assigned_outputs)?;
-        let status = i32_to_task_status(proto.status)?;
-        let function_id = proto.function_id.try_into()?;
-        let task_id = proto.task_id.try_into()?;
-        let result = proto.result.try_into()?;
-
+    fn try_from(proto: proto::ExportAuditLogResponse) -> Result<Self> {
         let ret = Self {
-            task_id,
-            creator: proto.creator.into(),
-            function_id,
-            function_owner: proto.function_owner.into(),
-            function_arguments,
-            inputs_ownership,
-            outputs_ownership,
-            participants: UserList::new(proto.participants),
-            approved_users: UserList::new(proto.approved_users),
-            assigned_inputs,
-            assigned_outputs,
-            status,
-            result,
+            entries: proto.entries,
+            next_offset: proto.next_offset,
+            checksum: proto.checksum,
         };
 
         Ok(ret)
     }
 }
 
-impl From<GetTaskResponse> for proto::GetTaskResponse {
-    fn from(response: GetTaskResponse) -> Self {
-        let function_arguments = response.function_arguments.into();
-        let inputs_ownership = to_proto_ownership(response.inputs_ownership);
-        let outputs_ownership = to_proto_ownership(response.outputs_ownership);
-        let assigned_inputs = to_proto_file_ids(response.assigned_inputs);
-        let assigned_outputs = to_proto_file_ids(response.assigned_outputs);
-        let status = i32_from_task_status(response.status);
+impl From<ExportAuditLogResponse> for proto::ExportAuditLogResponse {
+    fn from(response: ExportAuditLogResponse) -> Self {
         Self {
-            task_id: response.task_id.to_string(),
-            creator: response.creator.to_string(),
-            function_id: response.function_id.to_string(),
-            function_owner: response.function_owner.to_string(),
-            function_arguments,
-            inputs_ownership,
-            outputs_ownership,
-            participants: response.participants.into(),
-            approved_users: response.approved_users.into(),
-            assigned_inputs,
-            assigned_outputs,
-            status,
-            result: Some(response.result.into()),
+            entries: response.entries,
+            next_offset: response.next_offset,
+            checksum: response.checksum,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::AssignDataRequest> for AssignDataRequest {
+impl std::convert::TryFrom<proto::SetResultCallbackRequest> for SetResultCallbackRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::AssignDataRequest) -> Result<Self> {
-        let inputs = from_proto_file_ids(proto.inputs)?;
-        let outputs = from_proto_file_ids(proto.outputs)?;
-        let task_id = proto.task_id.try_into()?;
-        let ret = Self {
-            task_id,
-            inputs,
-            outputs,
+    fn try_from(proto: proto::SetResultCallbackRequest) -> Result<Self> {
+        let url = if proto.url.is_empty() {
+            None
+        } else {
+            Some(Url::parse(&proto.url)?)
         };
-
-        Ok(ret)
+        Ok(Self {
+            url,
+            recipient_public_key: proto.recipient_public_key,
+        })
     }
 }
 
-impl From<AssignDataRequest> for proto::AssignDataRequest {
-    fn from(request: AssignDataRequest) -> Self {
-        let inputs = to_proto_file_ids(request.inputs);
-        let outputs = to_proto_file_ids(request.outputs);
+impl From<SetResultCallbackRequest> for proto::SetResultCallbackRequest {
+    fn from(request: SetResultCallbackRequest) -> Self {
         Self {
-            task_id: request.task_id.to_string(),
-            inputs,
-            outputs,
+            url: request.url.map(|url| url.to_string()).unwrap_or_default(),
+            recipient_public_key: request.recipient_public_key,
         }
     }
 }
 
-impl std::convert::TryFrom<proto::AssignDataResponse> for AssignDataResponse {
+impl std::convert::TryFrom<proto::SetResultCallbackResponse> for SetResultCallbackResponse {
     type Error = Error;
 
-    fn try_from(_proto: proto::AssignDataResponse) -> Result<Self> {
-        Ok(AssignDataResponse)
+    fn try_from(proto: proto::SetResultCallbackResponse) -> Result<Self> {
+        Ok(Self {
+            secret: proto.secret,
+        })
     }
 }
 
-impl From<AssignDataResponse> for proto::AssignDataResponse {
-    fn from(_response: AssignDataResponse) -> Self {
-        Self {}
+impl From<SetResultCallbackResponse> for proto::SetResultCallbackResponse {
+    fn from(response: SetResultCallbackResponse) -> Self {
+        Self {
+            secret: response.secret,
+        }
     }
 }
 
-impl std::convert::TryFrom<proto::ApproveTaskRequest> for ApproveTaskRequest {
+impl std::convert::TryFrom<proto::ReproduceTaskRequest> for ReproduceTaskRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::ApproveTaskRequest) -> Result<Self> {
+    fn try_from(proto: proto::ReproduceTaskRequest) -> Result<Self> {
         let task_id = proto.task_id.try_into()?;
-        let ret = Self { task_id };
-
-        Ok(ret)
+        let output_urls = proto
+            .output_urls
+            .into_iter()
+            .map(|(name, url)| Ok((name, Url::parse(&url)?)))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            task_id,
+            output_urls,
+        })
     }
 }
 
-impl From<ApproveTaskRequest> for proto::ApproveTaskRequest {
-    fn from(request: ApproveTaskRequest) -> Self {
+impl From<ReproduceTaskRequest> for proto::ReproduceTaskRequest {
+    fn from(request: ReproduceTaskRequest) -> Self {
         Self {
             task_id: request.task_id.to_string(),
+            output_urls: request
+                .output_urls
+                .into_iter()
+                .map(|(name, url)| (name, url.to_string()))
+                .collect(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::ApproveTaskResponse> for ApproveTaskResponse {
+impl std::convert::TryFrom<proto::ReproduceTaskResponse> for ReproduceTaskResponse {
     type Error = Error;
 
-    fn try_from(_proto: proto::ApproveTaskResponse) -> Result<Self> {
-        Ok(ApproveTaskResponse)
+    fn try_from(proto: proto::ReproduceTaskResponse) -> Result<Self> {
+        Ok(Self {
+            reproduction_id: proto.reproduction_id.try_into()?,
+        })
     }
 }
 
-impl From<ApproveTaskResponse> for proto::ApproveTaskResponse {
-    fn from(_response: ApproveTaskResponse) -> Self {
-        Self {}
+impl From<ReproduceTaskResponse> for proto::ReproduceTaskResponse {
+    fn from(response: ReproduceTaskResponse) -> Self {
+        Self {
+            reproduction_id: response.reproduction_id.to_string(),
+        }
     }
 }
 
-impl std::convert::TryFrom<proto::InvokeTaskRequest> for InvokeTaskRequest {
+impl std::convert::TryFrom<proto::GetTaskReproductionRequest> for GetTaskReproductionRequest {
     type Error = Error;
 
-    fn try_from(proto: proto::InvokeTaskRequest) -> Result<Self> {
-        let task_id = proto.task_id.try_into()?;
-        let ret = Self { task_id };
-
-        Ok(ret)
+    fn try_from(proto: proto::GetTaskReproductionRequest) -> Result<Self> {
+        Ok(Self {
+            reproduction_id: proto.reproduction_id.try_into()?,
+        })
     }
 }
 
-impl From<InvokeTaskRequest> for proto::InvokeTaskRequest {
-    fn from(request: InvokeTaskRequest) -> Self {
+impl From<GetTaskReproductionRequest> for proto::GetTaskReproductionRequest {
+    fn from(request: GetTaskReproductionRequest) -> Self {
         Self {
-            task_id: request.task_id.to_string(),
+            reproduction_id: request.reproduction_id.to_string(),
         }
     }
 }
 
-impl std::convert::TryFrom<proto::InvokeTaskResponse> for InvokeTaskResponse {
+impl std::convert::TryFrom<proto::GetTaskReproductionResponse> for GetTaskReproductionResponse {
     type Error = Error;
 
-    fn try_from(_proto: proto::InvokeTaskResponse) -> Result<Self> {
-        Ok(InvokeTaskResponse)
+    fn try_from(proto: proto::GetTaskReproductionResponse) -> Result<Self> {
+        let status = i32_to_task_status(proto.status)?;
+        let finished = status == TaskStatus::Finished;
+        let failure = if proto.failure.is_empty() {
+            None
+        } else {
+            Some(proto.failure)
+        };
+        let matches = if finished && failure.is_none() {
+            Some(proto.matches)
+        } else {
+            None
+        };
+        Ok(Self {
+            task_id: proto.task_id.try_into()?,
+            status,
+            matches,
+            failure,
+        })
     }
 }
 
-impl From<InvokeTaskResponse> for proto::InvokeTaskResponse {
-    fn from(_response: InvokeTaskResponse) -> Self {
-        Self {}
+impl From<GetTaskReproductionResponse> for proto::GetTaskReproductionResponse {
+    fn from(response: GetTaskReproductionResponse) -> Self {
+        Self {
+            task_id: response.task_id.to_string(),
+            status: i32_from_task_status(response.status),
+            matches: response.matches.unwrap_or_default(),
+            failure: response.failure.unwrap_or_default(),
+        }
     }
 }
@@ -36,12 +36,16 @@ pub type RegisterInputFromOutputResponse =
     crate::teaclave_frontend_service::RegisterInputFromOutputResponse;
 pub type GetInputFileRequest = crate::teaclave_frontend_service::GetInputFileRequest;
 pub type GetInputFileResponse = crate::teaclave_frontend_service::GetInputFileResponse;
+pub type DeleteInputFileRequest = crate::teaclave_frontend_service::DeleteInputFileRequest;
+pub type DeleteInputFileResponse = crate::teaclave_frontend_service::DeleteInputFileResponse;
 pub type GetOutputFileRequest = crate::teaclave_frontend_service::GetOutputFileRequest;
 pub type GetOutputFileResponse = crate::teaclave_frontend_service::GetOutputFileResponse;
 pub type RegisterFunctionRequest = crate::teaclave_frontend_service::RegisterFunctionRequest;
 pub type RegisterFunctionResponse = crate::teaclave_frontend_service::RegisterFunctionResponse;
 pub type GetFunctionRequest = crate::teaclave_frontend_service::GetFunctionRequest;
 pub type GetFunctionResponse = crate::teaclave_frontend_service::GetFunctionResponse;
+pub type DeprecateFunctionRequest = crate::teaclave_frontend_service::DeprecateFunctionRequest;
+pub type DeprecateFunctionResponse = crate::teaclave_frontend_service::DeprecateFunctionResponse;
 pub type CreateTaskRequest = crate::teaclave_frontend_service::CreateTaskRequest;
 pub type CreateTaskResponse = crate::teaclave_frontend_service::CreateTaskResponse;
 pub type GetTaskRequest = crate::teaclave_frontend_service::GetTaskRequest;
@@ -52,3 +56,85 @@ pub type ApproveTaskRequest = crate::teaclave_frontend_service::ApproveTaskReque
 pub type ApproveTaskResponse = crate::teaclave_frontend_service::ApproveTaskResponse;
 pub type InvokeTaskRequest = crate::teaclave_frontend_service::InvokeTaskRequest;
 pub type InvokeTaskResponse = crate::teaclave_frontend_service::InvokeTaskResponse;
+pub type AdminGetTaskRequest = crate::teaclave_frontend_service::AdminGetTaskRequest;
+pub type AdminGetTaskResponse = crate::teaclave_frontend_service::AdminGetTaskResponse;
+pub type RunSelfTestRequest = crate::teaclave_frontend_service::RunSelfTestRequest;
+pub type RunSelfTestResponse = crate::teaclave_frontend_service::RunSelfTestResponse;
+pub type SelfTestStage = crate::teaclave_frontend_service::SelfTestStage;
+pub type LinkTasksRequest = crate::teaclave_frontend_service::LinkTasksRequest;
+pub type LinkTasksResponse = crate::teaclave_frontend_service::LinkTasksResponse;
+pub type FileEntry = crate::teaclave_frontend_service::FileEntry;
+pub type ListInputFilesRequest = crate::teaclave_frontend_service::ListInputFilesRequest;
+pub type ListInputFilesResponse = crate::teaclave_frontend_service::ListInputFilesResponse;
+pub type ListOutputFilesRequest = crate::teaclave_frontend_service::ListOutputFilesRequest;
+pub type ListOutputFilesResponse = crate::teaclave_frontend_service::ListOutputFilesResponse;
+pub type RegisterTaskTemplateRequest =
+    crate::teaclave_frontend_service::RegisterTaskTemplateRequest;
+pub type RegisterTaskTemplateResponse =
+    crate::teaclave_frontend_service::RegisterTaskTemplateResponse;
+pub type InstantiateTemplateRequest = crate::teaclave_frontend_service::InstantiateTemplateRequest;
+pub type InstantiateTemplateResponse =
+    crate::teaclave_frontend_service::InstantiateTemplateResponse;
+pub type GetTasksRequest = crate::teaclave_frontend_service::GetTasksRequest;
+pub type GetTasksResponse = crate::teaclave_frontend_service::GetTasksResponse;
+pub type CancelTaskRequest = crate::teaclave_frontend_service::CancelTaskRequest;
+pub type CancelTaskResponse = crate::teaclave_frontend_service::CancelTaskResponse;
+pub type ListTasksRequest = crate::teaclave_frontend_service::ListTasksRequest;
+pub type ListTasksResponse = crate::teaclave_frontend_service::ListTasksResponse;
+pub type ExportTaskRequest = crate::teaclave_frontend_service::ExportTaskRequest;
+pub type ExportTaskResponse = crate::teaclave_frontend_service::ExportTaskResponse;
+pub type ImportTaskRequest = crate::teaclave_frontend_service::ImportTaskRequest;
+pub type ImportTaskResponse = crate::teaclave_frontend_service::ImportTaskResponse;
+pub type GenerateOutputPresignedUrlRequest =
+    crate::teaclave_frontend_service::GenerateOutputPresignedUrlRequest;
+pub type GenerateOutputPresignedUrlResponse =
+    crate::teaclave_frontend_service::GenerateOutputPresignedUrlResponse;
+pub type EstimateTaskRequest = crate::teaclave_frontend_service::EstimateTaskRequest;
+pub type EstimateTaskResponse = crate::teaclave_frontend_service::EstimateTaskResponse;
+pub type ListFunctionsRequest = crate::teaclave_frontend_service::ListFunctionsRequest;
+pub type ListFunctionsResponse = crate::teaclave_frontend_service::ListFunctionsResponse;
+pub type RetryTaskRequest = crate::teaclave_frontend_service::RetryTaskRequest;
+pub type RetryTaskResponse = crate::teaclave_frontend_service::RetryTaskResponse;
+pub type RegisterInputFromTaskLogRequest =
+    crate::teaclave_frontend_service::RegisterInputFromTaskLogRequest;
+pub type RegisterInputFromTaskLogResponse =
+    crate::teaclave_frontend_service::RegisterInputFromTaskLogResponse;
+pub type SubmitTaskGraphRequest = crate::teaclave_frontend_service::SubmitTaskGraphRequest;
+pub type SubmitTaskGraphResponse = crate::teaclave_frontend_service::SubmitTaskGraphResponse;
+pub type SubscribeEventsRequest = crate::teaclave_frontend_service::SubscribeEventsRequest;
+pub type SubscribeEventsResponse = crate::teaclave_frontend_service::SubscribeEventsResponse;
+pub type SoftDeleteFunctionRequest = crate::teaclave_frontend_service::SoftDeleteFunctionRequest;
+pub type SoftDeleteFunctionResponse = crate::teaclave_frontend_service::SoftDeleteFunctionResponse;
+pub type RestoreFunctionRequest = crate::teaclave_frontend_service::RestoreFunctionRequest;
+pub type RestoreFunctionResponse = crate::teaclave_frontend_service::RestoreFunctionResponse;
+pub type RegisterFunctionBundleRequest =
+    crate::teaclave_frontend_service::RegisterFunctionBundleRequest;
+pub type RegisterFunctionBundleResponse =
+    crate::teaclave_frontend_service::RegisterFunctionBundleResponse;
+pub type RegisterCryptoKeyRequest = crate::teaclave_frontend_service::RegisterCryptoKeyRequest;
+pub type RegisterCryptoKeyResponse = crate::teaclave_frontend_service::RegisterCryptoKeyResponse;
+pub type CancelAllTasksRequest = crate::teaclave_frontend_service::CancelAllTasksRequest;
+pub type CancelAllTasksResponse = crate::teaclave_frontend_service::CancelAllTasksResponse;
+pub type CreateOutputAccessTokenRequest =
+    crate::teaclave_frontend_service::CreateOutputAccessTokenRequest;
+pub type CreateOutputAccessTokenResponse =
+    crate::teaclave_frontend_service::CreateOutputAccessTokenResponse;
+pub type GetMetricsRequest = crate::teaclave_frontend_service::GetMetricsRequest;
+pub type GetMetricsResponse = crate::teaclave_frontend_service::GetMetricsResponse;
+pub type ExportCatalogRequest = crate::teaclave_frontend_service::ExportCatalogRequest;
+pub type ExportCatalogResponse = crate::teaclave_frontend_service::ExportCatalogResponse;
+pub type ImportCatalogRequest = crate::teaclave_frontend_service::ImportCatalogRequest;
+pub type ImportCatalogResponse = crate::teaclave_frontend_service::ImportCatalogResponse;
+pub type SetFunctionRateLimitRequest =
+    crate::teaclave_frontend_service::SetFunctionRateLimitRequest;
+pub type SetFunctionRateLimitResponse =
+    crate::teaclave_frontend_service::SetFunctionRateLimitResponse;
+pub type ExportAuditLogRequest = crate::teaclave_frontend_service::ExportAuditLogRequest;
+pub type ExportAuditLogResponse = crate::teaclave_frontend_service::ExportAuditLogResponse;
+pub type SetResultCallbackRequest = crate::teaclave_frontend_service::SetResultCallbackRequest;
+pub type SetResultCallbackResponse = crate::teaclave_frontend_service::SetResultCallbackResponse;
+pub type ReproduceTaskRequest = crate::teaclave_frontend_service::ReproduceTaskRequest;
+pub type ReproduceTaskResponse = crate::teaclave_frontend_service::ReproduceTaskResponse;
+pub type GetTaskReproductionRequest = crate::teaclave_frontend_service::GetTaskReproductionRequest;
+pub type GetTaskReproductionResponse =
+    crate::teaclave_frontend_service::GetTaskReproductionResponse;
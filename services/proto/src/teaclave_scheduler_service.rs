@@ -30,7 +30,7 @@ pub use proto::TeaclaveSchedulerClient;
 pub use proto::TeaclaveSchedulerRequest;
 pub use proto::TeaclaveSchedulerResponse;
 use teaclave_rpc::into_request;
-use teaclave_types::{StagedTask, TaskFailure, TaskOutputs, TaskResult, TaskStatus};
+use teaclave_types::{FileAuthTag, StagedTask, TaskFailure, TaskOutputs, TaskResult, TaskStatus};
 use uuid::Uuid;
 
 #[into_request(TeaclaveSchedulerRequest::Subscribe)]
@@ -60,6 +60,7 @@ impl PullTaskResponse {
 pub struct UpdateTaskResultRequest {
     pub task_id: Uuid,
     pub task_result: TaskResult,
+    pub log: Vec<String>,
 }
 
 impl UpdateTaskResultRequest {
@@ -73,13 +74,38 @@ impl UpdateTaskResultRequest {
         Self {
             task_id,
             task_result: result,
+            log: Vec::new(),
         }
     }
+
+    pub fn log(self, log: Vec<String>) -> Self {
+        Self { log, ..self }
+    }
 }
 
 #[into_request(TeaclaveSchedulerResponse::UpdateTaskResult)]
 pub struct UpdateTaskResultResponse {}
 
+#[into_request(TeaclaveSchedulerRequest::UpdatePartialOutput)]
+pub struct UpdatePartialOutputRequest {
+    pub task_id: Uuid,
+    pub output_name: String,
+    pub cmac: FileAuthTag,
+}
+
+impl UpdatePartialOutputRequest {
+    pub fn new(task_id: Uuid, output_name: impl Into<String>, cmac: FileAuthTag) -> Self {
+        Self {
+            task_id,
+            output_name: output_name.into(),
+            cmac,
+        }
+    }
+}
+
+#[into_request(TeaclaveSchedulerResponse::UpdatePartialOutput)]
+pub struct UpdatePartialOutputResponse {}
+
 #[into_request(TeaclaveSchedulerRequest::UpdateTaskStatus)]
 pub struct UpdateTaskStatusRequest {
     pub task_id: Uuid,
@@ -97,6 +123,22 @@ impl UpdateTaskStatusRequest {
 #[into_request(TeaclaveSchedulerResponse::UpdateTaskStatus)]
 pub struct UpdateTaskStatusResponse {}
 
+#[into_request(TeaclaveSchedulerRequest::CheckTaskCancellation)]
+pub struct CheckTaskCancellationRequest {
+    pub task_id: Uuid,
+}
+
+impl CheckTaskCancellationRequest {
+    pub fn new(task_id: Uuid) -> Self {
+        Self { task_id }
+    }
+}
+
+#[into_request(TeaclaveSchedulerResponse::CheckTaskCancellation)]
+pub struct CheckTaskCancellationResponse {
+    pub cancel_requested: bool,
+}
+
 #[into_request(TeaclaveSchedulerRequest::PublishTask)]
 pub struct PublishTaskRequest {
     pub staged_task: StagedTask,
@@ -174,6 +216,7 @@ impl std::convert::TryFrom<proto::UpdateTaskResultRequest> for UpdateTaskResultR
         let ret = Self {
             task_id: Uuid::parse_str(&proto.task_id)?,
             task_result: proto.result.try_into()?,
+            log: proto.log,
         };
         Ok(ret)
     }
@@ -184,6 +227,7 @@ impl std::convert::From<UpdateTaskResultRequest> for proto::UpdateTaskResultRequ
         proto::UpdateTaskResultRequest {
             task_id: req.task_id.to_string(),
             result: Some(req.task_result.into()),
+            log: req.log,
         }
     }
 }
@@ -202,6 +246,42 @@ impl std::convert::From<UpdateTaskResultResponse> for proto::UpdateTaskResultRes
     }
 }
 
+impl std::convert::TryFrom<proto::UpdatePartialOutputRequest> for UpdatePartialOutputRequest {
+    type Error = Error;
+    fn try_from(proto: proto::UpdatePartialOutputRequest) -> Result<Self> {
+        let ret = Self {
+            task_id: Uuid::parse_str(&proto.task_id)?,
+            output_name: proto.output_name,
+            cmac: FileAuthTag::from_hex(&proto.cmac)?,
+        };
+        Ok(ret)
+    }
+}
+
+impl std::convert::From<UpdatePartialOutputRequest> for proto::UpdatePartialOutputRequest {
+    fn from(req: UpdatePartialOutputRequest) -> Self {
+        proto::UpdatePartialOutputRequest {
+            task_id: req.task_id.to_string(),
+            output_name: req.output_name,
+            cmac: req.cmac.to_hex(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::UpdatePartialOutputResponse> for UpdatePartialOutputResponse {
+    type Error = Error;
+    fn try_from(proto: proto::UpdatePartialOutputResponse) -> Result<Self> {
+        let ret = Self {};
+        Ok(ret)
+    }
+}
+
+impl std::convert::From<UpdatePartialOutputResponse> for proto::UpdatePartialOutputResponse {
+    fn from(req: UpdatePartialOutputResponse) -> Self {
+        proto::UpdatePartialOutputResponse {}
+    }
+}
+
 impl std::convert::TryFrom<proto::UpdateTaskStatusRequest> for UpdateTaskStatusRequest {
     type Error = Error;
     fn try_from(proto: proto::UpdateTaskStatusRequest) -> Result<Self> {
@@ -238,6 +318,42 @@ impl std::convert::From<UpdateTaskStatusResponse> for proto::UpdateTaskStatusRes
     }
 }
 
+impl std::convert::TryFrom<proto::CheckTaskCancellationRequest> for CheckTaskCancellationRequest {
+    type Error = Error;
+    fn try_from(proto: proto::CheckTaskCancellationRequest) -> Result<Self> {
+        let ret = Self {
+            task_id: Uuid::parse_str(&proto.task_id)?,
+        };
+        Ok(ret)
+    }
+}
+
+impl std::convert::From<CheckTaskCancellationRequest> for proto::CheckTaskCancellationRequest {
+    fn from(req: CheckTaskCancellationRequest) -> Self {
+        proto::CheckTaskCancellationRequest {
+            task_id: req.task_id.to_string(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<proto::CheckTaskCancellationResponse> for CheckTaskCancellationResponse {
+    type Error = Error;
+    fn try_from(proto: proto::CheckTaskCancellationResponse) -> Result<Self> {
+        let ret = Self {
+            cancel_requested: proto.cancel_requested,
+        };
+        Ok(ret)
+    }
+}
+
+impl std::convert::From<CheckTaskCancellationResponse> for proto::CheckTaskCancellationResponse {
+    fn from(req: CheckTaskCancellationResponse) -> Self {
+        proto::CheckTaskCancellationResponse {
+            cancel_requested: req.cancel_requested,
+        }
+    }
+}
+
 use teaclave_types::Storable;
 impl std::convert::TryFrom<proto::PublishTaskRequest> for PublishTaskRequest {
     type Error = Error;
@@ -51,6 +51,32 @@ impl GetResponse {
     }
 }
 
+#[into_request(TeaclaveStorageRequest::GetMulti)]
+#[derive(Debug)]
+pub struct GetMultiRequest {
+    pub keys: Vec<Vec<u8>>,
+}
+
+impl GetMultiRequest {
+    pub fn new(keys: Vec<Vec<u8>>) -> Self {
+        Self { keys }
+    }
+}
+
+// Values are in the order of the requested keys, and a missing key has no
+// value.
+#[into_request(TeaclaveStorageResponse::GetMulti)]
+#[derive(Debug)]
+pub struct GetMultiResponse {
+    pub values: Vec<Option<Vec<u8>>>,
+}
+
+impl GetMultiResponse {
+    pub fn new(values: Vec<Option<Vec<u8>>>) -> Self {
+        Self { values }
+    }
+}
+
 #[into_request(TeaclaveStorageRequest::Put)]
 #[derive(Debug)]
 pub struct PutRequest {
@@ -167,6 +193,51 @@ impl From<GetResponse> for proto::GetResponse {
     }
 }
 
+impl std::convert::TryFrom<proto::GetMultiRequest> for GetMultiRequest {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetMultiRequest) -> Result<Self> {
+        let ret = Self { keys: proto.keys };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetMultiRequest> for proto::GetMultiRequest {
+    fn from(request: GetMultiRequest) -> Self {
+        Self { keys: request.keys }
+    }
+}
+
+impl std::convert::TryFrom<proto::GetMultiResponse> for GetMultiResponse {
+    type Error = Error;
+
+    fn try_from(proto: proto::GetMultiResponse) -> Result<Self> {
+        let values = proto
+            .values
+            .into_iter()
+            .map(|value| if value.found { Some(value.value) } else { None })
+            .collect();
+        let ret = Self { values };
+
+        Ok(ret)
+    }
+}
+
+impl From<GetMultiResponse> for proto::GetMultiResponse {
+    fn from(response: GetMultiResponse) -> Self {
+        let values = response
+            .values
+            .into_iter()
+            .map(|value| proto::GetMultiValue {
+                found: value.is_some(),
+                value: value.unwrap_or_default(),
+            })
+            .collect();
+        Self { values }
+    }
+}
+
 impl std::convert::TryFrom<proto::PutRequest> for PutRequest {
     type Error = Error;
 
@@ -22,6 +22,7 @@ extern crate sgx_tstd as std;
 
 #[cfg(feature = "mesalock_sgx")]
 use std::prelude::v1::*;
+use std::time::Duration;
 
 #[macro_use]
 extern crate log;
@@ -30,7 +31,7 @@ use anyhow::{anyhow, Result};
 mod publisher;
 mod service;
 
-use teaclave_attestation::{verifier, AttestationConfig, RemoteAttestation};
+use teaclave_attestation::{verifier, RemoteAttestation};
 use teaclave_binder::proto::{
     ECallCommand, FinalizeEnclaveInput, FinalizeEnclaveOutput, InitEnclaveInput, InitEnclaveOutput,
     StartServiceInput, StartServiceOutput,
@@ -48,15 +49,15 @@ use teaclave_service_enclave_utils::ServiceEnclave;
 use teaclave_types::{EnclaveInfo, TeeServiceError, TeeServiceResult};
 
 fn start_service(config: &RuntimeConfig) -> Result<()> {
+    let root_ca_certs = config.attestation.root_ca_certs(AS_ROOT_CA_CERT);
     let listen_address = config.internal_endpoints.scheduler.listen_address;
-    let attestation_config = AttestationConfig::from_teaclave_config(&config)?;
-    let attested_tls_config = RemoteAttestation::new(attestation_config)
+    let attested_tls_config = RemoteAttestation::from_teaclave_config(&config)?
         .generate_and_endorse()?
         .attested_tls_config()
         .ok_or_else(|| anyhow!("cannot get attested TLS config"))?;
     let enclave_info = EnclaveInfo::verify_and_new(
         &config.audit.enclave_info_bytes,
-        AUDITOR_PUBLIC_KEYS,
+        &config.audit.auditor_public_keys(AUDITOR_PUBLIC_KEYS),
         &config.audit.auditor_signatures_bytes,
     )?;
     let accepted_enclave_attrs: Vec<teaclave_types::EnclaveAttr> = SCHEDULER_INBOUND_SERVICES
@@ -69,7 +70,7 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
     let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
         .attestation_report_verifier(
         accepted_enclave_attrs,
-        AS_ROOT_CA_CERT,
+        &root_ca_certs,
         verifier::universal_quote_verifier,
     )?;
 
@@ -77,17 +78,21 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
         SgxTrustedTlsServer::<TeaclaveSchedulerResponse, TeaclaveSchedulerRequest>::new(
             listen_address,
             server_config,
-        );
+        )
+        .shutdown_timeout(Duration::from_secs(config.server.shutdown_timeout_seconds));
 
     let storage_service_address = &config.internal_endpoints.storage.advertised_address;
     let storage_service_endpoint = create_trusted_storage_endpoint(
         &storage_service_address,
         &enclave_info,
-        AS_ROOT_CA_CERT,
+        &root_ca_certs,
         verifier::universal_quote_verifier,
     );
 
-    let service = service::TeaclaveSchedulerService::new(storage_service_endpoint)?;
+    let service = service::TeaclaveSchedulerService::new(
+        storage_service_endpoint,
+        &config.storage.key_namespace,
+    )?;
     match server.start(service) {
         Ok(_) => (),
         Err(e) => {
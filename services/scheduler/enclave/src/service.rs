@@ -22,6 +22,8 @@ use std::collections::VecDeque;
 #[cfg(feature = "mesalock_sgx")]
 use std::prelude::v1::*;
 use std::sync::{Arc, SgxMutex as Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::untrusted::time::SystemTimeEx;
 
 use std::collections::HashMap;
 use teaclave_proto::teaclave_scheduler_service::*;
@@ -30,8 +32,9 @@ use teaclave_rpc::endpoint::Endpoint;
 use teaclave_rpc::Request;
 use teaclave_service_enclave_utils::teaclave_service;
 use teaclave_types::{
-    ExternalID, OutputsTags, StagedTask, Storable, Task, TaskFiles, TaskResult, TaskStatus,
-    TeaclaveOutputFile, TeaclaveServiceResponseError, TeaclaveServiceResponseResult,
+    namespaced_key, ExternalID, OutputsTags, StagedTask, Storable, Task, TaskEvent, TaskFiles,
+    TaskLink, TaskReproduction, TaskResult, TaskStatus, TeaclaveInputFile, TeaclaveOutputFile,
+    TeaclaveServiceResponseError, TeaclaveServiceResponseResult, UserID,
 };
 use uuid::Uuid;
 
@@ -60,10 +63,13 @@ impl From<TeaclaveSchedulerError> for TeaclaveServiceResponseError {
 pub(crate) struct TeaclaveSchedulerService {
     storage_client: Arc<Mutex<TeaclaveStorageClient>>,
     task_queue: Arc<Mutex<VecDeque<StagedTask>>>,
+    key_namespace: String,
+    // Serializes changes to the users in turn
+    fair_staging_lock: Arc<Mutex<()>>,
 }
 
 impl TeaclaveSchedulerService {
-    pub(crate) fn new(storage_service_endpoint: Endpoint) -> Result<Self> {
+    pub(crate) fn new(storage_service_endpoint: Endpoint, key_namespace: &str) -> Result<Self> {
         let mut i = 0;
         let channel = loop {
             match storage_service_endpoint.connect() {
@@ -81,21 +87,84 @@ impl TeaclaveSchedulerService {
         let service = Self {
             storage_client,
             task_queue,
+            key_namespace: key_namespace.to_string(),
+            fair_staging_lock: Arc::new(Mutex::new(())),
         };
 
         Ok(service)
     }
 
     fn pull_staged_task<T: Storable>(&self, key: &[u8]) -> TeaclaveServiceResponseResult<T> {
-        let dequeue_request = DequeueRequest::new(key);
+        let value = self.dequeue_bytes_from_db(key)?;
+        T::from_slice(value.as_slice()).map_err(|_| TeaclaveSchedulerError::DataError.into())
+    }
+
+    // Users with staged tasks take turns, a user is kept in turn until the
+    // queue of the user is drained. Returns None if no user is in turn.
+    fn pull_fair_staged_task(&self) -> Option<StagedTask> {
+        let _guard = self.fair_staging_lock.lock().ok()?;
+        self.admit_arrived_users();
+        let users_key = StagedTask::get_users_queue_key();
+        loop {
+            let user_id = self.dequeue_bytes_from_db(users_key.as_bytes()).ok()?;
+            let user = UserID::from(String::from_utf8_lossy(&user_id).to_string());
+            let key = StagedTask::get_user_queue_key(&user);
+            let value = match self.dequeue_bytes_from_db(key.as_bytes()) {
+                Ok(value) => value,
+                // the queue of the user is drained, the user is put in turn
+                // again when new tasks arrive
+                Err(_) => {
+                    let turn_key = StagedTask::get_user_turn_key(&user);
+                    if let Err(e) = self.delete_from_db(turn_key.as_bytes()) {
+                        log::warn!("Failed to take user out of turn: {:?}", e);
+                        if let Err(e) = self.enqueue_bytes_into_db(users_key, user_id) {
+                            log::warn!("Failed to keep user in turn: {:?}", e);
+                        }
+                        return None;
+                    }
+                    continue;
+                }
+            };
+            if let Err(e) = self.enqueue_bytes_into_db(users_key, user_id) {
+                log::warn!("Failed to keep user in turn: {:?}", e);
+            }
+            match StagedTask::from_slice(&value) {
+                Ok(staged_task) => return Some(staged_task),
+                Err(e) => log::warn!("Invalid staged task in {}: {:?}", key, e),
+            }
+        }
+    }
+
+    // Puts users with newly staged tasks in turn unless they are already.
+    fn admit_arrived_users(&self) {
+        let arrivals_key = StagedTask::get_arrivals_queue_key();
+        while let Ok(user_id) = self.dequeue_bytes_from_db(arrivals_key.as_bytes()) {
+            let user = UserID::from(String::from_utf8_lossy(&user_id).to_string());
+            let turn_key = StagedTask::get_user_turn_key(&user);
+            if self.get_bytes_from_db(turn_key.as_bytes()).is_ok() {
+                continue;
+            }
+            if let Err(e) =
+                self.enqueue_bytes_into_db(StagedTask::get_users_queue_key(), user_id.clone())
+            {
+                log::warn!("Failed to put user in turn: {:?}", e);
+                continue;
+            }
+            if let Err(e) = self.put_bytes_into_db(turn_key.into_bytes(), user_id) {
+                log::warn!("Failed to mark user in turn: {:?}", e);
+            }
+        }
+    }
+
+    fn dequeue_bytes_from_db(&self, key: &[u8]) -> TeaclaveServiceResponseResult<Vec<u8>> {
+        let dequeue_request = DequeueRequest::new(namespaced_key(&self.key_namespace, key));
         let dequeue_response = self
             .storage_client
             .clone()
             .lock()
             .map_err(|_| TeaclaveSchedulerError::StorageError)?
             .dequeue(dequeue_request)?;
-        T::from_slice(dequeue_response.value.as_slice())
-            .map_err(|_| TeaclaveSchedulerError::DataError.into())
+        Ok(dequeue_response.value)
     }
 
     fn get_task(&self, task_id: &Uuid) -> Result<Task> {
@@ -103,9 +172,56 @@ impl TeaclaveSchedulerService {
         self.get_from_db(&key)
     }
 
+    // Staged tasks reproducing finished tasks have no tasks of their own, their
+    // updates go to the reproduction records instead.
+    fn get_reproduction(&self, task_id: &Uuid) -> Result<TaskReproduction> {
+        let key = ExternalID::new(TaskReproduction::key_prefix(), task_id.to_owned());
+        self.get_from_db(&key)
+    }
+
+    fn assign_linked_input(&self, task: &Task, link: &TaskLink) -> Result<()> {
+        let output = task
+            .assigned_outputs
+            .get(&link.output_name)
+            .ok_or_else(|| anyhow!("Output not assigned"))?;
+        let input = TeaclaveInputFile::from_output(output.clone())?;
+        self.put_into_db(&input)?;
+
+        let mut consumer: Task = self.get_from_db(&link.consumer_task_id)?;
+        let old_status = consumer.status;
+        consumer.assign_linked_input(&link.input_name, input)?;
+        self.put_task_into_db(&consumer, old_status)
+    }
+
+    // Writes a task and appends its status transition to the event log of
+    // the management service. Failing to record the event doesn't fail the
+    // request.
+    fn put_task_into_db(&self, task: &Task, old_status: TaskStatus) -> Result<()> {
+        self.put_into_db(task)?;
+        if task.status != old_status {
+            let event = TaskEvent::new(task, Some(old_status), current_timestamp());
+            if let Err(e) = self.enqueue_bytes_into_db(TaskEvent::get_queue_key(), event.to_vec()?)
+            {
+                log::warn!("Failed to record event of task {:?}: {:?}", task.task_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    fn enqueue_bytes_into_db(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        let enqueue_request =
+            EnqueueRequest::new(namespaced_key(&self.key_namespace, key.as_bytes()), value);
+        self.storage_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?
+            .enqueue(enqueue_request)?;
+        Ok(())
+    }
+
     fn get_from_db<T: Storable>(&self, key: &ExternalID) -> Result<T> {
         anyhow::ensure!(T::match_prefix(&key.prefix), "Key prefix doesn't match.");
-        let get_request = GetRequest::new(key.to_bytes());
+        let get_request = GetRequest::new(namespaced_key(&self.key_namespace, &key.to_bytes()));
         let response = self
             .storage_client
             .clone()
@@ -115,10 +231,33 @@ impl TeaclaveSchedulerService {
         T::from_slice(response.value.as_slice())
     }
 
+    fn get_bytes_from_db(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let get_request = GetRequest::new(namespaced_key(&self.key_namespace, key));
+        let response = self
+            .storage_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?
+            .get(get_request)?;
+        Ok(response.value)
+    }
+
+    fn delete_from_db(&self, key: &[u8]) -> Result<()> {
+        let delete_request = DeleteRequest::new(namespaced_key(&self.key_namespace, key));
+        self.storage_client
+            .clone()
+            .lock()
+            .map_err(|_| anyhow!("Cannot lock storage client"))?
+            .delete(delete_request)?;
+        Ok(())
+    }
+
     fn put_into_db(&self, item: &impl Storable) -> Result<()> {
-        let k = item.key();
-        let v = item.to_vec()?;
-        let put_request = PutRequest::new(k.as_slice(), v.as_slice());
+        self.put_bytes_into_db(item.key(), item.to_vec()?)
+    }
+
+    fn put_bytes_into_db(&self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        let put_request = PutRequest::new(namespaced_key(&self.key_namespace, &key), value);
         let _put_response = self
             .storage_client
             .clone()
@@ -129,6 +268,13 @@ impl TeaclaveSchedulerService {
     }
 }
 
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
 impl TeaclaveScheduler for TeaclaveSchedulerService {
     // Publisher
     fn publish_task(
@@ -158,8 +304,12 @@ impl TeaclaveScheduler for TeaclaveSchedulerService {
         &self,
         request: Request<PullTaskRequest>,
     ) -> TeaclaveServiceResponseResult<PullTaskResponse> {
-        let key = StagedTask::get_queue_key().as_bytes();
-        let staged_task = self.pull_staged_task(key)?;
+        // Tasks staged before fair staging is enabled are pulled once no user
+        // is in turn
+        let staged_task = match self.pull_fair_staged_task() {
+            Some(staged_task) => staged_task,
+            None => self.pull_staged_task(StagedTask::get_queue_key().as_bytes())?,
+        };
         let response = PullTaskResponse::new(staged_task);
         Ok(response)
     }
@@ -169,13 +319,30 @@ impl TeaclaveScheduler for TeaclaveSchedulerService {
         request: Request<UpdateTaskStatusRequest>,
     ) -> TeaclaveServiceResponseResult<UpdateTaskStatusResponse> {
         let request = request.message;
-        let mut task = self.get_task(&request.task_id)?;
+        let mut task = match self.get_task(&request.task_id) {
+            Ok(task) => task,
+            Err(e) => {
+                let mut reproduction = self.get_reproduction(&request.task_id).map_err(|_| e)?;
+                match request.task_status {
+                    TaskStatus::Running => reproduction.start(),
+                    _ => return Err(TeaclaveSchedulerError::DataError.into()),
+                }
+                self.put_into_db(&reproduction)?;
+                return Ok(UpdateTaskStatusResponse {});
+            }
+        };
+        let old_status = task.status;
 
-        // Only TaskStatus::Running is allowed here so far.
-        task.invoking_by_executor()?;
+        // Executors either start running a task or acknowledge the
+        // cancellation of it.
+        match request.task_status {
+            TaskStatus::Running => task.invoking_by_executor()?,
+            TaskStatus::Canceled => task.acknowledge_cancel()?,
+            _ => return Err(TeaclaveSchedulerError::DataError.into()),
+        }
 
         log::info!("UpdateTaskStatus: Task {:?}", task);
-        self.put_into_db(&task)?;
+        self.put_task_into_db(&task, old_status)?;
         Ok(UpdateTaskStatusResponse {})
     }
 
@@ -184,21 +351,103 @@ impl TeaclaveScheduler for TeaclaveSchedulerService {
         request: Request<UpdateTaskResultRequest>,
     ) -> TeaclaveServiceResponseResult<UpdateTaskResultResponse> {
         let request = request.message;
-        let mut task = self.get_task(&request.task_id)?;
+        let mut task = match self.get_task(&request.task_id) {
+            Ok(task) => task,
+            Err(e) => {
+                // Outputs of reproductions are only compared, not finalized
+                let mut reproduction = self.get_reproduction(&request.task_id).map_err(|_| e)?;
+                reproduction.finish(&request.task_result);
+                log::info!("UpdateTaskResult: Reproduction {:?}", reproduction);
+                self.put_into_db(&reproduction)?;
+                return Ok(UpdateTaskResultResponse {});
+            }
+        };
+        let old_status = task.status;
 
-        if let TaskResult::Ok(outputs) = &request.task_result {
+        // Updating task result means we have finished execution, the result
+        // is rejected if the return value does not match the function
+        task.finish(request.task_result)?;
+
+        if let TaskResult::Ok(outputs) = &task.result {
+            let finalized_at = current_timestamp();
             for (key, auth_tag) in outputs.tags_map.iter() {
-                let outfile = task.assigned_outputs.update_cmac(key, auth_tag)?;
-                self.put_into_db(outfile)?;
+                let mut outfile = task.assigned_outputs.update_cmac(key, auth_tag)?.clone();
+                outfile.finalized_at = Some(finalized_at);
+                self.put_into_db(&outfile)?;
             }
         };
 
-        // Updating task result means we have finished execution
-        task.finish(request.task_result)?;
+        if !request.log.is_empty() {
+            let value =
+                serde_json::to_vec(&request.log).map_err(|_| TeaclaveSchedulerError::DataError)?;
+            self.put_bytes_into_db(task.log_key(), value)?;
+            task.log_stored = true;
+        }
+
+        // Tags of large results are stored separately in chunks
+        for (index, chunk) in task.take_result_tags_chunks().iter().enumerate() {
+            let value = serde_json::to_vec(chunk).map_err(|_| TeaclaveSchedulerError::DataError)?;
+            self.put_bytes_into_db(task.result_tags_chunk_key(index), value)?;
+        }
+
+        self.put_task_into_db(&task, old_status)?;
+
+        // Outputs linked to other tasks are assigned as their inputs
+        if task.result.is_ok() {
+            for link in task.output_links.iter() {
+                if let Err(e) = self.assign_linked_input(&task, link) {
+                    log::warn!("Failed to assign linked input {:?}: {:?}", link, e);
+                }
+            }
+        }
 
-        self.put_into_db(&task)?;
         Ok(UpdateTaskResultResponse {})
     }
+
+    fn update_partial_output(
+        &self,
+        request: Request<UpdatePartialOutputRequest>,
+    ) -> TeaclaveServiceResponseResult<UpdatePartialOutputResponse> {
+        let request = request.message;
+        let mut task = match self.get_task(&request.task_id) {
+            Ok(task) => task,
+            Err(e) => {
+                self.get_reproduction(&request.task_id).map_err(|_| e)?;
+                return Ok(UpdatePartialOutputResponse {});
+            }
+        };
+
+        // Only the result of the task is updated, the output file is
+        // finalized when the task finishes.
+        task.update_partial_output(&request.output_name, request.cmac)?;
+
+        log::info!(
+            "UpdatePartialOutput: Task {:?}, output {}",
+            request.task_id,
+            request.output_name
+        );
+        self.put_into_db(&task)?;
+        Ok(UpdatePartialOutputResponse {})
+    }
+
+    fn check_task_cancellation(
+        &self,
+        request: Request<CheckTaskCancellationRequest>,
+    ) -> TeaclaveServiceResponseResult<CheckTaskCancellationResponse> {
+        let request = request.message;
+        let task = match self.get_task(&request.task_id) {
+            Ok(task) => task,
+            Err(e) => {
+                self.get_reproduction(&request.task_id).map_err(|_| e)?;
+                return Ok(CheckTaskCancellationResponse {
+                    cancel_requested: false,
+                });
+            }
+        };
+        Ok(CheckTaskCancellationResponse {
+            cancel_requested: task.cancel_requested,
+        })
+    }
 }
 
 #[cfg(test_mode)]
@@ -27,11 +27,12 @@ use std::format;
 use std::prelude::v1::*;
 use std::sync::mpsc::channel;
 use std::thread;
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 use rusty_leveldb::DB;
 
-use teaclave_attestation::{verifier, AttestationConfig, RemoteAttestation};
+use teaclave_attestation::{verifier, RemoteAttestation};
 use teaclave_binder::proto::{
     ECallCommand, FinalizeEnclaveInput, FinalizeEnclaveOutput, InitEnclaveInput, InitEnclaveOutput,
     StartServiceInput, StartServiceOutput,
@@ -49,15 +50,15 @@ mod proxy;
 mod service;
 
 fn start_service(config: &RuntimeConfig) -> Result<()> {
+    let root_ca_certs = config.attestation.root_ca_certs(AS_ROOT_CA_CERT);
     let listen_address = config.internal_endpoints.storage.listen_address;
-    let attestation_config = AttestationConfig::from_teaclave_config(&config)?;
-    let attested_tls_config = RemoteAttestation::new(attestation_config)
+    let attested_tls_config = RemoteAttestation::from_teaclave_config(&config)?
         .generate_and_endorse()?
         .attested_tls_config()
         .ok_or_else(|| anyhow!("cannot get attested TLS config"))?;
     let enclave_info = EnclaveInfo::verify_and_new(
         &config.audit.enclave_info_bytes,
-        AUDITOR_PUBLIC_KEYS,
+        &config.audit.auditor_public_keys(AUDITOR_PUBLIC_KEYS),
         &config.audit.auditor_signatures_bytes,
     )?;
     let accepted_enclave_attrs: Vec<teaclave_types::EnclaveAttr> = STORAGE_INBOUND_SERVICES
@@ -70,7 +71,7 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
     let server_config = SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config)?
         .attestation_report_verifier(
         accepted_enclave_attrs,
-        AS_ROOT_CA_CERT,
+        &root_ca_certs,
         verifier::universal_quote_verifier,
     )?;
 
@@ -86,7 +87,8 @@ fn start_service(config: &RuntimeConfig) -> Result<()> {
     let mut server = SgxTrustedTlsServer::<TeaclaveStorageResponse, TeaclaveStorageRequest>::new(
         listen_address,
         server_config,
-    );
+    )
+    .shutdown_timeout(Duration::from_secs(config.server.shutdown_timeout_seconds));
 
     let service = proxy::ProxyService::new(sender);
 
@@ -137,6 +139,7 @@ pub mod tests {
     pub fn run_tests() -> bool {
         run_tests!(
             service::tests::test_get_key,
+            service::tests::test_get_multi,
             service::tests::test_put_key,
             service::tests::test_delete_key,
             service::tests::test_enqueue,
@@ -22,7 +22,8 @@ use std::prelude::v1::*;
 use std::sync::mpsc::Receiver;
 use teaclave_proto::teaclave_storage_service::{
     DeleteRequest, DeleteResponse, DequeueRequest, DequeueResponse, EnqueueRequest,
-    EnqueueResponse, GetRequest, GetResponse, PutRequest, PutResponse, TeaclaveStorage,
+    EnqueueResponse, GetMultiRequest, GetMultiResponse, GetRequest, GetResponse, PutRequest,
+    PutResponse, TeaclaveStorage,
 };
 use teaclave_rpc::Request;
 use teaclave_service_enclave_utils::{bail, teaclave_service};
@@ -175,7 +176,17 @@ impl TeaclaveStorageService {
             };
             let database_request = request.request;
             let sender = request.sender;
+            // Requests made on behalf of user requests carry their correlation
+            // id, so that failures can be traced back to them.
+            let correlation_id = database_request
+                .metadata
+                .get("correlation_id")
+                .cloned()
+                .unwrap_or_else(|| "-".to_string());
             let response = self.dispatch(database_request);
+            if let Err(e) = &response {
+                debug!("[{}] storage request failed: {:?}", correlation_id, e);
+            }
 
             match sender.send(response) {
                 Ok(_) => (),
@@ -193,6 +204,16 @@ impl TeaclaveStorage for TeaclaveStorageService {
         }
     }
 
+    fn get_multi(
+        &self,
+        request: Request<GetMultiRequest>,
+    ) -> TeaclaveServiceResponseResult<GetMultiResponse> {
+        let request = request.message;
+        let mut database = self.database.borrow_mut();
+        let values = request.keys.iter().map(|key| database.get(key)).collect();
+        Ok(GetMultiResponse { values })
+    }
+
     fn put(&self, request: Request<PutRequest>) -> TeaclaveServiceResponseResult<PutResponse> {
         let request = request.message;
         self.database
@@ -275,6 +296,17 @@ pub mod tests {
         assert!(service.get(request).is_ok());
     }
 
+    pub fn test_get_multi() {
+        let service = get_mock_service();
+        let keys = vec![b"test_get_key".to_vec(), b"test_missing_key".to_vec()];
+        let request = GetMultiRequest::new(keys).into_request();
+        let response = service.get_multi(request).unwrap();
+        assert_eq!(
+            response.values,
+            vec![Some(b"test_get_value".to_vec()), None]
+        );
+    }
+
     pub fn test_put_key() {
         let service = get_mock_service();
         let request = PutRequest::new("test_put_key", "test_put_value").into_request();
@@ -50,6 +50,10 @@ impl TeaclaveServiceLauncher {
         }
     }
 
+    pub fn config(&self) -> &RuntimeConfig {
+        &self.config
+    }
+
     pub fn finalize(&self) {
         self.tee.finalize();
     }
@@ -51,6 +51,11 @@ pub fn teaclave_service(attr: TokenStream, input: TokenStream) -> TokenStream {
                 trace!("Dispatching request.");
                 self.dispatch(request)
             }
+
+            fn response_metadata(&self) -> std::collections::HashMap<std::string::String, std::string::String> {
+                use teaclave_proto::#crate_name_proto::#trait_name_ident;
+                #trait_name_ident::response_metadata(self)
+            }
         }
     );
     q.into()
@@ -61,6 +61,9 @@ impl ServiceEnclave {
     pub fn finalize() -> teaclave_types::TeeServiceResult<()> {
         debug!("Enclave finalizing");
 
+        // Let servers drain in-flight requests before the enclave is gone.
+        teaclave_rpc::server::SHUTDOWN_SIGNAL.shutdown();
+
         #[cfg(feature = "cov")]
         sgx_cov::cov_writeout();
 
@@ -0,0 +1,3 @@
+def entrypoint(argv):
+    assert argv[0] == "message" and argv[1] is not None
+    return argv[1]
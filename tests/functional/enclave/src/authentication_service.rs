@@ -144,8 +144,62 @@ fn test_register_fail() {
     let request = UserRegisterRequest::new("test_register_id2", "test_password");
     let response_result = client.user_register(request);
     assert!(response_result.is_ok());
-    let request = UserRegisterRequest::new("test_register_id2", "test_password");
+    let request = UserRegisterRequest::new("test_register_id2", "other_password");
     let response_result = client.user_register(request);
     info!("{:?}", response_result);
     assert!(response_result.is_err());
 }
+
+#[test_case]
+fn test_register_duplicate() {
+    let runtime_config = RuntimeConfig::from_toml("runtime.config.toml").expect("runtime");
+    let mut client = get_api_client();
+    let request = UserRegisterRequest::new("test_register_id3", "test_password");
+    let response_result = client.user_register(request);
+    assert!(response_result.is_ok());
+
+    // Registering again with the same password fails unless registration is
+    // idempotent
+    let request = UserRegisterRequest::new("test_register_id3", "test_password");
+    let response_result = client.user_register(request);
+    info!("{:?}", response_result);
+    assert_eq!(
+        response_result.is_ok(),
+        runtime_config.authentication.idempotent_registration
+    );
+}
+
+#[test_case]
+fn test_revoke_session() {
+    let mut api_client = get_api_client();
+    let mut internal_client = get_internal_client();
+    let request = UserRegisterRequest::new("test_session_id1", "test_password");
+    let response_result = api_client.user_register(request);
+    assert!(response_result.is_ok());
+
+    let request = UserLoginRequest::new("test_session_id1", "test_password");
+    let token1 = api_client.user_login(request).unwrap().token;
+    let request = UserLoginRequest::new("test_session_id1", "test_password");
+    let token2 = api_client.user_login(request).unwrap().token;
+
+    let credential = UserCredential::new("test_session_id1", &token1);
+    let request = ListSessionsRequest::new(credential);
+    let sessions = api_client.list_sessions(request).unwrap().sessions;
+    assert_eq!(sessions.len(), 2);
+
+    let credential = UserCredential::new("test_session_id1", &token1);
+    let request = RevokeSessionRequest::new(credential, &sessions[1].session_id);
+    let response_result = api_client.revoke_session(request);
+    info!("{:?}", response_result);
+    assert!(response_result.is_ok());
+
+    let credential = UserCredential::new("test_session_id1", &token1);
+    let request = UserAuthenticateRequest::new(credential);
+    let response_result = internal_client.user_authenticate(request);
+    assert!(response_result.unwrap().accept);
+
+    let credential = UserCredential::new("test_session_id1", &token2);
+    let request = UserAuthenticateRequest::new(credential);
+    let response_result = internal_client.user_authenticate(request);
+    assert!(!response_result.unwrap().accept);
+}
@@ -56,7 +56,7 @@ fn get_task_until(
                     log::error!("Task failed, reason: {:?}", failure);
                     return failure.to_string();
                 }
-                TaskResult::NotReady => unreachable!(),
+                TaskResult::NotReady | TaskResult::InProgress(_) => unreachable!(),
             }
         }
     }
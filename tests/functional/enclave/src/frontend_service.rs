@@ -154,6 +154,44 @@ fn test_get_function() {
     assert!(response.is_err());
 }
 
+#[test_case]
+fn test_correlation_id() {
+    // A failing request forwarded to the management service reports the
+    // correlation id supplied by the client.
+    let mut client = authorized_client();
+    client.metadata_mut().insert(
+        "correlation_id".to_string(),
+        "mock_correlation_id".to_string(),
+    );
+    let task_id = ExternalID::try_from("task-00000000-0000-0000-0000-000000000000").unwrap();
+    let request = GetTaskRequest::new(task_id.clone());
+    let error = client.get_task(request).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("(correlation id: mock_correlation_id)"));
+
+    // Successful responses carry the correlation id in their metadata.
+    let url = Url::parse("https://external-storage.com/filepath?presigned_token").unwrap();
+    let request =
+        RegisterInputFileRequest::new(url.clone(), FileAuthTag::mock(), FileCrypto::default());
+    client.register_input_file(request).unwrap();
+    assert_eq!(
+        client.response_metadata().get("correlation_id"),
+        Some(&"mock_correlation_id".to_string())
+    );
+
+    // Otherwise a correlation id is generated by the frontend service.
+    let request = GetTaskRequest::new(task_id);
+    let error = authorized_client().get_task(request).unwrap_err();
+    assert!(error.to_string().contains("(correlation id: "));
+
+    let mut client = authorized_client();
+    let request = RegisterInputFileRequest::new(url, FileAuthTag::mock(), FileCrypto::default());
+    client.register_input_file(request).unwrap();
+    let correlation_id = client.response_metadata().get("correlation_id").unwrap();
+    assert!(!correlation_id.is_empty());
+}
+
 #[test_case]
 fn test_create_task() {
     let function_id =
@@ -313,3 +351,30 @@ fn test_invoke_task() {
     let response = scheduler_client.pull_task(request);
     assert!(response.is_ok());
 }
+
+#[test_case]
+fn test_get_capabilities() {
+    let request = GetCapabilitiesRequest::new();
+    let response = authorized_client().get_capabilities(request).unwrap();
+    assert_eq!(
+        response.crypto_schemas,
+        vec!["aes-gcm-128", "aes-gcm-256", "teaclave-file-128", "raw"]
+    );
+    assert_eq!(
+        response.executors,
+        vec![Executor::MesaPy, Executor::Builtin]
+    );
+    assert_eq!(
+        response.executor_types,
+        vec![ExecutorType::Builtin, ExecutorType::Python]
+    );
+    assert_eq!(
+        response.url_schemes,
+        vec!["http", "https", "file", "fusion"]
+    );
+    assert!(!response.version.is_empty());
+
+    let request = GetCapabilitiesRequest::new();
+    let response = unauthorized_client().get_capabilities(request);
+    assert!(response.is_err());
+}
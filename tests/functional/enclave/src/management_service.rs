@@ -28,6 +28,14 @@ fn authorized_client(user_id: &str) -> TeaclaveManagementClient {
     get_management_client(user_id)
 }
 
+fn admin_client(user_id: &str) -> TeaclaveManagementClient {
+    let mut client = get_management_client(user_id);
+    client
+        .metadata_mut()
+        .insert("role".to_string(), "admin".to_string());
+    client
+}
+
 #[test_case]
 fn test_register_input_file() {
     let url = Url::parse("https://external-storage.com/filepath?presigned_token").unwrap();
@@ -123,6 +131,52 @@ fn test_get_input_file() {
     assert!(response.is_err());
 }
 
+#[test_case]
+fn test_list_input_files() {
+    let mut client = authorized_client("mock_list_files_user");
+    let mut data_ids = Vec::new();
+    for _ in 0..3 {
+        let url = Url::parse("https://external-storage.com/filepath?presigned_token").unwrap();
+        let request =
+            RegisterInputFileRequest::new(url, FileAuthTag::mock(), FileCrypto::default());
+        data_ids.push(client.register_input_file(request).unwrap().data_id);
+    }
+
+    let request = ListInputFilesRequest::new(None, 2);
+    let response = client.list_input_files(request).unwrap();
+    assert_eq!(response.files.len(), 2);
+    assert_eq!(response.files[0].data_id, data_ids[0]);
+    assert!(!response.files[0].consumed);
+    assert!(response.next_cursor.is_some());
+    let mut listed: Vec<ExternalID> = response.files.into_iter().map(|f| f.data_id).collect();
+
+    // files registered during the listing are listed after the others
+    let url = Url::parse("https://external-storage.com/filepath?presigned_token").unwrap();
+    let request = RegisterInputFileRequest::new(url, FileAuthTag::mock(), FileCrypto::default());
+    data_ids.push(client.register_input_file(request).unwrap().data_id);
+
+    let request = ListInputFilesRequest::new(response.next_cursor, 2);
+    let response = client.list_input_files(request).unwrap();
+    assert_eq!(response.files.len(), 2);
+    listed.extend(response.files.into_iter().map(|f| f.data_id));
+    assert_eq!(response.next_cursor, None);
+    assert_eq!(listed, data_ids);
+
+    // forged cursors are rejected
+    let request = ListInputFilesRequest::new(Some("00.00".to_string()), 2);
+    assert!(client.list_input_files(request).is_err());
+
+    // files of other users are not listed
+    let request = ListInputFilesRequest::new(None, 2);
+    let response = authorized_client("mock_another_user")
+        .list_input_files(request)
+        .unwrap();
+    assert!(response
+        .files
+        .iter()
+        .all(|file| !data_ids.contains(&file.data_id)));
+}
+
 #[test_case]
 fn test_register_function() {
     let function_input = FunctionInput::new("input", "input_desc");
@@ -175,6 +229,46 @@ fn test_get_function() {
     assert!(response.is_ok());
 }
 
+#[test_case]
+fn test_deprecate_function() {
+    let request = RegisterFunctionRequest::new()
+        .name("mock_function")
+        .executor_type(ExecutorType::Python)
+        .payload(b"def entrypoint:\n\treturn".to_vec())
+        .public(true);
+
+    let mut client = authorized_client("mock_user");
+    let response = client.register_function(request).unwrap();
+    let function_id = response.function_id;
+    let replacement =
+        ExternalID::try_from("function-00000000-0000-0000-0000-000000000001").unwrap();
+
+    let request =
+        DeprecateFunctionRequest::new(function_id.clone(), true).replacement(replacement.clone());
+    let response = client.deprecate_function(request);
+    assert!(response.is_ok());
+
+    let request = GetFunctionRequest::new(function_id.clone());
+    let response = client.get_function(request).unwrap();
+    assert!(response.deprecated);
+    assert_eq!(response.replacement, Some(replacement));
+
+    // Only the owner can deprecate a function
+    let mut unauthorized_client = authorized_client("mock_unauthorized_user");
+    let request = DeprecateFunctionRequest::new(function_id.clone(), false);
+    let response = unauthorized_client.deprecate_function(request);
+    assert!(response.is_err());
+
+    let request = DeprecateFunctionRequest::new(function_id.clone(), false);
+    let response = client.deprecate_function(request);
+    assert!(response.is_ok());
+
+    let request = GetFunctionRequest::new(function_id);
+    let response = client.get_function(request).unwrap();
+    assert!(!response.deprecated);
+    assert!(response.replacement.is_none());
+}
+
 fn create_valid_task_request() -> CreateTaskRequest {
     let function_id =
         ExternalID::try_from("function-00000000-0000-0000-0000-000000000001").unwrap();
@@ -227,6 +321,51 @@ fn test_create_task() {
     assert!(response.is_err());
 }
 
+#[test_case]
+fn test_task_template() {
+    let mut client = authorized_client("mock_user");
+
+    let task_request = create_valid_task_request();
+    let request = RegisterTaskTemplateRequest::new()
+        .name("mock_template")
+        .function_id(task_request.function_id)
+        .function_arguments(hashmap!("arg1" => "data1", "arg2" => "data2"))
+        .executor(Executor::MesaPy)
+        .inputs_ownership(task_request.inputs_ownership)
+        .outputs_ownership(task_request.outputs_ownership);
+    let template_id = client.register_task_template(request).unwrap().template_id;
+
+    let request = InstantiateTemplateRequest::new(template_id.clone());
+    let task_id = client.instantiate_template(request).unwrap().task_id;
+    let request = GetTaskRequest::new(task_id);
+    let response = client.get_task(request).unwrap();
+    assert_eq!(response.participants.len(), 4);
+    assert_eq!(
+        response.function_arguments.get("arg1").unwrap().as_str(),
+        "data1"
+    );
+
+    // arguments of the second instance are overridden
+    let request = InstantiateTemplateRequest::new(template_id.clone())
+        .function_arguments(hashmap!("arg1" => "data3"));
+    let task_id = client.instantiate_template(request).unwrap().task_id;
+    let request = GetTaskRequest::new(task_id);
+    let response = client.get_task(request).unwrap();
+    assert_eq!(
+        response.function_arguments.get("arg1").unwrap().as_str(),
+        "data3"
+    );
+    assert_eq!(
+        response.function_arguments.get("arg2").unwrap().as_str(),
+        "data2"
+    );
+
+    // private templates can only be instantiated by the owner
+    let request = InstantiateTemplateRequest::new(template_id);
+    let response = authorized_client("mock_another_user").instantiate_template(request);
+    assert!(response.is_err());
+}
+
 #[test_case]
 fn test_get_task() {
     let mut client = authorized_client("mock_user");
@@ -245,6 +384,25 @@ fn test_get_task() {
     }
 }
 
+#[test_case]
+fn test_get_tasks() {
+    let mut client = authorized_client("mock_user");
+    let request = create_valid_task_request();
+    let task_id = client.create_task(request).unwrap().task_id;
+
+    let mut other_client = authorized_client("non-participant");
+    let request = create_valid_task_request();
+    let other_task_id = other_client.create_task(request).unwrap().task_id;
+
+    let request = GetTasksRequest::new(vec![task_id.clone(), other_task_id.clone()]);
+    let response = client.get_tasks(request).unwrap();
+    assert_eq!(response.tasks.len(), 2);
+    assert_eq!(response.tasks[0].task_id, task_id);
+    assert!(response.tasks[0].task.is_some());
+    assert_eq!(response.tasks[1].task_id, other_task_id);
+    assert!(response.tasks[1].task.is_none());
+}
+
 #[test_case]
 fn test_assign_data() {
     let mut client = authorized_client("mock_user");
@@ -504,6 +662,183 @@ fn test_approve_task() {
     assert_eq!(response.status, TaskStatus::Approved);
 }
 
+#[test_case]
+fn test_admin_get_task() {
+    let mut client = authorized_client("mock_user");
+    let request = create_valid_task_request();
+    let response = client.create_task(request);
+    assert!(response.is_ok());
+    let task_id = response.unwrap().task_id;
+
+    // non-admin users are denied even if they are participants
+    let request = AdminGetTaskRequest::new(task_id.clone());
+    let response = client.admin_get_task(request);
+    assert!(response.is_err());
+
+    let mut client = admin_client("mock_admin");
+    let request = AdminGetTaskRequest::new(task_id.clone());
+    let task = client.admin_get_task(request).unwrap().task;
+    assert_eq!(task.task_id, task_id);
+    assert_eq!(task.creator, UserID::from("mock_user"));
+    assert!(!task.participants.contains(&UserID::from("mock_admin")));
+    let arg1 = task.function_arguments.get("arg1").unwrap();
+    assert_eq!(arg1.as_str(), "<redacted>");
+}
+
+#[test_case]
+fn test_assign_data_crypto_schema() {
+    let mut client = authorized_client("mock_user");
+    let function_output = FunctionOutput::new("output", "output_desc").crypto_schema("aes-gcm-128");
+    let request = RegisterFunctionRequest::new()
+        .name("mock_function")
+        .executor_type(ExecutorType::Python)
+        .payload(b"def entrypoint:\n\treturn".to_vec())
+        .public(true)
+        .outputs(vec![function_output]);
+    let function_id = client.register_function(request).unwrap().function_id;
+
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .executor(Executor::MesaPy)
+        .outputs_ownership(hashmap!("output" => vec!["mock_user"]));
+    let task_id = client.create_task(request).unwrap().task_id;
+
+    // the default crypto schema is teaclave-file-128
+    let url = Url::parse("https://output_file_path").unwrap();
+    let request = RegisterOutputFileRequest::new(url, FileCrypto::default());
+    let output_id = client.register_output_file(request).unwrap().data_id;
+    let request =
+        AssignDataRequest::new(task_id.clone(), hashmap!(), hashmap!("output" => output_id));
+    assert!(client.assign_data(request).is_err());
+
+    let url = Url::parse("https://output_file_path").unwrap();
+    let crypto = FileCrypto::new("aes-gcm-128", &[1; 16], &[2; 12]).unwrap();
+    let request = RegisterOutputFileRequest::new(url, crypto);
+    let output_id = client.register_output_file(request).unwrap().data_id;
+    let request = AssignDataRequest::new(task_id, hashmap!(), hashmap!("output" => output_id));
+    assert!(client.assign_data(request).is_ok());
+}
+
+#[test_case]
+fn test_link_tasks() {
+    let mut client = authorized_client("mock_user");
+    let function_id =
+        ExternalID::try_from("function-00000000-0000-0000-0000-000000000002").unwrap();
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .function_arguments(hashmap!("arg1" => "data1"))
+        .executor(Executor::MesaPy)
+        .outputs_ownership(hashmap!("output" => vec!["mock_user1"]));
+    let producer_id = client.create_task(request).unwrap().task_id;
+
+    let mut client = authorized_client("mock_user1");
+    let consumer_id = client
+        .create_task(create_valid_task_request())
+        .unwrap()
+        .task_id;
+
+    // the owners of the output do not match the owners of input2
+    let request =
+        LinkTasksRequest::new(producer_id.clone(), "output", consumer_id.clone(), "input2");
+    assert!(client.link_tasks(request).is_err());
+
+    let request =
+        LinkTasksRequest::new(producer_id.clone(), "output", consumer_id.clone(), "input");
+    assert!(client.link_tasks(request).is_ok());
+
+    // the input is already linked
+    let request =
+        LinkTasksRequest::new(producer_id.clone(), "output", consumer_id.clone(), "input");
+    assert!(client.link_tasks(request).is_err());
+
+    // only participants of both tasks can link them
+    let mut client = authorized_client("mock_user3");
+    let request = LinkTasksRequest::new(producer_id, "output", consumer_id, "input");
+    assert!(client.link_tasks(request).is_err());
+}
+
+#[test_case]
+fn test_run_self_test() {
+    let mut client = authorized_client("mock_user");
+    let response = client.run_self_test(RunSelfTestRequest::new());
+    assert!(response.is_err());
+
+    let mut client = admin_client("mock_admin");
+    let response = client.run_self_test(RunSelfTestRequest::new()).unwrap();
+    let stages: Vec<&str> = response.stages.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(
+        stages,
+        vec!["create_task", "assign_data", "approve_task", "invoke_task"]
+    );
+    assert!(response.success());
+}
+
+#[test_case]
+fn test_approve_task_twice() {
+    let mut client = authorized_client("mock_user");
+    let function_id =
+        ExternalID::try_from("function-00000000-0000-0000-0000-000000000002").unwrap();
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .function_arguments(hashmap!("arg1" => "data1"))
+        .executor(Executor::MesaPy)
+        .outputs_ownership(hashmap!("output" => vec!["mock_user"]));
+    let task_id = client.create_task(request).unwrap().task_id;
+
+    let url = Url::parse("https://output_file_path").unwrap();
+    let request = RegisterOutputFileRequest::new(url, FileCrypto::default());
+    let output_file_id = client.register_output_file(request).unwrap().data_id;
+    let request = AssignDataRequest::new(
+        task_id.clone(),
+        hashmap!(),
+        hashmap!("output" => output_file_id),
+    );
+    client.assign_data(request).unwrap();
+
+    let request = ApproveTaskRequest::new(task_id.clone());
+    let response = client.approve_task(request).unwrap();
+    assert!(!response.already_approved);
+
+    // a retried approval succeeds without changing the task
+    let request = ApproveTaskRequest::new(task_id.clone());
+    let response = client.approve_task(request).unwrap();
+    assert!(response.already_approved);
+
+    let request = GetTaskRequest::new(task_id);
+    let response = client.get_task(request).unwrap();
+    assert_eq!(response.approved_users.len(), 1);
+    assert_eq!(response.status, TaskStatus::Approved);
+}
+
+#[test_case]
+fn test_approve_task_after_deadline() {
+    let mut client = authorized_client("mock_user");
+    let function_id =
+        ExternalID::try_from("function-00000000-0000-0000-0000-000000000002").unwrap();
+    let request = CreateTaskRequest::new()
+        .function_id(function_id)
+        .function_arguments(hashmap!("arg1" => "data1"))
+        .executor(Executor::MesaPy)
+        .outputs_ownership(hashmap!("output" => vec!["mock_user"]))
+        .approval_deadline_seconds(1);
+    let response = client.create_task(request);
+    assert!(response.is_ok());
+    let task_id = response.unwrap().task_id;
+
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    let request = ApproveTaskRequest::new(task_id.clone());
+    let response = client.approve_task(request);
+    assert_eq!(
+        response.unwrap_err(),
+        TeaclaveServiceResponseError::RequestError("approval window closed".to_string())
+    );
+
+    let request = GetTaskRequest::new(task_id);
+    let response = client.get_task(request).unwrap();
+    assert_eq!(response.status, TaskStatus::Canceled);
+}
+
 #[test_case]
 fn test_invoke_task() {
     let mut client = authorized_client("mock_user");
@@ -17,6 +17,7 @@
 
 use crate::utils::*;
 use std::prelude::v1::*;
+use teaclave_proto::teaclave_management_service::*;
 use teaclave_proto::teaclave_scheduler_service::*;
 use teaclave_proto::teaclave_storage_service::*;
 use teaclave_test_utils::test_case;
@@ -48,6 +49,49 @@ fn test_pull_task() {
     assert_eq!(response.unwrap().staged_task.function_id, function_id);
 }
 
+#[test_case]
+fn test_pull_task_fair_staging() {
+    // Fair staging is enabled in the test config
+    let mut client = get_management_client("mock_user");
+    let request = RegisterFunctionRequest::new()
+        .name("fair-staging-echo")
+        .executor_type(ExecutorType::Builtin)
+        .public(true);
+    let function_id = client.register_function(request).unwrap().function_id;
+
+    let invoke = |user_id: &str| {
+        let mut client = get_management_client(user_id);
+        let request = CreateTaskRequest::new()
+            .function_id(function_id.clone())
+            .executor(Executor::Builtin);
+        let task_id = client.create_task(request).unwrap().task_id;
+        let request = ApproveTaskRequest::new(task_id.clone());
+        client.approve_task(request).unwrap();
+        let request = InvokeTaskRequest::new(task_id.clone());
+        client.invoke_task(request).unwrap();
+        task_id.uuid
+    };
+    let user_a = Uuid::new_v4().to_string();
+    let user_b = Uuid::new_v4().to_string();
+    let tasks_a: Vec<Uuid> = (0..3).map(|_| invoke(&user_a)).collect();
+    let tasks_b: Vec<Uuid> = (0..2).map(|_| invoke(&user_b)).collect();
+
+    // Tasks staged by other tests may be pulled in between
+    let mut client = get_scheduler_client();
+    let mut pulled = Vec::new();
+    while pulled.len() < 5 {
+        let response = client.pull_task(PullTaskRequest {}).unwrap();
+        let task_id = response.staged_task.task_id;
+        if tasks_a.contains(&task_id) || tasks_b.contains(&task_id) {
+            pulled.push(task_id);
+        }
+    }
+    assert_eq!(
+        pulled,
+        vec![tasks_a[0], tasks_b[0], tasks_a[1], tasks_b[1], tasks_a[2]]
+    );
+}
+
 #[test_case]
 fn test_update_task_status_result() {
     let task_id = Uuid::new_v4();
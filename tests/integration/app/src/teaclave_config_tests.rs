@@ -16,10 +16,16 @@
 // under the License.
 
 use std::env;
+use std::fs;
 use teaclave_config;
+use teaclave_types::EnclaveInfo;
 
 pub fn run_tests() -> bool {
-    teaclave_test_utils::run_tests!(test_runtime_config, test_runtime_config_with_env_vars)
+    teaclave_test_utils::run_tests!(
+        test_runtime_config,
+        test_runtime_config_with_env_vars,
+        test_runtime_auditor_public_keys
+    )
 }
 
 fn test_runtime_config() {
@@ -62,3 +68,45 @@ fn test_runtime_config_with_env_vars() {
     assert_eq!(config.attestation.key, "12345678901234567890123456789012");
     assert_eq!(config.attestation.spid, "90123456789012345678901234567890");
 }
+
+fn runtime_config_with_audit(audit: &str) -> anyhow::Result<teaclave_config::RuntimeConfig> {
+    let contents = fs::read_to_string("./fixtures/runtime.config.toml").unwrap();
+    let contents = contents.replacen("[audit]\n", &format!("[audit]\n{}\n", audit), 1);
+    let path = env::temp_dir().join("teaclave_runtime_auditor.config.toml");
+    fs::write(&path, contents).unwrap();
+    let config = teaclave_config::RuntimeConfig::from_toml(&path);
+    fs::remove_file(&path).unwrap();
+    config
+}
+
+fn test_runtime_auditor_public_keys() {
+    let config = runtime_config_with_audit(
+        r#"auditor_public_keys = [{ path = "fixtures/auditors/runtime_auditor.public.der" }]"#,
+    )
+    .unwrap();
+    let runtime_key = fs::read("./fixtures/auditors/runtime_auditor.public.der").unwrap();
+    let signature = fs::read("./fixtures/auditors/runtime_auditor.sign.sha256").unwrap();
+    assert_eq!(config.audit.auditor_public_keys_bytes, vec![runtime_key]);
+
+    let enclave_info = &config.audit.enclave_info_bytes;
+    let public_keys = config.audit.auditor_public_keys(&[]);
+    assert!(EnclaveInfo::verify(enclave_info, &public_keys, &[&signature]));
+    assert!(EnclaveInfo::verify_and_new(enclave_info, &public_keys, &[&signature]).is_ok());
+    // Without the runtime key the signature is not accepted
+    let built_in_keys: Vec<&[u8]> = vec![];
+    assert!(!EnclaveInfo::verify(enclave_info, &built_in_keys, &[&signature]));
+
+    // Keys already built in are not duplicated
+    let merged = config.audit.auditor_public_keys(&public_keys);
+    assert_eq!(merged.len(), 1);
+
+    // Files which are not DER encoded are rejected
+    assert!(runtime_config_with_audit(
+        r#"auditor_public_keys = [{ path = "fixtures/enclave_info.toml" }]"#
+    )
+    .is_err());
+    assert!(runtime_config_with_audit(
+        r#"auditor_public_keys = [{ path = "fixtures/auditors/runtime_auditor.sign.sha256" }]"#
+    )
+    .is_err());
+}
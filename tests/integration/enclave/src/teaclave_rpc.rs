@@ -70,6 +70,22 @@ impl TeaclaveService<EchoRequest, EchoResponse> for EchoService {
     }
 }
 
+// Echo service which takes a while to respond
+#[derive(Clone)]
+struct SlowEchoService;
+
+impl TeaclaveService<EchoRequest, EchoResponse> for SlowEchoService {
+    fn handle_request(
+        &self,
+        request: teaclave_rpc::Request<EchoRequest>,
+    ) -> TeaclaveServiceResponseResult<EchoResponse> {
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        EchoService.handle_request(request)
+    }
+}
+
+static SLOW_ECHO_SHUTDOWN_SIGNAL: ShutdownSignal = ShutdownSignal::new();
+
 struct EchoClient {
     channel: SgxTrustedTlsChannel<EchoRequest, EchoResponse>,
 }
@@ -86,7 +102,7 @@ impl EchoClient {
             message: request,
         };
         let response = match self.channel.invoke(request) {
-            Ok(response_result) => response_result,
+            Ok(response_result) => response_result.message,
             Err(_) => {
                 return Err(TeaclaveServiceResponseError::InternalError(
                     "internal".to_string(),
@@ -104,7 +120,26 @@ pub fn run_tests() -> bool {
 
     start_echo_service();
 
-    run_tests!(echo_success)
+    run_tests!(
+        echo_success,
+        shutdown_with_in_flight_request,
+        reject_connections_over_limit,
+        client_cert_allow_list,
+        attested_tls_config_renewal
+    )
+}
+
+fn server_config() -> SgxTrustedTlsServerConfig {
+    let cert = pemfile::certs(&mut io::BufReader::new(
+        fs::File::open(END_FULLCHAIN).unwrap(),
+    ))
+    .unwrap();
+    let private_key =
+        &pemfile::pkcs8_private_keys(&mut io::BufReader::new(fs::File::open(END_KEY).unwrap()))
+            .unwrap()[0];
+    SgxTrustedTlsServerConfig::new()
+        .server_cert(&cert[0].as_ref(), &private_key.0)
+        .unwrap()
 }
 
 fn start_echo_service() {
@@ -112,18 +147,9 @@ fn start_echo_service() {
     use std::thread;
     use std::time::Duration;
     thread::spawn(move || {
-        let cert = pemfile::certs(&mut io::BufReader::new(
-            fs::File::open(END_FULLCHAIN).unwrap(),
-        ))
-        .unwrap();
-        let private_key =
-            &pemfile::pkcs8_private_keys(&mut io::BufReader::new(fs::File::open(END_KEY).unwrap()))
-                .unwrap()[0];
         let addr = "127.0.0.1:12345".parse().unwrap();
-        let config = SgxTrustedTlsServerConfig::new()
-            .server_cert(&cert[0].as_ref(), &private_key.0)
-            .unwrap();
-        let mut server = SgxTrustedTlsServer::<EchoResponse, EchoRequest>::new(addr, config);
+        let mut server =
+            SgxTrustedTlsServer::<EchoResponse, EchoRequest>::new(addr, server_config());
         server.start(EchoService).unwrap();
     });
     thread::sleep(Duration::from_secs(3));
@@ -143,3 +169,140 @@ fn echo_success() {
     assert!(response_result.is_ok());
     assert!(response_result.unwrap().message == "Hello, World!");
 }
+
+fn shutdown_with_in_flight_request() {
+    use std::thread;
+    use std::time::Duration;
+
+    let server_thread = thread::spawn(move || {
+        let addr = "127.0.0.1:12346".parse().unwrap();
+        let mut server =
+            SgxTrustedTlsServer::<EchoResponse, EchoRequest>::new(addr, server_config())
+                .shutdown_signal(&SLOW_ECHO_SHUTDOWN_SIGNAL)
+                .shutdown_timeout(Duration::from_secs(10));
+        server.start(SlowEchoService).unwrap();
+    });
+    thread::sleep(Duration::from_secs(3));
+
+    let client_thread = thread::spawn(move || {
+        let channel = Endpoint::new("localhost:12346").connect().unwrap();
+        let mut client = EchoClient::new(channel).unwrap();
+        let request = SayRequest {
+            message: "Hello, World!".to_string(),
+        };
+        client.say(request)
+    });
+    thread::sleep(Duration::from_secs(1));
+    assert_eq!(SLOW_ECHO_SHUTDOWN_SIGNAL.in_flight(), 1);
+
+    // returns after the in-flight request is drained
+    SLOW_ECHO_SHUTDOWN_SIGNAL.shutdown();
+    assert_eq!(SLOW_ECHO_SHUTDOWN_SIGNAL.in_flight(), 0);
+    server_thread.join().unwrap();
+
+    let response_result = client_thread.join().unwrap();
+    assert!(response_result.is_ok());
+    assert!(response_result.unwrap().message == "Hello, World!");
+
+    // new connections are not accepted
+    assert!(Endpoint::new("localhost:12346").connect().is_err());
+}
+
+fn reject_connections_over_limit() {
+    use std::thread;
+    use std::time::Duration;
+
+    thread::spawn(move || {
+        let addr = "127.0.0.1:12347".parse().unwrap();
+        let mut server =
+            SgxTrustedTlsServer::<EchoResponse, EchoRequest>::new(addr, server_config())
+                .max_connections(Some(1));
+        server.start(SlowEchoService).unwrap();
+    });
+    thread::sleep(Duration::from_secs(3));
+
+    let say = || {
+        let channel = Endpoint::new("localhost:12347").connect().unwrap();
+        let mut client = EchoClient::new(channel).unwrap();
+        let request = SayRequest {
+            message: "Hello, World!".to_string(),
+        };
+        client.say(request)
+    };
+    let client_thread = thread::spawn(say);
+    thread::sleep(Duration::from_secs(1));
+
+    // the connection over the limit is closed
+    assert!(say().is_err());
+    assert!(client_thread.join().unwrap().is_ok());
+
+    // connections are accepted again once the first one is closed
+    thread::sleep(Duration::from_secs(1));
+    assert!(say().is_ok());
+}
+
+fn client_cert_allow_list() {
+    use std::thread;
+    use std::time::Duration;
+
+    let cert = pemfile::certs(&mut io::BufReader::new(
+        fs::File::open(END_FULLCHAIN).unwrap(),
+    ))
+    .unwrap();
+    let private_key =
+        &pemfile::pkcs8_private_keys(&mut io::BufReader::new(fs::File::open(END_KEY).unwrap()))
+            .unwrap()[0];
+    let start_server = |port: u16, fingerprint: String| {
+        thread::spawn(move || {
+            let addr = format!("127.0.0.1:{}", port).parse().unwrap();
+            let server_config = server_config().client_cert_fingerprints(&[fingerprint]);
+            let mut server =
+                SgxTrustedTlsServer::<EchoResponse, EchoRequest>::new(addr, server_config);
+            server.start(EchoService).unwrap();
+        });
+    };
+    start_server(12348, cert_fingerprint(cert[0].as_ref()));
+    start_server(12349, "00".repeat(32));
+    thread::sleep(Duration::from_secs(3));
+
+    let say = |port: u16, client_cert: bool| {
+        let mut config = SgxTrustedTlsClientConfig::new();
+        if client_cert {
+            config = config.client_cert(cert[0].as_ref(), &private_key.0);
+        }
+        let channel = Endpoint::new(&format!("localhost:{}", port))
+            .config(config)
+            .connect()
+            .unwrap();
+        let mut client = EchoClient::new(channel).unwrap();
+        let request = SayRequest {
+            message: "Hello, World!".to_string(),
+        };
+        client.say(request)
+    };
+
+    assert!(say(12348, true).is_ok());
+    assert!(say(12348, false).is_err());
+    assert!(say(12349, true).is_err());
+}
+
+fn attested_tls_config_renewal() {
+    use std::time::Duration;
+    use teaclave_attestation::{AttestationConfig, RemoteAttestation};
+
+    let attested_tls_config = RemoteAttestation::new(AttestationConfig::no_attestation())
+        .renewal_interval(Duration::from_secs(1))
+        .generate_and_endorse()
+        .unwrap()
+        .attested_tls_config()
+        .unwrap();
+    let mut server_config =
+        SgxTrustedTlsServerConfig::from_attested_tls_config(attested_tls_config).unwrap();
+    assert!(!server_config.need_refresh());
+
+    // the server config is refreshed with the renewed report
+    std::thread::sleep(Duration::from_secs(2));
+    assert!(server_config.need_refresh());
+    server_config.refresh_server_config().unwrap();
+    assert!(!server_config.need_refresh());
+}
@@ -0,0 +1,73 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+
+const AUDIT_LOG_QUEUE_KEY: &str = "audit-log";
+
+// An entry of the audit log. Like task events, entries are appended to a
+// queue which is never dequeued, so they can be exported from any offset.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuditEntry {
+    pub timestamp: u64,
+    // Empty for actions not made on behalf of a request
+    pub correlation_id: String,
+    pub action: String,
+    pub detail: String,
+}
+
+impl AuditEntry {
+    pub fn new(
+        timestamp: u64,
+        correlation_id: impl Into<String>,
+        action: impl Into<String>,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            timestamp,
+            correlation_id: correlation_id.into(),
+            action: action.into(),
+            detail: detail.into(),
+        }
+    }
+
+    pub fn to_vec(&self) -> anyhow::Result<Vec<u8>> {
+        let bytes = serde_json::to_vec(self)?;
+        Ok(bytes)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> anyhow::Result<Self> {
+        let obj = serde_json::from_slice(bytes)?;
+        Ok(obj)
+    }
+
+    pub fn get_queue_key() -> &'static str {
+        AUDIT_LOG_QUEUE_KEY
+    }
+
+    // Checksum of a page of serialized entries: the hex encoded SHA-256 of
+    // the entries, each followed by a newline.
+    pub fn page_checksum(entries: &[String]) -> String {
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        for entry in entries {
+            context.update(entry.as_bytes());
+            context.update(b"\n");
+        }
+        hex::encode(context.finish())
+    }
+}
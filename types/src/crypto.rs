@@ -81,6 +81,14 @@ pub enum FileCrypto {
 }
 
 impl FileCrypto {
+    pub const RAW_SCHEMA: &'static str = "raw";
+    pub const SUPPORTED_SCHEMAS: &'static [&'static str] = &[
+        AesGcm128Key::SCHEMA,
+        AesGcm256Key::SCHEMA,
+        TeaclaveFile128Key::SCHEMA,
+        FileCrypto::RAW_SCHEMA,
+    ];
+
     pub fn new(schema: &str, key: &[u8], iv: &[u8]) -> Result<Self> {
         let info = match schema {
             AesGcm128Key::SCHEMA => {
@@ -96,7 +104,7 @@ impl FileCrypto {
                 let crypto = TeaclaveFile128Key::new(key)?;
                 FileCrypto::TeaclaveFile128(crypto)
             }
-            "raw" => FileCrypto::Raw,
+            FileCrypto::RAW_SCHEMA => FileCrypto::Raw,
             _ => bail!("Invalid crypto schema: {}", schema),
         };
 
@@ -108,7 +116,7 @@ impl FileCrypto {
             FileCrypto::AesGcm128(_) => AesGcm128Key::SCHEMA,
             FileCrypto::AesGcm256(_) => AesGcm256Key::SCHEMA,
             FileCrypto::TeaclaveFile128(_) => TeaclaveFile128Key::SCHEMA,
-            FileCrypto::Raw => "raw",
+            FileCrypto::Raw => FileCrypto::RAW_SCHEMA,
         }
     }
 
@@ -120,6 +128,21 @@ impl FileCrypto {
             FileCrypto::Raw => (vec![], vec![]),
         }
     }
+
+    // Hex encoded SHA-256 of the key and IV of AES-GCM files, which identifies
+    // a (key, iv) pair without revealing the key. Other schemas take no IV
+    // from users.
+    pub fn nonce_fingerprint(&self) -> Option<String> {
+        let (key, iv) = match self {
+            FileCrypto::AesGcm128(crypto) => (&crypto.key[..], &crypto.iv[..]),
+            FileCrypto::AesGcm256(crypto) => (&crypto.key[..], &crypto.iv[..]),
+            _ => return None,
+        };
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        context.update(key);
+        context.update(iv);
+        Some(hex::encode(context.finish()))
+    }
 }
 
 impl std::convert::From<AesGcm128Key> for FileCrypto {
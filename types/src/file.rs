@@ -19,12 +19,14 @@ use crate::storage::Storable;
 use crate::{FileAuthTag, FileCrypto, OwnerList};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::prelude::v1::*;
 use url::Url;
 use uuid::Uuid;
 
 const INPUT_FILE_PREFIX: &str = "input";
 const OUTPUT_FILE_PREFIX: &str = "output";
+const INLINE_FILE_SCHEME: &str = "inline";
 
 fn create_uuid() -> Uuid {
     Uuid::new_v4()
@@ -37,6 +39,37 @@ pub struct TeaclaveInputFile {
     pub crypto_info: FileCrypto,
     pub owner: OwnerList,
     pub uuid: Uuid,
+    #[serde(default)]
+    pub cmac_pending: bool,
+    #[serde(default)]
+    pub inline_data: Option<Vec<u8>>,
+    // Size in bytes declared at registration, checked when the file is staged
+    #[serde(default)]
+    pub expected_size: Option<u64>,
+    // X25519 public key of the owner, secrets of the file are only returned
+    // encrypted to this key
+    #[serde(default)]
+    pub recipient_public_key: Option<Vec<u8>>,
+    #[serde(default)]
+    pub metadata: InputFileMetadata,
+    // Registered before the remote file is available, which is checked when
+    // a task consuming the input is invoked
+    #[serde(default)]
+    pub lazy: bool,
+    // Registered from a temporary fusion output
+    #[serde(default)]
+    pub temporary: bool,
+}
+
+// Provenance of an input file recorded at registration.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct InputFileMetadata {
+    // Size in bytes, declared by the owner or of the inline data
+    pub size: Option<u64>,
+    // Unix timestamp in seconds at which the file is registered
+    pub created_at: u64,
+    // Where the data comes from, e.g., the output an input is registered from
+    pub source: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -46,6 +79,16 @@ pub struct TeaclaveOutputFile {
     pub crypto_info: FileCrypto,
     pub owner: OwnerList,
     pub uuid: Uuid,
+    // Unix timestamp in seconds at which the cmac of the output is assigned
+    #[serde(default)]
+    pub finalized_at: Option<u64>,
+    // X25519 public keys of owners, to each of which the crypto info is sealed
+    // separately when the owner gets the file
+    #[serde(default)]
+    pub recipient_public_keys: HashMap<String, Vec<u8>>,
+    // Fusion output which is deleted once a task consuming it finishes
+    #[serde(default)]
+    pub temporary: bool,
 }
 
 impl TeaclaveInputFile {
@@ -61,6 +104,39 @@ impl TeaclaveInputFile {
             crypto_info,
             owner: owner.into(),
             uuid: create_uuid(),
+            cmac_pending: false,
+            inline_data: None,
+            expected_size: None,
+            recipient_public_key: None,
+            metadata: InputFileMetadata::default(),
+            lazy: false,
+            temporary: false,
+        }
+    }
+
+    // Creates an input whose encrypted data is kept in the storage of the
+    // service rather than at a remote location.
+    pub fn new_inline(
+        data: Vec<u8>,
+        cmac: FileAuthTag,
+        crypto_info: FileCrypto,
+        owner: impl Into<OwnerList>,
+    ) -> TeaclaveInputFile {
+        let uuid = create_uuid();
+        let url = Url::parse(&format!("{}:{}", INLINE_FILE_SCHEME, uuid)).unwrap();
+        TeaclaveInputFile {
+            url,
+            cmac,
+            crypto_info,
+            owner: owner.into(),
+            uuid,
+            cmac_pending: false,
+            inline_data: Some(data),
+            expected_size: None,
+            recipient_public_key: None,
+            metadata: InputFileMetadata::default(),
+            lazy: false,
+            temporary: false,
         }
     }
 
@@ -73,9 +149,40 @@ impl TeaclaveInputFile {
             crypto_info: output.crypto_info,
             owner: output.owner,
             uuid: output.uuid,
+            cmac_pending: false,
+            inline_data: None,
+            expected_size: None,
+            recipient_public_key: None,
+            metadata: InputFileMetadata::default(),
+            lazy: false,
+            temporary: output.temporary,
         };
         Ok(input)
     }
+
+    // Creates an input from an output which may not be finished yet. The cmac
+    // of the input is pending until it is resolved from the finished output.
+    pub fn from_output_unverified(output: TeaclaveOutputFile) -> TeaclaveInputFile {
+        TeaclaveInputFile {
+            url: output.url,
+            cmac_pending: output.cmac.is_none(),
+            cmac: output.cmac.unwrap_or_default(),
+            crypto_info: output.crypto_info,
+            owner: output.owner,
+            uuid: output.uuid,
+            inline_data: None,
+            expected_size: None,
+            recipient_public_key: None,
+            metadata: InputFileMetadata::default(),
+            lazy: false,
+            temporary: output.temporary,
+        }
+    }
+
+    pub fn resolve_cmac(&mut self, cmac: FileAuthTag) {
+        self.cmac = cmac;
+        self.cmac_pending = false;
+    }
 }
 
 impl Storable for TeaclaveInputFile {
@@ -100,6 +207,9 @@ impl TeaclaveOutputFile {
             crypto_info,
             owner: owner.into(),
             uuid: create_uuid(),
+            finalized_at: None,
+            recipient_public_keys: HashMap::new(),
+            temporary: false,
         }
     }
 
@@ -19,10 +19,22 @@ use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::prelude::v1::*;
 
+// URL schemes of remote files which can be downloaded and uploaded by the
+// file agent.
+pub const FILE_AGENT_URL_SCHEMES: &[&str] = &["http", "https", "file", "fusion"];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum HandleFileCommand {
     Download,
     Upload,
+    // Deletes the remote file, e.g., the storage of a deleted input
+    Delete,
+    // Posts the local file as JSON to an HTTP endpoint, e.g., a result
+    // callback
+    Post,
+    // Checks the remote file exists without downloading it, e.g., a lazily
+    // registered input
+    Check,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -53,6 +65,12 @@ impl FileAgentRequest {
 pub struct HandleFileInfo {
     pub local: PathBuf,
     pub remote: url::Url,
+    // Number of bytes a downloaded file must have, if known
+    #[serde(default)]
+    pub expected_size: Option<u64>,
+    // Name of a credential in the file agent's credential store
+    #[serde(default)]
+    pub credential: Option<String>,
 }
 
 impl HandleFileInfo {
@@ -60,6 +78,22 @@ impl HandleFileInfo {
         HandleFileInfo {
             local: local.as_ref().to_owned(),
             remote: remote.to_owned(),
+            expected_size: None,
+            credential: None,
+        }
+    }
+
+    pub fn expected_size(self, expected_size: Option<u64>) -> Self {
+        Self {
+            expected_size,
+            ..self
+        }
+    }
+
+    pub fn credential(self, credential: impl ToString) -> Self {
+        Self {
+            credential: Some(credential.to_string()),
+            ..self
         }
     }
 }
@@ -15,8 +15,9 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::{ExecutorType, Storable, UserID};
+use crate::{ExecutorType, ExternalID, FileCrypto, Storable, UserID};
 use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
 use std::prelude::v1::*;
 use uuid::Uuid;
 
@@ -24,6 +25,13 @@ use uuid::Uuid;
 pub struct FunctionInput {
     pub name: String,
     pub description: String,
+    // Optional inputs may be omitted when creating a task
+    #[serde(default)]
+    pub optional: bool,
+    // Inputs of the same group are alternatives, exactly one of them is
+    // assigned when creating a task
+    #[serde(default)]
+    pub exclusive_group: Option<String>,
 }
 
 impl FunctionInput {
@@ -31,6 +39,19 @@ impl FunctionInput {
         Self {
             name: name.into(),
             description: description.into(),
+            optional: false,
+            exclusive_group: None,
+        }
+    }
+
+    pub fn optional(self, optional: bool) -> Self {
+        Self { optional, ..self }
+    }
+
+    pub fn exclusive_group(self, group: impl Into<String>) -> Self {
+        Self {
+            exclusive_group: Some(group.into()),
+            ..self
         }
     }
 }
@@ -39,6 +60,9 @@ impl FunctionInput {
 pub struct FunctionOutput {
     pub name: String,
     pub description: String,
+    // Crypto schema expected for the output file, any schema if not set
+    #[serde(default)]
+    pub crypto_schema: Option<String>,
 }
 
 impl FunctionOutput {
@@ -46,12 +70,85 @@ impl FunctionOutput {
         Self {
             name: name.into(),
             description: description.into(),
+            crypto_schema: None,
+        }
+    }
+
+    pub fn crypto_schema(self, crypto_schema: impl Into<String>) -> Self {
+        Self {
+            crypto_schema: Some(crypto_schema.into()),
+            ..self
+        }
+    }
+
+    pub fn accepts(&self, crypto: &FileCrypto) -> bool {
+        match &self.crypto_schema {
+            Some(schema) => schema == crypto.schema(),
+            None => true,
         }
     }
 }
 
 const FUNCION_PREFIX: &str = "function";
 
+// Format of the return value of a function, which is checked when the result
+// of a task is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum ReturnValueSchema {
+    Utf8,
+    Json,
+}
+
+impl ReturnValueSchema {
+    pub fn validate(&self, value: &[u8]) -> anyhow::Result<()> {
+        match self {
+            ReturnValueSchema::Utf8 => {
+                std::str::from_utf8(value)?;
+            }
+            ReturnValueSchema::Json => {
+                serde_json::from_slice::<serde_json::Value>(value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::convert::TryFrom<&str> for ReturnValueSchema {
+    type Error = anyhow::Error;
+
+    fn try_from(selector: &str) -> anyhow::Result<Self> {
+        let schema = match selector {
+            "utf8" => ReturnValueSchema::Utf8,
+            "json" => ReturnValueSchema::Json,
+            _ => anyhow::bail!("Invalid return value schema: {}", selector),
+        };
+        Ok(schema)
+    }
+}
+
+impl std::convert::TryFrom<String> for ReturnValueSchema {
+    type Error = anyhow::Error;
+
+    fn try_from(selector: String) -> anyhow::Result<Self> {
+        selector.as_str().try_into()
+    }
+}
+
+impl std::convert::From<ReturnValueSchema> for String {
+    fn from(schema: ReturnValueSchema) -> String {
+        format!("{}", schema)
+    }
+}
+
+impl std::fmt::Display for ReturnValueSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReturnValueSchema::Utf8 => write!(f, "utf8"),
+            ReturnValueSchema::Json => write!(f, "json"),
+        }
+    }
+}
+
 #[derive(Default, Debug, Deserialize, Serialize)]
 pub struct Function {
     pub id: Uuid,
@@ -64,6 +161,63 @@ pub struct Function {
     pub inputs: Vec<FunctionInput>,
     pub outputs: Vec<FunctionOutput>,
     pub owner: UserID,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default)]
+    pub replacement: Option<ExternalID>,
+    #[serde(default)]
+    pub return_schema: Option<ReturnValueSchema>,
+    // Hex encoded SHA-256 of the payload, which clients use to check the
+    // stored payload
+    #[serde(default)]
+    pub payload_hash: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Time when the function is soft deleted, which hides it until it is
+    // restored or removed
+    #[serde(default)]
+    pub deleted_at: Option<u64>,
+    // Whether the function is registered from a bundle signed by a trusted
+    // publisher
+    #[serde(default)]
+    pub verified: bool,
+    // Limit on invocations of tasks of the function set by the owner
+    #[serde(default)]
+    pub rate_limit: Option<FunctionRateLimit>,
+    // IV of the payload encrypted at rest by the management service, the
+    // payload is stored in plaintext if empty
+    #[serde(default)]
+    pub payload_iv: Vec<u8>,
+}
+
+// At most max_invocations tasks of a function may be invoked within any
+// window of window_seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct FunctionRateLimit {
+    pub max_invocations: u32,
+    pub window_seconds: u64,
+}
+
+impl FunctionRateLimit {
+    pub fn new(max_invocations: u32, window_seconds: u64) -> Self {
+        Self {
+            max_invocations,
+            window_seconds,
+        }
+    }
+
+    // Drops invocation timestamps out of the window ending at now, and records
+    // an invocation at now if the limit is not reached.
+    pub fn admit(&self, invocations: &mut Vec<u64>, now: u64) -> bool {
+        invocations.retain(|invoked_at| invoked_at + self.window_seconds > now);
+        if invocations.len() >= self.max_invocations as usize {
+            return false;
+        }
+        invocations.push(now);
+        true
+    }
 }
 
 impl Function {
@@ -71,6 +225,14 @@ impl Function {
         Self::default()
     }
 
+    pub fn hash_payload(payload: &[u8]) -> String {
+        hex::encode(ring::digest::digest(&ring::digest::SHA256, payload))
+    }
+
+    pub fn is_payload_encrypted(&self) -> bool {
+        !self.payload_iv.is_empty()
+    }
+
     pub fn id(self, id: Uuid) -> Self {
         Self { id, ..self }
     }
@@ -97,7 +259,11 @@ impl Function {
     }
 
     pub fn payload(self, payload: Vec<u8>) -> Self {
-        Self { payload, ..self }
+        Self {
+            payload_hash: Self::hash_payload(&payload),
+            payload,
+            ..self
+        }
     }
 
     pub fn public(self, public: bool) -> Self {
@@ -122,6 +288,34 @@ impl Function {
             ..self
         }
     }
+
+    pub fn return_schema(self, return_schema: ReturnValueSchema) -> Self {
+        Self {
+            return_schema: Some(return_schema),
+            ..self
+        }
+    }
+
+    pub fn category(self, category: impl ToString) -> Self {
+        Self {
+            category: Some(category.to_string()),
+            ..self
+        }
+    }
+
+    pub fn tags<T: IntoIterator>(self, tags: T) -> Self
+    where
+        <T as IntoIterator>::Item: ToString,
+    {
+        Self {
+            tags: tags.into_iter().map(|x| x.to_string()).collect(),
+            ..self
+        }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
 }
 
 impl Storable for Function {
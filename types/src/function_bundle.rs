@@ -0,0 +1,110 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{ExecutorType, Function, FunctionInput, FunctionOutput, ReturnValueSchema};
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+
+// Description of the function distributed in a bundle, encoded as JSON.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FunctionBundleMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub executor_type: ExecutorType,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    #[serde(default)]
+    pub inputs: Vec<FunctionInput>,
+    #[serde(default)]
+    pub outputs: Vec<FunctionOutput>,
+    #[serde(default)]
+    pub return_schema: Option<ReturnValueSchema>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// A function payload with its metadata signed by a publisher. The signature
+// is RSA PKCS#1 v1.5 with SHA-256 over the length of the metadata as a
+// little-endian u64, the metadata and the payload.
+#[derive(Debug, Default)]
+pub struct FunctionBundle {
+    pub payload: Vec<u8>,
+    pub metadata: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl FunctionBundle {
+    pub fn new(
+        payload: impl Into<Vec<u8>>,
+        metadata: impl Into<Vec<u8>>,
+        signature: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self {
+            payload: payload.into(),
+            metadata: metadata.into(),
+            signature: signature.into(),
+        }
+    }
+
+    pub fn signed_content(&self) -> Vec<u8> {
+        let mut content = (self.metadata.len() as u64).to_le_bytes().to_vec();
+        content.extend_from_slice(&self.metadata);
+        content.extend_from_slice(&self.payload);
+        content
+    }
+
+    // The bundle is accepted if any of the publisher keys verifies it.
+    pub fn verify<T: AsRef<[u8]>>(&self, public_keys: &[T]) -> bool {
+        use ring::signature;
+
+        let content = self.signed_content();
+        public_keys.iter().any(|k| {
+            signature::UnparsedPublicKey::new(&signature::RSA_PKCS1_2048_8192_SHA256, k)
+                .verify(&content, &self.signature)
+                .is_ok()
+        })
+    }
+
+    // Builds the function of a verified bundle, which is public and marked
+    // as verified. The id and owner are left to the caller.
+    pub fn verify_and_open<T: AsRef<[u8]>>(self, public_keys: &[T]) -> Result<Function> {
+        ensure!(
+            self.verify(public_keys),
+            "Invalid function bundle signature"
+        );
+        let metadata: FunctionBundleMetadata = serde_json::from_slice(&self.metadata)?;
+
+        let mut function = Function::new()
+            .name(metadata.name)
+            .description(metadata.description)
+            .executor_type(metadata.executor_type)
+            .payload(self.payload)
+            .public(true)
+            .arguments(metadata.arguments)
+            .inputs(metadata.inputs)
+            .outputs(metadata.outputs)
+            .tags(metadata.tags);
+        function.return_schema = metadata.return_schema;
+        function.category = metadata.category;
+        function.verified = true;
+        Ok(function)
+    }
+}
@@ -0,0 +1,127 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{ExecutorType, ExternalID, Function, FunctionInput, FunctionOutput, ReturnValueSchema};
+use anyhow::{anyhow, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+
+// Definition of a registered function without its payload, which is
+// identified by its hash and supplied separately when the function is
+// imported.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FunctionManifest {
+    pub function_id: ExternalID,
+    pub name: String,
+    pub description: String,
+    pub executor_type: ExecutorType,
+    pub public: bool,
+    pub payload_hash: String,
+    pub arguments: Vec<String>,
+    pub inputs: Vec<FunctionInput>,
+    pub outputs: Vec<FunctionOutput>,
+    #[serde(default)]
+    pub return_schema: Option<ReturnValueSchema>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl From<Function> for FunctionManifest {
+    fn from(function: Function) -> Self {
+        // Functions registered before payload hashes are stored
+        let payload_hash = if function.payload_hash.is_empty() {
+            Function::hash_payload(&function.payload)
+        } else {
+            function.payload_hash.clone()
+        };
+        Self {
+            function_id: function.external_id(),
+            name: function.name,
+            description: function.description,
+            executor_type: function.executor_type,
+            public: function.public,
+            payload_hash,
+            arguments: function.arguments,
+            inputs: function.inputs,
+            outputs: function.outputs,
+            return_schema: function.return_schema,
+            category: function.category,
+            tags: function.tags,
+        }
+    }
+}
+
+impl FunctionManifest {
+    // Builds the function with the payload matching the manifest. The id and
+    // owner are left to the caller.
+    pub fn into_function(self, payload: Vec<u8>) -> Result<Function> {
+        ensure!(
+            Function::hash_payload(&payload) == self.payload_hash,
+            "payload of function {} does not match its hash",
+            self.function_id
+        );
+        let mut function = Function::new()
+            .name(self.name)
+            .description(self.description)
+            .executor_type(self.executor_type)
+            .payload(payload)
+            .public(self.public)
+            .arguments(self.arguments)
+            .inputs(self.inputs)
+            .outputs(self.outputs)
+            .tags(self.tags);
+        function.return_schema = self.return_schema;
+        function.category = self.category;
+        Ok(function)
+    }
+}
+
+// Functions visible to a user, exported as JSON to be documented or imported
+// into another deployment.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FunctionCatalog {
+    pub version: u32,
+    pub functions: Vec<FunctionManifest>,
+}
+
+impl FunctionCatalog {
+    pub const VERSION: u32 = 1;
+
+    pub fn new(functions: Vec<FunctionManifest>) -> Self {
+        Self {
+            version: Self::VERSION,
+            functions,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| anyhow!("cannot export catalog: {}", e))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let catalog: Self =
+            serde_json::from_str(json).map_err(|e| anyhow!("cannot import catalog: {}", e))?;
+        ensure!(
+            catalog.version == Self::VERSION,
+            "unsupported catalog version {}",
+            catalog.version
+        );
+        Ok(catalog)
+    }
+}
@@ -39,6 +39,10 @@ mod file;
 pub use file::*;
 mod function;
 pub use function::*;
+mod function_bundle;
+pub use function_bundle::*;
+mod function_catalog;
+pub use function_catalog::*;
 mod staged_task;
 pub use staged_task::*;
 mod staged_function;
@@ -46,11 +50,21 @@ pub use staged_function::*;
 mod staged_file;
 pub use staged_file::*;
 mod storage;
-pub use storage::Storable;
+pub use storage::{compress_value, namespaced_key, Storable, StorageFormat};
 mod task;
 pub use task::*;
+mod task_template;
+pub use task_template::*;
 mod file_agent;
 pub use file_agent::*;
+mod notification;
+pub use notification::*;
+mod task_event;
+pub use task_event::*;
+mod audit_entry;
+pub use audit_entry::*;
+mod task_reproduction;
+pub use task_reproduction::*;
 mod macros;
 pub use macros::*;
 
@@ -225,13 +239,34 @@ impl From<anyhow::Error> for TeaclaveServiceResponseError {
     }
 }
 
+impl TeaclaveServiceResponseError {
+    // Request error of which the message is followed by the causes of the
+    // error, e.g., "storage error: connection refused", so the root cause of a
+    // failure is kept.
+    pub fn request_error_with_causes(error: &(dyn std::error::Error + 'static)) -> Self {
+        let mut message = error.to_string();
+        let mut source = error.source();
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        TeaclaveServiceResponseError::RequestError(message)
+    }
+}
+
 pub type TeaclaveServiceResponseResult<T> = std::result::Result<T, TeaclaveServiceResponseError>;
 
 #[cfg(feature = "enclave_unit_test")]
 pub mod tests {
     use super::*;
+    use teaclave_test_utils::check_all_passed;
 
     pub fn run_tests() -> bool {
-        worker::tests::run_tests()
+        check_all_passed!(
+            worker::tests::run_tests(),
+            task::tests::run_tests(),
+            storage::tests::run_tests(),
+        )
     }
 }
@@ -0,0 +1,52 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::UserID;
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+
+const NOTIFICATION_QUEUE_PREFIX: &str = "notification";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Notification {
+    pub message: String,
+    pub timestamp: u64,
+}
+
+impl Notification {
+    pub fn new(message: impl ToString, timestamp: u64) -> Self {
+        Self {
+            message: message.to_string(),
+            timestamp,
+        }
+    }
+
+    pub fn to_vec(&self) -> anyhow::Result<Vec<u8>> {
+        let bytes = serde_json::to_vec(self)?;
+        Ok(bytes)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> anyhow::Result<Self> {
+        let obj = serde_json::from_slice(bytes)?;
+        Ok(obj)
+    }
+
+    // Each user has a queue of notifications in the storage service.
+    pub fn get_queue_key(user_id: &UserID) -> String {
+        format!("{}-{}", NOTIFICATION_QUEUE_PREFIX, user_id)
+    }
+}
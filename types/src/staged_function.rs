@@ -15,7 +15,7 @@
 // specific language governing permissions and limitations
 // under the License.
 
-use crate::{Executor, ExecutorType, StagedFiles, TeaclaveRuntime};
+use crate::{Executor, ExecutorType, StagedFiles, TaskCreationError, TeaclaveRuntime};
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -26,9 +26,15 @@ use anyhow::{Context, Result};
 
 pub type FunctionRuntime = Box<dyn TeaclaveRuntime + Send + Sync>;
 
+const REDACTED_ARGUMENT_VALUE: &str = "<redacted>";
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ArgumentValue {
     inner: String,
+    // The value is binary data encoded in base64, which executors decode with
+    // as_bytes.
+    #[serde(default)]
+    binary: bool,
 }
 
 impl From<String> for ArgumentValue {
@@ -57,7 +63,35 @@ impl From<ArgumentValue> for String {
 
 impl ArgumentValue {
     pub fn new(value: String) -> Self {
-        Self { inner: value }
+        Self {
+            inner: value,
+            binary: false,
+        }
+    }
+
+    pub fn binary(value: &[u8]) -> Self {
+        Self::from_base64(base64::encode(value))
+    }
+
+    // The encoding is checked when arguments are validated.
+    pub fn from_base64(value: String) -> Self {
+        Self {
+            inner: value,
+            binary: true,
+        }
+    }
+
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    // Decoded data of binary values, or the bytes of string values.
+    pub fn as_bytes(&self) -> Result<Vec<u8>> {
+        if self.binary {
+            base64::decode(&self.inner).with_context(|| format!("cannot decode {}", self.inner))
+        } else {
+            Ok(self.inner.as_bytes().to_vec())
+        }
     }
 
     pub fn inner(&self) -> &String {
@@ -137,12 +171,96 @@ impl FunctionArguments {
         &mut self.inner
     }
 
+    // Protos carry binary arguments base64-encoded in a separate map.
+    pub fn with_binary(mut self, binary: HashMap<String, String>) -> Self {
+        for (name, value) in binary {
+            self.inner.insert(name, ArgumentValue::from_base64(value));
+        }
+        self
+    }
+
+    // Splits the arguments into string arguments and base64-encoded binary
+    // arguments.
+    pub fn into_string_and_binary(self) -> (HashMap<String, String>, HashMap<String, String>) {
+        let mut strings = HashMap::new();
+        let mut binary = HashMap::new();
+        for (name, value) in self.inner {
+            if value.binary {
+                binary.insert(name, value.inner);
+            } else {
+                strings.insert(name, value.inner);
+            }
+        }
+        (strings, binary)
+    }
+
     pub fn get(&self, key: &str) -> anyhow::Result<&ArgumentValue> {
         self.inner
             .get(key)
             .with_context(|| format!("key not found: {}", key))
     }
 
+    // Returns a copy of the arguments with every value replaced, keeping
+    // only the argument names visible.
+    pub fn redacted(&self) -> Self {
+        let inner = self
+            .inner
+            .keys()
+            .map(|k| (k.to_owned(), ArgumentValue::from(REDACTED_ARGUMENT_VALUE)))
+            .collect();
+        Self { inner }
+    }
+
+    // Arguments are passed to executors, possibly on a command line, so
+    // names and values must be printable and of bounded length.
+    pub fn validate(&self, max_length: usize) -> std::result::Result<(), TaskCreationError> {
+        for (name, value) in self.inner.iter() {
+            for text in &[name.as_str(), value.as_str()] {
+                if text.chars().any(char::is_control) {
+                    return Err(TaskCreationError::ArgumentControlCharacter {
+                        name: name.escape_default().to_string(),
+                    });
+                }
+                if text.len() > max_length {
+                    return Err(TaskCreationError::ArgumentTooLong {
+                        name: name.chars().take(max_length).collect(),
+                        max_length,
+                    });
+                }
+            }
+            if value.binary && base64::decode(&value.inner).is_err() {
+                return Err(TaskCreationError::InvalidBinaryArgument {
+                    name: name.escape_default().to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Arguments are copied into every staged task, so their number and total
+    // size of names and values are bounded.
+    pub fn validate_total(
+        &self,
+        max_count: usize,
+        max_size: usize,
+    ) -> std::result::Result<(), TaskCreationError> {
+        if self.inner.len() > max_count {
+            return Err(TaskCreationError::TooManyArguments { max_count });
+        }
+        if self.total_size() > max_size {
+            return Err(TaskCreationError::ArgumentsTooLarge { max_size });
+        }
+        Ok(())
+    }
+
+    // Total length of the names and values of all arguments
+    pub fn total_size(&self) -> usize {
+        self.inner
+            .iter()
+            .map(|(name, value)| name.len() + value.as_str().len())
+            .sum()
+    }
+
     pub fn into_vec(self) -> Vec<String> {
         let mut vector = Vec::new();
 
@@ -19,17 +19,24 @@ use std::collections::hash_map::{IntoIter, Iter, IterMut};
 use std::collections::HashMap;
 use std::prelude::v1::*;
 
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use url::Url;
 use uuid::Uuid;
 
 use crate::{
     Executor, ExecutorType, FileAuthTag, FileCrypto, FunctionArguments, Storable,
-    TeaclaveInputFile, TeaclaveOutputFile,
+    TaskResourceLimits, TeaclaveInputFile, TeaclaveOutputFile, UserID,
 };
 
 const STAGED_TASK_PREFIX: &str = "staged-"; // staged-task-uuid
 pub const QUEUE_KEY: &str = "staged-task";
+// With fair staging, staged tasks are queued per user and the users with
+// queued tasks take turns in this queue.
+pub const USERS_QUEUE_KEY: &str = "staged-task-users";
+// Users with newly staged tasks, which the scheduler puts in turn unless they
+// are already, so that a user never takes more than one turn per round.
+pub const ARRIVALS_QUEUE_KEY: &str = "staged-task-arrivals";
 
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct FunctionInputFiles {
@@ -43,6 +50,18 @@ impl FunctionInputFiles {
     pub fn iter(&self) -> Iter<String, FunctionInputFile> {
         self.inner.iter()
     }
+
+    pub fn iter_mut(&mut self) -> IterMut<String, FunctionInputFile> {
+        self.inner.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl IntoIterator for FunctionInputFiles {
@@ -129,6 +148,12 @@ pub struct FunctionInputFile {
     pub url: Url,
     pub cmac: FileAuthTag,
     pub crypto_info: FileCrypto,
+    #[serde(default)]
+    pub inline_data: Option<Vec<u8>>,
+    #[serde(default)]
+    pub expected_size: Option<u64>,
+    #[serde(default)]
+    pub sample: Option<InputSample>,
 }
 
 impl FunctionInputFile {
@@ -137,8 +162,58 @@ impl FunctionInputFile {
             url,
             cmac,
             crypto_info: crypto.into(),
+            inline_data: None,
+            expected_size: None,
+            sample: None,
+        }
+    }
+}
+
+// A prefix of an input which the function is run on instead of the whole
+// input. The whole input is still downloaded and verified, the sample is taken
+// from the decrypted content.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum InputSample {
+    FirstBytes(u64),
+    FirstLines(u64),
+}
+
+impl InputSample {
+    pub fn new(unit: &str, limit: u64) -> Result<Self> {
+        match unit {
+            "bytes" => Ok(InputSample::FirstBytes(limit)),
+            "lines" => Ok(InputSample::FirstLines(limit)),
+            _ => bail!("Invalid input sample unit: {}", unit),
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            InputSample::FirstBytes(_) => "bytes",
+            InputSample::FirstLines(_) => "lines",
         }
     }
+
+    pub fn limit(&self) -> u64 {
+        match self {
+            InputSample::FirstBytes(limit) | InputSample::FirstLines(limit) => *limit,
+        }
+    }
+
+    // Lines are terminated by '\n', which is kept in the sample.
+    pub fn apply<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        let len = match *self {
+            InputSample::FirstBytes(limit) => (limit as usize).min(data.len()),
+            InputSample::FirstLines(0) => 0,
+            InputSample::FirstLines(limit) => data
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == b'\n')
+                .nth(limit as usize - 1)
+                .map_or(data.len(), |(index, _)| index + 1),
+        };
+        &data[..len]
+    }
 }
 
 impl From<TeaclaveInputFile> for FunctionInputFile {
@@ -147,6 +222,9 @@ impl From<TeaclaveInputFile> for FunctionInputFile {
             url: file.url,
             cmac: file.cmac,
             crypto_info: file.crypto_info,
+            inline_data: file.inline_data,
+            expected_size: file.expected_size,
+            sample: None,
         }
     }
 }
@@ -186,6 +264,12 @@ pub struct StagedTask {
     pub function_payload: Vec<u8>,
     pub input_data: FunctionInputFiles,
     pub output_data: FunctionOutputFiles,
+    #[serde(default)]
+    pub resource_limits: TaskResourceLimits,
+    // The finished task reproduced by this run, whose result is only compared
+    // with that of the task
+    #[serde(default)]
+    pub reproduction_of: Option<Uuid>,
 }
 
 impl Storable for StagedTask {
@@ -260,7 +344,31 @@ impl StagedTask {
         }
     }
 
+    pub fn resource_limits(self, resource_limits: TaskResourceLimits) -> Self {
+        Self {
+            resource_limits,
+            ..self
+        }
+    }
+
     pub fn get_queue_key() -> &'static str {
         QUEUE_KEY
     }
+
+    pub fn get_users_queue_key() -> &'static str {
+        USERS_QUEUE_KEY
+    }
+
+    pub fn get_user_queue_key(user_id: &UserID) -> String {
+        format!("{}-user-{}", QUEUE_KEY, user_id)
+    }
+
+    pub fn get_arrivals_queue_key() -> &'static str {
+        ARRIVALS_QUEUE_KEY
+    }
+
+    // Present while the user is in the users queue
+    pub fn get_user_turn_key(user_id: &UserID) -> String {
+        format!("{}-turn-{}", QUEUE_KEY, user_id)
+    }
 }
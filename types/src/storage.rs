@@ -16,10 +16,55 @@
 // under the License.
 
 use crate::ExternalID;
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 use std::prelude::v1::*;
 use uuid::Uuid;
 
+// Compressed and bincode values start with a byte which never starts a JSON
+// document, so values of every format coexist in the storage and values stored
+// before another format is enabled are still readable.
+const COMPRESSED_VALUE_MAGIC: u8 = 0x00;
+const BINCODE_VALUE_MAGIC: u8 = 0x01;
+
+// Serialization format of values written to the storage. JSON is readable when
+// debugging. Bincode is faster and smaller, but bincode values are unreadable
+// once their type gains fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StorageFormat {
+    Json,
+    Bincode,
+}
+
+impl Default for StorageFormat {
+    fn default() -> Self {
+        StorageFormat::Json
+    }
+}
+
+impl std::convert::TryFrom<&str> for StorageFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(format: &str) -> anyhow::Result<Self> {
+        let format = match format {
+            "json" => StorageFormat::Json,
+            "bincode" => StorageFormat::Bincode,
+            _ => anyhow::bail!("Unsupported storage format: {}", format),
+        };
+        Ok(format)
+    }
+}
+
+// Compresses a value of any format, which is transparently decompressed by
+// Storable::from_slice.
+pub fn compress_value(bytes: &[u8]) -> Vec<u8> {
+    let compressed = deflate::deflate_bytes(bytes);
+    let mut value = Vec::with_capacity(compressed.len() + 1);
+    value.push(COMPRESSED_VALUE_MAGIC);
+    value.extend_from_slice(&compressed);
+    value
+}
+
 pub trait Storable: Serialize + for<'de> Deserialize<'de> {
     fn key_prefix() -> &'static str;
 
@@ -42,8 +87,46 @@ pub trait Storable: Serialize + for<'de> Deserialize<'de> {
         Ok(bytes)
     }
 
+    fn to_vec_in(&self, format: StorageFormat) -> anyhow::Result<Vec<u8>> {
+        match format {
+            StorageFormat::Json => self.to_vec(),
+            // Types bincode cannot encode, e.g., with flattened fields, are
+            // written as JSON.
+            StorageFormat::Bincode => match bincode::serialize(self) {
+                Ok(encoded) => {
+                    let mut value = Vec::with_capacity(encoded.len() + 1);
+                    value.push(BINCODE_VALUE_MAGIC);
+                    value.extend_from_slice(&encoded);
+                    Ok(value)
+                }
+                Err(_) => self.to_vec(),
+            },
+        }
+    }
+
+    // Serializes the value as deflate compressed JSON, which is transparently
+    // decompressed by from_slice.
+    fn to_vec_compressed(&self) -> anyhow::Result<Vec<u8>> {
+        let bytes = self.to_vec()?;
+        Ok(compress_value(&bytes))
+    }
+
+    // Values of every format are accepted regardless of the format the caller
+    // writes.
     fn from_slice(bytes: &[u8]) -> anyhow::Result<Self> {
-        let obj = serde_json::from_slice(bytes)?;
+        let obj = match bytes.split_first() {
+            Some((&COMPRESSED_VALUE_MAGIC, compressed)) => {
+                let bytes = inflate::inflate_bytes(compressed)
+                    .map_err(|e| anyhow!("Cannot decompress value: {}", e))?;
+                anyhow::ensure!(
+                    bytes.first() != Some(&COMPRESSED_VALUE_MAGIC),
+                    "Value is compressed twice"
+                );
+                Self::from_slice(&bytes)?
+            }
+            Some((&BINCODE_VALUE_MAGIC, encoded)) => bincode::deserialize(encoded)?,
+            _ => serde_json::from_slice(bytes)?,
+        };
         Ok(obj)
     }
 
@@ -51,3 +134,123 @@ pub trait Storable: Serialize + for<'de> Deserialize<'de> {
         ExternalID::new(Self::key_prefix(), self.uuid())
     }
 }
+
+// Keys of different deployments sharing a storage service are separated by
+// prefixing them with the namespace of each deployment. The namespace only
+// applies to raw storage keys, so external IDs and their prefixes checked by
+// `Storable::match_prefix` stay the same in every deployment.
+pub fn namespaced_key(namespace: &str, key: &[u8]) -> Vec<u8> {
+    if namespace.is_empty() {
+        return key.to_vec();
+    }
+    let mut namespaced = Vec::with_capacity(namespace.len() + 1 + key.len());
+    namespaced.extend_from_slice(namespace.as_bytes());
+    namespaced.push(b'/');
+    namespaced.extend_from_slice(key);
+    namespaced
+}
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::{
+        hashmap, FileAuthTag, FileCrypto, Function, FunctionInput, FunctionInputFile,
+        FunctionOutputFile, StagedTask,
+    };
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+    use teaclave_test_utils::*;
+    use url::Url;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_compressed_value,
+            test_legacy_value,
+            test_storage_formats
+        )
+    }
+
+    fn large_staged_task() -> StagedTask {
+        let script = "def entrypoint(argv):\n    return argv[0]\n".repeat(2048);
+        let url = Url::parse("https://external-storage.com/filepath?presigned_token").unwrap();
+        let input_data: HashMap<String, FunctionInputFile> = (0..64)
+            .map(|i| {
+                let file =
+                    FunctionInputFile::new(url.clone(), FileAuthTag::mock(), FileCrypto::default());
+                (format!("input_{}", i), file)
+            })
+            .collect();
+        let output_data: HashMap<String, FunctionOutputFile> = (0..64)
+            .map(|i| {
+                let file = FunctionOutputFile::new(url.clone(), FileCrypto::default());
+                (format!("output_{}", i), file)
+            })
+            .collect();
+        StagedTask::new()
+            .task_id(Uuid::new_v4())
+            .function_name("large_function")
+            .function_arguments(hashmap!("arg" => "data"))
+            .function_payload(script.into_bytes())
+            .input_data(input_data)
+            .output_data(output_data)
+    }
+
+    fn test_compressed_value() {
+        let staged_task = large_staged_task();
+        let uncompressed = staged_task.to_vec().unwrap();
+        let compressed = staged_task.to_vec_compressed().unwrap();
+        log::debug!(
+            "Staged task of {} bytes is compressed to {} bytes",
+            uncompressed.len(),
+            compressed.len()
+        );
+        assert!(compressed.len() * 10 < uncompressed.len());
+
+        let deserialized = StagedTask::from_slice(&compressed).unwrap();
+        assert_eq!(deserialized.task_id, staged_task.task_id);
+        assert_eq!(deserialized.function_payload, staged_task.function_payload);
+        assert_eq!(
+            deserialized.output_data.len(),
+            staged_task.output_data.len()
+        );
+    }
+
+    fn test_legacy_value() {
+        let staged_task = large_staged_task();
+        let legacy = serde_json::to_vec(&staged_task).unwrap();
+        let deserialized = StagedTask::from_slice(&legacy).unwrap();
+        assert_eq!(deserialized.task_id, staged_task.task_id);
+        assert_eq!(deserialized.function_payload, staged_task.function_payload);
+
+        assert!(StagedTask::from_slice(&[COMPRESSED_VALUE_MAGIC, 0xff]).is_err());
+    }
+
+    fn test_storage_formats() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .payload(b"def entrypoint(argv):\n    return argv[0]\n".to_vec())
+            .inputs(vec![FunctionInput::new("input", "input_desc")])
+            .category("statistics")
+            .owner("mock_user");
+
+        for format in &["json", "bincode"] {
+            let format = StorageFormat::try_from(*format).unwrap();
+            let value = function.to_vec_in(format).unwrap();
+            let compressed = compress_value(&value);
+            for bytes in &[value, compressed] {
+                let deserialized = Function::from_slice(bytes).unwrap();
+                assert_eq!(deserialized.id, function.id);
+                assert_eq!(deserialized.payload, function.payload);
+                assert_eq!(deserialized.inputs[0].name, "input");
+                assert_eq!(deserialized.category, function.category);
+                assert_eq!(deserialized.payload_hash, function.payload_hash);
+            }
+        }
+        let json = function.to_vec_in(StorageFormat::Json).unwrap();
+        let bincode = function.to_vec_in(StorageFormat::Bincode).unwrap();
+        assert_eq!(json, function.to_vec().unwrap());
+        assert_eq!(bincode[0], BINCODE_VALUE_MAGIC);
+        assert!(StorageFormat::try_from("protobuf").is_err());
+    }
+}
@@ -21,7 +21,7 @@ use crate::*;
 use anyhow::{anyhow, bail, ensure, Error, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::hash_map::Iter;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryInto;
 use uuid::Uuid;
 
@@ -52,6 +52,48 @@ impl std::fmt::Display for UserID {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum UserRole {
+    User,
+    Admin,
+}
+
+impl std::default::Default for UserRole {
+    fn default() -> Self {
+        UserRole::User
+    }
+}
+
+impl std::convert::TryFrom<&str> for UserRole {
+    type Error = anyhow::Error;
+
+    fn try_from(role: &str) -> Result<Self> {
+        let role = match role {
+            "user" => UserRole::User,
+            "admin" => UserRole::Admin,
+            _ => bail!("Invalid user role: {}", role),
+        };
+        Ok(role)
+    }
+}
+
+impl std::convert::TryFrom<String> for UserRole {
+    type Error = anyhow::Error;
+
+    fn try_from(role: String) -> Result<Self> {
+        role.as_str().try_into()
+    }
+}
+
+impl std::fmt::Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserRole::User => write!(f, "user"),
+            UserRole::Admin => write!(f, "admin"),
+        }
+    }
+}
+
 pub type UserList = OwnerList;
 
 #[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
@@ -124,7 +166,7 @@ impl IntoIterator for OwnerList {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, std::cmp::PartialEq)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, std::cmp::PartialEq)]
 pub enum TaskStatus {
     Created,
     DataAssigned,
@@ -132,6 +174,7 @@ pub enum TaskStatus {
     Staged,
     Running,
     Finished,
+    Canceled,
 }
 
 impl Default for TaskStatus {
@@ -165,6 +208,29 @@ impl OutputsTags {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    pub fn split(self, chunk_size: usize) -> Vec<OutputsTags> {
+        let mut chunks = Vec::new();
+        let mut chunk = HashMap::new();
+        for (k, v) in self.inner {
+            chunk.insert(k, v);
+            if chunk.len() == chunk_size {
+                chunks.push(OutputsTags::new(std::mem::take(&mut chunk)));
+            }
+        }
+        if !chunk.is_empty() {
+            chunks.push(OutputsTags::new(chunk));
+        }
+        chunks
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, tag: FileAuthTag) {
+        self.inner.insert(key.into(), tag);
+    }
+
+    pub fn merge(&mut self, other: OutputsTags) {
+        self.inner.extend(other.inner);
+    }
 }
 
 impl std::convert::TryFrom<HashMap<String, String>> for OutputsTags {
@@ -210,6 +276,23 @@ impl TaskOutputs {
             tags_map: OutputsTags::new(tags_map),
         }
     }
+
+    // Hex encoded SHA-256 of the return value and the output tags in the
+    // order of output names, which is equal for results of identical runs.
+    pub fn digest(&self) -> String {
+        let mut tags: Vec<(&String, &FileAuthTag)> = self.tags_map.iter().collect();
+        tags.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        context.update(&(self.return_value.len() as u64).to_be_bytes());
+        context.update(&self.return_value);
+        for (fname, tag) in tags {
+            context.update(fname.as_bytes());
+            context.update(b"=");
+            context.update(tag.to_hex().as_bytes());
+            context.update(b";");
+        }
+        hex::encode(context.finish())
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -288,6 +371,8 @@ impl std::convert::TryFrom<String> for ExternalID {
 #[derive(Debug, Deserialize, Serialize)]
 pub enum TaskResult {
     NotReady,
+    // Tags of outputs written so far by a running task, which are not final
+    InProgress(OutputsTags),
     Ok(TaskOutputs),
     Err(TaskFailure),
 }
@@ -300,6 +385,13 @@ impl TaskResult {
         }
     }
 
+    pub fn is_err(&self) -> bool {
+        match self {
+            TaskResult::Err(_) => true,
+            _ => false,
+        }
+    }
+
     #[cfg(test_mode)]
     pub fn unwrap(self) -> TaskOutputs {
         match self {
@@ -308,6 +400,9 @@ impl TaskResult {
                 panic!("called `TaskResult::unwrap()` on an `Err` value: {:?}", &e)
             }
             TaskResult::NotReady => panic!("called `TaskResult::unwrap()` on NotReady case"),
+            TaskResult::InProgress(_) => {
+                panic!("called `TaskResult::unwrap()` on InProgress case")
+            }
         }
     }
 }
@@ -345,12 +440,12 @@ where
         match task_result {
             TaskResult::Ok(t) => Some(Ok(t.into())),
             TaskResult::Err(e) => Some(Err(e.into())),
-            TaskResult::NotReady => None,
+            TaskResult::NotReady | TaskResult::InProgress(_) => None,
         }
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct TaskFileOwners {
     inner: HashMap<String, OwnerList>,
 }
@@ -447,6 +542,18 @@ where
         self.inner.keys()
     }
 
+    pub fn iter(&self) -> std::collections::hash_map::Iter<String, T> {
+        self.inner.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::collections::hash_map::IterMut<String, T> {
+        self.inner.iter_mut()
+    }
+
+    pub fn get(&self, fname: &str) -> Option<&T> {
+        self.inner.get(fname)
+    }
+
     pub fn external_ids(&self) -> HashMap<String, ExternalID> {
         self.inner
             .iter()
@@ -498,6 +605,188 @@ impl std::convert::From<TaskFiles<TeaclaveOutputFile>> for FunctionOutputFiles {
 }
 
 const TASK_PREFIX: &str = "task";
+// Results with more tags than this are stored in chunks
+const RESULT_TAGS_CHUNK_SIZE: usize = 1024;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum TaskCreationError {
+    #[error("function_arguments mismatch, missing: {missing:?}, unexpected: {unexpected:?}")]
+    ArgumentMismatch {
+        missing: Vec<String>,
+        unexpected: Vec<String>,
+    },
+    #[error("input keys mismatch, missing: {missing:?}, unexpected: {unexpected:?}")]
+    InputMismatch {
+        missing: Vec<String>,
+        unexpected: Vec<String>,
+    },
+    #[error("output keys mismatch, missing: {missing:?}, unexpected: {unexpected:?}")]
+    OutputMismatch {
+        missing: Vec<String>,
+        unexpected: Vec<String>,
+    },
+    #[error("exactly one input of group {group:?} must be assigned, assigned: {assigned:?}")]
+    ExclusiveInputMismatch {
+        group: String,
+        assigned: Vec<String>,
+    },
+    #[error("function_arguments {name:?} contains control characters")]
+    ArgumentControlCharacter { name: String },
+    #[error("function_arguments {name:?} is not valid base64-encoded binary")]
+    InvalidBinaryArgument { name: String },
+    #[error("function_arguments {name:?} is longer than {max_length} bytes")]
+    ArgumentTooLong { name: String, max_length: usize },
+    #[error("too many function_arguments, at most {max_count} are allowed")]
+    TooManyArguments { max_count: usize },
+    #[error("function_arguments are larger than {max_size} bytes in total")]
+    ArgumentsTooLarge { max_size: usize },
+    #[error("too many labels, at most {max_count} are allowed")]
+    TooManyLabels { max_count: usize },
+    #[error("label {key:?} or its value is not a short identifier")]
+    InvalidLabel { key: String },
+    #[error("fusion file {name:?} must be owned by at least {min_count} users")]
+    TooFewFusionOwners { name: String, min_count: usize },
+    #[error("requested {resource} exceeds the maximum of {max}")]
+    ResourceLimitTooHigh { resource: String, max: u64 },
+    #[error("executor is not specified and no default executor is configured")]
+    MissingExecutor,
+    #[error("default executor {executor} cannot run {executor_type} functions")]
+    ExecutorMismatch {
+        executor: String,
+        executor_type: String,
+    },
+    #[error("task graph node {name:?} does not exist")]
+    UnknownGraphNode { name: String },
+    #[error("input {input_name:?} of task graph node {name:?} is linked more than once")]
+    DuplicateGraphInput { name: String, input_name: String },
+    #[error("task graph contains a cycle")]
+    CyclicTaskGraph,
+}
+
+pub const MAX_TASK_LABELS: usize = 16;
+const MAX_TASK_LABEL_LENGTH: usize = 63;
+
+// Labels are free-form metadata for grouping tasks, which are restricted to
+// short identifiers so that they are safe to log and display.
+pub fn validate_task_labels(
+    labels: &HashMap<String, String>,
+) -> std::result::Result<(), TaskCreationError> {
+    if labels.len() > MAX_TASK_LABELS {
+        return Err(TaskCreationError::TooManyLabels {
+            max_count: MAX_TASK_LABELS,
+        });
+    }
+    for (key, value) in labels.iter() {
+        let key_valid = !key.is_empty()
+            && key.len() <= MAX_TASK_LABEL_LENGTH
+            && key
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c));
+        let value_valid = value.len() <= MAX_TASK_LABEL_LENGTH
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c));
+        if !key_valid || !value_valid {
+            return Err(TaskCreationError::InvalidLabel {
+                key: key.escape_default().take(MAX_TASK_LABEL_LENGTH).collect(),
+            });
+        }
+    }
+    Ok(())
+}
+
+// Files owned by more than one user are fusion files, which must be shared by
+// enough users to meet the privacy policy of the deployment.
+pub fn validate_fusion_owners(
+    owners: &TaskFileOwners,
+    min_count: usize,
+) -> std::result::Result<(), TaskCreationError> {
+    let mut names: Vec<&String> = owners.keys().collect();
+    names.sort();
+    for name in names {
+        let count = owners.get(name).map_or(0, |owner| owner.len());
+        if count > 1 && count < min_count {
+            return Err(TaskCreationError::TooFewFusionOwners {
+                name: name.to_string(),
+                min_count,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Tasks not specifying an executor use the default executor of the
+// deployment, which must be able to run the function.
+pub fn resolve_task_executor(
+    requested: Option<Executor>,
+    default: Option<Executor>,
+    executor_type: ExecutorType,
+) -> std::result::Result<Executor, TaskCreationError> {
+    if let Some(executor) = requested {
+        return Ok(executor);
+    }
+    let executor = default.ok_or(TaskCreationError::MissingExecutor)?;
+    if executor.executor_type() != executor_type {
+        return Err(TaskCreationError::ExecutorMismatch {
+            executor: executor.to_string(),
+            executor_type: executor_type.to_string(),
+        });
+    }
+    Ok(executor)
+}
+
+// Resources a task may use when it is executed. The execution service fails a
+// task exceeding its runtime, the memory limit is carried in staged tasks for
+// executors which can bound their memory.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct TaskResourceLimits {
+    pub max_memory_mb: Option<u64>,
+    pub max_runtime_seconds: Option<u64>,
+}
+
+impl TaskResourceLimits {
+    // Checks the requested limits against the maxima of the deployment, which
+    // also apply to tasks not requesting a limit.
+    pub fn bounded_by(
+        self,
+        maximum: &TaskResourceLimits,
+    ) -> std::result::Result<Self, TaskCreationError> {
+        fn bound(
+            resource: &str,
+            requested: Option<u64>,
+            max: Option<u64>,
+        ) -> std::result::Result<Option<u64>, TaskCreationError> {
+            match (requested, max) {
+                (Some(requested), Some(max)) if requested > max => {
+                    Err(TaskCreationError::ResourceLimitTooHigh {
+                        resource: resource.to_string(),
+                        max,
+                    })
+                }
+                (requested, max) => Ok(requested.or(max)),
+            }
+        }
+
+        Ok(Self {
+            max_memory_mb: bound("max_memory_mb", self.max_memory_mb, maximum.max_memory_mb)?,
+            max_runtime_seconds: bound(
+                "max_runtime_seconds",
+                self.max_runtime_seconds,
+                maximum.max_runtime_seconds,
+            )?,
+        })
+    }
+}
+
+// Returns names required by the spec but absent in the request, and names
+// present in the request but not in the spec, both sorted.
+fn diff_names(spec: &HashSet<&String>, req: &HashSet<&String>) -> (Vec<String>, Vec<String>) {
+    let mut missing: Vec<String> = spec.difference(req).map(|s| s.to_string()).collect();
+    let mut unexpected: Vec<String> = req.difference(spec).map(|s| s.to_string()).collect();
+    missing.sort();
+    unexpected.sort();
+    (missing, unexpected)
+}
 
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Task {
@@ -515,6 +804,193 @@ pub struct Task {
     pub assigned_outputs: TaskFiles<TeaclaveOutputFile>,
     pub result: TaskResult,
     pub status: TaskStatus,
+    pub approval_deadline: Option<u64>,
+    #[serde(default)]
+    pub result_tags_chunks: usize,
+    #[serde(default)]
+    pub output_links: Vec<TaskLink>,
+    #[serde(default)]
+    pub return_schema: Option<ReturnValueSchema>,
+    #[serde(default)]
+    pub cancel_requested: bool,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    #[serde(default)]
+    pub resource_limits: TaskResourceLimits,
+    // Approvals of the creator do not count toward the quorum of the task
+    #[serde(default)]
+    pub approval_excludes_creator: bool,
+    // Number of times the task has been retried after failing
+    #[serde(default)]
+    pub retries: u32,
+    // Whether the execution log of the task is stored with log_key
+    #[serde(default)]
+    pub log_stored: bool,
+    // Tags of the result moved to cold storage
+    #[serde(default)]
+    pub result_archive: Option<ResultArchive>,
+    // Fingerprint of the inputs of the last successful run
+    #[serde(default)]
+    pub last_run_inputs_fingerprint: Option<String>,
+    // Payload hash of the function staged for the last run
+    #[serde(default)]
+    pub last_run_function_hash: Option<String>,
+    // Inputs of which the task is only run on a sample, so its results are
+    // not those of a run on the full inputs
+    #[serde(default)]
+    pub input_samples: HashMap<String, InputSample>,
+}
+
+// Location of the archived tags_map of a task result, with the SHA-256 digest
+// of the archive to verify it when it is restored.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ResultArchive {
+    pub url: url::Url,
+    pub digest: String,
+    // IV of the encrypted archive, empty if the archive is stored in plaintext
+    #[serde(default)]
+    pub iv: Vec<u8>,
+}
+
+// An output of a task which is assigned as an input of another task once the
+// task is finished.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TaskLink {
+    pub output_name: String,
+    pub consumer_task_id: ExternalID,
+    pub input_name: String,
+}
+
+// An edge of a task graph, which links an output of the producer node to an
+// input of the consumer node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskGraphEdge {
+    pub producer: String,
+    pub output_name: String,
+    pub consumer: String,
+    pub input_name: String,
+}
+
+impl TaskGraphEdge {
+    pub fn new(
+        producer: impl ToString,
+        output_name: impl ToString,
+        consumer: impl ToString,
+        input_name: impl ToString,
+    ) -> Self {
+        Self {
+            producer: producer.to_string(),
+            output_name: output_name.to_string(),
+            consumer: consumer.to_string(),
+            input_name: input_name.to_string(),
+        }
+    }
+}
+
+// Edges of a task graph must connect its nodes without forming a cycle, and an
+// input can only be linked once. Shapes of linked files are checked when the
+// tasks are linked.
+pub fn validate_task_graph(
+    nodes: &HashSet<String>,
+    edges: &[TaskGraphEdge],
+) -> std::result::Result<(), TaskCreationError> {
+    let mut in_degrees: HashMap<&str, usize> = nodes.iter().map(|n| (n.as_str(), 0)).collect();
+    let mut linked_inputs = HashSet::new();
+    for edge in edges {
+        for name in &[&edge.producer, &edge.consumer] {
+            if !nodes.contains(*name) {
+                return Err(TaskCreationError::UnknownGraphNode {
+                    name: name.to_string(),
+                });
+            }
+        }
+        if !linked_inputs.insert((&edge.consumer, &edge.input_name)) {
+            return Err(TaskCreationError::DuplicateGraphInput {
+                name: edge.consumer.clone(),
+                input_name: edge.input_name.clone(),
+            });
+        }
+        if let Some(in_degree) = in_degrees.get_mut(edge.consumer.as_str()) {
+            *in_degree += 1;
+        }
+    }
+
+    // Nodes are visited once all of their producers are visited, nodes on a
+    // cycle are never visited.
+    let mut ready: Vec<&str> = in_degrees
+        .iter()
+        .filter(|(_, in_degree)| **in_degree == 0)
+        .map(|(name, _)| *name)
+        .collect();
+    let mut visited = 0;
+    while let Some(name) = ready.pop() {
+        visited += 1;
+        for edge in edges.iter().filter(|edge| edge.producer == name) {
+            if let Some(in_degree) = in_degrees.get_mut(edge.consumer.as_str()) {
+                *in_degree -= 1;
+                if *in_degree == 0 {
+                    ready.push(edge.consumer.as_str());
+                }
+            }
+        }
+    }
+    if visited != nodes.len() {
+        return Err(TaskCreationError::CyclicTaskGraph);
+    }
+    Ok(())
+}
+
+// A self-contained definition of a task, which can be exported from one
+// deployment and imported into another. Function arguments may be secrets and
+// are only included on request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TaskExport {
+    pub version: u32,
+    pub function_id: ExternalID,
+    pub executor: Executor,
+    #[serde(default)]
+    pub function_arguments: Option<FunctionArguments>,
+    pub inputs_ownership: TaskFileOwners,
+    pub outputs_ownership: TaskFileOwners,
+    pub participants: UserList,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+impl TaskExport {
+    pub const VERSION: u32 = 1;
+
+    pub fn new(task: &Task, include_arguments: bool) -> Self {
+        Self {
+            version: Self::VERSION,
+            function_id: task.function_id.clone(),
+            executor: task.executor,
+            function_arguments: if include_arguments {
+                Some(task.function_arguments.clone())
+            } else {
+                None
+            },
+            inputs_ownership: task.inputs_ownership.clone(),
+            outputs_ownership: task.outputs_ownership.clone(),
+            participants: task.participants.clone(),
+            labels: task.labels.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| anyhow!("cannot export task: {}", e))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let export: Self =
+            serde_json::from_str(json).map_err(|e| anyhow!("cannot import task: {}", e))?;
+        ensure!(
+            export.version == Self::VERSION,
+            "unsupported task export version {}",
+            export.version
+        );
+        Ok(export)
+    }
 }
 
 impl Storable for Task {
@@ -535,7 +1011,7 @@ impl Task {
         req_input_owners: impl Into<TaskFileOwners>,
         req_output_owners: impl Into<TaskFileOwners>,
         function: Function,
-    ) -> Result<Self> {
+    ) -> std::result::Result<Self, TaskCreationError> {
         let req_input_owners = req_input_owners.into();
         let req_output_owners = req_output_owners.into();
 
@@ -551,17 +1027,66 @@ impl Task {
         //check function compatibility
         let fn_args_spec: HashSet<&String> = function.arguments.iter().collect();
         let req_args: HashSet<&String> = req_func_args.inner().keys().collect();
-        ensure!(fn_args_spec == req_args, "function_arguments mismatch");
+        if fn_args_spec != req_args {
+            let (missing, unexpected) = diff_names(&fn_args_spec, &req_args);
+            return Err(TaskCreationError::ArgumentMismatch {
+                missing,
+                unexpected,
+            });
+        }
 
         // check input fkeys
+        // optional inputs and alternatives of exclusive groups may be omitted,
+        // they are then left out of the task
         let inputs_spec: HashSet<&String> = function.inputs.iter().map(|f| &f.name).collect();
         let req_input_fkeys: HashSet<&String> = req_input_owners.keys().collect();
-        ensure!(inputs_spec == req_input_fkeys, "input keys mismatch");
+        let omitted_optional: HashSet<&String> = function
+            .inputs
+            .iter()
+            .filter(|f| {
+                (f.optional || f.exclusive_group.is_some()) && !req_input_fkeys.contains(&f.name)
+            })
+            .map(|f| &f.name)
+            .collect();
+        let required_inputs: HashSet<&String> =
+            inputs_spec.difference(&omitted_optional).cloned().collect();
+        if required_inputs != req_input_fkeys {
+            let (missing, unexpected) = diff_names(&required_inputs, &req_input_fkeys);
+            return Err(TaskCreationError::InputMismatch {
+                missing,
+                unexpected,
+            });
+        }
+
+        // check exclusive input groups
+        let mut groups: BTreeMap<&String, Vec<String>> = BTreeMap::new();
+        for input in function.inputs.iter() {
+            if let Some(group) = &input.exclusive_group {
+                let assigned = groups.entry(group).or_default();
+                if req_input_fkeys.contains(&input.name) {
+                    assigned.push(input.name.clone());
+                }
+            }
+        }
+        for (group, assigned) in groups {
+            if assigned.len() != 1 {
+                return Err(TaskCreationError::ExclusiveInputMismatch {
+                    group: group.clone(),
+                    assigned,
+                });
+            }
+        }
 
         // check output fkeys
         let outputs_spec: HashSet<&String> = function.outputs.iter().map(|f| &f.name).collect();
         let req_output_fkeys: HashSet<&String> = req_output_owners.keys().collect();
-        ensure!(outputs_spec == req_output_fkeys, "output keys mismatch");
+        if outputs_spec != req_output_fkeys {
+            let (missing, unexpected) = diff_names(&outputs_spec, &req_output_fkeys);
+            return Err(TaskCreationError::OutputMismatch {
+                missing,
+                unexpected,
+            });
+        }
 
         // Skip the assignment if no file is required
         let status = if req_input_owners.is_empty() && req_output_owners.is_empty() {
@@ -581,30 +1106,152 @@ impl Task {
             outputs_ownership: req_output_owners,
             participants,
             status,
+            return_schema: function.return_schema,
             ..Default::default()
         };
 
         Ok(task)
     }
 
-    pub fn approve(&mut self, requester: &UserID) -> Result<()> {
-        ensure!(
-            self.status == TaskStatus::DataAssigned,
-            "Unexpected task status when approving: {:?}",
-            self.status
-        );
-
+    // Returns false without changing the task if the requester has already
+    // approved it, so that retried approvals are harmless.
+    pub fn approve(&mut self, requester: &UserID) -> Result<bool> {
         ensure!(
             self.participants.contains(requester),
             "Unexpected user trying to approve a task: {:?}",
             requester
         );
 
+        if self.approved_users.contains(requester) {
+            return Ok(false);
+        }
+
+        ensure!(
+            self.status == TaskStatus::DataAssigned,
+            "Unexpected task status when approving: {:?}",
+            self.status
+        );
+
         self.approved_users.insert(requester.clone());
-        if self.participants == self.approved_users {
+        if self.pending_approvals().is_empty() {
             self.update_status(TaskStatus::Approved);
         }
 
+        Ok(true)
+    }
+
+    // Participants whose approvals are required. If the creator is excluded,
+    // the task cannot be approved by the creator alone, unless it is the only
+    // participant.
+    fn approval_quorum(&self) -> UserList {
+        let mut quorum = self.participants.clone();
+        if self.approval_excludes_creator && quorum.len() > 1 {
+            quorum.uids.remove(&self.creator);
+        }
+        quorum
+    }
+
+    // Participants who have not approved the task yet
+    pub fn pending_approvals(&self) -> UserList {
+        let uids = self
+            .approval_quorum()
+            .uids
+            .difference(&self.approved_users.uids)
+            .cloned()
+            .collect();
+        UserList { uids }
+    }
+
+    pub fn awaiting_approval(&self) -> bool {
+        self.status == TaskStatus::Created || self.status == TaskStatus::DataAssigned
+    }
+
+    pub fn approval_window_closed(&self, now: u64) -> bool {
+        match self.approval_deadline {
+            Some(deadline) => now > deadline,
+            None => false,
+        }
+    }
+
+    // Returns true if the task has been canceled because its approval
+    // deadline has passed before all participants approved it.
+    pub fn cancel_if_approval_expired(&mut self, now: u64) -> bool {
+        if self.awaiting_approval() && self.approval_window_closed(now) {
+            self.update_status(TaskStatus::Canceled);
+            return true;
+        }
+        false
+    }
+
+    // Tasks which are not staged yet are canceled at once and true is
+    // returned. Staged or running tasks are only marked for cancellation. It
+    // is best-effort: the executor checks the mark between the steps of
+    // running a task and acknowledges it, but a task which has already
+    // finished its last step is finished as usual.
+    pub fn request_cancel(&mut self) -> Result<bool> {
+        match self.status {
+            TaskStatus::Created | TaskStatus::DataAssigned | TaskStatus::Approved => {
+                self.update_status(TaskStatus::Canceled);
+                Ok(true)
+            }
+            TaskStatus::Staged | TaskStatus::Running => {
+                self.cancel_requested = true;
+                Ok(false)
+            }
+            _ => bail!("Unexpected task status when canceled: {:?}", self.status),
+        }
+    }
+
+    pub fn acknowledge_cancel(&mut self) -> Result<()> {
+        ensure!(
+            self.cancel_requested,
+            "Cancellation of the task is not requested"
+        );
+        ensure!(
+            self.status == TaskStatus::Staged || self.status == TaskStatus::Running,
+            "Unexpected task status when cancellation acknowledged: {:?}",
+            self.status
+        );
+        self.update_status(TaskStatus::Canceled);
+        Ok(())
+    }
+
+    // Fails a staged or running task which can no longer be finished by an
+    // executor, e.g., the staged task is lost.
+    pub fn abort(&mut self, reason: impl ToString) -> Result<()> {
+        ensure!(
+            self.status == TaskStatus::Staged || self.status == TaskStatus::Running,
+            "Unexpected task status when aborted: {:?}",
+            self.status
+        );
+        self.result = TaskResult::Err(TaskFailure::new(reason.to_string()));
+        self.update_status(TaskStatus::Finished);
+        Ok(())
+    }
+
+    // A failed task is approved again to be re-invoked with the same data and
+    // approvals, at most max_retries times.
+    pub fn retry(&mut self, requester: &UserID, max_retries: u32) -> Result<()> {
+        ensure!(
+            &self.creator == requester,
+            "Unexpected user trying to retry a task: {:?}",
+            requester
+        );
+        ensure!(
+            self.status == TaskStatus::Finished,
+            "Unexpected task status when retried: {:?}",
+            self.status
+        );
+        ensure!(self.result.is_err(), "Only failed tasks can be retried");
+        ensure!(
+            self.retries < max_retries,
+            "Task has been retried {} times",
+            self.retries
+        );
+        self.retries += 1;
+        self.result = TaskResult::NotReady;
+        self.cancel_requested = false;
+        self.update_status(TaskStatus::Approved);
         Ok(())
     }
 
@@ -618,17 +1265,83 @@ impl Task {
         Ok(())
     }
 
+    // Running tasks may expose the tags of outputs written so far. The outputs
+    // are not finalized, so they cannot be consumed as inputs until the task
+    // finishes.
+    pub fn update_partial_output(&mut self, fname: &str, auth_tag: FileAuthTag) -> Result<()> {
+        ensure!(
+            self.status == TaskStatus::Running,
+            "Unexpected task status when updating partial outputs: {:?}",
+            self.status
+        );
+        ensure!(
+            self.assigned_outputs.get(fname).is_some(),
+            "Partial output not found: {:?}",
+            fname
+        );
+        match &mut self.result {
+            TaskResult::InProgress(tags) => tags.insert(fname, auth_tag),
+            result => {
+                let mut tags = OutputsTags::default();
+                tags.insert(fname, auth_tag);
+                *result = TaskResult::InProgress(tags);
+            }
+        }
+        Ok(())
+    }
+
     pub fn finish(&mut self, result: TaskResult) -> Result<()> {
         ensure!(
             self.status == TaskStatus::Running,
             "Unexpected task status when invoked: {:?}",
             self.status
         );
-        self.result = result;
+        self.result = self.check_return_schema(result);
+        if self.result.is_ok() {
+            self.last_run_inputs_fingerprint = Some(self.inputs_fingerprint());
+        }
         self.status = TaskStatus::Finished;
         Ok(())
     }
 
+    // Hex encoded SHA-256 of the names and cmacs of the assigned inputs in
+    // the order of their names.
+    pub fn inputs_fingerprint(&self) -> String {
+        let mut inputs: Vec<(&String, &TeaclaveInputFile)> =
+            self.assigned_inputs.inner.iter().collect();
+        inputs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        for (fname, file) in inputs {
+            context.update(fname.as_bytes());
+            context.update(b"=");
+            context.update(file.cmac.to_hex().as_bytes());
+            context.update(b";");
+        }
+        hex::encode(context.finish())
+    }
+
+    // A successfully finished task need not be run again if the cmacs of its
+    // inputs are the same as in its last run.
+    pub fn inputs_unchanged_since_last_run(&self) -> bool {
+        self.status == TaskStatus::Finished
+            && self.result.is_ok()
+            && self.last_run_inputs_fingerprint.as_deref() == Some(&self.inputs_fingerprint())
+    }
+
+    // The task fails if its return value does not conform to the schema
+    // declared by the function.
+    fn check_return_schema(&self, result: TaskResult) -> TaskResult {
+        if let (TaskResult::Ok(outputs), Some(schema)) = (&result, self.return_schema) {
+            if let Err(e) = schema.validate(&outputs.return_value) {
+                return TaskResult::Err(TaskFailure::new(format!(
+                    "return value does not match schema {}: {}",
+                    schema, e
+                )));
+            }
+        }
+        result
+    }
+
     pub fn stage_for_running(
         &mut self,
         requester: &UserID,
@@ -644,21 +1357,67 @@ impl Task {
             "Unexpected task status when invoked: {:?}",
             self.status
         );
-        let function_arguments = self.function_arguments.clone();
-        let staged_task = StagedTask {
+        self.last_run_function_hash = Some(function.payload_hash.clone());
+        let staged_task = self.staged_task(function, self.assigned_outputs.clone().into());
+
+        self.update_status(TaskStatus::Staged);
+        Ok(staged_task)
+    }
+
+    // Stages a successfully finished task again with the same function,
+    // arguments and inputs. The outputs are written to the given urls
+    // instead, so the outputs of the task are left untouched.
+    pub fn stage_for_reproduction(
+        &self,
+        reproduction_id: Uuid,
+        function: Function,
+        output_urls: &HashMap<String, url::Url>,
+    ) -> Result<StagedTask> {
+        ensure!(
+            self.inputs_unchanged_since_last_run(),
+            "Only successfully finished tasks with unchanged inputs can be reproduced"
+        );
+        ensure!(
+            self.last_run_function_hash.as_deref() == Some(&function.payload_hash),
+            "Function of the last run is unknown or changed"
+        );
+        let mut output_data: FunctionOutputFiles = self.assigned_outputs.clone().into();
+        for (fname, file) in output_data.iter_mut() {
+            let url = output_urls
+                .get(fname)
+                .ok_or_else(|| anyhow!("Missing url of reproduced output: {:?}", fname))?;
+            ensure!(
+                url != &file.url,
+                "Reproduced output overwrites the output of the task: {:?}",
+                fname
+            );
+            file.url = url.clone();
+        }
+
+        let mut staged_task = self.staged_task(function, output_data);
+        staged_task.task_id = reproduction_id;
+        staged_task.reproduction_of = Some(self.task_id);
+        Ok(staged_task)
+    }
+
+    fn staged_task(&self, function: Function, output_data: FunctionOutputFiles) -> StagedTask {
+        let mut input_data: FunctionInputFiles = self.assigned_inputs.clone().into();
+        for (fname, file) in input_data.iter_mut() {
+            file.sample = self.input_samples.get(fname).copied();
+        }
+        StagedTask {
             task_id: self.task_id,
             executor: self.executor,
             executor_type: function.executor_type,
             function_id: function.id,
             function_name: function.name,
             function_payload: function.payload,
-            function_arguments,
-            input_data: self.assigned_inputs.clone().into(),
-            output_data: self.assigned_outputs.clone().into(),
-        };
-
-        self.update_status(TaskStatus::Staged);
-        Ok(staged_task)
+            function_arguments: self.function_arguments.clone(),
+            input_data,
+            output_data,
+            resource_limits: self.resource_limits,
+            reproduction_of: None,
+        }
     }
 
     pub fn assign_input(
@@ -679,6 +1438,42 @@ impl Task {
             file.external_id()
         );
 
+        self.assign_input_file(fname, file)
+    }
+
+    // Assigns an input from the output of a linked task. There is no
+    // requester, the ownership is checked against the task only.
+    pub fn assign_linked_input(&mut self, fname: &str, file: TeaclaveInputFile) -> Result<()> {
+        ensure!(
+            self.status == TaskStatus::Created,
+            "Unexpected task status during linked input assignment: {:?}",
+            self.status
+        );
+
+        self.assign_input_file(fname, file)
+    }
+
+    // Only assigned inputs can be sampled, before the task is approved.
+    pub fn sample_input(&mut self, fname: &str, sample: InputSample) -> Result<()> {
+        ensure!(
+            self.status == TaskStatus::Created || self.status == TaskStatus::DataAssigned,
+            "Unexpected task status when sampling inputs: {:?}",
+            self.status
+        );
+        ensure!(
+            self.assigned_inputs.get(fname).is_some(),
+            "Sample: input not assigned: {:?}",
+            fname
+        );
+        self.input_samples.insert(fname.to_string(), sample);
+        Ok(())
+    }
+
+    pub fn sampled(&self) -> bool {
+        !self.input_samples.is_empty()
+    }
+
+    fn assign_input_file(&mut self, fname: &str, file: TeaclaveInputFile) -> Result<()> {
         self.inputs_ownership.check(fname, &file.owner)?;
 
         self.assigned_inputs.assign(fname, file)?;
@@ -689,6 +1484,47 @@ impl Task {
         Ok(())
     }
 
+    pub fn link_output(
+        &mut self,
+        output_name: &str,
+        consumer: &Task,
+        input_name: &str,
+    ) -> Result<()> {
+        ensure!(
+            self.status != TaskStatus::Finished && self.status != TaskStatus::Canceled,
+            "Link: unexpected producer task status: {:?}",
+            self.status
+        );
+        ensure!(
+            consumer.status == TaskStatus::Created,
+            "Link: unexpected consumer task status: {:?}",
+            consumer.status
+        );
+
+        let output_owners = self
+            .outputs_ownership
+            .get(output_name)
+            .ok_or_else(|| anyhow!("Link: output not exist. {:?}", output_name))?;
+        consumer.inputs_ownership.check(input_name, output_owners)?;
+
+        let consumer_task_id = consumer.external_id();
+        ensure!(
+            consumer.assigned_inputs.get(input_name).is_none()
+                && !self.output_links.iter().any(|link| {
+                    link.consumer_task_id == consumer_task_id && link.input_name == input_name
+                }),
+            "Link: input already assigned. {:?}",
+            input_name
+        );
+
+        self.output_links.push(TaskLink {
+            output_name: output_name.to_string(),
+            consumer_task_id,
+            input_name: input_name.to_string(),
+        });
+        Ok(())
+    }
+
     pub fn assign_output(
         &mut self,
         requester: &UserID,
@@ -717,6 +1553,45 @@ impl Task {
         Ok(())
     }
 
+    // A large tags_map of the result may exceed the size limit of one storage
+    // entry. This moves the tags out of the task in chunks, which should be
+    // stored separately with keys from result_tags_chunk_key.
+    pub fn take_result_tags_chunks(&mut self) -> Vec<OutputsTags> {
+        match &mut self.result {
+            TaskResult::Ok(outputs) if outputs.tags_map.len() > RESULT_TAGS_CHUNK_SIZE => {
+                let tags_map = std::mem::take(&mut outputs.tags_map);
+                let chunks = tags_map.split(RESULT_TAGS_CHUNK_SIZE);
+                self.result_tags_chunks = chunks.len();
+                chunks
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn restore_result_tags_chunks(&mut self, chunks: Vec<OutputsTags>) -> Result<()> {
+        ensure!(
+            chunks.len() == self.result_tags_chunks,
+            "Unexpected number of result tags chunks: {:?}",
+            chunks.len()
+        );
+        if let TaskResult::Ok(outputs) = &mut self.result {
+            for chunk in chunks {
+                outputs.tags_map.merge(chunk);
+            }
+        }
+        self.result_tags_chunks = 0;
+        Ok(())
+    }
+
+    pub fn result_tags_chunk_key(&self, index: usize) -> Vec<u8> {
+        format!("{}-tags-{}", self.key_string(), index).into_bytes()
+    }
+
+    // Execution logs may be large, so they are stored separately from the task.
+    pub fn log_key(&self) -> Vec<u8> {
+        format!("{}-log", self.key_string()).into_bytes()
+    }
+
     fn update_status(&mut self, status: TaskStatus) {
         self.status = status;
     }
@@ -737,3 +1612,318 @@ impl Task {
         true
     }
 }
+
+#[cfg(feature = "enclave_unit_test")]
+pub mod tests {
+    use super::*;
+    use crate::hashmap;
+    use teaclave_test_utils::*;
+    use url::Url;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_task_argument_mismatch,
+            test_task_input_mismatch,
+            test_task_output_mismatch,
+            test_task_approve_twice,
+            test_task_result_tags_chunks,
+            test_task_link_output,
+            test_task_return_schema,
+            test_task_cancel,
+            test_task_labels,
+            test_task_partial_output,
+        )
+    }
+
+    fn mock_function() -> Function {
+        Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .arguments(vec!["arg".to_string()])
+            .inputs(vec![FunctionInput::new("input", "input_desc")])
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user")
+    }
+
+    fn test_task_argument_mismatch() {
+        let err = Task::new(
+            UserID::from("mock_user"),
+            Executor::MesaPy,
+            FunctionArguments::new(hashmap!("arg2" => "data")),
+            hashmap!("input" => vec!["mock_user"]),
+            hashmap!("output" => vec!["mock_user"]),
+            mock_function(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            TaskCreationError::ArgumentMismatch {
+                missing: vec!["arg".to_string()],
+                unexpected: vec!["arg2".to_string()],
+            }
+        );
+    }
+
+    fn test_task_input_mismatch() {
+        let err = Task::new(
+            UserID::from("mock_user"),
+            Executor::MesaPy,
+            FunctionArguments::new(hashmap!("arg" => "data")),
+            HashMap::<String, OwnerList>::new(),
+            hashmap!("output" => vec!["mock_user"]),
+            mock_function(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            TaskCreationError::InputMismatch {
+                missing: vec!["input".to_string()],
+                unexpected: vec![],
+            }
+        );
+    }
+
+    fn test_task_output_mismatch() {
+        let err = Task::new(
+            UserID::from("mock_user"),
+            Executor::MesaPy,
+            FunctionArguments::new(hashmap!("arg" => "data")),
+            hashmap!("input" => vec!["mock_user"]),
+            hashmap!("output" => vec!["mock_user"], "output2" => vec!["mock_user"]),
+            mock_function(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            TaskCreationError::OutputMismatch {
+                missing: vec![],
+                unexpected: vec!["output2".to_string()],
+            }
+        );
+    }
+
+    fn test_task_approve_twice() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .public(true)
+            .owner("mock_user");
+        let mut task = Task::new(
+            UserID::from("mock_user"),
+            Executor::MesaPy,
+            FunctionArguments::default(),
+            HashMap::<String, OwnerList>::new(),
+            HashMap::<String, OwnerList>::new(),
+            function,
+        )
+        .unwrap();
+
+        let user_id = UserID::from("mock_user");
+        assert!(task.approve(&user_id).unwrap());
+        assert_eq!(task.status, TaskStatus::Approved);
+        assert!(!task.approve(&user_id).unwrap());
+        assert_eq!(task.approved_users.len(), 1);
+        assert_eq!(task.status, TaskStatus::Approved);
+    }
+
+    fn test_task_result_tags_chunks() {
+        let tags_map: HashMap<String, FileAuthTag> = (0..RESULT_TAGS_CHUNK_SIZE * 2 + 1)
+            .map(|i| (format!("output{}", i), FileAuthTag::mock()))
+            .collect();
+        let mut task = Task {
+            result: TaskResult::Ok(TaskOutputs::new("return value", tags_map)),
+            ..Default::default()
+        };
+
+        let chunks = task.take_result_tags_chunks();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(task.result_tags_chunks, 3);
+
+        let stored_chunks: Vec<Vec<u8>> = chunks
+            .iter()
+            .map(|chunk| serde_json::to_vec(chunk).unwrap())
+            .collect();
+        let value = task.to_vec().unwrap();
+
+        let mut task = Task::from_slice(&value).unwrap();
+        let chunks = stored_chunks
+            .iter()
+            .map(|chunk| serde_json::from_slice(chunk).unwrap())
+            .collect();
+        task.restore_result_tags_chunks(chunks).unwrap();
+        assert_eq!(task.result_tags_chunks, 0);
+
+        let outputs = task.result.unwrap();
+        assert_eq!(outputs.tags_map.len(), RESULT_TAGS_CHUNK_SIZE * 2 + 1);
+        assert!(outputs.tags_map.get("output0").is_some());
+        assert_eq!(outputs.return_value, b"return value".to_vec());
+
+        // small results are stored in one entry
+        let tags_map = hashmap!("output" => FileAuthTag::mock());
+        let mut task = Task {
+            result: TaskResult::Ok(TaskOutputs::new("return value", tags_map)),
+            ..Default::default()
+        };
+        assert!(task.take_result_tags_chunks().is_empty());
+        assert_eq!(task.result_tags_chunks, 0);
+    }
+
+    fn test_task_link_output() {
+        let new_task = || {
+            Task::new(
+                UserID::from("mock_user"),
+                Executor::MesaPy,
+                FunctionArguments::new(hashmap!("arg" => "data")),
+                hashmap!("input" => vec!["mock_user"]),
+                hashmap!("output" => vec!["mock_user"]),
+                mock_function(),
+            )
+            .unwrap()
+        };
+        let mut producer = new_task();
+        let mut consumer = new_task();
+
+        assert!(producer.link_output("output2", &consumer, "input").is_err());
+        assert!(producer.link_output("output", &consumer, "input2").is_err());
+        producer.link_output("output", &consumer, "input").unwrap();
+        assert!(producer.link_output("output", &consumer, "input").is_err());
+
+        let link = &producer.output_links[0];
+        assert_eq!(link.consumer_task_id, consumer.external_id());
+
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let mut output = TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user"]);
+        output.cmac = Some(FileAuthTag::mock());
+        let input = TeaclaveInputFile::from_output(output).unwrap();
+        consumer
+            .assign_linked_input(&link.input_name, input)
+            .unwrap();
+        assert!(consumer.assigned_inputs.get("input").is_some());
+    }
+
+    fn test_task_return_schema() {
+        let new_task = || {
+            let function = Function::new()
+                .id(Uuid::new_v4())
+                .name("mock_function")
+                .public(true)
+                .owner("mock_user")
+                .return_schema(ReturnValueSchema::Json);
+            let mut task = Task::new(
+                UserID::from("mock_user"),
+                Executor::MesaPy,
+                FunctionArguments::default(),
+                HashMap::<String, OwnerList>::new(),
+                HashMap::<String, OwnerList>::new(),
+                function,
+            )
+            .unwrap();
+            task.status = TaskStatus::Running;
+            task
+        };
+
+        let mut task = new_task();
+        let outputs = TaskOutputs::new(r#"{"score": 1}"#, HashMap::new());
+        task.finish(TaskResult::Ok(outputs)).unwrap();
+        assert!(task.result.is_ok());
+
+        let mut task = new_task();
+        let outputs = TaskOutputs::new(r#"{"score": "#, HashMap::new());
+        task.finish(TaskResult::Ok(outputs)).unwrap();
+        assert_eq!(task.status, TaskStatus::Finished);
+        match task.result {
+            TaskResult::Err(failure) => assert!(failure.reason.contains("schema json")),
+            _ => panic!("malformed return value is accepted"),
+        }
+    }
+
+    fn test_task_cancel() {
+        let new_task = |status| {
+            let function = Function::new()
+                .id(Uuid::new_v4())
+                .name("mock_function")
+                .public(true)
+                .owner("mock_user");
+            let mut task = Task::new(
+                UserID::from("mock_user"),
+                Executor::MesaPy,
+                FunctionArguments::default(),
+                HashMap::<String, OwnerList>::new(),
+                HashMap::<String, OwnerList>::new(),
+                function,
+            )
+            .unwrap();
+            task.status = status;
+            task
+        };
+
+        let mut task = new_task(TaskStatus::Approved);
+        assert!(task.request_cancel().unwrap());
+        assert_eq!(task.status, TaskStatus::Canceled);
+        assert!(!task.cancel_requested);
+        assert!(task.request_cancel().is_err());
+
+        let mut task = new_task(TaskStatus::Running);
+        assert!(task.acknowledge_cancel().is_err());
+        assert!(!task.request_cancel().unwrap());
+        assert!(task.cancel_requested);
+        assert_eq!(task.status, TaskStatus::Running);
+        task.acknowledge_cancel().unwrap();
+        assert_eq!(task.status, TaskStatus::Canceled);
+
+        let mut task = new_task(TaskStatus::Finished);
+        assert!(task.request_cancel().is_err());
+    }
+
+    fn test_task_labels() {
+        let labels = hashmap!("project" => "teaclave", "run.id" => "exp-1.2_a");
+        assert!(validate_task_labels(&labels).is_ok());
+        assert!(validate_task_labels(&hashmap!("Project" => "teaclave")).is_err());
+        assert!(validate_task_labels(&hashmap!("" => "teaclave")).is_err());
+        assert!(validate_task_labels(&hashmap!("project" => "a b")).is_err());
+
+        let labels: HashMap<String, String> = (0..=MAX_TASK_LABELS)
+            .map(|i| (format!("key{}", i), "value".to_string()))
+            .collect();
+        assert!(validate_task_labels(&labels).is_err());
+    }
+
+    fn test_task_partial_output() {
+        let function = Function::new()
+            .id(Uuid::new_v4())
+            .name("mock_function")
+            .outputs(vec![FunctionOutput::new("output", "output_desc")])
+            .public(true)
+            .owner("mock_user");
+        let mut task = Task::new(
+            UserID::from("mock_user"),
+            Executor::MesaPy,
+            FunctionArguments::default(),
+            HashMap::<String, OwnerList>::new(),
+            hashmap!("output" => vec!["mock_user"]),
+            function,
+        )
+        .unwrap();
+        let url = Url::parse("s3://bucket_id/path?token=mock_token").unwrap();
+        let output = TeaclaveOutputFile::new(url, FileCrypto::default(), vec!["mock_user"]);
+        task.assign_output(&UserID::from("mock_user"), "output", output)
+            .unwrap();
+
+        let tag = FileAuthTag::mock();
+        assert!(task.update_partial_output("output", tag).is_err());
+        task.status = TaskStatus::Running;
+        assert!(task.update_partial_output("output2", tag).is_err());
+        task.update_partial_output("output", tag).unwrap();
+        match &task.result {
+            TaskResult::InProgress(tags) => assert_eq!(tags.get("output"), Some(&tag)),
+            _ => panic!("partial output is not exposed"),
+        }
+        // the output is not finalized by partial updates
+        assert!(task.assigned_outputs.get("output").unwrap().cmac.is_none());
+
+        let outputs = TaskOutputs::new(vec![], hashmap!("output" => tag));
+        task.finish(TaskResult::Ok(outputs)).unwrap();
+        assert!(task.result.is_ok());
+    }
+}
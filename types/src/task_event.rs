@@ -0,0 +1,61 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{ExternalID, Task, TaskStatus, UserList};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+
+const TASK_EVENT_QUEUE_KEY: &str = "task-events";
+
+// A status transition of a task. Events are appended to a queue which is
+// never dequeued, so subscribers can replay them from any offset.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TaskEvent {
+    pub task_id: ExternalID,
+    // None when the task is created
+    pub old_status: Option<TaskStatus>,
+    pub new_status: TaskStatus,
+    pub timestamp: u64,
+    // Participants of the task, who can see the event
+    pub participants: UserList,
+}
+
+impl TaskEvent {
+    pub fn new(task: &Task, old_status: Option<TaskStatus>, timestamp: u64) -> Self {
+        Self {
+            task_id: task.external_id(),
+            old_status,
+            new_status: task.status,
+            timestamp,
+            participants: task.participants.clone(),
+        }
+    }
+
+    pub fn to_vec(&self) -> anyhow::Result<Vec<u8>> {
+        let bytes = serde_json::to_vec(self)?;
+        Ok(bytes)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> anyhow::Result<Self> {
+        let obj = serde_json::from_slice(bytes)?;
+        Ok(obj)
+    }
+
+    pub fn get_queue_key() -> &'static str {
+        TASK_EVENT_QUEUE_KEY
+    }
+}
@@ -0,0 +1,85 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+use crate::{ExternalID, Storable, Task, TaskResult, TaskStatus, UserID, UserList};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use uuid::Uuid;
+
+const TASK_REPRODUCTION_PREFIX: &str = "reproduction";
+
+// A run of a finished task with the same function and inputs, requested by an
+// auditor to verify the result of the task. The result of the run is compared
+// with that of the task, which is left untouched.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TaskReproduction {
+    pub id: Uuid,
+    pub task_id: ExternalID,
+    pub requester: UserID,
+    // Participants of the task, who can see the reproduction
+    pub participants: UserList,
+    pub status: TaskStatus,
+    // Digest of the result of the task, see TaskOutputs::digest
+    pub expected_digest: String,
+    pub result_digest: Option<String>,
+    pub failure: Option<String>,
+}
+
+impl TaskReproduction {
+    pub fn new(task: &Task, requester: UserID, expected_digest: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            task_id: task.external_id(),
+            requester,
+            participants: task.participants.clone(),
+            status: TaskStatus::Staged,
+            expected_digest,
+            result_digest: None,
+            failure: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.status = TaskStatus::Running;
+    }
+
+    pub fn finish(&mut self, result: &TaskResult) {
+        match result {
+            TaskResult::Ok(outputs) => self.result_digest = Some(outputs.digest()),
+            TaskResult::Err(failure) => self.failure = Some(failure.reason.clone()),
+            _ => self.failure = Some("no result".to_string()),
+        }
+        self.status = TaskStatus::Finished;
+    }
+
+    // Whether the run reproduced the result of the task, None until the run
+    // finishes successfully
+    pub fn matches(&self) -> Option<bool> {
+        self.result_digest
+            .as_ref()
+            .map(|digest| digest == &self.expected_digest)
+    }
+}
+
+impl Storable for TaskReproduction {
+    fn key_prefix() -> &'static str {
+        TASK_REPRODUCTION_PREFIX
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.id
+    }
+}
@@ -0,0 +1,127 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+use crate::{Executor, ExternalID, FunctionArguments, Storable, TaskFileOwners, UserID};
+use serde::{Deserialize, Serialize};
+use std::prelude::v1::*;
+use uuid::Uuid;
+
+const TASK_TEMPLATE_PREFIX: &str = "template";
+
+// Reusable settings of tasks, which can be instantiated by the owner, or by
+// anyone if the template is public.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TaskTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub owner: UserID,
+    pub public: bool,
+    pub function_id: ExternalID,
+    pub executor: Executor,
+    pub function_arguments: FunctionArguments,
+    pub inputs_ownership: TaskFileOwners,
+    pub outputs_ownership: TaskFileOwners,
+}
+
+impl TaskTemplate {
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            ..Self::default()
+        }
+    }
+
+    pub fn name(self, name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            ..self
+        }
+    }
+
+    pub fn owner(self, owner: impl Into<UserID>) -> Self {
+        Self {
+            owner: owner.into(),
+            ..self
+        }
+    }
+
+    pub fn public(self, public: bool) -> Self {
+        Self { public, ..self }
+    }
+
+    pub fn function_id(self, function_id: ExternalID) -> Self {
+        Self {
+            function_id,
+            ..self
+        }
+    }
+
+    pub fn executor(self, executor: Executor) -> Self {
+        Self { executor, ..self }
+    }
+
+    pub fn function_arguments(self, function_arguments: FunctionArguments) -> Self {
+        Self {
+            function_arguments,
+            ..self
+        }
+    }
+
+    pub fn inputs_ownership(self, inputs_ownership: TaskFileOwners) -> Self {
+        Self {
+            inputs_ownership,
+            ..self
+        }
+    }
+
+    pub fn outputs_ownership(self, outputs_ownership: TaskFileOwners) -> Self {
+        Self {
+            outputs_ownership,
+            ..self
+        }
+    }
+
+    pub fn can_be_used_by(&self, user_id: &UserID) -> bool {
+        self.public || &self.owner == user_id
+    }
+
+    // Arguments of an instance, where overridden values take precedence
+    pub fn instance_arguments(&self, overrides: FunctionArguments) -> FunctionArguments {
+        let mut arguments = self.function_arguments.clone();
+        arguments.inner_mut().extend(overrides.inner().clone());
+        arguments
+    }
+
+    // Ownership of the files of an instance, where overridden files replace
+    // the files of the same names
+    pub fn instance_ownership(
+        ownership: &TaskFileOwners,
+        overrides: TaskFileOwners,
+    ) -> TaskFileOwners {
+        ownership.clone().into_iter().chain(overrides).collect()
+    }
+}
+
+impl Storable for TaskTemplate {
+    fn key_prefix() -> &'static str {
+        TASK_TEMPLATE_PREFIX
+    }
+
+    fn uuid(&self) -> Uuid {
+        self.id
+    }
+}
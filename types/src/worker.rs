@@ -43,6 +43,10 @@ pub enum ExecutorType {
     Python,
 }
 
+impl ExecutorType {
+    pub const SUPPORTED: &'static [ExecutorType] = &[ExecutorType::Builtin, ExecutorType::Python];
+}
+
 impl std::default::Default for ExecutorType {
     fn default() -> Self {
         ExecutorType::Builtin
@@ -91,6 +95,18 @@ pub enum Executor {
     Builtin,
 }
 
+impl Executor {
+    pub const SUPPORTED: &'static [Executor] = &[Executor::MesaPy, Executor::Builtin];
+
+    // The type of functions the executor runs.
+    pub fn executor_type(&self) -> ExecutorType {
+        match self {
+            Executor::MesaPy => ExecutorType::Python,
+            Executor::Builtin => ExecutorType::Builtin,
+        }
+    }
+}
+
 impl std::default::Default for Executor {
     fn default() -> Self {
         Executor::MesaPy